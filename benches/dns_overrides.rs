@@ -0,0 +1,74 @@
+//! Resolves 1M names against a client's DNS overrides, 10k of them.
+//!
+//! Run with `cargo bench --bench dns_overrides`.
+
+use std::{
+    hint::black_box,
+    net::{Ipv4Addr, SocketAddr},
+    sync::Arc,
+    task::{Context, Poll, Waker},
+    time::Instant,
+};
+
+use wreq::dns::{Addrs, Name, Resolve, Resolving};
+
+const OVERRIDES: usize = 10_000;
+const LOOKUPS: usize = 1_000_000;
+
+/// Resolves the names that are not overridden to no addresses.
+struct NoAddrs;
+
+impl Resolve for NoAddrs {
+    fn resolve(&self, _: Name) -> Resolving {
+        let addrs: Addrs = Box::new(std::iter::empty());
+        Box::pin(std::future::ready(Ok(addrs)))
+    }
+}
+
+fn main() {
+    let domains = (0..OVERRIDES)
+        .map(|i| format!("host-{i}.example.com"))
+        .collect::<Vec<_>>();
+    let addrs = (0..OVERRIDES)
+        .map(|i| [SocketAddr::from((Ipv4Addr::LOCALHOST, i as u16))])
+        .collect::<Vec<_>>();
+    let resolver = wreq::dns::overrides_resolver(
+        Arc::new(NoAddrs),
+        domains
+            .iter()
+            .zip(&addrs)
+            .map(|(domain, addrs)| (domain.as_str(), &addrs[..])),
+    );
+
+    bench(&*resolver, "hit", |i| format!("host-{i}.example.com"));
+    bench(&*resolver, "hit, uppercase", |i| {
+        format!("HOST-{i}.Example.com")
+    });
+    bench(&*resolver, "hit, trailing dot", |i| {
+        format!("host-{i}.example.com.")
+    });
+    bench(&*resolver, "miss", |i| format!("host-{i}.example.org"));
+}
+
+fn bench(resolver: &dyn Resolve, case: &str, name: impl Fn(usize) -> String) {
+    // Names are parsed up front, so only the lookups are timed.
+    let names = (0..LOOKUPS)
+        .map(|i| name(i % OVERRIDES).parse::<Name>().unwrap())
+        .collect::<Vec<_>>();
+    let mut cx = Context::from_waker(Waker::noop());
+
+    let start = Instant::now();
+    let mut resolved = 0;
+    for name in names {
+        let Poll::Ready(addrs) = resolver.resolve(name).as_mut().poll(&mut cx) else {
+            panic!("overrides resolve without waiting");
+        };
+        resolved += black_box(addrs.unwrap()).count();
+    }
+    let elapsed = start.elapsed();
+
+    println!(
+        "{case:>17}: {LOOKUPS} lookups, {resolved} addresses in {elapsed:?} ({:?} per lookup)",
+        elapsed / LOOKUPS as u32
+    );
+}
@@ -104,7 +104,7 @@ async fn main() -> wreq::Result<()> {
         .http2_config(http2)
         .default_headers(headers)
         .original_headers(original_headers)
-        .build();
+        .build()?;
 
     // Build a client with emulation config
     let client = Client::builder()
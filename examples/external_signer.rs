@@ -0,0 +1,76 @@
+//! Authenticates with a client certificate whose key is only reachable through a signer.
+//!
+//! Usage: `cargo run --example external_signer -- <cert.pem> <key.pem> <url>`
+//!
+//! The key is loaded in memory here, but the signer could as well forward the message to an
+//! HSM or a KMS.
+
+use std::sync::Arc;
+
+use boring2::{
+    hash::MessageDigest,
+    pkey::{PKey, Private},
+    rsa::Padding,
+    sign::{RsaPssSaltlen, Signer},
+};
+use wreq::{
+    Identity,
+    tls::{SignFuture, SignatureAlgorithm, TlsSigner},
+};
+
+struct InMemorySigner(PKey<Private>);
+
+impl TlsSigner for InMemorySigner {
+    fn sign(&self, algorithm: SignatureAlgorithm, message: &[u8]) -> SignFuture {
+        let result = sign(&self.0, algorithm, message);
+        Box::pin(async move { result })
+    }
+}
+
+fn sign(
+    key: &PKey<Private>,
+    algorithm: SignatureAlgorithm,
+    message: &[u8],
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let (digest, pss) = match algorithm {
+        SignatureAlgorithm::RSA_PSS_RSAE_SHA256 => (MessageDigest::sha256(), true),
+        SignatureAlgorithm::RSA_PSS_RSAE_SHA384 => (MessageDigest::sha384(), true),
+        SignatureAlgorithm::RSA_PSS_RSAE_SHA512 => (MessageDigest::sha512(), true),
+        SignatureAlgorithm::RSA_PKCS1_SHA256 | SignatureAlgorithm::ECDSA_SECP256R1_SHA256 => {
+            (MessageDigest::sha256(), false)
+        }
+        SignatureAlgorithm::RSA_PKCS1_SHA384 | SignatureAlgorithm::ECDSA_SECP384R1_SHA384 => {
+            (MessageDigest::sha384(), false)
+        }
+        SignatureAlgorithm::RSA_PKCS1_SHA512 | SignatureAlgorithm::ECDSA_SECP521R1_SHA512 => {
+            (MessageDigest::sha512(), false)
+        }
+        _ => return Err(format!("unsupported signature algorithm {algorithm:?}").into()),
+    };
+
+    let mut signer = Signer::new(digest, key)?;
+    if pss {
+        signer.set_rsa_padding(Padding::PKCS1_PSS)?;
+        signer.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
+    }
+    signer.update(message)?;
+    Ok(signer.sign_to_vec()?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let (Some(cert), Some(key), Some(url)) = (args.next(), args.next(), args.next()) else {
+        eprintln!("usage: external_signer <cert.pem> <key.pem> <url>");
+        return Ok(());
+    };
+
+    let key = PKey::private_key_from_pem(&std::fs::read(key)?)?;
+    let identity =
+        Identity::from_external_signer(&std::fs::read(cert)?, Arc::new(InMemorySigner(key)))?;
+
+    let client = wreq::Client::builder().identity(identity).build()?;
+    let resp = client.get(url).send().await?;
+    println!("{}", resp.text().await?);
+    Ok(())
+}
@@ -1,5 +1,7 @@
+#[cfg(feature = "stream")]
+use std::sync::Arc;
 use std::{
-    fmt,
+    fmt, io,
     pin::Pin,
     task::{Context, Poll, ready},
 };
@@ -13,7 +15,9 @@ use tokio::fs::File;
 #[cfg(feature = "stream")]
 use tokio_util::io::ReaderStream;
 
-use crate::error::{BoxError, Error};
+#[cfg(feature = "stream")]
+use crate::core::body::FlowControl;
+use crate::error::{BoxError, Error, Kind};
 
 /// An request body.
 pub struct Body {
@@ -29,6 +33,25 @@ enum Inner {
 #[cfg(any(feature = "stream", feature = "multipart"))]
 pub(crate) struct DataStream<B>(pub(crate) B);
 
+/// A handle to pause and resume reading a response body stream.
+///
+/// Returned by [`Response::bytes_stream_with_control`](crate::Response::bytes_stream_with_control).
+/// While paused, the stream yields no chunks and the body is not read from the connection:
+/// HTTP/2 window updates are withheld, so the server stops sending once the window is
+/// exhausted, and HTTP/1 stops reading from the socket, applying TCP backpressure.
+#[cfg(feature = "stream")]
+#[derive(Debug, Clone)]
+pub struct FlowHandle {
+    flow: Arc<FlowControl>,
+}
+
+/// A stream that is not polled while its [`FlowHandle`] is paused.
+#[cfg(feature = "stream")]
+pub(crate) struct ControlledStream<S> {
+    inner: S,
+    flow: Arc<FlowControl>,
+}
+
 impl Body {
     /// Returns a reference to the internal data of the `Body`.
     ///
@@ -70,7 +93,7 @@ impl Body {
         Body::stream(stream)
     }
 
-    #[cfg(any(feature = "stream", feature = "multipart"))]
+    #[cfg(any(feature = "stream", feature = "multipart", feature = "json"))]
     pub(crate) fn stream<S>(stream: S) -> Body
     where
         S: futures_util::stream::TryStream + Send + 'static,
@@ -237,7 +260,11 @@ impl HttpBody for Body {
                 Poll::Ready(ready!(Pin::new(body).poll_frame(cx)).map(|opt_chunk| {
                     opt_chunk.map_err(|err| match err.downcast::<Error>() {
                         Ok(err) => *err,
-                        Err(err) => Error::body(err),
+                        // e.g. a stream read through `into_async_read` of another response
+                        Err(err) => match err.downcast::<io::Error>() {
+                            Ok(err) => Error::from_io(Kind::Body, *err),
+                            Err(err) => Error::body(err),
+                        },
                     })
                 }))
             }
@@ -298,6 +325,66 @@ where
     }
 }
 
+// ===== impl FlowHandle =====
+
+#[cfg(feature = "stream")]
+impl FlowHandle {
+    /// Pause reading the body.
+    ///
+    /// Chunks already received by the connection may still be buffered, but no more
+    /// are requested from the server until [`resume`](Self::resume) is called.
+    pub fn pause(&self) {
+        self.flow.pause();
+    }
+
+    /// Resume reading the body.
+    pub fn resume(&self) {
+        self.flow.resume();
+    }
+
+    /// Returns true if reading the body is paused.
+    pub fn is_paused(&self) -> bool {
+        self.flow.is_paused()
+    }
+
+    /// Sets how many bytes the server may send ahead of the reader, for HTTP/2 bodies.
+    ///
+    /// Window updates are held back so that the window open to the server does not exceed
+    /// `bytes`, bounding the data buffered by the connection. The window already granted
+    /// when this is called is not revoked. `0` releases window updates as soon as data is read,
+    /// which is the default.
+    pub fn set_target_buffer(&self, bytes: usize) {
+        self.flow.set_target_buffer(bytes);
+    }
+}
+
+// ===== impl ControlledStream =====
+
+#[cfg(feature = "stream")]
+impl<S> ControlledStream<S> {
+    pub(crate) fn new(
+        inner: S,
+        flow: Option<Arc<FlowControl>>,
+    ) -> (ControlledStream<S>, FlowHandle) {
+        let flow = flow.unwrap_or_default();
+        let handle = FlowHandle { flow: flow.clone() };
+        (ControlledStream { inner, flow }, handle)
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<S> futures_util::Stream for ControlledStream<S>
+where
+    S: futures_util::Stream + Unpin,
+{
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        ready!(self.flow.poll_resumed(cx));
+        Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
+
 // ===== impl IntoBytesBody =====
 pin_project! {
     struct IntoBytesBody<B> {
@@ -0,0 +1,304 @@
+//! Inspecting and updating the configuration of a built client.
+
+use http::HeaderMap;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer, ser::SerializeMap};
+
+use super::Client;
+use crate::{
+    EmulationApply, EmulationProviderFactory, Error, OriginalHeaders, connect::TlsHandle,
+    http1::Http1Config, http2::Http2Config, tls::TlsConfig,
+};
+
+/// A read-only snapshot of the configuration a [`Client`] uses for new connections.
+///
+/// The TLS configuration is the one in effect after emulation and the overrides of the
+/// [`ClientBuilder`](super::ClientBuilder), such as the ALPN protocols of
+/// [`http1_only`](super::ClientBuilder::http1_only) or the versions of
+/// [`min_tls_version`](super::ClientBuilder::min_tls_version). Identities are only reported as
+/// present, their keys are never part of a snapshot.
+///
+/// The default headers and their order are the ones of the client, the profiles of an
+/// [`emulation_pool`](super::ClientBuilder::emulation_pool) replace them per request.
+///
+/// With the `serde` feature, the snapshot implements `Serialize`, so it can be written out and
+/// compared against another client's. The default headers and their order are serialized like
+/// the `default_headers` and `headers_order` of a
+/// [JSON profile](crate::EmulationProvider::from_json), with the values of sensitive headers
+/// redacted.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TlsConfigSnapshot {
+    pub(super) tls: TlsConfig,
+    pub(super) http1: Http1Config,
+    pub(super) http2: Http2Config,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_headers"))]
+    pub(super) default_headers: HeaderMap,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_headers_order"))]
+    pub(super) headers_order: Option<OriginalHeaders>,
+    pub(super) keylog: bool,
+    pub(super) cert_verification: bool,
+    pub(super) verify_hostname: bool,
+    pub(super) sni: bool,
+    pub(super) identity: bool,
+}
+
+impl TlsConfigSnapshot {
+    /// Get the effective TLS configuration.
+    pub fn tls(&self) -> &TlsConfig {
+        &self.tls
+    }
+
+    /// Get the HTTP/1 configuration.
+    pub fn http1(&self) -> &Http1Config {
+        &self.http1
+    }
+
+    /// Get the HTTP/2 configuration.
+    pub fn http2(&self) -> &Http2Config {
+        &self.http2
+    }
+
+    /// Get the default headers.
+    pub fn default_headers(&self) -> &HeaderMap {
+        &self.default_headers
+    }
+
+    /// Get the order and case of the headers, if set.
+    pub fn headers_order(&self) -> Option<&OriginalHeaders> {
+        self.headers_order.as_ref()
+    }
+
+    /// Returns whether TLS keys are logged.
+    pub fn keylog(&self) -> bool {
+        self.keylog
+    }
+
+    /// Returns whether server certificates are verified.
+    pub fn cert_verification(&self) -> bool {
+        self.cert_verification
+    }
+
+    /// Returns whether the hostname of server certificates is verified.
+    pub fn verify_hostname(&self) -> bool {
+        self.verify_hostname
+    }
+
+    /// Returns whether the Server Name Indication extension is sent.
+    pub fn sni(&self) -> bool {
+        self.sni
+    }
+
+    /// Returns whether a client identity is presented to any server.
+    pub fn identity(&self) -> bool {
+        self.identity
+    }
+}
+
+/// Serializes headers as an object of their names to a value, or a list of values.
+#[cfg(feature = "serde")]
+fn serialize_headers<S: Serializer>(headers: &HeaderMap, serializer: S) -> Result<S::Ok, S::Error> {
+    fn value(value: &http::HeaderValue) -> std::borrow::Cow<'_, str> {
+        if value.is_sensitive() {
+            "<redacted>".into()
+        } else {
+            String::from_utf8_lossy(value.as_bytes())
+        }
+    }
+
+    let mut map = serializer.serialize_map(Some(headers.keys_len()))?;
+    for name in headers.keys() {
+        let mut values = headers.get_all(name).iter();
+        match (values.next(), values.next()) {
+            (Some(first), None) => map.serialize_entry(name.as_str(), &value(first))?,
+            _ => {
+                let values: Vec<_> = headers.get_all(name).iter().map(value).collect();
+                map.serialize_entry(name.as_str(), &values)?
+            }
+        }
+    }
+    map.end()
+}
+
+/// Serializes the order of headers as a list of their names, in their case.
+#[cfg(feature = "serde")]
+fn serialize_headers_order<S: Serializer>(
+    order: &Option<OriginalHeaders>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let Some(order) = order else {
+        return serializer.serialize_none();
+    };
+    serializer.collect_seq(order.keys().flat_map(|name| {
+        order
+            .get_all(name)
+            .map(|orig| String::from_utf8_lossy(orig.as_ref()).into_owned())
+            .collect::<Vec<_>>()
+    }))
+}
+
+/// A change to the configuration of a built [`Client`], created by [`Client::update`].
+///
+/// An update is either [`apply`](Self::apply)ed or [`discard`](Self::discard)ed. Dropping an
+/// update with changes logs a warning, as the changes are lost.
+#[must_use = "an update does nothing until it is applied"]
+pub struct ClientUpdate<'a> {
+    tls: &'a TlsHandle,
+    generation: u64,
+    tls_config: Option<TlsConfig>,
+    error: Option<Error>,
+}
+
+impl ClientUpdate<'_> {
+    /// Modifies the TLS configuration, starting from the one in effect.
+    ///
+    /// The closure is called right away, and may be called several times to make several
+    /// changes. Options of the [`ClientBuilder`](super::ClientBuilder) that restrict the TLS
+    /// versions, such as [`tls13_only`](super::ClientBuilder::tls13_only), still apply to the
+    /// modified configuration.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use wreq::tls::TlsVersion;
+    ///
+    /// # fn doc(client: &wreq::Client) -> wreq::Result<()> {
+    /// client
+    ///     .update()
+    ///     .tls(|cfg| {
+    ///         *cfg = cfg
+    ///             .to_builder()
+    ///             .min_tls_version(TlsVersion::TLS_1_3)
+    ///             .build()
+    ///     })
+    ///     .apply()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tls<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut TlsConfig),
+    {
+        f(self.tls_config.get_or_insert_with(|| self.tls.config()));
+        self
+    }
+
+    /// Applies the TLS configuration of an emulation, if `parts` has
+    /// [`EmulationApply::TLS`].
+    ///
+    /// The TLS configuration is the only part of an emulation that can be changed on a built
+    /// client. The update fails to [`apply`](Self::apply) if `parts` selects another part the
+    /// emulation has, such as its headers.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use wreq::EmulationApply;
+    /// use wreq_util::Emulation;
+    ///
+    /// # fn doc(client: &wreq::Client) -> wreq::Result<()> {
+    /// client
+    ///     .update()
+    ///     .emulation_with(Emulation::Chrome131, EmulationApply::TLS)
+    ///     .apply()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn emulation_with<P>(mut self, factory: P, parts: EmulationApply) -> Self
+    where
+        P: EmulationProviderFactory,
+    {
+        let emulation = factory.emulation().retain(parts);
+        if emulation.http1_config.is_some()
+            || emulation.http2_config.is_some()
+            || emulation.default_headers.is_some()
+            || emulation.original_headers.is_some()
+        {
+            self.error = Some(Error::builder(
+                "only the TLS configuration of an emulation can be applied to a built client",
+            ));
+        }
+        if let Some(config) = emulation.tls_config {
+            self.tls_config = Some(config);
+        }
+        self
+    }
+
+    /// Applies the update to the client and all of its clones.
+    ///
+    /// A modified TLS configuration rebuilds the TLS connector, with an empty session cache.
+    /// It applies to subsequent handshakes, established connections are not affected. A
+    /// connector passed to [`ClientBuilder::tls_connector`](super::ClientBuilder::tls_connector)
+    /// is left untouched for the other clients sharing it.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if the TLS connector cannot be built from the modified
+    /// configuration, or if an emulation part that cannot be changed was selected, in which case
+    /// the client is left unchanged.
+    ///
+    /// It also fails if another update was applied to the client since [`Client::update`]
+    /// was called, as this update may be based on a configuration that is no longer in effect.
+    /// The error is then [`is_concurrent_update`](crate::Error::is_concurrent_update), and the
+    /// update can be started again, or applied anyway with
+    /// [`apply_overwrite`](Self::apply_overwrite).
+    pub fn apply(mut self) -> crate::Result<()> {
+        let generation = self.generation;
+        self.commit(Some(generation))
+    }
+
+    /// Applies the update like [`apply`](Self::apply), even if another update was applied
+    /// since it started, overwriting its changes.
+    pub fn apply_overwrite(mut self) -> crate::Result<()> {
+        self.commit(None)
+    }
+
+    /// Abandons the update, leaving the client unchanged.
+    pub fn discard(mut self) {
+        self.tls_config = None;
+        self.error = None;
+    }
+
+    fn commit(&mut self, generation: Option<u64>) -> crate::Result<()> {
+        let tls_config = self.tls_config.take();
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        if let Some(config) = tls_config {
+            self.tls.replace(config, generation)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ClientUpdate<'_> {
+    fn drop(&mut self) {
+        if self.tls_config.is_some() || self.error.is_some() {
+            warn!("ClientUpdate dropped without apply, its changes are lost");
+        }
+    }
+}
+
+impl Client {
+    /// Returns a snapshot of the configuration used for new connections.
+    ///
+    /// The snapshot reflects the updates applied with [`Client::update`] so far.
+    pub fn tls_config_snapshot(&self) -> TlsConfigSnapshot {
+        TlsConfigSnapshot {
+            tls: self.tls.config(),
+            ..TlsConfigSnapshot::clone(&self.snapshot)
+        }
+    }
+
+    /// Starts a change to the configuration of this client, without building a new one.
+    ///
+    /// Unlike building a new client, the connection pool and the cookie store are kept.
+    pub fn update(&self) -> ClientUpdate<'_> {
+        ClientUpdate {
+            tls: &self.tls,
+            generation: self.tls.generation(),
+            tls_config: None,
+            error: None,
+        }
+    }
+}
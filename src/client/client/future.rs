@@ -1,4 +1,5 @@
 use std::{
+    future::Future,
     pin::Pin,
     task::{Context, Poll},
 };
@@ -13,8 +14,11 @@ use super::{
     types::{BoxedClientService, CoreResponseFuture, GenericClientService, HttpRequest},
 };
 use crate::{
-    Body, Error,
-    client::{body, middleware::redirect::RequestUri},
+    Body, Error, ExpectedDigest,
+    client::{
+        body, digest::DigestBody, dispatch::DispatchInfo, emulation::FailoverTicket,
+        middleware::redirect::RequestUri,
+    },
     core::body::Incoming,
     error::BoxError,
     into_url::IntoUrlSealed,
@@ -25,11 +29,15 @@ pin_project! {
     pub enum Pending {
         BoxedRequest {
             url: Option<Url>,
+            digest: Option<ExpectedDigest>,
+            failover: Option<Box<FailoverTicket>>,
             #[pin]
             fut: Oneshot<BoxedClientService, HttpRequest<Body>>,
         },
         GenericRequest {
             url: Option<Url>,
+            digest: Option<ExpectedDigest>,
+            failover: Option<Box<FailoverTicket>>,
             fut: Pin<Box<Oneshot<GenericClientService, HttpRequest<Body>>>>,
         },
         Error {
@@ -44,6 +52,10 @@ pin_project! {
         Request {
             #[pin]
             fut: CoreResponseFuture,
+            dispatch: Option<DispatchInfo>,
+        },
+        ProxyAuth {
+            fut: Pin<Box<dyn Future<Output = Result<HttpResponse<Incoming>, BoxError>> + Send>>,
         },
         Error {
             error: Option<Error>,
@@ -57,13 +69,23 @@ impl Future for Pending {
     type Output = Result<Response, Error>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let (url, res) = match self.project() {
-            PendingProj::BoxedRequest { url, fut } => (url, fut.poll(cx)),
-            PendingProj::GenericRequest { url, fut } => (url, fut.as_mut().poll(cx)),
+        let (url, digest, failover, res) = match self.project() {
+            PendingProj::BoxedRequest {
+                url,
+                digest,
+                failover,
+                fut,
+            } => (url, digest, failover, fut.poll(cx)),
+            PendingProj::GenericRequest {
+                url,
+                digest,
+                failover,
+                fut,
+            } => (url, digest, failover, fut.as_mut().poll(cx)),
             PendingProj::Error { error } => return Poll::Ready(Err(take_err!(error))),
         };
 
-        let res = match res {
+        let mut res = match res {
             Poll::Ready(Ok(res)) => res.map(body::boxed),
             Poll::Ready(Err(err)) => {
                 let mut err = match err.downcast::<Error>() {
@@ -74,16 +96,30 @@ impl Future for Pending {
                 if err.url().is_none() {
                     err = err.with_url(take_url!(url));
                 }
+                if let Some(failover) = failover.take() {
+                    failover.on_error(&err);
+                }
 
                 return Poll::Ready(Err(err));
             }
             Poll::Pending => return Poll::Pending,
         };
 
+        if let Some(failover) = failover.take() {
+            failover.on_response(res.status(), res.headers());
+        }
+
         if let Some(uri) = res.extensions().get::<RequestUri>() {
             *url = Some(IntoUrlSealed::into_url(uri.0.to_string())?);
         }
 
+        if let Some(digest) = digest.take() {
+            if let Err(err) = digest.start(&res) {
+                return Poll::Ready(Err(err.with_url(take_url!(url))));
+            }
+            res = res.map(|body| body::boxed(DigestBody::new(body, digest)));
+        }
+
         Poll::Ready(Ok(Response::new(res, take_url!(url))))
     }
 }
@@ -95,11 +131,17 @@ impl Future for CorePending {
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         match self.project() {
-            CorePendingProj::Request { fut } => match fut.poll(cx) {
-                Poll::Ready(Ok(res)) => Poll::Ready(Ok(res)),
+            CorePendingProj::Request { fut, dispatch } => match fut.poll(cx) {
+                Poll::Ready(Ok(mut res)) => {
+                    if let Some(dispatch) = dispatch.take() {
+                        dispatch.apply(res.extensions_mut());
+                    }
+                    Poll::Ready(Ok(res))
+                }
                 Poll::Ready(Err(err)) => Poll::Ready(Err(err.into())),
                 Poll::Pending => Poll::Pending,
             },
+            CorePendingProj::ProxyAuth { fut } => fut.as_mut().poll(cx),
             CorePendingProj::Error { error } => Poll::Ready(Err(take_err!(error).into())),
         }
     }
@@ -0,0 +1,201 @@
+//! Accounting the size of request headers before they are sent.
+
+use http::{
+    HeaderMap, HeaderName, HeaderValue, Request, Uri, Version,
+    header::{COOKIE, HOST},
+    uri::Scheme,
+};
+
+use super::Body;
+use crate::error::{Error, RequestHeadersTooLarge};
+
+/// Response extension holding the names of the cookies dropped from a request to fit its
+/// headers in [`ClientBuilder::max_request_header_bytes`], in the order they were dropped.
+///
+/// See [`ClientBuilder::trim_cookies_to_fit`].
+///
+/// [`ClientBuilder::max_request_header_bytes`]: crate::ClientBuilder::max_request_header_bytes
+/// [`ClientBuilder::trim_cookies_to_fit`]: crate::ClientBuilder::trim_cookies_to_fit
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TrimmedCookies(pub Vec<String>);
+
+/// The size budget of the headers of requests.
+#[derive(Clone, Copy)]
+pub(super) struct HeaderBudget {
+    pub(super) limit: usize,
+    pub(super) trim_cookies: bool,
+}
+
+/// The framing the size of headers is estimated for.
+#[derive(Clone, Copy)]
+enum Framing {
+    Http1,
+    Http2,
+    /// Negotiated with ALPN, the larger of both estimates.
+    Either,
+}
+
+impl HeaderBudget {
+    /// Checks that the headers of `req`, sent with `version` if it is known, fit in the budget,
+    /// dropping its cookies until they do if allowed.
+    pub(super) fn enforce(
+        &self,
+        req: &mut Request<Body>,
+        version: Option<Version>,
+    ) -> Result<(), Error> {
+        req.extensions_mut().remove::<TrimmedCookies>();
+
+        let framing = match version {
+            Some(Version::HTTP_2) => Framing::Http2,
+            Some(_) => Framing::Http1,
+            None if req.uri().scheme() == Some(&Scheme::HTTPS) => Framing::Either,
+            None => Framing::Http1,
+        };
+        let mut estimated = estimate(req, framing);
+        if estimated <= self.limit {
+            return Ok(());
+        }
+
+        if self.trim_cookies {
+            let mut fields = cookie_fields(req.headers());
+            let mut dropped = Vec::new();
+            while estimated > self.limit {
+                let Some(cookie) = fields.last_mut().and_then(Vec::pop) else {
+                    break;
+                };
+                let name = cookie.split_once('=').map_or(&*cookie, |(name, _)| name);
+                dropped.push(name.trim().to_owned());
+                fields.retain(|cookies| !cookies.is_empty());
+
+                // the remaining cookies keep the fields they were sent in
+                req.headers_mut().remove(COOKIE);
+                for cookies in &fields {
+                    if let Ok(value) = HeaderValue::from_str(&cookies.join("; ")) {
+                        req.headers_mut().append(COOKIE, value);
+                    }
+                }
+                estimated = estimate(req, framing);
+            }
+
+            if !dropped.is_empty() {
+                debug!("dropped cookies {dropped:?} to fit the request headers");
+                req.extensions_mut().insert(TrimmedCookies(dropped));
+            }
+            if estimated <= self.limit {
+                return Ok(());
+            }
+        }
+
+        Err(Error::request(RequestHeadersTooLarge::new(
+            estimated,
+            self.limit,
+            largest_header(req.headers(), framing),
+        )))
+    }
+}
+
+/// Returns the cookies of each `Cookie` field, in order.
+fn cookie_fields(headers: &HeaderMap) -> Vec<Vec<String>> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .map(|value| {
+            value
+                .split(';')
+                .map(str::trim)
+                .filter(|cookie| !cookie.is_empty())
+                .map(str::to_owned)
+                .collect::<Vec<_>>()
+        })
+        .filter(|cookies| !cookies.is_empty())
+        .collect()
+}
+
+/// Returns the estimated size of the headers of `req`.
+fn estimate(req: &Request<Body>, framing: Framing) -> usize {
+    match framing {
+        Framing::Http1 => http1_size(req),
+        Framing::Http2 => http2_size(req),
+        Framing::Either => http1_size(req).max(http2_size(req)),
+    }
+}
+
+/// Returns the size of a field of `name` and `value`.
+fn field_size(name: &HeaderName, value: &HeaderValue, framing: Framing) -> usize {
+    match framing {
+        // `name: value\r\n`
+        Framing::Http1 => name.as_str().len() + value.len() + 4,
+        // the 32 bytes of overhead of a field of RFC 9113, section 6.5.2, larger than the
+        // HTTP/1 one
+        Framing::Http2 | Framing::Either => name.as_str().len() + value.len() + 32,
+    }
+}
+
+/// Returns the size of the head of `req` in HTTP/1: the request line, the fields with the
+/// `Host` the client adds, and the empty line ending it.
+fn http1_size(req: &Request<Body>) -> usize {
+    // `METHOD target HTTP/1.1\r\n`
+    let mut size = req.method().as_str().len() + 1 + target_len(req.uri()) + 11;
+
+    if !req.headers().contains_key(HOST) {
+        size += HOST.as_str().len() + authority_len(req.uri()) + 4;
+    }
+
+    let fields: usize = req
+        .headers()
+        .iter()
+        .map(|(name, value)| field_size(name, value, Framing::Http1))
+        .sum();
+    size + fields + 2
+}
+
+/// Returns the size of the header list of `req` in HTTP/2, as the server limits it with
+/// `SETTINGS_MAX_HEADER_LIST_SIZE`: the uncompressed pseudo-headers and fields, with 32 bytes
+/// of overhead each. The `Host` header is sent as the `:authority` pseudo-header.
+fn http2_size(req: &Request<Body>) -> usize {
+    let uri = req.uri();
+    let pseudo: usize = [
+        (":method", req.method().as_str().len()),
+        (":scheme", uri.scheme_str().map_or(0, str::len)),
+        (":authority", authority_len(uri)),
+        (":path", target_len(uri)),
+    ]
+    .iter()
+    .map(|(name, len)| name.len() + len + 32)
+    .sum();
+
+    let fields: usize = req
+        .headers()
+        .iter()
+        .filter(|(name, _)| *name != HOST)
+        .map(|(name, value)| field_size(name, value, Framing::Http2))
+        .sum();
+    pseudo + fields
+}
+
+fn target_len(uri: &Uri) -> usize {
+    uri.path_and_query()
+        .map_or(1, |target| target.as_str().len())
+}
+
+fn authority_len(uri: &Uri) -> usize {
+    uri.authority()
+        .map_or(0, |authority| authority.as_str().len())
+}
+
+/// Returns the name whose fields take the most room.
+fn largest_header(headers: &HeaderMap, framing: Framing) -> Option<HeaderName> {
+    headers
+        .keys()
+        .map(|name| {
+            let size: usize = headers
+                .get_all(name)
+                .iter()
+                .map(|value| field_size(name, value, framing))
+                .sum();
+            (size, name)
+        })
+        .max_by_key(|(size, _)| *size)
+        .map(|(_, name)| name.clone())
+}
@@ -0,0 +1,120 @@
+//! Keeping pooled connections alive on demand.
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use http::{Version, uri::Authority};
+
+use super::Client;
+
+/// The identifier of a connection, unique within the process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ConnId(u64);
+
+impl ConnId {
+    /// Returns a new identifier.
+    pub(crate) fn next() -> ConnId {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+        ConnId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl fmt::Display for ConnId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// A connection held by the pool of a client, see [`Client::pooled_connections`].
+#[derive(Debug, Clone)]
+pub struct PooledConnection {
+    pub(crate) id: ConnId,
+    pub(crate) authority: Authority,
+    pub(crate) version: Version,
+    pub(crate) idle_since: Instant,
+}
+
+impl PooledConnection {
+    /// Returns the identifier of the connection.
+    pub fn id(&self) -> ConnId {
+        self.id
+    }
+
+    /// Returns the authority of the origin the connection is for.
+    pub fn authority(&self) -> &Authority {
+        &self.authority
+    }
+
+    /// Returns the HTTP version of the connection, `HTTP/1.1` or `HTTP/2`.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Returns when the connection was last returned to the pool.
+    ///
+    /// An HTTP/2 connection is shared by its requests, and stays in the pool while they are
+    /// sent. This is then when the last request was started on it.
+    pub fn idle_since(&self) -> Instant {
+        self.idle_since
+    }
+}
+
+/// The outcome of [`Client::keepalive`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum KeepaliveOutcome {
+    /// The server acknowledged a PING sent on the HTTP/2 connection, after the round trip
+    /// time.
+    Acked(Duration),
+    /// The HTTP/1 connection is still open.
+    ///
+    /// Nothing is sent on HTTP/1 connections: an idle connection keeps reading, and notices
+    /// when the server closes it.
+    Open,
+    /// The connection was closed, or did not acknowledge the PING in time, and was evicted from
+    /// the pool.
+    Evicted,
+    /// No connection with the identifier is in the pool, it was closed or evicted before.
+    NotPooled,
+}
+
+impl Client {
+    /// Returns the connections held by the connection pool.
+    ///
+    /// Connections that are sending a request are only listed for HTTP/2, whose connections
+    /// are shared and stay in the pool.
+    pub fn pooled_connections(&self) -> Vec<PooledConnection> {
+        self.prober.service.client.pooled_connections()
+    }
+
+    /// Checks that the pooled connection `id` is still healthy, sending traffic on it if the
+    /// protocol allows it, and evicts it from the pool if it is not.
+    ///
+    /// On an HTTP/2 connection, a PING frame is sent, and the connection is evicted if it is not
+    /// acknowledged within `timeout`. The traffic keeps the NAT and firewall bindings of the
+    /// connection alive, on a schedule of the caller's choosing rather than a fixed interval.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use wreq::KeepaliveOutcome;
+    ///
+    /// # async fn doc(client: wreq::Client) {
+    /// // the radio is up again, refresh the bindings of the idle connections
+    /// for conn in client.pooled_connections() {
+    ///     let outcome = client.keepalive(conn.id(), Duration::from_secs(5)).await;
+    ///     if outcome == KeepaliveOutcome::Evicted {
+    ///         println!("{} to {} is gone", conn.id(), conn.authority());
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn keepalive(&self, id: ConnId, timeout: Duration) -> KeepaliveOutcome {
+        self.prober.service.client.keepalive(id, timeout).await
+    }
+}
@@ -1,11 +1,17 @@
 #[macro_use]
 mod macros;
+#[cfg(feature = "tls")]
+mod config;
 mod future;
+mod header_budget;
+mod keepalive;
+mod probe;
 mod service;
 mod types;
 
+#[cfg(feature = "tls")]
+use std::borrow::Cow;
 use std::{
-    collections::HashMap,
     convert::TryInto,
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     num::NonZeroU32,
@@ -14,11 +20,18 @@ use std::{
     time::Duration,
 };
 
+#[cfg(feature = "tls")]
+pub use config::{ClientUpdate, TlsConfigSnapshot};
 pub use future::Pending;
+use header_budget::HeaderBudget;
+pub use header_budget::TrimmedCookies;
 use http::{
-    Request as HttpRequest, Response as HttpResponse,
-    header::{HeaderMap, HeaderValue, USER_AGENT},
+    Request as HttpRequest, Response as HttpResponse, Version,
+    header::{ACCEPT_LANGUAGE, HeaderMap, HeaderValue, USER_AGENT},
 };
+pub use keepalive::{ConnId, KeepaliveOutcome, PooledConnection};
+use probe::Prober;
+pub use probe::{ProbeBuilder, ProbeDepth, ProbeReport};
 use service::{ClientConfig, ClientService};
 use tower::{
     Layer, Service, ServiceBuilder, ServiceExt,
@@ -36,13 +49,22 @@ use {super::middleware::cookie::CookieManagerLayer, crate::cookie};
     feature = "deflate",
 ))]
 use super::middleware::decoder::{AcceptEncoding, DecompressionLayer};
+#[cfg(feature = "vcr")]
+use super::middleware::vcr::VcrLayer;
 #[cfg(feature = "websocket")]
 use super::websocket::WebSocketRequestBuilder;
 use super::{
-    Body, EmulationProviderFactory,
+    Body, EmulationApply, EmulationProviderFactory, FailoverConfig, ProfileStats,
+    RotatingEmulation,
+    dispatch::{OriginalUrl, RedirectHop, RequestAttempt},
+    emulation::{Consistency, ProfileFailover},
     middleware::{
+        config::RequestExpectedDigest,
+        coop::CoopLayer,
+        headers_hook::{HeadersHook, HeadersHookLayer},
         redirect::FollowRedirectLayer,
-        retry::Http2RetryPolicy,
+        retry::{Http2RetryPolicy, RetryBudget},
+        think_time::{ThinkTime, ThinkTimeLayer},
         timeout::{ResponseBodyTimeoutLayer, TimeoutLayer},
     },
     request::{Request, RequestBuilder},
@@ -51,21 +73,33 @@ use super::{
 #[cfg(feature = "hickory-dns")]
 use crate::dns::hickory::{HickoryDnsResolver, LookupIpStrategy};
 use crate::{
-    IntoUrl, Method, OriginalHeaders, Proxy,
+    IntoUrl, Method, OriginalHeaders, Proxy, ProxyAuthHandler, ProxyLatencyStats, ProxySelector,
+    ProxyStrategy,
     connect::{BoxedConnectorLayer, BoxedConnectorService, Conn, Connector, Unnameable},
     core::{
-        client::{Builder, Client as HyperClient, connect::TcpConnectOptions},
+        client::{
+            Builder, Client as HyperClient,
+            connect::{ProxyProtocolConfig, TcpConnectOptions},
+        },
         ext::RequestConfig,
         rt::{TokioExecutor, tokio::TokioTimer},
     },
-    dns::{DnsResolverWithOverrides, DynResolver, Resolve, gai::GaiResolver},
+    dns::{
+        DnsOverrides, DnsResolverWithOverrides, DynResolver, ResolutionMemo, Resolve,
+        gai::GaiResolver, normalize_override_name,
+    },
     error::{self, BoxError, Error},
     http1::Http1Config,
     http2::Http2Config,
-    proxy::Matcher as ProxyMatcher,
+    proxy::{Matcher as ProxyMatcher, ProxyAuth, ProxyLatency, ProxyRace},
     redirect::{self, RedirectPolicy},
+};
+#[cfg(feature = "tls")]
+use crate::{
+    connect::TlsHandle,
     tls::{
-        AlpnProtocol, CertStore, CertificateInput, Identity, KeyLogPolicy, TlsConfig, TlsVersion,
+        AlpnProtocol, CertStore, CertificateInput, Crl, HostPattern, Identity, KeyLogPolicy,
+        RevocationCheck, RevocationPolicy, TlsConfig, TlsConnector, TlsSessionStats, TlsVersion,
     },
 };
 
@@ -85,6 +119,16 @@ use crate::{
 #[derive(Clone)]
 pub struct Client {
     inner: Arc<ClientRef>,
+    prober: Prober,
+    #[cfg(feature = "tls")]
+    tls: TlsHandle,
+    #[cfg(feature = "tls")]
+    tls_revocation: Option<RevocationCheck>,
+    #[cfg(feature = "tls")]
+    snapshot: Arc<TlsConfigSnapshot>,
+    emulation_pool: Option<RotatingEmulation>,
+    profile_failover: Option<ProfileFailover>,
+    proxy_latency: Option<Arc<ProxyLatency>>,
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -112,6 +156,7 @@ struct Config {
     error: Option<Error>,
     headers: HeaderMap,
     original_headers: Option<OriginalHeaders>,
+    headers_hook: Option<HeadersHook>,
     #[cfg(any(
         feature = "gzip",
         feature = "zstd",
@@ -120,6 +165,8 @@ struct Config {
     ))]
     accept_encoding: AcceptEncoding,
     connect_timeout: Option<Duration>,
+    #[cfg(feature = "socks")]
+    socks_handshake_timeout: Option<Duration>,
     connection_verbose: bool,
     pool_idle_timeout: Option<Duration>,
     pool_max_idle_per_host: usize,
@@ -132,36 +179,85 @@ struct Config {
     tcp_connect_options: Option<TcpConnectOptions>,
     #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
     tcp_user_timeout: Option<Duration>,
+    proxy_protocol: Option<ProxyProtocolConfig>,
     proxies: Vec<ProxyMatcher>,
+    proxy_strategy: ProxyStrategy,
+    proxy_race: Option<(usize, Duration)>,
+    proxy_auth_handler: Option<Arc<dyn ProxyAuthHandler>>,
+    proxy_selector: Option<Arc<dyn ProxySelector>>,
     auto_sys_proxy: bool,
     redirect_policy: redirect::Policy,
     referer: bool,
     timeout: Option<Duration>,
     read_timeout: Option<Duration>,
+    body_poll_budget: usize,
     #[cfg(feature = "cookies")]
     cookie_store: Option<Arc<dyn cookie::CookieStore>>,
+    #[cfg(feature = "vcr")]
+    vcr: Option<crate::vcr::Vcr>,
     #[cfg(feature = "hickory-dns")]
     hickory_dns: bool,
-    dns_overrides: HashMap<String, Vec<SocketAddr>>,
+    dns_overrides: DnsOverrides,
     dns_resolver: Option<Arc<dyn Resolve>>,
     http_version_pref: HttpVersionPref,
     https_only: bool,
+    max_request_header_bytes: Option<usize>,
+    trim_cookies_to_fit: bool,
     http1_config: Http1Config,
     http2_config: Http2Config,
     http2_max_retry: usize,
+    upload_chunk_bounds: Option<(usize, usize)>,
+    retry_budget: Option<RetryBudget>,
+    think_time: Option<ThinkTime>,
+    emulation_pool: Option<RotatingEmulation>,
+    profile_failover: Option<ProfileFailover>,
+    emulation_locale: Option<HeaderValue>,
+    emulation_lenient: bool,
     request_layers: Option<Vec<BoxedClientServiceLayer>>,
     connector_layers: Option<Vec<BoxedConnectorLayer>>,
     builder: Builder,
+    #[cfg(feature = "tls")]
     tls_keylog_policy: Option<KeyLogPolicy>,
+    #[cfg(feature = "tls")]
     tls_info: bool,
+    #[cfg(feature = "tls")]
+    tls_debug_capture: bool,
+    #[cfg(feature = "tls")]
     tls_sni: bool,
+    #[cfg(feature = "tls")]
     tls_verify_hostname: bool,
+    #[cfg(feature = "tls")]
     tls_identity: Option<Identity>,
+    #[cfg(feature = "tls")]
+    tls_host_identities: Vec<(HostPattern, Identity)>,
+    #[cfg(feature = "tls")]
     tls_cert_store: CertStore,
+    #[cfg(feature = "webpki-roots")]
+    tls_bundled_roots: bool,
+    #[cfg(feature = "tls")]
     tls_cert_verification: bool,
+    #[cfg(feature = "tls")]
+    tls_crls: Option<Vec<Crl>>,
+    #[cfg(feature = "tls")]
+    tls_crl_check_all: bool,
+    #[cfg(feature = "tls")]
+    tls_revocation_policy: RevocationPolicy,
+    #[cfg(feature = "tls")]
+    tls_require_ocsp_staple: bool,
+    #[cfg(feature = "tls")]
     min_tls_version: Option<TlsVersion>,
+    #[cfg(feature = "tls")]
     max_tls_version: Option<TlsVersion>,
+    #[cfg(feature = "tls")]
+    tls13_only: bool,
+    #[cfg(feature = "tls")]
+    tls_cipher_list: Option<Cow<'static, str>>,
+    #[cfg(feature = "tls")]
+    tls_sigalgs_list: Option<Cow<'static, str>>,
+    #[cfg(feature = "tls")]
     tls_config: TlsConfig,
+    #[cfg(feature = "tls")]
+    tls_connector: Option<TlsConnector>,
 }
 
 impl Default for ClientBuilder {
@@ -180,6 +276,7 @@ impl ClientBuilder {
                 error: None,
                 headers: HeaderMap::new(),
                 original_headers: None,
+                headers_hook: None,
                 #[cfg(any(
                     feature = "gzip",
                     feature = "zstd",
@@ -188,6 +285,8 @@ impl ClientBuilder {
                 ))]
                 accept_encoding: AcceptEncoding::default(),
                 connect_timeout: None,
+                #[cfg(feature = "socks")]
+                socks_handshake_timeout: None,
                 connection_verbose: false,
                 pool_idle_timeout: Some(Duration::from_secs(90)),
                 pool_max_idle_per_host: usize::MAX,
@@ -202,36 +301,85 @@ impl ClientBuilder {
                 tcp_reuse_address: false,
                 #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
                 tcp_user_timeout: None,
+                proxy_protocol: None,
                 proxies: Vec::new(),
+                proxy_strategy: ProxyStrategy::First,
+                proxy_race: None,
+                proxy_auth_handler: None,
+                proxy_selector: None,
                 auto_sys_proxy: true,
                 redirect_policy: redirect::Policy::default(),
                 referer: true,
                 timeout: None,
                 read_timeout: None,
+                body_poll_budget: 512 * 1024,
                 #[cfg(feature = "hickory-dns")]
                 hickory_dns: cfg!(feature = "hickory-dns"),
                 #[cfg(feature = "cookies")]
                 cookie_store: None,
-                dns_overrides: HashMap::new(),
+                #[cfg(feature = "vcr")]
+                vcr: None,
+                dns_overrides: DnsOverrides::new(),
                 dns_resolver: None,
                 http_version_pref: HttpVersionPref::All,
                 builder: HyperClient::builder(TokioExecutor::new()),
                 https_only: false,
+                max_request_header_bytes: None,
+                trim_cookies_to_fit: false,
                 http1_config: Http1Config::default(),
                 http2_config: Http2Config::default(),
                 http2_max_retry: 2,
+                upload_chunk_bounds: None,
+                retry_budget: None,
+                think_time: None,
+                emulation_pool: None,
+                profile_failover: None,
+                emulation_locale: None,
+                emulation_lenient: false,
                 request_layers: None,
                 connector_layers: None,
+                #[cfg(feature = "tls")]
                 tls_keylog_policy: None,
+                #[cfg(feature = "tls")]
                 tls_info: false,
+                #[cfg(feature = "tls")]
+                tls_debug_capture: false,
+                #[cfg(feature = "tls")]
                 tls_sni: true,
+                #[cfg(feature = "tls")]
                 tls_verify_hostname: true,
+                #[cfg(feature = "tls")]
                 tls_identity: None,
+                #[cfg(feature = "tls")]
+                tls_host_identities: Vec::new(),
+                #[cfg(feature = "tls")]
                 tls_cert_store: CertStore::default(),
+                #[cfg(feature = "webpki-roots")]
+                tls_bundled_roots: false,
+                #[cfg(feature = "tls")]
                 tls_cert_verification: true,
+                #[cfg(feature = "tls")]
+                tls_crls: None,
+                #[cfg(feature = "tls")]
+                tls_crl_check_all: false,
+                #[cfg(feature = "tls")]
+                tls_revocation_policy: RevocationPolicy::HardFail,
+                #[cfg(feature = "tls")]
+                tls_require_ocsp_staple: false,
+                #[cfg(feature = "tls")]
                 min_tls_version: None,
+                #[cfg(feature = "tls")]
                 max_tls_version: None,
+                #[cfg(feature = "tls")]
+                tls13_only: false,
+                #[cfg(feature = "tls")]
+                tls_cipher_list: None,
+                #[cfg(feature = "tls")]
+                tls_sigalgs_list: None,
+                #[cfg(feature = "tls")]
                 tls_config: TlsConfig::default(),
+                #[cfg(feature = "tls")]
+                tls_connector: None,
             },
         }
     }
@@ -241,7 +389,11 @@ impl ClientBuilder {
     /// # Errors
     ///
     /// This method fails if a TLS backend cannot be initialized, or the resolver
-    /// cannot load the system configuration.
+    /// cannot load the system configuration. It also fails with an
+    /// [`EmulationConflicts`](crate::EmulationConflicts) cause if [`http1_only`](Self::http1_only)
+    /// or [`http2_only`](Self::http2_only) replace ALPN protocols of the emulation that do not
+    /// offer the version, unless the emulation is not
+    /// [`strict`](crate::EmulationProviderBuilder::strict).
     pub fn build(self) -> crate::Result<Client> {
         let mut config = self.config;
 
@@ -249,6 +401,24 @@ impl ClientBuilder {
             return Err(err);
         }
 
+        let http_version = match config.http_version_pref {
+            HttpVersionPref::Http1 => Some(Version::HTTP_11),
+            HttpVersionPref::Http2 => Some(Version::HTTP_2),
+            HttpVersionPref::All => None,
+        };
+        if !config.emulation_lenient {
+            Consistency {
+                #[cfg(feature = "tls")]
+                tls: Some(&config.tls_config),
+                http2: None,
+                version: http_version,
+                headers: None,
+                headers_order: None,
+                headers_hook: false,
+            }
+            .check()?;
+        }
+
         let mut proxies = config.proxies;
         if config.auto_sys_proxy {
             proxies.push(ProxyMatcher::system());
@@ -258,38 +428,59 @@ impl ClientBuilder {
         let proxies_maybe_http_custom_headers = proxies
             .iter()
             .any(ProxyMatcher::maybe_has_http_custom_headers);
+        let proxy_auth = config
+            .proxy_auth_handler
+            .map(|handler| Arc::new(ProxyAuth::new(handler)));
+        let proxy_latency = matches!(config.proxy_strategy, ProxyStrategy::FastestOf(_))
+            .then(|| Arc::new(ProxyLatency::default()));
+
+        #[cfg(feature = "tls")]
+        let http1_config = config.http1_config.clone();
+        #[cfg(feature = "tls")]
+        let http2_config = config.http2_config.clone();
 
         config
             .builder
             .http1_config(config.http1_config)
             .http2_config(config.http2_config)
             .http2_only(matches!(config.http_version_pref, HttpVersionPref::Http2))
+            .http2_chunk_bounds(config.upload_chunk_bounds)
             .http2_timer(TokioTimer::new())
             .pool_timer(TokioTimer::new())
             .pool_idle_timeout(config.pool_idle_timeout)
             .pool_max_idle_per_host(config.pool_max_idle_per_host)
-            .pool_max_size(config.pool_max_size);
-
-        let connector = {
-            let resolver = {
-                let mut resolver: Arc<dyn Resolve> = match config.dns_resolver {
-                    Some(dns_resolver) => dns_resolver,
-                    #[cfg(feature = "hickory-dns")]
-                    None if config.hickory_dns => {
-                        Arc::new(HickoryDnsResolver::new(LookupIpStrategy::Ipv4thenIpv6)?)
-                    }
-                    None => Arc::new(GaiResolver::new()),
-                };
-
-                if !config.dns_overrides.is_empty() {
-                    resolver = Arc::new(DnsResolverWithOverrides::new(
-                        resolver,
-                        config.dns_overrides,
-                    ));
+            .pool_max_size(config.pool_max_size)
+            .retry_budget(config.retry_budget.clone());
+
+        #[cfg(feature = "tls")]
+        let tls_revocation = config
+            .tls_crls
+            .map(|crls| {
+                RevocationCheck::new(crls, config.tls_crl_check_all, config.tls_revocation_policy)
+            })
+            .transpose()?;
+
+        let resolver = {
+            let mut resolver: Arc<dyn Resolve> = match config.dns_resolver {
+                Some(dns_resolver) => dns_resolver,
+                #[cfg(feature = "hickory-dns")]
+                None if config.hickory_dns => {
+                    Arc::new(HickoryDnsResolver::new(LookupIpStrategy::Ipv4thenIpv6)?)
                 }
-                DynResolver::new(resolver)
+                None => Arc::new(GaiResolver::new()),
             };
 
+            if !config.dns_overrides.is_empty() {
+                resolver = Arc::new(DnsResolverWithOverrides::new(
+                    resolver,
+                    Arc::new(config.dns_overrides),
+                ));
+            }
+            resolver
+        };
+
+        let built = {
+            #[cfg(feature = "tls")]
             match config.http_version_pref {
                 HttpVersionPref::Http1 => {
                     config.tls_config.alpn_protos = Some(AlpnProtocol::HTTP1.encode());
@@ -300,44 +491,130 @@ impl ClientBuilder {
                 _ => {}
             }
 
-            Connector::builder(proxies.clone(), resolver)
+            #[cfg(feature = "tls")]
+            if let Some(cipher_list) = config.tls_cipher_list {
+                config.tls_config.cipher_list = Some(cipher_list);
+            }
+
+            #[cfg(feature = "tls")]
+            if let Some(sigalgs_list) = config.tls_sigalgs_list {
+                config.tls_config.sigalgs_list = Some(sigalgs_list);
+            }
+
+            #[cfg(feature = "webpki-roots")]
+            if config.tls_bundled_roots && config.tls_cert_store.is_empty() {
+                config.tls_cert_store = CertStore::mozilla_bundle();
+            }
+
+            let connector = Connector::builder(proxies.clone(), DynResolver::new(resolver.clone()))
+                .proxy_strategy(config.proxy_strategy)
+                .proxy_latency(proxy_latency.clone())
+                .proxy_race(
+                    config
+                        .proxy_race
+                        .map(|(candidates, stagger)| ProxyRace::new(candidates, stagger)),
+                )
+                .proxy_auth(proxy_auth.clone())
                 .connect_timeout(config.connect_timeout)
+                .socks_handshake_timeout(
+                    #[cfg(feature = "socks")]
+                    config.socks_handshake_timeout,
+                )
                 .tcp_keepalive(config.tcp_keepalive)
                 .tcp_keepalive_interval(config.tcp_keepalive_interval)
                 .tcp_keepalive_retries(config.tcp_keepalive_retries)
                 .tcp_reuse_address(config.tcp_reuse_address)
                 .tcp_connect_options(config.tcp_connect_options)
                 .tcp_nodelay(config.tcp_nodelay)
+                .proxy_protocol(config.proxy_protocol)
                 .verbose(config.connection_verbose)
+                .tcp_user_timeout(
+                    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+                    config.tcp_user_timeout,
+                );
+
+            #[cfg(feature = "tls")]
+            let connector = connector
                 .tls_max_version(config.max_tls_version)
                 .tls_min_version(config.min_tls_version)
+                .tls13_only(config.tls13_only)
                 .tls_info(config.tls_info)
+                .tls_debug_capture(config.tls_debug_capture)
                 .tls_sni(config.tls_sni)
                 .tls_verify_hostname(config.tls_verify_hostname)
                 .tls_cert_verification(config.tls_cert_verification)
                 .tls_cert_store(config.tls_cert_store)
+                .tls_revocation(tls_revocation.clone())
+                .tls_require_ocsp_staple(config.tls_require_ocsp_staple)
                 .tls_identity(config.tls_identity)
+                .tls_host_identities(config.tls_host_identities)
                 .tls_keylog_policy(config.tls_keylog_policy)
-                .tcp_user_timeout(
-                    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
-                    config.tcp_user_timeout,
-                )
-                .build(config.tls_config, config.connector_layers)?
+                .tls_connector(config.tls_connector);
+
+            connector.build(
+                #[cfg(feature = "tls")]
+                config.tls_config,
+                config.connector_layers,
+            )?
+        };
+        #[cfg(feature = "tls")]
+        let (connector, tls) = built;
+        #[cfg(not(feature = "tls"))]
+        let connector = built;
+
+        #[cfg(feature = "tls")]
+        let snapshot = {
+            let connector = tls.current();
+            let origin = connector.origin();
+            TlsConfigSnapshot {
+                tls: TlsConfig::default(),
+                http1: http1_config,
+                http2: http2_config,
+                default_headers: config.headers.clone(),
+                headers_order: config.original_headers.clone(),
+                keylog: origin.keylog_enabled(),
+                cert_verification: origin.cert_verification_enabled(),
+                verify_hostname: origin.verify_hostname_enabled(),
+                sni: origin.tls_sni_enabled(),
+                identity: origin.has_identity(),
+            }
         };
 
-        let service = {
-            let service = ClientService {
-                client: config.builder.build(connector),
-                config: Arc::new(ClientConfig {
-                    default_headers: config.headers,
-                    original_headers: RequestConfig::new(config.original_headers),
-                    skip_default_headers: RequestConfig::default(),
-                    https_only: config.https_only,
-                    proxies,
-                    proxies_maybe_http_auth,
-                    proxies_maybe_http_custom_headers,
+        let headers_hook = HeadersHookLayer::new(config.headers.clone(), config.headers_hook);
+        let service = ClientService {
+            client: config.builder.build(connector),
+            config: Arc::new(ClientConfig {
+                default_headers: config.headers,
+                original_headers: RequestConfig::new(config.original_headers),
+                skip_default_headers: RequestConfig::default(),
+                https_only: config.https_only,
+                header_budget: config.max_request_header_bytes.map(|limit| HeaderBudget {
+                    limit,
+                    trim_cookies: config.trim_cookies_to_fit,
                 }),
-            };
+                http_version,
+                proxies,
+                proxies_maybe_http_auth,
+                proxies_maybe_http_custom_headers,
+                proxy_auth,
+                proxy_selector: config.proxy_selector,
+                proxy_strategy: config.proxy_strategy,
+                proxy_latency: proxy_latency.clone(),
+            }),
+        };
+
+        let prober = Prober {
+            service: service.clone(),
+            resolver,
+        };
+
+        let service = {
+            #[cfg(feature = "vcr")]
+            let service = ServiceBuilder::new()
+                .layer(VcrLayer::new(
+                    config.vcr.map(crate::vcr::Cassette::open).transpose()?,
+                ))
+                .service(service);
 
             #[cfg(any(
                 feature = "gzip",
@@ -356,11 +633,17 @@ impl ClientBuilder {
                 ))
                 .service(service);
 
+            let service = ServiceBuilder::new()
+                .layer(CoopLayer::new(config.body_poll_budget))
+                .service(service);
+
             #[cfg(feature = "cookies")]
             let service = ServiceBuilder::new()
                 .layer(CookieManagerLayer::new(config.cookie_store))
                 .service(service);
 
+            let service = ServiceBuilder::new().layer(headers_hook).service(service);
+
             let policy = RedirectPolicy::new(config.redirect_policy)
                 .with_referer(config.referer)
                 .with_https_only(config.https_only);
@@ -370,9 +653,9 @@ impl ClientBuilder {
                 .service(service);
 
             let service = ServiceBuilder::new()
-                .layer(RetryLayer::new(Http2RetryPolicy::new(
-                    config.http2_max_retry,
-                )))
+                .layer(RetryLayer::new(
+                    Http2RetryPolicy::new(config.http2_max_retry).with_budget(config.retry_budget),
+                ))
                 .service(service);
 
             match config.request_layers {
@@ -392,6 +675,11 @@ impl ClientBuilder {
                         .map_err(error::map_timeout_to_request_error)
                         .service(service);
 
+                    // The think time is waited before the deadline of the request is armed.
+                    let service = ServiceBuilder::new()
+                        .layer(ThinkTimeLayer::new(config.think_time))
+                        .service(service);
+
                     ClientRef::Boxed(BoxCloneSyncService::new(service))
                 }
                 None => {
@@ -403,6 +691,10 @@ impl ClientBuilder {
                         .map_err(error::map_timeout_to_request_error as _)
                         .service(service);
 
+                    let service = ServiceBuilder::new()
+                        .layer(ThinkTimeLayer::new(config.think_time))
+                        .service(service);
+
                     ClientRef::Generic(service)
                 }
             }
@@ -410,6 +702,16 @@ impl ClientBuilder {
 
         Ok(Client {
             inner: Arc::new(service),
+            prober,
+            #[cfg(feature = "tls")]
+            tls,
+            #[cfg(feature = "tls")]
+            tls_revocation,
+            #[cfg(feature = "tls")]
+            snapshot: Arc::new(snapshot),
+            emulation_pool: config.emulation_pool,
+            profile_failover: config.profile_failover,
+            proxy_latency,
         })
     }
 
@@ -496,6 +798,36 @@ impl ClientBuilder {
         self
     }
 
+    /// Orders the headers of every request as the default headers were inserted, replacing the
+    /// [`original_headers`](Self::original_headers).
+    ///
+    /// The order is taken from the default headers set so far, so this is called after
+    /// [`default_headers`](Self::default_headers). `host` and `content-length` are sent before
+    /// them, and `cookie` after them. The headers of a request missing from the order are sent
+    /// after the ordered ones, in the order they were added.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use wreq::header::{ACCEPT, HeaderMap, HeaderValue, USER_AGENT};
+    ///
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert(USER_AGENT, HeaderValue::from_static("Mozilla/5.0"));
+    /// headers.insert(ACCEPT, HeaderValue::from_static("*/*"));
+    ///
+    /// // host, content-length, user-agent, accept, cookie
+    /// let client = wreq::Client::builder()
+    ///     .default_headers(headers)
+    ///     .headers_order_from_defaults()
+    ///     .build()?;
+    /// # let _ = client;
+    /// # Ok::<(), wreq::Error>(())
+    /// ```
+    pub fn headers_order_from_defaults(mut self) -> ClientBuilder {
+        self.config.original_headers = Some(OriginalHeaders::from_defaults(&self.config.headers));
+        self
+    }
+
     /// Enable a persistent cookie store for the client.
     ///
     /// Cookies received in responses will be preserved and included in
@@ -535,6 +867,21 @@ impl ClientBuilder {
         self
     }
 
+    /// Records the interactions of the client into a cassette, or replays them from it.
+    ///
+    /// Each request is matched or recorded on its own, so the hops of a redirect and the
+    /// retries of a request are separate interactions. The cassette is read when the
+    /// client is built. See the [`vcr`](crate::vcr) module for details.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `vcr` feature to be enabled.
+    #[cfg(feature = "vcr")]
+    pub fn vcr(mut self, vcr: crate::vcr::Vcr) -> ClientBuilder {
+        self.config.vcr = Some(vcr);
+        self
+    }
+
     /// Enable auto gzip decompression by checking the `Content-Encoding` response header.
     ///
     /// If auto gzip decompression is turned on:
@@ -733,6 +1080,116 @@ impl ClientBuilder {
         self
     }
 
+    /// Set the strategy used to choose between several proxies that intercept the same request.
+    ///
+    /// By default, the first matching proxy, in the order they were added, is used.
+    ///
+    /// # Example
+    /// ```
+    /// use wreq::{Client, Proxy, ProxyStrategy};
+    ///
+    /// let client = Client::builder()
+    ///     .proxy(Proxy::all("http://proxy1:8080").unwrap())
+    ///     .proxy(Proxy::all("http://proxy2:8080").unwrap())
+    ///     .proxy(Proxy::all("http://proxy3:8080").unwrap())
+    ///     .proxy_strategy(ProxyStrategy::FastestOf(2))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn proxy_strategy(mut self, strategy: ProxyStrategy) -> ClientBuilder {
+        self.config.proxy_strategy = strategy;
+        self
+    }
+
+    /// Race the tunnels through several proxies that intercept the same request.
+    ///
+    /// Up to `candidates` of the matching proxies are tried for a new connection: the next
+    /// one is started whenever `stagger` elapses, or as soon as an attempt fails, and the
+    /// first tunnel established is used. The other attempts are cancelled, closing their
+    /// connection to the proxy even in the middle of a `CONNECT`.
+    ///
+    /// The proxy that won is tried first for the same destination during the next 30
+    /// seconds, so that later connections only start another proxy if it is slower than
+    /// `stagger`. With [`ProxyStrategy::FastestOf`], the other candidates are tried from
+    /// the lowest to the highest latency, and cancelled attempts do not count as failures.
+    ///
+    /// Plain `http://` requests forwarded to a proxy are not raced, since their proxy
+    /// headers are chosen before connecting. The [`connect_timeout`](Self::connect_timeout)
+    /// applies to the whole race.
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use wreq::{Client, Proxy};
+    ///
+    /// let client = Client::builder()
+    ///     .proxy(Proxy::all("http://eu.proxy:8080").unwrap())
+    ///     .proxy(Proxy::all("http://us.proxy:8080").unwrap())
+    ///     .proxy_race(2, Duration::from_millis(250))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn proxy_race(mut self, candidates: usize, stagger: Duration) -> ClientBuilder {
+        self.config.proxy_race = Some((candidates, stagger));
+        self
+    }
+
+    /// Set a handler answering the authentication challenges of proxies.
+    ///
+    /// When a proxy responds with `407 Proxy Authentication Required` to a `CONNECT` request or
+    /// a forwarded `http` request, the handler is awaited for credentials, and the request is
+    /// retried once with them. The credentials are cached per proxy, and sent with later
+    /// requests until the proxy rejects them with another `407`. Concurrent requests
+    /// challenged by the same proxy share a single call of the handler.
+    ///
+    /// A forwarded `http` request is only retried if its body can be cloned. The `407`
+    /// response is returned otherwise, or if the handler returns no credentials.
+    ///
+    /// Proxies with a [`credentials_provider`](Proxy::credentials_provider) ask it for new
+    /// credentials instead.
+    pub fn proxy_auth_handler(mut self, handler: Arc<dyn ProxyAuthHandler>) -> ClientBuilder {
+        self.config.proxy_auth_handler = Some(handler);
+        self
+    }
+
+    /// Set a selector choosing the proxy of each request, before the proxies of the client.
+    ///
+    /// The selector is called with the URI of every request, and of each redirect. The proxy it
+    /// returns is used as if set with [`RequestBuilder::proxy`], and connections through it are
+    /// only reused by requests given an equal proxy. When it returns `None`, the proxies added
+    /// with [`proxy`](Self::proxy) are used, or none after [`no_proxy`](Self::no_proxy).
+    ///
+    /// A proxy set with [`RequestBuilder::proxy`] takes precedence over the selector.
+    ///
+    /// # Example
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use http::Uri;
+    /// use wreq::{Client, Proxy, ProxySelector};
+    ///
+    /// struct Residential(Proxy);
+    ///
+    /// impl ProxySelector for Residential {
+    ///     fn select(&self, uri: &Uri) -> Option<Proxy> {
+    ///         let host = uri.host()?;
+    ///         host.ends_with(".shop.example").then(|| self.0.clone())
+    ///     }
+    /// }
+    ///
+    /// let selector = Residential(Proxy::all("http://residential.proxy:8080").unwrap());
+    /// let client = Client::builder()
+    ///     .proxy_selector(Arc::new(selector))
+    ///     .no_proxy()
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn proxy_selector(mut self, selector: Arc<dyn ProxySelector>) -> ClientBuilder {
+        self.config.proxy_selector = Some(selector);
+        self
+    }
+
     /// Clear all `Proxies`, so `Client` will use no proxy anymore.
     ///
     /// # Note
@@ -751,7 +1208,11 @@ impl ClientBuilder {
     /// Enables a request timeout.
     ///
     /// The timeout is applied from when the request starts connecting until the
-    /// response body has finished.
+    /// response body has finished. A single deadline covers the whole exchange,
+    /// including redirects, and stops applying once the connection is upgraded.
+    ///
+    /// A timeout set with `RequestBuilder::timeout()` takes precedence, and a zero
+    /// duration disables the timeout.
     ///
     /// Default is no timeout.
     pub fn timeout(mut self, timeout: Duration) -> ClientBuilder {
@@ -761,12 +1222,34 @@ impl ClientBuilder {
 
     /// Set a timeout for only the read phase of a `Client`.
     ///
+    /// The timeout resets after every successful read, and does not apply to the IO
+    /// of an upgraded connection.
+    ///
+    /// A read timeout set with `RequestBuilder::read_timeout()` takes precedence, and
+    /// a zero duration disables the timeout.
+    ///
     /// Default is `None`.
     pub fn read_timeout(mut self, timeout: Duration) -> ClientBuilder {
         self.config.read_timeout = Some(timeout);
         self
     }
 
+    /// Sets how many bytes of a response body are read before yielding to the runtime.
+    ///
+    /// A body whose data arrives faster than it is read never waits for more, so reading it,
+    /// for example with [`Response::bytes`], keeps its task running, and the other tasks of
+    /// the same worker wait until the whole body is read. Once a body returned this many
+    /// bytes without waiting, reading it yields once, letting the runtime schedule other
+    /// tasks. The bytes are counted after decompression.
+    ///
+    /// A budget of `0` disables yielding.
+    ///
+    /// Default is 512 KiB.
+    pub fn body_poll_budget(mut self, budget: usize) -> ClientBuilder {
+        self.config.body_poll_budget = budget;
+        self
+    }
+
     /// Set a timeout for only the connect phase of a `Client`.
     ///
     /// Default is `None`.
@@ -780,6 +1263,20 @@ impl ClientBuilder {
         self
     }
 
+    /// Set a timeout for the SOCKS handshake with a proxy, once connected to it.
+    ///
+    /// A proxy accepting connections but never answering the handshake fails the connection
+    /// once the timeout elapses, with a [timeout](crate::Error::is_timeout) error naming the
+    /// [proxy](crate::Error::proxy), so that the next proxy can be tried without waiting for
+    /// the whole connect timeout.
+    ///
+    /// Default is the [`connect_timeout`](Self::connect_timeout).
+    #[cfg(feature = "socks")]
+    pub fn socks_handshake_timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.config.socks_handshake_timeout = Some(timeout);
+        self
+    }
+
     /// Set whether connections should emit verbose logs.
     ///
     /// Enabling this option will emit [log][] messages at the `TRACE` level
@@ -837,12 +1334,98 @@ impl ClientBuilder {
         self
     }
 
+    /// Sets the maximum estimated size of the headers of a request, in bytes.
+    ///
+    /// The headers are measured right before a request is sent, once the default headers,
+    /// cookies, proxy headers and proxy authorization are added: in HTTP/1, as the request head
+    /// is written; in HTTP/2, as the uncompressed header list a server limits with
+    /// `SETTINGS_MAX_HEADER_LIST_SIZE`. When the version is left to ALPN, the larger of both is
+    /// used. A request over the limit fails without being sent, with an error carrying a
+    /// [`RequestHeadersTooLarge`](crate::RequestHeadersTooLarge), instead of being rejected by
+    /// the server with a `431` or a reset stream.
+    ///
+    /// Default is no limit.
+    pub fn max_request_header_bytes(mut self, max: usize) -> ClientBuilder {
+        self.config.max_request_header_bytes = Some(max);
+        self
+    }
+
+    /// Sets whether cookies are dropped from the requests exceeding
+    /// [`max_request_header_bytes`](Self::max_request_header_bytes) until they fit.
+    ///
+    /// The cookies listed last in the `Cookie` header are dropped first. For the cookies of the
+    /// cookie store, those are the ones with the shortest paths. The names of the dropped
+    /// cookies are stored in the [`TrimmedCookies`] extension of the response. A request that
+    /// still exceeds the limit without cookies fails.
+    ///
+    /// Default is `false`.
+    pub fn trim_cookies_to_fit(mut self, enabled: bool) -> ClientBuilder {
+        self.config.trim_cookies_to_fit = enabled;
+        self
+    }
+
     /// Sets the maximum number of safe retries for HTTP/2 connections.
     pub fn http2_max_retry(mut self, max: usize) -> ClientBuilder {
         self.config.http2_max_retry = max;
         self
     }
 
+    /// Sizes the DATA frames request bodies are sent in from the observed throughput, between
+    /// `min` and `max` bytes.
+    ///
+    /// Sending starts with 16KB pieces. The rate each stream drains its body is then sampled,
+    /// and the pieces grow on fast links, saving frames and wake-ups, and shrink on slow ones,
+    /// so that a large piece does not hold back the other streams of the connection. Body
+    /// chunks are split without copying them when they are `Bytes`, but never merged, so
+    /// smaller chunks are still sent as they are yielded.
+    ///
+    /// Requests with an [`h2_chunk_hint`](crate::RequestBuilder::h2_chunk_hint) use their hint
+    /// instead. This has no effect on HTTP/1 requests, whose body chunks are already batched
+    /// into vectored writes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is zero or larger than `max`.
+    pub fn upload_chunk_bounds(mut self, min: usize, max: usize) -> ClientBuilder {
+        assert!(
+            0 < min && min <= max,
+            "upload chunk bounds must satisfy 0 < min <= max"
+        );
+        self.config.upload_chunk_bounds = Some((min, max));
+        self
+    }
+
+    /// Limits the retries of all requests by a [`RetryBudget`].
+    ///
+    /// Both the safe HTTP/2 retries and the retries of requests canceled on a reused
+    /// connection withdraw from the budget, and are skipped when it is exhausted. Retrying
+    /// requests rejected as TLS early data is not limited.
+    ///
+    /// By default, retries are only limited per request.
+    pub fn retry_budget(mut self, budget: RetryBudget) -> ClientBuilder {
+        self.config.retry_budget = Some(budget);
+        self
+    }
+
+    /// Waits a think time drawn from `think_time` before sending each request.
+    ///
+    /// Requests sent at perfectly regular intervals are easy to tell apart from a person
+    /// browsing, who pauses for varying times between pages. The delay applies once per
+    /// request, before anything is sent for it, so redirects and retries of the request are
+    /// not delayed again, and a connection is only checked out of the pool once the delay is
+    /// over. It does not count toward the total [`timeout`](ClientBuilder::timeout), whose
+    /// deadline starts once the delay is over. The delay is reported by a
+    /// [`ThinkTimeDelay`](crate::ThinkTimeDelay) extension on the request seen by
+    /// [`layer`](ClientBuilder::layer)s and on the response.
+    ///
+    /// Requests opt out with [`RequestBuilder::no_think_time`].
+    ///
+    /// By default, requests are sent without delay.
+    pub fn think_time(mut self, think_time: ThinkTime) -> ClientBuilder {
+        self.config.think_time = Some(think_time);
+        self
+    }
+
     // TCP options
 
     /// Set whether sockets have `TCP_NODELAY` enabled.
@@ -978,6 +1561,37 @@ impl ClientBuilder {
         self
     }
 
+    /// Write a PROXY protocol header at the start of each connection.
+    ///
+    /// The header is written once the TCP connection is established, before any TLS or HTTP
+    /// byte, telling a server behind a load balancer which client the connection stands for.
+    /// Its source address is the local address of the connection, unless
+    /// [another](ProxyProtocolConfig::source) is configured.
+    ///
+    /// The connections through a proxy are to the proxy, so the header is written to the
+    /// proxy rather than to the destination of a tunnel. See
+    /// [`ProxyProtocolConfig::proxies`] to only write it on direct connections.
+    ///
+    /// With [`connection_verbose`](Self::connection_verbose), the headers are logged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use wreq::{ProxyProtocolConfig, ProxyProtocolVersion};
+    ///
+    /// let client = wreq::Client::builder()
+    ///     .proxy_protocol(
+    ///         ProxyProtocolConfig::new(ProxyProtocolVersion::V2)
+    ///             .source("192.0.2.1:56324".parse().unwrap()),
+    ///     )
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn proxy_protocol(mut self, config: ProxyProtocolConfig) -> ClientBuilder {
+        self.config.proxy_protocol = Some(config);
+        self
+    }
+
     // TLS/HTTP2 emulation options
 
     /// Configures the client builder to emulation the specified HTTP context.
@@ -990,6 +1604,7 @@ impl ClientBuilder {
     /// # Note
     /// This will overwrite the existing configuration.
     /// You must set emulation before you can perform subsequent HTTP1/HTTP2/TLS fine-tuning.
+    /// Use [`emulation_with`](Self::emulation_with) to apply only some parts of the emulation.
     ///
     /// # Example
     ///
@@ -1014,6 +1629,11 @@ impl ClientBuilder {
         let mut emulation = factory.emulation();
 
         if let Some(mut headers) = emulation.default_headers {
+            if let Some(ref locale) = self.config.emulation_locale {
+                if headers.contains_key(ACCEPT_LANGUAGE) {
+                    headers.insert(ACCEPT_LANGUAGE, locale.clone());
+                }
+            }
             swap(&mut self.config.headers, &mut headers);
         }
 
@@ -1024,6 +1644,10 @@ impl ClientBuilder {
             );
         }
 
+        if emulation.headers_hook.is_some() {
+            swap(&mut self.config.headers_hook, &mut emulation.headers_hook);
+        }
+
         if let Some(mut http1_config) = emulation.http1_config.take() {
             swap(&mut self.config.http1_config, &mut http1_config);
         }
@@ -1032,13 +1656,124 @@ impl ClientBuilder {
             swap(&mut self.config.http2_config, &mut http2_config);
         }
 
+        // The checks at build time only cover the TLS configuration, skipped only for the one of
+        // a lenient emulation.
+        #[cfg(feature = "tls")]
         if let Some(mut tls_config) = emulation.tls_config.take() {
             swap(&mut self.config.tls_config, &mut tls_config);
+            self.config.emulation_lenient = emulation.lenient;
         }
 
         self
     }
 
+    /// Applies only the `parts` of an emulation, keeping the rest of the configuration.
+    ///
+    /// [`emulation`](Self::emulation) replaces the headers, the HTTP/1 and HTTP/2
+    /// configurations and the TLS configuration together. With this method, the parts left out
+    /// keep what was configured before, such as the default headers of the client.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use wreq::{Client, EmulationApply};
+    /// use wreq_util::Emulation;
+    ///
+    /// // Chrome's handshakes, with the headers of the client.
+    /// let client = Client::builder()
+    ///     .user_agent("my-crawler/1.0")
+    ///     .emulation_with(Emulation::Chrome131, EmulationApply::TLS | EmulationApply::HTTP2)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn emulation_with<P>(self, factory: P, parts: EmulationApply) -> ClientBuilder
+    where
+        P: EmulationProviderFactory,
+    {
+        self.emulation(factory.emulation().retain(parts))
+    }
+
+    /// Sets the `Accept-Language` header of emulations, in place of the one of their profile.
+    ///
+    /// The `Accept-Language` default header is replaced whether the emulation is applied with
+    /// [`emulation`](Self::emulation) or [`emulation_with`](Self::emulation_with) before or
+    /// after this call, and the other headers of the profile are kept. The header is not added
+    /// to profiles without one, and requests setting the header themselves keep their value.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use wreq::Client;
+    /// use wreq_util::Emulation;
+    ///
+    /// let client = Client::builder()
+    ///     .emulation_locale("de-DE,de;q=0.9")
+    ///     .emulation(Emulation::Chrome131)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn emulation_locale<V>(mut self, value: V) -> ClientBuilder
+    where
+        V: TryInto<HeaderValue>,
+        V::Error: Into<http::Error>,
+    {
+        match value.try_into() {
+            Ok(value) => {
+                if self.config.headers.contains_key(ACCEPT_LANGUAGE) {
+                    self.config.headers.insert(ACCEPT_LANGUAGE, value.clone());
+                }
+                self.config.emulation_locale = Some(value);
+            }
+            Err(err) => {
+                self.config.error = Some(Error::builder(err.into()));
+            }
+        };
+        self
+    }
+
+    /// Sends each request with a profile picked from a weighted `pool` of emulations.
+    ///
+    /// The headers, HTTP/1 and HTTP/2 configurations and TLS configuration of the picked
+    /// profile are applied together, as with [`RequestBuilder::emulation`], and the profile
+    /// is kept across the redirects and retries of the request. Its headers take the place of
+    /// the default headers of the client. A connection is only reused by requests that picked
+    /// the same profile, so rotating does not mix fingerprints on a connection. The pick is
+    /// reported by an [`EmulationSelection`](crate::EmulationSelection) extension on the
+    /// response.
+    ///
+    /// Requests with their own [`RequestBuilder::emulation`] are sent as they are. This
+    /// replaces the [`ClientBuilder::profile_failover`] of the client.
+    ///
+    /// By default, every request is sent with the emulation of the client.
+    pub fn emulation_pool(mut self, pool: RotatingEmulation) -> ClientBuilder {
+        self.config.emulation_pool = Some(pool);
+        self.config.profile_failover = None;
+        self
+    }
+
+    /// Sends the requests to each origin with the first profile of `config` that the origin
+    /// does not block.
+    ///
+    /// Requests are sent with the primary profile, the first one, until the `trigger` of
+    /// `config` reports that the origin blocks it, such as by resetting the TLS handshake or
+    /// answering with a challenge. The requests to that origin only are then sent with the next
+    /// profile, and so on. Once `cooldown` has elapsed since the origin was last blocked, the
+    /// primary profile is tried again, and the origin returns to it if it succeeds.
+    ///
+    /// Profiles are applied as with [`ClientBuilder::emulation_pool`], and reported by an
+    /// [`EmulationSelection`](crate::EmulationSelection) extension on the response. The state
+    /// of the origins is available from [`Client::profile_stats`]. This replaces the
+    /// [`ClientBuilder::emulation_pool`] of the client.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `config` has no profile.
+    pub fn profile_failover(mut self, config: FailoverConfig) -> ClientBuilder {
+        self.config.profile_failover = Some(ProfileFailover::new(config));
+        self.config.emulation_pool = None;
+        self
+    }
+
     /// Configures SSL/TLS certificate pinning for the client.
     ///
     /// This method allows you to specify a set of PEM-encoded certificates that the client
@@ -1051,6 +1786,7 @@ impl ClientBuilder {
     ///
     /// - `certs`: An iterator of DER-encoded certificates. Each certificate should be provided as a
     ///   byte slice (`&[u8]`).
+    #[cfg(feature = "tls")]
     pub fn ssl_pinning<'c, I>(mut self, certs: I) -> ClientBuilder
     where
         I: IntoIterator,
@@ -1066,11 +1802,51 @@ impl ClientBuilder {
     }
 
     /// Sets the identity to be used for client certificate authentication.
+    #[cfg(feature = "tls")]
     pub fn identity(mut self, identity: Identity) -> ClientBuilder {
         self.config.tls_identity = Some(identity);
         self
     }
 
+    /// Sets the identity to be used for client certificate authentication with the hosts
+    /// matching `host_pattern`, instead of the one set by [`ClientBuilder::identity`].
+    ///
+    /// The pattern is either an exact host, or `*.` followed by a domain to match all of its
+    /// subdomains, but not the domain itself. Patterns are matched against the server name
+    /// sent in the handshake. When several patterns match, an exact host is preferred over a
+    /// wildcard, and a longer wildcard domain over a shorter one.
+    ///
+    /// Connections are pooled by host and server name, so a connection authenticated with
+    /// one identity is never reused for a host using another.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::fs;
+    ///
+    /// let payments =
+    ///     wreq::Identity::from_pem_parts(&fs::read("payments.pem")?, &fs::read("payments.key")?)?;
+    /// let internal =
+    ///     wreq::Identity::from_pem_parts(&fs::read("internal.pem")?, &fs::read("internal.key")?)?;
+    ///
+    /// let client = wreq::Client::builder()
+    ///     .identity_for("payments.example.com", payments)
+    ///     .identity_for("*.internal.example.com", internal)
+    ///     .build()?;
+    /// # drop(client);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "tls")]
+    pub fn identity_for(mut self, host_pattern: &str, identity: Identity) -> ClientBuilder {
+        match HostPattern::parse(host_pattern) {
+            Ok(pattern) => self.config.tls_host_identities.push((pattern, identity)),
+            Err(err) => self.config.error = Some(err),
+        }
+        self
+    }
+
     /// Controls the use of certificate validation.
     ///
     /// Defaults to `true`.
@@ -1082,11 +1858,110 @@ impl ClientBuilder {
     /// will be trusted for use. This includes expired certificates. This
     /// introduces significant vulnerabilities, and should only be used
     /// as a last resort.
+    #[cfg(feature = "tls")]
     pub fn cert_verification(mut self, cert_verification: bool) -> ClientBuilder {
         self.config.tls_cert_verification = cert_verification;
         self
     }
 
+    /// Checks the revocation status of server certificates against the given CRLs.
+    ///
+    /// Each item is a DER-encoded CRL, or PEM data with any number of CRLs. Calling this
+    /// again adds to the CRLs already set. Once set, a certificate fails verification if
+    /// it is revoked, or, depending on the [`revocation_policy`](Self::revocation_policy),
+    /// if no valid CRL of its issuer is available. The cause is reported as a
+    /// [`RevocationError`](crate::tls::RevocationError) in the source chain of the error.
+    ///
+    /// Only the leaf certificate is checked, unless [`crl_check_all`](Self::crl_check_all)
+    /// is set. The CRLs can be replaced later with [`Client::set_crls`].
+    #[cfg(feature = "tls")]
+    pub fn crl<I>(mut self, crls: I) -> ClientBuilder
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        let parsed = self.config.tls_crls.get_or_insert_with(Vec::new);
+        for crl in crls {
+            match Crl::parse(crl.as_ref()) {
+                Ok(crls) => parsed.extend(crls),
+                Err(err) => {
+                    self.config.error = Some(err);
+                    break;
+                }
+            }
+        }
+        self
+    }
+
+    /// Checks the revocation status of every certificate in the chain, rather than the
+    /// leaf only.
+    ///
+    /// Only has an effect together with [`crl`](Self::crl). Defaults to `false`.
+    #[cfg(feature = "tls")]
+    pub fn crl_check_all(mut self, enabled: bool) -> ClientBuilder {
+        self.config.tls_crl_check_all = enabled;
+        self
+    }
+
+    /// Sets how certificates are treated when no valid CRL of their issuer is available.
+    ///
+    /// Only has an effect together with [`crl`](Self::crl). Defaults to
+    /// [`RevocationPolicy::HardFail`].
+    #[cfg(feature = "tls")]
+    pub fn revocation_policy(mut self, policy: RevocationPolicy) -> ClientBuilder {
+        self.config.tls_revocation_policy = policy;
+        self
+    }
+
+    /// Fails connections whose server does not staple an OCSP response to the handshake.
+    ///
+    /// Requesting a staple is implied, so this also sets
+    /// [`enable_ocsp_stapling`](crate::tls::TlsConfigBuilder::enable_ocsp_stapling), which
+    /// changes the ClientHello of emulations that do not request one. The stapled response
+    /// is available as [`TlsInfo::ocsp_response`](crate::tls::TlsInfo::ocsp_response), but
+    /// is not verified. Such failures are reported by
+    /// [`Error::is_ocsp_staple_missing`](crate::Error::is_ocsp_staple_missing).
+    ///
+    /// Defaults to `false`.
+    #[cfg(feature = "tls")]
+    pub fn require_ocsp_staple(mut self, enabled: bool) -> ClientBuilder {
+        self.config.tls_require_ocsp_staple = enabled;
+        self
+    }
+
+    /// Uses an already built TLS connector, instead of building one from the options of this
+    /// builder.
+    ///
+    /// Clients given clones of the same [`TlsConnector`] share its session cache, and skip the
+    /// work of building one. All TLS options of the connector are those it was built with:
+    /// the TLS options of this builder and of its emulation are ignored, including the ALPN
+    /// protocols set by [`http1_only`](ClientBuilder::http1_only) and
+    /// [`http2_only`](ClientBuilder::http2_only). Requests with a TLS configuration of their
+    /// own still use a connector built for them, with the options of the shared one.
+    ///
+    /// The shared connector is never modified: updating the TLS configuration of a client
+    /// with [`Client::update`] gives it a connector of its own.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use wreq::tls::{TlsConfig, TlsConnector};
+    ///
+    /// # fn doc() -> wreq::Result<()> {
+    /// let connector = TlsConnector::builder().build(TlsConfig::default())?;
+    /// let a = wreq::Client::builder()
+    ///     .tls_connector(connector.clone())
+    ///     .build()?;
+    /// let b = wreq::Client::builder().tls_connector(connector).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "tls")]
+    pub fn tls_connector(mut self, connector: TlsConnector) -> ClientBuilder {
+        self.config.tls_connector = Some(connector);
+        self
+    }
+
     /// Sets the verify certificate store for the client.
     ///
     /// This method allows you to specify a custom verify certificate store to be used
@@ -1103,20 +1978,40 @@ impl ClientBuilder {
     ///   specific certificates that are not included in the system's default store.
     /// - Ensure that the provided verify certificate store is properly configured to avoid
     ///   potential security risks.
+    #[cfg(feature = "tls")]
     pub fn cert_store(mut self, store: CertStore) -> ClientBuilder {
         self.config.tls_cert_store = store;
         self
     }
 
+    /// Falls back to the bundled Mozilla root certificates when the certificate store is
+    /// empty.
+    ///
+    /// A store loaded from the system's default locations, for example with
+    /// [`CertStoreBuilder::set_default_paths`], is empty on minimal systems without
+    /// `/etc/ssl`, and then fails every certificate verification. With this enabled, such
+    /// a store is replaced by [`CertStore::mozilla_bundle`] when the client is built.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// [`CertStoreBuilder::set_default_paths`]: crate::tls::CertStoreBuilder::set_default_paths
+    #[cfg(feature = "webpki-roots")]
+    pub fn use_bundled_roots(mut self, enabled: bool) -> ClientBuilder {
+        self.config.tls_bundled_roots = enabled;
+        self
+    }
+
     /// Configures the use of Server Name Indication (SNI) when connecting.
     ///
     /// Defaults to `true`.
+    #[cfg(feature = "tls")]
     pub fn tls_sni(mut self, tls_sni: bool) -> ClientBuilder {
         self.config.tls_sni = tls_sni;
         self
     }
 
     /// Configures TLS key logging policy for the client.
+    #[cfg(feature = "tls")]
     pub fn keylog(mut self, policy: KeyLogPolicy) -> ClientBuilder {
         self.config.tls_keylog_policy = Some(policy);
         self
@@ -1130,6 +2025,7 @@ impl ClientBuilder {
     /// You should think very carefully before you use this method. If hostname verification is not
     /// used, *any* valid certificate for *any* site will be trusted for use from any other. This
     /// introduces a significant vulnerability to man-in-the-middle attacks.
+    #[cfg(feature = "tls")]
     pub fn verify_hostname(mut self, verify_hostname: bool) -> ClientBuilder {
         self.config.tls_verify_hostname = verify_hostname;
         self
@@ -1138,6 +2034,7 @@ impl ClientBuilder {
     /// Set the minimum required TLS version for connections.
     ///
     /// By default the TLS backend's own default is used.
+    #[cfg(feature = "tls")]
     pub fn min_tls_version(mut self, version: TlsVersion) -> ClientBuilder {
         self.config.min_tls_version = Some(version);
         self
@@ -1146,21 +2043,88 @@ impl ClientBuilder {
     /// Set the maximum allowed TLS version for connections.
     ///
     /// By default there's no maximum.
+    #[cfg(feature = "tls")]
     pub fn max_tls_version(mut self, version: TlsVersion) -> ClientBuilder {
         self.config.max_tls_version = Some(version);
         self
     }
 
+    /// Restrict connections to TLS 1.3.
+    ///
+    /// This overrides any TLS version configured on the client or on a request, and only
+    /// advertises the TLS 1.3 cipher suites, in the order set by
+    /// [`TlsConfig::tls13_cipher_suites`](crate::tls::TlsConfigBuilder::tls13_cipher_suites).
+    ///
+    /// Defaults to `false`.
+    #[cfg(feature = "tls")]
+    pub fn tls13_only(mut self, enabled: bool) -> ClientBuilder {
+        self.config.tls13_only = enabled;
+        self
+    }
+
+    /// Set the cipher list for connections, in the format of
+    /// [`TlsConfig::cipher_list`](crate::tls::TlsConfigBuilder::cipher_list).
+    ///
+    /// Only the cipher list of the TLS configuration set by [`emulation`](Self::emulation)
+    /// is replaced, and its other settings are kept. This takes precedence over the
+    /// emulation regardless of the order of the calls.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let client = wreq::Client::builder()
+    ///     .tls_cipher_list("ECDHE-ECDSA-AES128-GCM-SHA256:ECDHE-RSA-AES128-GCM-SHA256")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[cfg(feature = "tls")]
+    pub fn tls_cipher_list<T>(mut self, ciphers: T) -> ClientBuilder
+    where
+        T: Into<Cow<'static, str>>,
+    {
+        self.config.tls_cipher_list = Some(ciphers.into());
+        self
+    }
+
+    /// Set the supported signature algorithms for connections, in the format of
+    /// [`TlsConfig::sigalgs_list`](crate::tls::TlsConfigBuilder::sigalgs_list).
+    ///
+    /// Like [`tls_cipher_list`](Self::tls_cipher_list), only this setting of the emulation
+    /// is replaced, regardless of the order of the calls.
+    #[cfg(feature = "tls")]
+    pub fn tls_sigalgs_list<T>(mut self, sigalgs: T) -> ClientBuilder
+    where
+        T: Into<Cow<'static, str>>,
+    {
+        self.config.tls_sigalgs_list = Some(sigalgs.into());
+        self
+    }
+
     /// Add TLS information as `TlsInfo` extension to responses.
     ///
     /// # Optional
     ///
     /// feature to be enabled.
+    #[cfg(feature = "tls")]
     pub fn tls_info(mut self, tls_info: bool) -> ClientBuilder {
         self.config.tls_info = tls_info;
         self
     }
 
+    /// Add the raw TLS handshake messages as `TlsHandshakeCapture` extension to responses.
+    ///
+    /// When enabled, the ClientHello sent and the ServerHello received while establishing
+    /// a connection are recorded, so they can be compared byte-for-byte against another
+    /// client's capture. Each message is bounded in size, and the capture is only attached
+    /// to responses served by the connection it was recorded on.
+    ///
+    /// Defaults to `false`.
+    #[cfg(feature = "tls")]
+    pub fn tls_debug_capture(mut self, enabled: bool) -> ClientBuilder {
+        self.config.tls_debug_capture = enabled;
+        self
+    }
+
     /// Restrict the Client to be used with HTTPS only requests.
     ///
     /// Defaults to false.
@@ -1202,10 +2166,12 @@ impl ClientBuilder {
     /// traffic to a particular port you must include this port in the URL
     /// itself, any port in the overridden addresses will be ignored and traffic sent
     /// to the conventional port for the given scheme (e.g. 80 for http).
+    ///
+    /// Domains are matched case-insensitively, ignoring a trailing dot.
     pub fn resolve_to_addrs(mut self, domain: &str, addrs: &[SocketAddr]) -> ClientBuilder {
         self.config
             .dns_overrides
-            .insert(domain.to_string(), addrs.to_vec());
+            .insert(normalize_override_name(domain), Arc::from(addrs));
         self
     }
 
@@ -1214,6 +2180,9 @@ impl ClientBuilder {
     /// Pass an `Arc` wrapping a trait object implementing `Resolve`.
     /// Overrides for specific names passed to `resolve` and `resolve_to_addrs` will
     /// still be applied on top of this resolver.
+    ///
+    /// Within a single request, including its retries and redirect hops, the addresses a
+    /// host resolved to are reused for a few seconds rather than resolved again.
     pub fn dns_resolver<R: Resolve + 'static>(mut self, resolver: Arc<R>) -> ClientBuilder {
         self.config.dns_resolver = Some(resolver as _);
         self
@@ -1392,10 +2361,83 @@ impl Client {
     ///
     /// This method fails whenever the supplied `Url` cannot be parsed.
     pub fn request<U: IntoUrl>(&self, method: Method, url: U) -> RequestBuilder {
-        let req = url.into_url().map(move |url| Request::new(method, url));
+        let req = url.into_url_with_zone().map(move |(url, zone)| {
+            let mut req = Request::new(method, url);
+            *req.ipv6_zone_mut() = zone;
+            req
+        });
         RequestBuilder::new(self.clone(), req)
     }
 
+    /// Removes all sessions from the TLS session cache.
+    ///
+    /// Subsequent connections perform a full handshake. This is useful after rotating
+    /// credentials, or when a server starts rejecting resumed sessions. Requests in
+    /// flight are not affected.
+    ///
+    /// Connections made with a per-request TLS configuration use their own cache, which
+    /// is not cleared.
+    #[cfg(feature = "tls")]
+    pub fn clear_tls_sessions(&self) {
+        self.tls.sessions().clear();
+    }
+
+    /// Returns the statistics of the TLS session cache.
+    ///
+    /// All counts are zero if session resumption is disabled, see
+    /// [`TlsConfigBuilder::pre_shared_key`](crate::tls::TlsConfigBuilder::pre_shared_key).
+    #[cfg(feature = "tls")]
+    pub fn tls_session_stats(&self) -> TlsSessionStats {
+        self.tls.sessions().stats()
+    }
+
+    /// Returns the failover state of the origins that were blocked since the client was built,
+    /// ordered by origin, see [`ClientBuilder::profile_failover`].
+    pub fn profile_stats(&self) -> Vec<ProfileStats> {
+        self.profile_failover
+            .as_ref()
+            .map(ProfileFailover::stats)
+            .unwrap_or_default()
+    }
+
+    /// Returns the latency estimates of the proxies measured since the client was built,
+    /// ordered by proxy.
+    ///
+    /// Only tracked with [`ProxyStrategy::FastestOf`], see [`ClientBuilder::proxy_strategy`].
+    pub fn proxy_latency_stats(&self) -> Vec<ProxyLatencyStats> {
+        self.proxy_latency
+            .as_ref()
+            .map(|latency| latency.stats())
+            .unwrap_or_default()
+    }
+
+    /// Replaces the CRLs server certificates are checked against.
+    ///
+    /// Takes the same input as [`ClientBuilder::crl`], and applies to subsequent
+    /// handshakes. Established connections are not affected.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if a CRL cannot be parsed, or if revocation checking was not
+    /// enabled with [`ClientBuilder::crl`].
+    #[cfg(feature = "tls")]
+    pub fn set_crls<I>(&self, crls: I) -> crate::Result<()>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        let check = self
+            .tls_revocation
+            .as_ref()
+            .ok_or_else(|| Error::builder("certificate revocation checking is not enabled"))?;
+
+        let mut parsed = Vec::new();
+        for crl in crls {
+            parsed.extend(Crl::parse(crl.as_ref())?);
+        }
+        check.set_crls(parsed)
+    }
+
     /// Executes a `Request`.
     ///
     /// A `Request` can be built manually with `Request::new()` or obtained
@@ -1408,18 +2450,42 @@ impl Client {
     ///
     /// This method fails if there was an error while sending request,
     /// redirect loop was detected or redirect limit was exhausted.
-    pub fn execute(&self, request: Request) -> Pending {
+    pub fn execute(&self, mut request: Request) -> Pending {
+        // The profile is picked once, so that redirects and retries keep it.
+        let mut failover = None;
+        if request.transport_config_mut().is_none() {
+            if let Some(ref pool) = self.emulation_pool {
+                pool.apply(pool.select(), &mut request);
+            } else if let Some(ref profiles) = self.profile_failover {
+                failover = Some(Box::new(profiles.apply(&mut request)));
+            }
+        }
+
         match request.try_into() {
-            Ok((url, req)) => {
+            Ok((url, mut req)) => {
+                let extensions = req.extensions_mut();
+                extensions.insert(OriginalUrl(url.clone()));
+                extensions.insert(RequestAttempt(1));
+                extensions.insert(RedirectHop(0));
+                extensions.insert(ResolutionMemo::default());
+
+                // The digest applies to the body of the final response, after redirects
+                // and retries.
+                let digest = RequestConfig::<RequestExpectedDigest>::get(req.extensions()).cloned();
+
                 // Prepare the future request by ensuring we use the exact same Service instance
                 // for both poll_ready and call.
                 match *self.inner {
                     ClientRef::Boxed(ref service) => Pending::BoxedRequest {
                         url: Some(url),
+                        digest,
+                        failover,
                         fut: service.clone().oneshot(req),
                     },
                     ClientRef::Generic(ref service) => Pending::GenericRequest {
                         url: Some(url),
+                        digest,
+                        failover,
                         fut: Box::pin(service.clone().oneshot(req)),
                     },
                 }
@@ -0,0 +1,421 @@
+//! Health probes of an origin.
+
+use std::{
+    error::Error as StdError,
+    fmt,
+    net::SocketAddr,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use http::{Method, Request as HttpRequest, StatusCode, Version, uri::Scheme};
+use url::{Host, Url};
+
+use super::{Body, Client, service::ClientService};
+#[cfg(feature = "tls")]
+use crate::tls::TlsInfo;
+use crate::{
+    IntoUrl,
+    connect::ConnectTiming,
+    core::client::connect::{ConnectError, Connected, HttpInfo, proxy::TunnelError},
+    dns::{Name, Resolve},
+    error::{BoxError, Error, TimedOut},
+};
+
+/// The timeout of a probe, by default.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How far a [`Client::probe`] goes in reaching an origin.
+///
+/// Every depth includes the phases before it, and the phases are also used to attribute the
+/// failure of a probe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ProbeDepth {
+    /// Resolves the host with the resolver and DNS overrides of the client.
+    Dns,
+    /// Connects to the host, or the proxy serving it, including establishing a tunnel through
+    /// the proxy.
+    Tcp,
+    /// Completes the TLS handshake with the host. Equivalent to `Tcp` for `http` URLs.
+    Tls,
+    /// Sends a `HEAD` request, or the method set with [`ProbeBuilder::method`].
+    Http,
+}
+
+/// State shared with the probes of a client.
+#[derive(Clone)]
+pub(super) struct Prober {
+    pub(super) service: ClientService,
+    pub(super) resolver: Arc<dyn Resolve>,
+}
+
+/// A builder to configure a probe, created by [`Client::probe`].
+#[must_use = "a probe does nothing until it is sent"]
+pub struct ProbeBuilder {
+    prober: Prober,
+    url: crate::Result<Url>,
+    depth: ProbeDepth,
+    keep: bool,
+    method: Method,
+    timeout: Duration,
+}
+
+/// The outcome of a probe, with the duration of every phase that completed.
+///
+/// A failed probe reports the phases before the failure, and the phase it failed in.
+#[derive(Debug)]
+pub struct ProbeReport {
+    depth: ProbeDepth,
+    addrs: Vec<SocketAddr>,
+    dns: Option<Duration>,
+    tcp: Option<Duration>,
+    tls: Option<Duration>,
+    http: Option<Duration>,
+    remote_addr: Option<SocketAddr>,
+    version: Option<Version>,
+    #[cfg(feature = "tls")]
+    tls_info: Option<TlsInfo>,
+    status: Option<StatusCode>,
+    failure: Option<(ProbeDepth, Error)>,
+}
+
+impl Client {
+    /// Probes how healthy the origin of `url` is, reaching it exactly as a request would: through
+    /// the same proxy, with the same TLS configuration and DNS overrides.
+    ///
+    /// Unlike a request, a probe always establishes a new connection, and neither takes one
+    /// from the connection pool nor adds one to it, unless [`ProbeBuilder::keep`] is set.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use wreq::ProbeDepth;
+    ///
+    /// # async fn doc() -> wreq::Result<()> {
+    /// let client = wreq::Client::new();
+    /// let report = client
+    ///     .probe("https://example.com", ProbeDepth::Tls)
+    ///     .send()
+    ///     .await?;
+    ///
+    /// match report.error() {
+    ///     Some(err) => println!("{:?} failed: {err}", report.failed_phase()),
+    ///     None => println!("handshake took {:?}", report.tls()),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn probe<U: IntoUrl>(&self, url: U, depth: ProbeDepth) -> ProbeBuilder {
+        ProbeBuilder {
+            prober: self.prober.clone(),
+            url: url.into_url(),
+            depth,
+            keep: false,
+            method: Method::HEAD,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+impl ProbeBuilder {
+    /// Adds the connection of a successful `Tls` or `Http` probe to the connection pool, for
+    /// reuse by later requests.
+    ///
+    /// The TLS info of a kept connection is only reported if the client was built with
+    /// [`ClientBuilder::tls_info`](crate::ClientBuilder::tls_info), since it is then added to
+    /// the responses of the requests reusing the connection.
+    pub fn keep(mut self, keep: bool) -> ProbeBuilder {
+        self.keep = keep;
+        self
+    }
+
+    /// Sets the method of the request of an `Http` probe, such as `OPTIONS`.
+    ///
+    /// Defaults to `HEAD`.
+    pub fn method(mut self, method: Method) -> ProbeBuilder {
+        self.method = method;
+        self
+    }
+
+    /// Sets the timeout of the whole probe.
+    ///
+    /// Defaults to 5 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> ProbeBuilder {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Runs the probe.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if the URL is invalid, or not `http` or `https`. A failure to reach
+    /// the origin is not an error, but reported in the [`ProbeReport`].
+    pub async fn send(self) -> crate::Result<ProbeReport> {
+        let url = self.url?;
+        let is_https = match url.scheme() {
+            "https" => true,
+            "http" => false,
+            _ => return Err(Error::url_bad_scheme(url)),
+        };
+        let port = url
+            .port_or_known_default()
+            .expect("http and https have default ports");
+
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        let mut report = ProbeReport::new(self.depth);
+
+        let start = Instant::now();
+        let resolving = resolve(&*self.prober.resolver, &url, port);
+        match tokio::time::timeout_at(deadline, resolving).await {
+            Ok(Ok(addrs)) => report.addrs = addrs,
+            Ok(Err(err)) => return Ok(report.fail(ProbeDepth::Dns, Error::request(err), url)),
+            Err(_) => return Ok(report.fail(ProbeDepth::Dns, Error::request(TimedOut), url)),
+        }
+        report.dns = Some(start.elapsed());
+
+        if self.depth == ProbeDepth::Dns {
+            return Ok(report);
+        }
+
+        // A TCP probe of an `https` URL stops before the handshake
+        let mut uri = url.as_str().parse::<http::Uri>().map_err(Error::builder)?;
+        if self.depth == ProbeDepth::Tcp && is_https {
+            let mut parts = uri.into_parts();
+            parts.scheme = Some(Scheme::HTTP);
+            parts.authority = Some(
+                format!("{}:{port}", url.host_str().unwrap_or_default())
+                    .parse()
+                    .map_err(Error::builder)?,
+            );
+            uri = http::Uri::from_parts(parts).map_err(Error::builder)?;
+        }
+
+        let mut req = HttpRequest::new(Body::empty());
+        *req.method_mut() = self.method;
+        *req.uri_mut() = uri;
+
+        let send = self.depth == ProbeDepth::Http;
+        let keep = self.keep && self.depth >= ProbeDepth::Tls;
+        let tls = is_https && self.depth >= ProbeDepth::Tls;
+
+        let start = Instant::now();
+        let mut connected = None;
+        let probing = self.prober.service.probe(req, send, keep, &mut connected);
+        let result = match tokio::time::timeout_at(deadline, probing).await {
+            Ok(result) => result,
+            Err(_) => Err(Box::new(TimedOut) as BoxError),
+        };
+
+        let established = connected.is_some();
+        if let Some(connected) = connected {
+            report.connected(&connected, tls);
+        }
+
+        match result {
+            Ok(res) => {
+                if let Some(res) = res {
+                    report.http = Some(
+                        start
+                            .elapsed()
+                            .saturating_sub(report.tcp.unwrap_or_default())
+                            .saturating_sub(report.tls.unwrap_or_default()),
+                    );
+                    report.version = Some(res.version());
+                    report.status = Some(res.status());
+                }
+                Ok(report)
+            }
+            Err(err) => {
+                let phase = if established {
+                    ProbeDepth::Http
+                } else if !tls || is_transport_error(&*err) {
+                    ProbeDepth::Tcp
+                } else {
+                    ProbeDepth::Tls
+                };
+
+                let err = match err.downcast::<Error>() {
+                    Ok(err) => *err,
+                    Err(err) => Error::request(err),
+                };
+                Ok(report.fail(phase, err, url))
+            }
+        }
+    }
+}
+
+impl ProbeReport {
+    fn new(depth: ProbeDepth) -> ProbeReport {
+        ProbeReport {
+            depth,
+            addrs: Vec::new(),
+            dns: None,
+            tcp: None,
+            tls: None,
+            http: None,
+            remote_addr: None,
+            version: None,
+            #[cfg(feature = "tls")]
+            tls_info: None,
+            status: None,
+            failure: None,
+        }
+    }
+
+    /// Records the phases of an established connection.
+    fn connected(&mut self, connected: &Connected, tls: bool) {
+        let mut extensions = http::Extensions::new();
+        connected.get_extras(&mut extensions);
+
+        if let Some(timing) = extensions.get::<ConnectTiming>() {
+            self.tcp = Some(timing.tcp());
+            self.tls = timing.tls();
+        }
+        self.remote_addr = extensions.get::<HttpInfo>().map(HttpInfo::remote_addr);
+
+        if tls {
+            self.version = Some(if connected.is_negotiated_h2() {
+                Version::HTTP_2
+            } else {
+                Version::HTTP_11
+            });
+            #[cfg(feature = "tls")]
+            self.tls_info = extensions.remove::<TlsInfo>();
+        }
+    }
+
+    fn fail(mut self, phase: ProbeDepth, err: Error, url: Url) -> ProbeReport {
+        self.failure = Some((phase, err.with_url(url)));
+        self
+    }
+
+    /// Returns the depth the probe was run with.
+    pub fn depth(&self) -> ProbeDepth {
+        self.depth
+    }
+
+    /// Returns whether every phase of the probe succeeded.
+    pub fn is_success(&self) -> bool {
+        self.failure.is_none()
+    }
+
+    /// Returns the phase the probe failed in, if it failed.
+    ///
+    /// A connect timeout is attributed to the `Tcp` phase.
+    pub fn failed_phase(&self) -> Option<ProbeDepth> {
+        self.failure.as_ref().map(|(phase, _)| *phase)
+    }
+
+    /// Returns the error the probe failed with, if it failed.
+    pub fn error(&self) -> Option<&Error> {
+        self.failure.as_ref().map(|(_, err)| err)
+    }
+
+    /// Returns the addresses the host resolved to.
+    ///
+    /// An IP address host resolves to itself.
+    pub fn addrs(&self) -> &[SocketAddr] {
+        &self.addrs
+    }
+
+    /// Returns how long resolving the host took.
+    pub fn dns(&self) -> Option<Duration> {
+        self.dns
+    }
+
+    /// Returns how long connecting took, including resolving the host again as the connector
+    /// does, and establishing a tunnel through a proxy.
+    pub fn tcp(&self) -> Option<Duration> {
+        self.tcp
+    }
+
+    /// Returns how long the TLS handshake took.
+    pub fn tls(&self) -> Option<Duration> {
+        self.tls
+    }
+
+    /// Returns how long the request of an `Http` probe took, after connecting.
+    pub fn http(&self) -> Option<Duration> {
+        self.http
+    }
+
+    /// Returns the address connected to: the host's, or the proxy's.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    /// Returns the HTTP version of the response of an `Http` probe, or else the version
+    /// negotiated with ALPN: HTTP/1.1 unless HTTP/2 was negotiated.
+    pub fn version(&self) -> Option<Version> {
+        self.version
+    }
+
+    /// Returns the TLS info of the connection, with the negotiated cipher suite.
+    #[cfg(feature = "tls")]
+    pub fn tls_info(&self) -> Option<&TlsInfo> {
+        self.tls_info.as_ref()
+    }
+
+    /// Returns the status of the response of an `Http` probe.
+    pub fn status(&self) -> Option<StatusCode> {
+        self.status
+    }
+}
+
+impl fmt::Debug for ProbeBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProbeBuilder")
+            .field("url", &self.url)
+            .field("depth", &self.depth)
+            .field("keep", &self.keep)
+            .field("method", &self.method)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+/// Resolves the host of `url`, setting the port of the addresses as the connector does.
+async fn resolve(
+    resolver: &dyn Resolve,
+    url: &Url,
+    port: u16,
+) -> Result<Vec<SocketAddr>, BoxError> {
+    let host = match url.host() {
+        Some(Host::Domain(domain)) => domain,
+        Some(Host::Ipv4(ip)) => return Ok(vec![SocketAddr::new(ip.into(), port)]),
+        Some(Host::Ipv6(ip)) => return Ok(vec![SocketAddr::new(ip.into(), port)]),
+        None => return Err("URL has no host".into()),
+    };
+
+    let name = Name::from_str(host).map_err(|_| "invalid host name")?;
+    let addrs = resolver.resolve(name).await?;
+    Ok(addrs
+        .map(|mut addr| {
+            if url.port().is_some() || addr.port() == 0 {
+                addr.set_port(port);
+            }
+            addr
+        })
+        .collect())
+}
+
+/// Returns whether a connect error happened before the TLS handshake: while connecting to the
+/// host or proxy, or establishing a tunnel through the proxy.
+fn is_transport_error(err: &(dyn StdError + 'static)) -> bool {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        if err.is::<ConnectError>() || err.is::<TunnelError>() || err.is::<TimedOut>() {
+            return true;
+        }
+
+        #[cfg(feature = "socks")]
+        if err.is::<crate::core::client::connect::proxy::SocksError<BoxError>>() {
+            return true;
+        }
+
+        source = err.source();
+    }
+    false
+}
@@ -3,21 +3,36 @@ use std::{
     task::{Context, Poll},
 };
 
-use http::{HeaderMap, Request, Response, header::PROXY_AUTHORIZATION, uri::Scheme};
+use http::{
+    HeaderMap, Request, Response, StatusCode, Uri, Version,
+    header::{PROXY_AUTHENTICATE, PROXY_AUTHORIZATION},
+    uri::Scheme,
+};
 use tower::Service;
 
-use super::{Body, future::CorePending};
+use super::{Body, HeaderBudget, future::CorePending};
+#[cfg(not(feature = "tls"))]
+use crate::error::TlsDisabled;
 use crate::{
-    client::middleware::config::RequestSkipDefaultHeaders,
+    client::{
+        dispatch::DispatchInfo,
+        middleware::config::{RequestRemovedHeaders, RequestSkipDefaultHeaders},
+    },
     connect::Connector,
     core::{
         body::Incoming,
-        client::Client,
-        ext::{RequestConfig, RequestOriginalHeaders},
+        client::{Client, connect::Connected},
+        ext::{
+            RequestConfig, RequestHttpVersionPref, RequestNoProxy, RequestOriginalHeaders,
+            RequestProxyMatcher, RequestTemplatedProxyAuth,
+        },
     },
     error::{BoxError, Error},
     into_url::IntoUrlSealed,
-    proxy::Matcher as ProxyMatcher,
+    proxy::{
+        CredentialsCache, DigestAuth, Intercepted, Matcher as ProxyMatcher, Proxy, ProxyAuth,
+        ProxyLatency, ProxySelector, ProxyStrategy, TemplatedAuth,
+    },
 };
 
 #[derive(Clone)]
@@ -31,24 +46,191 @@ pub(super) struct ClientConfig {
     pub(super) skip_default_headers: RequestConfig<RequestSkipDefaultHeaders>,
     pub(super) original_headers: RequestConfig<RequestOriginalHeaders>,
     pub(super) https_only: bool,
+    pub(super) header_budget: Option<HeaderBudget>,
+    /// The version of all requests, if the client only uses one.
+    pub(super) http_version: Option<Version>,
     pub(super) proxies: Arc<Vec<ProxyMatcher>>,
     pub(super) proxies_maybe_http_auth: bool,
     pub(super) proxies_maybe_http_custom_headers: bool,
+    pub(super) proxy_auth: Option<Arc<ProxyAuth>>,
+    pub(super) proxy_selector: Option<Arc<dyn ProxySelector>>,
+    pub(super) proxy_strategy: ProxyStrategy,
+    /// Only tracked when proxies are selected by latency.
+    pub(super) proxy_latency: Option<Arc<ProxyLatency>>,
 }
 
 impl ClientService {
+    /// Probes the origin of `req` as it would be reached by a request, see
+    /// [`HyperClient::probe`](crate::core::client::Client::probe).
+    ///
+    /// The connection info is stored in `connected` as soon as the connection is established.
+    pub(super) async fn probe(
+        &self,
+        mut req: Request<Body>,
+        send: bool,
+        keep: bool,
+        connected: &mut Option<Connected>,
+    ) -> Result<Option<Response<Incoming>>, BoxError> {
+        self.apply_default_headers(&mut req);
+        self.apply_proxy_selector(&mut req);
+        self.apply_proxy_strategy(&mut req);
+        self.apply_proxy_auth_template(&mut req);
+        self.apply_proxy_headers(&mut req);
+        self.client
+            .probe(req, send, keep, connected)
+            .await
+            .map_err(Into::into)
+    }
+
+    #[inline]
+    fn apply_default_headers(&self, req: &mut Request<Body>) {
+        // Only skip setting default headers if skip_default_headers is explicitly Some(true).
+        let skip = self
+            .config
+            .skip_default_headers
+            .fetch(req.extensions())
+            .copied()
+            == Some(true);
+
+        if !skip {
+            let removed = RequestConfig::<RequestRemovedHeaders>::get(req.extensions()).cloned();
+            let removed = removed.as_deref().unwrap_or_default();
+            let headers = req.headers_mut();
+            // Insert default headers if they are not already present in the request, nor
+            // removed from it.
+            for name in self.config.default_headers.keys() {
+                if !headers.contains_key(name) && !removed.contains(name) {
+                    for value in self.config.default_headers.get_all(name) {
+                        headers.append(name, value.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sets the proxy chosen by the proxy selector on `req`, unless it has a proxy of its own.
+    ///
+    /// The proxy becomes part of the pool key of the connection, so that connections through
+    /// different proxies are never shared.
+    fn apply_proxy_selector(&self, req: &mut Request<Body>) {
+        let Some(ref selector) = self.config.proxy_selector else {
+            return;
+        };
+        if bypasses_proxies(req) {
+            return;
+        }
+
+        let matcher = RequestConfig::<RequestProxyMatcher>::get_mut(req.extensions_mut());
+        if matcher.is_none() {
+            *matcher = selector.select(req.uri()).map(Proxy::into_matcher);
+        }
+    }
+
+    /// Sets the proxy chosen by latency on a plain HTTP request, unless it has a proxy of its
+    /// own.
+    ///
+    /// The proxy headers of plain HTTP requests are applied before connecting, so their proxy
+    /// is chosen here rather than by the connector. The proxy becomes part of the pool key of
+    /// the connection, so that the request is only sent on connections through it.
+    fn apply_proxy_strategy(&self, req: &mut Request<Body>) {
+        let (ProxyStrategy::FastestOf(n), Some(latency)) = (
+            self.config.proxy_strategy,
+            self.config.proxy_latency.as_ref(),
+        ) else {
+            return;
+        };
+        if req.uri().scheme() != Some(&Scheme::HTTP) || bypasses_proxies(req) {
+            return;
+        }
+        if RequestConfig::<RequestProxyMatcher>::get(req.extensions()).is_some() {
+            return;
+        }
+
+        let uri = req.uri();
+        let candidates = self
+            .config
+            .proxies
+            .iter()
+            .filter_map(|proxy| Some((proxy, proxy.intercept(uri)?)))
+            .collect::<Vec<_>>();
+        if candidates.len() < 2 {
+            return;
+        }
+
+        if let Some((proxy, _)) =
+            latency.select(candidates, n, |(_, intercepted)| intercepted.uri())
+        {
+            *RequestConfig::<RequestProxyMatcher>::get_mut(req.extensions_mut()) =
+                Some(proxy.clone());
+        }
+    }
+
+    /// Evaluates the auth templates of the proxy of `req`, and of the proxies chained before
+    /// it, for the request.
+    ///
+    /// The credentials are sent with the request if it is forwarded to the proxy. Otherwise
+    /// they are sent when opening the tunnels, and become part of the pool key of the
+    /// connection, so that tunnels opened with other credentials are never shared.
+    fn apply_proxy_auth_template(&self, req: &mut Request<Body>) {
+        let Some(proxy) = self.proxy(req) else {
+            return;
+        };
+
+        // the proxies chained before are always tunneled through
+        let mut tunneled: Vec<TemplatedAuth> = proxy
+            .chain()
+            .iter()
+            .filter_map(|hop| {
+                let template = hop.auth_template()?;
+                Some(template.evaluate(hop.uri(), req.uri(), req.extensions()))
+            })
+            .collect();
+
+        if let Some(template) = proxy.auth_template() {
+            let auth = template.evaluate(proxy.uri(), req.uri(), req.extensions());
+            if req.uri().scheme() == Some(&Scheme::HTTP) && proxy.forwards_http() {
+                if !req.headers().contains_key(PROXY_AUTHORIZATION) {
+                    req.headers_mut().insert(PROXY_AUTHORIZATION, auth.header());
+                }
+            } else {
+                tunneled.push(auth);
+            }
+        }
+
+        if !tunneled.is_empty() {
+            *RequestConfig::<RequestTemplatedProxyAuth>::get_mut(req.extensions_mut()) =
+                Some(tunneled.into());
+        }
+    }
+
     #[inline]
     fn apply_proxy_headers(&self, req: &mut Request<Body>) {
         // Skip if the destination is not plain HTTP.
         // For HTTPS, the proxy headers should be part of the CONNECT tunnel instead.
-        if req.uri().scheme() != Some(&Scheme::HTTP) {
+        if req.uri().scheme() != Some(&Scheme::HTTP) || bypasses_proxies(req) {
             return;
         }
 
+        // A proxy set on the request takes the place of the proxies of the client.
+        let request_proxy = self.request_proxy(req);
+        let proxies = match request_proxy {
+            Some(ref proxy) => std::slice::from_ref(proxy),
+            None => self.config.proxies.as_slice(),
+        };
+
         // Determine whether we need to apply proxy auth and/or custom headers.
-        let need_auth = self.config.proxies_maybe_http_auth
-            && !req.headers_mut().contains_key(PROXY_AUTHORIZATION);
-        let need_custom_headers = self.config.proxies_maybe_http_custom_headers;
+        let (maybe_http_auth, maybe_http_custom_headers) = match request_proxy {
+            Some(ref proxy) => (
+                proxy.maybe_has_http_auth(),
+                proxy.maybe_has_http_custom_headers(),
+            ),
+            None => (
+                self.config.proxies_maybe_http_auth,
+                self.config.proxies_maybe_http_custom_headers,
+            ),
+        };
+        let need_auth = maybe_http_auth && !req.headers_mut().contains_key(PROXY_AUTHORIZATION);
+        let need_custom_headers = maybe_http_custom_headers;
 
         // If no headers need to be applied, return early.
         if !need_auth && !need_custom_headers {
@@ -58,7 +240,7 @@ impl ClientService {
         let mut inserted_auth = false;
         let mut inserted_custom = false;
 
-        for proxy in self.config.proxies.iter() {
+        for proxy in proxies {
             // Insert basic auth header from the first applicable proxy.
             if need_auth && !inserted_auth {
                 if let Some(auth_header) = proxy.http_non_tunnel_basic_auth(req.uri()) {
@@ -70,9 +252,8 @@ impl ClientService {
             // Insert custom headers from the first applicable proxy.
             if need_custom_headers && !inserted_custom {
                 if let Some(custom_headers) = proxy.http_non_tunnel_custom_headers(req.uri()) {
-                    for (key, value) in custom_headers.iter() {
-                        req.headers_mut().insert(key.clone(), value.clone());
-                    }
+                    // replaces the headers of the request with every value of the proxy's
+                    req.headers_mut().extend(custom_headers);
                     inserted_custom = true;
                 }
             }
@@ -83,6 +264,178 @@ impl ClientService {
             }
         }
     }
+
+    /// Sends `req` as it is, once its size fits in the header budget.
+    ///
+    /// The budget is checked here rather than in `call`, since the proxy authentication paths
+    /// set `Proxy-Authorization` last, and send the request again with another one.
+    fn send(&mut self, mut req: Request<Body>) -> CorePending {
+        if let Some(ref budget) = self.config.header_budget {
+            let version = RequestConfig::<RequestHttpVersionPref>::get(req.extensions())
+                .copied()
+                .or(self.config.http_version);
+            if let Err(error) = budget.enforce(&mut req, version) {
+                return CorePending::Error { error: Some(error) };
+            }
+        }
+
+        CorePending::Request {
+            dispatch: Some(DispatchInfo::new(req.extensions())),
+            fut: self.client.call(req),
+        }
+    }
+
+    /// Returns the proxy set on `req` if it intercepts the request, which the connector uses
+    /// instead of the proxies of the client.
+    fn request_proxy(&self, req: &Request<Body>) -> Option<ProxyMatcher> {
+        RequestConfig::<RequestProxyMatcher>::get(req.extensions())
+            .filter(|proxy| proxy.intercept(req.uri()).is_some())
+            .cloned()
+    }
+
+    /// Returns the proxy `req` is sent through, unless it bypasses proxies.
+    fn proxy(&self, req: &Request<Body>) -> Option<Intercepted> {
+        if bypasses_proxies(req) {
+            return None;
+        }
+
+        let uri = req.uri();
+        match self.request_proxy(req) {
+            Some(proxy) => proxy.intercept(uri),
+            None => self
+                .config
+                .proxies
+                .iter()
+                .find_map(|proxy| proxy.intercept(uri)),
+        }
+    }
+
+    /// Returns the proxy a plain HTTP request is forwarded to.
+    fn forward_proxy(&self, req: &Request<Body>) -> Option<Intercepted> {
+        if req.uri().scheme() != Some(&Scheme::HTTP) {
+            return None;
+        }
+
+        let proxy = self.proxy(req)?;
+        // `https` proxies tunnel plain HTTP requests too
+        proxy.forwards_http().then_some(proxy)
+    }
+
+    /// Sends a request forwarded to `proxy`, and sends it again once if the proxy answers with
+    /// an authentication challenge.
+    async fn send_with_proxy_auth(
+        mut self,
+        mut req: Request<Body>,
+        proxy: Uri,
+        proxy_auth: Arc<ProxyAuth>,
+    ) -> Result<Response<Incoming>, BoxError> {
+        if let Some(auth) = proxy_auth.cached(&proxy) {
+            req.headers_mut().insert(PROXY_AUTHORIZATION, auth);
+        }
+
+        let retry = clone_request(&req);
+        let res = self.send(req).await?;
+        if res.status() != StatusCode::PROXY_AUTHENTICATION_REQUIRED {
+            return Ok(res);
+        }
+
+        let Some(mut retry) = retry else {
+            return Ok(res);
+        };
+
+        let sent = retry.headers().get(PROXY_AUTHORIZATION).cloned();
+        let challenges = res.headers().get_all(PROXY_AUTHENTICATE);
+        match proxy_auth
+            .challenge(&proxy, challenges, sent.as_ref())
+            .await
+        {
+            Some(auth) => {
+                drop(res);
+                retry.headers_mut().insert(PROXY_AUTHORIZATION, auth);
+                self.send(retry).await
+            }
+            None => Ok(res),
+        }
+    }
+
+    /// Sends a request forwarded to a proxy, answering a `Digest` challenge of the proxy with
+    /// its credentials, and once more if the proxy found the nonce stale.
+    async fn send_with_proxy_digest(
+        mut self,
+        mut req: Request<Body>,
+        mut digest: DigestAuth,
+    ) -> Result<Response<Incoming>, BoxError> {
+        let method = req.method().clone();
+        let uri = req.uri().to_string();
+
+        loop {
+            let retry = clone_request(&req);
+            let res = self.send(req).await?;
+            if res.status() != StatusCode::PROXY_AUTHENTICATION_REQUIRED {
+                return Ok(res);
+            }
+
+            let Some(mut retry) = retry else {
+                return Ok(res);
+            };
+
+            let challenges = res.headers().get_all(PROXY_AUTHENTICATE);
+            let Some(auth) = digest.answer(challenges, method.as_str(), &uri) else {
+                return Ok(res);
+            };
+            drop(res);
+            retry.headers_mut().insert(PROXY_AUTHORIZATION, auth);
+            req = retry;
+        }
+    }
+
+    /// Sends a request forwarded to a proxy with the credentials of its provider, and sends it
+    /// again once with refreshed credentials if the proxy rejects them.
+    async fn send_with_proxy_credentials(
+        mut self,
+        mut req: Request<Body>,
+        credentials: CredentialsCache,
+    ) -> Result<Response<Incoming>, BoxError> {
+        let dst = req.uri().clone();
+        if let Some(auth) = credentials.header(&dst).await {
+            req.headers_mut().insert(PROXY_AUTHORIZATION, auth);
+        }
+
+        let retry = clone_request(&req);
+        let res = self.send(req).await?;
+        if res.status() != StatusCode::PROXY_AUTHENTICATION_REQUIRED {
+            return Ok(res);
+        }
+
+        let Some(mut retry) = retry else {
+            return Ok(res);
+        };
+
+        let sent = retry.headers().get(PROXY_AUTHORIZATION).cloned();
+        match credentials.refresh(&dst, sent.as_ref()).await {
+            Some(auth) => {
+                drop(res);
+                retry.headers_mut().insert(PROXY_AUTHORIZATION, auth);
+                self.send(retry).await
+            }
+            None => Ok(res),
+        }
+    }
+}
+
+/// Returns whether `req` connects directly, bypassing every proxy.
+fn bypasses_proxies(req: &Request<Body>) -> bool {
+    RequestConfig::<RequestNoProxy>::get(req.extensions()) == Some(&true)
+}
+
+fn clone_request(req: &Request<Body>) -> Option<Request<Body>> {
+    let mut new_req = Request::new(req.body().try_clone()?);
+    *new_req.method_mut() = req.method().clone();
+    *new_req.uri_mut() = req.uri().clone();
+    *new_req.version_mut() = req.version();
+    *new_req.headers_mut() = req.headers().clone();
+    *new_req.extensions_mut() = req.extensions().clone();
+    Some(new_req)
 }
 
 impl Service<Request<Body>> for ClientService {
@@ -110,34 +463,56 @@ impl Service<Request<Body>> for ClientService {
             return CorePending::Error { error: Some(error) };
         }
 
-        // Only skip setting default headers if skip_default_headers is explicitly Some(true).
-        let skip = self
-            .config
-            .skip_default_headers
-            .fetch(req.extensions())
-            .copied()
-            == Some(true);
+        // Without the `tls` feature, `https` URLs are rejected before connecting
+        #[cfg(not(feature = "tls"))]
+        if scheme == Some(&Scheme::HTTPS) {
+            let error = match IntoUrlSealed::into_url(req.uri().to_string()) {
+                Ok(url) => Error::builder(TlsDisabled).with_url(url),
+                Err(err) => Error::builder(err),
+            };
 
-        if !skip {
-            let headers = req.headers_mut();
-            // Insert default headers if they are not already present in the request.
-            for name in self.config.default_headers.keys() {
-                if !headers.contains_key(name) {
-                    for value in self.config.default_headers.get_all(name) {
-                        headers.append(name, value.clone());
-                    }
-                }
-            }
+            return CorePending::Error { error: Some(error) };
         }
 
+        self.apply_default_headers(&mut req);
+
         // Apply original headers if they are set in the request extensions.
         self.config.original_headers.store(req.extensions_mut());
 
+        // Route the request through the proxy of the selector, if it chooses one.
+        self.apply_proxy_selector(&mut req);
+
+        // Choose the fastest proxy of a plain HTTP request, if proxies are chosen by latency.
+        self.apply_proxy_strategy(&mut req);
+
+        // Evaluate the auth template of the proxy of the request, if it has one.
+        self.apply_proxy_auth_template(&mut req);
+
         // Apply proxy headers if the request is routed through a proxy.
         self.apply_proxy_headers(&mut req);
 
-        CorePending::Request {
-            fut: self.client.call(req),
+        // Authenticate with the proxy a request is forwarded to, and answer its challenges.
+        if let Some(proxy) = self.forward_proxy(&req) {
+            if let Some(credentials) = proxy.credentials() {
+                let fut = self
+                    .clone()
+                    .send_with_proxy_credentials(req, credentials.clone());
+                return CorePending::ProxyAuth { fut: Box::pin(fut) };
+            }
+
+            if let Some(ref proxy_auth) = self.config.proxy_auth {
+                let fut =
+                    self.clone()
+                        .send_with_proxy_auth(req, proxy.uri().clone(), proxy_auth.clone());
+                return CorePending::ProxyAuth { fut: Box::pin(fut) };
+            }
+
+            if let Some(digest) = proxy.digest_auth() {
+                let fut = self.clone().send_with_proxy_digest(req, digest);
+                return CorePending::ProxyAuth { fut: Box::pin(fut) };
+            }
         }
+
+        self.send(req)
     }
 }
@@ -7,8 +7,11 @@ use tower::{
 use super::{Body, service::ClientService};
 use crate::{
     client::middleware::{
+        coop::{Coop, CoopBody},
+        headers_hook::HeadersHookService,
         redirect::FollowRedirect,
         retry::Http2RetryPolicy,
+        think_time::ThinkTimeService,
         timeout::{ResponseBodyTimeout, Timeout, TimeoutBody},
     },
     core::body::Incoming,
@@ -44,7 +47,13 @@ type MaybeDecompression<T> = crate::client::middleware::decoder::Decompression<T
     feature = "brotli",
     feature = "deflate"
 ))]
-pub type ResponseBody = TimeoutBody<tower_http::decompression::DecompressionBody<Incoming>>;
+pub type ResponseBody = CoopBody<
+    TimeoutBody<
+        crate::client::middleware::decoder::SalvageBody<
+            crate::client::middleware::decoder::DrainBody<Incoming>,
+        >,
+    >,
+>;
 
 #[cfg(not(any(
     feature = "gzip",
@@ -52,17 +61,26 @@ pub type ResponseBody = TimeoutBody<tower_http::decompression::DecompressionBody
     feature = "brotli",
     feature = "deflate"
 )))]
-pub type ResponseBody = TimeoutBody<Incoming>;
+pub type ResponseBody = CoopBody<TimeoutBody<Incoming>>;
+
+#[cfg(not(feature = "vcr"))]
+type MaybeVcr<T> = T;
+
+#[cfg(feature = "vcr")]
+type MaybeVcr<T> = crate::client::middleware::vcr::VcrService<T>;
 
 type RedirectLayer = FollowRedirect<
-    MaybeCookieLayer<ResponseBodyTimeout<MaybeDecompression<ClientService>>>,
+    HeadersHookService<
+        MaybeCookieLayer<Coop<ResponseBodyTimeout<MaybeDecompression<MaybeVcr<ClientService>>>>>,
+    >,
     RedirectPolicy,
 >;
 
 pub type CoreResponseFuture = crate::core::client::ResponseFuture;
 
-pub type GenericClientService =
-    MapErr<Timeout<Retry<Http2RetryPolicy, RedirectLayer>>, fn(BoxError) -> BoxError>;
+pub type GenericClientService = ThinkTimeService<
+    MapErr<Timeout<Retry<Http2RetryPolicy, RedirectLayer>>, fn(BoxError) -> BoxError>,
+>;
 
 pub type BoxedClientService =
     BoxCloneSyncService<HttpRequest<Body>, HttpResponse<ResponseBody>, BoxError>;
@@ -0,0 +1,331 @@
+//! User-Agent client hints consistent with a user agent string.
+//!
+//! Chromium-based browsers send `sec-ch-ua`, `sec-ch-ua-mobile` and `sec-ch-ua-platform`
+//! alongside their `User-Agent`. When the user agent of an emulation is changed, its hints
+//! must follow, or the mismatch gives the client away. [`from_user_agent`] derives them the
+//! way Chromium does, including the GREASE brand of the major version.
+//!
+//! # Example
+//!
+//! ```rust
+//! use wreq::client_hints;
+//!
+//! let headers = client_hints::from_user_agent(
+//!     "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+//!      Chrome/131.0.0.0 Safari/537.36",
+//! )?;
+//! assert_eq!(
+//!     headers["sec-ch-ua"],
+//!     r#""Google Chrome";v="131", "Chromium";v="131", "Not_A Brand";v="24""#
+//! );
+//! assert_eq!(headers["sec-ch-ua-platform"], r#""Windows""#);
+//!
+//! // replaces the user agent and the hints of an emulation, keeping its other headers
+//! let client = wreq::Client::builder().default_headers(headers).build()?;
+//! # let _ = client;
+//! # Ok::<(), wreq::Error>(())
+//! ```
+
+use http::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+
+use crate::Error;
+
+static SEC_CH_UA: HeaderName = HeaderName::from_static("sec-ch-ua");
+static SEC_CH_UA_MOBILE: HeaderName = HeaderName::from_static("sec-ch-ua-mobile");
+static SEC_CH_UA_PLATFORM: HeaderName = HeaderName::from_static("sec-ch-ua-platform");
+
+/// The first Chromium version whose GREASE brand is derived from its major version.
+const MIN_MAJOR_VERSION: u32 = 105;
+
+/// Returns the `User-Agent` header `user_agent`, with the `sec-ch-ua`, `sec-ch-ua-mobile` and
+/// `sec-ch-ua-platform` hints a browser sending it would send.
+///
+/// Chrome, Edge and Opera are recognized on Windows, macOS, Linux, ChromeOS and Android.
+///
+/// # Errors
+///
+/// Fails rather than guessing if the user agent cannot be classified: browsers that do not send
+/// client hints such as Firefox, Safari or Chrome on iOS, unknown Chromium-based browsers or
+/// platforms, and Chromium versions before 105.
+pub fn from_user_agent(user_agent: &str) -> crate::Result<HeaderMap> {
+    let hints = ClientHints::parse(user_agent).map_err(|reason| {
+        Error::builder(format!(
+            "cannot derive client hints from user agent `{user_agent}`: {reason}"
+        ))
+    })?;
+
+    let mut headers = HeaderMap::with_capacity(4);
+    headers.insert(
+        USER_AGENT,
+        HeaderValue::from_str(user_agent).map_err(Error::builder)?,
+    );
+    headers.insert(
+        SEC_CH_UA.clone(),
+        HeaderValue::from_str(&hints.brands()).map_err(Error::builder)?,
+    );
+    headers.insert(
+        SEC_CH_UA_MOBILE.clone(),
+        HeaderValue::from_static(if hints.mobile { "?1" } else { "?0" }),
+    );
+    headers.insert(
+        SEC_CH_UA_PLATFORM.clone(),
+        HeaderValue::from_str(&format!("\"{}\"", hints.platform)).map_err(Error::builder)?,
+    );
+    Ok(headers)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct ClientHints {
+    brand: &'static str,
+    brand_major: u32,
+    chromium_major: u32,
+    platform: &'static str,
+    mobile: bool,
+}
+
+impl ClientHints {
+    fn parse(user_agent: &str) -> Result<ClientHints, String> {
+        let (comment, products) = split_comments(user_agent);
+
+        let mut chromium_major = None;
+        let mut brand = None;
+        let mut mobile = false;
+        for token in products.split_whitespace() {
+            let (name, version) = match token.split_once('/') {
+                Some((name, version)) => (name, Some(version)),
+                None => (token, None),
+            };
+            match (name, version) {
+                ("Mozilla" | "AppleWebKit" | "Safari", Some(_)) => {}
+                ("Mobile", None) => mobile = true,
+                ("Chrome", Some(version)) => chromium_major = Some(major(version)?),
+                ("Edg" | "EdgA", Some(version)) => {
+                    set_brand(&mut brand, "Microsoft Edge", version)?
+                }
+                ("OPR", Some(version)) => set_brand(&mut brand, "Opera", version)?,
+                ("CriOS", _) => return Err("Chrome on iOS does not send client hints".into()),
+                _ => return Err(format!("unknown product `{token}`")),
+            }
+        }
+
+        let chromium_major =
+            chromium_major.ok_or("not a Chromium-based browser, it sends no client hints")?;
+        if chromium_major < MIN_MAJOR_VERSION {
+            return Err(format!(
+                "Chromium versions before {MIN_MAJOR_VERSION} are not supported"
+            ));
+        }
+        let (brand, brand_major) = brand.unwrap_or(("Google Chrome", chromium_major));
+
+        Ok(ClientHints {
+            brand,
+            brand_major,
+            chromium_major,
+            platform: platform(comment)?,
+            mobile,
+        })
+    }
+
+    /// Returns the brand list of `sec-ch-ua`, with the GREASE brand and the order Chromium
+    /// derives from the major version.
+    fn brands(&self) -> String {
+        const GREASE_CHARS: [char; 11] = [' ', '(', ':', '-', '.', '/', ')', ';', '=', '?', '_'];
+        const GREASE_VERSIONS: [&str; 3] = ["8", "99", "24"];
+        const ORDERS: [[usize; 3]; 6] = [
+            [0, 1, 2],
+            [0, 2, 1],
+            [1, 0, 2],
+            [1, 2, 0],
+            [2, 0, 1],
+            [2, 1, 0],
+        ];
+
+        let seed = self.chromium_major as usize;
+        let grease = format!(
+            "\"Not{}A{}Brand\";v=\"{}\"",
+            GREASE_CHARS[seed % GREASE_CHARS.len()],
+            GREASE_CHARS[(seed + 1) % GREASE_CHARS.len()],
+            GREASE_VERSIONS[seed % GREASE_VERSIONS.len()],
+        );
+        let chromium = format!("\"Chromium\";v=\"{}\"", self.chromium_major);
+        let brand = format!("\"{}\";v=\"{}\"", self.brand, self.brand_major);
+
+        let order = ORDERS[seed % ORDERS.len()];
+        let mut brands = [""; 3];
+        brands[order[0]] = grease.as_str();
+        brands[order[1]] = chromium.as_str();
+        brands[order[2]] = brand.as_str();
+        brands.join(", ")
+    }
+}
+
+/// Splits `user_agent` into its first comment, holding the platform, and its products.
+fn split_comments(user_agent: &str) -> (&str, String) {
+    let mut comment = None;
+    let mut products = String::with_capacity(user_agent.len());
+    let mut rest = user_agent;
+    while let Some(start) = rest.find('(') {
+        products.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find(')')
+            .map_or(rest.len(), |end| start + end);
+        comment.get_or_insert(&rest[start + 1..end]);
+        rest = rest.get(end + 1..).unwrap_or_default();
+        products.push(' ');
+    }
+    products.push_str(rest);
+    (comment.unwrap_or_default(), products)
+}
+
+fn major(version: &str) -> Result<u32, String> {
+    version
+        .split('.')
+        .next()
+        .and_then(|major| major.parse().ok())
+        .ok_or_else(|| format!("invalid version `{version}`"))
+}
+
+fn set_brand(
+    brand: &mut Option<(&'static str, u32)>,
+    name: &'static str,
+    version: &str,
+) -> Result<(), String> {
+    if brand.is_some() {
+        return Err("several browser brands".into());
+    }
+    *brand = Some((name, major(version)?));
+    Ok(())
+}
+
+fn platform(comment: &str) -> Result<&'static str, String> {
+    let platform = if comment.contains("Android") {
+        "Android"
+    } else if comment.starts_with("Windows") {
+        "Windows"
+    } else if comment.starts_with("Macintosh") {
+        "macOS"
+    } else if comment.contains("CrOS") {
+        "Chrome OS"
+    } else if comment.starts_with("X11") && comment.contains("Linux") {
+        "Linux"
+    } else {
+        return Err(format!("unknown platform `{comment}`"));
+    };
+    Ok(platform)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hints(user_agent: &str) -> (String, String, String) {
+        let headers = from_user_agent(user_agent).unwrap();
+        assert_eq!(headers[USER_AGENT], user_agent);
+        let value = |name: &HeaderName| headers[name].to_str().unwrap().to_owned();
+        (
+            value(&SEC_CH_UA),
+            value(&SEC_CH_UA_MOBILE),
+            value(&SEC_CH_UA_PLATFORM),
+        )
+    }
+
+    #[test]
+    fn matches_the_hints_of_browsers() {
+        let cases = [
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+                 Chrome/131.0.0.0 Safari/537.36",
+                r#""Google Chrome";v="131", "Chromium";v="131", "Not_A Brand";v="24""#,
+                "?0",
+                r#""Windows""#,
+            ),
+            (
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like \
+                 Gecko) Chrome/124.0.0.0 Safari/537.36",
+                r#""Chromium";v="124", "Google Chrome";v="124", "Not-A.Brand";v="99""#,
+                "?0",
+                r#""macOS""#,
+            ),
+            (
+                "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) \
+                 Chrome/120.0.0.0 Safari/537.36",
+                r#""Not_A Brand";v="8", "Chromium";v="120", "Google Chrome";v="120""#,
+                "?0",
+                r#""Linux""#,
+            ),
+            (
+                "Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 (KHTML, like Gecko) \
+                 Chrome/110.0.0.0 Mobile Safari/537.36",
+                r#""Chromium";v="110", "Not A(Brand";v="24", "Google Chrome";v="110""#,
+                "?1",
+                r#""Android""#,
+            ),
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+                 Chrome/131.0.0.0 Safari/537.36 Edg/131.0.0.0",
+                r#""Microsoft Edge";v="131", "Chromium";v="131", "Not_A Brand";v="24""#,
+                "?0",
+                r#""Windows""#,
+            ),
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+                 Chrome/130.0.0.0 Safari/537.36 OPR/115.0.0.0",
+                r#""Chromium";v="130", "Opera";v="115", "Not?A_Brand";v="99""#,
+                "?0",
+                r#""Windows""#,
+            ),
+            (
+                "Mozilla/5.0 (X11; CrOS x86_64 14541.0.0) AppleWebKit/537.36 (KHTML, like Gecko) \
+                 Chrome/109.0.0.0 Safari/537.36",
+                r#""Not_A Brand";v="99", "Google Chrome";v="109", "Chromium";v="109""#,
+                "?0",
+                r#""Chrome OS""#,
+            ),
+        ];
+
+        for (user_agent, brands, mobile, platform) in cases {
+            assert_eq!(
+                hints(user_agent),
+                (brands.to_owned(), mobile.to_owned(), platform.to_owned()),
+                "{user_agent}"
+            );
+        }
+    }
+
+    #[test]
+    fn refuses_user_agents_it_cannot_classify() {
+        let cases = [
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:133.0) Gecko/20100101 Firefox/133.0",
+                "unknown product `Gecko/20100101`",
+            ),
+            (
+                "Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X) AppleWebKit/605.1.15 \
+                 (KHTML, like Gecko) CriOS/131.0.6778.73 Mobile/15E148 Safari/604.1",
+                "Chrome on iOS",
+            ),
+            (
+                "Mozilla/5.0 (Linux; Android 14) AppleWebKit/537.36 (KHTML, like Gecko) \
+                 SamsungBrowser/26.0 Chrome/122.0.0.0 Mobile Safari/537.36",
+                "unknown product `SamsungBrowser/26.0`",
+            ),
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+                 Chrome/100.0.4896.75 Safari/537.36",
+                "before 105",
+            ),
+            (
+                "Mozilla/5.0 (PlayStation; PlayStation 5/2.26) AppleWebKit/605.1.15 (KHTML, like \
+                 Gecko) Chrome/120.0.0.0 Safari/605.1.15",
+                "unknown platform",
+            ),
+            ("curl/8.5.0", "unknown product `curl/8.5.0`"),
+        ];
+
+        for (user_agent, reason) in cases {
+            let err = from_user_agent(user_agent).unwrap_err();
+            assert!(err.is_builder());
+            assert!(err.to_string().contains(reason), "{err}");
+        }
+    }
+}
@@ -0,0 +1,431 @@
+//! Verifying the digest of response bodies as they are read.
+
+use std::{
+    fmt,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, ready},
+};
+
+#[cfg(feature = "tls")]
+use boring2::hash::{Hasher as BoringHasher, MessageDigest};
+use bytes::Bytes;
+use http::{Response, StatusCode, header::CONTENT_RANGE};
+use http_body::{Body, Frame, SizeHint};
+use pin_project_lite::pin_project;
+#[cfg(not(feature = "tls"))]
+use sha2::Digest;
+
+use crate::{Error, error::BoxError, sync::Mutex};
+
+/// A hash algorithm a response body can be verified with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DigestAlgorithm {
+    /// SHA-256, with 32 byte digests.
+    Sha256,
+    /// SHA-512, with 64 byte digests.
+    Sha512,
+    /// BLAKE3, with 32 byte digests.
+    #[cfg(feature = "blake3")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "blake3")))]
+    Blake3,
+}
+
+impl DigestAlgorithm {
+    /// Returns the length of the digests of this algorithm, in bytes.
+    pub const fn output_len(self) -> usize {
+        match self {
+            DigestAlgorithm::Sha256 => 32,
+            DigestAlgorithm::Sha512 => 64,
+            #[cfg(feature = "blake3")]
+            DigestAlgorithm::Blake3 => 32,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            DigestAlgorithm::Sha256 => "SHA-256",
+            DigestAlgorithm::Sha512 => "SHA-512",
+            #[cfg(feature = "blake3")]
+            DigestAlgorithm::Blake3 => "BLAKE3",
+        }
+    }
+}
+
+enum Hasher {
+    #[cfg(feature = "tls")]
+    Boring(BoringHasher),
+    #[cfg(not(feature = "tls"))]
+    Sha256(sha2::Sha256),
+    #[cfg(not(feature = "tls"))]
+    Sha512(sha2::Sha512),
+    #[cfg(feature = "blake3")]
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    #[cfg(feature = "tls")]
+    fn new(algorithm: DigestAlgorithm) -> Hasher {
+        let md = match algorithm {
+            DigestAlgorithm::Sha256 => MessageDigest::sha256(),
+            DigestAlgorithm::Sha512 => MessageDigest::sha512(),
+            #[cfg(feature = "blake3")]
+            DigestAlgorithm::Blake3 => return Hasher::Blake3(Box::default()),
+        };
+        Hasher::Boring(BoringHasher::new(md).expect("digest is supported"))
+    }
+
+    #[cfg(not(feature = "tls"))]
+    fn new(algorithm: DigestAlgorithm) -> Hasher {
+        match algorithm {
+            DigestAlgorithm::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
+            DigestAlgorithm::Sha512 => Hasher::Sha512(sha2::Sha512::new()),
+            #[cfg(feature = "blake3")]
+            DigestAlgorithm::Blake3 => Hasher::Blake3(Box::default()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) -> Result<(), BoxError> {
+        match self {
+            #[cfg(feature = "tls")]
+            Hasher::Boring(hasher) => hasher.update(data).map_err(Into::into),
+            #[cfg(not(feature = "tls"))]
+            Hasher::Sha256(hasher) => {
+                hasher.update(data);
+                Ok(())
+            }
+            #[cfg(not(feature = "tls"))]
+            Hasher::Sha512(hasher) => {
+                hasher.update(data);
+                Ok(())
+            }
+            #[cfg(feature = "blake3")]
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+                Ok(())
+            }
+        }
+    }
+
+    fn finish(&mut self) -> Result<Bytes, BoxError> {
+        match self {
+            #[cfg(feature = "tls")]
+            Hasher::Boring(hasher) => Ok(Bytes::copy_from_slice(&hasher.finish()?)),
+            #[cfg(not(feature = "tls"))]
+            Hasher::Sha256(hasher) => Ok(Bytes::copy_from_slice(&hasher.finalize_reset())),
+            #[cfg(not(feature = "tls"))]
+            Hasher::Sha512(hasher) => Ok(Bytes::copy_from_slice(&hasher.finalize_reset())),
+            #[cfg(feature = "blake3")]
+            Hasher::Blake3(hasher) => Ok(Bytes::copy_from_slice(hasher.finalize().as_bytes())),
+        }
+    }
+}
+
+struct State {
+    hasher: Hasher,
+    hashed: u64,
+}
+
+/// The digest a response body is expected to have, see
+/// [`RequestBuilder::expected_digest`](crate::RequestBuilder::expected_digest).
+///
+/// Clones share the hashing state, so that a download resumed with a range request can
+/// continue to hash where the interrupted one stopped. A cloned request, such as one of
+/// [`RequestBuilder::fan_out`](crate::RequestBuilder::fan_out), hashes its body on its own.
+#[derive(Clone)]
+pub struct ExpectedDigest {
+    algorithm: DigestAlgorithm,
+    expected: Bytes,
+    state: Arc<Mutex<State>>,
+}
+
+impl ExpectedDigest {
+    /// Expects the body to have the digest `expected` with `algorithm`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `expected` is not the
+    /// [`output_len`](DigestAlgorithm::output_len) of `algorithm`.
+    pub fn new<D>(algorithm: DigestAlgorithm, expected: D) -> ExpectedDigest
+    where
+        D: AsRef<[u8]>,
+    {
+        let expected = Bytes::copy_from_slice(expected.as_ref());
+        assert_eq!(
+            expected.len(),
+            algorithm.output_len(),
+            "{} digests are {} bytes long",
+            algorithm.name(),
+            algorithm.output_len()
+        );
+
+        ExpectedDigest {
+            algorithm,
+            expected,
+            state: Arc::new(Mutex::new(State {
+                hasher: Hasher::new(algorithm),
+                hashed: 0,
+            })),
+        }
+    }
+
+    /// Returns the same expected digest, with hashing state of its own.
+    pub(crate) fn fork(&self) -> ExpectedDigest {
+        ExpectedDigest {
+            algorithm: self.algorithm,
+            expected: self.expected.clone(),
+            state: Arc::new(Mutex::new(State {
+                hasher: Hasher::new(self.algorithm),
+                hashed: 0,
+            })),
+        }
+    }
+
+    /// Returns the algorithm of the digest.
+    pub fn algorithm(&self) -> DigestAlgorithm {
+        self.algorithm
+    }
+
+    /// Returns the number of bytes hashed so far.
+    ///
+    /// After a body ended early, this is the offset to resume the download from. It goes back
+    /// to zero once a body was read to its end and verified.
+    pub fn hashed_bytes(&self) -> u64 {
+        self.state.lock().hashed
+    }
+
+    /// Prepares hashing the body of `res`.
+    ///
+    /// A partial response continues the hashed data, and must start where it stopped. Any
+    /// other response carries the whole body, and restarts hashing.
+    pub(crate) fn start<B>(&self, res: &Response<B>) -> crate::Result<()> {
+        let mut state = self.state.lock();
+        if state.hashed == 0 {
+            return Ok(());
+        }
+
+        if res.status() != StatusCode::PARTIAL_CONTENT {
+            *state = State {
+                hasher: Hasher::new(self.algorithm),
+                hashed: 0,
+            };
+            return Ok(());
+        }
+
+        let start = res
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("bytes "))
+            .and_then(|range| range.split_once('-'))
+            .and_then(|(start, _)| start.parse::<u64>().ok());
+        match start {
+            Some(start) if start == state.hashed => Ok(()),
+            _ => Err(Error::body(format!(
+                "partial response does not continue the {} bytes hashed so far",
+                state.hashed
+            ))),
+        }
+    }
+
+    /// Discards the hashed data, for a body downloaded again from its start.
+    pub(crate) fn restart(&self) {
+        *self.state.lock() = State {
+            hasher: Hasher::new(self.algorithm),
+            hashed: 0,
+        };
+    }
+
+    pub(crate) fn update(&self, data: &[u8]) -> Result<(), BoxError> {
+        let mut state = self.state.lock();
+        state.hasher.update(data)?;
+        state.hashed += data.len() as u64;
+        Ok(())
+    }
+
+    /// Compares the digest of the hashed data with the expected one, and restarts hashing.
+    pub(crate) fn verify(&self) -> Result<(), BoxError> {
+        let mut state = self.state.lock();
+        let actual = state.hasher.finish();
+        *state = State {
+            hasher: Hasher::new(self.algorithm),
+            hashed: 0,
+        };
+
+        let actual = actual?;
+        if actual == self.expected {
+            return Ok(());
+        }
+        Err(Error::body(DigestMismatch {
+            algorithm: self.algorithm,
+            expected: self.expected.clone(),
+            actual,
+        })
+        .into())
+    }
+}
+
+impl fmt::Debug for ExpectedDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExpectedDigest")
+            .field("algorithm", &self.algorithm)
+            .field("expected", &Hex(&self.expected))
+            .finish()
+    }
+}
+
+/// The cause of an error when a response body does not have its expected digest.
+///
+/// The whole body was read by then. See [`Error::digest_mismatch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DigestMismatch {
+    algorithm: DigestAlgorithm,
+    expected: Bytes,
+    actual: Bytes,
+}
+
+impl DigestMismatch {
+    /// Returns the algorithm of the digests.
+    pub fn algorithm(&self) -> DigestAlgorithm {
+        self.algorithm
+    }
+
+    /// Returns the expected digest.
+    pub fn expected(&self) -> &[u8] {
+        &self.expected
+    }
+
+    /// Returns the digest of the body.
+    pub fn actual(&self) -> &[u8] {
+        &self.actual
+    }
+}
+
+impl fmt::Display for DigestMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} digest mismatch: expected {}, got {}",
+            self.algorithm.name(),
+            Hex(&self.expected),
+            Hex(&self.actual)
+        )
+    }
+}
+
+impl std::error::Error for DigestMismatch {}
+
+struct Hex<'a>(&'a [u8]);
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{b:02x}"))
+    }
+}
+
+impl fmt::Debug for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+pin_project! {
+    /// A body hashing its data, and failing at its end if the digest is not the expected one.
+    pub(crate) struct DigestBody<B> {
+        #[pin]
+        body: B,
+        digest: ExpectedDigest,
+        done: bool,
+    }
+}
+
+impl<B> DigestBody<B> {
+    pub(crate) fn new(body: B, digest: ExpectedDigest) -> Self {
+        DigestBody {
+            body,
+            digest,
+            done: false,
+        }
+    }
+}
+
+impl<B> Body for DigestBody<B>
+where
+    B: Body<Data = Bytes, Error = BoxError>,
+{
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        if *this.done {
+            return Poll::Ready(None);
+        }
+
+        match ready!(this.body.poll_frame(cx)) {
+            Some(Ok(frame)) => {
+                if let Some(data) = frame.data_ref() {
+                    this.digest.update(data)?;
+                }
+                Poll::Ready(Some(Ok(frame)))
+            }
+            Some(Err(err)) => Poll::Ready(Some(Err(err))),
+            None => {
+                // The verification is the last item of the body, so it is never skipped by
+                // readers stopping at `is_end_stream`.
+                *this.done = true;
+                Poll::Ready(this.digest.verify().err().map(Err))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.done
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    fn unhex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn verifies_across_updates() {
+        let digest = ExpectedDigest::new(DigestAlgorithm::Sha256, unhex(HELLO_SHA256));
+        digest.update(b"he").unwrap();
+        digest.update(b"llo").unwrap();
+        assert_eq!(digest.hashed_bytes(), 5);
+        digest.verify().unwrap();
+        assert_eq!(digest.hashed_bytes(), 0);
+
+        digest.update(b"hellO").unwrap();
+        let err = digest.verify().unwrap_err();
+        let err = err.downcast_ref::<Error>().unwrap();
+        let mismatch = err.digest_mismatch().unwrap();
+        assert_eq!(mismatch.expected(), unhex(HELLO_SHA256));
+        assert_ne!(mismatch.actual(), mismatch.expected());
+        assert!(err.to_string().contains(HELLO_SHA256), "{err}");
+    }
+
+    #[test]
+    #[should_panic = "SHA-512 digests are 64 bytes long"]
+    fn rejects_digest_of_wrong_length() {
+        ExpectedDigest::new(DigestAlgorithm::Sha512, [0; 32]);
+    }
+}
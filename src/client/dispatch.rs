@@ -0,0 +1,75 @@
+//! Extensions describing how a request is dispatched.
+//!
+//! The client attaches these to every request it sends, so that layers added with
+//! [`ClientBuilder::layer`](crate::ClientBuilder::layer) can distinguish the original
+//! request from retries and redirects. The values of the request that produced a response
+//! are also attached to the response.
+
+use http::Extensions;
+
+use crate::{EmulationSelection, TrimmedCookies, Url};
+
+/// Request and response [`http::Extensions`] value holding the attempt number of a request.
+///
+/// The first attempt is `1`. Every retry of the request, such as after the server refused an
+/// HTTP/2 stream or after a pooled connection closed before the request was sent, increments
+/// it. A retry starts over from the original request, so it also
+/// resets the [`RedirectHop`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RequestAttempt(pub u32);
+
+/// Request and response [`http::Extensions`] value holding the redirect hop of a request.
+///
+/// The original request is hop `0`, and every redirect followed increments it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RedirectHop(pub u32);
+
+/// Request and response [`http::Extensions`] value holding the URL the request was made for,
+/// before any redirect was followed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OriginalUrl(pub Url);
+
+/// The dispatch extensions of a request, carried over to its response.
+#[derive(Default)]
+pub(crate) struct DispatchInfo {
+    attempt: Option<RequestAttempt>,
+    redirect_hop: Option<RedirectHop>,
+    original_url: Option<OriginalUrl>,
+    emulation: Option<EmulationSelection>,
+    trimmed_cookies: Option<TrimmedCookies>,
+}
+
+impl DispatchInfo {
+    /// Captures the dispatch extensions of a request.
+    pub(crate) fn new(extensions: &Extensions) -> Self {
+        DispatchInfo {
+            attempt: extensions.get().copied(),
+            redirect_hop: extensions.get().copied(),
+            original_url: extensions.get().cloned(),
+            emulation: extensions.get().copied(),
+            trimmed_cookies: extensions.get().cloned(),
+        }
+    }
+
+    /// Attaches the captured extensions to a response.
+    pub(crate) fn apply(self, extensions: &mut Extensions) {
+        // a request retried by the connection pool already reports its later attempt
+        if let Some(attempt) = self.attempt {
+            if extensions.get::<RequestAttempt>().is_none() {
+                extensions.insert(attempt);
+            }
+        }
+        if let Some(redirect_hop) = self.redirect_hop {
+            extensions.insert(redirect_hop);
+        }
+        if let Some(original_url) = self.original_url {
+            extensions.insert(original_url);
+        }
+        if let Some(emulation) = self.emulation {
+            extensions.insert(emulation);
+        }
+        if let Some(trimmed_cookies) = self.trimmed_cookies {
+            extensions.insert(trimmed_cookies);
+        }
+    }
+}
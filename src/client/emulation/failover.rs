@@ -0,0 +1,339 @@
+//! Failing over to fallback emulation profiles for the origins that block one.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+#[cfg(feature = "tls")]
+use std::{error::Error as StdError, io};
+
+#[cfg(feature = "tls")]
+use boring2::ssl::ErrorCode;
+use http::{HeaderMap, StatusCode};
+
+use super::{EmulationProvider, EmulationSelection, rotating::apply_profile};
+use crate::{Error, Request, sync::Mutex};
+
+/// The configuration of
+/// [`ClientBuilder::profile_failover`](crate::ClientBuilder::profile_failover).
+///
+/// # Example
+///
+/// ```
+/// use std::{sync::Arc, time::Duration};
+///
+/// use wreq::{EmulationProvider, FailoverConfig, FailureContext};
+///
+/// # fn profiles() -> (EmulationProvider, EmulationProvider) { Default::default() }
+/// let (primary, fallback) = profiles();
+///
+/// let client = wreq::Client::builder()
+///     .profile_failover(FailoverConfig {
+///         profiles: vec![primary, fallback],
+///         trigger: Arc::new(|ctx: &FailureContext<'_>| ctx.is_blocked()),
+///         cooldown: Duration::from_secs(600),
+///     })
+///     .build()?;
+/// # let _ = client;
+/// # Ok::<(), wreq::Error>(())
+/// ```
+pub struct FailoverConfig {
+    /// The profiles requests are sent with, the primary first, then the fallbacks in the order
+    /// they are failed over to.
+    pub profiles: Vec<EmulationProvider>,
+    /// Decides if the outcome of a request means that its origin blocks the profile it was
+    /// sent with.
+    pub trigger: Arc<dyn Fn(&FailureContext<'_>) -> bool + Send + Sync>,
+    /// How long an origin is sent a fallback before the primary is tried again.
+    pub cooldown: Duration,
+}
+
+impl fmt::Debug for FailoverConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FailoverConfig")
+            .field("profiles", &self.profiles.len())
+            .field("cooldown", &self.cooldown)
+            .finish()
+    }
+}
+
+/// The outcome of a request sent with a profile of a [`FailoverConfig`], passed to its trigger.
+#[derive(Debug)]
+pub struct FailureContext<'a> {
+    origin: &'a str,
+    profile: usize,
+    kind: Option<FailureKind>,
+    status: Option<StatusCode>,
+    challenge: bool,
+}
+
+impl FailureContext<'_> {
+    /// Returns the origin the request was sent to, such as `https://example.com`.
+    pub fn origin(&self) -> &str {
+        self.origin
+    }
+
+    /// Returns the index of the profile the request was sent with.
+    pub fn profile(&self) -> usize {
+        self.profile
+    }
+
+    /// Returns how the request failed, or `None` if a response was received.
+    pub fn kind(&self) -> Option<FailureKind> {
+        self.kind
+    }
+
+    /// Returns the status of the response, or `None` if the request failed.
+    pub fn status(&self) -> Option<StatusCode> {
+        self.status
+    }
+
+    /// Returns true if the response is a bot challenge rather than the requested resource.
+    ///
+    /// The challenges of the Cloudflare and AWS WAF bot protections are recognized, from the
+    /// `cf-mitigated: challenge` and `x-amzn-waf-action: challenge` or `captcha` headers they
+    /// announce them with.
+    pub fn challenge(&self) -> bool {
+        self.challenge
+    }
+
+    /// Returns true if the TLS handshake was rejected or reset, or if the response is a
+    /// challenge, the ways an origin blocking a fingerprint usually responds.
+    pub fn is_blocked(&self) -> bool {
+        self.challenge
+            || matches!(
+                self.kind,
+                Some(FailureKind::HandshakeRejected | FailureKind::HandshakeReset)
+            )
+    }
+}
+
+/// How a request failed, see [`FailureContext::kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FailureKind {
+    /// The server answered the ClientHello with an alert, failing the TLS handshake.
+    HandshakeRejected,
+    /// The server reset or closed the connection after the ClientHello, before the TLS handshake
+    /// completed.
+    HandshakeReset,
+    /// The connection was reset after it was established.
+    ConnectionReset,
+    /// The request failed otherwise.
+    Other,
+}
+
+impl FailureKind {
+    fn of(err: &Error) -> FailureKind {
+        #[cfg(feature = "tls")]
+        if err.is_connect() {
+            let mut source = err.source();
+            while let Some(cause) = source {
+                let ssl_err = cause
+                    .downcast_ref::<io::Error>()
+                    .and_then(io::Error::get_ref)
+                    .and_then(|cause| cause.downcast_ref::<boring2::ssl::Error>())
+                    .or_else(|| cause.downcast_ref::<boring2::ssl::Error>());
+
+                if let Some(ssl_err) = ssl_err {
+                    let reset = ssl_err.io_error().map_or(
+                        matches!(ssl_err.code(), ErrorCode::SYSCALL | ErrorCode::ZERO_RETURN),
+                        |io| {
+                            matches!(
+                                io.kind(),
+                                io::ErrorKind::ConnectionReset
+                                    | io::ErrorKind::ConnectionAborted
+                                    | io::ErrorKind::BrokenPipe
+                                    | io::ErrorKind::UnexpectedEof
+                            )
+                        },
+                    );
+                    return if reset {
+                        FailureKind::HandshakeReset
+                    } else {
+                        FailureKind::HandshakeRejected
+                    };
+                }
+                source = cause.source();
+            }
+        }
+
+        if err.is_connection_reset() {
+            FailureKind::ConnectionReset
+        } else {
+            FailureKind::Other
+        }
+    }
+}
+
+/// The failover state of an origin, see [`Client::profile_stats`](crate::Client::profile_stats).
+#[derive(Debug, Clone)]
+pub struct ProfileStats {
+    origin: String,
+    profile: usize,
+    since: Instant,
+    failovers: u64,
+    recoveries: u64,
+}
+
+impl ProfileStats {
+    /// Returns the origin, such as `https://example.com`.
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    /// Returns the index of the profile the origin was last failed over to, or `0` once it
+    /// recovered.
+    ///
+    /// After the cooldown, requests to the origin are sent with the primary profile again,
+    /// and the origin recovers with the first of them that succeeds.
+    pub fn profile(&self) -> usize {
+        self.profile
+    }
+
+    /// Returns when the origin was last blocked, which starts its cooldown, or when it
+    /// recovered.
+    pub fn since(&self) -> Instant {
+        self.since
+    }
+
+    /// Returns how many times the origin failed over to a fallback.
+    pub fn failovers(&self) -> u64 {
+        self.failovers
+    }
+
+    /// Returns how many times the origin recovered to the primary profile.
+    pub fn recoveries(&self) -> u64 {
+        self.recoveries
+    }
+}
+
+/// The profiles of a [`FailoverConfig`], and the failover state of the origins.
+#[derive(Clone)]
+pub(crate) struct ProfileFailover {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    config: FailoverConfig,
+    origins: Mutex<HashMap<String, ProfileStats>>,
+}
+
+impl ProfileFailover {
+    pub(crate) fn new(config: FailoverConfig) -> ProfileFailover {
+        assert!(
+            !config.profiles.is_empty(),
+            "profile failover needs at least one profile"
+        );
+
+        ProfileFailover {
+            inner: Arc::new(Inner {
+                config,
+                origins: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Applies the profile the origin of `req` is sent with, returning the ticket its outcome
+    /// is reported with.
+    pub(crate) fn apply(&self, req: &mut Request) -> FailoverTicket {
+        let origin = req.url().origin().ascii_serialization();
+        let profile = match self.inner.origins.lock().get(&origin) {
+            Some(stats) if stats.since.elapsed() < self.inner.config.cooldown => stats.profile,
+            // probe the primary again
+            _ => 0,
+        };
+
+        apply_profile(
+            &self.inner.config.profiles[profile],
+            EmulationSelection(profile),
+            req,
+        );
+        FailoverTicket {
+            failover: self.clone(),
+            origin,
+            profile,
+        }
+    }
+
+    /// Returns the state of the origins that failed over, ordered by origin.
+    pub(crate) fn stats(&self) -> Vec<ProfileStats> {
+        let mut stats: Vec<_> = self.inner.origins.lock().values().cloned().collect();
+        stats.sort_by(|a, b| a.origin.cmp(&b.origin));
+        stats
+    }
+}
+
+/// The profile a request was sent with, reporting its outcome to the failover state.
+pub(crate) struct FailoverTicket {
+    failover: ProfileFailover,
+    origin: String,
+    profile: usize,
+}
+
+impl FailoverTicket {
+    pub(crate) fn on_response(self, status: StatusCode, headers: &HeaderMap) {
+        let challenge = headers
+            .get("cf-mitigated")
+            .is_some_and(|v| v.as_bytes().eq_ignore_ascii_case(b"challenge"))
+            || headers.get("x-amzn-waf-action").is_some_and(|v| {
+                v.as_bytes().eq_ignore_ascii_case(b"challenge")
+                    || v.as_bytes().eq_ignore_ascii_case(b"captcha")
+            });
+
+        self.report(None, Some(status), challenge);
+    }
+
+    pub(crate) fn on_error(self, err: &Error) {
+        self.report(Some(FailureKind::of(err)), None, false);
+    }
+
+    fn report(self, kind: Option<FailureKind>, status: Option<StatusCode>, challenge: bool) {
+        let inner = &self.failover.inner;
+        let blocked = (inner.config.trigger)(&FailureContext {
+            origin: &self.origin,
+            profile: self.profile,
+            kind,
+            status,
+            challenge,
+        });
+
+        let mut origins = inner.origins.lock();
+        let now = Instant::now();
+        if blocked {
+            let stats = origins
+                .entry(self.origin.clone())
+                .or_insert_with(|| ProfileStats {
+                    origin: self.origin.clone(),
+                    profile: 0,
+                    since: now,
+                    failovers: 0,
+                    recoveries: 0,
+                });
+
+            // a failed probe of the primary keeps the fallback the origin was failed over to
+            let next = (self.profile + 1).min(inner.config.profiles.len() - 1);
+            if self.profile >= stats.profile && next != stats.profile {
+                debug!(
+                    "{} blocks emulation profile {}, failing over to {}",
+                    self.origin, self.profile, next
+                );
+                stats.profile = next;
+                stats.failovers += 1;
+            }
+            stats.since = now;
+        } else if let Some(stats) = origins.get_mut(&self.origin) {
+            if self.profile == 0 && stats.profile != 0 {
+                debug!(
+                    "{} accepts the primary emulation profile again",
+                    self.origin
+                );
+                stats.profile = 0;
+                stats.recoveries += 1;
+                stats.since = now;
+            }
+        }
+    }
+}
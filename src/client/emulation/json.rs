@@ -0,0 +1,280 @@
+//! Loading an [`EmulationProvider`] from a JSON profile.
+//!
+//! The `tls`, `http1` and `http2` sections use the serialized forms of [`TlsConfig`],
+//! [`Http1Config`] and [`Http2Config`]. Errors are reported with the path to the field they
+//! come from, such as `tls.cipher_list` or `headers_order[2]`.
+//!
+//! [`TlsConfig`]: crate::tls::TlsConfig
+//! [`Http1Config`]: crate::http1::Http1Config
+//! [`Http2Config`]: crate::http2::Http2Config
+
+use std::{fmt, io};
+
+use http::{HeaderMap, HeaderName, HeaderValue};
+use serde::{
+    Deserialize, Deserializer,
+    de::{self, DeserializeOwned, MapAccess, Visitor},
+};
+use serde_json::{Map, Value};
+
+use super::EmulationProvider;
+use crate::{Error, OriginalHeaders};
+
+const FIELDS: &[&str] = &["default_headers", "headers_order", "http1", "http2", "tls"];
+
+impl EmulationProvider {
+    /// Loads an `EmulationProvider` from a JSON profile.
+    ///
+    /// A profile is an object with the optional fields:
+    ///
+    /// - `default_headers`: an object of header names to a value or a list of values, sent in the
+    ///   order they are written.
+    /// - `headers_order`: a list of header names, in the order and case they are sent in.
+    /// - `http1`, `http2` and `tls`: the configurations, whose missing fields take their default
+    ///   values. Without the `tls` feature, the `tls` section is ignored, as TLS is terminated
+    ///   elsewhere.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use wreq::EmulationProvider;
+    ///
+    /// let provider = EmulationProvider::from_json(
+    ///     r#"{
+    ///         "default_headers": { "user-agent": "Mozilla/5.0" },
+    ///         "headers_order": ["User-Agent", "Accept"],
+    ///         "http2": { "settings_order": ["HEADER_TABLE_SIZE", "ENABLE_PUSH"] },
+    ///         "tls": { "alpn_protos": ["h2", "http/1.1"], "cipher_list": "ALL" }
+    ///     }"#,
+    /// )?;
+    /// let client = wreq::Client::builder().emulation(provider).build()?;
+    /// # let _ = client;
+    /// # Ok::<(), wreq::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This method fails if the profile is not valid JSON, has a field that is not known, or
+    /// holds an invalid header name or value. The error names the path to the bad field.
+    pub fn from_json(json: &str) -> crate::Result<EmulationProvider> {
+        serde_json::from_str(json).map_err(Error::builder)
+    }
+
+    /// Loads an `EmulationProvider` from a JSON profile read from `reader`.
+    ///
+    /// See [`EmulationProvider::from_json`] for the format of the profile.
+    pub fn from_reader<R: io::Read>(reader: R) -> crate::Result<EmulationProvider> {
+        serde_json::from_reader(reader).map_err(Error::builder)
+    }
+}
+
+impl<'de> Deserialize<'de> for EmulationProvider {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(ProviderVisitor)
+    }
+}
+
+struct ProviderVisitor;
+
+impl<'de> Visitor<'de> for ProviderVisitor {
+    type Value = EmulationProvider;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an emulation profile")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut provider = EmulationProvider::default();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "default_headers" => {
+                    provider.default_headers = Some(map.next_value::<DefaultHeaders>()?.0);
+                }
+                "headers_order" => {
+                    let names = map
+                        .next_value::<Vec<String>>()
+                        .map_err(|err| de::Error::custom(format_args!("headers_order: {err}")))?;
+                    provider.original_headers = Some(headers_order(names)?);
+                }
+                "http1" => provider.http1_config = Some(section(&key, map.next_value()?)?),
+                "http2" => provider.http2_config = Some(section(&key, map.next_value()?)?),
+                #[cfg(feature = "tls")]
+                "tls" => provider.tls_config = Some(section(&key, map.next_value()?)?),
+                #[cfg(not(feature = "tls"))]
+                "tls" => {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+                _ => return Err(de::Error::unknown_field(&key, FIELDS)),
+            }
+        }
+        Ok(provider)
+    }
+}
+
+/// Deserializes the section `name` of a profile.
+///
+/// On error, the fields of the section are deserialized one by one to find the bad one, the
+/// others taking their default values. Errors that involve several fields, such as two that
+/// exclude each other, are reported for the whole section.
+fn section<T: DeserializeOwned, E: de::Error>(name: &str, value: Value) -> Result<T, E> {
+    let err = match T::deserialize(&value) {
+        Ok(config) => return Ok(config),
+        Err(err) => err,
+    };
+
+    if let Value::Object(fields) = value {
+        for (field, value) in fields {
+            let single = Value::Object(Map::from_iter([(field.clone(), value)]));
+            if let Err(err) = T::deserialize(single) {
+                return Err(E::custom(format_args!("{name}.{field}: {err}")));
+            }
+        }
+    }
+    Err(E::custom(format_args!("{name}: {err}")))
+}
+
+/// Builds the order of headers from their names, keeping their case.
+fn headers_order<E: de::Error>(names: Vec<String>) -> Result<OriginalHeaders, E> {
+    let mut order = OriginalHeaders::with_capacity(names.len());
+    for (i, name) in names.into_iter().enumerate() {
+        if HeaderName::from_bytes(name.as_bytes()).is_err() {
+            return Err(E::custom(format_args!(
+                "headers_order[{i}]: invalid header name `{name}`"
+            )));
+        }
+        order.insert(name);
+    }
+    Ok(order)
+}
+
+/// The default headers of a profile, kept in the order they are written.
+struct DefaultHeaders(HeaderMap);
+
+/// A header with one or several values.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Values {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl<'de> Deserialize<'de> for DefaultHeaders {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(DefaultHeadersVisitor)
+    }
+}
+
+struct DefaultHeadersVisitor;
+
+impl<'de> Visitor<'de> for DefaultHeadersVisitor {
+    type Value = DefaultHeaders;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an object of header names to a value or a list of values")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut headers = HeaderMap::new();
+        while let Some(name) = map.next_key::<String>()? {
+            let header = HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+                de::Error::custom(format_args!(
+                    "default_headers: invalid header name `{name}`"
+                ))
+            })?;
+            let values = match map.next_value::<Values>() {
+                Ok(Values::One(value)) => vec![value],
+                Ok(Values::Many(values)) => values,
+                Err(_) => {
+                    return Err(de::Error::custom(format_args!(
+                        "default_headers.{name}: expected a string or a list of strings"
+                    )));
+                }
+            };
+
+            for value in values {
+                let value = HeaderValue::from_str(&value).map_err(|_| {
+                    de::Error::custom(format_args!(
+                        "default_headers.{name}: invalid header value `{}`",
+                        value.escape_debug()
+                    ))
+                })?;
+                headers.append(header.clone(), value);
+            }
+        }
+        Ok(DefaultHeaders(headers))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::header;
+
+    use super::*;
+
+    fn error(json: &str) -> String {
+        EmulationProvider::from_json(json).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn headers_keep_their_order_and_values() {
+        let provider = EmulationProvider::from_json(
+            r#"{
+                "default_headers": {
+                    "user-agent": "Mozilla/5.0",
+                    "accept": ["text/html", "*/*"],
+                    "accept-language": "en-US"
+                },
+                "headers_order": ["Accept", "User-Agent"]
+            }"#,
+        )
+        .unwrap();
+
+        let headers = provider.default_headers.unwrap();
+        let names: Vec<_> = headers.keys().map(HeaderName::as_str).collect();
+        assert_eq!(names, ["user-agent", "accept", "accept-language"]);
+        let accept: Vec<_> = headers.get_all(header::ACCEPT).iter().collect();
+        assert_eq!(accept, ["text/html", "*/*"]);
+        assert_eq!(provider.original_headers.unwrap().len(), 2);
+        #[cfg(feature = "tls")]
+        assert!(provider.tls_config.is_none());
+    }
+
+    #[test]
+    fn errors_name_the_bad_field() {
+        #[cfg(feature = "tls")]
+        {
+            let err = error(r#"{"tls": {"alpn_protos": ["h2"], "cipher_lsit": "ALL"}}"#);
+            assert!(err.contains("tls.cipher_lsit: unknown field"), "{err}");
+        }
+
+        let err = error(r#"{"http2": {"settings_order": ["HEADER_TABLE_SIZE", "PUSH"]}}"#);
+        assert!(
+            err.contains("http2.settings_order: unknown setting `PUSH`"),
+            "{err}"
+        );
+
+        let err = error(r#"{"http1": {"read_buf_exact_size": 8192, "max_buf_size": 8192}}"#);
+        assert!(err.contains("http1: `read_buf_exact_size`"), "{err}");
+
+        let err = error(r#"{"default_headers": {"user agent": "x"}}"#);
+        assert!(
+            err.contains("default_headers: invalid header name"),
+            "{err}"
+        );
+
+        let err = error(r#"{"default_headers": {"accept": ["*/*", "a\nb"]}}"#);
+        assert!(
+            err.contains("default_headers.accept: invalid header value"),
+            "{err}"
+        );
+
+        let err = error(r#"{"headers_order": ["Accept", "Bad:Name"]}"#);
+        assert!(
+            err.contains("headers_order[1]: invalid header name"),
+            "{err}"
+        );
+
+        let err = error(r#"{"http3": {}}"#);
+        assert!(err.contains("unknown field `http3`"), "{err}");
+    }
+}
@@ -0,0 +1,395 @@
+mod failover;
+#[cfg(all(feature = "serde", feature = "json"))]
+mod json;
+mod rotating;
+mod validate;
+
+use std::{
+    ops::{BitOr, BitOrAssign},
+    sync::Arc,
+};
+
+use http::{HeaderMap, Method};
+use url::Url;
+
+pub use self::{
+    failover::{FailoverConfig, FailureContext, FailureKind, ProfileStats},
+    rotating::{EmulationSelection, RotatingEmulation},
+    validate::EmulationConflicts,
+};
+pub(crate) use self::{
+    failover::{FailoverTicket, ProfileFailover},
+    validate::Consistency,
+};
+#[cfg(feature = "tls")]
+use crate::tls::TlsConfig;
+use crate::{
+    OriginalHeaders, client::middleware::headers_hook::HeadersHook, http1::Http1Config,
+    http2::Http2Config,
+};
+
+/// Trait defining the interface for providing an `EmulationProvider`.
+///
+/// The `EmulationProviderFactory` trait is designed to be implemented by types that can provide
+/// an `EmulationProvider` instance. This trait abstracts the creation and configuration of
+/// `EmulationProvider`, allowing different types to offer their own specific configurations.
+///
+/// # Example
+///
+/// ```rust
+/// use wreq::{
+///     EmulationProvider,
+///     EmulationProviderFactory,
+/// };
+///
+/// struct MyEmulationProvider;
+///
+/// impl EmulationProviderFactory for MyEmulationProvider {
+///     fn emulation(self) -> EmulationProvider {
+///         EmulationProvider::default()
+///     }
+/// }
+///
+/// let provider = MyEmulationProvider.emulation();
+/// ```
+pub trait EmulationProviderFactory {
+    /// Provides an `EmulationProvider` instance.
+    fn emulation(self) -> EmulationProvider;
+}
+
+/// Builder for creating an `EmulationProvider`.
+#[must_use]
+#[derive(Debug)]
+pub struct EmulationProviderBuilder {
+    provider: EmulationProvider,
+}
+
+/// HTTP connection context that manages both HTTP and TLS configurations.
+///
+/// The `EmulationProvider` provides a complete environment for HTTP connections,
+/// including both HTTP-specific settings and the underlying TLS configuration.
+/// This unified context ensures consistent behavior across connections.
+///
+/// # Components
+///
+/// - **TLS Configuration**: Manages secure connection settings.
+/// - **HTTP Settings**: Controls HTTP/1 and HTTP/2 behaviors.
+/// - **Header Management**: Handles default headers and their ordering.
+///
+/// # Example
+///
+/// ```rust
+/// use wreq::{
+///     EmulationProvider,
+///     TlsConfig,
+/// };
+///
+/// let provider = EmulationProvider::builder()
+///     .tls_config(TlsConfig::default())
+///     .build()?;
+/// # Ok::<(), wreq::Error>(())
+/// ```
+#[derive(Default, Debug)]
+pub struct EmulationProvider {
+    #[cfg(feature = "tls")]
+    pub(crate) tls_config: Option<TlsConfig>,
+    pub(crate) http1_config: Option<Http1Config>,
+    pub(crate) http2_config: Option<Http2Config>,
+    pub(crate) default_headers: Option<HeaderMap>,
+    pub(crate) original_headers: Option<OriginalHeaders>,
+    pub(crate) headers_hook: Option<HeadersHook>,
+    /// Whether contradictions were allowed, see [`EmulationProviderBuilder::strict`].
+    pub(crate) lenient: bool,
+}
+
+impl EmulationProviderBuilder {
+    /// Sets the TLS configuration for the `EmulationProvider`.
+    #[cfg(feature = "tls")]
+    pub fn tls_config<C>(mut self, config: C) -> Self
+    where
+        C: Into<Option<TlsConfig>>,
+    {
+        self.provider.tls_config = config.into();
+        self
+    }
+
+    /// Sets the HTTP/1 configuration for the `EmulationProvider`.
+    pub fn http1_config<C>(mut self, config: C) -> Self
+    where
+        C: Into<Option<Http1Config>>,
+    {
+        self.provider.http1_config = config.into();
+        self
+    }
+
+    /// Sets the HTTP/2 configuration for the `EmulationProvider`.
+    pub fn http2_config<C>(mut self, config: C) -> Self
+    where
+        C: Into<Option<Http2Config>>,
+    {
+        self.provider.http2_config = config.into();
+        self
+    }
+
+    /// Sets the default headers for the `EmulationProvider`.
+    pub fn default_headers<H>(mut self, headers: H) -> Self
+    where
+        H: Into<Option<HeaderMap>>,
+    {
+        self.provider.default_headers = headers.into();
+        self
+    }
+
+    /// Sets the original headers for the `EmulationProvider`.
+    pub fn original_headers<H>(mut self, headers: H) -> Self
+    where
+        H: Into<Option<OriginalHeaders>>,
+    {
+        self.provider.original_headers = headers.into();
+        self
+    }
+
+    /// Orders the headers as the default headers were inserted, replacing the
+    /// [`original_headers`](Self::original_headers), see
+    /// [`ClientBuilder::headers_order_from_defaults`](crate::ClientBuilder::headers_order_from_defaults).
+    ///
+    /// The order is taken from the default headers set so far, and left unset without them.
+    pub fn headers_order_from_defaults(mut self) -> Self {
+        self.provider.original_headers = self
+            .provider
+            .default_headers
+            .as_ref()
+            .map(OriginalHeaders::from_defaults);
+        self
+    }
+
+    /// Sets a hook computing the headers of each request from its url and method, such as the
+    /// `sec-fetch-site`, `referer` or `priority` headers a browser varies per navigation.
+    ///
+    /// The hook runs after the default headers are merged into the request, and before its
+    /// cookies and `Accept-Encoding` are added. It runs again on every redirect, with the url
+    /// redirected to, and the headers of the previous hop.
+    ///
+    /// The headers the hook inserts that are not in the
+    /// [`original_headers`](Self::original_headers) order are sent after the ordered ones, in the
+    /// order they were inserted.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use wreq::{
+    ///     EmulationProvider, Method, Url,
+    ///     header::{HeaderMap, HeaderValue},
+    /// };
+    ///
+    /// let provider = EmulationProvider::builder()
+    ///     .headers_hook(Arc::new(|url: &Url, _: &Method, headers: &mut HeaderMap| {
+    ///         if url.path().ends_with(".js") {
+    ///             headers.insert("sec-fetch-dest", HeaderValue::from_static("script"));
+    ///         }
+    ///     }))
+    ///     .build()?;
+    /// # let _ = provider;
+    /// # Ok::<(), wreq::Error>(())
+    /// ```
+    pub fn headers_hook(
+        mut self,
+        hook: Arc<dyn Fn(&Url, &Method, &mut HeaderMap) + Send + Sync>,
+    ) -> Self {
+        self.provider.headers_hook = Some(HeadersHook(hook));
+        self
+    }
+
+    /// Sets whether [`build`](Self::build) fails on contradictions in the configuration.
+    ///
+    /// The checks catch the configurations producing fingerprints no browser sends:
+    ///
+    /// - an HTTP/2 configuration with ALPN protocols not offering `h2`,
+    /// - ALPS enabled with ALPN protocols not offering `h2`,
+    /// - a headers order listing headers that are neither default headers nor headers a request
+    ///   carries, such as `cookie` or `content-type`, unless a [`headers_hook`](Self::headers_hook)
+    ///   is set,
+    /// - a pseudo-header or settings order listing an item twice.
+    ///
+    /// A lenient emulation also skips the checks of
+    /// [`ClientBuilder::build`](crate::ClientBuilder::build) once applied.
+    ///
+    /// Default is `true`.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.provider.lenient = !strict;
+        self
+    }
+
+    /// Builds the `EmulationProvider` instance.
+    ///
+    /// # Errors
+    ///
+    /// Fails with an [`EmulationConflicts`] cause if the configuration contradicts itself,
+    /// unless it is not [`strict`](Self::strict).
+    pub fn build(self) -> crate::Result<EmulationProvider> {
+        let provider = self.provider;
+        if !provider.lenient {
+            Consistency {
+                #[cfg(feature = "tls")]
+                tls: provider.tls_config.as_ref(),
+                http2: provider.http2_config.as_ref(),
+                version: None,
+                headers: provider.default_headers.as_ref(),
+                headers_order: provider.original_headers.as_ref(),
+                headers_hook: provider.headers_hook.is_some(),
+            }
+            .check()?;
+        }
+        Ok(provider)
+    }
+}
+
+impl EmulationProvider {
+    /// Creates a new `EmulationProviderBuilder`.
+    ///
+    /// # Returns
+    ///
+    /// Returns a new `EmulationProviderBuilder` instance.
+    pub fn builder() -> EmulationProviderBuilder {
+        EmulationProviderBuilder {
+            provider: EmulationProvider::default(),
+        }
+    }
+
+    /// Creates an emulation of the HTTP/2 fingerprint and the headers of a profile only, for
+    /// connections whose TLS is terminated elsewhere, such as by a proxy.
+    ///
+    /// Applying it leaves the TLS and HTTP/1 configurations of the client untouched, and
+    /// replaces its default headers, and their order if `headers_order` is set.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`EmulationProviderBuilder::build`] if the configuration contradicts itself.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use wreq::{EmulationProvider, header::HeaderMap, http2::Http2Config};
+    ///
+    /// let provider = EmulationProvider::http2_only(
+    ///     Http2Config::builder()
+    ///         .initial_stream_window_size(6291456)
+    ///         .build(),
+    ///     HeaderMap::new(),
+    ///     None,
+    /// )?;
+    /// # let _ = provider;
+    /// # Ok::<(), wreq::Error>(())
+    /// ```
+    pub fn http2_only<O>(
+        http2_config: Http2Config,
+        default_headers: HeaderMap,
+        headers_order: O,
+    ) -> crate::Result<EmulationProvider>
+    where
+        O: Into<Option<OriginalHeaders>>,
+    {
+        EmulationProvider::builder()
+            .http2_config(http2_config)
+            .default_headers(default_headers)
+            .original_headers(headers_order)
+            .build()
+    }
+
+    /// Keeps the `parts` of the emulation, and strips the others.
+    ///
+    /// A stripped part leaves the configuration it would have replaced untouched when the
+    /// emulation is applied.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use wreq::{EmulationApply, EmulationProvider};
+    ///
+    /// # fn chrome() -> EmulationProvider { EmulationProvider::default() }
+    /// // Chrome's handshakes, with the headers of the client.
+    /// let provider = chrome().retain(EmulationApply::TLS | EmulationApply::HTTP2);
+    /// # let _ = provider;
+    /// ```
+    pub fn retain(mut self, parts: EmulationApply) -> EmulationProvider {
+        #[cfg(feature = "tls")]
+        if !parts.contains(EmulationApply::TLS) {
+            self.tls_config = None;
+        }
+        if !parts.contains(EmulationApply::HTTP1) {
+            self.http1_config = None;
+        }
+        if !parts.contains(EmulationApply::HTTP2) {
+            self.http2_config = None;
+        }
+        if !parts.contains(EmulationApply::HEADERS) {
+            self.default_headers = None;
+            self.original_headers = None;
+            self.headers_hook = None;
+        }
+        self
+    }
+}
+
+/// The parts of an emulation to apply, see
+/// [`ClientBuilder::emulation_with`](crate::ClientBuilder::emulation_with).
+///
+/// Combine parts with `|`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EmulationApply(u8);
+
+impl EmulationApply {
+    /// The TLS configuration.
+    pub const TLS: EmulationApply = EmulationApply(1 << 0);
+
+    /// The HTTP/1 configuration.
+    pub const HTTP1: EmulationApply = EmulationApply(1 << 1);
+
+    /// The HTTP/2 configuration.
+    pub const HTTP2: EmulationApply = EmulationApply(1 << 2);
+
+    /// The default headers, the headers hook, and the order and case of the headers.
+    pub const HEADERS: EmulationApply = EmulationApply(1 << 3);
+
+    /// Returns no parts.
+    pub const fn empty() -> EmulationApply {
+        EmulationApply(0)
+    }
+
+    /// Returns all parts, as applied by
+    /// [`ClientBuilder::emulation`](crate::ClientBuilder::emulation).
+    pub const fn all() -> EmulationApply {
+        EmulationApply(0b1111)
+    }
+
+    /// Returns whether all parts of `other` are set.
+    pub fn contains(self, other: EmulationApply) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for EmulationApply {
+    type Output = EmulationApply;
+
+    fn bitor(self, rhs: EmulationApply) -> EmulationApply {
+        EmulationApply(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for EmulationApply {
+    fn bitor_assign(&mut self, rhs: EmulationApply) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Implement `EmulationProviderFactory` for `EmulationProvider`.
+///
+/// This implementation allows an `EmulationProvider` to be used wherever an
+/// `EmulationProviderFactory` is required, providing a default emulation configuration.
+impl EmulationProviderFactory for EmulationProvider {
+    fn emulation(self) -> EmulationProvider {
+        self
+    }
+}
@@ -0,0 +1,193 @@
+//! Rotating requests across a weighted pool of emulation profiles.
+
+use std::{fmt, sync::Arc};
+
+use super::{EmulationProvider, EmulationProviderFactory};
+use crate::{Request, util::RandomSource};
+
+/// A weighted pool of emulation profiles, one of which is picked for every request, see
+/// [`ClientBuilder::emulation_pool`](crate::ClientBuilder::emulation_pool).
+///
+/// Profiles are picked from a random sequence of their own, which can be
+/// [`seed`](RotatingEmulation::seed)ed to reproduce the same picks. Clones share the sequence.
+///
+/// # Example
+///
+/// ```
+/// use wreq::{EmulationProvider, RotatingEmulation};
+///
+/// # fn profiles() -> (EmulationProvider, EmulationProvider) { Default::default() }
+/// let (chrome, firefox) = profiles();
+///
+/// // Three requests out of four look like Chrome, the others like Firefox.
+/// let pool = RotatingEmulation::new([(chrome, 3), (firefox, 1)]).seed(42);
+/// let client = wreq::Client::builder().emulation_pool(pool).build()?;
+/// # let _ = client;
+/// # Ok::<(), wreq::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct RotatingEmulation {
+    profiles: Arc<[Profile]>,
+    total_weight: u64,
+    random: RandomSource,
+}
+
+struct Profile {
+    provider: EmulationProvider,
+    weight: u32,
+}
+
+impl RotatingEmulation {
+    /// Creates a pool of `profiles`, each picked with a probability proportional to its weight.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pool is empty, or if all of its weights are zero.
+    pub fn new<I, P>(profiles: I) -> RotatingEmulation
+    where
+        I: IntoIterator<Item = (P, u32)>,
+        P: EmulationProviderFactory,
+    {
+        let profiles: Arc<[Profile]> = profiles
+            .into_iter()
+            .map(|(factory, weight)| Profile {
+                provider: factory.emulation(),
+                weight,
+            })
+            .collect();
+        let total_weight = profiles.iter().map(|p| u64::from(p.weight)).sum();
+        assert!(
+            total_weight > 0,
+            "emulation pool needs a profile with a non-zero weight"
+        );
+
+        RotatingEmulation {
+            profiles,
+            total_weight,
+            random: RandomSource::Fast,
+        }
+    }
+
+    /// Picks the profiles from a sequence seeded with `seed`, instead of a random one.
+    pub fn seed(mut self, seed: u64) -> RotatingEmulation {
+        self.random = RandomSource::seeded(seed);
+        self
+    }
+
+    /// Picks the profile of the next request.
+    pub(crate) fn select(&self) -> EmulationSelection {
+        let mut n = self.random.next() % self.total_weight;
+        for (index, profile) in self.profiles.iter().enumerate() {
+            match n.checked_sub(u64::from(profile.weight)) {
+                Some(rest) => n = rest,
+                None => return EmulationSelection(index),
+            }
+        }
+        unreachable!("the sum of the weights is the total weight")
+    }
+
+    /// Applies the profile picked for `req`, see [`apply_profile`].
+    pub(crate) fn apply(&self, selection: EmulationSelection, req: &mut Request) {
+        apply_profile(&self.profiles[selection.0].provider, selection, req);
+    }
+}
+
+/// Applies `provider` to `req`, the way
+/// [`RequestBuilder::emulation`](crate::RequestBuilder::emulation) does, and tags it with
+/// `selection` so that it only reuses connections of the same profile.
+///
+/// The headers of the profile take the place of the default headers of the client, and do
+/// not replace the headers set on the request.
+pub(super) fn apply_profile(
+    provider: &EmulationProvider,
+    selection: EmulationSelection,
+    req: &mut Request,
+) {
+    let transport_config = req.transport_config_mut().get_or_insert_default();
+    transport_config.set_http1_config(provider.http1_config.clone());
+    transport_config.set_http2_config(provider.http2_config.clone());
+    #[cfg(feature = "tls")]
+    transport_config.set_tls_config(provider.tls_config.clone());
+
+    if req.original_headers_mut().is_none() {
+        *req.original_headers_mut() = provider.original_headers.clone();
+    }
+
+    if req.headers_hook_mut().is_none() {
+        *req.headers_hook_mut() = provider.headers_hook.clone();
+    }
+
+    let skip_default_headers = req.default_headers_mut();
+    if *skip_default_headers != Some(true) {
+        *skip_default_headers = Some(true);
+        if let Some(ref default_headers) = provider.default_headers {
+            let headers = req.headers_mut();
+            for name in default_headers.keys() {
+                if !headers.contains_key(name) {
+                    for value in default_headers.get_all(name) {
+                        headers.append(name, value.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    req.extensions_mut().insert(selection);
+}
+
+impl fmt::Debug for RotatingEmulation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let weights: Vec<_> = self.profiles.iter().map(|p| p.weight).collect();
+        f.debug_struct("RotatingEmulation")
+            .field("weights", &weights)
+            .finish()
+    }
+}
+
+/// Request and response [`http::Extensions`] value holding the index of the profile picked for
+/// a request from a [`RotatingEmulation`] pool, or from the profiles of a
+/// [`FailoverConfig`](crate::FailoverConfig), in the order the profiles were given.
+///
+/// A connection is only reused by requests that picked the same profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EmulationSelection(pub usize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(weights: &[u32]) -> RotatingEmulation {
+        RotatingEmulation::new(
+            weights
+                .iter()
+                .map(|&weight| (EmulationProvider::default(), weight)),
+        )
+    }
+
+    #[test]
+    fn picks_follow_the_weights() {
+        let pool = pool(&[3, 0, 1]).seed(7);
+        let mut counts = [0usize; 3];
+        for _ in 0..20_000 {
+            counts[pool.select().0] += 1;
+        }
+
+        assert_eq!(counts[1], 0);
+        let share = counts[0] as f64 / 20_000.0;
+        assert!((share - 0.75).abs() < 0.02, "share {share}");
+    }
+
+    #[test]
+    fn seeded_picks_are_reproducible() {
+        let a = pool(&[1, 1, 1, 1]).seed(42);
+        let b = pool(&[1, 1, 1, 1]).seed(42);
+        let picks = |pool: &RotatingEmulation| (0..100).map(|_| pool.select()).collect::<Vec<_>>();
+        assert_eq!(picks(&a), picks(&b));
+    }
+
+    #[test]
+    #[should_panic = "emulation pool needs a profile with a non-zero weight"]
+    fn rejects_pool_without_weight() {
+        pool(&[0, 0]);
+    }
+}
@@ -0,0 +1,193 @@
+//! Consistency checks of emulation configurations.
+
+use std::{error::Error as StdError, fmt};
+
+use http::{HeaderMap, HeaderName, Version, header};
+
+#[cfg(feature = "tls")]
+use crate::tls::TlsConfig;
+use crate::{Error, OriginalHeaders, http2::Http2Config};
+
+/// The headers a request carries on its own, or gets from the client as it is sent, which an
+/// order may list without them being default headers.
+const REQUEST_HEADERS: &[HeaderName] = &[
+    header::ACCEPT_ENCODING,
+    header::AUTHORIZATION,
+    header::CACHE_CONTROL,
+    header::CONNECTION,
+    header::CONTENT_ENCODING,
+    header::CONTENT_LENGTH,
+    header::CONTENT_TYPE,
+    header::COOKIE,
+    header::HOST,
+    header::IF_MATCH,
+    header::IF_MODIFIED_SINCE,
+    header::IF_NONE_MATCH,
+    header::IF_RANGE,
+    header::IF_UNMODIFIED_SINCE,
+    header::ORIGIN,
+    header::PRAGMA,
+    header::PROXY_AUTHORIZATION,
+    header::RANGE,
+    header::REFERER,
+    header::SEC_WEBSOCKET_EXTENSIONS,
+    header::SEC_WEBSOCKET_KEY,
+    header::SEC_WEBSOCKET_PROTOCOL,
+    header::SEC_WEBSOCKET_VERSION,
+    header::TE,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+/// The cause of a builder error when an emulation contradicts itself, such as an HTTP/2
+/// configuration with ALPN protocols not offering `h2`.
+///
+/// See [`Error::emulation_conflicts`](crate::Error::emulation_conflicts), and
+/// [`EmulationProviderBuilder::strict`](crate::EmulationProviderBuilder::strict) to allow them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmulationConflicts {
+    problems: Vec<String>,
+}
+
+impl EmulationConflicts {
+    /// Returns the description of each contradiction found.
+    pub fn problems(&self) -> &[String] {
+        &self.problems
+    }
+}
+
+impl fmt::Display for EmulationConflicts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("inconsistent emulation: ")?;
+        f.write_str(&self.problems.join("; "))
+    }
+}
+
+impl StdError for EmulationConflicts {}
+
+/// The parts of a configuration checked for contradictions. The unset parts are not checked.
+pub(crate) struct Consistency<'a> {
+    #[cfg(feature = "tls")]
+    pub(crate) tls: Option<&'a TlsConfig>,
+    pub(crate) http2: Option<&'a Http2Config>,
+    /// The version all requests are forced to.
+    #[cfg_attr(not(feature = "tls"), allow(dead_code))]
+    pub(crate) version: Option<Version>,
+    pub(crate) headers: Option<&'a HeaderMap>,
+    pub(crate) headers_order: Option<&'a OriginalHeaders>,
+    pub(crate) headers_hook: bool,
+}
+
+impl Consistency<'_> {
+    /// Fails with an [`EmulationConflicts`] listing every contradiction found.
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        let mut problems = Vec::new();
+        #[cfg(feature = "tls")]
+        self.check_alpn(&mut problems);
+        self.check_http2_orders(&mut problems);
+        self.check_headers_order(&mut problems);
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::builder(EmulationConflicts { problems }))
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    fn check_alpn(&self, problems: &mut Vec<String>) {
+        let Some(tls) = self.tls else {
+            return;
+        };
+        let offers = |name: &[u8]| {
+            tls.alpn_protos
+                .as_deref()
+                .is_some_and(|protos| alpn_names(protos).any(|proto| proto == name))
+        };
+
+        match self.version {
+            Some(Version::HTTP_2) if !offers(b"h2") => problems
+                .push("`http2_only` replaces ALPN protocols that do not offer `h2`".to_owned()),
+            Some(Version::HTTP_11) if !offers(b"http/1.1") => problems.push(
+                "`http1_only` replaces ALPN protocols that do not offer `http/1.1`".to_owned(),
+            ),
+            Some(_) => {}
+            None => {
+                if self.http2.is_some() && !offers(b"h2") {
+                    problems.push(
+                        "the HTTP/2 configuration is never used, the ALPN protocols do not \
+                         offer `h2`"
+                            .to_owned(),
+                    );
+                }
+                let alps = tls.alps_protos.as_deref().unwrap_or_default();
+                if !alps.is_empty() && !offers(b"h2") {
+                    problems.push(
+                        "ALPS is enabled, but the ALPN protocols do not offer `h2`".to_owned(),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_http2_orders(&self, problems: &mut Vec<String>) {
+        let Some(http2) = self.http2 else {
+            return;
+        };
+        let h2 = &http2.h2_builder;
+
+        if let Some(ref order) = h2.headers_pseudo_order {
+            let ids: Vec<_> = order.clone().into_iter().collect();
+            if let Some(id) = first_duplicate(&ids) {
+                problems.push(format!("the pseudo-header order lists {id:?} twice"));
+            }
+        }
+        if let Some(ref order) = h2.settings_order {
+            let ids: Vec<_> = order.clone().into_iter().collect();
+            if let Some(id) = first_duplicate(&ids) {
+                problems.push(format!("the settings order lists {id:?} twice"));
+            }
+        }
+    }
+
+    fn check_headers_order(&self, problems: &mut Vec<String>) {
+        // a hook may add any header
+        if self.headers_hook {
+            return;
+        }
+        let (Some(headers), Some(order)) = (self.headers, self.headers_order) else {
+            return;
+        };
+
+        let missing: Vec<_> = order
+            .keys()
+            .filter(|name| !headers.contains_key(*name) && !REQUEST_HEADERS.contains(*name))
+            .map(HeaderName::as_str)
+            .collect();
+        if !missing.is_empty() {
+            problems.push(format!(
+                "the headers order lists headers that are never sent: {}",
+                missing.join(", ")
+            ));
+        }
+    }
+}
+
+/// Returns the names of a length-prefixed ALPN protocol list.
+#[cfg(feature = "tls")]
+fn alpn_names(mut protos: &[u8]) -> impl Iterator<Item = &[u8]> {
+    std::iter::from_fn(move || {
+        let (&len, rest) = protos.split_first()?;
+        let (name, rest) = rest.split_at_checked(usize::from(len))?;
+        protos = rest;
+        Some(name)
+    })
+}
+
+fn first_duplicate<T: PartialEq>(items: &[T]) -> Option<&T> {
+    items
+        .iter()
+        .enumerate()
+        .find(|(i, item)| items[..*i].contains(*item))
+        .map(|(_, item)| item)
+}
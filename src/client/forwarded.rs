@@ -0,0 +1,644 @@
+//! `Forwarded` and `X-Forwarded-*` headers, for clients used by reverse proxies.
+//!
+//! [`RequestBuilder::forwarded`](crate::RequestBuilder::forwarded) adds the proxy's hop to
+//! the headers of an outgoing request, and [`Forwarded::parse`] reads the hops of an
+//! incoming `Forwarded` header (RFC 7239).
+
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    str::FromStr,
+};
+
+use http::header::{FORWARDED, HeaderMap, HeaderName, HeaderValue};
+
+use crate::Error;
+
+static X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+static X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+static X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+
+/// A node of a `Forwarded` header, identifying a client or a proxy.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Identifier {
+    /// An IP address.
+    Ip(IpAddr),
+    /// An IP address and port.
+    Socket(SocketAddr),
+    /// An obfuscated identifier, such as `_hidden`, possibly with an obfuscated port.
+    ///
+    /// It must start with `_`, followed by ASCII letters, digits, `.`, `_` or `-`.
+    Obfuscated(String),
+    /// The node is not known.
+    Unknown,
+}
+
+impl Identifier {
+    /// Returns the identifier with any address replaced by a random obfuscated one.
+    fn obfuscate(&self) -> Identifier {
+        match self {
+            Identifier::Ip(_) | Identifier::Socket(_) => {
+                Identifier::Obfuscated(format!("_{:016x}", crate::util::fast_random()))
+            }
+            other => other.clone(),
+        }
+    }
+
+    /// Fails if an obfuscated identifier is malformed.
+    fn check(&self) -> crate::Result<()> {
+        if let Identifier::Obfuscated(name) = self {
+            let parsed = name.parse::<Identifier>();
+            if !matches!(parsed, Ok(Identifier::Obfuscated(ref parsed)) if parsed == name) {
+                return Err(Error::builder(format!(
+                    "invalid obfuscated identifier `{name}`"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the IP address of the identifier, if it has one.
+    pub fn ip(&self) -> Option<IpAddr> {
+        match self {
+            Identifier::Ip(ip) => Some(*ip),
+            Identifier::Socket(addr) => Some(addr.ip()),
+            Identifier::Obfuscated(_) | Identifier::Unknown => None,
+        }
+    }
+}
+
+impl From<IpAddr> for Identifier {
+    fn from(ip: IpAddr) -> Identifier {
+        Identifier::Ip(ip)
+    }
+}
+
+impl From<SocketAddr> for Identifier {
+    fn from(addr: SocketAddr) -> Identifier {
+        Identifier::Socket(addr)
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Identifier::Ip(IpAddr::V4(ip)) => write!(f, "{ip}"),
+            Identifier::Ip(IpAddr::V6(ip)) => write!(f, "[{ip}]"),
+            Identifier::Socket(addr) => write!(f, "{addr}"),
+            Identifier::Obfuscated(name) => f.write_str(name),
+            Identifier::Unknown => f.write_str("unknown"),
+        }
+    }
+}
+
+impl FromStr for Identifier {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Identifier, ParseError> {
+        // The port follows the bracketed IPv6 address, or the first colon otherwise
+        let (name, port) = match s.strip_prefix('[') {
+            Some(rest) => {
+                let end = rest.find(']').ok_or(ParseError(()))?;
+                let port = &rest[end + 1..];
+                let port = match port {
+                    "" => None,
+                    port => Some(port.strip_prefix(':').ok_or(ParseError(()))?),
+                };
+                (&s[..end + 2], port)
+            }
+            None => match s.split_once(':') {
+                Some((name, port)) => (name, Some(port)),
+                None => (s, None),
+            },
+        };
+
+        let port = match port {
+            None => None,
+            Some(port) if is_obfuscated(port) => None,
+            Some(port) => Some(port.parse::<u16>().map_err(|_| ParseError(()))?),
+        };
+
+        let ip = if name.eq_ignore_ascii_case("unknown") {
+            return Ok(Identifier::Unknown);
+        } else if is_obfuscated(name) {
+            return Ok(Identifier::Obfuscated(s.to_owned()));
+        } else if let Some(ip) = name.strip_prefix('[').and_then(|n| n.strip_suffix(']')) {
+            IpAddr::V6(ip.parse::<Ipv6Addr>().map_err(|_| ParseError(()))?)
+        } else {
+            IpAddr::V4(name.parse::<Ipv4Addr>().map_err(|_| ParseError(()))?)
+        };
+
+        Ok(match port {
+            Some(port) => Identifier::Socket(SocketAddr::new(ip, port)),
+            None => Identifier::Ip(ip),
+        })
+    }
+}
+
+/// Which headers [`RequestBuilder::forwarded`](crate::RequestBuilder::forwarded) writes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// Only the `Forwarded` header.
+    #[default]
+    Standard,
+    /// Only the `X-Forwarded-For`, `X-Forwarded-Host` and `X-Forwarded-Proto` headers.
+    Legacy,
+    /// Both the standard and the legacy headers.
+    Both,
+}
+
+/// How the addresses of the client and the proxy are disclosed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Privacy {
+    /// Addresses are written as they are.
+    #[default]
+    Full,
+    /// Addresses are replaced by random obfuscated identifiers.
+    ///
+    /// `X-Forwarded-For` can only carry addresses, so it is not extended in this mode.
+    Obfuscated,
+}
+
+/// The hop a proxy adds to the forwarding headers of a request.
+#[derive(Debug, Clone)]
+pub struct ForwardedContext {
+    client: Identifier,
+    by: Option<Identifier>,
+    host: Option<String>,
+    proto: Option<String>,
+    mode: Mode,
+    privacy: Privacy,
+}
+
+impl ForwardedContext {
+    /// Creates a hop for a request received from `client`.
+    pub fn new<I: Into<Identifier>>(client: I) -> ForwardedContext {
+        ForwardedContext {
+            client: client.into(),
+            by: None,
+            host: None,
+            proto: None,
+            mode: Mode::default(),
+            privacy: Privacy::default(),
+        }
+    }
+
+    /// Sets the interface the request was received on.
+    pub fn by<I: Into<Identifier>>(mut self, by: I) -> ForwardedContext {
+        self.by = Some(by.into());
+        self
+    }
+
+    /// Sets the `Host` of the received request.
+    pub fn host<T: Into<String>>(mut self, host: T) -> ForwardedContext {
+        self.host = Some(host.into());
+        self
+    }
+
+    /// Sets the protocol the request was received with, such as `https`.
+    pub fn proto<T: Into<String>>(mut self, proto: T) -> ForwardedContext {
+        self.proto = Some(proto.into());
+        self
+    }
+
+    /// Sets which headers are written. Defaults to [`Mode::Standard`].
+    pub fn mode(mut self, mode: Mode) -> ForwardedContext {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets how addresses are disclosed. Defaults to [`Privacy::Full`].
+    pub fn privacy(mut self, privacy: Privacy) -> ForwardedContext {
+        self.privacy = privacy;
+        self
+    }
+
+    /// Adds the hop to the headers, extending the values already present.
+    pub(crate) fn apply(&self, headers: &mut HeaderMap) -> crate::Result<()> {
+        let (client, by) = match self.privacy {
+            Privacy::Full => (self.client.clone(), self.by.clone()),
+            Privacy::Obfuscated => (
+                self.client.obfuscate(),
+                self.by.as_ref().map(Identifier::obfuscate),
+            ),
+        };
+
+        if self.mode != Mode::Legacy {
+            let element = ForwardedElement {
+                forwarded_for: Some(client.clone()),
+                by,
+                host: self.host.clone(),
+                proto: self.proto.clone(),
+            };
+            for node in [&element.forwarded_for, &element.by].into_iter().flatten() {
+                node.check()?;
+            }
+            append_list(headers, &FORWARDED, &element.to_string())?;
+        }
+
+        if self.mode != Mode::Standard {
+            if let Some(ip) = client.ip() {
+                append_list(headers, &X_FORWARDED_FOR, &ip.to_string())?;
+            }
+
+            // Set by the first proxy, which saw the original request
+            if let Some(ref host) = self.host {
+                let host = HeaderValue::try_from(host.as_str()).map_err(Error::builder)?;
+                headers.entry(X_FORWARDED_HOST.clone()).or_insert(host);
+            }
+            if let Some(ref proto) = self.proto {
+                let proto = HeaderValue::try_from(proto.as_str()).map_err(Error::builder)?;
+                headers.entry(X_FORWARDED_PROTO.clone()).or_insert(proto);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The hops of a `Forwarded` header, from the original client to the latest proxy.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Forwarded {
+    elements: Vec<ForwardedElement>,
+}
+
+impl Forwarded {
+    /// Parses a `Forwarded` header value.
+    pub fn parse(value: &HeaderValue) -> Result<Forwarded, ParseError> {
+        Forwarded::parse_all([value])
+    }
+
+    /// Parses every value of a `Forwarded` header, in order.
+    pub fn parse_all<'a, I>(values: I) -> Result<Forwarded, ParseError>
+    where
+        I: IntoIterator<Item = &'a HeaderValue>,
+    {
+        let mut elements = Vec::new();
+        for value in values {
+            let value = value.to_str().map_err(|_| ParseError(()))?;
+            parse_elements(value, &mut elements)?;
+        }
+        Ok(Forwarded { elements })
+    }
+
+    /// Returns the hops, from the original client to the latest proxy.
+    pub fn elements(&self) -> &[ForwardedElement] {
+        &self.elements
+    }
+}
+
+impl fmt::Display for Forwarded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, element) in self.elements.iter().enumerate() {
+            if idx > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{element}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A single hop of a `Forwarded` header.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ForwardedElement {
+    forwarded_for: Option<Identifier>,
+    by: Option<Identifier>,
+    host: Option<String>,
+    proto: Option<String>,
+}
+
+impl ForwardedElement {
+    /// Returns the client that made the request to the proxy (the `for` parameter).
+    pub fn forwarded_for(&self) -> Option<&Identifier> {
+        self.forwarded_for.as_ref()
+    }
+
+    /// Returns the interface the proxy received the request on (the `by` parameter).
+    pub fn by(&self) -> Option<&Identifier> {
+        self.by.as_ref()
+    }
+
+    /// Returns the `Host` of the request received by the proxy.
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    /// Returns the protocol of the request received by the proxy.
+    pub fn proto(&self) -> Option<&str> {
+        self.proto.as_deref()
+    }
+}
+
+impl fmt::Display for ForwardedElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params = [
+            ("for", self.forwarded_for.as_ref().map(ToString::to_string)),
+            ("by", self.by.as_ref().map(ToString::to_string)),
+            ("host", self.host.clone()),
+            ("proto", self.proto.clone()),
+        ];
+
+        let mut first = true;
+        for (name, value) in params {
+            if let Some(value) = value {
+                if !first {
+                    f.write_str(";")?;
+                }
+                first = false;
+                write!(f, "{name}=")?;
+                write_value(f, &value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An invalid `Forwarded` header or node.
+#[derive(Debug)]
+pub struct ParseError(());
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid Forwarded header")
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn is_tchar(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+/// Returns whether `s` is an obfuscated node name or port.
+fn is_obfuscated(s: &str) -> bool {
+    s.strip_prefix('_').is_some_and(|rest| {
+        !rest.is_empty()
+            && rest
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+    })
+}
+
+/// Writes a parameter value as a token, or as a quoted string if it isn't one.
+fn write_value(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    if !value.is_empty() && value.chars().all(is_tchar) {
+        return f.write_str(value);
+    }
+
+    f.write_str("\"")?;
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            f.write_str("\\")?;
+        }
+        write!(f, "{c}")?;
+    }
+    f.write_str("\"")
+}
+
+/// Appends `value` to a comma-separated list header, merging its existing values.
+fn append_list(headers: &mut HeaderMap, name: &HeaderName, value: &str) -> crate::Result<()> {
+    let mut list = String::new();
+    for existing in headers.get_all(name) {
+        let existing = existing.to_str().map_err(Error::builder)?.trim();
+        if !existing.is_empty() {
+            list.push_str(existing);
+            list.push_str(", ");
+        }
+    }
+    list.push_str(value);
+
+    let value = HeaderValue::try_from(list).map_err(Error::builder)?;
+    headers.insert(name.clone(), value);
+    Ok(())
+}
+
+/// Parses the comma-separated elements of a header value.
+fn parse_elements(mut s: &str, elements: &mut Vec<ForwardedElement>) -> Result<(), ParseError> {
+    fn trim(s: &str) -> &str {
+        s.trim_start_matches([' ', '\t'])
+    }
+
+    let mut element = ForwardedElement::default();
+    let mut has_params = false;
+    loop {
+        s = trim(s);
+
+        // Empty list elements are allowed
+        if let Some(rest) = s.strip_prefix(',') {
+            if has_params {
+                elements.push(std::mem::take(&mut element));
+                has_params = false;
+            }
+            s = rest;
+            continue;
+        }
+        if s.is_empty() {
+            if has_params {
+                elements.push(element);
+            }
+            return Ok(());
+        }
+        if has_params {
+            s = s.strip_prefix(';').map(trim).ok_or(ParseError(()))?;
+        }
+
+        let name_len = s.find(|c| !is_tchar(c)).unwrap_or(s.len());
+        let (name, rest) = s.split_at(name_len);
+        let rest = rest.strip_prefix('=').ok_or(ParseError(()))?;
+        if name.is_empty() {
+            return Err(ParseError(()));
+        }
+
+        let (value, rest) = match rest.strip_prefix('"') {
+            Some(rest) => parse_quoted(rest)?,
+            None => {
+                let len = rest.find(|c| !is_tchar(c)).unwrap_or(rest.len());
+                (rest[..len].to_owned(), &rest[len..])
+            }
+        };
+        s = rest;
+
+        // Each parameter occurs at most once per element, unknown ones are ignored
+        let duplicate = if name.eq_ignore_ascii_case("for") {
+            element.forwarded_for.replace(value.parse()?).is_some()
+        } else if name.eq_ignore_ascii_case("by") {
+            element.by.replace(value.parse()?).is_some()
+        } else if name.eq_ignore_ascii_case("host") {
+            element.host.replace(value).is_some()
+        } else if name.eq_ignore_ascii_case("proto") {
+            element.proto.replace(value).is_some()
+        } else {
+            false
+        };
+        if duplicate {
+            return Err(ParseError(()));
+        }
+        has_params = true;
+    }
+}
+
+/// Parses a quoted string after its opening quote, returning it unescaped and the rest.
+fn parse_quoted(s: &str) -> Result<(String, &str), ParseError> {
+    let mut value = String::new();
+    let mut chars = s.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, &s[idx + 1..])),
+            '\\' => value.push(chars.next().ok_or(ParseError(()))?.1),
+            c => value.push(c),
+        }
+    }
+    Err(ParseError(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(headers: &mut HeaderMap, context: ForwardedContext) {
+        context.apply(headers).unwrap();
+    }
+
+    #[test]
+    fn ipv6_nodes_are_bracketed_and_quoted() {
+        let mut headers = HeaderMap::new();
+        apply(
+            &mut headers,
+            ForwardedContext::new("2001:db8:cafe::17".parse::<IpAddr>().unwrap())
+                .by("[2001:db8::1]:8443".parse::<SocketAddr>().unwrap())
+                .host("[::1]:8080")
+                .proto("https")
+                .mode(Mode::Both),
+        );
+
+        assert_eq!(
+            headers[FORWARDED],
+            r#"for="[2001:db8:cafe::17]";by="[2001:db8::1]:8443";host="[::1]:8080";proto=https"#
+        );
+        assert_eq!(headers[&X_FORWARDED_FOR], "2001:db8:cafe::17");
+        assert_eq!(headers[&X_FORWARDED_HOST], "[::1]:8080");
+        assert_eq!(headers[&X_FORWARDED_PROTO], "https");
+    }
+
+    #[test]
+    fn existing_values_are_extended() {
+        let mut headers = HeaderMap::new();
+        headers.append(FORWARDED, HeaderValue::from_static("for=192.0.2.43"));
+        headers.append(FORWARDED, HeaderValue::from_static("for=198.51.100.17"));
+        headers.append(X_FORWARDED_FOR, HeaderValue::from_static("192.0.2.43"));
+        headers.append(X_FORWARDED_PROTO, HeaderValue::from_static("http"));
+
+        apply(
+            &mut headers,
+            ForwardedContext::new("203.0.113.60:4711".parse::<SocketAddr>().unwrap())
+                .proto("https")
+                .mode(Mode::Both),
+        );
+
+        assert_eq!(
+            headers.get_all(FORWARDED).iter().collect::<Vec<_>>(),
+            [r#"for=192.0.2.43, for=198.51.100.17, for="203.0.113.60:4711";proto=https"#]
+        );
+        assert_eq!(headers[&X_FORWARDED_FOR], "192.0.2.43, 203.0.113.60");
+        assert_eq!(headers[&X_FORWARDED_PROTO], "http");
+    }
+
+    #[test]
+    fn obfuscated_privacy_hides_addresses() {
+        let mut headers = HeaderMap::new();
+        apply(
+            &mut headers,
+            ForwardedContext::new("192.0.2.43".parse::<IpAddr>().unwrap())
+                .by("198.51.100.1".parse::<IpAddr>().unwrap())
+                .mode(Mode::Both)
+                .privacy(Privacy::Obfuscated),
+        );
+
+        let value = headers[FORWARDED].to_str().unwrap();
+        assert!(!value.contains("192.0.2.43") && !value.contains("198.51.100.1"));
+        assert!(headers.get(&X_FORWARDED_FOR).is_none());
+
+        let forwarded = Forwarded::parse(&headers[FORWARDED]).unwrap();
+        let element = &forwarded.elements()[0];
+        assert!(matches!(
+            element.forwarded_for(),
+            Some(Identifier::Obfuscated(_))
+        ));
+        assert!(matches!(element.by(), Some(Identifier::Obfuscated(_))));
+
+        let err = ForwardedContext::new(Identifier::Obfuscated("hidden".into()))
+            .apply(&mut HeaderMap::new())
+            .unwrap_err();
+        assert!(err.is_builder());
+    }
+
+    #[test]
+    fn parses_forwarded_elements() {
+        let value = HeaderValue::from_static(
+            r#"For="[2001:db8:cafe::17]:4711";proto=http;by=_hidden, , for=unknown;HOST="a,\"b\"""#,
+        );
+        let forwarded = Forwarded::parse(&value).unwrap();
+        let [first, second] = forwarded.elements() else {
+            panic!("expected two elements: {forwarded:?}");
+        };
+
+        assert_eq!(
+            first.forwarded_for(),
+            Some(&Identifier::Socket(
+                "[2001:db8:cafe::17]:4711".parse().unwrap()
+            ))
+        );
+        assert_eq!(first.proto(), Some("http"));
+        assert_eq!(first.by(), Some(&Identifier::Obfuscated("_hidden".into())));
+        assert_eq!(second.forwarded_for(), Some(&Identifier::Unknown));
+        assert_eq!(second.host(), Some(r#"a,"b""#));
+
+        assert_eq!(
+            forwarded.to_string(),
+            r#"for="[2001:db8:cafe::17]:4711";by=_hidden;proto=http, for=unknown;host="a,\"b\"""#
+        );
+        assert_eq!(
+            Forwarded::parse(&HeaderValue::from_str(&forwarded.to_string()).unwrap()).unwrap(),
+            forwarded
+        );
+    }
+
+    #[test]
+    fn parses_nodes() {
+        let node = |s: &str| s.parse::<Identifier>();
+        assert_eq!(
+            node("192.0.2.43").unwrap(),
+            Identifier::Ip("192.0.2.43".parse().unwrap())
+        );
+        assert_eq!(
+            node("[2001:db8::1]:_port").unwrap(),
+            Identifier::Ip("2001:db8::1".parse().unwrap())
+        );
+        assert_eq!(
+            node("_gazonk:_port").unwrap(),
+            Identifier::Obfuscated("_gazonk:_port".into())
+        );
+        assert_eq!(node("UNKNOWN").unwrap(), Identifier::Unknown);
+
+        for invalid in [
+            "2001:db8::1",
+            "[2001:db8::1",
+            "example.com",
+            "_",
+            "192.0.2.43:x",
+        ] {
+            assert!(node(invalid).is_err(), "{invalid}");
+        }
+        for invalid in [
+            "for",
+            "for=",
+            "for=192.0.2.43;for=192.0.2.44",
+            "for=\"_a",
+            "=x",
+        ] {
+            let value = HeaderValue::from_static(invalid);
+            assert!(Forwarded::parse(&value).is_err(), "{invalid}");
+        }
+    }
+}
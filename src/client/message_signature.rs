@@ -0,0 +1,265 @@
+//! Signature bases of HTTP message signatures on responses ([RFC 9421]).
+//!
+//! A signed response carries a `Signature-Input` header listing the components the signature
+//! covers, and a `Signature` header with the signature over the signature base derived from
+//! them. [`signature_base`] derives it from a [`RawResponseHead`] captured with
+//! [`RequestBuilder::capture_raw_response_head`], whose fields keep every line of a name, in the
+//! order it was received.
+//!
+//! Only the `@status` derived component and HTTP fields without parameters can be covered, the
+//! other derived components describe the request.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use wreq::{RawResponseHead, message_signature};
+//!
+//! # async fn doc(client: wreq::Client) -> wreq::Result<()> {
+//! let res = client
+//!     .get("https://example.com/")
+//!     .capture_raw_response_head(true)
+//!     .send()
+//!     .await?;
+//! let head = res.extensions().get::<RawResponseHead>().unwrap();
+//!
+//! // the `sig1` member of the `Signature-Input` header of the response
+//! let params = r#"("@status" "content-digest");created=1618884473;keyid="test-key""#;
+//! let base = message_signature::signature_base(head, params)?;
+//! // verify the `sig1` member of the `Signature` header over `base`
+//! # let _ = base;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [RFC 9421]: https://www.rfc-editor.org/rfc/rfc9421
+//! [`RequestBuilder::capture_raw_response_head`]: crate::RequestBuilder::capture_raw_response_head
+
+use crate::{Error, RawResponseHead};
+
+/// Returns the signature base of the response captured in `head`, for the signature parameters
+/// `signature_params`.
+///
+/// `signature_params` is the value of a member of the `Signature-Input` header, the inner list
+/// of the covered components followed by the parameters of the signature, such as
+/// `("@status" "content-type");keyid="test-key"`. It ends the signature base verbatim.
+///
+/// The value of a field is the values of its lines in the order they were received, joined
+/// with `, `, with the whitespace around them and the obsolete line folding removed.
+///
+/// # Errors
+///
+/// Fails if `signature_params` is not an inner list of component identifiers, if a component
+/// is covered twice, has parameters, or is a derived component other than `@status`, if a field
+/// is missing from the response, or if a value is not ASCII.
+pub fn signature_base(head: &RawResponseHead, signature_params: &str) -> crate::Result<String> {
+    build(head, signature_params).map_err(|reason| {
+        Error::builder(format!(
+            "cannot build the signature base for `{signature_params}`: {reason}"
+        ))
+    })
+}
+
+fn build(head: &RawResponseHead, signature_params: &str) -> Result<String, String> {
+    let components = parse_components(signature_params)?;
+
+    let mut base = String::new();
+    for (i, component) in components.iter().enumerate() {
+        if components[..i].contains(component) {
+            return Err(format!("component `{component}` is covered twice"));
+        }
+
+        let value = if component == "@status" {
+            head.status().as_str().to_owned()
+        } else if component.starts_with('@') {
+            return Err(format!(
+                "derived component `{component}` is not available on a response"
+            ));
+        } else if component.bytes().any(|b| b.is_ascii_uppercase()) {
+            return Err(format!("field name `{component}` is not lowercase"));
+        } else {
+            field_value(head, component)?
+        };
+
+        base.push('"');
+        base.push_str(component);
+        base.push_str("\": ");
+        base.push_str(&value);
+        base.push('\n');
+    }
+
+    base.push_str("\"@signature-params\": ");
+    base.push_str(signature_params);
+    Ok(base)
+}
+
+/// Parses the component identifiers of the inner list starting `signature_params`.
+fn parse_components(signature_params: &str) -> Result<Vec<String>, String> {
+    let mut rest = signature_params
+        .strip_prefix('(')
+        .ok_or("the signature parameters do not start with an inner list")?;
+
+    let mut components = Vec::new();
+    loop {
+        rest = rest.trim_start_matches(' ');
+        if let Some(after) = rest.strip_prefix(')') {
+            if !after.is_empty() && !after.starts_with(';') {
+                return Err("unexpected data after the inner list".into());
+            }
+            return Ok(components);
+        }
+
+        let (component, after) = parse_string(rest)?;
+        if after.starts_with(';') {
+            return Err(format!(
+                "parameters of component `{component}` are not supported"
+            ));
+        }
+        if after.is_empty() {
+            return Err("unterminated inner list".into());
+        }
+        if !after.starts_with([' ', ')']) {
+            return Err("component identifiers must be separated by spaces".into());
+        }
+        components.push(component);
+        rest = after;
+    }
+}
+
+/// Parses the structured field string starting `input`, returning it and the rest of `input`.
+fn parse_string(input: &str) -> Result<(String, &str), String> {
+    let mut chars = input
+        .strip_prefix('"')
+        .ok_or("a component identifier is not a string")?
+        .char_indices();
+
+    let mut value = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, &input[i + 2..])),
+            '\\' => match chars.next() {
+                Some((_, c @ ('"' | '\\'))) => value.push(c),
+                _ => return Err("invalid escape in a component identifier".into()),
+            },
+            ' '..='~' => value.push(c),
+            _ => return Err("invalid character in a component identifier".into()),
+        }
+    }
+    Err("unterminated component identifier".into())
+}
+
+/// Returns the canonical value of the field `name` of the response.
+fn field_value(head: &RawResponseHead, name: &str) -> Result<String, String> {
+    let mut value = String::new();
+    let mut found = false;
+    for line in head.get_all(name) {
+        if found {
+            value.push_str(", ");
+        }
+        found = true;
+        push_line(&mut value, line).map_err(|()| format!("field `{name}` is not ASCII"))?;
+    }
+
+    if !found {
+        return Err(format!("field `{name}` is missing from the response"));
+    }
+    Ok(value)
+}
+
+/// Appends the value of a field line, trimmed and unfolded.
+fn push_line(value: &mut String, line: &[u8]) -> Result<(), ()> {
+    let is_ws = |b: &u8| matches!(b, b' ' | b'\t' | b'\r' | b'\n');
+    let start = line.iter().position(|b| !is_ws(b)).unwrap_or(line.len());
+    let end = line
+        .iter()
+        .rposition(|b| !is_ws(b))
+        .map_or(start, |i| i + 1);
+
+    let mut folding = false;
+    for &b in &line[start..end] {
+        match b {
+            b'\r' | b'\n' => folding = true,
+            b' ' | b'\t' if folding => {}
+            b' ' | b'\t' | b'!'..=b'~' => {
+                if folding {
+                    // an obsolete line folding and the whitespace around it make a single space
+                    while value.ends_with([' ', '\t']) {
+                        value.pop();
+                    }
+                    value.push(' ');
+                    folding = false;
+                }
+                value.push(b as char);
+            }
+            _ => return Err(()),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use http::StatusCode;
+
+    use super::*;
+
+    fn head(raw: &'static str) -> RawResponseHead {
+        let mut fields = Vec::new();
+        let mut offset = raw.find("\r\n").unwrap() + 2;
+        for line in raw[offset..].split("\r\n") {
+            let colon = line.find(':').unwrap_or(0);
+            if colon > 0 {
+                let value = line[colon + 1..].trim();
+                let value_start = offset + line.find(value).unwrap();
+                fields.push((
+                    (offset, offset + colon),
+                    (value_start, value_start + value.len()),
+                ));
+            }
+            offset += line.len() + 2;
+        }
+        RawResponseHead::http1(StatusCode::OK, Bytes::from_static(raw.as_bytes()), fields)
+    }
+
+    #[test]
+    fn builds_base_from_status_and_fields() {
+        let head = head(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nX-Dup: a\r\nDate: today\r\n\
+             x-dup:  b \r\n\r\n",
+        );
+        let params = r#"("@status" "x-dup" "content-type");created=1618884473;keyid="test-key""#;
+
+        assert_eq!(
+            signature_base(&head, params).unwrap(),
+            "\"@status\": 200\n\
+             \"x-dup\": a, b\n\
+             \"content-type\": application/json\n\
+             \"@signature-params\": (\"@status\" \"x-dup\" \"content-type\");\
+             created=1618884473;keyid=\"test-key\""
+        );
+    }
+
+    #[test]
+    fn unfolds_obsolete_line_folding() {
+        let mut line = String::new();
+        push_line(&mut line, b"Value with\r\n   obsolete folding ").unwrap();
+        assert_eq!(line, "Value with obsolete folding");
+    }
+
+    #[test]
+    fn rejects_uncoverable_components() {
+        let head = head("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\n");
+        for params in [
+            r#""@status";keyid="k""#,
+            r#"("@status" "@status")"#,
+            r#"("@method")"#,
+            r#"("content-type";sf)"#,
+            r#"("Content-Type")"#,
+            r#"("digest")"#,
+            r#"("@status""#,
+        ] {
+            assert!(signature_base(&head, params).is_err(), "{params}");
+        }
+        assert!(signature_base(&head, "()").is_ok());
+    }
+}
@@ -1,6 +1,8 @@
 use std::time::Duration;
 
-use crate::{core::ext::RequestConfigValue, redirect::Policy};
+use http::HeaderName;
+
+use crate::{ExpectedDigest, core::ext::RequestConfigValue, redirect::Policy};
 
 // ================================
 //
@@ -9,21 +11,24 @@ use crate::{core::ext::RequestConfigValue, redirect::Policy};
 //
 // To add a new config:
 //
-// 1. create a new struct for the config key like `RequestTimeout`.
+// 1. create a new struct for the config key like `RequestTotalTimeout`.
 // 2. implement `RequestConfigValue` for the struct, the `Value` is the config value's type.
 //
 // ================================
 
 #[derive(Clone, Copy)]
-pub(crate) struct RequestTimeout;
+pub(crate) struct RequestTotalTimeout;
 
-impl RequestConfigValue for RequestTimeout {
+impl RequestConfigValue for RequestTotalTimeout {
     type Value = Duration;
 }
 
-pub(crate) type RequestTotalTimeout = RequestTimeout;
+#[derive(Clone, Copy)]
+pub(crate) struct RequestReadTimeout;
 
-pub(crate) type RequestReadTimeout = RequestTimeout;
+impl RequestConfigValue for RequestReadTimeout {
+    type Value = Duration;
+}
 
 #[derive(Clone, Copy)]
 pub(crate) struct RequestRedirectPolicy;
@@ -50,8 +55,51 @@ impl RequestConfigValue for RequestAcceptEncoding {
     type Value = crate::client::middleware::decoder::AcceptEncoding;
 }
 
+#[cfg(any(
+    feature = "gzip",
+    feature = "zstd",
+    feature = "brotli",
+    feature = "deflate",
+))]
+#[derive(Clone, Copy)]
+pub(crate) struct RequestSalvagePartialBody;
+
+#[cfg(any(
+    feature = "gzip",
+    feature = "zstd",
+    feature = "brotli",
+    feature = "deflate",
+))]
+impl RequestConfigValue for RequestSalvagePartialBody {
+    type Value = bool;
+}
+
 #[derive(Clone, Copy)]
 pub(crate) struct RequestSkipDefaultHeaders;
 impl RequestConfigValue for RequestSkipDefaultHeaders {
     type Value = bool;
 }
+
+#[derive(Clone, Copy)]
+pub(crate) struct RequestRemovedHeaders;
+impl RequestConfigValue for RequestRemovedHeaders {
+    type Value = Vec<HeaderName>;
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct RequestThinkTime;
+impl RequestConfigValue for RequestThinkTime {
+    type Value = bool;
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct RequestExpectedDigest;
+impl RequestConfigValue for RequestExpectedDigest {
+    type Value = ExpectedDigest;
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct RequestHeadersHook;
+impl RequestConfigValue for RequestHeadersHook {
+    type Value = crate::client::middleware::headers_hook::HeadersHook;
+}
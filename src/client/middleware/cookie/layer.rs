@@ -8,7 +8,10 @@ use tower::Layer;
 use tower_service::Service;
 
 use super::future::ResponseFuture;
-use crate::cookie::CookieStore;
+use crate::{
+    cookie::CookieStore,
+    core::ext::{RequestConfig, RequestIpv6Zone},
+};
 
 /// Layer to apply [`CookieManager`] middleware.
 #[derive(Clone)]
@@ -60,7 +63,13 @@ where
             // Try to extract the request URL.
             let mut url = None;
             if req.headers().get(COOKIE).is_none() {
-                url = url::Url::parse(&req.uri().to_string()).ok();
+                url = url::Url::parse(&req.uri().to_string()).ok().map(|url| {
+                    // the cookies of the zones of an address are kept apart
+                    match RequestConfig::<RequestIpv6Zone>::get(req.extensions()) {
+                        Some(zone) => zone.cookie_url(&url),
+                        None => url,
+                    }
+                });
 
                 if let Some(ref url) = url {
                     let headers = req.headers_mut();
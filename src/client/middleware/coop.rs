@@ -0,0 +1,159 @@
+//! Middleware yielding to the runtime while response bodies are read.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use bytes::Buf;
+use http::{Request, Response};
+use http_body::{Body, Frame, SizeHint};
+use pin_project_lite::pin_project;
+use tower::Layer;
+use tower_service::Service;
+
+type Yield = Pin<Box<dyn Future<Output = ()> + Send + Sync>>;
+
+/// Layer to apply [`Coop`] middleware.
+#[derive(Clone, Copy)]
+pub struct CoopLayer {
+    budget: usize,
+}
+
+impl CoopLayer {
+    /// Create a new layer yielding after `budget` bytes, or never if it is zero.
+    pub const fn new(budget: usize) -> Self {
+        CoopLayer { budget }
+    }
+}
+
+impl<S> Layer<S> for CoopLayer {
+    type Service = Coop<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Coop {
+            inner,
+            budget: self.budget,
+        }
+    }
+}
+
+/// Middleware wrapping response bodies in a [`CoopBody`].
+#[derive(Clone)]
+pub struct Coop<S> {
+    inner: S,
+    budget: usize,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for Coop<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = Response<CoopBody<ResBody>>;
+    type Error = S::Error;
+    type Future = CoopFuture<S::Future>;
+
+    #[inline(always)]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        CoopFuture {
+            inner: self.inner.call(req),
+            budget: self.budget,
+        }
+    }
+}
+
+pin_project! {
+    /// Response future of [`Coop`].
+    pub struct CoopFuture<F> {
+        #[pin]
+        inner: F,
+        budget: usize,
+    }
+}
+
+impl<F, B, E> Future for CoopFuture<F>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+{
+    type Output = Result<Response<CoopBody<B>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.inner.poll(cx))?;
+        let budget = *this.budget;
+        Poll::Ready(Ok(res.map(|body| CoopBody {
+            body,
+            budget,
+            remaining: budget,
+            yielding: None,
+        })))
+    }
+}
+
+pin_project! {
+    /// A body yielding to the runtime once it returned `budget` bytes in a row without
+    /// waiting for more.
+    ///
+    /// A body that is always ready, because its data arrives faster than it is read, would
+    /// otherwise keep the task reading it running, and starve the other tasks of its worker.
+    pub struct CoopBody<B> {
+        #[pin]
+        body: B,
+        budget: usize,
+        remaining: usize,
+        yielding: Option<Yield>,
+    }
+}
+
+impl<B> Body for CoopBody<B>
+where
+    B: Body,
+{
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        if let Some(yielding) = this.yielding.as_mut() {
+            ready!(yielding.as_mut().poll(cx));
+            *this.yielding = None;
+        }
+
+        match this.body.poll_frame(cx) {
+            Poll::Ready(Some(Ok(frame))) => {
+                if let (Some(data), true) = (frame.data_ref(), *this.budget > 0) {
+                    *this.remaining = this.remaining.saturating_sub(data.remaining());
+                    if *this.remaining == 0 {
+                        // Yield on the next poll, the runtime runs other tasks in between
+                        *this.remaining = *this.budget;
+                        *this.yielding = Some(Box::pin(tokio::task::yield_now()));
+                    }
+                }
+                Poll::Ready(Some(Ok(frame)))
+            }
+            Poll::Pending => {
+                *this.remaining = *this.budget;
+                Poll::Pending
+            }
+            res => res,
+        }
+    }
+
+    #[inline(always)]
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+
+    #[inline(always)]
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+}
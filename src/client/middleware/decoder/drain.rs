@@ -0,0 +1,120 @@
+//! Draining a body as it was sent, without decoding it.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Context, Poll, ready},
+};
+
+use bytes::{Buf, Bytes};
+use http::Response;
+use http_body::{Body, Frame, SizeHint};
+use pin_project_lite::pin_project;
+use tower_http::decompression::DecompressionBody;
+
+use crate::error::BoxError;
+
+/// Switches a [`DrainBody`] to bypass its decoder, carried in the response extensions.
+#[derive(Clone, Default)]
+pub(crate) struct DrainSwitch(Arc<AtomicBool>);
+
+impl DrainSwitch {
+    /// Reads the rest of the body as it was sent, skipping its decompression.
+    pub(crate) fn bypass(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+
+    fn is_bypassed(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+pin_project! {
+    /// A decompressed body that can be drained without decoding it.
+    ///
+    /// Bodies that are discarded anyway are read as they were sent, so no decompression work is
+    /// spent on them and their length is counted in wire bytes.
+    pub struct DrainBody<B> {
+        #[pin]
+        body: DecompressionBody<B>,
+        switch: DrainSwitch,
+    }
+}
+
+impl<B> Body for DrainBody<B>
+where
+    B: Body,
+    B::Error: Into<BoxError>,
+{
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        if !this.switch.is_bypassed() {
+            return this.body.poll_frame(cx);
+        }
+
+        match ready!(this.body.get_pin_mut().poll_frame(cx)) {
+            Some(Ok(frame)) => Poll::Ready(Some(Ok(
+                frame.map_data(|mut data| data.copy_to_bytes(data.remaining()))
+            ))),
+            Some(Err(err)) => Poll::Ready(Some(Err(err.into()))),
+            None => Poll::Ready(None),
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        if self.switch.is_bypassed() {
+            self.body.get_ref().size_hint()
+        } else {
+            self.body.size_hint()
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        if self.switch.is_bypassed() {
+            self.body.get_ref().is_end_stream()
+        } else {
+            self.body.is_end_stream()
+        }
+    }
+}
+
+pin_project! {
+    /// Response future wrapping the body in a [`DrainBody`].
+    pub struct DrainFuture<F> {
+        #[pin]
+        inner: F,
+    }
+}
+
+impl<F> DrainFuture<F> {
+    pub(super) fn new(inner: F) -> Self {
+        DrainFuture { inner }
+    }
+}
+
+impl<F, B, E> Future for DrainFuture<F>
+where
+    F: Future<Output = Result<Response<DecompressionBody<B>>, E>>,
+    B: Body,
+{
+    type Output = Result<Response<DrainBody<B>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut res = ready!(self.project().inner.poll(cx))?;
+
+        let switch = DrainSwitch::default();
+        res.extensions_mut().insert(switch.clone());
+
+        Poll::Ready(Ok(res.map(|body| DrainBody { body, switch })))
+    }
+}
@@ -3,13 +3,14 @@ use std::task::{Context, Poll};
 use http::{Request, Response};
 use http_body::Body;
 use tower::Layer;
-use tower_http::decompression::{
-    Decompression as TowerDecompression, DecompressionBody, ResponseFuture,
-};
+use tower_http::decompression::{Decompression as TowerDecompression, ResponseFuture};
 use tower_service::Service;
 
-use super::AcceptEncoding;
-use crate::{client::middleware::config::RequestAcceptEncoding, core::ext::RequestConfig};
+use super::{AcceptEncoding, DrainBody, DrainFuture, SalvageBody, SalvageFuture};
+use crate::{
+    client::middleware::config::{RequestAcceptEncoding, RequestSalvagePartialBody},
+    core::ext::RequestConfig,
+};
 
 /// Decompresses response bodies of the underlying service.
 ///
@@ -40,7 +41,9 @@ impl<S> Layer<S> for DecompressionLayer {
 /// Decompresses response bodies of the underlying service.
 ///
 /// This adds the `Accept-Encoding` header to requests and transparently decompresses response
-/// bodies based on the `Content-Encoding` header.
+/// bodies based on the `Content-Encoding` header. Requests may opt into salvaging the data
+/// decoded before a decompression error, see [`SalvageBody`], and drained bodies skip their
+/// decompression, see [`DrainBody`].
 #[derive(Clone)]
 pub struct Decompression<S> {
     decoder: TowerDecompression<S>,
@@ -81,9 +84,9 @@ where
     ReqBody: Body,
     ResBody: Body,
 {
-    type Response = Response<DecompressionBody<ResBody>>;
+    type Response = Response<SalvageBody<DrainBody<ResBody>>>;
     type Error = S::Error;
-    type Future = ResponseFuture<S::Future>;
+    type Future = SalvageFuture<DrainFuture<ResponseFuture<S::Future>>>;
 
     #[inline(always)]
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
@@ -97,6 +100,9 @@ where
             std::mem::swap(&mut self.decoder, &mut decoder);
         }
 
-        self.decoder.call(req)
+        let salvage = RequestConfig::<RequestSalvagePartialBody>::get(req.extensions())
+            .copied()
+            .unwrap_or(false);
+        SalvageFuture::new(DrainFuture::new(self.decoder.call(req)), salvage)
     }
 }
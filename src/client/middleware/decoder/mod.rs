@@ -1,8 +1,14 @@
 //! Middleware for decoding
 
+mod drain;
 mod layer;
+mod salvage;
 
+pub(crate) use drain::DrainSwitch;
+pub use drain::{DrainBody, DrainFuture};
 pub use layer::{Decompression, DecompressionLayer};
+pub(crate) use salvage::SalvageSlot;
+pub use salvage::{PartialBody, SalvageBody, SalvageFuture};
 
 #[derive(Clone, Debug)]
 pub(crate) struct AcceptEncoding {
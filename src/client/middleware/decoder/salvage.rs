@@ -0,0 +1,153 @@
+//! Salvaging the data decoded before a decompression error.
+
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, ready},
+};
+
+use bytes::Buf;
+use http::Response;
+use http_body::{Body, Frame, SizeHint};
+use pin_project_lite::pin_project;
+
+use crate::{Error, error::BoxError, sync::Mutex};
+
+/// Response extension describing a body that ended early, after its decompression failed.
+///
+/// Only attached when the request enabled
+/// [`salvage_partial_body`](crate::RequestBuilder::salvage_partial_body), once the body has been
+/// read to its end with [`Response::chunk`](crate::Response::chunk). The body then ends with the
+/// data decoded before the error, and the connection it was read from is not reused.
+#[derive(Debug, Clone)]
+pub struct PartialBody {
+    decoded_bytes: u64,
+    error: Arc<Error>,
+}
+
+impl PartialBody {
+    /// Returns the number of bytes decoded before the error.
+    pub fn decoded_bytes(&self) -> u64 {
+        self.decoded_bytes
+    }
+
+    /// Returns the error the decoder failed with.
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+}
+
+/// Where a [`SalvageBody`] leaves its [`PartialBody`], carried in the response extensions.
+#[derive(Clone, Default)]
+pub(crate) struct SalvageSlot(Arc<Mutex<Option<PartialBody>>>);
+
+impl SalvageSlot {
+    /// Takes the salvaged body, if the body ended early.
+    pub(crate) fn take(&self) -> Option<PartialBody> {
+        self.0.lock().take()
+    }
+}
+
+pin_project! {
+    /// A body ending normally on a decompression error, if salvaging was enabled.
+    pub struct SalvageBody<B> {
+        #[pin]
+        body: Option<B>,
+        decoded: u64,
+        slot: Option<SalvageSlot>,
+    }
+}
+
+impl<B> Body for SalvageBody<B>
+where
+    B: Body<Error = BoxError>,
+{
+    type Data = B::Data;
+    type Error = BoxError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let mut this = self.project();
+        let Some(body) = this.body.as_mut().as_pin_mut() else {
+            return Poll::Ready(None);
+        };
+
+        match ready!(body.poll_frame(cx)) {
+            Some(Ok(frame)) => {
+                if let Some(data) = frame.data_ref() {
+                    *this.decoded += data.remaining() as u64;
+                }
+                Poll::Ready(Some(Ok(frame)))
+            }
+            // Errors of the underlying body are passed through as they are,
+            // the decoder fails with an `io::Error`.
+            Some(Err(err)) if *this.decoded > 0 && err.is::<io::Error>() => match this.slot {
+                Some(slot) => {
+                    *slot.0.lock() = Some(PartialBody {
+                        decoded_bytes: *this.decoded,
+                        error: Arc::new(Error::decode(err)),
+                    });
+
+                    // Dropping the body closes a connection that was not read to its end.
+                    this.body.set(None);
+                    Poll::Ready(None)
+                }
+                None => Poll::Ready(Some(Err(err))),
+            },
+            res => Poll::Ready(res),
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body
+            .as_ref()
+            .map(Body::size_hint)
+            .unwrap_or_else(|| SizeHint::with_exact(0))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.as_ref().is_none_or(Body::is_end_stream)
+    }
+}
+
+pin_project! {
+    /// Response future wrapping the body in a [`SalvageBody`].
+    pub struct SalvageFuture<F> {
+        #[pin]
+        inner: F,
+        salvage: bool,
+    }
+}
+
+impl<F> SalvageFuture<F> {
+    pub(super) fn new(inner: F, salvage: bool) -> Self {
+        SalvageFuture { inner, salvage }
+    }
+}
+
+impl<F, B, E> Future for SalvageFuture<F>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+{
+    type Output = Result<Response<SalvageBody<B>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut res = ready!(this.inner.poll(cx))?;
+
+        let slot = this.salvage.then(SalvageSlot::default);
+        if let Some(ref slot) = slot {
+            res.extensions_mut().insert(slot.clone());
+        }
+
+        Poll::Ready(Ok(res.map(|body| SalvageBody {
+            body: Some(body),
+            decoded: 0,
+            slot,
+        })))
+    }
+}
@@ -0,0 +1,119 @@
+//! Middleware letting an emulation compute the headers of each request from its url, and
+//! removing the headers a request asked not to send.
+
+use std::{
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use http::{HeaderMap, Method, Request};
+use tower::Layer;
+use tower_service::Service;
+use url::Url;
+
+use crate::{
+    client::middleware::config::{
+        RequestHeadersHook, RequestRemovedHeaders, RequestSkipDefaultHeaders,
+    },
+    core::ext::RequestConfig,
+};
+
+/// A hook adjusting the headers of a request, see
+/// [`EmulationProviderBuilder::headers_hook`](crate::EmulationProviderBuilder::headers_hook).
+#[derive(Clone)]
+pub(crate) struct HeadersHook(pub(crate) Arc<dyn Fn(&Url, &Method, &mut HeaderMap) + Send + Sync>);
+
+impl fmt::Debug for HeadersHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("HeadersHook")
+    }
+}
+
+/// Layer to apply [`HeadersHookService`] middleware.
+#[derive(Clone)]
+pub struct HeadersHookLayer {
+    default_headers: Arc<HeaderMap>,
+    hook: RequestConfig<RequestHeadersHook>,
+}
+
+impl HeadersHookLayer {
+    /// Create a new layer running `hook` on the requests without a hook of their own, after
+    /// merging `default_headers` into them.
+    pub(crate) fn new(default_headers: HeaderMap, hook: Option<HeadersHook>) -> Self {
+        HeadersHookLayer {
+            default_headers: Arc::new(default_headers),
+            hook: RequestConfig::new(hook),
+        }
+    }
+}
+
+impl<S> Layer<S> for HeadersHookLayer {
+    type Service = HeadersHookService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HeadersHookService {
+            inner,
+            default_headers: self.default_headers.clone(),
+            hook: self.hook.clone(),
+        }
+    }
+}
+
+/// Middleware running the headers hook of a request on every hop, then removing the headers
+/// of [`RequestBuilder::remove_header`](crate::RequestBuilder::remove_header), before its
+/// cookies and `Accept-Encoding` are added.
+#[derive(Clone)]
+pub struct HeadersHookService<S> {
+    inner: S,
+    default_headers: Arc<HeaderMap>,
+    hook: RequestConfig<RequestHeadersHook>,
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for HeadersHookService<S>
+where
+    S: Service<Request<ReqBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline(always)]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let hook = self.hook.fetch(req.extensions()).cloned();
+        if let Some(hook) = hook {
+            // the hook sees the default headers, which are otherwise merged by the client
+            // service, after the cookies and `Accept-Encoding`
+            let skip = RequestConfig::<RequestSkipDefaultHeaders>::get(req.extensions()).copied()
+                == Some(true);
+            if !skip {
+                let headers = req.headers_mut();
+                for name in self.default_headers.keys() {
+                    if !headers.contains_key(name) {
+                        for value in self.default_headers.get_all(name) {
+                            headers.append(name, value.clone());
+                        }
+                    }
+                }
+            }
+
+            if let Ok(url) = Url::parse(&req.uri().to_string()) {
+                let method = req.method().clone();
+                (hook.0)(&url, &method, req.headers_mut());
+            }
+        }
+
+        // the headers set on the request, by its emulation or by the hook, the client service
+        // leaves the removed ones out of the default headers
+        let removed = RequestConfig::<RequestRemovedHeaders>::get(req.extensions()).cloned();
+        for name in removed.iter().flatten() {
+            req.headers_mut().remove(name);
+        }
+
+        self.inner.call(req)
+    }
+}
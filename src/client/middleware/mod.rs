@@ -3,6 +3,7 @@
 pub mod config;
 #[cfg(feature = "cookies")]
 pub mod cookie;
+pub mod coop;
 #[cfg(any(
     feature = "gzip",
     feature = "zstd",
@@ -10,6 +11,10 @@ pub mod cookie;
     feature = "deflate",
 ))]
 pub mod decoder;
+pub mod headers_hook;
 pub mod redirect;
 pub mod retry;
+pub mod think_time;
 pub mod timeout;
+#[cfg(feature = "vcr")]
+pub mod vcr;
@@ -20,6 +20,7 @@ use super::{
     BodyRepr, RequestUri,
     policy::{Action, Attempt, Policy},
 };
+use crate::client::RedirectHop;
 
 pin_project! {
     /// Response future for [`FollowRedirectLayer`].
@@ -130,6 +131,9 @@ where
                         *uri = location;
                         body.try_clone_from(&take_body, &policy);
 
+                        let hop = extensions.get::<RedirectHop>().map_or(0, |hop| hop.0);
+                        extensions.insert(RedirectHop(hop.saturating_add(1)));
+
                         let mut req = Request::new(take_body);
                         *req.uri_mut() = uri.clone();
                         *req.method_mut() = method.clone();
@@ -0,0 +1,203 @@
+//! A retry budget shared by the requests of a client.
+
+use std::{
+    fmt,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::sync::Mutex;
+
+/// Number of slots the window of a budget is divided into.
+const SLOTS: usize = 10;
+
+/// Fixed-point scale of the balance, where a retry costs `SCALE`.
+const SCALE: i64 = 1000;
+
+/// A budget limiting retries to a share of the successful requests of a client.
+///
+/// Every request that succeeds on its first attempt deposits `retry_ratio` into the budget,
+/// and every retry withdraws one. On top of the deposits, `min_per_sec` retries per second
+/// are always allowed, so clients with little traffic can still retry. Deposits and
+/// withdrawals expire after `ttl`.
+///
+/// The budget is a handle: clones share their state, so one budget can be shared by several
+/// clients, and a clone kept by the caller observes the retries refused.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// // Retry at most 20% of requests, plus 1 retry per second.
+/// let budget = wreq::RetryBudget::new(Duration::from_secs(10), 1, 0.2);
+/// let client = wreq::Client::builder().retry_budget(budget.clone()).build()?;
+/// # let _ = client;
+/// println!("retries refused: {}", budget.exhausted());
+/// # Ok::<(), wreq::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct RetryBudget {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    reserve: i64,
+    deposit: i64,
+    slot_len: Duration,
+    window: Mutex<Window>,
+    exhausted: AtomicU64,
+}
+
+/// Balance changes over the last `ttl`, by slot.
+struct Window {
+    slots: [i64; SLOTS],
+    idx: usize,
+    slot_start: Option<Instant>,
+}
+
+impl RetryBudget {
+    /// Creates a budget allowing `min_per_sec` retries per second, plus `retry_ratio` retries
+    /// per successful request, over a sliding window of `ttl`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ttl` is zero, or `retry_ratio` is negative or not finite.
+    pub fn new(ttl: Duration, min_per_sec: u32, retry_ratio: f32) -> RetryBudget {
+        assert!(!ttl.is_zero(), "retry budget ttl must not be zero");
+        assert!(
+            retry_ratio.is_finite() && retry_ratio >= 0.0,
+            "retry budget ratio must be a non-negative number"
+        );
+
+        RetryBudget {
+            inner: Arc::new(Inner {
+                reserve: (f64::from(min_per_sec) * ttl.as_secs_f64() * SCALE as f64) as i64,
+                deposit: (f64::from(retry_ratio) * SCALE as f64) as i64,
+                slot_len: ttl / SLOTS as u32,
+                window: Mutex::new(Window {
+                    slots: [0; SLOTS],
+                    idx: 0,
+                    slot_start: None,
+                }),
+                exhausted: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Returns the number of retries refused because the budget was exhausted.
+    pub fn exhausted(&self) -> u64 {
+        self.inner.exhausted.load(Ordering::Relaxed)
+    }
+
+    /// Records a request that succeeded on its first attempt.
+    pub(crate) fn deposit(&self) {
+        self.deposit_at(Instant::now());
+    }
+
+    /// Takes a retry from the budget, returning `false` if none is left.
+    pub(crate) fn withdraw(&self) -> bool {
+        self.withdraw_at(Instant::now())
+    }
+
+    fn deposit_at(&self, now: Instant) {
+        let mut window = self.inner.window.lock();
+        window.advance(now, self.inner.slot_len);
+        let idx = window.idx;
+        window.slots[idx] += self.inner.deposit;
+    }
+
+    fn withdraw_at(&self, now: Instant) -> bool {
+        let mut window = self.inner.window.lock();
+        window.advance(now, self.inner.slot_len);
+
+        let balance = self.inner.reserve + window.slots.iter().sum::<i64>();
+        if balance >= SCALE {
+            let idx = window.idx;
+            window.slots[idx] -= SCALE;
+            true
+        } else {
+            self.inner.exhausted.fetch_add(1, Ordering::Relaxed);
+            false
+        }
+    }
+}
+
+impl Window {
+    /// Moves to the slot of `now`, clearing the slots that expired on the way.
+    fn advance(&mut self, now: Instant, slot_len: Duration) {
+        let start = *self.slot_start.get_or_insert(now);
+        let passed = now.saturating_duration_since(start).as_nanos() / slot_len.as_nanos().max(1);
+        if passed == 0 {
+            return;
+        }
+
+        for _ in 0..passed.min(SLOTS as u128) {
+            self.idx = (self.idx + 1) % SLOTS;
+            self.slots[self.idx] = 0;
+        }
+
+        self.slot_start = Some(if passed >= SLOTS as u128 {
+            now
+        } else {
+            start + slot_len * passed as u32
+        });
+    }
+}
+
+impl fmt::Debug for RetryBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryBudget")
+            .field("exhausted", &self.exhausted())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_follow_ratio_of_deposits() {
+        let budget = RetryBudget::new(Duration::from_secs(10), 0, 0.2);
+        let start = Instant::now();
+
+        // a burst of failures before any success is refused
+        assert!(!budget.withdraw_at(start));
+
+        for i in 0..100 {
+            budget.deposit_at(start + Duration::from_millis(i * 10));
+        }
+        let now = start + Duration::from_secs(1);
+        let allowed = (0..100).filter(|_| budget.withdraw_at(now)).count();
+        assert_eq!(allowed, 20);
+        assert_eq!(budget.exhausted(), 81);
+
+        // deposits and withdrawals expire together once the window has passed
+        let later = start + Duration::from_secs(12);
+        assert!(!budget.withdraw_at(later));
+        budget.deposit_at(later);
+        budget.deposit_at(later);
+        budget.deposit_at(later);
+        budget.deposit_at(later);
+        budget.deposit_at(later);
+        assert!(budget.withdraw_at(later));
+        assert!(!budget.withdraw_at(later));
+    }
+
+    #[test]
+    fn reserve_allows_retries_without_deposits() {
+        let budget = RetryBudget::new(Duration::from_secs(10), 1, 0.0);
+        let start = Instant::now();
+
+        let allowed = (0..20).filter(|_| budget.withdraw_at(start)).count();
+        assert_eq!(allowed, 10);
+
+        // withdrawals are returned to the reserve as they expire
+        assert!(!budget.withdraw_at(start + Duration::from_secs(9)));
+        assert!(budget.withdraw_at(start + Duration::from_secs(10)));
+    }
+}
@@ -1,32 +1,58 @@
 //! Middleware for retrying requests.
 
+mod budget;
+
 use futures_util::future;
 use http::{Request, Response};
 use tower::retry::Policy;
+
+pub use self::budget::RetryBudget;
 #[cfg(any(
     feature = "gzip",
     feature = "zstd",
     feature = "brotli",
     feature = "deflate",
 ))]
-use tower_http::decompression::DecompressionBody;
-
-use super::timeout::TimeoutBody;
-use crate::{Body, core::body::Incoming, error::BoxError};
+use super::decoder::{DrainBody, SalvageBody};
+use super::{coop::CoopBody, timeout::TimeoutBody};
+#[cfg(feature = "tls")]
+use crate::tls::{EarlyData, is_early_data_rejected};
+use crate::{Body, client::RequestAttempt, core::body::Incoming, error::BoxError};
 
 /// A retry policy for HTTP/2 requests that safely determines whether and how many times
 /// a request should be retried based on error type and a maximum retry count.
 ///
 /// This policy helps avoid unsafe or infinite retries by tracking the number of attempts
 /// and only retrying errors that are considered safe to repeat (such as connection-level errors).
+///
+/// A request rejected as TLS early data is also retried once, without early data, regardless
+/// of the number of attempts left.
+///
+/// With a [`RetryBudget`], retries are also limited by the budget, which the first attempts
+/// of requests answered without a server error fund.
 #[derive(Clone)]
-pub struct Http2RetryPolicy(usize);
+pub struct Http2RetryPolicy {
+    attempts: usize,
+    budget: Option<RetryBudget>,
+    retried: bool,
+}
 
 impl Http2RetryPolicy {
     /// Create a new `Http2RetryPolicy` policy with the specified number of attempts.
     #[inline]
     pub const fn new(attempts: usize) -> Self {
-        Self(attempts)
+        Self {
+            attempts,
+            budget: None,
+            retried: false,
+        }
+    }
+
+    /// Limits retries by the given budget.
+    #[inline]
+    pub fn with_budget(mut self, budget: Option<RetryBudget>) -> Self {
+        self.budget = budget;
+        self
     }
 
     /// Determines whether the given error is considered retryable for HTTP/2 requests.
@@ -64,6 +90,14 @@ impl Http2RetryPolicy {
     }
 }
 
+/// Increments the [`RequestAttempt`] of a request about to be retried.
+fn next_attempt(req: &mut Req) {
+    if let Some(RequestAttempt(attempt)) = req.extensions().get::<RequestAttempt>().copied() {
+        req.extensions_mut()
+            .insert(RequestAttempt(attempt.saturating_add(1)));
+    }
+}
+
 type Req = Request<Body>;
 #[cfg(not(any(
     feature = "gzip",
@@ -71,39 +105,61 @@ type Req = Request<Body>;
     feature = "brotli",
     feature = "deflate",
 )))]
-type Res = Response<TimeoutBody<Incoming>>;
+type Res = Response<CoopBody<TimeoutBody<Incoming>>>;
 #[cfg(any(
     feature = "gzip",
     feature = "zstd",
     feature = "brotli",
     feature = "deflate",
 ))]
-type Res = Response<TimeoutBody<DecompressionBody<Incoming>>>;
+type Res = Response<CoopBody<TimeoutBody<SalvageBody<DrainBody<Incoming>>>>>;
 
 impl Policy<Req, Res, BoxError> for Http2RetryPolicy {
     type Future = future::Ready<()>;
 
-    fn retry(
-        &mut self,
-        _req: &mut Req,
-        result: &mut Result<Res, BoxError>,
-    ) -> Option<Self::Future> {
+    fn retry(&mut self, req: &mut Req, result: &mut Result<Res, BoxError>) -> Option<Self::Future> {
+        if let (Ok(res), false, Some(budget)) = (&result, self.retried, &self.budget) {
+            if !res.status().is_server_error() {
+                budget.deposit();
+            }
+        }
+
         if let Err(err) = result {
+            // Only requests safe to replay are sent as early data, so replay it once.
+            #[cfg(feature = "tls")]
+            if req.extensions().get::<EarlyData>().is_none() && is_early_data_rejected(err.as_ref())
+            {
+                trace!("Early data rejected, retrying request over a full handshake");
+                req.extensions_mut().insert(EarlyData::Rejected);
+                next_attempt(req);
+                return Some(future::ready(()));
+            }
+
             if !self.is_retryable_error(err.as_ref()) {
                 return None;
             }
 
             // Treat all errors as failures...
             // But we limit the number of attempts...
-            return if self.0 > 0 {
-                trace!("Retrying HTTP/2 request, attempts left: {}", self.0);
-                // Try again!
-                self.0 -= 1;
-                Some(future::ready(()))
-            } else {
+            if self.attempts == 0 {
                 // Used all our attempts, no retry...
-                None
-            };
+                return None;
+            }
+
+            // ...and the retries of all requests together.
+            if let Some(ref budget) = self.budget {
+                if !budget.withdraw() {
+                    trace!("Retry budget exhausted, not retrying HTTP/2 request");
+                    return None;
+                }
+            }
+
+            trace!("Retrying HTTP/2 request, attempts left: {}", self.attempts);
+            // Try again!
+            self.attempts -= 1;
+            self.retried = true;
+            next_attempt(req);
+            return Some(future::ready(()));
         }
 
         None
@@ -0,0 +1,307 @@
+//! Middleware delaying requests by a think time, to pace them like a person browsing.
+
+use std::{
+    f64::consts::TAU,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures_util::future::Either;
+use http::{Request, Response};
+use tower::Layer;
+use tower_service::Service;
+
+use crate::{
+    client::middleware::config::RequestThinkTime, core::ext::RequestConfig, error::BoxError,
+    util::RandomSource,
+};
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = Result<T, BoxError>> + Send>>;
+
+/// The distribution of the delays a client waits before sending requests, see
+/// [`ClientBuilder::think_time`](crate::ClientBuilder::think_time).
+///
+/// Delays are drawn from a random sequence of their own, which can be
+/// [`seed`](ThinkTime::seed)ed to reproduce the same delays. Clones share the sequence.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use wreq::ThinkTime;
+///
+/// // Half of the delays are shorter than 2 seconds, with a long tail of longer ones.
+/// let think_time = ThinkTime::log_normal(Duration::from_secs(2), 0.6).seed(42);
+/// let client = wreq::Client::builder().think_time(think_time).build()?;
+/// # let _ = client;
+/// # Ok::<(), wreq::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct ThinkTime {
+    distribution: Distribution,
+    random: RandomSource,
+}
+
+#[derive(Clone)]
+enum Distribution {
+    Fixed(Duration),
+    UniformJitter { base: Duration, jitter: Duration },
+    LogNormal { median: Duration, sigma: f64 },
+    Custom(Arc<dyn Fn() -> Duration + Send + Sync>),
+}
+
+impl ThinkTime {
+    /// Waits `delay` before every request.
+    pub fn fixed(delay: Duration) -> ThinkTime {
+        ThinkTime::new(Distribution::Fixed(delay))
+    }
+
+    /// Waits a delay drawn uniformly between `base - jitter` and `base + jitter`.
+    ///
+    /// Delays shorter than zero are waited as zero.
+    pub fn uniform_jitter(base: Duration, jitter: Duration) -> ThinkTime {
+        ThinkTime::new(Distribution::UniformJitter { base, jitter })
+    }
+
+    /// Waits a delay drawn from a log-normal distribution, with the given `median` and
+    /// standard deviation `sigma` of its logarithm.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sigma` is negative or not finite.
+    pub fn log_normal(median: Duration, sigma: f64) -> ThinkTime {
+        assert!(
+            sigma.is_finite() && sigma >= 0.0,
+            "log-normal sigma must be a non-negative number"
+        );
+        ThinkTime::new(Distribution::LogNormal { median, sigma })
+    }
+
+    /// Waits the delay returned by `f` before every request.
+    ///
+    /// The delays of `f` do not depend on the [`seed`](ThinkTime::seed).
+    pub fn custom<F>(f: F) -> ThinkTime
+    where
+        F: Fn() -> Duration + Send + Sync + 'static,
+    {
+        ThinkTime::new(Distribution::Custom(Arc::new(f)))
+    }
+
+    /// Draws the delays from a sequence seeded with `seed`, instead of a random one.
+    pub fn seed(mut self, seed: u64) -> ThinkTime {
+        self.random = RandomSource::seeded(seed);
+        self
+    }
+
+    /// Draws the next delay.
+    pub fn sample(&self) -> Duration {
+        match self.distribution {
+            Distribution::Fixed(delay) => delay,
+            Distribution::UniformJitter { base, jitter } => {
+                let offset = jitter.mul_f64(2.0 * self.unit());
+                (base + offset).saturating_sub(jitter)
+            }
+            Distribution::LogNormal { median, sigma } => {
+                // Box-Muller transform of two uniform samples, the first in (0, 1]
+                let normal = (-2.0 * (1.0 - self.unit()).ln()).sqrt() * (TAU * self.unit()).cos();
+                Duration::try_from_secs_f64(median.as_secs_f64() * (sigma * normal).exp())
+                    .unwrap_or(Duration::MAX)
+            }
+            Distribution::Custom(ref f) => f(),
+        }
+    }
+
+    fn new(distribution: Distribution) -> ThinkTime {
+        ThinkTime {
+            distribution,
+            random: RandomSource::Fast,
+        }
+    }
+
+    /// Draws a number uniformly in [0, 1).
+    fn unit(&self) -> f64 {
+        (self.random.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl fmt::Debug for ThinkTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.distribution {
+            Distribution::Fixed(delay) => f.debug_tuple("Fixed").field(&delay).finish(),
+            Distribution::UniformJitter { base, jitter } => f
+                .debug_struct("UniformJitter")
+                .field("base", &base)
+                .field("jitter", &jitter)
+                .finish(),
+            Distribution::LogNormal { median, sigma } => f
+                .debug_struct("LogNormal")
+                .field("median", &median)
+                .field("sigma", &sigma)
+                .finish(),
+            Distribution::Custom(_) => f.pad("Custom"),
+        }
+    }
+}
+
+/// Request and response [`http::Extensions`] value holding the think time a request was
+/// delayed by before it was sent.
+///
+/// Only attached to requests that were delayed, see
+/// [`ClientBuilder::think_time`](crate::ClientBuilder::think_time).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ThinkTimeDelay(pub Duration);
+
+/// Layer to apply [`ThinkTimeService`] middleware.
+#[derive(Clone)]
+pub struct ThinkTimeLayer {
+    think_time: Option<ThinkTime>,
+}
+
+impl ThinkTimeLayer {
+    /// Create a new layer delaying requests by `think_time`, if any.
+    pub fn new(think_time: Option<ThinkTime>) -> Self {
+        ThinkTimeLayer { think_time }
+    }
+}
+
+impl<S> Layer<S> for ThinkTimeLayer {
+    type Service = ThinkTimeService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ThinkTimeService {
+            inner,
+            think_time: self.think_time.clone(),
+        }
+    }
+}
+
+/// Middleware waiting a think time before sending requests.
+#[derive(Clone)]
+pub struct ThinkTimeService<S> {
+    inner: S,
+    think_time: Option<ThinkTime>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for ThinkTimeService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>, Error = BoxError>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+    ReqBody: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Either<S::Future, BoxFuture<S::Response>>;
+
+    #[inline(always)]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let enabled = RequestConfig::<RequestThinkTime>::get(req.extensions())
+            .copied()
+            .unwrap_or(true);
+        let Some(delay) = self
+            .think_time
+            .as_ref()
+            .filter(|_| enabled)
+            .map(ThinkTime::sample)
+        else {
+            return Either::Left(self.inner.call(req));
+        };
+
+        // Nothing is acquired for the request until the delay is over, so dropping it
+        // while waiting leaves nothing behind.
+        req.extensions_mut().insert(ThinkTimeDelay(delay));
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Either::Right(Box::pin(async move {
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+            let mut res = inner.call(req).await?;
+            res.extensions_mut().insert(ThinkTimeDelay(delay));
+            Ok(res)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(think_time: &ThinkTime, n: usize) -> Vec<f64> {
+        (0..n).map(|_| think_time.sample().as_secs_f64()).collect()
+    }
+
+    #[test]
+    fn uniform_jitter_is_uniform() {
+        let think_time =
+            ThinkTime::uniform_jitter(Duration::from_secs(2), Duration::from_secs(1)).seed(7);
+        let samples = samples(&think_time, 20_000);
+
+        assert!(samples.iter().all(|s| (1.0..=3.0).contains(s)));
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        assert!((mean - 2.0).abs() < 0.02, "mean {mean}");
+
+        // each quarter of the range holds a quarter of the samples
+        for quarter in 0..4 {
+            let lo = 1.0 + quarter as f64 * 0.5;
+            let count = samples
+                .iter()
+                .filter(|s| (lo..lo + 0.5).contains(*s))
+                .count();
+            let share = count as f64 / samples.len() as f64;
+            assert!((share - 0.25).abs() < 0.02, "quarter {quarter}: {share}");
+        }
+    }
+
+    #[test]
+    fn log_normal_has_median_and_sigma() {
+        let think_time = ThinkTime::log_normal(Duration::from_millis(500), 0.5).seed(7);
+        let mut logs: Vec<f64> = samples(&think_time, 20_000)
+            .into_iter()
+            .map(f64::ln)
+            .collect();
+        logs.sort_by(f64::total_cmp);
+
+        let median = logs[logs.len() / 2].exp();
+        assert!((median - 0.5).abs() < 0.02, "median {median}");
+
+        let mean = logs.iter().sum::<f64>() / logs.len() as f64;
+        let variance = logs.iter().map(|l| (l - mean).powi(2)).sum::<f64>() / logs.len() as f64;
+        assert!(
+            (variance.sqrt() - 0.5).abs() < 0.02,
+            "sigma {}",
+            variance.sqrt()
+        );
+    }
+
+    #[test]
+    fn seeded_samples_are_reproducible() {
+        let a = ThinkTime::log_normal(Duration::from_secs(1), 1.0).seed(42);
+        let b = ThinkTime::log_normal(Duration::from_secs(1), 1.0).seed(42);
+        assert_eq!(samples(&a, 100), samples(&b, 100));
+
+        // clones share the sequence
+        let c = a.clone();
+        assert_ne!(a.sample(), c.sample());
+    }
+
+    #[test]
+    fn fixed_and_custom_delays() {
+        let fixed = ThinkTime::fixed(Duration::from_millis(300));
+        assert_eq!(fixed.sample(), Duration::from_millis(300));
+
+        let custom = ThinkTime::custom(|| Duration::from_millis(7));
+        assert_eq!(custom.sample(), Duration::from_millis(7));
+    }
+}
@@ -7,7 +7,7 @@ use std::{
 
 use http_body::Body;
 use pin_project_lite::pin_project;
-use tokio::time::{Sleep, sleep};
+use tokio::time::{Instant, Sleep, sleep, sleep_until};
 
 use crate::{
     Error,
@@ -66,9 +66,9 @@ pin_project! {
 
 /// ==== impl TimeoutBody ====
 impl<B> TimeoutBody<B> {
-    /// Creates a new [`TimeoutBody`] with no timeout.
-    pub fn new(deadline: Option<Duration>, read_timeout: Option<Duration>, body: B) -> Self {
-        let deadline = deadline.map(sleep).map(Box::pin);
+    /// Creates a new [`TimeoutBody`] bounded by the given total deadline and read timeout.
+    pub fn new(deadline: Option<Instant>, read_timeout: Option<Duration>, body: B) -> Self {
+        let deadline = deadline.map(sleep_until).map(Box::pin);
         match (deadline, read_timeout) {
             (Some(total_timeout), Some(read_timeout)) => TimeoutBody::CombinedTimeout {
                 body: TotalTimeoutBody {
@@ -2,14 +2,13 @@ use std::{
     future::Future,
     pin::Pin,
     task::{Context, Poll, ready},
-    time::Duration,
 };
 
-use http::{Response, Uri};
+use http::{Response, StatusCode, Uri};
 use pin_project_lite::pin_project;
 use tokio::time::Sleep;
 
-use super::body::TimeoutBody;
+use super::{body::TimeoutBody, layer::EffectiveTimeout};
 use crate::{
     error::{BoxError, Error, TimedOut},
     into_url::IntoUrlSealed,
@@ -84,8 +83,7 @@ pin_project! {
     pub struct ResponseBodyTimeoutFuture<Fut> {
         #[pin]
         pub(crate) inner: Fut,
-        pub(crate) total_timeout: Option<Duration>,
-        pub(crate) read_timeout: Option<Duration>,
+        pub(crate) timeout: EffectiveTimeout,
     }
 }
 
@@ -96,12 +94,18 @@ where
     type Output = Result<Response<TimeoutBody<ResBody>>, E>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let total_timeout = self.total_timeout;
-        let read_timeout = self.read_timeout;
+        let timeout = self.timeout;
         let this = self.project();
         let res = ready!(this.inner.poll(cx))?;
-        Poll::Ready(Ok(
-            res.map(|body| TimeoutBody::new(total_timeout, read_timeout, body))
-        ))
+
+        // Once the connection is upgraded, the IO is handed to the caller,
+        // so neither the total nor the read timeout applies any longer.
+        if res.status() == StatusCode::SWITCHING_PROTOCOLS {
+            return Poll::Ready(Ok(res.map(|body| TimeoutBody::new(None, None, body))));
+        }
+
+        Poll::Ready(Ok(res.map(|body| {
+            TimeoutBody::new(timeout.deadline, timeout.read_timeout, body)
+        })))
     }
 }
@@ -3,7 +3,8 @@ use std::{
     time::Duration,
 };
 
-use http::{Request, Response};
+use http::{Extensions, Request, Response};
+use tokio::time::Instant;
 use tower::Layer;
 use tower_service::Service;
 
@@ -17,6 +18,44 @@ use crate::{
     error::BoxError,
 };
 
+/// The effective timeouts of a single request.
+///
+/// Resolved exactly once per request by the outermost [`Timeout`] layer and carried in the
+/// request extensions, so that the response body shares the same total deadline instead of
+/// arming a fresh one.
+///
+/// A request-level value always overrides the client-level one, and an explicit zero duration
+/// disables the corresponding timeout.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct EffectiveTimeout {
+    pub(crate) deadline: Option<Instant>,
+    pub(crate) read_timeout: Option<Duration>,
+}
+
+impl EffectiveTimeout {
+    fn resolve(
+        total_timeout: &RequestConfig<RequestTotalTimeout>,
+        read_timeout: &RequestConfig<RequestReadTimeout>,
+        ext: &Extensions,
+    ) -> Self {
+        let deadline = total_timeout
+            .fetch(ext)
+            .copied()
+            .filter(|timeout| !timeout.is_zero())
+            .map(|timeout| Instant::now() + timeout);
+
+        let read_timeout = read_timeout
+            .fetch(ext)
+            .copied()
+            .filter(|timeout| !timeout.is_zero());
+
+        EffectiveTimeout {
+            deadline,
+            read_timeout,
+        }
+    }
+}
+
 /// [`Layer`] that applies a [`Timeout`] middleware to a service.
 // This layer allows you to set a total timeout and a read timeout for requests.
 #[derive(Clone)]
@@ -69,18 +108,18 @@ where
         self.inner.poll_ready(cx)
     }
 
-    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
-        let total_timeout = self
-            .total_timeout
-            .fetch(req.extensions())
-            .copied()
-            .map(tokio::time::sleep);
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        debug_assert!(
+            req.extensions().get::<EffectiveTimeout>().is_none(),
+            "request timeouts must be armed exactly once"
+        );
 
-        let read_timeout = self
-            .read_timeout
-            .fetch(req.extensions())
-            .copied()
-            .map(tokio::time::sleep);
+        let timeout =
+            EffectiveTimeout::resolve(&self.total_timeout, &self.read_timeout, req.extensions());
+        req.extensions_mut().insert(timeout);
+
+        let total_timeout = timeout.deadline.map(tokio::time::sleep_until);
+        let read_timeout = timeout.read_timeout.map(tokio::time::sleep);
 
         let uri = req.uri().clone();
         let response = self.inner.call(req);
@@ -146,12 +185,19 @@ where
     }
 
     fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
-        let total_timeout = self.total_timeout.fetch(req.extensions()).copied();
-        let read_timeout = self.read_timeout.fetch(req.extensions()).copied();
+        // Reuse the timeouts resolved by the outer `Timeout` layer, so the total
+        // deadline spans both the response head and the body.
+        let timeout = req
+            .extensions()
+            .get::<EffectiveTimeout>()
+            .copied()
+            .unwrap_or_else(|| {
+                EffectiveTimeout::resolve(&self.total_timeout, &self.read_timeout, req.extensions())
+            });
+
         ResponseBodyTimeoutFuture {
             inner: self.inner.call(req),
-            total_timeout,
-            read_timeout,
+            timeout,
         }
     }
 }
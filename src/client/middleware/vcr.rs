@@ -0,0 +1,93 @@
+//! Middleware to record and replay interactions, see [`crate::vcr`].
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures_util::future::{self, Either};
+use http::{Request, Response};
+use http_body_util::BodyExt;
+use tower::Layer;
+use tower_service::Service;
+
+use crate::{
+    Body,
+    core::body::Incoming,
+    error::BoxError,
+    vcr::{Cassette, Lookup},
+};
+
+type BoxFuture = Pin<Box<dyn Future<Output = Result<Response<Incoming>, BoxError>> + Send>>;
+
+/// Layer to apply [`VcrService`] middleware.
+#[derive(Clone)]
+pub struct VcrLayer {
+    cassette: Option<Arc<Cassette>>,
+}
+
+impl VcrLayer {
+    /// Create a new layer using `cassette`, if any.
+    pub fn new(cassette: Option<Cassette>) -> Self {
+        Self {
+            cassette: cassette.map(Arc::new),
+        }
+    }
+}
+
+impl<S> Layer<S> for VcrLayer {
+    type Service = VcrService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        VcrService {
+            inner,
+            cassette: self.cassette.clone(),
+        }
+    }
+}
+
+/// Middleware answering requests from a cassette, or recording them into it.
+#[derive(Clone)]
+pub struct VcrService<S> {
+    inner: S,
+    cassette: Option<Arc<Cassette>>,
+}
+
+impl<S> Service<Request<Body>> for VcrService<S>
+where
+    S: Service<Request<Body>, Response = Response<Incoming>, Error = BoxError>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Either<S::Future, BoxFuture>;
+
+    #[inline(always)]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let Some(ref cassette) = self.cassette else {
+            return Either::Left(self.inner.call(req));
+        };
+
+        match cassette.lookup(&req) {
+            Lookup::Replay(res) => Either::Right(Box::pin(future::ready(Ok(res)))),
+            Lookup::Unmatched(err) => Either::Right(Box::pin(future::ready(Err(err.into())))),
+            Lookup::Passthrough => Either::Left(self.inner.call(req)),
+            Lookup::Record(key) => {
+                let cassette = cassette.clone();
+                let fut = self.inner.call(req);
+                Either::Right(Box::pin(async move {
+                    let (parts, body) = fut.await?.into_parts();
+                    let body = body.collect().await?.to_bytes();
+                    cassette.record(key, &parts, &body)?;
+                    Ok::<_, BoxError>(Response::from_parts(parts, Incoming::full(body)))
+                }))
+            }
+        }
+    }
+}
@@ -0,0 +1,517 @@
+//! Downloading content available from several mirrors, failing over between them.
+//!
+//! [`Client::download_from_mirrors`] tries the mirrors in order, or by latency, and fails over
+//! to the next mirror when one is down, answers with an error status, or serves content that
+//! does not have its expected digest. A download interrupted after some of the content was
+//! received is resumed with a range request, on the same mirror or the next one, as long as
+//! the mirror serves the same [`ETag`](http::header::ETAG). It restarts otherwise.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use wreq::{
+//!     DigestAlgorithm, ExpectedDigest, Url,
+//!     mirrors::MirrorOptions,
+//! };
+//!
+//! # async fn doc(client: wreq::Client, sha256: [u8; 32]) -> wreq::Result<()> {
+//! let urls = vec![
+//!     Url::parse("https://eu.mirror.example/release.tar.gz").unwrap(),
+//!     Url::parse("https://us.mirror.example/release.tar.gz").unwrap(),
+//! ];
+//! let options = MirrorOptions::new()
+//!     .expected_digest(ExpectedDigest::new(DigestAlgorithm::Sha256, sha256))
+//!     .order_by_latency(true);
+//!
+//! let download = client.download_from_mirrors(urls, options).await?;
+//! println!("{} bytes from {}", download.body().len(), download.mirror());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{collections::VecDeque, error::Error as StdError, fmt, sync::Arc, time::Duration};
+
+use bytes::{Bytes, BytesMut};
+use futures_util::future::{self, Either};
+use http::{
+    HeaderValue, StatusCode,
+    header::{CONTENT_RANGE, ETAG, IF_RANGE, RANGE},
+};
+use url::Url;
+
+use crate::{Client, Error, ExpectedDigest, ProbeDepth, Response};
+
+/// The options of [`Client::download_from_mirrors`].
+#[must_use]
+#[derive(Clone)]
+pub struct MirrorOptions {
+    attempts_per_mirror: usize,
+    digest: Option<ExpectedDigest>,
+    failover: Arc<dyn Fn(&Error) -> bool + Send + Sync>,
+    race_first_byte: bool,
+    order_by_latency: bool,
+}
+
+impl MirrorOptions {
+    /// Creates the default options: two attempts per mirror, failing over on the errors
+    /// described in [`failover_when`](Self::failover_when).
+    pub fn new() -> MirrorOptions {
+        MirrorOptions {
+            attempts_per_mirror: 2,
+            digest: None,
+            failover: Arc::new(|err: &Error| {
+                err.is_connect() || err.status().is_some() || err.digest_mismatch().is_some()
+            }),
+            race_first_byte: false,
+            order_by_latency: false,
+        }
+    }
+
+    /// Sets how many times a mirror is tried before failing over to the next one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `attempts` is zero.
+    pub fn attempts_per_mirror(mut self, attempts: usize) -> MirrorOptions {
+        assert!(attempts > 0, "a mirror must be tried at least once");
+        self.attempts_per_mirror = attempts;
+        self
+    }
+
+    /// Verifies the content against an [`ExpectedDigest`].
+    ///
+    /// Content with another digest is discarded, and its mirror fails over with a
+    /// [`DigestMismatch`](crate::DigestMismatch) error.
+    pub fn expected_digest(mut self, digest: ExpectedDigest) -> MirrorOptions {
+        self.digest = Some(digest);
+        self
+    }
+
+    /// Sets which errors make a mirror fail over to the next one right away, without using the
+    /// rest of its attempts.
+    ///
+    /// By default, connect errors, error statuses and digest mismatches fail over, while the
+    /// other errors, such as a body interrupted by a timeout or a reset, are retried on the
+    /// same mirror first.
+    pub fn failover_when<F>(mut self, f: F) -> MirrorOptions
+    where
+        F: Fn(&Error) -> bool + Send + Sync + 'static,
+    {
+        self.failover = Arc::new(f);
+        self
+    }
+
+    /// Requests the content from the next two mirrors at once, and keeps the first to send a
+    /// byte of it, the other one is tried again after the remaining mirrors.
+    ///
+    /// Only downloads starting from the beginning of the content are raced, resumed ones are
+    /// not.
+    pub fn race_first_byte(mut self, enabled: bool) -> MirrorOptions {
+        self.race_first_byte = enabled;
+        self
+    }
+
+    /// Tries the mirrors by the latency of connecting to them, measured with a
+    /// [`Client::probe`] of `Tls` depth, instead of in the order they are given.
+    ///
+    /// Mirrors that fail their probe are tried last.
+    pub fn order_by_latency(mut self, enabled: bool) -> MirrorOptions {
+        self.order_by_latency = enabled;
+        self
+    }
+}
+
+impl Default for MirrorOptions {
+    fn default() -> MirrorOptions {
+        MirrorOptions::new()
+    }
+}
+
+impl fmt::Debug for MirrorOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MirrorOptions")
+            .field("attempts_per_mirror", &self.attempts_per_mirror)
+            .field("digest", &self.digest)
+            .field("race_first_byte", &self.race_first_byte)
+            .field("order_by_latency", &self.order_by_latency)
+            .finish()
+    }
+}
+
+/// Content downloaded by [`Client::download_from_mirrors`].
+#[derive(Debug)]
+pub struct MirrorDownload {
+    mirror: Url,
+    body: Bytes,
+    attempts: Vec<MirrorAttempt>,
+}
+
+impl MirrorDownload {
+    /// Returns the mirror that served the end of the content.
+    pub fn mirror(&self) -> &Url {
+        &self.mirror
+    }
+
+    /// Returns the content.
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// Consumes the download, returning the content.
+    pub fn into_body(self) -> Bytes {
+        self.body
+    }
+
+    /// Returns the attempts made to download the content, in the order they ended, the last
+    /// one being the one that served it.
+    pub fn attempts(&self) -> &[MirrorAttempt] {
+        &self.attempts
+    }
+}
+
+/// An attempt to download content from a mirror.
+#[derive(Debug)]
+pub struct MirrorAttempt {
+    mirror: Url,
+    offset: u64,
+    outcome: MirrorOutcome,
+}
+
+impl MirrorAttempt {
+    /// Returns the mirror the attempt was made to.
+    pub fn mirror(&self) -> &Url {
+        &self.mirror
+    }
+
+    /// Returns the offset in the content the attempt requested, not zero if it resumed an
+    /// interrupted download.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns how the attempt ended.
+    pub fn outcome(&self) -> &MirrorOutcome {
+        &self.outcome
+    }
+}
+
+/// How an attempt to download content from a mirror ended, see [`MirrorAttempt::outcome`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum MirrorOutcome {
+    /// The mirror served the content.
+    Served,
+    /// Another mirror sent the first byte of the content sooner, see
+    /// [`MirrorOptions::race_first_byte`].
+    LostRace,
+    /// The attempt failed.
+    Failed(Error),
+}
+
+/// The cause of an error when no mirror could serve the content, see
+/// [`Error::mirrors_exhausted`].
+#[derive(Debug)]
+pub struct MirrorsExhausted {
+    attempts: Vec<MirrorAttempt>,
+}
+
+impl MirrorsExhausted {
+    /// Returns the attempts made to download the content, in the order they ended.
+    pub fn attempts(&self) -> &[MirrorAttempt] {
+        &self.attempts
+    }
+}
+
+impl fmt::Display for MirrorsExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no mirror served the content")?;
+        let last = self
+            .attempts
+            .iter()
+            .rev()
+            .find_map(|attempt| match attempt.outcome {
+                MirrorOutcome::Failed(ref err) => Some(err),
+                _ => None,
+            });
+        match last {
+            Some(err) => write!(
+                f,
+                " after {} attempts, the last: {err}",
+                self.attempts.len()
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
+impl StdError for MirrorsExhausted {}
+
+impl Client {
+    /// Downloads content available from several mirrors, failing over between them, see the
+    /// [`mirrors`](crate::mirrors) module.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `urls` is empty, or once every mirror used its attempts or failed over. The
+    /// attempts are then reported by [`Error::mirrors_exhausted`].
+    pub async fn download_from_mirrors(
+        &self,
+        urls: Vec<Url>,
+        options: MirrorOptions,
+    ) -> crate::Result<MirrorDownload> {
+        if urls.is_empty() {
+            return Err(Error::builder("no mirror to download from"));
+        }
+
+        let urls = if options.order_by_latency {
+            by_latency(self, urls).await
+        } else {
+            urls
+        };
+
+        let mut download = Download {
+            client: self,
+            body: BytesMut::new(),
+            etag: None,
+            attempts: Vec::new(),
+            options,
+        };
+        download.run(urls).await
+    }
+}
+
+/// Sorts `urls` by the latency of connecting to their origin.
+async fn by_latency(client: &Client, urls: Vec<Url>) -> Vec<Url> {
+    let probes = urls.iter().map(|url| async move {
+        let report = client.probe(url.clone(), ProbeDepth::Tls).send().await.ok();
+        report
+            .filter(|report| report.is_success())
+            .map(|report| report.tcp().unwrap_or_default() + report.tls().unwrap_or_default())
+    });
+    let latencies: Vec<Option<Duration>> = future::join_all(probes).await;
+
+    let mut urls: Vec<_> = urls.into_iter().zip(latencies).collect();
+    urls.sort_by_key(|(_, latency)| (latency.is_none(), *latency));
+    urls.into_iter().map(|(url, _)| url).collect()
+}
+
+/// A response to a request for the content, with its first chunk.
+struct Opened {
+    res: Response,
+    first: Option<Bytes>,
+    partial: bool,
+    etag: Option<HeaderValue>,
+}
+
+/// Requests the content from `url`, from the offset of `resume` if the mirror still serves
+/// the content with its `ETag`.
+async fn open(
+    client: &Client,
+    url: Url,
+    resume: Option<(u64, &HeaderValue)>,
+) -> crate::Result<Opened> {
+    let mut req = client.get(url.clone());
+    if let Some((offset, etag)) = resume {
+        req = req
+            .header(RANGE, format!("bytes={offset}-"))
+            .header(IF_RANGE, etag.clone());
+    }
+
+    let mut res = req.send().await?.error_for_status()?;
+    let partial = res.status() == StatusCode::PARTIAL_CONTENT;
+    if partial {
+        let start = res
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("bytes "))
+            .and_then(|range| range.split_once('-'))
+            .and_then(|(start, _)| start.parse::<u64>().ok());
+        let continues = resume.is_some_and(|(offset, etag)| {
+            start == Some(offset) && res.headers().get(ETAG) == Some(etag)
+        });
+        if !continues {
+            return Err(
+                Error::body("partial response does not continue the content").with_url(url),
+            );
+        }
+    }
+
+    // weak validators cannot be used to resume
+    let etag = res
+        .headers()
+        .get(ETAG)
+        .filter(|etag| !etag.as_bytes().starts_with(b"W/"))
+        .cloned();
+    let first = res.chunk().await?;
+    Ok(Opened {
+        res,
+        first,
+        partial,
+        etag,
+    })
+}
+
+/// The state of a download from mirrors.
+struct Download<'a> {
+    client: &'a Client,
+    options: MirrorOptions,
+    body: BytesMut,
+    etag: Option<HeaderValue>,
+    attempts: Vec<MirrorAttempt>,
+}
+
+impl Download<'_> {
+    async fn run(&mut self, urls: Vec<Url>) -> crate::Result<MirrorDownload> {
+        // the mirrors left, with their attempts left, in the order they are tried
+        let mut mirrors: VecDeque<_> = urls
+            .into_iter()
+            .map(|url| (url, self.options.attempts_per_mirror))
+            .collect();
+
+        while let Some((url, attempts)) = mirrors.pop_front() {
+            let offset = self.offset();
+            let resume = self.etag.as_ref().map(|etag| (offset, etag));
+
+            let rival = if self.options.race_first_byte && offset == 0 {
+                mirrors.pop_front()
+            } else {
+                None
+            };
+            let (url, attempts, opened) = match rival {
+                None => {
+                    let opened = open(self.client, url.clone(), resume).await;
+                    (url, attempts, opened)
+                }
+                Some((rival, rival_attempts)) => {
+                    let a = Box::pin(open(self.client, url.clone(), None));
+                    let b = Box::pin(open(self.client, rival.clone(), None));
+                    let (winner, loser) = match future::select(a, b).await {
+                        Either::Left((opened, b)) => {
+                            ((url, attempts, opened), (rival, rival_attempts, b))
+                        }
+                        Either::Right((opened, a)) => {
+                            ((rival, rival_attempts, opened), (url, attempts, a))
+                        }
+                    };
+
+                    let (url, attempts, opened) = match winner {
+                        // the loser may still send the first byte
+                        (url, attempts, Err(err)) => {
+                            self.fail(&mut mirrors, url, attempts, 0, err);
+                            let (url, attempts, opened) = loser;
+                            (url, attempts, opened.await)
+                        }
+                        winner => {
+                            let (url, attempts, _) = loser;
+                            self.record(url.clone(), 0, MirrorOutcome::LostRace);
+                            mirrors.push_back((url, attempts));
+                            winner
+                        }
+                    };
+                    (url, attempts, opened)
+                }
+            };
+
+            let result = match opened {
+                Ok(opened) => self.read(opened).await,
+                Err(err) => Err(err),
+            };
+            match result {
+                Ok(()) => {
+                    self.record(url.clone(), offset, MirrorOutcome::Served);
+                    return Ok(MirrorDownload {
+                        mirror: url,
+                        body: std::mem::take(&mut self.body).freeze(),
+                        attempts: std::mem::take(&mut self.attempts),
+                    });
+                }
+                Err(err) => self.fail(&mut mirrors, url, attempts, offset, err),
+            }
+        }
+
+        Err(Error::request(MirrorsExhausted {
+            attempts: std::mem::take(&mut self.attempts),
+        }))
+    }
+
+    /// Returns the offset to resume the content from, zero if it cannot be resumed.
+    fn offset(&self) -> u64 {
+        if self.etag.is_some() {
+            self.body.len() as u64
+        } else {
+            0
+        }
+    }
+
+    /// Reads the content of an opened response, appending it to the content received so far.
+    async fn read(&mut self, opened: Opened) -> crate::Result<()> {
+        let Opened {
+            mut res,
+            first,
+            partial,
+            etag,
+        } = opened;
+        let url = res.url().clone();
+
+        if !partial {
+            self.body.clear();
+            if let Some(ref digest) = self.options.digest {
+                digest.restart();
+            }
+        }
+        self.etag = etag;
+
+        let mut chunk = first;
+        while let Some(data) = chunk {
+            if let Some(ref digest) = self.options.digest {
+                digest
+                    .update(&data)
+                    .map_err(|err| Error::body(err).with_url(url.clone()))?;
+            }
+            self.body.extend_from_slice(&data);
+            chunk = res.chunk().await?;
+        }
+
+        if let Some(ref digest) = self.options.digest {
+            if let Err(err) = digest.verify() {
+                // the content is corrupt, and cannot be resumed
+                self.body.clear();
+                self.etag = None;
+                let err = match err.downcast::<Error>() {
+                    Ok(err) => *err,
+                    Err(err) => Error::body(err),
+                };
+                return Err(err.with_url(url));
+            }
+        }
+        Ok(())
+    }
+
+    /// Records a failed attempt, and tries the mirror again unless it used its attempts or the
+    /// error fails it over.
+    fn fail(
+        &mut self,
+        mirrors: &mut VecDeque<(Url, usize)>,
+        url: Url,
+        attempts: usize,
+        offset: u64,
+        err: Error,
+    ) {
+        let attempts = attempts - 1;
+        if attempts > 0 && !(self.options.failover)(&err) {
+            mirrors.push_front((url.clone(), attempts));
+        } else {
+            debug!("failing over from mirror {url}: {err}");
+        }
+        self.record(url, offset, MirrorOutcome::Failed(err));
+    }
+
+    fn record(&mut self, mirror: Url, offset: u64, outcome: MirrorOutcome) {
+        self.attempts.push(MirrorAttempt {
+            mirror,
+            offset,
+            outcome,
+        });
+    }
+}
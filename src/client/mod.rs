@@ -1,7 +1,33 @@
+#[cfg(feature = "stream")]
+pub use self::body::FlowHandle;
+#[cfg(feature = "tls")]
+pub use self::client::{ClientUpdate, TlsConfigSnapshot};
+#[cfg(any(
+    feature = "gzip",
+    feature = "zstd",
+    feature = "brotli",
+    feature = "deflate",
+))]
+pub use self::middleware::decoder::PartialBody;
+#[cfg(feature = "json")]
+pub use self::ndjson::Ndjson;
 pub use self::{
     body::Body,
-    client::{Client, ClientBuilder},
-    emulation::{EmulationProvider, EmulationProviderFactory},
+    client::{
+        Client, ClientBuilder, ConnId, KeepaliveOutcome, PooledConnection, ProbeBuilder,
+        ProbeDepth, ProbeReport, TrimmedCookies,
+    },
+    digest::{DigestAlgorithm, DigestMismatch, ExpectedDigest},
+    dispatch::{OriginalUrl, RedirectHop, RequestAttempt},
+    emulation::{
+        EmulationApply, EmulationConflicts, EmulationProvider, EmulationProviderBuilder,
+        EmulationProviderFactory, EmulationSelection, FailoverConfig, FailureContext, FailureKind,
+        ProfileStats, RotatingEmulation,
+    },
+    middleware::{
+        retry::RetryBudget,
+        think_time::{ThinkTime, ThinkTimeDelay},
+    },
     request::{Request, RequestBuilder},
     response::Response,
     upgrade::Upgraded,
@@ -10,10 +36,18 @@ pub use self::{
 pub mod body;
 #[allow(clippy::module_inception)]
 mod client;
+pub mod client_hints;
+mod digest;
+pub(crate) mod dispatch;
 mod emulation;
+pub mod forwarded;
+pub mod message_signature;
 pub(crate) mod middleware;
+pub mod mirrors;
 #[cfg(feature = "multipart")]
 pub mod multipart;
+#[cfg(feature = "json")]
+mod ndjson;
 pub(crate) mod request;
 mod response;
 mod upgrade;
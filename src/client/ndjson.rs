@@ -0,0 +1,232 @@
+//! Newline-delimited JSON request bodies.
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures_util::{FutureExt, Stream, StreamExt, stream};
+use pin_project_lite::pin_project;
+use serde::Serialize;
+
+use super::body::Body;
+
+/// The number of bytes buffered before a chunk is emitted, by default.
+const DEFAULT_FLUSH_THRESHOLD: usize = 16 * 1024;
+
+/// A request body of `application/x-ndjson`, serializing each item as one line of JSON.
+///
+/// Created by [`Body::ndjson_from_iter`] or [`Body::ndjson_from_stream`], and converted into a
+/// [`Body`], or sent with [`RequestBuilder::ndjson`](crate::RequestBuilder::ndjson).
+///
+/// Items are serialized lazily, as the body is sent, into chunks of about the flush threshold,
+/// so only one chunk is held in memory at a time. The body is streamed without a
+/// `Content-Length`, unless it is [`buffered`](Ndjson::buffered).
+///
+/// If an item fails to serialize, the body fails with the error, aborting the request.
+#[must_use = "an Ndjson body does nothing until it is sent"]
+pub struct Ndjson<S> {
+    items: S,
+    flush_threshold: usize,
+    buffered: bool,
+}
+
+pin_project! {
+    /// Serializes the items of a stream into chunks of lines.
+    struct Lines<S> {
+        #[pin]
+        items: S,
+        buf: Vec<u8>,
+        flush_threshold: usize,
+        done: bool,
+    }
+}
+
+impl Body {
+    /// Creates an `application/x-ndjson` body from an iterator of items.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wreq::Body;
+    /// let docs = (0..3).map(|id| serde_json::json!({ "id": id }));
+    /// let body = Body::from(Body::ndjson_from_iter(docs));
+    /// # drop(body);
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `json` feature enabled.
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn ndjson_from_iter<I>(items: I) -> Ndjson<stream::Iter<I::IntoIter>>
+    where
+        I: IntoIterator,
+        I::Item: Serialize,
+    {
+        Ndjson::new(stream::iter(items))
+    }
+
+    /// Creates an `application/x-ndjson` body from a stream of items.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `json` feature enabled.
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn ndjson_from_stream<S>(items: S) -> Ndjson<S>
+    where
+        S: Stream,
+        S::Item: Serialize,
+    {
+        Ndjson::new(items)
+    }
+}
+
+impl<S> Ndjson<S> {
+    fn new(items: S) -> Ndjson<S> {
+        Ndjson {
+            items,
+            flush_threshold: DEFAULT_FLUSH_THRESHOLD,
+            buffered: false,
+        }
+    }
+
+    /// Sets the number of bytes of serialized lines buffered before they are sent as a chunk.
+    ///
+    /// A chunk is also sent when a stream of items has no item ready. Defaults to 16 KiB.
+    pub fn flush_threshold(mut self, bytes: usize) -> Self {
+        self.flush_threshold = bytes.max(1);
+        self
+    }
+}
+
+impl<I> Ndjson<stream::Iter<I>>
+where
+    I: ExactSizeIterator,
+{
+    /// Serializes all items up front, so that the body is sent with a `Content-Length`.
+    ///
+    /// This holds the whole body in memory, and is only available for iterators of a known
+    /// length.
+    pub fn buffered(mut self) -> Self {
+        self.buffered = true;
+        self
+    }
+}
+
+impl<S> From<Ndjson<S>> for Body
+where
+    S: Stream + Send + 'static,
+    S::Item: Serialize,
+{
+    fn from(ndjson: Ndjson<S>) -> Body {
+        let mut lines = Lines {
+            items: ndjson.items,
+            buf: Vec::new(),
+            flush_threshold: ndjson.flush_threshold,
+            done: false,
+        };
+
+        if !ndjson.buffered {
+            return Body::stream(lines);
+        }
+
+        // only iterators are buffered, so every item is ready
+        lines.flush_threshold = usize::MAX;
+        match Box::pin(lines).next().now_or_never().flatten() {
+            Some(Ok(body)) => Body::reusable(body),
+            Some(Err(err)) => Body::stream(stream::once(async { Err::<Bytes, _>(err) })),
+            None => Body::empty(),
+        }
+    }
+}
+
+impl<S> Stream for Lines<S>
+where
+    S: Stream,
+    S::Item: Serialize,
+{
+    type Item = Result<Bytes, serde_json::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        if *this.done {
+            return Poll::Ready(None);
+        }
+
+        while this.buf.len() < *this.flush_threshold {
+            match this.items.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    if let Err(err) = serde_json::to_writer(&mut *this.buf, &item) {
+                        *this.done = true;
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                    this.buf.push(b'\n');
+                }
+                Poll::Ready(None) => {
+                    *this.done = true;
+                    break;
+                }
+                // send what is buffered while waiting for the next item
+                Poll::Pending if this.buf.is_empty() => return Poll::Pending,
+                Poll::Pending => break,
+            }
+        }
+
+        if this.buf.is_empty() {
+            return Poll::Ready(None);
+        }
+
+        let chunk = std::mem::replace(this.buf, Vec::with_capacity(*this.flush_threshold));
+        Poll::Ready(Some(Ok(Bytes::from(chunk))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http_body::Body as _;
+    use http_body_util::BodyExt;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Doc {
+        id: u32,
+    }
+
+    #[tokio::test]
+    async fn ndjson_chunks_lines_by_flush_threshold() {
+        let docs = (0..4).map(|id| Doc { id });
+        let mut body = Body::from(Body::ndjson_from_iter(docs).flush_threshold(20));
+        assert_eq!(body.size_hint().exact(), None);
+
+        let mut chunks = Vec::new();
+        while let Some(frame) = body.frame().await {
+            chunks.push(frame.unwrap().into_data().unwrap());
+        }
+        assert_eq!(
+            chunks,
+            ["{\"id\":0}\n{\"id\":1}\n{\"id\":2}\n", "{\"id\":3}\n"]
+        );
+    }
+
+    #[tokio::test]
+    async fn buffered_ndjson_has_content_length() {
+        let body = Body::from(Body::ndjson_from_iter([Doc { id: 1 }, Doc { id: 2 }]).buffered());
+        assert_eq!(body.size_hint().exact(), Some(18));
+        assert_eq!(body.as_bytes(), Some(&b"{\"id\":1}\n{\"id\":2}\n"[..]));
+
+        let empty = Body::from(Body::ndjson_from_iter(Vec::<Doc>::new()).buffered());
+        assert_eq!(empty.size_hint().exact(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn ndjson_serialization_error_fails_body() {
+        use std::collections::HashMap;
+
+        // maps with non-string keys cannot be serialized
+        let bad = HashMap::from([((1, 2), 3)]);
+        let body = Body::from(Body::ndjson_from_iter([bad]));
+        assert!(body.collect().await.is_err());
+    }
+}
@@ -1,3 +1,5 @@
+#[cfg(feature = "tls")]
+use std::borrow::Cow;
 use std::{
     convert::TryFrom,
     fmt,
@@ -15,23 +17,37 @@ use serde::Serialize;
     feature = "brotli",
     feature = "deflate",
 ))]
-use super::middleware::{config::RequestAcceptEncoding, decoder::AcceptEncoding};
+use super::middleware::{
+    config::{RequestAcceptEncoding, RequestSalvagePartialBody},
+    decoder::AcceptEncoding,
+};
 #[cfg(feature = "multipart")]
 use super::multipart;
 use super::{
     body::Body,
     client::{Client, Pending},
-    middleware::config::{
-        RequestReadTimeout, RequestRedirectPolicy, RequestSkipDefaultHeaders, RequestTotalTimeout,
+    forwarded::ForwardedContext,
+    middleware::{
+        config::{
+            RequestExpectedDigest, RequestHeadersHook, RequestReadTimeout, RequestRedirectPolicy,
+            RequestRemovedHeaders, RequestSkipDefaultHeaders, RequestThinkTime,
+            RequestTotalTimeout,
+        },
+        headers_hook::HeadersHook,
     },
     response::Response,
 };
 use crate::{
-    EmulationProviderFactory, Error, Method, OriginalHeaders, Proxy, Url,
+    DigestAlgorithm, EmulationProviderFactory, Error, ExpectedDigest, IntoUrl, Ipv6Zone, Method,
+    OriginalHeaders, Proxy, Url,
     core::{
-        client::{config::TransportConfig, connect::TcpConnectOptions},
+        client::{
+            config::{TransportConfig, http2::StreamHints},
+            connect::TcpConnectOptions,
+        },
         ext::{
-            RequestConfig, RequestHttpVersionPref, RequestOriginalHeaders, RequestProxyMatcher,
+            RequestCaptureRawHead, RequestConfig, RequestH2StreamHints, RequestHttpVersionPref,
+            RequestIpv6Zone, RequestNoProxy, RequestOriginalHeaders, RequestProxyMatcher,
             RequestTcpConnectOptions, RequestTransportConfig,
         },
     },
@@ -39,6 +55,16 @@ use crate::{
     proxy::Matcher as ProxyMatcher,
     redirect,
 };
+#[cfg(feature = "tls")]
+use crate::{
+    core::ext::{
+        RequestAlps, RequestCertVerification, RequestServerName, RequestTlsKeyLog,
+        RequestTlsNoResume,
+    },
+    tls::{
+        AlpsOverride, AlpsProtocol, CertStore, CertVerification, KeyLogPolicy, ServerNameOverride,
+    },
+};
 
 /// A request which can be executed with `Client::execute()`.
 pub struct Request {
@@ -95,6 +121,20 @@ impl Request {
         &mut self.url
     }
 
+    /// Get the zone of the IPv6 literal host of the url.
+    #[inline]
+    pub fn ipv6_zone(&self) -> Option<&Ipv6Zone> {
+        RequestConfig::<RequestIpv6Zone>::get(&self.extensions)
+    }
+
+    /// Get a mutable reference to the zone of the IPv6 literal host of the url.
+    ///
+    /// The zone only applies while the host of the url is its address.
+    #[inline(always)]
+    pub fn ipv6_zone_mut(&mut self) -> &mut Option<Ipv6Zone> {
+        RequestConfig::<RequestIpv6Zone>::get_mut(&mut self.extensions)
+    }
+
     /// Get the headers.
     #[inline(always)]
     pub fn headers(&self) -> &HeaderMap {
@@ -143,6 +183,18 @@ impl Request {
         RequestConfig::<RequestHttpVersionPref>::get_mut(&mut self.extensions)
     }
 
+    /// Get a mutable reference to the HTTP/2 stream hints.
+    #[inline(always)]
+    pub(crate) fn h2_stream_hints_mut(&mut self) -> &mut Option<StreamHints> {
+        RequestConfig::<RequestH2StreamHints>::get_mut(&mut self.extensions)
+    }
+
+    /// Get a mutable reference to the raw response head capture option.
+    #[inline(always)]
+    pub(crate) fn capture_raw_head_mut(&mut self) -> &mut Option<bool> {
+        RequestConfig::<RequestCaptureRawHead>::get_mut(&mut self.extensions)
+    }
+
     /// Get a mutable reference to the timeout.
     #[inline(always)]
     pub fn timeout_mut(&mut self) -> &mut Option<Duration> {
@@ -167,6 +219,47 @@ impl Request {
         RequestConfig::<RequestProxyMatcher>::get_mut(&mut self.extensions)
     }
 
+    /// Get a mutable reference to the proxy bypass override.
+    #[inline(always)]
+    pub(crate) fn no_proxy_mut(&mut self) -> &mut Option<bool> {
+        RequestConfig::<RequestNoProxy>::get_mut(&mut self.extensions)
+    }
+
+    /// Get a mutable reference to the TLS server name override.
+    #[cfg(feature = "tls")]
+    #[inline(always)]
+    pub(crate) fn server_name_mut(&mut self) -> &mut Option<ServerNameOverride> {
+        RequestConfig::<RequestServerName>::get_mut(&mut self.extensions)
+    }
+
+    /// Get a mutable reference to the ALPS override.
+    #[cfg(feature = "tls")]
+    #[inline(always)]
+    pub(crate) fn alps_mut(&mut self) -> &mut Option<AlpsOverride> {
+        RequestConfig::<RequestAlps>::get_mut(&mut self.extensions)
+    }
+
+    /// Get a mutable reference to the TLS session resumption override.
+    #[cfg(feature = "tls")]
+    #[inline(always)]
+    pub(crate) fn tls_no_resume_mut(&mut self) -> &mut Option<bool> {
+        RequestConfig::<RequestTlsNoResume>::get_mut(&mut self.extensions)
+    }
+
+    /// Get a mutable reference to the TLS key logging override.
+    #[cfg(feature = "tls")]
+    #[inline(always)]
+    pub(crate) fn keylog_mut(&mut self) -> &mut Option<KeyLogPolicy> {
+        RequestConfig::<RequestTlsKeyLog>::get_mut(&mut self.extensions)
+    }
+
+    /// Get a mutable reference to the certificate verification override.
+    #[cfg(feature = "tls")]
+    #[inline(always)]
+    pub(crate) fn cert_verification_mut(&mut self) -> &mut Option<CertVerification> {
+        RequestConfig::<RequestCertVerification>::get_mut(&mut self.extensions)
+    }
+
     /// Get the accepts encoding.
     #[cfg(any(
         feature = "gzip",
@@ -179,12 +272,48 @@ impl Request {
         RequestConfig::<RequestAcceptEncoding>::get_mut(&mut self.extensions)
     }
 
+    /// Get a mutable reference to the partial body salvaging option.
+    #[cfg(any(
+        feature = "gzip",
+        feature = "zstd",
+        feature = "brotli",
+        feature = "deflate",
+    ))]
+    #[inline(always)]
+    pub(crate) fn salvage_partial_body_mut(&mut self) -> &mut Option<bool> {
+        RequestConfig::<RequestSalvagePartialBody>::get_mut(&mut self.extensions)
+    }
+
     /// Skip client default headers.
     #[inline(always)]
     pub(crate) fn default_headers_mut(&mut self) -> &mut Option<bool> {
         RequestConfig::<RequestSkipDefaultHeaders>::get_mut(&mut self.extensions)
     }
 
+    /// Get a mutable reference to the headers not to send.
+    #[inline(always)]
+    pub(crate) fn removed_headers_mut(&mut self) -> &mut Option<Vec<HeaderName>> {
+        RequestConfig::<RequestRemovedHeaders>::get_mut(&mut self.extensions)
+    }
+
+    /// Get a mutable reference to the headers hook of the emulation.
+    #[inline(always)]
+    pub(crate) fn headers_hook_mut(&mut self) -> &mut Option<HeadersHook> {
+        RequestConfig::<RequestHeadersHook>::get_mut(&mut self.extensions)
+    }
+
+    /// Get a mutable reference to the think time override.
+    #[inline(always)]
+    pub(crate) fn think_time_mut(&mut self) -> &mut Option<bool> {
+        RequestConfig::<RequestThinkTime>::get_mut(&mut self.extensions)
+    }
+
+    /// Get a mutable reference to the expected digest of the response body.
+    #[inline(always)]
+    pub(crate) fn expected_digest_mut(&mut self) -> &mut Option<ExpectedDigest> {
+        RequestConfig::<RequestExpectedDigest>::get_mut(&mut self.extensions)
+    }
+
     #[inline(always)]
     pub(crate) fn transport_config_mut(&mut self) -> &mut Option<TransportConfig> {
         RequestConfig::<RequestTransportConfig>::get_mut(&mut self.extensions)
@@ -214,6 +343,10 @@ impl Request {
         *req.headers_mut() = self.headers().clone();
         *req.version_mut() = self.version().cloned();
         *req.extensions_mut() = self.extensions().clone();
+        // the clone is sent on its own, so it hashes its body on its own
+        if let Some(digest) = RequestConfig::<RequestExpectedDigest>::get(self.extensions()) {
+            *req.expected_digest_mut() = Some(digest.fork());
+        }
         req.body = body;
         Some(req)
     }
@@ -332,6 +465,22 @@ impl RequestBuilder {
         self
     }
 
+    /// Add this client's hop to the `Forwarded` or `X-Forwarded-*` headers of the request.
+    ///
+    /// Meant for reverse proxies. Values already on the request are extended rather than
+    /// replaced, so the headers of the incoming request should be set first. See
+    /// [`forwarded`](crate::forwarded) for details.
+    pub fn forwarded(mut self, context: ForwardedContext) -> RequestBuilder {
+        let mut error = None;
+        if let Ok(ref mut req) = self.request {
+            error = context.apply(req.headers_mut()).err();
+        }
+        if let Some(err) = error {
+            self.request = Err(err);
+        }
+        self
+    }
+
     /// Set the original headers for this request.
     pub fn original_headers(mut self, original_headers: OriginalHeaders) -> RequestBuilder {
         if let Ok(ref mut req) = self.request {
@@ -348,6 +497,51 @@ impl RequestBuilder {
         self
     }
 
+    /// Removes a header from this request, so that it is not sent.
+    ///
+    /// Unlike [`default_headers`](Self::default_headers), which leaves out all the default
+    /// headers of the client, only this header is left out: whether it is a default header,
+    /// set by an emulation, by its headers hook, or on this request. The removal also applies
+    /// to the requests following redirects. Headers added by the client as the request is
+    /// sent, such as `Cookie` from the cookie store and `Accept-Encoding`, are still sent.
+    ///
+    /// ```rust
+    /// # use wreq::Error;
+    /// #
+    /// # async fn run() -> Result<(), Error> {
+    /// let client = wreq::Client::new();
+    /// let res = client
+    ///     .get("https://www.rust-lang.org")
+    ///     .remove_header("sec-fetch-user")
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remove_header<K>(mut self, key: K) -> RequestBuilder
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<http::Error>,
+    {
+        let mut error = None;
+        if let Ok(ref mut req) = self.request {
+            match <HeaderName as TryFrom<K>>::try_from(key) {
+                Ok(key) => {
+                    req.headers_mut().remove(&key);
+                    let removed = req.removed_headers_mut().get_or_insert_default();
+                    if !removed.contains(&key) {
+                        removed.push(key);
+                    }
+                }
+                Err(e) => error = Some(Error::builder(e.into())),
+            }
+        }
+        if let Some(err) = error {
+            self.request = Err(err);
+        }
+        self
+    }
+
     /// Enable HTTP authentication.
     pub fn auth<V>(self, value: V) -> RequestBuilder
     where
@@ -414,7 +608,8 @@ impl RequestBuilder {
     ///
     /// The timeout is applied from when the request starts connecting until the
     /// response body has finished. It affects only this request and overrides
-    /// the timeout configured using `ClientBuilder::timeout()`.
+    /// the timeout configured using `ClientBuilder::timeout()`. A zero duration
+    /// disables the timeout for this request.
     pub fn timeout(mut self, timeout: Duration) -> RequestBuilder {
         if let Ok(ref mut req) = self.request {
             *req.timeout_mut() = Some(timeout);
@@ -427,6 +622,7 @@ impl RequestBuilder {
     /// The read timeout is applied from when the response body starts being read
     /// until the response body has finished. It affects only this request and
     /// overrides the read timeout configured using `ClientBuilder::read_timeout()`.
+    /// A zero duration disables the read timeout for this request.
     pub fn read_timeout(mut self, timeout: Duration) -> RequestBuilder {
         if let Ok(ref mut req) = self.request {
             *req.read_timeout_mut() = Some(timeout);
@@ -434,6 +630,100 @@ impl RequestBuilder {
         self
     }
 
+    /// Sends this request without waiting the think time of the client.
+    ///
+    /// Useful for requests a page makes on its own, such as API calls, which a person
+    /// browsing would not pause before. See
+    /// [`ClientBuilder::think_time`](crate::ClientBuilder::think_time).
+    pub fn no_think_time(mut self) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *req.think_time_mut() = Some(false);
+        }
+        self
+    }
+
+    /// Verifies the response body against an `expected` digest computed with `algorithm`.
+    ///
+    /// The body is hashed as it is read, after decompression, by whichever method reads it.
+    /// Once it was read to its end, a digest that is not the expected one fails reading it:
+    /// [`bytes`](Response::bytes) and the other methods reading the whole body return the
+    /// error, and [`bytes_stream`](Response::bytes_stream) yields it as its last item. The
+    /// details are given by [`Error::digest_mismatch`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use wreq::DigestAlgorithm;
+    ///
+    /// # async fn run(expected: [u8; 32]) -> wreq::Result<()> {
+    /// let artifact = wreq::Client::new()
+    ///     .get("https://example.com/artifact.tar.gz")
+    ///     .verify_digest(DigestAlgorithm::Sha256, expected)
+    ///     .send()
+    ///     .await?
+    ///     .bytes()
+    ///     .await?;
+    /// # let _ = artifact;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `expected` is not the
+    /// [`output_len`](DigestAlgorithm::output_len) of `algorithm`.
+    pub fn verify_digest<D>(self, algorithm: DigestAlgorithm, expected: D) -> RequestBuilder
+    where
+        D: AsRef<[u8]>,
+    {
+        self.expected_digest(ExpectedDigest::new(algorithm, expected))
+    }
+
+    /// Verifies the response body against an [`ExpectedDigest`], continuing the data it
+    /// hashed so far.
+    ///
+    /// Unlike [`verify_digest`](Self::verify_digest), the digest can be shared with the request
+    /// resuming an interrupted download: when the response is a `206 Partial Content` starting
+    /// at [`hashed_bytes`](ExpectedDigest::hashed_bytes), its body is hashed as the rest of the
+    /// interrupted one. Any other response restarts hashing, and a partial response starting
+    /// elsewhere fails.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use wreq::{DigestAlgorithm, ExpectedDigest, header::RANGE};
+    ///
+    /// # async fn run(expected: [u8; 32]) -> wreq::Result<()> {
+    /// let client = wreq::Client::new();
+    /// let url = "https://example.com/artifact.tar.gz";
+    /// let digest = ExpectedDigest::new(DigestAlgorithm::Sha256, expected);
+    ///
+    /// let mut artifact = Vec::new();
+    /// loop {
+    ///     let res = client
+    ///         .get(url)
+    ///         .header(RANGE, format!("bytes={}-", artifact.len()))
+    ///         .expected_digest(digest.clone())
+    ///         .send()
+    ///         .await?;
+    ///     match res.bytes_allow_incomplete().await? {
+    ///         (bytes, None) => {
+    ///             artifact.extend_from_slice(&bytes);
+    ///             break;
+    ///         }
+    ///         (bytes, Some(_)) => artifact.extend_from_slice(&bytes),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn expected_digest(mut self, digest: ExpectedDigest) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *req.expected_digest_mut() = Some(digest);
+        }
+        self
+    }
+
     /// Sends a multipart/form-data body.
     ///
     /// ```
@@ -519,6 +809,54 @@ impl RequestBuilder {
         self
     }
 
+    /// Caps the flow-control window the client reopens for the response body of this request,
+    /// in bytes, when it is sent over HTTP/2.
+    ///
+    /// Window updates for the stream are held back so that no more than `window` bytes are
+    /// open to the server, bounding how much of the body is buffered ahead of the reader. The
+    /// stream still starts with the connection's initial stream window, so the cap takes
+    /// effect once that has been consumed.
+    ///
+    /// This is advisory and has no effect on HTTP/1 requests.
+    pub fn h2_stream_window(mut self, window: u32) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            req.h2_stream_hints_mut().get_or_insert_default().window = Some(window);
+        }
+        self
+    }
+
+    /// Caps the size of the DATA frames the body of this request is sent in, in bytes, when it
+    /// is sent over HTTP/2.
+    ///
+    /// Body chunks larger than `size` are split, and each piece is sent once the stream has
+    /// send capacity for it. Frames are also limited by the peer's maximum frame size.
+    ///
+    /// This is advisory and has no effect on HTTP/1 requests.
+    pub fn h2_chunk_hint(mut self, size: usize) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            req.h2_stream_hints_mut().get_or_insert_default().chunk_hint = Some(size);
+        }
+        self
+    }
+
+    /// Sets if the head of the response to this request is captured as it was received.
+    ///
+    /// The capture is attached to the response as a [`RawResponseHead`] extension, keeping the
+    /// order, the casing and the repeated lines of the fields that the parsed headers lose, as
+    /// verifying a signature over the head may require. See [`message_signature`] to build the
+    /// signature base of a response from it.
+    ///
+    /// When redirects are followed, the capture is of the head of the final response.
+    ///
+    /// [`RawResponseHead`]: crate::RawResponseHead
+    /// [`message_signature`]: crate::message_signature
+    pub fn capture_raw_response_head(mut self, enable: bool) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *req.capture_raw_head_mut() = Some(enable);
+        }
+        self
+    }
+
     /// Set the redirect policy for this request.
     pub fn redirect(mut self, policy: redirect::Policy) -> RequestBuilder {
         if let Ok(ref mut req) = self.request {
@@ -567,8 +905,37 @@ impl RequestBuilder {
         self
     }
 
+    /// Sets if this request keeps the data decoded before a decompression error.
+    ///
+    /// By default, a response body that fails to decompress fails as a whole. With this
+    /// enabled, the body ends normally with the data decoded so far instead, if any was, and
+    /// reading it to its end with [`Response::chunk`] attaches a [`PartialBody`] extension to
+    /// the response describing the error. The connection the body was read from is not reused.
+    ///
+    /// Errors of the underlying connection are not affected.
+    ///
+    /// [`Response::chunk`]: crate::Response::chunk
+    /// [`PartialBody`]: crate::PartialBody
+    #[cfg(any(
+        feature = "gzip",
+        feature = "zstd",
+        feature = "brotli",
+        feature = "deflate",
+    ))]
+    pub fn salvage_partial_body(mut self, enable: bool) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *req.salvage_partial_body_mut() = Some(enable);
+        }
+        self
+    }
+
     /// Set the proxy for this request.
     ///
+    /// When the proxy intercepts the request, it is used instead of the proxies of the client,
+    /// with its basic auth and custom headers, both for tunnels and for plain HTTP requests
+    /// forwarded to it. Connections through the proxy are only reused by requests through the
+    /// same proxy, and redirects keep using it.
+    ///
     /// # Examples
     ///
     /// ```
@@ -593,6 +960,47 @@ impl RequestBuilder {
         self
     }
 
+    /// Connect directly for this request, bypassing every proxy.
+    ///
+    /// Neither the proxy of the request, the proxies of the client, nor the system proxy are
+    /// used. Connections made this way are pooled separately from proxied connections to the
+    /// same host.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = wreq::Client::builder()
+    ///     .proxy(wreq::Proxy::all("http://hyper.rs/prox")?)
+    ///     .build()?;
+    ///
+    /// let resp = client
+    ///     .get("https://www.example.com/health")
+    ///     .no_proxy()
+    ///     .send()
+    ///     .await?;
+    /// ```
+    pub fn no_proxy(mut self) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *req.no_proxy_mut() = Some(true);
+        }
+        self
+    }
+
+    /// Insert a value into the extensions of this request, replacing a value of the same type.
+    ///
+    /// Extensions are not sent; they carry per-request values to the configuration of the
+    /// client, such as the session id read by the
+    /// [`auth_template`](crate::Proxy::auth_template) of a proxy.
+    pub fn extension<T>(mut self, extension: T) -> RequestBuilder
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        if let Ok(ref mut req) = self.request {
+            req.extensions_mut().insert(extension);
+        }
+        self
+    }
+
     /// Set the local address for this request.
     pub fn local_address<V>(mut self, local_address: V) -> RequestBuilder
     where
@@ -642,6 +1050,171 @@ impl RequestBuilder {
         self
     }
 
+    /// Set the TLS server name (SNI) sent for this request, instead of the URL host.
+    ///
+    /// The connection is still made to the host in the URL, and the server certificate is
+    /// verified against this name unless [`sni_verify_uri_host`](Self::sni_verify_uri_host)
+    /// is enabled. TLS sessions are not resumed on connections with an overridden server name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = wreq::Client::new();
+    ///
+    /// let resp = client
+    ///     .get("https://203.0.113.10/")
+    ///     .sni("front.example.com")
+    ///     .send()
+    ///     .await?;
+    /// ```
+    #[cfg(feature = "tls")]
+    pub fn sni<S>(mut self, name: S) -> RequestBuilder
+    where
+        S: Into<Cow<'static, str>>,
+    {
+        if let Ok(ref mut req) = self.request {
+            req.server_name_mut().get_or_insert_default().sni = Some(name.into());
+        }
+        self
+    }
+
+    /// Verify the server certificate against the URL host rather than the name set with
+    /// [`sni`](Self::sni).
+    ///
+    /// Has no effect unless the server name is overridden. Defaults to `false`.
+    #[cfg(feature = "tls")]
+    pub fn sni_verify_uri_host(mut self, enabled: bool) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            req.server_name_mut()
+                .get_or_insert_default()
+                .verify_uri_host = enabled;
+        }
+        self
+    }
+
+    /// Set the ALPS protocols for this request, overriding the TLS configuration.
+    ///
+    /// `None` disables ALPS, and `use_new_codepoint` selects the new ALPS extension codepoint.
+    /// Connections with different ALPS settings are pooled separately.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wreq::tls::AlpsProtocol;
+    ///
+    /// let client = wreq::Client::new();
+    ///
+    /// let resp = client
+    ///     .get("https://www.example.com/")
+    ///     .alps(Some(&[AlpsProtocol::HTTP2]), true)
+    ///     .send()
+    ///     .await?;
+    /// ```
+    #[cfg(feature = "tls")]
+    pub fn alps(
+        mut self,
+        alps: Option<&[AlpsProtocol]>,
+        use_new_codepoint: bool,
+    ) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *req.alps_mut() = Some(AlpsOverride {
+                protos: alps.map(AlpsProtocol::encode_sequence),
+                use_new_codepoint,
+            });
+        }
+        self
+    }
+
+    /// Disables TLS session resumption for the connection serving this request.
+    ///
+    /// The connection performs a full handshake, without looking up a cached session or
+    /// offering a session ticket. The session it obtains is still cached, so subsequent
+    /// requests resume normally. Connections without resumption are pooled separately.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = wreq::Client::new();
+    ///
+    /// let resp = client
+    ///     .get("https://www.example.com/")
+    ///     .tls_no_resume(true)
+    ///     .send()
+    ///     .await?;
+    /// ```
+    #[cfg(feature = "tls")]
+    pub fn tls_no_resume(mut self, no_resume: bool) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *req.tls_no_resume_mut() = Some(no_resume);
+        }
+        self
+    }
+
+    /// Logs the TLS keys of the connection serving this request, overriding the client's
+    /// [`keylog`](crate::ClientBuilder::keylog) policy.
+    ///
+    /// Only connections established for this request log their keys to the file of `policy`,
+    /// those of other requests keep following the policy of the client. Connections with a
+    /// key logging override are pooled separately, so they only serve requests with the same
+    /// override. A request reusing such a connection does not log keys again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wreq::tls::KeyLogPolicy;
+    ///
+    /// let client = wreq::Client::new();
+    ///
+    /// let resp = client
+    ///     .get("https://www.example.com/")
+    ///     .keylog(KeyLogPolicy::File("/tmp/sslkeys.log".into()))
+    ///     .send()
+    ///     .await?;
+    /// ```
+    #[cfg(feature = "tls")]
+    pub fn keylog(mut self, policy: KeyLogPolicy) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *req.keylog_mut() = Some(policy);
+        }
+        self
+    }
+
+    /// Controls the use of certificate validation for this request, overriding the client.
+    ///
+    /// Connections made with a different verification than the client's are pooled
+    /// separately, and never resume TLS sessions, so they are not reused by other requests.
+    ///
+    /// # Warning
+    ///
+    /// You should think very carefully before using this method. If
+    /// invalid certificates are trusted, *any* certificate for *any* site
+    /// will be trusted for use. This includes expired certificates. This
+    /// introduces significant vulnerabilities, and should only be used
+    /// as a last resort.
+    #[cfg(feature = "tls")]
+    pub fn danger_accept_invalid_certs(mut self, accept_invalid_certs: bool) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            req.cert_verification_mut()
+                .get_or_insert_default()
+                .accept_invalid_certs = Some(accept_invalid_certs);
+        }
+        self
+    }
+
+    /// Sets the verify certificate store for this request, overriding the client store.
+    ///
+    /// Like [`danger_accept_invalid_certs`](Self::danger_accept_invalid_certs), connections
+    /// verified against this store are pooled separately.
+    #[cfg(feature = "tls")]
+    pub fn cert_store(mut self, store: CertStore) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            req.cert_verification_mut()
+                .get_or_insert_default()
+                .cert_store = Some(store);
+        }
+        self
+    }
+
     /// Configures the request builder to emulation the specified HTTP context.
     ///
     /// This method sets the necessary headers, HTTP/1 and HTTP/2 configurations, and TLS config
@@ -658,8 +1231,13 @@ impl RequestBuilder {
 
             transport_config.set_http1_config(emulation.http1_config);
             transport_config.set_http2_config(emulation.http2_config);
+            #[cfg(feature = "tls")]
             transport_config.set_tls_config(emulation.tls_config);
 
+            if let Some(headers_hook) = emulation.headers_hook {
+                *req.headers_hook_mut() = Some(headers_hook);
+            }
+
             if let Some(default_headers) = emulation.default_headers {
                 self = self.headers(default_headers);
             }
@@ -745,6 +1323,44 @@ impl RequestBuilder {
         self
     }
 
+    /// Send a newline-delimited JSON body, setting the `Content-Type` to
+    /// `application/x-ndjson` if it is not set yet.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn run() -> wreq::Result<()> {
+    /// let docs = (0..100_000).map(|id| serde_json::json!({ "index": { "_id": id } }));
+    ///
+    /// let res = wreq::Client::new()
+    ///     .post("http://localhost:9200/_bulk")
+    ///     .ndjson(wreq::Body::ndjson_from_iter(docs))
+    ///     .send()
+    ///     .await?;
+    /// # drop(res);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `json` feature enabled.
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn ndjson<S>(mut self, body: Ndjson<S>) -> RequestBuilder
+    where
+        S: futures_util::Stream + Send + 'static,
+        S::Item: Serialize,
+    {
+        if let Ok(ref mut req) = self.request {
+            req.headers_mut()
+                .entry(CONTENT_TYPE)
+                .or_insert(HeaderValue::from_static("application/x-ndjson"));
+            *req.body_mut() = Some(body.into());
+        }
+        self
+    }
+
     /// Build a `Request`, which can be inspected, modified and executed with
     /// `Client::execute()`.
     pub fn build(self) -> crate::Result<Request> {
@@ -813,6 +1429,62 @@ impl RequestBuilder {
                 request: Ok(req),
             })
     }
+
+    /// Splits the RequestBuilder into one builder per URL.
+    ///
+    /// Every builder shares the method, headers, body, timeouts and extensions of this
+    /// one, but targets its own URL, and can be sent independently. An invalid URL is
+    /// reported when its builder is sent, as with [`Client::request`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if this builder already holds an error, or if the request body is a
+    /// stream and therefore can not be reused.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wreq::Error;
+    /// #
+    /// # async fn run() -> Result<(), Error> {
+    /// let client = wreq::Client::new();
+    /// let builders = client
+    ///     .post("http://eu.example.com/write")
+    ///     .body("payload")
+    ///     .fan_out(["http://eu.example.com/write", "http://us.example.com/write"])?;
+    ///
+    /// let responses = futures_util::future::join_all(builders.into_iter().map(|b| b.send())).await;
+    /// # drop(responses);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fan_out<I, U>(self, urls: I) -> crate::Result<Vec<RequestBuilder>>
+    where
+        I: IntoIterator<Item = U>,
+        U: IntoUrl,
+    {
+        let req = self.request?;
+        if req.body().is_some_and(|body| body.try_clone().is_none()) {
+            return Err(Error::builder("request body is not reusable"));
+        }
+
+        let builders = urls
+            .into_iter()
+            .map(|url| {
+                let request = url.into_url_with_zone().and_then(|(url, zone)| {
+                    let mut req = req
+                        .try_clone()
+                        .ok_or_else(|| Error::builder("request body is not reusable"))?;
+                    *req.url_mut() = url;
+                    *req.ipv6_zone_mut() = zone;
+                    Ok(req)
+                });
+                RequestBuilder::new(self.client.clone(), request)
+            })
+            .collect();
+
+        Ok(builders)
+    }
 }
 
 impl fmt::Debug for Request {
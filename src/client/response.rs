@@ -1,9 +1,9 @@
 use std::{fmt, net::SocketAddr};
 
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 #[cfg(feature = "charset")]
 use encoding_rs::{Encoding, UTF_8};
-use http::{HeaderMap, StatusCode, Version};
+use http::{HeaderMap, StatusCode, Uri, Version};
 #[cfg(feature = "charset")]
 use mime::Mime;
 #[cfg(feature = "json")]
@@ -15,9 +15,14 @@ use super::body::{Body, ResponseBody};
 use crate::cookie;
 use crate::{
     Error, Upgraded,
+    connect::ProxyUsed,
     core::{client::connect::HttpInfo, ext::ReasonPhrase},
 };
 
+/// How much of an error response body [`Response::error_for_status_drained`] reads before
+/// giving up on reusing its connection.
+const ERROR_BODY_DRAIN_MAX: u64 = 64 * 1024;
+
 /// A Response to a submitted `Request`.
 pub struct Response {
     res: http::Response<Body>,
@@ -102,6 +107,18 @@ impl Response {
             .map(|info| info.remote_addr())
     }
 
+    /// Get the URI of the proxy the connection of this `Response` was established through, or
+    /// `None` if it was connected to directly.
+    ///
+    /// A response received on a reused connection reports the proxy the connection was first
+    /// established through.
+    pub fn proxy_used(&self) -> Option<&Uri> {
+        self.res
+            .extensions()
+            .get::<ProxyUsed>()
+            .map(|proxy| &proxy.0)
+    }
+
     /// Returns a reference to the associated extensions.
     pub fn extensions(&self) -> &http::Extensions {
         self.res.extensions()
@@ -285,6 +302,39 @@ impl Response {
             .map(|buf| buf.to_bytes())
     }
 
+    /// Get the full response body as `Bytes`, keeping the bytes received if the body ends
+    /// before its declared length.
+    ///
+    /// The error of an incomplete body is returned along with the bytes, see
+    /// [`Error::incomplete_body`]. Any other error fails the call.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = wreq::Client::new().get("http://httpbin.org/bytes/1024").send().await?;
+    /// let (bytes, err) = res.bytes_allow_incomplete().await?;
+    /// if let Some(incomplete) = err.as_ref().and_then(wreq::Error::incomplete_body) {
+    ///     println!("resume from byte {}", incomplete.received());
+    /// }
+    /// # drop(bytes);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn bytes_allow_incomplete(mut self) -> crate::Result<(Bytes, Option<Error>)> {
+        let mut buf = BytesMut::new();
+        loop {
+            match self.chunk().await {
+                Ok(Some(chunk)) => buf.extend_from_slice(&chunk),
+                Ok(None) => return Ok((buf.freeze(), None)),
+                Err(err) if err.incomplete_body().is_some() => {
+                    return Ok((buf.freeze(), Some(err)));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
     /// Stream a chunk of the response body.
     ///
     /// When the response body has been exhausted, this will return `None`.
@@ -313,11 +363,85 @@ impl Response {
                 }
                 // else continue
             } else {
+                #[cfg(any(
+                    feature = "gzip",
+                    feature = "zstd",
+                    feature = "brotli",
+                    feature = "deflate",
+                ))]
+                if let Some(partial) = self
+                    .res
+                    .extensions()
+                    .get::<crate::client::middleware::decoder::SalvageSlot>()
+                    .and_then(|slot| slot.take())
+                {
+                    self.res.extensions_mut().insert(partial);
+                }
                 return Ok(None);
             }
         }
     }
 
+    /// Read the response body to completion and discard it, returning its length.
+    ///
+    /// Draining a response instead of dropping it lets an HTTP/1 connection be reused for
+    /// later requests. The body is read as it was sent, so a compressed body is not
+    /// decompressed, and the length is that of the body on the wire. A digest expected with
+    /// [`verify_digest`](crate::RequestBuilder::verify_digest) is not checked. The client's
+    /// read timeouts apply while draining.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = wreq::Client::new().get("https://hyper.rs").send().await?;
+    /// println!("status: {}", res.status());
+    /// res.drain().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn drain(self) -> crate::Result<u64> {
+        self.drain_max(u64::MAX).await
+    }
+
+    /// Like [`drain`](Self::drain), but fails with a body error once more than `max` bytes
+    /// have been read.
+    ///
+    /// The connection is not reused after failing.
+    pub async fn drain_max(mut self, max: u64) -> crate::Result<u64> {
+        #[cfg(any(
+            feature = "gzip",
+            feature = "zstd",
+            feature = "brotli",
+            feature = "deflate",
+        ))]
+        if let Some(switch) = self
+            .res
+            .extensions()
+            .get::<crate::client::middleware::decoder::DrainSwitch>()
+        {
+            switch.bypass();
+        }
+
+        let mut len = 0u64;
+        loop {
+            match self.chunk().await {
+                Ok(Some(chunk)) => {
+                    len += chunk.len() as u64;
+                    if len > max {
+                        return Err(Error::body(format!(
+                            "response body exceeds drain limit of {max} bytes"
+                        )));
+                    }
+                }
+                Ok(None) => return Ok(len),
+                // The bytes are discarded, and no longer decoded before being hashed.
+                Err(err) if err.digest_mismatch().is_some() => return Ok(len),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
     /// Convert the response into a `Stream` of `Bytes` from the body.
     ///
     /// # Example
@@ -348,6 +472,90 @@ impl Response {
         super::body::DataStream(self.res.into_body())
     }
 
+    /// Convert the response into a `Stream` of `Bytes` from the body, along with a
+    /// [`FlowHandle`](crate::FlowHandle) to pause and resume reading it.
+    ///
+    /// Unlike not polling the stream from [`bytes_stream`](Self::bytes_stream), pausing
+    /// through the handle also stops the connection from requesting more data, so the
+    /// server stops sending instead of filling connection buffers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures_util::StreamExt;
+    ///
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let (mut stream, flow) = wreq::Client::new()
+    ///     .get("http://httpbin.org/stream-bytes/65536")
+    ///     .send()
+    ///     .await?
+    ///     .bytes_stream_with_control();
+    ///
+    /// flow.set_target_buffer(64 * 1024);
+    ///
+    /// while let Some(item) = stream.next().await {
+    ///     println!("Chunk: {:?}", item?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `stream` feature to be enabled.
+    #[cfg(feature = "stream")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "stream")))]
+    pub fn bytes_stream_with_control(
+        self,
+    ) -> (
+        impl futures_util::Stream<Item = crate::Result<Bytes>>,
+        crate::FlowHandle,
+    ) {
+        let flow = self.res.extensions().get().cloned();
+        super::body::ControlledStream::new(super::body::DataStream(self.res.into_body()), flow)
+    }
+
+    /// Convert the response into an `AsyncRead` of the body.
+    ///
+    /// Errors reading the body are converted into an [`std::io::Error`] holding the original
+    /// [`Error`], which can be recovered by downcasting its inner error. Its kind is mapped as
+    /// described on the `From<Error>` implementation of `io::Error`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tokio::io::AsyncReadExt;
+    ///
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut reader = wreq::Client::new()
+    ///     .get("http://httpbin.org/ip")
+    ///     .send()
+    ///     .await?
+    ///     .into_async_read();
+    ///
+    /// let mut body = String::new();
+    /// if let Err(err) = reader.read_to_string(&mut body).await {
+    ///     if let Some(err) = err.get_ref().and_then(|e| e.downcast_ref::<wreq::Error>()) {
+    ///         println!("request failed: {err}");
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `stream` feature to be enabled.
+    #[cfg(feature = "stream")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "stream")))]
+    pub fn into_async_read(self) -> impl tokio::io::AsyncBufRead + Send + Unpin {
+        use futures_util::TryStreamExt;
+
+        tokio_util::io::StreamReader::new(
+            super::body::DataStream(self.res.into_body()).map_err(std::io::Error::from),
+        )
+    }
+
     // util methods
 
     /// Turn a response into an error if the server returned an error.
@@ -406,6 +614,38 @@ impl Response {
         }
     }
 
+    /// Like [`error_for_status`](Self::error_for_status), but drains the body of an error
+    /// response so its connection can be reused.
+    ///
+    /// At most 64 KiB of the body are drained, as with [`drain_max`](Self::drain_max). A longer
+    /// body is dropped instead, closing its connection. A failure to drain the body is ignored
+    /// in favor of the status error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = wreq::Client::new()
+    ///     .get("https://hyper.rs")
+    ///     .send()
+    ///     .await?
+    ///     .error_for_status_drained()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn error_for_status_drained(self) -> crate::Result<Self> {
+        let status = self.status();
+        if status.is_client_error() || status.is_server_error() {
+            let reason = self.extensions().get::<ReasonPhrase>().cloned();
+            let url = self.url.clone();
+            let _ = self.drain_max(ERROR_BODY_DRAIN_MAX).await;
+            Err(Error::status_code(*url, status, reason))
+        } else {
+            Ok(self)
+        }
+    }
+
     /// Consumes the response and returns a future for a possible HTTP upgrade.
     pub async fn upgrade(self) -> crate::Result<Upgraded> {
         crate::core::upgrade::on(self.res)
@@ -4,13 +4,18 @@ use std::{
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use http::uri::Scheme;
+use futures_util::{StreamExt, stream::FuturesUnordered};
+use http::{Uri, header::PROXY_AUTHORIZATION, uri::Scheme};
 use pin_project_lite::pin_project;
+#[cfg(feature = "tls")]
 use tls_conn::TlsConn;
 use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixStream;
+#[cfg(feature = "tls")]
 use tokio_boring2::SslStream;
 use tower::{
     ServiceBuilder,
@@ -20,25 +25,98 @@ use tower::{
 use tower_service::Service;
 
 pub(crate) use self::conn::{Conn, Unnameable};
+#[cfg(unix)]
+use crate::core::client::proxy::matcher;
+#[cfg(not(feature = "tls"))]
+use crate::error::TlsDisabled;
 use crate::{
     core::{
         client::{
             ConnRequest,
-            connect::{self, Connected, Connection, TcpConnectOptions, proxy},
+            connect::{self, Connected, Connection, ProxyProtocolConfig, TcpConnectOptions, proxy},
         },
         rt::{Read, ReadBufCursor, TokioIo, Write},
     },
-    dns::DynResolver,
-    error::{BoxError, TimedOut, map_timeout_to_connector_error},
-    proxy::{Intercepted, Matcher as ProxyMatcher},
+    dns::{DynResolver, ResolutionMemo},
+    error::{BoxError, Error, ProxyConnectError, TimedOut, map_timeout_to_connector_error},
+    proxy::{
+        Intercepted, Matcher as ProxyMatcher, ProxyAuth, ProxyLatency, ProxyRace, ProxyStrategy,
+        ProxyTtfb, TemplatedAuth,
+    },
+};
+#[cfg(feature = "tls")]
+use crate::{
+    error::{ConcurrentUpdate, ProxyTlsError},
+    sync::{Mutex, RwLock},
     tls::{
-        CertStore, HttpsConnector, Identity, KeyLogPolicy, MaybeHttpsStream, TlsConfig,
-        TlsConnector, TlsConnectorBuilder, TlsInfo, TlsVersion,
+        AlpnProtocol, CertStore, EarlyDataHandshake, HostPattern, HttpsConnector, Identity,
+        KeyLogPolicy, MaybeHttpsStream, RevocationCheck, TlsConfig, TlsConnector,
+        TlsConnectorBuilder, TlsHandshakeCapture, TlsInfo, TlsSessions, TlsVersion,
+        handshake_capture, handshake_started, tls_info,
     },
 };
 
 type BoxConn = Box<dyn AsyncConnWithInfo>;
 
+/// Response extension naming the proxy the connection a request was sent on was established
+/// through.
+#[derive(Debug, Clone)]
+pub(crate) struct ProxyUsed(pub(crate) Uri);
+
+/// Response extension reporting how long it took to establish the connection a request was
+/// sent on.
+///
+/// Requests sent on a reused pooled connection report `reused` with zero durations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectTiming {
+    tcp: Duration,
+    tls: Option<Duration>,
+    reused: bool,
+}
+
+impl ConnectTiming {
+    /// The timing of a request sent on a reused connection.
+    pub(crate) const REUSED: ConnectTiming = ConnectTiming {
+        tcp: Duration::ZERO,
+        tls: None,
+        reused: true,
+    };
+
+    /// Measures a connection established since `start`.
+    fn measure(start: Instant, conn: &dyn AsyncConnWithInfo) -> ConnectTiming {
+        let now = Instant::now();
+        let (tcp, tls) = match conn.tls_handshake_started() {
+            Some(handshake) => (
+                handshake.saturating_duration_since(start),
+                Some(now.saturating_duration_since(handshake)),
+            ),
+            None => (now.saturating_duration_since(start), None),
+        };
+
+        ConnectTiming {
+            tcp,
+            tls,
+            reused: false,
+        }
+    }
+
+    /// Returns how long it took to resolve the host and connect, including establishing a
+    /// tunnel through a proxy.
+    pub fn tcp(&self) -> Duration {
+        self.tcp
+    }
+
+    /// Returns how long the TLS handshake took, or `None` if the connection is not encrypted.
+    pub fn tls(&self) -> Option<Duration> {
+        self.tls
+    }
+
+    /// Returns whether the request was sent on a reused pooled connection.
+    pub fn reused(&self) -> bool {
+        self.reused
+    }
+}
+
 type Connecting = Pin<Box<dyn Future<Output = Result<Conn, BoxError>> + Send>>;
 
 pub(crate) type HttpConnector = connect::HttpConnector<DynResolver>;
@@ -48,9 +126,23 @@ pub(crate) type BoxedConnectorService = BoxCloneSyncService<Unnameable, Conn, Bo
 pub(crate) type BoxedConnectorLayer =
     BoxCloneSyncServiceLayer<BoxedConnectorService, Unnameable, Conn, BoxError>;
 
+/// The connector built by [`ConnectorBuilder::build`], along with a handle to its TLS
+/// connector.
+#[cfg(feature = "tls")]
+pub(crate) type BuiltConnector = (Connector, TlsHandle);
+
+/// The connector built by [`ConnectorBuilder::build`].
+#[cfg(not(feature = "tls"))]
+pub(crate) type BuiltConnector = Connector;
+
 pub(crate) struct ConnectorBuilder {
     http: HttpConnector,
     proxies: Arc<Vec<ProxyMatcher>>,
+    proxy_strategy: ProxyStrategy,
+    proxy_latency: Option<Arc<ProxyLatency>>,
+    proxy_race: Option<ProxyRace>,
+    proxy_auth: Option<Arc<ProxyAuth>>,
+    proxy_protocol: Option<ProxyProtocolConfig>,
     verbose: verbose::Wrapper,
     /// When there is a single timeout layer and no other layers,
     /// we embed it directly inside our base Service::call().
@@ -60,9 +152,15 @@ pub(crate) struct ConnectorBuilder {
     tcp_nodelay: bool,
     #[cfg(feature = "socks")]
     resolver: DynResolver,
+    #[cfg(feature = "socks")]
+    socks_handshake_timeout: Option<Duration>,
 
+    #[cfg(feature = "tls")]
     tls_info: bool,
+    #[cfg(feature = "tls")]
     tls_builder: TlsConnectorBuilder,
+    #[cfg(feature = "tls")]
+    tls_connector: Option<TlsConnector>,
 }
 
 impl ConnectorBuilder {
@@ -132,6 +230,19 @@ impl ConnectorBuilder {
         self
     }
 
+    /// Set the time allowed for SOCKS handshakes, once connected to the proxy.
+    #[inline(always)]
+    pub(crate) fn socks_handshake_timeout(
+        #[allow(unused_mut)] mut self,
+        #[cfg(feature = "socks")] timeout: Option<Duration>,
+    ) -> ConnectorBuilder {
+        #[cfg(feature = "socks")]
+        {
+            self.socks_handshake_timeout = timeout;
+        }
+        self
+    }
+
     /// Set the tcp_nodelay flag for the connector.
     #[inline(always)]
     pub(crate) fn tcp_nodelay(mut self, enabled: bool) -> ConnectorBuilder {
@@ -140,14 +251,53 @@ impl ConnectorBuilder {
         self
     }
 
+    /// Set the strategy used to choose between several matching proxies.
+    #[inline(always)]
+    pub(crate) fn proxy_strategy(mut self, strategy: ProxyStrategy) -> ConnectorBuilder {
+        self.proxy_strategy = strategy;
+        self
+    }
+
+    /// Set the latency estimates the proxies are chosen by, shared with the client.
+    #[inline(always)]
+    pub(crate) fn proxy_latency(mut self, latency: Option<Arc<ProxyLatency>>) -> ConnectorBuilder {
+        self.proxy_latency = latency;
+        self
+    }
+
+    /// Set racing the tunnels through several matching proxies.
+    #[inline(always)]
+    pub(crate) fn proxy_race(mut self, race: Option<ProxyRace>) -> ConnectorBuilder {
+        self.proxy_race = race;
+        self
+    }
+
+    /// Set the credentials answering the authentication challenges of proxies.
+    #[inline(always)]
+    pub(crate) fn proxy_auth(mut self, auth: Option<Arc<ProxyAuth>>) -> ConnectorBuilder {
+        self.proxy_auth = auth;
+        self
+    }
+
+    /// Set the PROXY protocol header written at the start of connections.
+    #[inline(always)]
+    pub(crate) fn proxy_protocol(
+        mut self,
+        proxy_protocol: Option<ProxyProtocolConfig>,
+    ) -> ConnectorBuilder {
+        self.proxy_protocol = proxy_protocol;
+        self
+    }
+
     /// Set connecting verbose mode.
     #[inline(always)]
     pub(crate) fn verbose(mut self, enabled: bool) -> ConnectorBuilder {
-        self.verbose.0 = enabled;
+        self.verbose.enabled = enabled;
         self
     }
 
     /// Sets the maximum TLS version to be used.
+    #[cfg(feature = "tls")]
     #[inline(always)]
     pub(crate) fn tls_max_version<T>(mut self, version: T) -> ConnectorBuilder
     where
@@ -158,6 +308,7 @@ impl ConnectorBuilder {
     }
 
     /// Sets the minimum TLS version to be used.
+    #[cfg(feature = "tls")]
     #[inline(always)]
     pub(crate) fn tls_min_version<T>(mut self, version: T) -> ConnectorBuilder
     where
@@ -167,7 +318,16 @@ impl ConnectorBuilder {
         self
     }
 
+    /// Sets an already built TLS connector, used instead of building one.
+    #[cfg(feature = "tls")]
+    #[inline(always)]
+    pub(crate) fn tls_connector(mut self, connector: Option<TlsConnector>) -> ConnectorBuilder {
+        self.tls_connector = connector;
+        self
+    }
+
     /// Sets the TLS keylog policy.
+    #[cfg(feature = "tls")]
     #[inline(always)]
     pub(crate) fn tls_keylog_policy(
         mut self,
@@ -178,13 +338,31 @@ impl ConnectorBuilder {
     }
 
     /// Sets the TLS info flag.
+    #[cfg(feature = "tls")]
     #[inline(always)]
     pub(crate) fn tls_info(mut self, enabled: bool) -> ConnectorBuilder {
         self.tls_info = enabled;
         self
     }
 
+    /// Sets the TLS 1.3-only flag.
+    #[cfg(feature = "tls")]
+    #[inline(always)]
+    pub(crate) fn tls13_only(mut self, enabled: bool) -> ConnectorBuilder {
+        self.tls_builder = self.tls_builder.tls13_only(enabled);
+        self
+    }
+
+    /// Sets the TLS handshake message capture flag.
+    #[cfg(feature = "tls")]
+    #[inline(always)]
+    pub(crate) fn tls_debug_capture(mut self, enabled: bool) -> ConnectorBuilder {
+        self.tls_builder = self.tls_builder.debug_capture(enabled);
+        self
+    }
+
     /// Sets the Server Name Indication (SNI) flag.
+    #[cfg(feature = "tls")]
     #[inline(always)]
     pub(crate) fn tls_sni(mut self, enabled: bool) -> ConnectorBuilder {
         self.tls_builder = self.tls_builder.tls_sni(enabled);
@@ -192,6 +370,7 @@ impl ConnectorBuilder {
     }
 
     /// Sets the hostname verification flag.
+    #[cfg(feature = "tls")]
     #[inline(always)]
     pub(crate) fn tls_verify_hostname(mut self, enabled: bool) -> ConnectorBuilder {
         self.tls_builder = self.tls_builder.verify_hostname(enabled);
@@ -199,13 +378,26 @@ impl ConnectorBuilder {
     }
 
     /// Sets the identity to be used for client certificate authentication.
+    #[cfg(feature = "tls")]
     #[inline(always)]
     pub(crate) fn tls_identity(mut self, identity: Option<Identity>) -> ConnectorBuilder {
         self.tls_builder = self.tls_builder.identity(identity);
         self
     }
 
+    /// Sets the identities to be used for client certificate authentication with matching hosts.
+    #[cfg(feature = "tls")]
+    #[inline(always)]
+    pub(crate) fn tls_host_identities(
+        mut self,
+        identities: Vec<(HostPattern, Identity)>,
+    ) -> ConnectorBuilder {
+        self.tls_builder = self.tls_builder.host_identities(identities);
+        self
+    }
+
     /// Sets the certificate store used for TLS verification.
+    #[cfg(feature = "tls")]
     #[inline(always)]
     pub(crate) fn tls_cert_store(mut self, cert_store: CertStore) -> ConnectorBuilder {
         self.tls_builder = self.tls_builder.cert_store(cert_store);
@@ -213,22 +405,63 @@ impl ConnectorBuilder {
     }
 
     /// Sets the certificate verification flag.
+    #[cfg(feature = "tls")]
     #[inline(always)]
     pub(crate) fn tls_cert_verification(mut self, enabled: bool) -> ConnectorBuilder {
         self.tls_builder = self.tls_builder.cert_verification(enabled);
         self
     }
 
+    /// Sets the OCSP staple requirement flag.
+    #[cfg(feature = "tls")]
+    #[inline(always)]
+    pub(crate) fn tls_require_ocsp_staple(mut self, enabled: bool) -> ConnectorBuilder {
+        self.tls_builder = self.tls_builder.require_ocsp_staple(enabled);
+        self
+    }
+
+    /// Sets the revocation check of server certificates.
+    #[cfg(feature = "tls")]
+    #[inline(always)]
+    pub(crate) fn tls_revocation(mut self, check: Option<RevocationCheck>) -> ConnectorBuilder {
+        self.tls_builder = self.tls_builder.revocation(check);
+        self
+    }
+
     /// Builds the connector with the provided TLS configuration and optional layers.
+    ///
+    /// With the `tls` feature, also returns a handle to the TLS connector, which can replace it
+    /// after it is built.
     pub(crate) fn build(
         self,
-        tls_config: TlsConfig,
+        #[cfg(feature = "tls")] tls_config: TlsConfig,
         layers: Option<Vec<BoxedConnectorLayer>>,
-    ) -> crate::Result<Connector> {
+    ) -> crate::Result<BuiltConnector> {
+        #[cfg(feature = "tls")]
+        let tls = match self.tls_connector {
+            Some(connector) => TlsHandle::new(connector),
+            None => TlsHandle::new(self.tls_builder.build(tls_config)?),
+        };
+        #[cfg(feature = "tls")]
+        let proxy_tls = ProxyTls {
+            builder: Arc::new(self.tls_builder),
+            connector: Arc::default(),
+        };
+
         let mut service = ConnectorService {
             http: self.http,
-            tls: self.tls_builder.build(tls_config)?,
+            #[cfg(feature = "tls")]
+            tls: tls.clone(),
+            #[cfg(feature = "tls")]
+            proxy_tls,
             proxies: self.proxies,
+            proxy_latency: self.proxy_latency,
+            proxy_strategy: self.proxy_strategy,
+            proxy_race: self.proxy_race,
+            proxy_auth: self.proxy_auth,
+            proxy_protocol: self
+                .proxy_protocol
+                .map(|proxy_protocol| proxy_protocol.verbose(self.verbose.enabled)),
             verbose: self.verbose,
             // The timeout is initially set to None and will be reassigned later
             // based on the presence or absence of user-provided layers.
@@ -236,11 +469,13 @@ impl ConnectorBuilder {
             tcp_nodelay: self.tcp_nodelay,
             #[cfg(feature = "socks")]
             resolver: self.resolver,
+            #[cfg(feature = "socks")]
+            socks_handshake_timeout: self.socks_handshake_timeout.or(self.timeout),
+            #[cfg(feature = "tls")]
             tls_info: self.tls_info,
-            tls_builder: Arc::new(self.tls_builder),
         };
 
-        if let Some(layers) = layers {
+        let connector = if let Some(layers) = layers {
             // otherwise we have user provided layers
             // so we need type erasure all the way through
             // as well as mapping the unnameable type of the layers back to ConnectRequest for the
@@ -266,7 +501,7 @@ impl ConnectorBuilder {
                         .map_err(map_timeout_to_connector_error)
                         .service(service);
                     let service = BoxCloneSyncService::new(service);
-                    Ok(Connector::WithLayers(service))
+                    Connector::WithLayers(service)
                 }
                 None => {
                     // no timeout, but still map err
@@ -276,14 +511,20 @@ impl ConnectorBuilder {
                         .map_err(map_timeout_to_connector_error)
                         .service(service);
                     let service = BoxCloneSyncService::new(service);
-                    Ok(Connector::WithLayers(service))
+                    Connector::WithLayers(service)
                 }
             }
         } else {
             // we have no user-provided layers, only use concrete types
             service.timeout = self.timeout;
-            Ok(Connector::Simple(service))
-        }
+            Connector::Simple(service)
+        };
+
+        #[cfg(feature = "tls")]
+        return Ok((connector, tls));
+
+        #[cfg(not(feature = "tls"))]
+        Ok(connector)
     }
 }
 
@@ -304,6 +545,8 @@ impl Connector {
         ConnectorBuilder {
             #[cfg(feature = "socks")]
             resolver: resolver.clone(),
+            #[cfg(feature = "socks")]
+            socks_handshake_timeout: None,
             http: {
                 // Create a new HttpConnector with the provided resolver
                 let mut http = HttpConnector::new_with_resolver(resolver);
@@ -311,13 +554,22 @@ impl Connector {
                 http
             },
             proxies,
+            proxy_strategy: ProxyStrategy::First,
+            proxy_latency: None,
+            proxy_race: None,
+            proxy_auth: None,
+            proxy_protocol: None,
             verbose: verbose::OFF,
             timeout: None,
             tcp_nodelay: false,
 
             // TLS connector and its configuration
+            #[cfg(feature = "tls")]
             tls_info: false,
+            #[cfg(feature = "tls")]
             tls_builder: TlsConnector::builder(),
+            #[cfg(feature = "tls")]
+            tls_connector: None,
         }
     }
 }
@@ -347,8 +599,17 @@ impl Service<ConnRequest> for Connector {
 #[derive(Clone)]
 pub(crate) struct ConnectorService {
     http: HttpConnector,
-    tls: TlsConnector,
+    #[cfg(feature = "tls")]
+    tls: TlsHandle,
+    #[cfg(feature = "tls")]
+    proxy_tls: ProxyTls,
     proxies: Arc<Vec<ProxyMatcher>>,
+    proxy_strategy: ProxyStrategy,
+    proxy_race: Option<ProxyRace>,
+    /// Only tracked when proxies are selected by latency.
+    proxy_latency: Option<Arc<ProxyLatency>>,
+    proxy_auth: Option<Arc<ProxyAuth>>,
+    proxy_protocol: Option<ProxyProtocolConfig>,
     verbose: verbose::Wrapper,
     /// When there is a single timeout layer and no other layers,
     /// we embed it directly inside our base Service::call().
@@ -358,141 +619,911 @@ pub(crate) struct ConnectorService {
     tcp_nodelay: bool,
     #[cfg(feature = "socks")]
     resolver: DynResolver,
+    /// The time allowed for SOCKS handshakes, defaulting to the connect timeout.
+    #[cfg(feature = "socks")]
+    socks_handshake_timeout: Option<Duration>,
 
     // TLS configuration
     // Note: these are not used in the `TlsConnectorBuilder` but rather
     // in the `TlsConnector` that is built from it.
+    #[cfg(feature = "tls")]
     tls_info: bool,
-    tls_builder: Arc<TlsConnectorBuilder>,
+}
+
+/// The TLS connector of a client.
+///
+/// Clones share the handle, so replacing the connector applies to every clone of the client.
+/// The connector itself may be shared with other clients, and is never modified: replacing
+/// it gives the client a connector of its own.
+#[cfg(feature = "tls")]
+#[derive(Clone)]
+pub(crate) struct TlsHandle {
+    current: Arc<RwLock<CurrentTls>>,
+}
+
+#[cfg(feature = "tls")]
+struct CurrentTls {
+    connector: TlsConnector,
+    /// The number of times the connector was replaced.
+    generation: u64,
+}
+
+#[cfg(feature = "tls")]
+impl TlsHandle {
+    fn new(connector: TlsConnector) -> TlsHandle {
+        TlsHandle {
+            current: Arc::new(RwLock::new(CurrentTls {
+                connector,
+                generation: 0,
+            })),
+        }
+    }
+
+    /// Returns the connector currently in use.
+    pub(crate) fn current(&self) -> TlsConnector {
+        self.current.read().connector.clone()
+    }
+
+    /// Returns the configuration of the connector, after the overrides of the client.
+    pub(crate) fn config(&self) -> TlsConfig {
+        self.current.read().connector.config().clone()
+    }
+
+    /// Returns the number of times the connector was replaced.
+    pub(crate) fn generation(&self) -> u64 {
+        self.current.read().generation
+    }
+
+    /// Returns a handle to the session cache of the connector.
+    pub(crate) fn sessions(&self) -> TlsSessions {
+        self.current.read().connector.sessions()
+    }
+
+    /// Replaces the connector with one built from `config`, with a new session cache.
+    ///
+    /// With a `generation`, fails if the connector was replaced since that generation.
+    pub(crate) fn replace(&self, config: TlsConfig, generation: Option<u64>) -> crate::Result<()> {
+        let connector = self.current().rebuild(config)?;
+        let mut current = self.current.write();
+        if generation.is_some_and(|generation| generation != current.generation) {
+            return Err(Error::builder(ConcurrentUpdate));
+        }
+        current.connector = connector;
+        current.generation += 1;
+        Ok(())
+    }
+
+    /// Returns the connector, or one built from the configuration of a request.
+    fn connector(&self, config: Option<TlsConfig>) -> crate::Result<TlsConnector> {
+        match config {
+            Some(config) => self.current().rebuild(config),
+            None => Ok(self.current()),
+        }
+    }
+}
+
+/// The TLS connector of `https` proxies, built on first use.
+///
+/// It verifies proxies with the certificate configuration of the client, but never uses the
+/// TLS configuration of the client or of a request, which fingerprint the destination.
+#[cfg(feature = "tls")]
+#[derive(Clone)]
+struct ProxyTls {
+    builder: Arc<TlsConnectorBuilder>,
+    connector: Arc<Mutex<Option<TlsConnector>>>,
+}
+
+#[cfg(feature = "tls")]
+impl ProxyTls {
+    fn connector(&self) -> crate::Result<TlsConnector> {
+        let mut connector = self.connector.lock();
+        if let Some(ref connector) = *connector {
+            return Ok(connector.clone());
+        }
+
+        // HTTP proxies only speak HTTP/1 to forward requests and open tunnels, and SOCKS
+        // proxies ignore the protocol offered
+        let config = TlsConfig::builder()
+            .alpn_protos(&[AlpnProtocol::HTTP1])
+            .build();
+        let built = self.builder.build(config)?;
+        *connector = Some(built.clone());
+        Ok(built)
+    }
+}
+
+/// Returns whether the connection to `proxy` is made over TLS: for `https` proxies, and
+/// SOCKS proxies with a scheme ending with `s`, such as `socks5s`.
+fn is_tls_proxy(proxy: &Uri) -> bool {
+    matches!(
+        proxy.scheme_str(),
+        Some("https" | "socks4s" | "socks4as" | "socks5s" | "socks5hs")
+    )
+}
+
+/// Returns the SOCKS version of `proxy`, and where it resolves destinations, for SOCKS proxies.
+///
+/// The schemes ending with `s` run the SOCKS handshake over TLS.
+#[cfg(feature = "socks")]
+fn socks_version(proxy: &Uri) -> Option<(proxy::SocksVersion, proxy::DnsResolve)> {
+    use proxy::{DnsResolve, SocksVersion};
+
+    match proxy.scheme_str()? {
+        "socks4" | "socks4s" => Some((SocksVersion::V4, DnsResolve::Local)),
+        "socks4a" | "socks4as" => Some((SocksVersion::V4, DnsResolve::Remote)),
+        "socks5" | "socks5s" | "socks5+unix" => Some((SocksVersion::V5, DnsResolve::Local)),
+        "socks5h" | "socks5hs" | "socks5h+unix" => Some((SocksVersion::V5, DnsResolve::Remote)),
+        _ => None,
+    }
+}
+
+/// Connects to a proxy, over TLS for an `https` or TLS SOCKS proxy, or to the unix socket of
+/// a `unix` proxy.
+#[derive(Clone)]
+struct ProxyConnector {
+    http: HttpConnector,
+    #[cfg(feature = "tls")]
+    tls: Option<TlsConnector>,
+    /// The proxies chained before the proxy, tunneled through to reach it, the service
+    /// opening the tunnels, and the credentials the auth templates of the proxies gave.
+    chain: Option<(
+        ConnectorService,
+        Arc<[Intercepted]>,
+        Option<Arc<[TemplatedAuth]>>,
+    )>,
+}
+
+/// A connection to a proxy.
+#[derive(Debug)]
+enum ProxyStream {
+    #[cfg(feature = "tls")]
+    Tcp(MaybeHttpsStream<TcpStream>),
+    #[cfg(not(feature = "tls"))]
+    Tcp(TokioIo<TcpStream>),
+    #[cfg(unix)]
+    Unix(UnixStream),
+    /// A TLS connection through the tunnel of the proxy chained before.
+    #[cfg(feature = "tls")]
+    Tls(Box<SslStream<TokioIo<ProxyStream>>>),
+}
+
+impl Service<Uri> for ProxyConnector {
+    type Response = ProxyStream;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, BoxError>> + Send>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.http.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, proxy: Uri) -> Self::Future {
+        // Tunnel to the proxy through the proxies chained before it
+        if let Some((ref service, ref chain, ref templated)) = self.chain {
+            return Box::pin(service.clone().connect_chained(
+                self.http.clone(),
+                #[cfg(feature = "tls")]
+                self.tls.clone(),
+                chain.clone(),
+                templated.clone(),
+                proxy,
+            ));
+        }
+
+        #[cfg(unix)]
+        {
+            if let Some(path) = matcher::unix_socket_path(&proxy) {
+                return Box::pin(async move {
+                    UnixStream::connect(&path)
+                        .await
+                        .map(ProxyStream::Unix)
+                        .map_err(|err| {
+                            BoxError::from(io::Error::new(
+                                err.kind(),
+                                format!(
+                                    "failed to connect to unix socket {}: {err}",
+                                    path.display()
+                                ),
+                            ))
+                        })
+                });
+            }
+        }
+
+        let connecting = self.http.call(proxy.clone());
+        #[cfg(feature = "tls")]
+        let tls = self
+            .tls
+            .clone()
+            .map(|tls| HttpsConnector::with_connector(self.http.clone(), tls));
+
+        Box::pin(async move {
+            let conn = connecting.await?;
+
+            #[cfg(feature = "tls")]
+            return match tls {
+                Some(mut tls) if is_tls_proxy(&proxy) => {
+                    let stream = tls
+                        .call((proxy.clone(), conn))
+                        .await
+                        .map_err(|err| ProxyTlsError::new(proxy, err))?;
+                    Ok(ProxyStream::Tcp(MaybeHttpsStream::Https(stream)))
+                }
+                _ => Ok(ProxyStream::Tcp(MaybeHttpsStream::Http(conn.into_inner()))),
+            };
+
+            #[cfg(not(feature = "tls"))]
+            Ok(ProxyStream::Tcp(conn))
+        })
+    }
+}
+
+impl Connection for ProxyStream {
+    fn connected(&self) -> Connected {
+        match self {
+            ProxyStream::Tcp(stream) => stream.connected(),
+            #[cfg(unix)]
+            ProxyStream::Unix(_) => Connected::new(),
+            #[cfg(feature = "tls")]
+            ProxyStream::Tls(stream) => stream.get_ref().inner().connected(),
+        }
+    }
+}
+
+impl Read for ProxyStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: ReadBufCursor<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            ProxyStream::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(unix)]
+            ProxyStream::Unix(stream) => Pin::new(&mut TokioIo::new(stream)).poll_read(cx, buf),
+            #[cfg(feature = "tls")]
+            ProxyStream::Tls(stream) => Pin::new(&mut TokioIo::new(stream)).poll_read(cx, buf),
+        }
+    }
+}
+
+impl Write for ProxyStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            ProxyStream::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(unix)]
+            ProxyStream::Unix(stream) => Pin::new(&mut TokioIo::new(stream)).poll_write(cx, buf),
+            #[cfg(feature = "tls")]
+            ProxyStream::Tls(stream) => Pin::new(&mut TokioIo::new(stream)).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            ProxyStream::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(unix)]
+            ProxyStream::Unix(stream) => Pin::new(&mut TokioIo::new(stream)).poll_flush(cx),
+            #[cfg(feature = "tls")]
+            ProxyStream::Tls(stream) => Pin::new(&mut TokioIo::new(stream)).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            ProxyStream::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(unix)]
+            ProxyStream::Unix(stream) => Pin::new(&mut TokioIo::new(stream)).poll_shutdown(cx),
+            #[cfg(feature = "tls")]
+            ProxyStream::Tls(stream) => Pin::new(&mut TokioIo::new(stream)).poll_shutdown(cx),
+        }
+    }
 }
 
 impl ConnectorService {
-    async fn connect(self, mut req: ConnRequest, is_proxy: bool) -> Result<Conn, BoxError> {
+    async fn connect(
+        self,
+        #[allow(unused_mut)] mut req: ConnRequest,
+        is_proxy: bool,
+    ) -> Result<Conn, BoxError> {
         trace!("connect with maybe proxy: {:?}", is_proxy);
 
+        let start = Instant::now();
+        #[cfg(feature = "tls")]
+        let tls_info = self.tls_info || req.tls_info();
         let uri = req.uri().clone();
         let mut http = self.http.clone();
 
         // Disable Nagle's algorithm for TLS handshake
         //
         // https://www.openssl.org/docs/man1.1.1/man3/SSL_connect.html#NOTES
+        #[cfg(feature = "tls")]
         if !self.tcp_nodelay && (uri.scheme() == Some(&Scheme::HTTPS)) {
             http.set_nodelay(true);
         }
-
-        let mut connector = self.create_https_connector(http, &mut req)?;
-        let io = connector.call(uri).await?;
+        http.set_ipv6_scope_id(req.ipv6_scope_id());
+        http.set_proxy_protocol(self.proxy_protocol(is_proxy));
 
         // If the connection is HTTPS, wrap the TLS stream in a TlsConn for unified handling.
         // For plain HTTP, use the stream directly without additional wrapping.
-        let inner = if let MaybeHttpsStream::Https(stream) = io {
-            if !self.tcp_nodelay {
-                stream.get_ref().set_nodelay(false)?;
+        #[cfg(feature = "tls")]
+        let inner = {
+            let mut connector = self.create_https_connector(http, &mut req)?;
+            let io = connector.call(uri).await?;
+
+            if let MaybeHttpsStream::Https(stream) = io {
+                if !self.tcp_nodelay {
+                    stream.get_ref().set_nodelay(false)?;
+                }
+                self.verbose.wrap(TlsConn::new(stream))
+            } else {
+                self.verbose.wrap(io)
             }
-            self.verbose.wrap(TlsConn {
-                inner: TokioIo::new(stream),
-            })
-        } else {
-            self.verbose.wrap(io)
+        };
+
+        // Without TLS, only plain HTTP is ever spoken on the stream.
+        #[cfg(not(feature = "tls"))]
+        let inner = {
+            if uri.scheme() == Some(&Scheme::HTTPS) {
+                return Err(Box::new(TlsDisabled));
+            }
+            http.set_tcp_connect_options(req.tcp_opts().cloned());
+            self.verbose.wrap(http.call(uri).await?)
         };
 
         Ok(Conn {
+            timing: ConnectTiming::measure(start, &*inner),
             inner,
             is_proxy,
-            tls_info: self.tls_info,
+            proxy: None,
+            proxy_latency: None,
+            #[cfg(feature = "tls")]
+            tls_info,
+            #[cfg(not(feature = "tls"))]
+            tls_info: false,
         })
     }
 
+    /// Connects through `proxy`, recording it on the connection, or naming it in the error.
+    ///
+    /// Errors connecting to the proxy are named as such by [`Self::connect_through_proxy`],
+    /// the others happened connecting to the destination through it.
     async fn connect_via_proxy(
+        self,
+        req: ConnRequest,
+        proxy: Intercepted,
+    ) -> Result<Conn, BoxError> {
+        let proxy_uri = proxy.uri().clone();
+        match self.connect_through_proxy(req, proxy).await {
+            Ok(mut conn) => {
+                conn.proxy = Some(proxy_uri);
+                conn.proxy_latency = self.proxy_latency.clone();
+                Ok(conn)
+            }
+            Err(err) => Err(ProxyConnectError::at_destination(proxy_uri, err)),
+        }
+    }
+
+    async fn connect_through_proxy(
         self,
         mut req: ConnRequest,
         proxy: Intercepted,
     ) -> Result<Conn, BoxError> {
+        let connect_start = Instant::now();
+        #[cfg(feature = "tls")]
+        let tls_info = self.tls_info || req.tls_info();
         let uri = req.uri().clone();
         let proxy_uri = proxy.uri().clone();
+        let at_proxy = |err: BoxError| ProxyConnectError::at_proxy(proxy_uri.clone(), err);
 
+        // Tunnel to HTTPS destinations, and to any destination through an `https` proxy, the
+        // last proxy of a chain, or a SOCKS proxy, which always tunnel
+        let tunnels = uri.scheme() == Some(&Scheme::HTTPS)
+            || proxy_uri.scheme() == Some(&Scheme::HTTPS)
+            || !proxy.chain().is_empty();
         #[cfg(feature = "socks")]
+        let tunnels = tunnels || socks_version(&proxy_uri).is_some();
+        if tunnels {
+            trace!("tunneling {:?} over proxy: {:?}", uri.scheme(), proxy_uri);
+            let proxy_connector = self
+                .proxy_connector(&req, &proxy)
+                .map_err(|err| at_proxy(err.into()))?;
+            let templated = req.templated_proxy_auth(&proxy_uri);
+            let tunneled = self
+                .open_tunnel(&proxy, proxy_connector, &uri, templated)
+                .await?;
+
+            // Plain HTTP through the tunnel
+            if uri.scheme() != Some(&Scheme::HTTPS) {
+                let inner = self.verbose.wrap(tunneled);
+                return Ok(Conn {
+                    timing: ConnectTiming::measure(connect_start, &*inner),
+                    inner,
+                    is_proxy: false,
+                    proxy: None,
+                    proxy_latency: None,
+                    tls_info: false,
+                });
+            }
+
+            #[cfg(not(feature = "tls"))]
+            return Err(Box::new(TlsDisabled));
+
+            // We don't wrap this again in an HttpsConnector since that uses Maybe,
+            // and we know this is definitely HTTPS.
+            #[cfg(feature = "tls")]
+            {
+                let mut connector = self.create_https_connector(self.http.clone(), &mut req)?;
+                let tunneled = TokioIo::new(tunneled);
+                let tunneled = TokioIo::new(tunneled);
+                let io = connector.call((uri, tunneled)).await?;
+                let inner = self.verbose.wrap(TlsConn::new(io));
+
+                return Ok(Conn {
+                    timing: ConnectTiming::measure(connect_start, &*inner),
+                    inner,
+                    is_proxy: false,
+                    proxy: None,
+                    proxy_latency: None,
+                    tls_info,
+                });
+            }
+        }
+
+        // Keep the credentials and sensitive headers sent to the proxy out of the logs
+        let mut redacted = vec![PROXY_AUTHORIZATION];
+        if let Some(headers) = proxy.custom_headers() {
+            redacted.extend(
+                headers
+                    .iter()
+                    .filter(|(_, value)| value.is_sensitive())
+                    .map(|(name, _)| name.clone()),
+            );
+        }
+        let mut this = self;
+        this.verbose = this.verbose.redacting(redacted.into());
+
+        // Forward over a connection to the unix socket of a `unix` proxy
+        #[cfg(unix)]
         {
-            use proxy::{DnsResolve, Socks, SocksVersion};
-
-            if let Some((version, dns_resolve)) = match proxy.uri().scheme_str() {
-                Some("socks4") => Some((SocksVersion::V4, DnsResolve::Local)),
-                Some("socks4a") => Some((SocksVersion::V4, DnsResolve::Remote)),
-                Some("socks5") => Some((SocksVersion::V5, DnsResolve::Local)),
-                Some("socks5h") => Some((SocksVersion::V5, DnsResolve::Remote)),
-                _ => None,
-            } {
-                trace!("connecting via SOCKS proxy: {:?}", proxy_uri);
-
-                let mut socks = Socks::new_with_resolver(
-                    self.http.clone(),
-                    self.resolver.clone(),
-                    proxy_uri.clone(),
-                )
-                .with_auth(proxy.raw_auth())
-                .with_version(version)
-                .with_local_dns(dns_resolve);
-
-                let conn = socks.call(uri.clone()).await?;
-
-                return if uri.scheme() == Some(&Scheme::HTTPS) {
-                    trace!("socks HTTPS over proxy");
-                    let mut connector = self.create_https_connector(self.http.clone(), &mut req)?;
-                    let io = connector.call((uri, conn)).await?;
-
-                    Ok(Conn {
-                        inner: self.verbose.wrap(TlsConn {
-                            inner: TokioIo::new(io),
-                        }),
-                        is_proxy: false,
-                        tls_info: self.tls_info,
-                    })
-                } else {
-                    Ok(Conn {
-                        inner: self.verbose.wrap(conn),
-                        is_proxy: false,
-                        tls_info: false,
-                    })
+            if matcher::unix_socket_path(&proxy_uri).is_some() {
+                let io = this
+                    .proxy_connector(&req, &proxy)
+                    .map_err(|err| at_proxy(err.into()))?
+                    .call(proxy_uri.clone())
+                    .await
+                    .map_err(at_proxy)?;
+                let inner = this.verbose.wrap(io);
+
+                return Ok(Conn {
+                    timing: ConnectTiming::measure(connect_start, &*inner),
+                    inner,
+                    is_proxy: true,
+                    proxy: None,
+                    proxy_latency: None,
+                    tls_info: false,
+                });
+            }
+        }
+
+        // Update the connect URI to the proxy URI
+        *req.uri_mut() = proxy_uri.clone();
+
+        this.connect(req, true).await.map_err(at_proxy)
+    }
+
+    /// Opens a tunnel to `dst` through `proxy`, reached with `connector`: with a SOCKS
+    /// handshake for SOCKS proxies, or else a `CONNECT` request answering the authentication
+    /// challenges of the proxy. The `templated` credentials take the place of those of the
+    /// proxy.
+    ///
+    /// Errors are named as happening at the proxy, unless they name a proxy chained before it.
+    async fn open_tunnel(
+        &self,
+        proxy: &Intercepted,
+        connector: ProxyConnector,
+        dst: &Uri,
+        templated: Option<&TemplatedAuth>,
+    ) -> Result<ProxyStream, BoxError> {
+        let proxy_uri = proxy.uri().clone();
+        let at_proxy = |err: BoxError| ProxyConnectError::at_proxy(proxy_uri.clone(), err);
+
+        #[cfg(feature = "socks")]
+        if let Some((version, dns_resolve)) = socks_version(&proxy_uri) {
+            use proxy::{Socks, SocksError};
+
+            trace!("connecting via SOCKS proxy: {:?}", proxy_uri);
+            let auth = templated
+                .map(TemplatedAuth::raw)
+                .or_else(|| proxy.raw_auth());
+            let mut socks =
+                Socks::new_with_resolver(connector, self.resolver.clone(), proxy_uri.clone())
+                    .with_auth(auth)
+                    .with_version(version)
+                    .with_local_dns(dns_resolve)
+                    .with_handshake_timeout(self.socks_handshake_timeout);
+
+            let start = Instant::now();
+            let tunneled = socks.call(dst.clone()).await;
+            self.record_proxy_latency(&proxy_uri, start, tunneled.is_ok());
+            return tunneled.map_err(|err| match err {
+                // name the proxy whose TLS handshake failed
+                #[cfg(feature = "tls")]
+                SocksError::Inner(err) if err.is::<ProxyTlsError>() => err,
+                // name the proxy of the chain that failed
+                SocksError::Inner(err) if err.is::<ProxyConnectError>() => err,
+                SocksError::HandshakeTimedOut => at_proxy(Box::new(TimedOut)),
+                err => at_proxy(err.into()),
+            });
+        }
+
+        let mut tunnel = proxy::Tunnel::new(proxy_uri.clone(), connector);
+        let credentials = proxy.credentials();
+        let provided = match credentials {
+            Some(credentials) => credentials.header(dst).await,
+            None => None,
+        };
+        let auth = provided
+            .or_else(|| {
+                self.proxy_auth
+                    .as_ref()
+                    .and_then(|proxy_auth| proxy_auth.cached(&proxy_uri))
+            })
+            .or_else(|| templated.map(TemplatedAuth::header))
+            .or_else(|| proxy.basic_auth().cloned());
+        if let Some(ref auth) = auth {
+            tunnel = tunnel.with_auth(auth.clone());
+        }
+
+        if let Some(headers) = proxy.custom_headers() {
+            tunnel = tunnel.with_headers(headers.clone());
+        }
+
+        let start = Instant::now();
+        let mut tunneled = tunnel.call(dst.clone()).await;
+        self.record_proxy_latency(&proxy_uri, start, tunneled.is_ok());
+
+        // Without a provider or handler, answer a `Digest` challenge with the credentials of
+        // the proxy, and once more if the proxy found the nonce stale
+        let digest = templated
+            .map(TemplatedAuth::digest_auth)
+            .or_else(|| proxy.digest_auth());
+        if let (None, None, Some(mut digest)) = (credentials, &self.proxy_auth, digest) {
+            let port = if dst.scheme() == Some(&Scheme::HTTP) {
+                80
+            } else {
+                443
+            };
+            let target = format!(
+                "{}:{}",
+                dst.host().unwrap_or_default(),
+                dst.port_u16().unwrap_or(port)
+            );
+            while let Err(proxy::TunnelError::ProxyAuthRequired(ref challenges)) = tunneled {
+                let Some(answer) = digest.answer(challenges, "CONNECT", &target) else {
+                    break;
                 };
+                tunnel = tunnel.with_auth(answer);
+                tunneled = tunnel.call(dst.clone()).await;
             }
         }
 
-        // Handle HTTPS proxy tunneling connection
-        if uri.scheme() == Some(&Scheme::HTTPS) {
-            trace!("tunneling HTTPS over HTTP proxy: {:?}", proxy_uri);
-            let mut connector = self.create_https_connector(self.http.clone(), &mut req)?;
+        // Refresh the provided credentials, or answer an authentication challenge of the
+        // proxy, and tunnel again once
+        if let Err(proxy::TunnelError::ProxyAuthRequired(ref challenges)) = tunneled {
+            let answer = match (credentials, &self.proxy_auth) {
+                (Some(credentials), _) => credentials.refresh(dst, auth.as_ref()).await,
+                (None, Some(proxy_auth)) => {
+                    proxy_auth
+                        .challenge(&proxy_uri, challenges, auth.as_ref())
+                        .await
+                }
+                (None, None) => None,
+            };
+            if let Some(answer) = answer {
+                tunnel = tunnel.with_auth(answer);
+                tunneled = tunnel.call(dst.clone()).await;
+            }
+        }
+        tunneled.map_err(|err| match err {
+            // name the proxy of the chain that failed
+            proxy::TunnelError::ConnectFailed(err) if err.is::<ProxyConnectError>() => err,
+            err => at_proxy(err.into()),
+        })
+    }
 
-            let mut tunnel = proxy::Tunnel::new(proxy_uri, connector.clone());
-            if let Some(auth) = proxy.basic_auth() {
-                tunnel = tunnel.with_auth(auth.clone());
+    /// Connects to `proxy` through the tunnel of the last proxy of `chain`, reached through
+    /// the proxies before it, over TLS for an `https` or TLS SOCKS proxy. The `templated`
+    /// credentials of a proxy of the chain take the place of its own.
+    async fn connect_chained(
+        self,
+        http: HttpConnector,
+        #[cfg(feature = "tls")] tls: Option<TlsConnector>,
+        chain: Arc<[Intercepted]>,
+        templated: Option<Arc<[TemplatedAuth]>>,
+        proxy: Uri,
+    ) -> Result<ProxyStream, BoxError> {
+        let (hop, before) = chain.split_last().expect("a chain has a proxy");
+        let connector = self
+            .chained_connector(http.clone(), hop.uri(), before, templated.clone())
+            .map_err(|err| ProxyConnectError::at_proxy(hop.uri().clone(), err.into()))?;
+        let hop_auth = templated
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .find(|auth| auth.is_for(hop.uri()));
+        let stream = self.open_tunnel(hop, connector, &proxy, hop_auth).await?;
+
+        #[cfg(feature = "tls")]
+        if let Some(tls) = tls.filter(|_| is_tls_proxy(&proxy)) {
+            let stream = HttpsConnector::with_connector(http, tls)
+                .call((proxy.clone(), TokioIo::new(TokioIo::new(stream))))
+                .await
+                .map_err(|err| ProxyTlsError::new(proxy, err))?;
+            return Ok(ProxyStream::Tls(Box::new(stream)));
+        }
+
+        Ok(stream)
+    }
+
+    /// Connects through the routes the PAC script of `matcher` gives for the destination,
+    /// trying each in order until one succeeds.
+    ///
+    /// The script is fetched through `scripts`, the connector without the DNS memo of the
+    /// request.
+    #[cfg(feature = "pac")]
+    async fn connect_via_pac(
+        self,
+        req: ConnRequest,
+        matcher: ProxyMatcher,
+        scripts: ConnectorService,
+    ) -> Result<Conn, BoxError> {
+        let routes = matcher.pac_routes(req.uri(), &scripts).await?;
+        let mut last_err = None;
+
+        for route in routes {
+            let conn = match route {
+                Some(proxy) => {
+                    trace!("connecting via PAC proxy: {:?}", proxy);
+                    self.clone().connect_via_proxy(req.clone(), proxy).await
+                }
+                None => {
+                    trace!("connecting directly as PAC script returned DIRECT");
+                    self.clone().connect(req.clone(), false).await
+                }
+            };
+            match conn {
+                Ok(conn) => return Ok(conn),
+                Err(err) => {
+                    debug!("PAC route failed: {}", err);
+                    last_err = Some(err);
+                }
             }
+        }
+
+        Err(last_err.unwrap_or_else(|| "PAC script returned no usable route".into()))
+    }
 
-            if let Some(headers) = proxy.custom_headers() {
-                tunnel = tunnel.with_headers(headers.clone());
+    /// Returns the matcher whose PAC script routes `uri`: the proxy matcher of the request if
+    /// it has a script, or else the first proxy of the client with a script, unless a proxy
+    /// intercepts `uri` before it.
+    #[cfg(feature = "pac")]
+    fn pac_matcher(&self, matcher: Option<&ProxyMatcher>, uri: &Uri) -> Option<ProxyMatcher> {
+        if let Some(matcher) = matcher {
+            if matcher.has_pac() {
+                return Some(matcher.clone());
+            }
+            if matcher.intercept(uri).is_some() {
+                return None;
             }
+        }
+        self.proxies
+            .iter()
+            .find(|prox| prox.has_pac() || prox.intercept(uri).is_some())
+            .filter(|prox| prox.has_pac())
+            .cloned()
+    }
 
-            // We don't wrap this again in an HttpsConnector since that uses Maybe,
-            // and we know this is definitely HTTPS.
-            let tunneled = tunnel.call(uri.clone()).await?;
-            let tunneled = TokioIo::new(tunneled);
-            let tunneled = TokioIo::new(tunneled);
-            let io = connector.call((uri, tunneled)).await?;
-
-            return Ok(Conn {
-                inner: self.verbose.wrap(TlsConn {
-                    inner: TokioIo::new(io),
-                }),
-                is_proxy: false,
-                tls_info: self.tls_info,
-            });
+    /// Returns this connector without the proxies routing with PAC scripts, to fetch the
+    /// scripts.
+    #[cfg(feature = "pac")]
+    pub(crate) fn without_pac(&self) -> ConnectorService {
+        let mut connector = self.clone();
+        connector.proxies = Arc::new(
+            self.proxies
+                .iter()
+                .filter(|prox| !prox.has_pac())
+                .cloned()
+                .collect(),
+        );
+        connector
+    }
+
+    /// Returns the basic auth of the first proxy forwarding the plain HTTP request to `uri`
+    /// with credentials, as the client applies to its requests.
+    #[cfg(feature = "pac")]
+    pub(crate) fn forward_proxy_auth(&self, uri: &Uri) -> Option<http::HeaderValue> {
+        if uri.scheme() != Some(&Scheme::HTTP) {
+            return None;
         }
+        self.proxies
+            .iter()
+            .find_map(|prox| prox.http_non_tunnel_basic_auth(uri))
+    }
 
-        // Update the connect URI to the proxy URI
-        *req.uri_mut() = proxy_uri;
+    /// Returns the PROXY protocol header to write on a connection, to a proxy if `is_proxy`.
+    fn proxy_protocol(&self, is_proxy: bool) -> Option<ProxyProtocolConfig> {
+        self.proxy_protocol
+            .clone()
+            .filter(|proxy_protocol| !is_proxy || proxy_protocol.writes_to_proxies())
+    }
+
+    /// Creates the connector to `proxy`, with the TCP options of the request.
+    fn proxy_connector(
+        &self,
+        req: &ConnRequest,
+        proxy: &Intercepted,
+    ) -> crate::Result<ProxyConnector> {
+        let mut http = self.http.clone();
+        http.set_tcp_connect_options(req.tcp_opts().cloned());
+        http.set_proxy_protocol(self.proxy_protocol(true));
+        self.chained_connector(
+            http,
+            proxy.uri(),
+            proxy.chain(),
+            req.templated_proxy_auths().cloned(),
+        )
+    }
+
+    /// Creates the connector to `proxy`, through the proxies of `chain` chained before it,
+    /// opening their tunnels with the `templated` credentials given for them.
+    fn chained_connector(
+        &self,
+        http: HttpConnector,
+        proxy: &Uri,
+        chain: &[Intercepted],
+        templated: Option<Arc<[TemplatedAuth]>>,
+    ) -> crate::Result<ProxyConnector> {
+        #[cfg(feature = "tls")]
+        let tls = if is_tls_proxy(proxy) {
+            Some(self.proxy_tls.connector()?)
+        } else {
+            None
+        };
+        #[cfg(not(feature = "tls"))]
+        if is_tls_proxy(proxy) {
+            return Err(Error::builder(TlsDisabled));
+        }
+        let chain = (!chain.is_empty()).then(|| (self.clone(), Arc::from(chain), templated));
+        Ok(ProxyConnector {
+            http,
+            #[cfg(feature = "tls")]
+            tls,
+            chain,
+        })
+    }
+
+    /// Connects through the first of `candidates` to establish a tunnel, starting the next
+    /// candidate whenever the stagger elapses or an attempt fails.
+    ///
+    /// Attempts still in progress once one succeeds are dropped, closing their connection to
+    /// the proxy. They are not counted as failures of their proxy.
+    async fn race_proxies(
+        self,
+        race: ProxyRace,
+        req: ConnRequest,
+        mut candidates: Vec<Intercepted>,
+    ) -> Result<Conn, BoxError> {
+        let dst = req.uri().clone();
+        candidates.reverse();
+        let mut attempts = FuturesUnordered::new();
+        let mut last_err = None;
+
+        loop {
+            if let Some(proxy) = candidates.pop() {
+                trace!("racing tunnel through proxy: {:?}", proxy.uri());
+                let proxy_uri = proxy.uri().clone();
+                let attempt = self.clone().connect_via_proxy(req.clone(), proxy);
+                attempts.push(async move { (proxy_uri, attempt.await) });
+            }
+
+            let mut stagger = std::pin::pin!(tokio::time::sleep(race.stagger()));
+            let next = std::future::poll_fn(|cx| {
+                if let Poll::Ready(Some(done)) = attempts.poll_next_unpin(cx) {
+                    return Poll::Ready(Some(done));
+                }
+                if !candidates.is_empty() && stagger.as_mut().poll(cx).is_ready() {
+                    return Poll::Ready(None);
+                }
+                Poll::Pending
+            })
+            .await;
+
+            match next {
+                Some((proxy, Ok(conn))) => {
+                    race.won(&dst, &proxy);
+                    return Ok(conn);
+                }
+                Some((proxy, Err(err))) => {
+                    debug!("tunnel through proxy {:?} failed: {}", proxy, err);
+                    race.failed(&dst, &proxy);
+                    last_err = Some(err);
+                    if attempts.is_empty() && candidates.is_empty() {
+                        break;
+                    }
+                }
+                // the stagger elapsed
+                None => {}
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| "no proxy to race".into()))
+    }
+
+    /// Returns the proxies to race for `uri`, if racing is enabled and more than one
+    /// proxy intercepts it.
+    fn race_candidates(&self, uri: &Uri) -> Option<(ProxyRace, Vec<Intercepted>)> {
+        // Plain HTTP requests have their proxy headers applied before connecting,
+        // so they must keep using the first matching proxy.
+        let race = self.proxy_race.as_ref()?;
+        if uri.scheme() == Some(&Scheme::HTTP) {
+            return None;
+        }
+
+        let mut candidates = self
+            .proxies
+            .iter()
+            .filter_map(|prox| prox.intercept(uri))
+            .collect::<Vec<_>>();
+        if candidates.len() < 2 {
+            return None;
+        }
+
+        if let Some(ref latency) = self.proxy_latency {
+            latency.sort(&mut candidates, Intercepted::uri);
+        }
+        race.order(uri, &mut candidates, Intercepted::uri);
+        Some((race.clone(), candidates))
+    }
+
+    /// Picks the proxy intercepting `uri` according to the configured strategy.
+    fn intercept(&self, uri: &Uri) -> Option<Intercepted> {
+        match (self.proxy_strategy, self.proxy_latency.as_ref()) {
+            // Plain HTTP requests have their proxy chosen by the client, before their proxy
+            // headers are applied.
+            (ProxyStrategy::FastestOf(n), Some(latency)) if uri.scheme() != Some(&Scheme::HTTP) => {
+                let candidates = self
+                    .proxies
+                    .iter()
+                    .filter_map(|prox| prox.intercept(uri))
+                    .collect();
+                latency.select(candidates, n, Intercepted::uri)
+            }
+            _ => self.proxies.iter().find_map(|prox| prox.intercept(uri)),
+        }
+    }
 
-        self.connect(req, true).await
+    /// Records how long it took to establish a tunnel through `proxy`.
+    fn record_proxy_latency(&self, proxy: &Uri, start: Instant, success: bool) {
+        if let Some(ref latency) = self.proxy_latency {
+            if success {
+                latency.record(proxy, start.elapsed());
+            } else {
+                latency.record_failure(proxy);
+            }
+        }
     }
 
+    /// Resolves hosts through the resolution memo of the request being connected.
+    fn use_dns_memo(&mut self, memo: ResolutionMemo) {
+        #[cfg(feature = "socks")]
+        self.resolver.set_memo(memo.clone());
+        self.http.resolver_mut().set_memo(memo);
+    }
+
+    #[cfg(feature = "tls")]
     fn create_https_connector(
         &self,
         http: HttpConnector,
@@ -500,14 +1531,23 @@ impl ConnectorService {
     ) -> Result<HttpsConnector<HttpConnector>, BoxError> {
         let (tcp_opts, tls_cfg, alpn_protocol) = conn_req.take_config_bundle();
 
-        let tls = tls_cfg
-            .map(|cfg| self.tls_builder.build(cfg))
-            .transpose()?
-            .unwrap_or_else(|| self.tls.clone());
+        let tls = self.tls.connector(tls_cfg)?;
 
         let mut connector = HttpsConnector::with_connector(http, tls);
         connector.set_alpn_protocol(alpn_protocol);
         connector.set_tcp_connect_options(tcp_opts);
+        connector.set_early_data(conn_req.early_data());
+        connector.set_server_name(conn_req.take_server_name());
+        connector.set_cert_verification(conn_req.take_cert_verification());
+        connector.set_alps(conn_req.take_alps());
+        connector.set_no_resume(conn_req.no_resume());
+        connector.set_keylog(
+            conn_req
+                .keylog()
+                .cloned()
+                .map(KeyLogPolicy::open_handle)
+                .transpose()?,
+        );
 
         Ok(connector)
     }
@@ -541,53 +1581,110 @@ impl Service<ConnRequest> for ConnectorService {
     fn call(&mut self, mut req: ConnRequest) -> Self::Future {
         debug!("starting new connection: {:?}", req.uri());
 
-        let intercepted = req
-            .take_proxy_matcher()
-            .and_then(|scheme| scheme.intercept(req.uri()))
-            .or_else(|| {
-                self.proxies
-                    .iter()
-                    .find_map(|prox| prox.intercept(req.uri()))
-            });
+        let mut this = self.clone();
+        if let Some(memo) = req.take_dns_memo() {
+            this.use_dns_memo(memo);
+        }
+
+        let matcher = req.take_proxy_matcher();
+
+        if req.no_proxy() {
+            trace!("bypassing proxies for {:?}", req.uri());
+            return Box::pin(with_timeout(this.connect(req, false), self.timeout));
+        }
+
+        #[cfg(feature = "pac")]
+        if let Some(matcher) = self.pac_matcher(matcher.as_ref(), req.uri()) {
+            return Box::pin(with_timeout(
+                this.connect_via_pac(req, matcher, self.clone()),
+                self.timeout,
+            ));
+        }
+
+        let intercepted = matcher.and_then(|scheme| scheme.intercept(req.uri()));
+
+        if intercepted.is_none() {
+            if let Some((race, candidates)) = self.race_candidates(req.uri()) {
+                return Box::pin(with_timeout(
+                    this.race_proxies(race, req, candidates),
+                    self.timeout,
+                ));
+            }
+        }
+
+        let intercepted = intercepted.or_else(|| self.intercept(req.uri()));
 
         if let Some(intercepted) = intercepted {
             return Box::pin(with_timeout(
-                self.clone().connect_via_proxy(req, intercepted),
+                this.connect_via_proxy(req, intercepted),
                 self.timeout,
             ));
         }
 
-        Box::pin(with_timeout(self.clone().connect(req, false), self.timeout))
+        Box::pin(with_timeout(this.connect(req, false), self.timeout))
     }
 }
 
 trait TlsInfoFactory {
-    fn tls_info(&self) -> Option<TlsInfo>;
-}
-
-impl TlsInfoFactory for TcpStream {
+    #[cfg(feature = "tls")]
     fn tls_info(&self) -> Option<TlsInfo> {
         None
     }
+
+    #[cfg(feature = "tls")]
+    fn tls_handshake_capture(&self) -> Option<TlsHandshakeCapture> {
+        None
+    }
+
+    #[cfg(feature = "tls")]
+    fn tls_early_data(&self) -> Option<EarlyDataHandshake> {
+        None
+    }
+
+    fn tls_handshake_started(&self) -> Option<Instant> {
+        None
+    }
 }
 
+impl TlsInfoFactory for TcpStream {}
+
 impl<T: TlsInfoFactory> TlsInfoFactory for TokioIo<T> {
+    #[cfg(feature = "tls")]
     fn tls_info(&self) -> Option<TlsInfo> {
         self.inner().tls_info()
     }
+
+    #[cfg(feature = "tls")]
+    fn tls_handshake_capture(&self) -> Option<TlsHandshakeCapture> {
+        self.inner().tls_handshake_capture()
+    }
+
+    #[cfg(feature = "tls")]
+    fn tls_early_data(&self) -> Option<EarlyDataHandshake> {
+        self.inner().tls_early_data()
+    }
+
+    fn tls_handshake_started(&self) -> Option<Instant> {
+        self.inner().tls_handshake_started()
+    }
 }
 
+#[cfg(feature = "tls")]
 impl TlsInfoFactory for SslStream<TcpStream> {
     fn tls_info(&self) -> Option<TlsInfo> {
-        self.ssl()
-            .peer_certificate()
-            .and_then(|c| c.to_der().ok())
-            .map(|c| TlsInfo {
-                peer_certificate: Some(c),
-            })
+        Some(tls_info(self.ssl()))
+    }
+
+    fn tls_handshake_capture(&self) -> Option<TlsHandshakeCapture> {
+        handshake_capture(self.ssl())
+    }
+
+    fn tls_handshake_started(&self) -> Option<Instant> {
+        handshake_started(self.ssl())
     }
 }
 
+#[cfg(feature = "tls")]
 impl TlsInfoFactory for MaybeHttpsStream<TcpStream> {
     fn tls_info(&self) -> Option<TlsInfo> {
         match self {
@@ -595,16 +1692,68 @@ impl TlsInfoFactory for MaybeHttpsStream<TcpStream> {
             MaybeHttpsStream::Http(_) => None,
         }
     }
+
+    fn tls_handshake_capture(&self) -> Option<TlsHandshakeCapture> {
+        match self {
+            MaybeHttpsStream::Https(tls) => tls.tls_handshake_capture(),
+            MaybeHttpsStream::Http(_) => None,
+        }
+    }
+
+    fn tls_handshake_started(&self) -> Option<Instant> {
+        match self {
+            MaybeHttpsStream::Https(tls) => tls.tls_handshake_started(),
+            MaybeHttpsStream::Http(_) => None,
+        }
+    }
 }
 
-impl TlsInfoFactory for SslStream<TokioIo<MaybeHttpsStream<TcpStream>>> {
+impl TlsInfoFactory for ProxyStream {
+    #[cfg(feature = "tls")]
     fn tls_info(&self) -> Option<TlsInfo> {
-        self.ssl()
-            .peer_certificate()
-            .and_then(|c| c.to_der().ok())
-            .map(|c| TlsInfo {
-                peer_certificate: Some(c),
-            })
+        match self {
+            ProxyStream::Tcp(stream) => stream.tls_info(),
+            #[cfg(unix)]
+            ProxyStream::Unix(_) => None,
+            #[cfg(feature = "tls")]
+            ProxyStream::Tls(stream) => stream.tls_info(),
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    fn tls_handshake_capture(&self) -> Option<TlsHandshakeCapture> {
+        match self {
+            ProxyStream::Tcp(stream) => stream.tls_handshake_capture(),
+            #[cfg(unix)]
+            ProxyStream::Unix(_) => None,
+            #[cfg(feature = "tls")]
+            ProxyStream::Tls(stream) => stream.tls_handshake_capture(),
+        }
+    }
+
+    fn tls_handshake_started(&self) -> Option<Instant> {
+        match self {
+            ProxyStream::Tcp(stream) => stream.tls_handshake_started(),
+            #[cfg(unix)]
+            ProxyStream::Unix(_) => None,
+            #[cfg(feature = "tls")]
+            ProxyStream::Tls(stream) => stream.tls_handshake_started(),
+        }
+    }
+}
+
+#[cfg(feature = "tls")]
+impl TlsInfoFactory for SslStream<TokioIo<ProxyStream>> {
+    fn tls_info(&self) -> Option<TlsInfo> {
+        Some(tls_info(self.ssl()))
+    }
+
+    fn tls_handshake_capture(&self) -> Option<TlsHandshakeCapture> {
+        handshake_capture(self.ssl())
+    }
+
+    fn tls_handshake_started(&self) -> Option<Instant> {
+        handshake_started(self.ssl())
     }
 }
 
@@ -635,22 +1784,49 @@ mod conn {
             pub(super) inner: BoxConn,
             pub(super) is_proxy: bool,
             pub(super) tls_info: bool,
+            pub(super) timing: ConnectTiming,
+            pub(super) proxy: Option<Uri>,
+            pub(super) proxy_latency: Option<Arc<ProxyLatency>>,
         }
     }
 
     impl Connection for Conn {
         fn connected(&self) -> Connected {
-            let connected = self.inner.connected().proxy(self.is_proxy);
+            let mut connected = self
+                .inner
+                .connected()
+                .proxy(self.is_proxy)
+                .extra(self.timing);
+
+            if let Some(ref proxy) = self.proxy {
+                connected = connected.extra(ProxyUsed(proxy.clone()));
+
+                // Samples the time to first byte of the responses received through the proxy
+                if let Some(ref latency) = self.proxy_latency {
+                    connected = connected.extra(ProxyTtfb::new(proxy.clone(), latency.clone()));
+                }
+            }
 
+            #[cfg(feature = "tls")]
             if self.tls_info {
                 if let Some(tls_info) = self.inner.tls_info() {
-                    connected.extra(tls_info)
-                } else {
-                    connected
+                    connected = connected.extra(tls_info);
                 }
-            } else {
-                connected
             }
+
+            // Only present when handshake capture was enabled for this connection
+            #[cfg(feature = "tls")]
+            if let Some(capture) = self.inner.tls_handshake_capture() {
+                connected = connected.extra(capture);
+            }
+
+            // Reported on the response once the handshake completed
+            #[cfg(feature = "tls")]
+            if let Some(handshake) = self.inner.tls_early_data() {
+                connected = connected.early_data(handshake);
+            }
+
+            connected
         }
     }
 
@@ -700,11 +1876,13 @@ mod conn {
     }
 }
 
+#[cfg(feature = "tls")]
 mod tls_conn {
     use std::{
         io::{self, IoSlice},
         pin::Pin,
         task::{Context, Poll},
+        time::Instant,
     };
 
     use pin_project_lite::pin_project;
@@ -714,19 +1892,30 @@ mod tls_conn {
     };
     use tokio_boring2::SslStream;
 
-    use super::{TlsInfo, TlsInfoFactory};
-    use crate::{
-        core::{
-            client::connect::{Connected, Connection},
-            rt::{Read, ReadBufCursor, TokioIo, Write},
-        },
-        tls::MaybeHttpsStream,
+    use super::{EarlyDataHandshake, ProxyStream, TlsHandshakeCapture, TlsInfo, TlsInfoFactory};
+    use crate::core::{
+        client::connect::{Connected, Connection},
+        rt::{Read, ReadBufCursor, TokioIo, Write},
     };
 
     pin_project! {
         pub(super) struct TlsConn<T> {
             #[pin]
-            pub(super) inner: TokioIo<SslStream<T>>,
+            inner: TokioIo<SslStream<T>>,
+            // Set on connections that sent early data
+            early_data: Option<EarlyDataHandshake>,
+        }
+    }
+
+    impl<T> TlsConn<T> {
+        pub(super) fn new(stream: SslStream<T>) -> TlsConn<T> {
+            TlsConn {
+                early_data: stream
+                    .ssl()
+                    .in_early_data()
+                    .then(EarlyDataHandshake::default),
+                inner: TokioIo::new(stream),
+            }
         }
     }
 
@@ -741,7 +1930,7 @@ mod tls_conn {
         }
     }
 
-    impl Connection for TlsConn<TokioIo<MaybeHttpsStream<TcpStream>>> {
+    impl Connection for TlsConn<TokioIo<ProxyStream>> {
         fn connected(&self) -> Connected {
             let connected = self.inner.inner().get_ref().connected();
             if self.inner.inner().ssl().selected_alpn_protocol() == Some(b"h2") {
@@ -758,8 +1947,13 @@ mod tls_conn {
             cx: &mut Context,
             buf: ReadBufCursor<'_>,
         ) -> Poll<tokio::io::Result<()>> {
-            let this = self.project();
-            Read::poll_read(this.inner, cx, buf)
+            let mut this = self.project();
+            let res = Read::poll_read(this.inner.as_mut(), cx, buf);
+            // The handshake completes while reading the response to the early data
+            if let Some(handshake) = this.early_data {
+                handshake.update(this.inner.inner().ssl());
+            }
+            res
         }
     }
 
@@ -810,27 +2004,59 @@ mod tls_conn {
         fn tls_info(&self) -> Option<TlsInfo> {
             self.inner.tls_info()
         }
+
+        fn tls_handshake_capture(&self) -> Option<TlsHandshakeCapture> {
+            self.inner.tls_handshake_capture()
+        }
+
+        fn tls_early_data(&self) -> Option<EarlyDataHandshake> {
+            self.early_data.clone()
+        }
+
+        fn tls_handshake_started(&self) -> Option<Instant> {
+            self.inner.tls_handshake_started()
+        }
     }
 }
 
 mod verbose {
+    use std::sync::Arc;
+
+    use http::HeaderName;
+
     use super::{AsyncConnWithInfo, BoxConn};
 
-    pub(super) const OFF: Wrapper = Wrapper(false);
+    pub(super) const OFF: Wrapper = Wrapper {
+        enabled: false,
+        redacted: None,
+    };
 
-    #[derive(Clone, Copy)]
-    pub(super) struct Wrapper(pub(super) bool);
+    #[derive(Clone)]
+    pub(super) struct Wrapper {
+        pub(super) enabled: bool,
+        /// The headers whose values are masked in the logs.
+        #[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+        redacted: Option<Arc<[HeaderName]>>,
+    }
 
     impl Wrapper {
+        /// Returns a wrapper masking the values of the `redacted` headers of the HTTP/1
+        /// messages written to the connection.
+        pub(super) fn redacting(mut self, redacted: Arc<[HeaderName]>) -> Wrapper {
+            self.redacted = Some(redacted);
+            self
+        }
+
         #[cfg_attr(not(feature = "tracing"), inline(always))]
         pub(super) fn wrap<T: AsyncConnWithInfo>(&self, conn: T) -> BoxConn {
             #[cfg(feature = "tracing")]
             {
-                if self.0 {
+                if self.enabled {
                     return Box::new(sealed::Verbose {
                         // truncate is fine
                         id: crate::util::fast_random() as u32,
                         inner: conn,
+                        redacted: self.redacted.clone(),
                     });
                 }
             }
@@ -845,22 +2071,28 @@ mod verbose {
             fmt,
             io::{self, IoSlice},
             pin::Pin,
+            sync::Arc,
             task::{Context, Poll},
+            time::Instant,
         };
 
+        use http::HeaderName;
+
         use super::super::TlsInfoFactory;
+        #[cfg(feature = "tls")]
+        use crate::tls::{EarlyDataHandshake, TlsHandshakeCapture, TlsInfo};
         use crate::{
             core::{
                 client::connect::{Connected, Connection},
                 rt::{Read, ReadBufCursor, Write},
             },
-            tls::TlsInfo,
             util::Escape,
         };
 
         pub(super) struct Verbose<T> {
             pub(super) id: u32,
             pub(super) inner: T,
+            pub(super) redacted: Option<Arc<[HeaderName]>>,
         }
 
         impl<T: Connection + Read + Write + Unpin> Connection for Verbose<T> {
@@ -904,7 +2136,14 @@ mod verbose {
             ) -> Poll<Result<usize, std::io::Error>> {
                 match Pin::new(&mut self.inner).poll_write(cx, buf) {
                     Poll::Ready(Ok(n)) => {
-                        trace!("{:08x} write: {:?}", self.id, Escape::new(&buf[..n]));
+                        trace!(
+                            "{:08x} write: {:?}",
+                            self.id,
+                            Redacted {
+                                bytes: &buf[..n],
+                                names: self.redacted.as_deref().unwrap_or_default(),
+                            }
+                        );
                         Poll::Ready(Ok(n))
                     }
                     Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
@@ -922,7 +2161,11 @@ mod verbose {
                         trace!(
                             "{:08x} write (vectored): {:?}",
                             self.id,
-                            Vectored { bufs, nwritten }
+                            Vectored {
+                                bufs,
+                                nwritten,
+                                names: self.redacted.as_deref().unwrap_or_default(),
+                            }
                         );
                         Poll::Ready(Ok(nwritten))
                     }
@@ -951,14 +2194,30 @@ mod verbose {
         }
 
         impl<T: TlsInfoFactory> TlsInfoFactory for Verbose<T> {
+            #[cfg(feature = "tls")]
             fn tls_info(&self) -> Option<TlsInfo> {
                 self.inner.tls_info()
             }
+
+            #[cfg(feature = "tls")]
+            fn tls_handshake_capture(&self) -> Option<TlsHandshakeCapture> {
+                self.inner.tls_handshake_capture()
+            }
+
+            #[cfg(feature = "tls")]
+            fn tls_early_data(&self) -> Option<EarlyDataHandshake> {
+                self.inner.tls_early_data()
+            }
+
+            fn tls_handshake_started(&self) -> Option<Instant> {
+                self.inner.tls_handshake_started()
+            }
         }
 
         struct Vectored<'a, 'b> {
             bufs: &'a [IoSlice<'b>],
             nwritten: usize,
+            names: &'a [HeaderName],
         }
 
         impl fmt::Debug for Vectored<'_, '_> {
@@ -969,11 +2228,53 @@ mod verbose {
                         break;
                     }
                     let n = std::cmp::min(left, buf.len());
-                    Escape::new(&buf[..n]).fmt(f)?;
+                    Redacted {
+                        bytes: &buf[..n],
+                        names: self.names,
+                    }
+                    .fmt(f)?;
                     left -= n;
                 }
                 Ok(())
             }
         }
+
+        /// Bytes written to a connection, with the values of the `names` headers masked.
+        struct Redacted<'a> {
+            bytes: &'a [u8],
+            names: &'a [HeaderName],
+        }
+
+        impl fmt::Debug for Redacted<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                if self.names.is_empty() {
+                    return Escape::new(self.bytes).fmt(f);
+                }
+
+                let mut masked = Vec::with_capacity(self.bytes.len());
+                for line in self.bytes.split_inclusive(|&b| b == b'\n') {
+                    let name = line
+                        .iter()
+                        .position(|&b| b == b':')
+                        .map(|colon| &line[..colon])
+                        .filter(|name| {
+                            self.names.iter().any(|redacted| {
+                                name.eq_ignore_ascii_case(redacted.as_str().as_bytes())
+                            })
+                        });
+                    match name {
+                        Some(name) => {
+                            masked.extend_from_slice(name);
+                            masked.extend_from_slice(b": ***");
+                            if line.ends_with(b"\r\n") {
+                                masked.extend_from_slice(b"\r\n");
+                            }
+                        }
+                        None => masked.extend_from_slice(line),
+                    }
+                }
+                Escape::new(&masked).fmt(f)
+            }
+        }
     }
 }
@@ -248,7 +248,7 @@ impl Jar {
     pub fn add_cookie_str(&self, cookie: &str, url: &url::Url) {
         let cookies = cookie_crate::Cookie::parse(cookie)
             .ok()
-            .map(|c| c.into_owned())
+            .and_then(|c| check_domain(c.into_owned(), url))
             .into_iter();
         self.0.write().store_response_cookies(cookies, url);
     }
@@ -275,7 +275,9 @@ impl Jar {
     /// // and now add to a `ClientBuilder`?
     /// ```
     pub fn add_cookie(&self, cookie: Cookie<'_>, url: &url::Url) {
-        let _ = self.0.write().insert_raw(&cookie.0, url);
+        if let Some(cookie) = check_domain(cookie.0, url) {
+            let _ = self.0.write().insert_raw(&cookie, url);
+        }
     }
 
     /// Removes a `Cookie` from the store, returning the `Cookie` if it was in the jar.
@@ -328,8 +330,9 @@ impl Jar {
 
 impl CookieStore for Jar {
     fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &url::Url) {
-        let iter =
-            cookie_headers.filter_map(|val| Cookie::parse(val).map(|c| c.0.into_owned()).ok());
+        let iter = cookie_headers
+            .filter_map(|val| Cookie::parse(val).map(|c| c.0.into_owned()).ok())
+            .filter_map(|c| check_domain(c, url));
 
         self.0.write().store_response_cookies(iter, url);
     }
@@ -337,7 +340,10 @@ impl CookieStore for Jar {
     fn cookies(&self, url: &url::Url) -> Option<Vec<HeaderValue>> {
         let mut cookies = Vec::new();
         let lock = self.0.read();
-        for (name, value) in lock.get_request_values(url) {
+        // RFC 6265, section 5.4: the cookies with longer paths are listed first.
+        let mut matches = lock.matches(url);
+        matches.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+        for (name, value) in matches.iter().map(|cookie| cookie.name_value()) {
             let mut cookie = bytes::BytesMut::with_capacity(64);
             cookie.put(name.as_bytes());
             cookie.put(&b"="[..]);
@@ -355,6 +361,35 @@ impl CookieStore for Jar {
     }
 }
 
+/// Rejects a cookie whose `Domain` attribute is a public suffix, unless it is the request host
+/// itself, in which case the cookie is made host-only (RFC 6265, section 5.3).
+#[cfg(feature = "psl")]
+fn check_domain<'c>(mut cookie: RawCookie<'c>, url: &url::Url) -> Option<RawCookie<'c>> {
+    let host_only = match cookie.domain() {
+        Some(domain) if crate::psl::is_public_suffix(domain) => {
+            if !url
+                .host_str()
+                .is_some_and(|host| host.eq_ignore_ascii_case(domain))
+            {
+                return None;
+            }
+            true
+        }
+        _ => false,
+    };
+
+    if host_only {
+        cookie.unset_domain();
+    }
+    Some(cookie)
+}
+
+#[cfg(not(feature = "psl"))]
+#[inline(always)]
+fn check_domain<'c>(cookie: RawCookie<'c>, _: &url::Url) -> Option<RawCookie<'c>> {
+    Some(cookie)
+}
+
 impl Default for Jar {
     fn default() -> Self {
         Self(RwLock::new(cookie_store::CookieStore::default()))
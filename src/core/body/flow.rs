@@ -0,0 +1,84 @@
+use std::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    task::{Context, Poll},
+};
+
+use futures_util::task::AtomicWaker;
+
+/// Consumer-driven flow control shared between a response body and its reader.
+///
+/// While paused, the body is not polled, so no HTTP/2 window updates are released
+/// and HTTP/1 connections stop reading from the socket.
+#[derive(Debug, Default)]
+pub(crate) struct FlowControl {
+    paused: AtomicBool,
+    target_buffer: AtomicUsize,
+    waker: AtomicWaker,
+}
+
+impl FlowControl {
+    pub(crate) fn pause(&self) {
+        self.paused.store(true, Ordering::Release);
+    }
+
+    pub(crate) fn resume(&self) {
+        self.paused.store(false, Ordering::Release);
+        self.waker.wake();
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn set_target_buffer(&self, bytes: usize) {
+        self.target_buffer.store(bytes, Ordering::Release);
+    }
+
+    /// Returns `Ready` once the reader is not paused.
+    pub(crate) fn poll_resumed(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if !self.is_paused() {
+            return Poll::Ready(());
+        }
+
+        self.waker.register(cx.waker());
+
+        // check again, `resume` may have run before the waker was registered
+        if self.is_paused() {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+
+    /// Returns how many of the `pending` received bytes may be released to the peer,
+    /// given the window it still has `available`.
+    ///
+    /// Without a target buffer everything is released. Otherwise releases are held
+    /// back so that the window open to the peer does not exceed the target.
+    pub(crate) fn releasable(&self, available: isize, pending: usize) -> usize {
+        match self.target_buffer.load(Ordering::Acquire) {
+            0 => pending,
+            target => {
+                let available = usize::try_from(available).unwrap_or(0);
+                pending.min(target.saturating_sub(available))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn releasable_respects_target_buffer() {
+        let flow = FlowControl::default();
+        assert_eq!(flow.releasable(65_535, 16_384), 16_384);
+
+        flow.set_target_buffer(32_768);
+        assert_eq!(flow.releasable(65_535, 16_384), 0);
+        assert_eq!(flow.releasable(20_000, 16_384), 12_768);
+        assert_eq!(flow.releasable(0, 16_384), 16_384);
+        assert_eq!(flow.releasable(-10, 65_536), 32_768);
+    }
+}
@@ -2,6 +2,7 @@ use std::{
     fmt,
     future::Future,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll, ready},
 };
 
@@ -11,7 +12,7 @@ use futures_util::{Stream, stream::FusedStream};
 use http::HeaderMap;
 use http_body::{Body, Frame, SizeHint};
 
-use super::DecodedLength;
+use super::{DecodedLength, FlowControl};
 use crate::core::{common::watch, proto::h2::ping};
 
 type BodySender = mpsc::Sender<Result<Bytes, crate::core::Error>>;
@@ -50,6 +51,8 @@ enum Kind {
         data_done: bool,
         ping: ping::Recorder,
         recv: http2::RecvStream,
+        flow: Arc<FlowControl>,
+        unreleased: usize,
     },
 }
 
@@ -115,6 +118,15 @@ impl Incoming {
         Incoming { kind }
     }
 
+    /// Creates a body yielding `chunk` at once.
+    #[cfg(feature = "vcr")]
+    pub(crate) fn full(chunk: Bytes) -> Incoming {
+        let (mut tx, rx) = Self::new_channel(DecodedLength::new(chunk.len() as u64), false);
+        // a fresh sender always has room for one message
+        let _ = tx.try_send_data(chunk);
+        rx
+    }
+
     #[allow(dead_code)]
     pub(crate) fn empty() -> Incoming {
         Incoming::new(Kind::Empty)
@@ -124,6 +136,7 @@ impl Incoming {
         recv: http2::RecvStream,
         mut content_length: DecodedLength,
         ping: ping::Recorder,
+        flow: Arc<FlowControl>,
     ) -> Self {
         // If the stream is already EOS, then the "unknown length" is clearly
         // actually ZERO.
@@ -136,6 +149,8 @@ impl Incoming {
             ping,
             content_length,
             recv,
+            flow,
+            unreleased: 0,
         })
     }
 }
@@ -176,11 +191,18 @@ impl Body for Incoming {
                 ref ping,
                 recv: ref mut h2,
                 content_length: ref mut len,
+                ref flow,
+                ref mut unreleased,
             } => {
                 if !*data_done {
+                    // capacity held back earlier may fit in the target buffer by now
+                    if *unreleased > 0 {
+                        release_capacity(h2, flow, unreleased, 0);
+                    }
+
                     match ready!(h2.poll_data(cx)) {
                         Some(Ok(bytes)) => {
-                            let _ = h2.flow_control().release_capacity(bytes.len());
+                            release_capacity(h2, flow, unreleased, bytes.len());
                             len.sub_if(bytes.len() as u64);
                             ping.record_data(bytes.len());
                             return Poll::Ready(Some(Ok(Frame::data(bytes))));
@@ -240,6 +262,22 @@ impl Body for Incoming {
     }
 }
 
+/// Releases the capacity of `received` bytes, plus any held back before, that fits
+/// within the target buffer of the reader.
+fn release_capacity(
+    h2: &mut http2::RecvStream,
+    flow: &FlowControl,
+    unreleased: &mut usize,
+    received: usize,
+) {
+    let pending = *unreleased + received;
+    let release = flow.releasable(h2.flow_control().available_capacity(), pending);
+    if release > 0 {
+        let _ = h2.flow_control().release_capacity(release);
+    }
+    *unreleased = pending - release;
+}
+
 impl fmt::Debug for Incoming {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         #[derive(Debug)]
@@ -20,7 +20,7 @@ impl DecodedLength {
     pub(crate) const CHUNKED: DecodedLength = DecodedLength(u64::MAX - 1);
     pub(crate) const ZERO: DecodedLength = DecodedLength(0);
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "vcr"))]
     pub(crate) fn new(len: u64) -> Self {
         debug_assert!(len <= MAX_LEN);
         DecodedLength(len)
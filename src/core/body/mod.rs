@@ -18,8 +18,9 @@
 //! [`http-body-util`]: https://docs.rs/http-body-util
 
 pub use self::incoming::Incoming;
-pub(crate) use self::{incoming::Sender, length::DecodedLength};
+pub(crate) use self::{flow::FlowControl, incoming::Sender, length::DecodedLength};
 
+mod flow;
 mod incoming;
 mod length;
 
@@ -2,7 +2,7 @@
 
 use httparse::ParserConfig;
 
-use crate::core::proto;
+use crate::{OriginalHeaders, core::proto};
 
 /// Builder for `Http1Config`.
 #[must_use]
@@ -19,9 +19,13 @@ pub struct Http1ConfigBuilder {
 #[derive(Debug, Default, Clone)]
 pub struct Http1Config {
     pub(crate) h09_responses: bool,
-    pub(crate) h1_parser_config: ParserConfig,
+    pub(crate) h1_allow_spaces_after_header_name: bool,
+    pub(crate) h1_ignore_invalid_headers: bool,
+    pub(crate) h1_allow_obsolete_multiline_headers: bool,
     pub(crate) h1_writev: Option<bool>,
     pub(crate) h1_preserve_header_case: bool,
+    pub(crate) h1_title_case_headers: bool,
+    pub(crate) h1_header_case: Option<OriginalHeaders>,
     pub(crate) h1_max_headers: Option<usize>,
     pub(crate) h1_read_buf_exact_size: Option<usize>,
     pub(crate) h1_max_buf_size: Option<usize>,
@@ -67,6 +71,35 @@ impl Http1ConfigBuilder {
         self
     }
 
+    /// Set whether the names of the request headers are written in title case, such as
+    /// `Accept-Encoding`, rather than in lowercase.
+    ///
+    /// This applies to the headers set by default as well as to the ones of the request, after
+    /// they are sorted by the headers order. Title case replaces the spellings of the headers
+    /// order, unless [`preserve_header_case`](Self::preserve_header_case) is set, in which case
+    /// it only applies to the headers the order does not spell.
+    ///
+    /// Default is false.
+    pub fn title_case_headers(mut self, title_case_headers: bool) -> Self {
+        self.config.h1_title_case_headers = title_case_headers;
+        self
+    }
+
+    /// Set the exact spellings the names of the request headers are written with.
+    ///
+    /// The names of the map are written with its spelling whatever the headers order and
+    /// [`title_case_headers`](Self::title_case_headers), such as the lowercase `sec-ch-ua`
+    /// headers Chrome sends along title-cased ones. The order of the map is not used.
+    ///
+    /// Default is `None`.
+    pub fn header_case<H>(mut self, header_case: H) -> Self
+    where
+        H: Into<Option<OriginalHeaders>>,
+    {
+        self.config.h1_header_case = header_case.into();
+        self
+    }
+
     /// Set the maximum number of headers.
     ///
     /// When a response is received, the parser will reserve a buffer to store headers for optimal
@@ -135,9 +168,7 @@ impl Http1ConfigBuilder {
     ///
     /// [RFC 7230 Section 3.2.4.]: https://tools.ietf.org/html/rfc7230#section-3.2.4
     pub fn allow_spaces_after_header_name_in_responses(mut self, enabled: bool) -> Self {
-        self.config
-            .h1_parser_config
-            .allow_spaces_after_header_name_in_responses(enabled);
+        self.config.h1_allow_spaces_after_header_name = enabled;
         self
     }
 
@@ -149,9 +180,7 @@ impl Http1ConfigBuilder {
     ///
     /// Default is false.
     pub fn ignore_invalid_headers_in_responses(mut self, enabled: bool) -> Self {
-        self.config
-            .h1_parser_config
-            .ignore_invalid_headers_in_responses(enabled);
+        self.config.h1_ignore_invalid_headers = enabled;
         self
     }
 
@@ -160,11 +189,8 @@ impl Http1ConfigBuilder {
         mut self,
         allow_obsolete_multiline_headers_in_responses: bool,
     ) -> Self {
-        self.config
-            .h1_parser_config
-            .allow_obsolete_multiline_headers_in_responses(
-                allow_obsolete_multiline_headers_in_responses,
-            );
+        self.config.h1_allow_obsolete_multiline_headers =
+            allow_obsolete_multiline_headers_in_responses;
         self
     }
 
@@ -181,4 +207,16 @@ impl Http1Config {
             config: Http1Config::default(),
         }
     }
+
+    /// Returns the response parser configuration.
+    pub(crate) fn h1_parser_config(&self) -> ParserConfig {
+        let mut config = ParserConfig::default();
+        config
+            .allow_spaces_after_header_name_in_responses(self.h1_allow_spaces_after_header_name)
+            .ignore_invalid_headers_in_responses(self.h1_ignore_invalid_headers)
+            .allow_obsolete_multiline_headers_in_responses(
+                self.h1_allow_obsolete_multiline_headers,
+            );
+        config
+    }
 }
@@ -6,8 +6,12 @@ pub use http2::frame::{
     SettingsOrder, SettingsOrderBuilder, StreamDependency, StreamId,
 };
 
+use std::sync::Arc;
+
+pub(crate) use crate::core::proto::h2::StreamHints;
+pub use crate::core::proto::h2::client::RemoteSettingsSnapshot;
 use crate::core::proto::{
-    h2::client::Config,
+    h2::client::{Config, GreaseSetting, OnRemoteSettings},
     {self},
 };
 
@@ -258,6 +262,61 @@ impl Http2ConfigBuilder {
         self
     }
 
+    /// Sets whether a setting of a reserved identifier is sent in the initial SETTINGS frame,
+    /// as Chrome does.
+    ///
+    /// The setting has a random identifier of the reserved form `0x?a?a` and a random value,
+    /// picked again for every connection unless [pinned](Self::grease_setting_value), and is
+    /// sent with the [experimental settings](Self::experimental_settings). Servers ignore
+    /// settings they do not know, as the client ignores those it receives.
+    ///
+    /// The setting is sent last, or at the [position](Self::grease_setting_position) set.
+    /// Without a [`settings_order`](Self::settings_order), the other settings are sent in the
+    /// order of their identifiers.
+    ///
+    /// Default is `false`.
+    pub fn grease_setting(mut self, enabled: bool) -> Self {
+        self.config.h2_builder.grease_setting = if enabled {
+            self.config
+                .h2_builder
+                .grease_setting
+                .or(Some(GreaseSetting::default()))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Sends the [GREASE setting](Self::grease_setting) with the identifier `id` and `value`
+    /// on every connection, instead of random ones, and enables it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is not of the reserved form `0x?a?a`.
+    pub fn grease_setting_value(mut self, id: u16, value: u32) -> Self {
+        assert!(
+            GreaseSetting::is_reserved(id),
+            "grease setting identifier must be of the form 0x?a?a"
+        );
+        self.config
+            .h2_builder
+            .grease_setting
+            .get_or_insert_default()
+            .pinned = Some((id, value));
+        self
+    }
+
+    /// Sends the [GREASE setting](Self::grease_setting) at index `position` of the settings
+    /// order, or last if the order has fewer settings, and enables it.
+    pub fn grease_setting_position(mut self, position: usize) -> Self {
+        self.config
+            .h2_builder
+            .grease_setting
+            .get_or_insert_default()
+            .position = Some(position);
+        self
+    }
+
     /// Sets the list of PRIORITY frames to be sent immediately after the connection is established,
     /// but before the first request is sent.
     ///
@@ -267,6 +326,10 @@ impl Http2ConfigBuilder {
     ///
     /// Each `Priority` in the list must have a valid (non-zero) stream ID. Any priority with a
     /// stream ID of zero will be ignored.
+    ///
+    /// The frames are sent once per connection, in the order of the list, after the SETTINGS
+    /// frame of the preface and before the first request. Firefox, for instance, sends PRIORITY
+    /// frames for the idle streams 3 to 13 to build the dependency tree of its requests.
     pub fn priorities<T>(mut self, priorities: T) -> Self
     where
         T: Into<Option<Priorities>>,
@@ -275,6 +338,24 @@ impl Http2ConfigBuilder {
         self
     }
 
+    /// Sets a callback invoked whenever the settings announced by the server change on a
+    /// connection.
+    ///
+    /// The callback receives the settings in effect, and runs on the connection task, so it
+    /// should return quickly. It is first invoked if the server's initial SETTINGS differ from
+    /// the values assumed before they arrived, such as
+    /// [`initial_max_send_streams`](Self::initial_max_send_streams).
+    ///
+    /// Lowering `SETTINGS_MAX_CONCURRENT_STREAMS` below the number of open streams lets those
+    /// streams finish, while new requests wait until a stream is available.
+    pub fn on_remote_settings<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(RemoteSettingsSnapshot) + Send + Sync + 'static,
+    {
+        self.config.h2_builder.on_remote_settings = Some(OnRemoteSettings(Arc::new(callback)));
+        self
+    }
+
     /// Builds the `Http2Config` instance.
     pub fn build(self) -> Http2Config {
         self.config
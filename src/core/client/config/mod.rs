@@ -1,9 +1,12 @@
 pub mod http1;
 pub mod http2;
+#[cfg(feature = "serde")]
+mod serde;
 
 use http1::Http1Config;
 use http2::Http2Config;
 
+#[cfg(feature = "tls")]
 use crate::tls::TlsConfig;
 
 /// TransportConfig holds configuration for HTTP/1, HTTP/2, and TLS transport layers.
@@ -14,6 +17,7 @@ use crate::tls::TlsConfig;
 pub(crate) struct TransportConfig {
     pub(super) http1_config: Option<Http1Config>,
     pub(super) http2_config: Option<Http2Config>,
+    #[cfg(feature = "tls")]
     pub(super) tls_config: Option<TlsConfig>,
 }
 
@@ -37,6 +41,7 @@ impl TransportConfig {
     }
 
     /// Sets the TLS configuration.
+    #[cfg(feature = "tls")]
     #[inline]
     pub fn set_tls_config<C>(&mut self, config: C)
     where
@@ -0,0 +1,465 @@
+//! Serde support for [`Http1Config`] and [`Http2Config`].
+//!
+//! Pseudo-headers and settings are written by name, and stream dependencies as
+//! `{ "stream_id", "weight", "exclusive" }` objects. Experimental HTTP/2 settings and
+//! the [`on_remote_settings`] callback are not serialized.
+//!
+//! [`on_remote_settings`]: super::http2::Http2ConfigBuilder::on_remote_settings
+
+use http::HeaderName;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de, ser};
+
+use super::{
+    http1::Http1Config,
+    http2::{
+        Http2Config, Priorities, Priority, PseudoId, PseudoOrder, SettingId, SettingsOrder,
+        StreamDependency, StreamId,
+    },
+};
+use crate::OriginalHeaders;
+
+/// The serialized form of [`Http1Config`].
+///
+/// Missing fields take the value of [`Http1Config::default`].
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Http1Repr {
+    http09_responses: bool,
+    writev: Option<bool>,
+    preserve_header_case: bool,
+    title_case_headers: bool,
+    header_case: Option<Vec<String>>,
+    max_headers: Option<usize>,
+    read_buf_exact_size: Option<usize>,
+    max_buf_size: Option<usize>,
+    allow_spaces_after_header_name_in_responses: bool,
+    ignore_invalid_headers_in_responses: bool,
+    allow_obsolete_multiline_headers_in_responses: bool,
+}
+
+impl Default for Http1Repr {
+    fn default() -> Self {
+        Http1Repr::from(&Http1Config::default())
+    }
+}
+
+impl From<&Http1Config> for Http1Repr {
+    fn from(config: &Http1Config) -> Self {
+        Http1Repr {
+            http09_responses: config.h09_responses,
+            writev: config.h1_writev,
+            preserve_header_case: config.h1_preserve_header_case,
+            title_case_headers: config.h1_title_case_headers,
+            header_case: config.h1_header_case.as_ref().map(|case| {
+                case.keys()
+                    .flat_map(|name| case.get_all(name))
+                    .map(|orig| String::from_utf8_lossy(orig.as_ref()).into_owned())
+                    .collect()
+            }),
+            max_headers: config.h1_max_headers,
+            read_buf_exact_size: config.h1_read_buf_exact_size,
+            max_buf_size: config.h1_max_buf_size,
+            allow_spaces_after_header_name_in_responses: config.h1_allow_spaces_after_header_name,
+            ignore_invalid_headers_in_responses: config.h1_ignore_invalid_headers,
+            allow_obsolete_multiline_headers_in_responses: config
+                .h1_allow_obsolete_multiline_headers,
+        }
+    }
+}
+
+impl TryFrom<Http1Repr> for Http1Config {
+    type Error = &'static str;
+
+    fn try_from(repr: Http1Repr) -> Result<Self, Self::Error> {
+        if repr.read_buf_exact_size.is_some() && repr.max_buf_size.is_some() {
+            return Err("`read_buf_exact_size` and `max_buf_size` are mutually exclusive");
+        }
+
+        if repr
+            .max_buf_size
+            .is_some_and(|max| max < crate::core::proto::h1::MINIMUM_MAX_BUFFER_SIZE)
+        {
+            return Err("`max_buf_size` is smaller than the minimum that h1 specifies");
+        }
+
+        let header_case = repr
+            .header_case
+            .map(|names| {
+                let mut case = OriginalHeaders::with_capacity(names.len());
+                for name in names {
+                    if HeaderName::from_bytes(name.as_bytes()).is_err() {
+                        return Err("`header_case` holds an invalid header name");
+                    }
+                    case.insert(name);
+                }
+                Ok(case)
+            })
+            .transpose()?;
+
+        Ok(Http1Config {
+            h09_responses: repr.http09_responses,
+            h1_allow_spaces_after_header_name: repr.allow_spaces_after_header_name_in_responses,
+            h1_ignore_invalid_headers: repr.ignore_invalid_headers_in_responses,
+            h1_allow_obsolete_multiline_headers: repr.allow_obsolete_multiline_headers_in_responses,
+            h1_writev: repr.writev,
+            h1_preserve_header_case: repr.preserve_header_case,
+            h1_title_case_headers: repr.title_case_headers,
+            h1_header_case: header_case,
+            h1_max_headers: repr.max_headers,
+            h1_read_buf_exact_size: repr.read_buf_exact_size,
+            h1_max_buf_size: repr.max_buf_size,
+        })
+    }
+}
+
+impl Serialize for Http1Config {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Http1Repr::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Http1Config {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = Http1Repr::deserialize(deserializer)?;
+        Http1Config::try_from(repr).map_err(de::Error::custom)
+    }
+}
+
+const PSEUDO_IDS: &[(&str, PseudoId)] = &[
+    (":method", PseudoId::Method),
+    (":scheme", PseudoId::Scheme),
+    (":authority", PseudoId::Authority),
+    (":path", PseudoId::Path),
+];
+
+const SETTING_IDS: &[(&str, SettingId)] = &[
+    ("HEADER_TABLE_SIZE", SettingId::HeaderTableSize),
+    ("ENABLE_PUSH", SettingId::EnablePush),
+    ("MAX_CONCURRENT_STREAMS", SettingId::MaxConcurrentStreams),
+    ("INITIAL_WINDOW_SIZE", SettingId::InitialWindowSize),
+    ("MAX_FRAME_SIZE", SettingId::MaxFrameSize),
+    ("MAX_HEADER_LIST_SIZE", SettingId::MaxHeaderListSize),
+    ("ENABLE_CONNECT_PROTOCOL", SettingId::EnableConnectProtocol),
+    ("NO_RFC7540_PRIORITIES", SettingId::NoRfc7540Priorities),
+];
+
+fn to_name<T: PartialEq>(names: &[(&'static str, T)], value: &T) -> Option<&'static str> {
+    names
+        .iter()
+        .find(|(_, v)| v == value)
+        .map(|(name, _)| *name)
+}
+
+fn from_name<T: Clone>(names: &[(&'static str, T)], name: &str) -> Option<T> {
+    names
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, value)| value.clone())
+}
+
+/// The serialized form of a [`StreamDependency`].
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DependencyRepr {
+    stream_id: u32,
+    weight: u8,
+    #[serde(default)]
+    exclusive: bool,
+}
+
+impl From<&StreamDependency> for DependencyRepr {
+    fn from(dep: &StreamDependency) -> Self {
+        DependencyRepr {
+            stream_id: u32::from(dep.dependency_id()),
+            weight: dep.weight(),
+            exclusive: dep.is_exclusive(),
+        }
+    }
+}
+
+impl From<DependencyRepr> for StreamDependency {
+    fn from(repr: DependencyRepr) -> Self {
+        StreamDependency::new(StreamId::from(repr.stream_id), repr.weight, repr.exclusive)
+    }
+}
+
+/// The serialized form of a [`Priority`] frame.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PriorityRepr {
+    stream_id: u32,
+    dependency: DependencyRepr,
+}
+
+/// The serialized form of [`Http2Config`].
+///
+/// Missing fields take the value of [`Http2Config::default`].
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Http2Repr {
+    adaptive_window: bool,
+    initial_stream_id: Option<u32>,
+    initial_connection_window_size: u32,
+    initial_stream_window_size: u32,
+    initial_max_send_streams: usize,
+    max_frame_size: Option<u32>,
+    max_header_list_size: Option<u32>,
+    header_table_size: Option<u32>,
+    max_concurrent_streams: Option<u32>,
+    enable_push: Option<bool>,
+    enable_connect_protocol: Option<bool>,
+    no_rfc7540_priorities: Option<bool>,
+    max_concurrent_reset_streams: Option<usize>,
+    max_send_buf_size: usize,
+    max_pending_accept_reset_streams: Option<usize>,
+    headers_stream_dependency: Option<DependencyRepr>,
+    headers_pseudo_order: Option<Vec<String>>,
+    settings_order: Option<Vec<String>>,
+    priorities: Option<Vec<PriorityRepr>>,
+}
+
+impl Default for Http2Repr {
+    fn default() -> Self {
+        Http2Repr::try_from(&Http2Config::default()).expect("default config is serializable")
+    }
+}
+
+impl TryFrom<&Http2Config> for Http2Repr {
+    type Error = &'static str;
+
+    fn try_from(config: &Http2Config) -> Result<Self, Self::Error> {
+        let h2 = &config.h2_builder;
+
+        let headers_pseudo_order = h2
+            .headers_pseudo_order
+            .clone()
+            .map(|order| {
+                order
+                    .into_iter()
+                    .map(|id| to_name(PSEUDO_IDS, &id).map(str::to_owned))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or("unknown pseudo-header")
+            })
+            .transpose()?;
+
+        let settings_order = h2
+            .settings_order
+            .clone()
+            .map(|order| {
+                order
+                    .into_iter()
+                    .map(|id| to_name(SETTING_IDS, &id).map(str::to_owned))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or("unknown setting")
+            })
+            .transpose()?;
+
+        let priorities = h2.priorities.clone().map(|priorities| {
+            priorities
+                .into_iter()
+                .map(|priority| PriorityRepr {
+                    stream_id: u32::from(priority.stream_id()),
+                    dependency: DependencyRepr::from(priority.dependency()),
+                })
+                .collect()
+        });
+
+        Ok(Http2Repr {
+            adaptive_window: h2.adaptive_window,
+            initial_stream_id: h2.initial_stream_id,
+            initial_connection_window_size: h2.initial_conn_window_size,
+            initial_stream_window_size: h2.initial_stream_window_size,
+            initial_max_send_streams: h2.initial_max_send_streams,
+            max_frame_size: h2.max_frame_size,
+            max_header_list_size: h2.max_header_list_size,
+            header_table_size: h2.header_table_size,
+            max_concurrent_streams: h2.max_concurrent_streams,
+            enable_push: h2.enable_push,
+            enable_connect_protocol: h2.enable_connect_protocol,
+            no_rfc7540_priorities: h2.no_rfc7540_priorities,
+            max_concurrent_reset_streams: h2.max_concurrent_reset_streams,
+            max_send_buf_size: h2.max_send_buffer_size,
+            max_pending_accept_reset_streams: h2.max_pending_accept_reset_streams,
+            headers_stream_dependency: h2.headers_stream_dependency.as_ref().map(Into::into),
+            headers_pseudo_order,
+            settings_order,
+            priorities,
+        })
+    }
+}
+
+impl TryFrom<Http2Repr> for Http2Config {
+    type Error = String;
+
+    fn try_from(repr: Http2Repr) -> Result<Self, Self::Error> {
+        if repr.max_send_buf_size > u32::MAX as usize {
+            return Err("`max_send_buf_size` must be no larger than `u32::MAX`".to_owned());
+        }
+
+        let headers_pseudo_order = repr
+            .headers_pseudo_order
+            .map(|names| {
+                names
+                    .iter()
+                    .map(|name| {
+                        from_name(PSEUDO_IDS, name)
+                            .ok_or_else(|| format!("unknown pseudo-header `{name}`"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(|ids| PseudoOrder::builder().extend(ids).build())
+            })
+            .transpose()?;
+
+        let settings_order = repr
+            .settings_order
+            .map(|names| {
+                names
+                    .iter()
+                    .map(|name| {
+                        from_name(SETTING_IDS, name)
+                            .ok_or_else(|| format!("unknown setting `{name}`"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(|ids| SettingsOrder::builder().extend(ids).build())
+            })
+            .transpose()?;
+
+        let priorities = repr.priorities.map(|priorities| {
+            Priorities::builder()
+                .extend(priorities.into_iter().map(|priority| {
+                    Priority::new(
+                        StreamId::from(priority.stream_id),
+                        priority.dependency.into(),
+                    )
+                }))
+                .build()
+        });
+
+        let mut config = Http2Config::default();
+        let h2 = &mut config.h2_builder;
+        h2.adaptive_window = repr.adaptive_window;
+        h2.initial_stream_id = repr.initial_stream_id;
+        h2.initial_conn_window_size = repr.initial_connection_window_size;
+        h2.initial_stream_window_size = repr.initial_stream_window_size;
+        h2.initial_max_send_streams = repr.initial_max_send_streams;
+        h2.max_frame_size = repr.max_frame_size;
+        h2.max_header_list_size = repr.max_header_list_size;
+        h2.header_table_size = repr.header_table_size;
+        h2.max_concurrent_streams = repr.max_concurrent_streams;
+        h2.enable_push = repr.enable_push;
+        h2.enable_connect_protocol = repr.enable_connect_protocol;
+        h2.no_rfc7540_priorities = repr.no_rfc7540_priorities;
+        h2.max_concurrent_reset_streams = repr.max_concurrent_reset_streams;
+        h2.max_send_buffer_size = repr.max_send_buf_size;
+        h2.max_pending_accept_reset_streams = repr.max_pending_accept_reset_streams;
+        h2.headers_stream_dependency = repr.headers_stream_dependency.map(Into::into);
+        h2.headers_pseudo_order = headers_pseudo_order;
+        h2.settings_order = settings_order;
+        h2.priorities = priorities;
+        Ok(config)
+    }
+}
+
+impl Serialize for Http2Config {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Http2Repr::try_from(self)
+            .map_err(ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Http2Config {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = Http2Repr::deserialize(deserializer)?;
+        Http2Config::try_from(repr).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use super::*;
+
+    fn chrome() -> Http2Config {
+        Http2Config::builder()
+            .initial_stream_window_size(6291456)
+            .initial_connection_window_size(15728640)
+            .max_header_list_size(262144)
+            .header_table_size(65536)
+            .enable_push(false)
+            .headers_stream_dependency(StreamDependency::new(StreamId::zero(), 255, true))
+            .headers_pseudo_order(
+                PseudoOrder::builder()
+                    .extend([
+                        PseudoId::Method,
+                        PseudoId::Authority,
+                        PseudoId::Scheme,
+                        PseudoId::Path,
+                    ])
+                    .build(),
+            )
+            .settings_order(
+                SettingsOrder::builder()
+                    .extend([
+                        SettingId::HeaderTableSize,
+                        SettingId::EnablePush,
+                        SettingId::InitialWindowSize,
+                        SettingId::MaxHeaderListSize,
+                    ])
+                    .build(),
+            )
+            .build()
+    }
+
+    #[test]
+    fn http2_config_round_trips() {
+        let json = serde_json::to_value(chrome()).unwrap();
+        assert_eq!(
+            json["headers_pseudo_order"],
+            serde_json::json!([":method", ":authority", ":scheme", ":path"])
+        );
+        assert_eq!(
+            json["headers_stream_dependency"],
+            serde_json::json!({ "stream_id": 0, "weight": 255, "exclusive": true })
+        );
+        assert_eq!(json["adaptive_window"], false);
+
+        let config: Http2Config = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(config.h2_builder.initial_stream_window_size, 6291456);
+        assert_eq!(serde_json::to_value(config).unwrap(), json);
+    }
+
+    #[test]
+    fn http2_config_rejects_unknown_names() {
+        serde_json::from_str::<Http2Config>(r#"{"max_frame_sise": 16384}"#).unwrap_err();
+        serde_json::from_str::<Http2Config>(r#"{"headers_pseudo_order": [":status"]}"#)
+            .unwrap_err();
+        serde_json::from_str::<Http2Config>(r#"{"settings_order": ["UNKNOWN"]}"#).unwrap_err();
+    }
+
+    #[test]
+    fn http1_config_round_trips() {
+        let config = Http1Config::builder()
+            .allow_obsolete_multiline_headers_in_responses(true)
+            .max_headers(100)
+            .title_case_headers(true)
+            .header_case({
+                let mut case = OriginalHeaders::new();
+                case.insert("sec-ch-ua");
+                case
+            })
+            .build();
+
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["allow_obsolete_multiline_headers_in_responses"], true);
+        assert_eq!(json["max_headers"], 100);
+        assert_eq!(json["header_case"], serde_json::json!(["sec-ch-ua"]));
+
+        let config: Http1Config = serde_json::from_value(json.clone()).unwrap();
+        assert!(config.h1_allow_obsolete_multiline_headers);
+        assert_eq!(serde_json::to_value(config).unwrap(), json);
+
+        serde_json::from_str::<Http1Config>(r#"{"max_buf_size": 1}"#).unwrap_err();
+        serde_json::from_str::<Http1Config>(r#"{"max_header": 1}"#).unwrap_err();
+        serde_json::from_str::<Http1Config>(r#"{"header_case": ["sec ch"]}"#).unwrap_err();
+    }
+}
@@ -257,7 +257,7 @@ impl Builder {
 
             let (tx, rx) = dispatch::channel();
             let mut conn = proto::Conn::new(io);
-            conn.set_h1_parser_config(opts.h1_parser_config);
+            conn.set_h1_parser_config(opts.h1_parser_config());
             if let Some(writev) = opts.h1_writev {
                 if writev {
                     conn.set_write_strategy_queue();
@@ -268,6 +268,12 @@ impl Builder {
             if opts.h1_preserve_header_case {
                 conn.set_preserve_header_case();
             }
+            if opts.h1_title_case_headers {
+                conn.set_title_case_headers();
+            }
+            if let Some(header_case) = opts.h1_header_case {
+                conn.set_header_case(header_case);
+            }
             if let Some(max_headers) = opts.h1_max_headers {
                 conn.set_http1_max_headers(max_headers);
             }
@@ -8,17 +8,19 @@ use std::{
     pin::Pin,
     sync::Arc,
     task::{Context, Poll, ready},
+    time::Duration,
 };
 
 use http::{Request, Response};
 use http_body::Body;
+use tokio::sync::oneshot;
 
 use crate::{
     core::{
         body::Incoming as IncomingBody,
         client::dispatch::{self, TrySendError},
         common::time::Time,
-        proto,
+        proto::{self, h2::ping::Pinger},
         rt::{Read, Timer, Write, bounds::Http2ClientConnExec},
     },
     http2::Http2Config,
@@ -27,12 +29,14 @@ use crate::{
 /// The sender side of an established connection.
 pub struct SendRequest<B> {
     dispatch: dispatch::UnboundedSender<Request<B>, Response<IncomingBody>>,
+    pinger: Pinger,
 }
 
 impl<B> Clone for SendRequest<B> {
     fn clone(&self) -> SendRequest<B> {
         SendRequest {
             dispatch: self.dispatch.clone(),
+            pinger: self.pinger.clone(),
         }
     }
 }
@@ -65,6 +69,7 @@ pub struct Builder<Ex> {
     pub(super) exec: Ex,
     pub(super) timer: Time,
     config: Http2Config,
+    chunk_bounds: Option<(usize, usize)>,
 }
 
 // ===== impl SendRequest
@@ -103,6 +108,14 @@ impl<B> SendRequest<B> {
     pub fn is_closed(&self) -> bool {
         self.dispatch.is_closed()
     }
+
+    /// Sends a PING frame on the connection, and returns a receiver of the round trip time of
+    /// its acknowledgement.
+    ///
+    /// Returns `None` if the connection is closed.
+    pub(crate) fn ping(&self) -> Option<oneshot::Receiver<Duration>> {
+        self.pinger.ping()
+    }
 }
 
 impl<B> SendRequest<B>
@@ -195,6 +208,7 @@ where
             exec,
             timer: Time::Empty,
             config: Default::default(),
+            chunk_bounds: None,
         }
     }
 
@@ -213,6 +227,15 @@ where
         self
     }
 
+    /// Sizes the DATA frames of request bodies from the drain rate of their streams, between
+    /// `min` and `max` bytes, unless a request sets a chunk hint.
+    ///
+    /// This is kept when the configuration is replaced with [`Builder::config`].
+    pub fn chunk_bounds(&mut self, bounds: Option<(usize, usize)>) -> &mut Builder<Ex> {
+        self.chunk_bounds = bounds;
+        self
+    }
+
     /// Constructs a connection with the configured options and IO.
     /// See [`client::conn`](crate::core::client::conn) for more.
     ///
@@ -239,6 +262,7 @@ where
                 io,
                 rx,
                 &opts.config.h2_builder,
+                opts.chunk_bounds,
                 opts.exec,
                 opts.timer,
             )
@@ -246,6 +270,7 @@ where
             Ok((
                 SendRequest {
                     dispatch: tx.unbound(),
+                    pinger: h2.pinger(),
                 },
                 Connection {
                     inner: (PhantomData, h2),
@@ -214,6 +214,20 @@ impl SocketAddrs {
         None
     }
 
+    /// Scopes the IPv6 addresses to the interface of index `scope_id`.
+    pub(super) fn with_ipv6_scope_id(self, scope_id: u32) -> SocketAddrs {
+        SocketAddrs::new(
+            self.iter
+                .map(|mut addr| {
+                    if let SocketAddr::V6(ref mut addr) = addr {
+                        addr.set_scope_id(scope_id);
+                    }
+                    addr
+                })
+                .collect(),
+        )
+    }
+
     #[inline]
     fn filter(self, predicate: impl FnMut(&SocketAddr) -> bool) -> SocketAddrs {
         SocketAddrs::new(self.iter.filter(predicate).collect())
@@ -22,7 +22,7 @@ use tokio::{
 };
 
 use super::{
-    Connected, Connection,
+    Connected, Connection, ProxyProtocolConfig,
     dns::{self, GaiResolver, Resolve, resolve},
 };
 use crate::core::{client::connect::options::TcpConnectOptions, error::BoxError, rt::TokioIo};
@@ -74,6 +74,8 @@ struct Config {
     happy_eyeballs_timeout: Option<Duration>,
     tcp_keepalive_config: TcpKeepaliveConfig,
     tcp_connect_options: Option<TcpConnectOptions>,
+    ipv6_scope_id: Option<u32>,
+    proxy_protocol: Option<ProxyProtocolConfig>,
     nodelay: bool,
     reuse_address: bool,
     send_buffer_size: Option<usize>,
@@ -224,6 +226,8 @@ impl<R> HttpConnector<R> {
                 happy_eyeballs_timeout: Some(Duration::from_millis(300)),
                 tcp_keepalive_config: TcpKeepaliveConfig::default(),
                 tcp_connect_options: None,
+                ipv6_scope_id: None,
+                proxy_protocol: None,
                 nodelay: false,
                 reuse_address: false,
                 send_buffer_size: None,
@@ -235,6 +239,12 @@ impl<R> HttpConnector<R> {
         }
     }
 
+    /// Returns a mutable reference to the resolver used for DNS lookups.
+    #[inline]
+    pub(crate) fn resolver_mut(&mut self) -> &mut R {
+        &mut self.resolver
+    }
+
     /// Option to enforce all `Uri`s have the `http` scheme.
     ///
     /// Enabled by default.
@@ -294,6 +304,19 @@ impl<R> HttpConnector<R> {
         self.config_mut().tcp_connect_options = options;
     }
 
+    /// Set the scope identifier of the zone of IPv6 literal hosts, the index of the interface
+    /// their link-local address is reached through.
+    #[inline]
+    pub(crate) fn set_ipv6_scope_id(&mut self, scope_id: Option<u32>) {
+        self.config_mut().ipv6_scope_id = scope_id;
+    }
+
+    /// Set the PROXY protocol header written at the start of each connection.
+    #[inline]
+    pub(crate) fn set_proxy_protocol(&mut self, proxy_protocol: Option<ProxyProtocolConfig>) {
+        self.config_mut().proxy_protocol = proxy_protocol;
+    }
+
     /// Set the connect timeout.
     ///
     /// If a domain resolves to multiple IP addresses, the timeout will be
@@ -443,7 +466,10 @@ where
         // If the host is already an IP addr (v4 or v6),
         // skip resolving the dns and start connecting right away.
         let addrs = if let Some(addrs) = dns::SocketAddrs::try_parse(host, port) {
-            addrs
+            match config.ipv6_scope_id {
+                Some(scope_id) => addrs.with_ipv6_scope_id(scope_id),
+                None => addrs,
+            }
         } else {
             let addrs = resolve(&mut self.resolver, dns::Name::new(host.into()))
                 .await
@@ -466,6 +492,13 @@ where
             warn!("tcp set_nodelay error: {}", e);
         }
 
+        if let Some(ref proxy_protocol) = config.proxy_protocol {
+            proxy_protocol
+                .write(&sock)
+                .await
+                .map_err(ConnectError::m("proxy protocol header error"))?;
+        }
+
         Ok(TokioIo::new(sock))
     }
 }
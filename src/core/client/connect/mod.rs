@@ -72,6 +72,7 @@ pub mod dns;
 mod http;
 mod options;
 pub mod proxy;
+mod proxy_protocol;
 
 use std::{
     fmt::{self, Formatter},
@@ -83,11 +84,15 @@ use std::{
 
 use ::http::Extensions;
 
+pub(crate) use self::http::ConnectError;
 pub use self::{
     http::{HttpConnector, HttpInfo},
     options::TcpConnectOptions,
+    proxy_protocol::{ProxyProtocolConfig, ProxyProtocolVersion},
     sealed::Connect,
 };
+#[cfg(feature = "tls")]
+use crate::tls::{EarlyData, EarlyDataHandshake};
 
 /// Describes a type returned by a connector.
 pub trait Connection {
@@ -105,6 +110,8 @@ pub struct Connected {
     pub(super) is_proxied: bool,
     pub(super) extra: Option<Extra>,
     pub(super) poisoned: PoisonPill,
+    #[cfg(feature = "tls")]
+    early_data: Option<EarlyDataHandshake>,
 }
 
 #[derive(Clone)]
@@ -161,6 +168,8 @@ impl Connected {
             is_proxied: false,
             extra: None,
             poisoned: PoisonPill::healthy(),
+            #[cfg(feature = "tls")]
+            early_data: None,
         }
     }
 
@@ -220,6 +229,35 @@ impl Connected {
         self.alpn == Alpn::H2
     }
 
+    /// Set that the transport sent early data, its handshake completing later.
+    #[cfg(feature = "tls")]
+    pub(crate) fn early_data(mut self, handshake: EarlyDataHandshake) -> Connected {
+        self.early_data = Some(handshake);
+        self
+    }
+
+    /// Determines if the handshake of the transport is still in progress after sending early
+    /// data, the transport not being safe to reuse until it completes.
+    pub(crate) fn is_in_early_data(&self) -> bool {
+        #[cfg(feature = "tls")]
+        return self
+            .early_data
+            .as_ref()
+            .is_some_and(EarlyDataHandshake::is_pending);
+
+        #[cfg(not(feature = "tls"))]
+        false
+    }
+
+    /// Returns [`EarlyData::Accepted`] if the server accepted the early data of the transport,
+    /// once its handshake completed.
+    #[cfg(feature = "tls")]
+    pub(crate) fn early_data_outcome(&self) -> Option<EarlyData> {
+        self.early_data
+            .as_ref()
+            .and_then(EarlyDataHandshake::outcome)
+    }
+
     /// Poison this connection
     ///
     /// A poisoned connection will not be reused for subsequent requests by the pool
@@ -238,6 +276,8 @@ impl Connected {
             is_proxied: self.is_proxied,
             extra: self.extra.clone(),
             poisoned: self.poisoned.clone(),
+            #[cfg(feature = "tls")]
+            early_data: self.early_data.clone(),
         }
     }
 }
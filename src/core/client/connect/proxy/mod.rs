@@ -3,6 +3,9 @@
 mod socks;
 mod tunnel;
 
+#[cfg(feature = "socks")]
+pub(crate) use self::socks::SocksError;
 #[cfg(feature = "socks")]
 pub use self::socks::{DnsResolve, Socks, SocksVersion};
 pub use self::tunnel::Tunnel;
+pub(crate) use self::tunnel::TunnelError;
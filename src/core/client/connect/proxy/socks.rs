@@ -2,6 +2,7 @@ use std::{
     borrow::Cow,
     pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
 
 use bytes::Bytes;
@@ -27,6 +28,7 @@ pub enum SocksError<C> {
 
     DnsFailure,
     MissingHost,
+    HandshakeTimedOut,
 }
 
 impl<C> std::fmt::Display for SocksError<C> {
@@ -43,6 +45,7 @@ impl<C> std::fmt::Display for SocksError<C> {
 
             Self::DnsFailure => f.write_str("could not resolve to acceptable address type"),
             Self::MissingHost => f.write_str("missing destination host"),
+            Self::HandshakeTimedOut => f.write_str("SOCKS handshake timed out"),
         }
     }
 }
@@ -118,6 +121,7 @@ pub struct Socks<C, R = GaiResolver> {
     auth: Option<(Bytes, Bytes)>,
     version: SocksVersion,
     dns_resolve: DnsResolve,
+    handshake_timeout: Option<Duration>,
 }
 
 impl<C, R> Socks<C, R>
@@ -132,7 +136,7 @@ where
     ///
     /// The `auth` parameter is optional and can be used to provide a username and password for
     /// SOCKS authentication. If provided, it should be a tuple containing the username and
-    /// password.
+    /// password. SOCKS4 only sends the username, as the user id of its request.
     pub fn new_with_resolver(inner: C, resolver: R, proxy: Uri) -> Self {
         Socks {
             inner,
@@ -141,6 +145,7 @@ where
             version: SocksVersion::V5,
             dns_resolve: DnsResolve::Local,
             auth: None,
+            handshake_timeout: None,
         }
     }
 
@@ -161,6 +166,14 @@ where
             ..self
         }
     }
+
+    /// Sets the time allowed for the SOCKS handshake, once connected to the proxy.
+    pub fn with_handshake_timeout(self, handshake_timeout: Option<Duration>) -> Self {
+        Socks {
+            handshake_timeout,
+            ..self
+        }
+    }
 }
 
 impl<C, R> Service<Uri> for Socks<C, R>
@@ -186,6 +199,7 @@ where
         let version = self.version;
         let dns_resolve = self.dns_resolve;
         let auth = self.auth.clone();
+        let handshake_timeout = self.handshake_timeout;
         let mut resolver = self.resolver.clone();
 
         let fut = async move {
@@ -214,31 +228,52 @@ where
                 DnsResolve::Remote => TargetAddr::Domain(Cow::Borrowed(host), port),
             };
 
-            match version {
-                SocksVersion::V4 => {
-                    // For SOCKS4, we connect directly to the target address.
-                    let stream = Socks4Stream::connect_with_socket(socket, target_addr).await?;
-                    Ok(stream.into_inner().into_inner())
-                }
-                SocksVersion::V5 => {
-                    // For SOCKS5, we need to handle authentication if provided.
-                    // The `auth` is an optional tuple of (username, password).
-                    let stream = match auth {
-                        Some((username, password)) => {
-                            let username = std::str::from_utf8(&username)?;
-                            let password = std::str::from_utf8(&password)?;
-                            Socks5Stream::connect_with_password_and_socket(
-                                socket,
-                                target_addr,
-                                username,
-                                password,
-                            )
-                            .await?
-                        }
-                        None => Socks5Stream::connect_with_socket(socket, target_addr).await?,
-                    };
-                    Ok(stream.into_inner().into_inner())
+            let handshake = async move {
+                match version {
+                    SocksVersion::V4 => {
+                        // For SOCKS4, the username is sent as the user id, without the
+                        // password.
+                        let stream = match auth {
+                            Some((username, _)) => {
+                                let user_id = std::str::from_utf8(&username)?;
+                                Socks4Stream::connect_with_userid_and_socket(
+                                    socket,
+                                    target_addr,
+                                    user_id,
+                                )
+                                .await?
+                            }
+                            None => Socks4Stream::connect_with_socket(socket, target_addr).await?,
+                        };
+                        Ok(stream.into_inner().into_inner())
+                    }
+                    SocksVersion::V5 => {
+                        // For SOCKS5, we need to handle authentication if provided.
+                        // The `auth` is an optional tuple of (username, password).
+                        let stream = match auth {
+                            Some((username, password)) => {
+                                let username = std::str::from_utf8(&username)?;
+                                let password = std::str::from_utf8(&password)?;
+                                Socks5Stream::connect_with_password_and_socket(
+                                    socket,
+                                    target_addr,
+                                    username,
+                                    password,
+                                )
+                                .await?
+                            }
+                            None => Socks5Stream::connect_with_socket(socket, target_addr).await?,
+                        };
+                        Ok(stream.into_inner().into_inner())
+                    }
                 }
+            };
+
+            match handshake_timeout {
+                Some(timeout) => tokio::time::timeout(timeout, handshake)
+                    .await
+                    .unwrap_or(Err(SocksError::HandshakeTimedOut)),
+                None => handshake.await,
             }
         };
 
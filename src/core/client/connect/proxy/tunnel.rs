@@ -5,7 +5,7 @@ use std::{
     task::{self, Poll},
 };
 
-use http::{HeaderMap, HeaderValue, Uri};
+use http::{HeaderMap, HeaderValue, StatusCode, Uri};
 use pin_project_lite::pin_project;
 use tower_service::Service;
 
@@ -38,10 +38,12 @@ pub enum TunnelError {
     ConnectFailed(BoxError),
     Io(std::io::Error),
     MissingHost,
-    ProxyAuthRequired,
+    /// The proxy responded with `407`, with the values of its `Proxy-Authenticate` headers.
+    ProxyAuthRequired(Vec<HeaderValue>),
     ProxyHeadersTooLong,
     TunnelUnexpectedEof,
-    TunnelUnsuccessful,
+    /// The proxy responded with a status other than `200`, if it could be parsed.
+    TunnelUnsuccessful(Option<StatusCode>),
 }
 
 pin_project! {
@@ -150,7 +152,10 @@ where
                 tunnel(
                     conn,
                     dst.host().ok_or(TunnelError::MissingHost)?,
-                    dst.port().map(|p| p.as_u16()).unwrap_or(443),
+                    dst.port_u16().unwrap_or(match dst.scheme_str() {
+                        Some("http") => 80,
+                        _ => 443,
+                    }),
                     &headers,
                 )
                 .await
@@ -229,10 +234,28 @@ where
                 return Err(TunnelError::ProxyHeadersTooLong);
             }
         // else read more
-        } else if recvd.starts_with(b"HTTP/1.1 407") {
-            return Err(TunnelError::ProxyAuthRequired);
+        } else if recvd.starts_with(b"HTTP/1.1 407") || recvd.starts_with(b"HTTP/1.0 407") {
+            let mut headers = [httparse::EMPTY_HEADER; 64];
+            let mut res = httparse::Response::new(&mut headers);
+            match res.parse(recvd) {
+                Ok(httparse::Status::Complete(_)) => {
+                    let challenges = res
+                        .headers
+                        .iter()
+                        .filter(|header| header.name.eq_ignore_ascii_case("proxy-authenticate"))
+                        .filter_map(|header| HeaderValue::from_bytes(header.value).ok())
+                        .collect();
+                    return Err(TunnelError::ProxyAuthRequired(challenges));
+                }
+                // read more
+                Ok(httparse::Status::Partial) if pos < buf.len() => {}
+                _ => return Err(TunnelError::ProxyAuthRequired(Vec::new())),
+            }
         } else {
-            return Err(TunnelError::TunnelUnsuccessful);
+            let status = recvd
+                .get(9..12)
+                .and_then(|status| StatusCode::from_bytes(status).ok());
+            return Err(TunnelError::TunnelUnsuccessful(status));
         }
     }
 }
@@ -243,16 +266,27 @@ impl std::fmt::Display for TunnelError {
 
         f.write_str(match self {
             TunnelError::MissingHost => "missing destination host",
-            TunnelError::ProxyAuthRequired => "proxy authorization required",
+            TunnelError::ProxyAuthRequired(_) => "proxy authorization required",
             TunnelError::ProxyHeadersTooLong => "proxy response headers too long",
             TunnelError::TunnelUnexpectedEof => "unexpected end of file",
-            TunnelError::TunnelUnsuccessful => "unsuccessful",
+            TunnelError::TunnelUnsuccessful(_) => "unsuccessful",
             TunnelError::ConnectFailed(_) => "failed to create underlying connection",
             TunnelError::Io(_) => "io error establishing tunnel",
         })
     }
 }
 
+impl TunnelError {
+    /// Returns the status the proxy refused the tunnel with.
+    pub(crate) fn status(&self) -> Option<StatusCode> {
+        match self {
+            TunnelError::ProxyAuthRequired(_) => Some(StatusCode::PROXY_AUTHENTICATION_REQUIRED),
+            TunnelError::TunnelUnsuccessful(status) => *status,
+            _ => None,
+        }
+    }
+}
+
 impl std::error::Error for TunnelError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
@@ -0,0 +1,213 @@
+//! The PROXY protocol of HAProxy, whose header tells the server of a connection the address of
+//! the client it stands for.
+//!
+//! See <https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt>.
+
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+};
+
+use tokio::net::TcpStream;
+
+use crate::util::Escape;
+
+/// The signature starting the headers of version 2.
+const SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// The version of the PROXY protocol header, see [`ProxyProtocolConfig`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ProxyProtocolVersion {
+    /// The text header of version 1, such as `PROXY TCP4 192.0.2.1 198.51.100.7 56324 443`.
+    V1,
+    /// The binary header of version 2.
+    #[default]
+    V2,
+}
+
+/// The PROXY protocol header written at the start of the connections of a client, see
+/// [`ClientBuilder::proxy_protocol`](crate::ClientBuilder::proxy_protocol).
+///
+/// The header names the source address of the connection, the local address of its socket
+/// unless [another](ProxyProtocolConfig::source) is set, and its destination, the address the
+/// socket is connected to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProxyProtocolConfig {
+    version: ProxyProtocolVersion,
+    source: Option<SocketAddr>,
+    proxies: bool,
+    verbose: bool,
+}
+
+impl ProxyProtocolConfig {
+    /// Creates the configuration of a header of `version`.
+    pub fn new(version: ProxyProtocolVersion) -> ProxyProtocolConfig {
+        ProxyProtocolConfig {
+            version,
+            source: None,
+            proxies: true,
+            verbose: false,
+        }
+    }
+
+    /// Sets the source address and port of the header, the client address the server learns.
+    ///
+    /// Defaults to the local address of each connection.
+    pub fn source(mut self, source: SocketAddr) -> ProxyProtocolConfig {
+        self.source = Some(source);
+        self
+    }
+
+    /// Sets whether the header is written on the connections to proxies. Defaults to `true`.
+    ///
+    /// The connections made through a proxy are to the proxy, so the header is written to the
+    /// proxy, not to the destination inside a tunnel. When disabled, only direct connections
+    /// have a header.
+    pub fn proxies(mut self, enabled: bool) -> ProxyProtocolConfig {
+        self.proxies = enabled;
+        self
+    }
+
+    /// Logs the headers written, for [`connection_verbose`].
+    ///
+    /// [`connection_verbose`]: crate::ClientBuilder::connection_verbose
+    pub(crate) fn verbose(mut self, enabled: bool) -> ProxyProtocolConfig {
+        self.verbose = enabled;
+        self
+    }
+
+    /// Returns whether the header is written on the connections to proxies.
+    pub(crate) fn writes_to_proxies(&self) -> bool {
+        self.proxies
+    }
+
+    /// Writes the header of the connection `stream`, before any other byte.
+    pub(crate) async fn write(&self, stream: &TcpStream) -> io::Result<()> {
+        let local = self.source.map_or_else(|| stream.local_addr(), Ok)?;
+        let peer = stream.peer_addr()?;
+        let header = self.header(local, peer);
+        if self.verbose {
+            trace!(
+                "proxy protocol header to {peer}: {:?}",
+                Escape::new(&header)
+            );
+        }
+
+        let mut unwritten = &header[..];
+        while !unwritten.is_empty() {
+            stream.writable().await?;
+            match stream.try_write(unwritten) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => unwritten = &unwritten[n..],
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the header of a connection from `source` to `destination`.
+    fn header(&self, source: SocketAddr, destination: SocketAddr) -> Vec<u8> {
+        // both addresses are of the same family, an IPv4 one being mapped to IPv6 if the other
+        // is IPv6
+        let (source_ip, destination_ip) = match (source.ip(), destination.ip()) {
+            (IpAddr::V4(source), IpAddr::V6(destination)) => {
+                (IpAddr::V6(source.to_ipv6_mapped()), IpAddr::V6(destination))
+            }
+            (IpAddr::V6(source), IpAddr::V4(destination)) => {
+                (IpAddr::V6(source), IpAddr::V6(destination.to_ipv6_mapped()))
+            }
+            ips => ips,
+        };
+
+        match self.version {
+            ProxyProtocolVersion::V1 => {
+                let family = if source_ip.is_ipv4() { "TCP4" } else { "TCP6" };
+                format!(
+                    "PROXY {family} {source_ip} {destination_ip} {} {}\r\n",
+                    source.port(),
+                    destination.port()
+                )
+                .into_bytes()
+            }
+            ProxyProtocolVersion::V2 => {
+                let mut header = SIGNATURE.to_vec();
+                // version 2, PROXY command
+                header.push(0x21);
+                match (source_ip, destination_ip) {
+                    (IpAddr::V4(source), IpAddr::V4(destination)) => {
+                        // TCP over IPv4
+                        header.push(0x11);
+                        header.extend_from_slice(&12u16.to_be_bytes());
+                        header.extend_from_slice(&source.octets());
+                        header.extend_from_slice(&destination.octets());
+                    }
+                    (source, destination) => {
+                        // TCP over IPv6
+                        header.push(0x21);
+                        header.extend_from_slice(&36u16.to_be_bytes());
+                        header.extend_from_slice(&ipv6_octets(source));
+                        header.extend_from_slice(&ipv6_octets(destination));
+                    }
+                }
+                header.extend_from_slice(&source.port().to_be_bytes());
+                header.extend_from_slice(&destination.port().to_be_bytes());
+                header
+            }
+        }
+    }
+}
+
+fn ipv6_octets(ip: IpAddr) -> [u8; 16] {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped().octets(),
+        IpAddr::V6(ip) => ip.octets(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_1_headers() {
+        let config = ProxyProtocolConfig::new(ProxyProtocolVersion::V1);
+        let header = config.header(
+            "192.0.2.1:56324".parse().unwrap(),
+            "198.51.100.7:443".parse().unwrap(),
+        );
+        assert_eq!(header, b"PROXY TCP4 192.0.2.1 198.51.100.7 56324 443\r\n");
+
+        let header = config.header(
+            "192.0.2.1:56324".parse().unwrap(),
+            "[2001:db8::7]:443".parse().unwrap(),
+        );
+        assert_eq!(
+            header,
+            b"PROXY TCP6 ::ffff:192.0.2.1 2001:db8::7 56324 443\r\n"
+        );
+    }
+
+    #[test]
+    fn version_2_headers() {
+        let config = ProxyProtocolConfig::new(ProxyProtocolVersion::V2);
+        let header = config.header(
+            "192.0.2.1:56324".parse().unwrap(),
+            "198.51.100.7:443".parse().unwrap(),
+        );
+        let mut expected = SIGNATURE.to_vec();
+        expected.extend_from_slice(&[0x21, 0x11, 0, 12]);
+        expected.extend_from_slice(&[192, 0, 2, 1, 198, 51, 100, 7]);
+        expected.extend_from_slice(&[0xdc, 0x04, 0x01, 0xbb]);
+        assert_eq!(header, expected);
+
+        let header = config.header(
+            "[2001:db8::1]:56324".parse().unwrap(),
+            "[2001:db8::7]:443".parse().unwrap(),
+        );
+        assert_eq!(header.len(), 16 + 36);
+        assert_eq!(header[12..16], [0x21, 0x21, 0, 36]);
+        assert_eq!(header[16..18], [0x20, 0x01]);
+        assert_eq!(header[48..], [0xdc, 0x04, 0x01, 0xbb]);
+    }
+}
@@ -18,8 +18,9 @@ use std::{
     future::Future,
     num::NonZeroU32,
     pin::Pin,
+    sync::Arc,
     task::{self, Poll},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use futures_util::future::{self, Either, FutureExt, TryFutureExt};
@@ -33,6 +34,9 @@ use pool::Ver;
 use sync_wrapper::SyncWrapper;
 
 use crate::{
+    ConnId, EmulationSelection, KeepaliveOutcome, PooledConnection, RequestAttempt,
+    client::middleware::retry::RetryBudget,
+    connect::ConnectTiming,
     core::{
         body::Incoming,
         client::{
@@ -43,13 +47,25 @@ use crate::{
         common::{Exec, Lazy, lazy, timer},
         error::BoxError,
         ext::{
-            RequestConfig, RequestHttpVersionPref, RequestProxyMatcher, RequestTcpConnectOptions,
+            RequestConfig, RequestHttpVersionPref, RequestIpv6Zone, RequestNoProxy,
+            RequestProxyMatcher, RequestTcpConnectOptions, RequestTemplatedProxyAuth,
             RequestTransportConfig,
         },
         rt::{Executor, Timer},
     },
-    proxy::Matcher as ProxyMacher,
-    tls::{AlpnProtocol, TlsConfig},
+    dns::ResolutionMemo,
+    proxy::{Matcher as ProxyMacher, ProxyTtfb, TemplatedAuth},
+};
+#[cfg(feature = "tls")]
+use crate::{
+    core::ext::{
+        RequestAlps, RequestCertVerification, RequestServerName, RequestTlsKeyLog,
+        RequestTlsNoResume,
+    },
+    tls::{
+        AlpnProtocol, AlpsOverride, CertVerification, EarlyData, KeyLogPolicy, ServerNameOverride,
+        TlsConfig,
+    },
 };
 
 type BoxSendFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
@@ -65,11 +81,62 @@ pub struct ConnRequest {
     uri: Uri,
     version: Option<Version>,
     proxy_matcher: Option<ProxyMacher>,
+    no_proxy: bool,
+    templated_proxy_auth: Option<Arc<[TemplatedAuth]>>,
     tcp_opts: Option<TcpConnectOptions>,
+    #[cfg(feature = "tls")]
     tls_config: Option<TlsConfig>,
+    #[cfg(feature = "tls")]
+    server_name: Option<ServerNameOverride>,
+    #[cfg(feature = "tls")]
+    cert_verification: Option<CertVerification>,
+    #[cfg(feature = "tls")]
+    alps: Option<AlpsOverride>,
+    #[cfg(feature = "tls")]
+    early_data: bool,
+    #[cfg(feature = "tls")]
+    no_resume: bool,
+    #[cfg(feature = "tls")]
+    keylog: Option<KeyLogPolicy>,
+    emulation: Option<EmulationSelection>,
+    #[cfg(feature = "tls")]
+    tls_info: bool,
+    dns_memo: Option<ResolutionMemo>,
+    ipv6_scope_id: Option<u32>,
 }
 
 impl ConnRequest {
+    /// Creates a connection request for `uri` without any per-request overrides.
+    fn new(uri: Uri) -> ConnRequest {
+        ConnRequest {
+            uri,
+            version: None,
+            proxy_matcher: None,
+            no_proxy: false,
+            templated_proxy_auth: None,
+            tcp_opts: None,
+            #[cfg(feature = "tls")]
+            tls_config: None,
+            #[cfg(feature = "tls")]
+            server_name: None,
+            #[cfg(feature = "tls")]
+            cert_verification: None,
+            #[cfg(feature = "tls")]
+            alps: None,
+            #[cfg(feature = "tls")]
+            early_data: false,
+            #[cfg(feature = "tls")]
+            no_resume: false,
+            #[cfg(feature = "tls")]
+            keylog: None,
+            emulation: None,
+            #[cfg(feature = "tls")]
+            tls_info: false,
+            dns_memo: None,
+            ipv6_scope_id: None,
+        }
+    }
+
     /// Returns a reference to the target URI for this connection request.
     #[inline]
     pub(crate) fn uri(&self) -> &Uri {
@@ -82,6 +149,97 @@ impl ConnRequest {
         &mut self.uri
     }
 
+    /// Returns the TCP options of the connection, without consuming them.
+    #[inline]
+    pub(crate) fn tcp_opts(&self) -> Option<&TcpConnectOptions> {
+        self.tcp_opts.as_ref()
+    }
+
+    /// Returns whether the request may be sent as TLS early data.
+    #[cfg(feature = "tls")]
+    #[inline]
+    pub(crate) fn early_data(&self) -> bool {
+        self.early_data
+    }
+
+    /// Returns whether the connection must not resume a cached TLS session.
+    #[cfg(feature = "tls")]
+    #[inline]
+    pub(crate) fn no_resume(&self) -> bool {
+        self.no_resume
+    }
+
+    /// Returns the key logging policy of the connection, overriding the connector's.
+    #[cfg(feature = "tls")]
+    #[inline]
+    pub(crate) fn keylog(&self) -> Option<&KeyLogPolicy> {
+        self.keylog.as_ref()
+    }
+
+    /// Returns whether TLS info should be recorded for the connection, whether or not the
+    /// connector records it for every connection.
+    #[cfg(feature = "tls")]
+    #[inline]
+    pub(crate) fn tls_info(&self) -> bool {
+        self.tls_info
+    }
+
+    /// Returns the scope identifier of the IPv6 literal host of the URI, if it has a zone.
+    #[inline]
+    pub(crate) fn ipv6_scope_id(&self) -> Option<u32> {
+        self.ipv6_scope_id
+    }
+
+    /// Takes and returns the TLS server name override, if any, consuming it from the request.
+    #[cfg(feature = "tls")]
+    #[inline]
+    pub(crate) fn take_server_name(&mut self) -> Option<ServerNameOverride> {
+        self.server_name.take()
+    }
+
+    /// Takes and returns the certificate verification override, if any, consuming it from the
+    /// request.
+    #[cfg(feature = "tls")]
+    #[inline]
+    pub(crate) fn take_cert_verification(&mut self) -> Option<CertVerification> {
+        self.cert_verification.take()
+    }
+
+    /// Takes and returns the ALPS override, if any, consuming it from the request.
+    #[cfg(feature = "tls")]
+    #[inline]
+    pub(crate) fn take_alps(&mut self) -> Option<AlpsOverride> {
+        self.alps.take()
+    }
+
+    /// Takes and returns the resolution memo of the request, if any, consuming it from the
+    /// request.
+    #[inline]
+    pub(crate) fn take_dns_memo(&mut self) -> Option<ResolutionMemo> {
+        self.dns_memo.take()
+    }
+
+    /// Returns whether the connection is made directly, bypassing every proxy.
+    #[inline]
+    pub(crate) fn no_proxy(&self) -> bool {
+        self.no_proxy
+    }
+
+    /// Returns the credentials the auth template of `proxy` gave for the request, if any.
+    #[inline]
+    pub(crate) fn templated_proxy_auth(&self, proxy: &Uri) -> Option<&TemplatedAuth> {
+        self.templated_proxy_auths()?
+            .iter()
+            .find(|auth| auth.is_for(proxy))
+    }
+
+    /// Returns the credentials the auth templates of the proxy and of the proxies chained
+    /// before it gave for the request, if any.
+    #[inline]
+    pub(crate) fn templated_proxy_auths(&self) -> Option<&Arc<[TemplatedAuth]>> {
+        self.templated_proxy_auth.as_ref()
+    }
+
     /// Takes and returns the proxy matcher, if any, consuming it from the request.
     #[inline]
     pub(crate) fn take_proxy_matcher(&mut self) -> Option<ProxyMacher> {
@@ -92,6 +250,7 @@ impl ConnRequest {
     ///
     /// This method consumes the TCP and TLS options from the request, and determines the ALPN
     /// protocol based on the HTTP version (HTTP/1.x or HTTP/2).
+    #[cfg(feature = "tls")]
     #[inline]
     pub(crate) fn take_config_bundle(
         &mut self,
@@ -114,14 +273,32 @@ impl ConnRequest {
     /// Returns a `PoolKey` representing the unique identity of this connection for pooling
     /// purposes.
     ///
-    /// The key includes the URI, HTTP version, proxy matcher, and TCP options.
+    /// The key includes the URI, HTTP version, proxy matcher and bypass, the credentials of
+    /// the auth template of the proxy, TCP options, the per-request TLS overrides, the emulation
+    /// profile picked for the request, and the scope of the IPv6 zone, so that connections
+    /// authenticated or verified differently, with another fingerprint, or to another interface
+    /// are never shared.
     #[inline]
     fn pool_key(&self) -> PoolKey {
         PoolKey {
             uri: self.uri.clone(),
             version: self.version,
             proxy_matcher: self.proxy_matcher.clone(),
+            no_proxy: self.no_proxy,
+            templated_proxy_auth: self.templated_proxy_auth.clone(),
             tcp_connect_options: self.tcp_opts.clone(),
+            #[cfg(feature = "tls")]
+            server_name: self.server_name.clone(),
+            #[cfg(feature = "tls")]
+            cert_verification: self.cert_verification.clone(),
+            #[cfg(feature = "tls")]
+            alps: self.alps.clone(),
+            #[cfg(feature = "tls")]
+            no_resume: self.no_resume,
+            #[cfg(feature = "tls")]
+            keylog: self.keylog.clone(),
+            emulation: self.emulation,
+            ipv6_scope_id: self.ipv6_scope_id,
         }
     }
 }
@@ -139,9 +316,10 @@ pub struct Client<C, B> {
     pool: pool::Pool<PoolClient<B>, PoolKey>,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 struct Config {
     retry_canceled_requests: bool,
+    retry_budget: Option<RetryBudget>,
     set_host: bool,
     ver: Ver,
 }
@@ -198,7 +376,21 @@ struct PoolKey {
     uri: Uri,
     version: Option<Version>,
     proxy_matcher: Option<ProxyMacher>,
+    no_proxy: bool,
+    templated_proxy_auth: Option<Arc<[TemplatedAuth]>>,
     tcp_connect_options: Option<TcpConnectOptions>,
+    #[cfg(feature = "tls")]
+    server_name: Option<ServerNameOverride>,
+    #[cfg(feature = "tls")]
+    cert_verification: Option<CertVerification>,
+    #[cfg(feature = "tls")]
+    alps: Option<AlpsOverride>,
+    #[cfg(feature = "tls")]
+    no_resume: bool,
+    #[cfg(feature = "tls")]
+    keylog: Option<KeyLogPolicy>,
+    emulation: Option<EmulationSelection>,
+    ipv6_scope_id: Option<u32>,
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -314,7 +506,19 @@ where
         // Extract config extensions
         let (transport_config, version, proxy_matcher, tcp_connect_options) =
             extract_request_configs(req.extensions_mut());
-
+        #[cfg(feature = "tls")]
+        let (server_name, cert_verification, alps, no_resume, keylog) =
+            extract_tls_configs(req.extensions_mut());
+        let no_proxy =
+            RequestConfig::<RequestNoProxy>::remove(req.extensions_mut()).unwrap_or_default();
+        let templated_proxy_auth =
+            RequestConfig::<RequestTemplatedProxyAuth>::remove(req.extensions_mut());
+        // the zone is dropped once redirects leave its address
+        let ipv6_scope_id = RequestConfig::<RequestIpv6Zone>::remove(req.extensions_mut())
+            .filter(|zone| uri.host().is_some_and(|host| zone.applies_to(host)))
+            .map(|zone| zone.scope_id());
+
+        #[cfg(feature = "tls")]
         let mut tls_config = None;
         let mut this = self.clone();
 
@@ -325,15 +529,46 @@ where
             if let Some(config) = cfg.http2_config.take() {
                 this.h2_builder.config(config);
             }
-            tls_config = cfg.tls_config.take();
+            #[cfg(feature = "tls")]
+            {
+                tls_config = cfg.tls_config.take();
+            }
         }
 
+        // Only requests that are safe to replay may be sent as early data,
+        // and never again once the server rejected it.
+        #[cfg(feature = "tls")]
+        let early_data = matches!(*req.method(), Method::GET | Method::HEAD)
+            && req.body().is_end_stream()
+            && req.extensions().get::<EarlyData>().is_none();
+
         let conn_req = ConnRequest {
             uri,
             version,
-            proxy_matcher,
+            // the proxy of the request is not used when bypassing proxies
+            proxy_matcher: proxy_matcher.filter(|_| !no_proxy),
+            no_proxy,
+            templated_proxy_auth: templated_proxy_auth.filter(|_| !no_proxy),
             tcp_opts: tcp_connect_options,
+            #[cfg(feature = "tls")]
             tls_config,
+            #[cfg(feature = "tls")]
+            server_name,
+            #[cfg(feature = "tls")]
+            cert_verification,
+            #[cfg(feature = "tls")]
+            alps,
+            #[cfg(feature = "tls")]
+            early_data,
+            #[cfg(feature = "tls")]
+            no_resume,
+            #[cfg(feature = "tls")]
+            keylog,
+            emulation: req.extensions().get::<EmulationSelection>().copied(),
+            #[cfg(feature = "tls")]
+            tls_info: false,
+            dns_memo: req.extensions().get::<ResolutionMemo>().cloned(),
+            ipv6_scope_id,
         };
 
         ResponseFuture::new(this.send_request(req, conn_req))
@@ -345,10 +580,22 @@ where
         conn_req: ConnRequest,
     ) -> Result<Response<Incoming>, Error> {
         let uri = req.uri().clone();
+        #[cfg(feature = "tls")]
+        let early_data = req.extensions().get::<EarlyData>().copied();
+        let mut retried = None;
 
         loop {
             req = match self.try_send_request(req, conn_req.clone()).await {
-                Ok(resp) => return Ok(resp),
+                Ok(mut resp) => {
+                    #[cfg(feature = "tls")]
+                    if let Some(early_data) = early_data {
+                        resp.extensions_mut().insert(early_data);
+                    }
+                    if let Some(attempt) = retried {
+                        resp.extensions_mut().insert(attempt);
+                    }
+                    return Ok(resp);
+                }
                 Err(TrySendError::Nope(err)) => return Err(err),
                 Err(TrySendError::Retryable {
                     mut req,
@@ -361,11 +608,23 @@ where
                         return Err(error);
                     }
 
+                    if let Some(ref budget) = self.config.retry_budget {
+                        if !budget.withdraw() {
+                            trace!("retry budget exhausted, not retrying canceled request");
+                            return Err(error);
+                        }
+                    }
+
                     trace!(
                         "unstarted request canceled, trying again (reason={:?})",
                         error
                     );
                     *req.uri_mut() = uri.clone();
+                    if let Some(RequestAttempt(attempt)) = req.extensions().get().copied() {
+                        let attempt = RequestAttempt(attempt.saturating_add(1));
+                        req.extensions_mut().insert(attempt);
+                        retried = Some(attempt);
+                    }
                     req
                 }
             }
@@ -374,16 +633,24 @@ where
 
     async fn try_send_request(
         &self,
-        mut req: Request<B>,
+        req: Request<B>,
         conn_req: ConnRequest,
     ) -> Result<Response<Incoming>, TrySendError<B>> {
-        let mut pooled = self
+        let pooled = self
             .connection_for(conn_req)
             .await
             // `connection_for` already retries checkout errors, so if
             // it returns an error, there's not much else to retry
             .map_err(TrySendError::Nope)?;
 
+        self.send_on(pooled, req).await
+    }
+
+    async fn send_on(
+        &self,
+        mut pooled: pool::Pooled<PoolClient<B>, PoolKey>,
+        mut req: Request<B>,
+    ) -> Result<Response<Incoming>, TrySendError<B>> {
         if pooled.is_http1() {
             if req.version() == Version::HTTP_2 {
                 warn!("Connection is HTTP/1, but request requires HTTP/2");
@@ -418,6 +685,7 @@ where
             authority_form(req.uri_mut());
         }
 
+        let sent = Instant::now();
         let mut res = match pooled.try_send_request(req).await {
             Ok(res) => res,
             Err(mut err) => {
@@ -442,6 +710,25 @@ where
             extra.set(res.extensions_mut());
         }
 
+        // Sample the time to first byte of responses received through a proxy
+        if let Some(ttfb) = res.extensions_mut().remove::<ProxyTtfb>() {
+            ttfb.record(sent.elapsed());
+        }
+
+        // Only the request a connection was established for may be sent as early data, and
+        // reading its response completed the handshake.
+        #[cfg(feature = "tls")]
+        if !pooled.is_reused() {
+            if let Some(early_data) = pooled.conn_info.early_data_outcome() {
+                res.extensions_mut().insert(early_data);
+            }
+        }
+
+        // The connection was established for an earlier request
+        if pooled.is_reused() && res.extensions().get::<ConnectTiming>().is_some() {
+            res.extensions_mut().insert(ConnectTiming::REUSED);
+        }
+
         // If pooled is HTTP/2, we can toss this reference immediately.
         //
         // when pooled is dropped, it will try to insert back into the
@@ -573,13 +860,111 @@ where
         }
     }
 
+    /// Establishes a new connection for a probe of the origin of `req`, never checking out an
+    /// idle one.
+    ///
+    /// Only the transport is established unless `send` or `keep` is set. With `send`, `req` is
+    /// sent on the connection, and with `keep`, the connection is added to the pool once done.
+    ///
+    /// The connection info is stored in `connected` as soon as the connection is established.
+    pub(crate) async fn probe(
+        &self,
+        mut req: Request<B>,
+        send: bool,
+        keep: bool,
+        connected: &mut Option<Connected>,
+    ) -> Result<Option<Response<Incoming>>, Error> {
+        let mut conn_req = ConnRequest::new(normalize_uri(&mut req, false)?);
+        // The info of a kept connection is added to the responses of later requests
+        #[cfg(feature = "tls")]
+        {
+            conn_req.tls_info = !keep;
+        }
+
+        if !send && !keep {
+            let io = self
+                .connector
+                .clone()
+                .connect(conn_req)
+                .await
+                .map_err(|src| e!(Connect, src))?;
+            *connected = Some(io.connected());
+            return Ok(None);
+        }
+
+        let pool = if keep {
+            self.pool.clone()
+        } else {
+            pool::Pool::disabled()
+        };
+        let pooled = self.connect_with(conn_req, pool).await?;
+        *connected = Some(pooled.conn_info.clone());
+        if !send {
+            return Ok(None);
+        }
+
+        match self.send_on(pooled, req).await {
+            Ok(res) => Ok(Some(res)),
+            Err(TrySendError::Retryable { error, .. } | TrySendError::Nope(error)) => Err(error),
+        }
+    }
+
+    /// Returns the connections held by the pool.
+    pub(crate) fn pooled_connections(&self) -> Vec<PooledConnection> {
+        let mut conns = Vec::new();
+        self.pool.for_each_idle(|key, idle_at, client| {
+            if let Some(authority) = key.uri.authority() {
+                conns.push(PooledConnection {
+                    id: client.id,
+                    authority: authority.clone(),
+                    version: if client.is_http2() {
+                        Version::HTTP_2
+                    } else {
+                        Version::HTTP_11
+                    },
+                    idle_since: idle_at,
+                });
+            }
+        });
+        conns
+    }
+
+    /// Checks that the pooled connection `id` is healthy, and evicts it from the pool if not.
+    pub(crate) async fn keepalive(&self, id: ConnId, timeout: Duration) -> KeepaliveOutcome {
+        let check = self
+            .pool
+            .find_idle(|client| (client.id == id).then(|| client.keepalive_check()));
+
+        match check {
+            None => return KeepaliveOutcome::NotPooled,
+            Some(KeepaliveCheck::Open) => return KeepaliveOutcome::Open,
+            Some(KeepaliveCheck::Closed) => trace!("connection {} is closed", id),
+            Some(KeepaliveCheck::Ping(pong)) => match tokio::time::timeout(timeout, pong).await {
+                Ok(Ok(rtt)) => return KeepaliveOutcome::Acked(rtt),
+                Ok(Err(_closed)) => trace!("connection {} closed before the pong", id),
+                Err(_elapsed) => trace!("connection {} did not ack the ping in time", id),
+            },
+        }
+
+        self.pool.evict_idle(|client| client.id == id);
+        KeepaliveOutcome::Evicted
+    }
+
     fn connect_to(
         &self,
         conn_req: ConnRequest,
     ) -> impl Lazy<Output = Result<pool::Pooled<PoolClient<B>, PoolKey>, Error>> + Send + Unpin + 'static
+    {
+        self.connect_with(conn_req, self.pool.clone())
+    }
+
+    fn connect_with(
+        &self,
+        conn_req: ConnRequest,
+        pool: pool::Pool<PoolClient<B>, PoolKey>,
+    ) -> impl Lazy<Output = Result<pool::Pooled<PoolClient<B>, PoolKey>, Error>> + Send + Unpin + 'static
     {
         let executor = self.exec.clone();
-        let pool = self.pool.clone();
 
         let h1_builder = self.h1_builder.clone();
         let h2_builder = self.h2_builder.clone();
@@ -743,6 +1128,7 @@ where
                             Ok(pool.pooled(
                                 connecting,
                                 PoolClient {
+                                    id: ConnId::next(),
                                     conn_info: connected,
                                     tx,
                                 },
@@ -797,7 +1183,7 @@ where
 impl<C: Clone, B> Clone for Client<C, B> {
     fn clone(&self) -> Client<C, B> {
         Client {
-            config: self.config,
+            config: self.config.clone(),
             exec: self.exec.clone(),
 
             h1_builder: self.h1_builder.clone(),
@@ -851,6 +1237,7 @@ impl Future for ResponseFuture {
 // FIXME: allow() required due to `impl Trait` leaking types to this lint
 #[allow(missing_debug_implementations)]
 struct PoolClient<B> {
+    id: ConnId,
     conn_info: Connected,
     tx: PoolTx<B>,
 }
@@ -896,6 +1283,27 @@ impl<B> PoolClient<B> {
             PoolTx::Http2(ref tx) => tx.is_ready(),
         }
     }
+
+    /// Checks the connection for a keepalive, sending a PING on HTTP/2 connections.
+    fn keepalive_check(&self) -> KeepaliveCheck {
+        if self.is_poisoned() || !self.is_ready() {
+            return KeepaliveCheck::Closed;
+        }
+        match self.tx {
+            PoolTx::Http1(_) => KeepaliveCheck::Open,
+
+            PoolTx::Http2(ref tx) => tx
+                .ping()
+                .map_or(KeepaliveCheck::Closed, KeepaliveCheck::Ping),
+        }
+    }
+}
+
+/// The state of a pooled connection found by a keepalive.
+enum KeepaliveCheck {
+    Closed,
+    Open,
+    Ping(tokio::sync::oneshot::Receiver<Duration>),
 }
 
 impl<B: Body + 'static> PoolClient<B> {
@@ -918,22 +1326,36 @@ where
     B: Send + 'static,
 {
     fn is_open(&self) -> bool {
-        !self.is_poisoned() && self.is_ready()
+        // A connection that sent early data is not reused before its handshake completed,
+        // since other requests would be sent as early data too.
+        !self.is_poisoned() && self.is_ready() && !self.conn_info.is_in_early_data()
     }
 
     fn reserve(self) -> pool::Reservation<Self> {
         match self.tx {
             PoolTx::Http1(tx) => pool::Reservation::Unique(PoolClient {
+                id: self.id,
                 conn_info: self.conn_info,
                 tx: PoolTx::Http1(tx),
             }),
 
+            // Shared once reinserted after the handshake completed
+            PoolTx::Http2(tx) if self.conn_info.is_in_early_data() => {
+                pool::Reservation::Unique(PoolClient {
+                    id: self.id,
+                    conn_info: self.conn_info,
+                    tx: PoolTx::Http2(tx),
+                })
+            }
+
             PoolTx::Http2(tx) => {
                 let b = PoolClient {
+                    id: self.id,
                     conn_info: self.conn_info.clone(),
                     tx: PoolTx::Http2(tx.clone()),
                 };
                 let a = PoolClient {
+                    id: self.id,
                     conn_info: self.conn_info,
                     tx: PoolTx::Http2(tx),
                 };
@@ -1016,6 +1438,26 @@ fn extract_request_configs(
     (transport_config, version, proxy, tcp)
 }
 
+#[cfg(feature = "tls")]
+fn extract_tls_configs(
+    extensions: &mut http::Extensions,
+) -> (
+    Option<ServerNameOverride>,
+    Option<CertVerification>,
+    Option<AlpsOverride>,
+    bool,
+    Option<KeyLogPolicy>,
+) {
+    let server_name =
+        RequestConfig::<RequestServerName>::remove(extensions).filter(|name| name.sni.is_some());
+    let cert_verification = RequestConfig::<RequestCertVerification>::remove(extensions)
+        .filter(|verification| !verification.is_empty());
+    let alps = RequestConfig::<RequestAlps>::remove(extensions);
+    let no_resume = RequestConfig::<RequestTlsNoResume>::remove(extensions).unwrap_or_default();
+    let keylog = RequestConfig::<RequestTlsKeyLog>::remove(extensions);
+    (server_name, cert_verification, alps, no_resume, keylog)
+}
+
 fn normalize_uri<B>(req: &mut Request<B>, is_http_connect: bool) -> Result<Uri, Error> {
     let uri = req.uri().clone();
 
@@ -1114,6 +1556,7 @@ impl Builder {
         Self {
             client_config: Config {
                 retry_canceled_requests: true,
+                retry_budget: None,
                 set_host: true,
                 ver: Ver::Auto,
             },
@@ -1224,6 +1667,15 @@ impl Builder {
         self
     }
 
+    /// Sizes the DATA frames of HTTP/2 request bodies adaptively, between `min` and `max`
+    /// bytes.
+    ///
+    /// Default is `None`, sending body chunks as they are yielded.
+    pub fn http2_chunk_bounds(&mut self, bounds: Option<(usize, usize)>) -> &mut Self {
+        self.h2_builder.chunk_bounds(bounds);
+        self
+    }
+
     /// Provide a timer to be used for timeouts and intervals in connection pools.
     pub fn pool_timer<M>(&mut self, timer: M) -> &mut Self
     where
@@ -1250,6 +1702,15 @@ impl Builder {
         self
     }
 
+    /// Set the budget that retries of canceled requests are withdrawn from.
+    ///
+    /// Default is `None`, retrying without limit.
+    #[inline]
+    pub fn retry_budget(&mut self, budget: Option<RetryBudget>) -> &mut Self {
+        self.client_config.retry_budget = budget;
+        self
+    }
+
     /// Set whether to automatically add the `Host` header to requests.
     ///
     /// If true, and a request does not include a `Host` header, one will be
@@ -1272,7 +1733,7 @@ impl Builder {
         let exec = self.exec.clone();
         let timer = self.pool_timer.clone();
         Client {
-            config: self.client_config,
+            config: self.client_config.clone(),
             exec: exec.clone(),
 
             h1_builder: self.h1_builder.clone(),
@@ -157,6 +157,11 @@ impl<T, K: Key> Pool<T, K> {
         Pool { inner }
     }
 
+    /// Returns a pool that never keeps connections.
+    pub(crate) fn disabled() -> Pool<T, K> {
+        Pool { inner: None }
+    }
+
     pub(crate) fn is_enabled(&self) -> bool {
         self.inner.is_some()
     }
@@ -242,6 +247,61 @@ impl<T: Poolable, K: Key> Pool<T, K> {
         }
     }
 
+    /// Calls `f` with every idle connection of the pool, and the instant it became idle.
+    pub(crate) fn for_each_idle<F>(&self, mut f: F)
+    where
+        F: FnMut(&K, Instant, &T),
+    {
+        if let Some(ref enabled) = self.inner {
+            let inner = enabled.lock();
+            for (key, values) in inner.idle.iter() {
+                for entry in values {
+                    f(key, entry.idle_at, &entry.value);
+                }
+            }
+        }
+    }
+
+    /// Returns the first value `f` returns for an idle connection of the pool.
+    pub(crate) fn find_idle<F, R>(&self, mut f: F) -> Option<R>
+    where
+        F: FnMut(&T) -> Option<R>,
+    {
+        let enabled = self.inner.as_ref()?;
+        let inner = enabled.lock();
+        inner
+            .idle
+            .iter()
+            .flat_map(|(_, values)| values)
+            .find_map(|entry| f(&entry.value))
+    }
+
+    /// Removes the idle connections that `f` returns `true` for.
+    pub(crate) fn evict_idle<F>(&self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        if let Some(ref enabled) = self.inner {
+            let mut inner = enabled.lock();
+            let mut keys_to_remove = Vec::new();
+            for (key, values) in inner.idle.iter_mut() {
+                values.retain(|entry| {
+                    if f(&entry.value) {
+                        trace!("evicting idle connection for {:?}", key);
+                        return false;
+                    }
+                    true
+                });
+                if values.is_empty() {
+                    keys_to_remove.push(key.clone());
+                }
+            }
+            for key in keys_to_remove {
+                inner.idle.remove(&key);
+            }
+        }
+    }
+
     fn reuse(&self, key: &K, value: T) -> Pooled<T, K> {
         debug!("reuse idle connection for {:?}", key);
         // TODO: unhack this
@@ -908,6 +968,35 @@ mod tests {
         };
     }
 
+    #[tokio::test]
+    async fn test_pool_inspect_and_evict_idle() {
+        let pool = pool_no_timer();
+        drop(pool.pooled(c(host_key("foo")), Uniq(41)));
+        drop(pool.pooled(c(host_key("foo")), Uniq(42)));
+        drop(pool.pooled(c(host_key("bar")), Uniq(43)));
+
+        let mut idle = Vec::new();
+        pool.for_each_idle(|key, _, value| idle.push((key.1.to_string(), value.0)));
+        idle.sort();
+        assert_eq!(
+            idle,
+            [
+                ("bar".to_owned(), 43),
+                ("foo".to_owned(), 41),
+                ("foo".to_owned(), 42)
+            ]
+        );
+
+        assert_eq!(
+            pool.find_idle(|value| (value.0 == 42).then_some(value.0 * 2)),
+            Some(84)
+        );
+
+        pool.evict_idle(|value| value.0 != 42);
+        assert!(pool.locked().idle.peek(&host_key("bar")).is_none());
+        assert_eq!(pool.find_idle(|value| Some(value.0)), Some(42));
+    }
+
     /// Helper to check if the future is ready after polling once.
     struct PollOnce<'a, F>(&'a mut F);
 
@@ -155,9 +155,89 @@ impl RequestConfigValue for RequestProxyMatcher {
     type Value = crate::proxy::Matcher;
 }
 
+#[derive(Clone, Copy)]
+pub(crate) struct RequestNoProxy;
+
+impl RequestConfigValue for RequestNoProxy {
+    type Value = bool;
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct RequestTemplatedProxyAuth;
+
+impl RequestConfigValue for RequestTemplatedProxyAuth {
+    type Value = std::sync::Arc<[crate::proxy::TemplatedAuth]>;
+}
+
+#[cfg(feature = "tls")]
+#[derive(Clone, Copy)]
+pub(crate) struct RequestServerName;
+
+#[cfg(feature = "tls")]
+impl RequestConfigValue for RequestServerName {
+    type Value = crate::tls::ServerNameOverride;
+}
+
+#[cfg(feature = "tls")]
+#[derive(Clone, Copy)]
+pub(crate) struct RequestAlps;
+
+#[cfg(feature = "tls")]
+impl RequestConfigValue for RequestAlps {
+    type Value = crate::tls::AlpsOverride;
+}
+
+#[cfg(feature = "tls")]
+#[derive(Clone, Copy)]
+pub(crate) struct RequestTlsNoResume;
+
+#[cfg(feature = "tls")]
+impl RequestConfigValue for RequestTlsNoResume {
+    type Value = bool;
+}
+
+#[cfg(feature = "tls")]
+#[derive(Clone, Copy)]
+pub(crate) struct RequestTlsKeyLog;
+
+#[cfg(feature = "tls")]
+impl RequestConfigValue for RequestTlsKeyLog {
+    type Value = crate::tls::KeyLogPolicy;
+}
+
+#[cfg(feature = "tls")]
+#[derive(Clone, Copy)]
+pub(crate) struct RequestCertVerification;
+
+#[cfg(feature = "tls")]
+impl RequestConfigValue for RequestCertVerification {
+    type Value = crate::tls::CertVerification;
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct RequestH2StreamHints;
+
+impl RequestConfigValue for RequestH2StreamHints {
+    type Value = crate::core::proto::h2::StreamHints;
+}
+
 #[derive(Clone, Copy)]
 pub(crate) struct RequestOriginalHeaders;
 
 impl RequestConfigValue for RequestOriginalHeaders {
     type Value = crate::core::header::OriginalHeaders;
 }
+
+#[derive(Clone, Copy)]
+pub(crate) struct RequestCaptureRawHead;
+
+impl RequestConfigValue for RequestCaptureRawHead {
+    type Value = bool;
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct RequestIpv6Zone;
+
+impl RequestConfigValue for RequestIpv6Zone {
+    type Value = crate::Ipv6Zone;
+}
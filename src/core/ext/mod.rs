@@ -3,14 +3,38 @@
 mod config;
 mod h1_reason_phrase;
 mod header;
+mod raw_head;
 
 use std::fmt;
 
+#[cfg(feature = "tls")]
 pub(crate) use config::{
-    RequestConfig, RequestConfigValue, RequestHttpVersionPref, RequestOriginalHeaders,
-    RequestProxyMatcher, RequestTcpConnectOptions, RequestTransportConfig,
+    RequestAlps, RequestCertVerification, RequestServerName, RequestTlsKeyLog, RequestTlsNoResume,
+};
+pub(crate) use config::{
+    RequestCaptureRawHead, RequestConfig, RequestConfigValue, RequestH2StreamHints,
+    RequestHttpVersionPref, RequestIpv6Zone, RequestNoProxy, RequestOriginalHeaders,
+    RequestProxyMatcher, RequestTcpConnectOptions, RequestTemplatedProxyAuth,
+    RequestTransportConfig,
 };
 pub(crate) use h1_reason_phrase::ReasonPhrase;
+pub use raw_head::RawResponseHead;
+
+/// Response extension reporting data a server sent beyond the `Content-Length` of an
+/// HTTP/1 response.
+///
+/// The body is truncated at the declared length, and the connection is closed rather than
+/// reused, so that the excess data is never mistaken for the next response. Only the data
+/// received along with the response head is counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExcessData(pub(crate) u64);
+
+impl ExcessData {
+    /// Returns the number of bytes received beyond the end of the body.
+    pub fn bytes(&self) -> u64 {
+        self.0
+    }
+}
 
 /// Represents the `:protocol` pseudo-header used by
 /// the [Extended CONNECT Protocol].
@@ -0,0 +1,91 @@
+use bytes::Bytes;
+use http::{HeaderMap, StatusCode};
+
+/// Response extension holding the head of a response as it was received, see
+/// [`RequestBuilder::capture_raw_response_head`](crate::RequestBuilder::capture_raw_response_head).
+///
+/// Unlike the [`HeaderMap`] of the response, the capture keeps every field line in the order it
+/// was received, with the casing of its name, and without merging the lines of a name.
+///
+/// # HTTP/1
+///
+/// The bytes of the status line and of the header block are kept verbatim, up to and including
+/// the empty line ending the head. Their size is bounded by the maximum size of the read buffer
+/// of the connection, past which the response fails to parse. The heads of informational (`1xx`)
+/// responses are not kept.
+///
+/// # HTTP/2
+///
+/// There are no bytes to keep, the fields are the decoded header list, with the lowercase names
+/// HTTP/2 requires. The values of a name are in the order they were received, but the fields of
+/// different names are grouped by name, in the order each name was first received.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawResponseHead {
+    status: StatusCode,
+    bytes: Option<Bytes>,
+    fields: Vec<(Bytes, Bytes)>,
+}
+
+impl RawResponseHead {
+    /// Captures the head of an HTTP/1 response from its bytes, and the ranges of its fields.
+    pub(crate) fn http1<I>(status: StatusCode, bytes: Bytes, fields: I) -> RawResponseHead
+    where
+        I: IntoIterator<Item = ((usize, usize), (usize, usize))>,
+    {
+        let fields = fields
+            .into_iter()
+            .map(|(name, value)| (bytes.slice(name.0..name.1), bytes.slice(value.0..value.1)))
+            .collect();
+        RawResponseHead {
+            status,
+            bytes: Some(bytes),
+            fields,
+        }
+    }
+
+    /// Captures the head of an HTTP/2 response from its decoded headers.
+    pub(crate) fn http2(status: StatusCode, headers: &HeaderMap) -> RawResponseHead {
+        let fields = headers
+            .iter()
+            .map(|(name, value)| {
+                (
+                    Bytes::copy_from_slice(name.as_str().as_bytes()),
+                    Bytes::copy_from_slice(value.as_bytes()),
+                )
+            })
+            .collect();
+        RawResponseHead {
+            status,
+            bytes: None,
+            fields,
+        }
+    }
+
+    /// Returns the status code of the response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Returns the bytes of the status line and of the header block of an HTTP/1 response, or
+    /// `None` for an HTTP/2 response.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        self.bytes.as_deref()
+    }
+
+    /// Returns the field lines of the response, in the order they were received.
+    ///
+    /// The value of a field is as it was received, without the whitespace around it.
+    pub fn fields(&self) -> impl Iterator<Item = (&[u8], &[u8])> {
+        self.fields
+            .iter()
+            .map(|(name, value)| (name.as_ref(), value.as_ref()))
+    }
+
+    /// Returns the values of the field lines named `name`, compared case-insensitively, in the
+    /// order they were received.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a [u8]> + 'a {
+        self.fields()
+            .filter(move |(n, _)| n.eq_ignore_ascii_case(name.as_bytes()))
+            .map(|(_, value)| value)
+    }
+}
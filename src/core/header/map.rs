@@ -1,5 +1,8 @@
 use bytes::Bytes;
-use http::{HeaderMap, HeaderName, header::IntoHeaderName};
+use http::{
+    HeaderMap, HeaderName,
+    header::{CONTENT_LENGTH, COOKIE, HOST, IntoHeaderName},
+};
 
 use super::name::OriginalHeaderName;
 
@@ -12,6 +15,11 @@ use super::name::OriginalHeaderName;
 ///
 /// This type allows you to associate each normalized `HeaderName` with its original string
 /// representation, enabling restoration or reference to the original header casing when needed.
+///
+/// As the order of the headers of a request, the values of a name are sent where the name is
+/// first listed, in the order they were appended to the request's `HeaderMap`. Each value is
+/// sent as its own line in HTTP/1 and its own field in HTTP/2, never joined with the others. A
+/// name listed several times gives its spellings to its values in turn.
 #[derive(Debug, Clone)]
 pub struct OriginalHeaders(HeaderMap<Bytes>);
 
@@ -74,6 +82,24 @@ impl OriginalHeaders {
 }
 
 impl OriginalHeaders {
+    /// Derives the order of the headers from the insertion order of `defaults`, see
+    /// [`ClientBuilder::headers_order_from_defaults`](crate::ClientBuilder::headers_order_from_defaults).
+    ///
+    /// `host` and `content-length` are placed first, and `cookie` last, wherever `defaults`
+    /// holds them.
+    pub(crate) fn from_defaults(defaults: &HeaderMap) -> OriginalHeaders {
+        let mut order = OriginalHeaders::with_capacity(defaults.keys_len() + 3);
+        order.append(HOST, Bytes::from_static(b"host"));
+        order.append(CONTENT_LENGTH, Bytes::from_static(b"content-length"));
+        for name in defaults.keys() {
+            if name != HOST && name != CONTENT_LENGTH && name != COOKIE {
+                order.append(name, Bytes::copy_from_slice(name.as_str().as_bytes()));
+            }
+        }
+        order.append(COOKIE, Bytes::from_static(b"cookie"));
+        order
+    }
+
     /// Appends a header name to the end of the collection.
     #[inline(always)]
     pub(crate) fn append<N>(&mut self, name: N, orig: Bytes)
@@ -93,6 +119,12 @@ impl OriginalHeaders {
         self.0.get_all(name).into_iter()
     }
 
+    /// Returns `true` if the collection contains the header name.
+    #[inline(always)]
+    pub(crate) fn contains_key(&self, name: &HeaderName) -> bool {
+        self.0.contains_key(name)
+    }
+
     /// Returns an iterator over all header names and their original spellings.
     #[inline(always)]
     pub(crate) fn keys(&self) -> impl Iterator<Item = &HeaderName> {
@@ -130,6 +162,33 @@ mod test {
         assert_eq!(iter.next().unwrap().1, "x-test2");
     }
 
+    #[test]
+    fn test_header_order_from_defaults() {
+        let mut defaults = http::HeaderMap::new();
+        defaults.insert("user-agent", "agent".parse().unwrap());
+        defaults.insert("cookie", "a=b".parse().unwrap());
+        defaults.insert("accept", "*/*".parse().unwrap());
+        defaults.append("accept", "text/html".parse().unwrap());
+        defaults.insert("host", "example.com".parse().unwrap());
+        defaults.insert("accept-language", "en".parse().unwrap());
+
+        // host and content-length first, cookie last, the others in insertion order
+        let order = OriginalHeaders::from_defaults(&defaults);
+        let names: Vec<_> = order.keys().map(|name| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "host",
+                "content-length",
+                "user-agent",
+                "accept",
+                "accept-language",
+                "cookie"
+            ]
+        );
+        assert_eq!(order.len(), names.len());
+    }
+
     #[test]
     fn test_header_case() {
         let mut headers = OriginalHeaders::new();
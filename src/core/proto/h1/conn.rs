@@ -18,6 +18,8 @@ use super::{
 };
 use crate::core::{
     body::DecodedLength,
+    ext::{ExcessData, RequestCaptureRawHead, RequestConfig},
+    header::OriginalHeaders,
     proto::{BodyLength, MessageHead, headers},
     rt::{Read, Write},
 };
@@ -55,6 +57,9 @@ where
                 h1_parser_config: ParserConfig::default(),
                 h1_max_headers: None,
                 preserve_header_case: false,
+                title_case_headers: false,
+                header_case: None,
+                capture_raw_head: false,
                 h09_responses: false,
                 notify_read: false,
                 reading: Reading::Init,
@@ -93,6 +98,14 @@ where
         self.state.preserve_header_case = true;
     }
 
+    pub(crate) fn set_title_case_headers(&mut self) {
+        self.state.title_case_headers = true;
+    }
+
+    pub(crate) fn set_header_case(&mut self, header_case: OriginalHeaders) {
+        self.state.header_case = Some(header_case);
+    }
+
     pub(crate) fn set_h09_responses(&mut self) {
         self.state.h09_responses = true;
     }
@@ -154,7 +167,7 @@ where
         debug_assert!(self.can_read_head());
         trace!("Conn::read_head");
 
-        let msg = match self.io.parse::<T>(
+        let mut msg = match self.io.parse::<T>(
             cx,
             ParseContext {
                 cached_headers: &mut self.state.cached_headers,
@@ -162,6 +175,7 @@ where
                 h1_parser_config: self.state.h1_parser_config.clone(),
                 h1_max_headers: self.state.h1_max_headers,
                 preserve_header_case: self.state.preserve_header_case,
+                capture_raw_head: self.state.capture_raw_head,
                 h09_responses: self.state.h09_responses,
             },
         ) {
@@ -184,6 +198,19 @@ where
         self.state.keep_alive &= msg.keep_alive;
         self.state.version = msg.head.version;
 
+        // Data buffered beyond the declared length of a response is excess, which must
+        // not be parsed as the response to the next request.
+        if !T::should_read_first() && !msg.wants_upgrade {
+            if let Some(len) = msg.decode.into_opt() {
+                let buffered = self.io.read_buf().len() as u64;
+                if buffered > len {
+                    debug!("response has {} bytes of excess data", buffered - len);
+                    msg.head.extensions.insert(ExcessData(buffered - len));
+                    self.state.disable_keep_alive();
+                }
+            }
+        }
+
         let mut wants = if msg.wants_upgrade {
             Wants::UPGRADE
         } else {
@@ -267,8 +294,19 @@ where
                             let slice = frame.data_ref().unwrap_or_else(|| unreachable!());
                             let (reading, maybe_frame) = if decoder.is_eof() {
                                 debug!("incoming body completed");
+                                // Excess data after a response closes the connection.
+                                let reading =
+                                    if T::should_read_first() || self.io.read_buf().is_empty() {
+                                        Reading::KeepAlive
+                                    } else {
+                                        debug!(
+                                            "received {} bytes after the response body",
+                                            self.io.read_buf().len()
+                                        );
+                                        Reading::Closed
+                                    };
                                 (
-                                    Reading::KeepAlive,
+                                    reading,
                                     if !slice.is_empty() {
                                         Some(Ok(frame))
                                     } else {
@@ -513,12 +551,18 @@ where
 
         self.enforce_version(&mut head);
 
+        self.state.capture_raw_head =
+            RequestConfig::<RequestCaptureRawHead>::get(&head.extensions).is_some_and(|v| *v);
+
         let buf = self.io.headers_buf();
         match super::role::encode_headers::<T>(
             Encode {
                 head: &mut head,
                 body,
                 req_method: &mut self.state.method,
+                preserve_header_case: self.state.preserve_header_case,
+                title_case_headers: self.state.title_case_headers,
+                header_case: self.state.header_case.as_ref(),
             },
             buf,
         ) {
@@ -798,6 +842,11 @@ struct State {
     h1_parser_config: ParserConfig,
     h1_max_headers: Option<usize>,
     preserve_header_case: bool,
+    title_case_headers: bool,
+    /// The exact spellings of the names of the request headers.
+    header_case: Option<OriginalHeaders>,
+    /// If the head of the response to the request being written is captured.
+    capture_raw_head: bool,
     h09_responses: bool,
     /// Set to true when the Dispatcher should poll read operations
     /// again. See the `maybe_notify` method for more.
@@ -1,5 +1,4 @@
 use std::{
-    error::Error as StdError,
     fmt, io,
     task::{Context, Poll, ready},
 };
@@ -10,6 +9,7 @@ use http_body::Frame;
 
 use self::Kind::{Chunked, Eof, Length};
 use super::{DecodedLength, io::MemRead, role::DEFAULT_MAX_HEADERS};
+use crate::error::IncompleteBody;
 
 /// Maximum amount of bytes allowed in chunked extensions.
 ///
@@ -28,6 +28,8 @@ const TRAILER_LIMIT: usize = 1024 * 16;
 #[derive(Clone, PartialEq)]
 pub(crate) struct Decoder {
     kind: Kind,
+    /// The number of body bytes decoded so far.
+    received: u64,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -86,6 +88,7 @@ impl Decoder {
     pub(crate) fn length(x: u64) -> Decoder {
         Decoder {
             kind: Kind::Length(x),
+            received: 0,
         }
     }
 
@@ -103,12 +106,14 @@ impl Decoder {
                 h1_max_headers,
                 h1_max_header_size,
             },
+            received: 0,
         }
     }
 
     pub(crate) fn eof() -> Decoder {
         Decoder {
             kind: Kind::Eof(false),
+            received: 0,
         }
     }
 
@@ -155,13 +160,15 @@ impl Decoder {
                     if num > *remaining {
                         *remaining = 0;
                     } else if num == 0 {
+                        let expected = self.received + *remaining;
                         return Poll::Ready(Err(io::Error::new(
                             io::ErrorKind::UnexpectedEof,
-                            IncompleteBody,
+                            IncompleteBody::new(self.received, Some(expected)),
                         )));
                     } else {
                         *remaining -= num;
                     }
+                    self.received += num;
                     Poll::Ready(Ok(Frame::data(buf)))
                 }
             }
@@ -179,7 +186,7 @@ impl Decoder {
                 loop {
                     let mut buf = None;
                     // advances the chunked state
-                    *state = ready!(state.step(
+                    *state = match ready!(state.step(
                         cx,
                         body,
                         chunk_len,
@@ -189,7 +196,16 @@ impl Decoder {
                         trailers_cnt,
                         h1_max_headers,
                         h1_max_header_size
-                    ))?;
+                    )) {
+                        Ok(state) => state,
+                        Err(e) if e.get_ref().is_some_and(|e| e.is::<IncompleteBody>()) => {
+                            return Poll::Ready(Err(io::Error::new(
+                                io::ErrorKind::UnexpectedEof,
+                                IncompleteBody::new(self.received, None),
+                            )));
+                        }
+                        Err(e) => return Poll::Ready(Err(e)),
+                    };
                     if *state == ChunkedState::End {
                         trace!("end of chunked");
 
@@ -219,6 +235,7 @@ impl Decoder {
                         return Poll::Ready(Ok(Frame::data(Bytes::new())));
                     }
                     if let Some(buf) = buf {
+                        self.received += buf.len() as u64;
                         return Poll::Ready(Ok(Frame::data(buf)));
                     }
                 }
@@ -232,6 +249,7 @@ impl Decoder {
                     // allocate buffers to big
                     body.read_mem(cx, 8192).map_ok(|slice| {
                         *is_eof = slice.is_empty();
+                        self.received += slice.len() as u64;
                         Frame::data(slice)
                     })
                 }
@@ -487,7 +505,7 @@ impl ChunkedState {
             *rem = 0;
             return Poll::Ready(Err(io::Error::new(
                 io::ErrorKind::UnexpectedEof,
-                IncompleteBody,
+                IncompleteBody::new(0, None),
             )));
         }
         *buf = Some(slice);
@@ -674,17 +692,6 @@ fn decode_trailers(buf: &mut BytesMut, count: usize) -> Result<HeaderMap, io::Er
     }
 }
 
-#[derive(Debug)]
-struct IncompleteBody;
-
-impl fmt::Display for IncompleteBody {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "end of file before message length reached")
-    }
-}
-
-impl StdError for IncompleteBody {}
-
 #[cfg(test)]
 mod tests {
     use std::{pin::Pin, time::Duration};
@@ -862,6 +869,8 @@ mod tests {
         );
         let e = decoder.decode_fut(&mut bytes).await.unwrap_err();
         assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+        let details = e.get_ref().unwrap().downcast_ref::<IncompleteBody>();
+        assert_eq!(details, Some(&IncompleteBody::new(7, Some(10))));
     }
 
     #[cfg(not(miri))]
@@ -884,6 +893,8 @@ mod tests {
         );
         let e = decoder.decode_fut(&mut bytes).await.unwrap_err();
         assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+        let details = e.get_ref().unwrap().downcast_ref::<IncompleteBody>();
+        assert_eq!(details, Some(&IncompleteBody::new(7, None)));
     }
 
     #[cfg(not(miri))]
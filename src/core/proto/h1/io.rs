@@ -169,6 +169,7 @@ where
                     h1_parser_config: parse_ctx.h1_parser_config.clone(),
                     h1_max_headers: parse_ctx.h1_max_headers,
                     preserve_header_case: parse_ctx.preserve_header_case,
+                    capture_raw_head: parse_ctx.capture_raw_head,
                     h09_responses: parse_ctx.h09_responses,
                 },
             )? {
@@ -650,6 +651,7 @@ mod tests {
                 h1_parser_config: Default::default(),
                 h1_max_headers: None,
                 preserve_header_case: false,
+                capture_raw_head: false,
                 h09_responses: false,
             };
             assert!(
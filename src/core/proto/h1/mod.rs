@@ -12,6 +12,7 @@ pub(crate) use self::{
 };
 use crate::core::{
     body::DecodedLength,
+    header::OriginalHeaders,
     proto::{BodyLength, MessageHead},
 };
 
@@ -71,6 +72,7 @@ pub(crate) struct ParseContext<'a> {
     h1_parser_config: ParserConfig,
     h1_max_headers: Option<usize>,
     preserve_header_case: bool,
+    capture_raw_head: bool,
     h09_responses: bool,
 }
 
@@ -79,6 +81,9 @@ pub(crate) struct Encode<'a, T> {
     head: &'a mut MessageHead<T>,
     body: Option<BodyLength>,
     req_method: &'a mut Option<Method>,
+    preserve_header_case: bool,
+    title_case_headers: bool,
+    header_case: Option<&'a OriginalHeaders>,
 }
 
 /// Extra flags that a request "wants", like expect-continue or upgrades.
@@ -13,7 +13,7 @@ use smallvec::{SmallVec, smallvec, smallvec_inline};
 use crate::core::{
     body::DecodedLength,
     error::Parse,
-    ext::{RequestConfig, RequestOriginalHeaders},
+    ext::{RawResponseHead, RequestConfig, RequestOriginalHeaders},
     header::OriginalHeaders,
     proto::{
         BodyLength, MessageHead, RequestHead, RequestLine,
@@ -177,6 +177,22 @@ impl Http1Transaction for Client {
 
             let mut slice = buf.split_to(len);
 
+            // copy the head before folded lines are rewritten in place
+            let raw_head = if ctx.capture_raw_head && version != Version::HTTP_09 {
+                let fields = headers_indices[..headers_len].iter().map(|header| {
+                    // SAFETY: array is valid up to `headers_len`
+                    let header = unsafe { header.assume_init_ref() };
+                    (header.name, header.value)
+                });
+                Some(RawResponseHead::http1(
+                    status,
+                    Bytes::copy_from_slice(&slice),
+                    fields,
+                ))
+            } else {
+                None
+            };
+
             if ctx
                 .h1_parser_config
                 .obsolete_multiline_headers_in_responses_are_allowed()
@@ -231,6 +247,10 @@ impl Http1Transaction for Client {
                 extensions.insert(header_case_map);
             }
 
+            if let Some(raw_head) = raw_head {
+                extensions.insert(raw_head);
+            }
+
             if let Some(reason) = reason {
                 // Safety: httparse ensures that only valid reason phrase bytes are present in this
                 // field.
@@ -293,12 +313,25 @@ impl Http1Transaction for Client {
         }
         extend(dst, b"\r\n");
 
-        if let Some(orig_headers) =
-            RequestConfig::<RequestOriginalHeaders>::get(&msg.head.extensions)
-        {
-            write_headers_original_case(&mut msg.head.headers, orig_headers, dst);
-        } else {
+        // the headers are ordered first, then cased
+        let orig_headers = RequestConfig::<RequestOriginalHeaders>::get(&msg.head.extensions);
+        if let Some(orig_headers) = orig_headers {
+            crate::core::proto::headers::sort_headers(&mut msg.head.headers, orig_headers);
+        }
+
+        if orig_headers.is_none() && !msg.title_case_headers && msg.header_case.is_none() {
             write_headers(&msg.head.headers, dst);
+        } else {
+            // title case replaces the spellings of the order, unless they are preserved
+            let orig_case =
+                orig_headers.filter(|_| msg.preserve_header_case || !msg.title_case_headers);
+            write_headers_cased(
+                &msg.head.headers,
+                orig_case,
+                msg.header_case,
+                msg.title_case_headers,
+                dst,
+            );
         }
 
         extend(dst, b"\r\n");
@@ -642,24 +675,32 @@ pub(crate) fn write_headers(headers: &HeaderMap, dst: &mut Vec<u8>) {
     }
 }
 
-fn write_headers_original_case(
-    headers: &mut HeaderMap,
-    orig_case: &OriginalHeaders,
+/// Writes the headers with the exact spellings of `header_case`, else the original spellings of
+/// `orig_case`, else in title case if `title_case` is set.
+fn write_headers_cased(
+    headers: &HeaderMap,
+    orig_case: Option<&OriginalHeaders>,
+    header_case: Option<&OriginalHeaders>,
+    title_case: bool,
     dst: &mut Vec<u8>,
 ) {
-    crate::core::proto::headers::sort_headers(headers, orig_case);
-
     // For each header name/value pair, there may be a value in the casemap
     // that corresponds to the HeaderValue. So, we iterator all the keys,
     // and for each one, try to pair the originally cased name with the value.
     //
     // TODO: consider adding http::HeaderMap::entries() iterator
     for name in headers.keys() {
-        let mut names = orig_case.get_all(name);
+        let mut names = orig_case.into_iter().flat_map(|orig| orig.get_all(name));
+        let exact_name = header_case.and_then(|case| case.get_all(name).next());
 
         for value in headers.get_all(name) {
-            if let Some(orig_name) = names.next() {
+            let orig_name = names.next();
+            if let Some(ref exact_name) = exact_name {
+                extend(dst, exact_name.as_ref());
+            } else if let Some(orig_name) = orig_name {
                 extend(dst, orig_name.as_ref());
+            } else if title_case {
+                title_case_name(dst, name.as_str().as_bytes());
             } else {
                 extend(dst, name.as_str().as_bytes());
             }
@@ -676,6 +717,22 @@ fn write_headers_original_case(
     }
 }
 
+/// Writes `name` with the first letter of each of its words uppercase.
+fn title_case_name(dst: &mut Vec<u8>, name: &[u8]) {
+    dst.reserve(name.len());
+
+    let mut prev = b'-';
+    for &c in name {
+        let c = if prev == b'-' {
+            c.to_ascii_uppercase()
+        } else {
+            c
+        };
+        dst.push(c);
+        prev = c;
+    }
+}
+
 struct FastWrite<'a>(&'a mut Vec<u8>);
 
 impl fmt::Write for FastWrite<'_> {
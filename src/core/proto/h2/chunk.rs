@@ -0,0 +1,109 @@
+//! Adaptive sizing of the pieces a request body is sent in.
+
+use std::time::{Duration, Instant};
+
+/// The size of the first pieces, before the drain rate of the stream is known.
+const INITIAL_SIZE: usize = 16 * 1024;
+
+/// How long the drain rate is sampled over before the size is adjusted.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How long a piece should take to drain at the sampled rate.
+///
+/// Pieces taking longer delay the frames of the other streams of the connection, while
+/// shorter ones mostly add frames and wake-ups.
+const PIECE_DURATION: Duration = Duration::from_millis(10);
+
+/// Sizes the pieces a request body is split into from the rate the stream drains them.
+#[derive(Debug)]
+pub(crate) struct ChunkSizer {
+    min: usize,
+    max: usize,
+    size: usize,
+    sample_start: Option<Instant>,
+    sample_bytes: usize,
+}
+
+impl ChunkSizer {
+    pub(crate) fn new((min, max): (usize, usize)) -> ChunkSizer {
+        ChunkSizer {
+            min,
+            max,
+            size: INITIAL_SIZE.clamp(min, max),
+            sample_start: None,
+            sample_bytes: 0,
+        }
+    }
+
+    /// Returns the size of the next piece.
+    pub(crate) fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Records that `len` bytes were handed to the stream.
+    pub(crate) fn on_sent(&mut self, len: usize) {
+        self.record(len, Instant::now());
+    }
+
+    fn record(&mut self, len: usize, now: Instant) {
+        let start = *self.sample_start.get_or_insert(now);
+        self.sample_bytes += len;
+
+        let elapsed = now.saturating_duration_since(start);
+        if elapsed < SAMPLE_INTERVAL {
+            return;
+        }
+
+        let rate = self.sample_bytes as f64 / elapsed.as_secs_f64();
+        let target = ((rate * PIECE_DURATION.as_secs_f64()) as usize).clamp(self.min, self.max);
+        // move halfway to the target, so that a single stalled sample does not collapse the size
+        self.size = if target > self.size {
+            self.size + (target - self.size).div_ceil(2)
+        } else {
+            self.size - (self.size - target).div_ceil(2)
+        };
+        trace!(
+            "sampled body drain rate: {:.0} B/s, piece size {}",
+            rate, self.size
+        );
+
+        self.sample_start = Some(now);
+        self.sample_bytes = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOUNDS: (usize, usize) = (1024, 1024 * 1024);
+
+    fn drain(sizer: &mut ChunkSizer, every: Duration, samples: usize) {
+        let mut now = Instant::now();
+        for _ in 0..samples {
+            sizer.record(sizer.size(), now);
+            now += every;
+        }
+    }
+
+    #[test]
+    fn starts_at_initial_size_within_bounds() {
+        assert_eq!(ChunkSizer::new(BOUNDS).size(), INITIAL_SIZE);
+        assert_eq!(ChunkSizer::new((32 * 1024, 64 * 1024)).size(), 32 * 1024);
+        assert_eq!(ChunkSizer::new((1, 4096)).size(), 4096);
+    }
+
+    #[test]
+    fn grows_on_fast_stream() {
+        let mut sizer = ChunkSizer::new(BOUNDS);
+        drain(&mut sizer, Duration::from_micros(10), 100_000);
+        assert_eq!(sizer.size(), BOUNDS.1);
+    }
+
+    #[test]
+    fn shrinks_on_slow_stream() {
+        let mut sizer = ChunkSizer::new(BOUNDS);
+        drain(&mut sizer, Duration::from_millis(50), 100);
+        assert_eq!(sizer.size(), BOUNDS.0);
+    }
+}
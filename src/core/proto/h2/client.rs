@@ -3,6 +3,7 @@ use std::{
     future::Future,
     marker::PhantomData,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll, ready},
     time::Duration,
 };
@@ -14,7 +15,7 @@ use futures_channel::{
     oneshot,
 };
 use futures_util::{
-    future::{Either, FusedFuture},
+    future::FusedFuture,
     stream::{FusedStream, Stream},
 };
 use http::{Method, Request, Response, StatusCode};
@@ -22,20 +23,26 @@ use http_body::Body;
 use http2::{
     SendStream,
     client::{Builder, Connection, ResponseFuture, SendRequest},
-    frame::{ExperimentalSettings, Priorities, PseudoOrder, SettingsOrder, StreamDependency},
+    frame::{
+        ExperimentalSettings, Priorities, PseudoOrder, Setting, SettingId, SettingsOrder,
+        StreamDependency,
+    },
 };
 use pin_project_lite::pin_project;
 
 use super::{
-    H2Upgraded, PipeToSendStream, SendBuf, ping,
+    H2Upgraded, PipeToSendStream, SendBuf, StreamHints, ping,
     ping::{Ponger, Recorder},
 };
 use crate::core::{
-    body::Incoming as IncomingBody,
+    body::{FlowControl, Incoming as IncomingBody},
     client::dispatch::{self, Callback, SendWhen, TrySendError},
     common::{io::Compat, time::Time},
     error::BoxError,
-    ext::{Protocol, RequestConfig, RequestOriginalHeaders},
+    ext::{
+        Protocol, RawResponseHead, RequestCaptureRawHead, RequestConfig, RequestH2StreamHints,
+        RequestOriginalHeaders,
+    },
     proto::{Dispatched, h2::UpgradedSendStream, headers},
     rt::{Read, Write, bounds::Http2ClientConnExec},
     upgrade::Upgraded,
@@ -67,6 +74,18 @@ const DEFAULT_MAX_SEND_BUF_SIZE: usize = 1024 * 1024; // 1mb
 // the `REFUSED_STREAM` error.
 const DEFAULT_INITIAL_MAX_SEND_STREAMS: usize = 100;
 
+// The order of the initial SETTINGS a GREASE setting is inserted into when no order is set.
+const DEFAULT_SETTINGS_ORDER: [SettingId; 8] = [
+    SettingId::HeaderTableSize,
+    SettingId::EnablePush,
+    SettingId::MaxConcurrentStreams,
+    SettingId::InitialWindowSize,
+    SettingId::MaxFrameSize,
+    SettingId::MaxHeaderListSize,
+    SettingId::EnableConnectProtocol,
+    SettingId::NoRfc7540Priorities,
+];
+
 #[derive(Clone, Debug)]
 pub(crate) struct Config {
     pub(crate) adaptive_window: bool,
@@ -91,7 +110,77 @@ pub(crate) struct Config {
     pub(crate) headers_stream_dependency: Option<StreamDependency>,
     pub(crate) experimental_settings: Option<ExperimentalSettings>,
     pub(crate) settings_order: Option<SettingsOrder>,
+    pub(crate) grease_setting: Option<GreaseSetting>,
     pub(crate) priorities: Option<Priorities>,
+    pub(crate) on_remote_settings: Option<OnRemoteSettings>,
+}
+
+/// A setting of a reserved identifier sent in the initial SETTINGS frame, so that servers keep
+/// ignoring the settings they do not know.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct GreaseSetting {
+    /// The identifier and value sent on every connection, instead of random ones.
+    pub(crate) pinned: Option<(u16, u32)>,
+    /// The index of the setting in the settings order, the end if `None`.
+    pub(crate) position: Option<usize>,
+}
+
+impl GreaseSetting {
+    /// Returns whether `id` is of the reserved form `0x?a?a`.
+    pub(crate) fn is_reserved(id: u16) -> bool {
+        id & 0x0f0f == 0x0a0a
+    }
+
+    /// Picks the identifier and value of the setting for a connection.
+    fn pick(&self) -> (u16, u32) {
+        self.pinned.unwrap_or_else(|| {
+            let random = crate::util::fast_random();
+            let id =
+                0x0a0a | (((random & 0xf) as u16) << 12) | ((((random >> 4) & 0xf) as u16) << 4);
+            (id, (random >> 32) as u32)
+        })
+    }
+}
+
+/// The HTTP/2 settings announced by the server that are in effect on a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemoteSettingsSnapshot {
+    max_concurrent_streams: Option<u32>,
+}
+
+impl RemoteSettingsSnapshot {
+    /// Get the maximum number of concurrent streams the client may open, or `None` if the
+    /// server set no limit.
+    pub fn max_concurrent_streams(&self) -> Option<u32> {
+        self.max_concurrent_streams
+    }
+}
+
+/// Callback invoked when the settings in effect on a connection change.
+#[derive(Clone)]
+pub(crate) struct OnRemoteSettings(pub(crate) Arc<dyn Fn(RemoteSettingsSnapshot) + Send + Sync>);
+
+impl std::fmt::Debug for OnRemoteSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("OnRemoteSettings(..)")
+    }
+}
+
+/// Reports changes of the settings in effect on a connection to an [`OnRemoteSettings`].
+struct SettingsObserver {
+    callback: OnRemoteSettings,
+    max_concurrent_streams: usize,
+}
+
+impl SettingsObserver {
+    fn observe(&mut self, max_concurrent_streams: usize) {
+        if self.max_concurrent_streams != max_concurrent_streams {
+            self.max_concurrent_streams = max_concurrent_streams;
+            (self.callback.0)(RemoteSettingsSnapshot {
+                max_concurrent_streams: u32::try_from(max_concurrent_streams).ok(),
+            });
+        }
+    }
 }
 
 impl Default for Config {
@@ -117,9 +206,11 @@ impl Default for Config {
             no_rfc7540_priorities: None,
             experimental_settings: None,
             settings_order: None,
+            grease_setting: None,
             headers_pseudo_order: None,
             headers_stream_dependency: None,
             priorities: None,
+            on_remote_settings: None,
         }
     }
 }
@@ -161,11 +252,33 @@ fn new_builder(config: &Config) -> Builder {
     if let Some(v) = config.no_rfc7540_priorities {
         builder.no_rfc7540_priorities(v);
     }
-    if let Some(ref order) = config.settings_order {
-        builder.settings_order(order.clone());
-    }
-    if let Some(ref experimental_settings) = config.experimental_settings {
-        builder.experimental_settings(experimental_settings.clone());
+    if let Some(ref grease) = config.grease_setting {
+        // picked for each connection, as the builder is
+        let (id, value) = grease.pick();
+        let mut order: Vec<_> = match config.settings_order {
+            Some(ref order) => order.clone().into_iter().collect(),
+            None => DEFAULT_SETTINGS_ORDER.to_vec(),
+        };
+        let position = grease
+            .position
+            .map_or(order.len(), |pos| pos.min(order.len()));
+        order.insert(position, SettingId::Unknown(id));
+        builder.settings_order(SettingsOrder::builder().extend(order).build());
+
+        let settings = config
+            .experimental_settings
+            .clone()
+            .into_iter()
+            .flatten()
+            .chain([Setting::from_id(SettingId::Unknown(id), value)]);
+        builder.experimental_settings(ExperimentalSettings::builder().extend(settings).build());
+    } else {
+        if let Some(ref order) = config.settings_order {
+            builder.settings_order(order.clone());
+        }
+        if let Some(ref experimental_settings) = config.experimental_settings {
+            builder.experimental_settings(experimental_settings.clone());
+        }
     }
     if let Some(stream_dependency) = config.headers_stream_dependency {
         builder.headers_stream_dependency(stream_dependency);
@@ -196,6 +309,7 @@ pub(crate) async fn handshake<T, B, E>(
     io: T,
     req_rx: ClientRx<B>,
     config: &Config,
+    chunk_bounds: Option<(usize, usize)>,
     mut exec: E,
     timer: Time,
 ) -> crate::core::Result<ClientTask<B, E, T>>
@@ -220,18 +334,22 @@ where
 
     let ping_config = new_ping_config(config);
 
-    let (conn, ping) = if ping_config.is_enabled() {
-        let pp = conn.ping_pong().expect("conn.ping_pong");
-        let (recorder, ponger) = ping::channel(pp, ping_config, timer);
-
-        let conn: Conn<_, B> = Conn::new(ponger, conn);
-        (Either::Left(conn), recorder)
-    } else {
-        (Either::Right(conn), ping::disabled())
-    };
+    let pp = conn.ping_pong().expect("conn.ping_pong");
+    let (ping, ponger, pinger) = ping::channel(pp, ping_config, timer);
+    let conn: Conn<_, B> = Conn::new(ponger, conn);
+    // Streams beyond a lowered limit are held pending open by `h2_tx`, so only
+    // the callback needs to learn about changed settings.
+    let observer = config
+        .on_remote_settings
+        .clone()
+        .map(|callback| SettingsObserver {
+            callback,
+            max_concurrent_streams: config.initial_max_send_streams,
+        });
     let conn: ConnMapErr<T, B> = ConnMapErr {
         conn,
         is_terminated: false,
+        observer,
     };
 
     exec.execute_h2_future(H2ClientFuture::Task {
@@ -240,6 +358,8 @@ where
 
     Ok(ClientTask {
         ping,
+        pinger,
+        chunk_bounds,
         conn_drop_ref,
         conn_eof,
         executor: exec,
@@ -306,9 +426,10 @@ pin_project! {
         T: Unpin,
     {
         #[pin]
-        conn: Either<Conn<T, B>, Connection<Compat<T>, SendBuf<<B as Body>::Data>>>,
+        conn: Conn<T, B>,
         #[pin]
         is_terminated: bool,
+        observer: Option<SettingsObserver>,
     }
 }
 
@@ -325,10 +446,15 @@ where
         if *this.is_terminated {
             return Poll::Pending;
         }
-        let polled = this.conn.poll(cx);
+        let polled = this.conn.as_mut().poll(cx);
         if polled.is_ready() {
             *this.is_terminated = true;
         }
+
+        // Received SETTINGS frames are applied while polling the connection
+        if let Some(observer) = this.observer {
+            observer.observe(this.conn.conn.max_concurrent_send_streams());
+        }
         polled.map_err(|_e| {
             debug!(error = %_e, "connection error");
         })
@@ -458,6 +584,8 @@ where
 {
     is_connect: bool,
     eos: bool,
+    hints: StreamHints,
+    capture_raw_head: bool,
     fut: ResponseFuture,
     body_tx: SendStream<SendBuf<B::Data>>,
     body: B,
@@ -472,6 +600,8 @@ where
     E: Unpin,
 {
     ping: ping::Recorder,
+    pinger: ping::Pinger,
+    chunk_bounds: Option<(usize, usize)>,
     conn_drop_ref: ConnDropRef,
     conn_eof: ConnEof,
     executor: E,
@@ -520,6 +650,17 @@ where
     }
 }
 
+impl<B, E, T> ClientTask<B, E, T>
+where
+    B: Body,
+    E: Unpin,
+{
+    /// Returns a handle sending pings on the connection on demand.
+    pub(crate) fn pinger(&self) -> ping::Pinger {
+        self.pinger.clone()
+    }
+}
+
 impl<B, E, T> ClientTask<B, E, T>
 where
     B: Body + 'static + Unpin,
@@ -533,7 +674,8 @@ where
 
         let send_stream = if !f.is_connect {
             if !f.eos {
-                let mut pipe = PipeToSendStream::new(f.body, f.body_tx);
+                let mut pipe =
+                    PipeToSendStream::new(f.body, f.body_tx, f.hints.chunk_hint, self.chunk_bounds);
 
                 // eagerly see if the body pipe is ready and
                 // can thus skip allocating in the executor
@@ -569,6 +711,8 @@ where
                     fut: f.fut,
                     ping: Some(ping),
                     send_stream: Some(send_stream),
+                    stream_window: f.hints.window,
+                    capture_raw_head: f.capture_raw_head,
                 },
                 call_back: Some(f.cb),
             },
@@ -588,6 +732,8 @@ pin_project! {
         ping: Option<Recorder>,
         #[pin]
         send_stream: Option<Option<SendStream<SendBuf<<B as Body>::Data>>>>,
+        stream_window: Option<u32>,
+        capture_raw_head: bool,
     }
 }
 
@@ -607,10 +753,15 @@ where
         let send_stream = this.send_stream.take().expect("Future polled twice");
 
         match result {
-            Ok(res) => {
+            Ok(mut res) => {
                 // record that we got the response headers
                 ping.record_non_data();
 
+                if *this.capture_raw_head {
+                    let raw_head = RawResponseHead::http2(res.status(), res.headers());
+                    res.extensions_mut().insert(raw_head);
+                }
+
                 let content_length = headers::content_length_parse_all(res.headers());
                 if let (Some(mut send_stream), StatusCode::OK) = (send_stream, res.status()) {
                     if content_length.is_some_and(|len| len != 0) {
@@ -639,10 +790,15 @@ where
 
                     Poll::Ready(Ok(res))
                 } else {
-                    let res = res.map(|stream| {
+                    let flow = Arc::new(FlowControl::default());
+                    if let Some(window) = *this.stream_window {
+                        flow.set_target_buffer(window as usize);
+                    }
+                    let mut res = res.map(|stream| {
                         let ping = ping.for_stream(&stream);
-                        IncomingBody::h2(stream, content_length.into(), ping)
+                        IncomingBody::h2(stream, content_length.into(), ping, flow.clone())
                     });
+                    res.extensions_mut().insert(flow);
                     Poll::Ready(Ok(res))
                 }
             }
@@ -713,6 +869,12 @@ where
 
                     let is_connect = req.method() == Method::CONNECT;
                     let eos = body.is_end_stream();
+                    let hints = RequestConfig::<RequestH2StreamHints>::get(req.extensions())
+                        .copied()
+                        .unwrap_or_default();
+                    let capture_raw_head =
+                        RequestConfig::<RequestCaptureRawHead>::get(req.extensions())
+                            .is_some_and(|v| *v);
 
                     if is_connect
                         && headers::content_length_parse_all(req.headers())
@@ -745,6 +907,8 @@ where
                     let f = FutCtx {
                         is_connect,
                         eos,
+                        hints,
+                        capture_raw_head,
                         fut,
                         body_tx,
                         body,
@@ -1,3 +1,4 @@
+mod chunk;
 pub(crate) mod client;
 pub(crate) mod ping;
 
@@ -18,6 +19,7 @@ use http_body::Body;
 use http2::{Reason, RecvStream, SendStream};
 use pin_project_lite::pin_project;
 
+use self::chunk::ChunkSizer;
 pub(crate) use self::client::ClientTask;
 use crate::core::{
     error::BoxError,
@@ -28,6 +30,15 @@ use crate::core::{
 /// Default initial stream window size defined in HTTP2 spec.
 pub(crate) const SPEC_WINDOW_SIZE: u32 = 65_535;
 
+/// Per-stream hints set on a request, ignored by HTTP/1.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct StreamHints {
+    /// Caps the receive window reopened for the response body of the stream.
+    pub(crate) window: Option<u32>,
+    /// Caps the size of the DATA frames the request body is sent in.
+    pub(crate) chunk_hint: Option<usize>,
+}
+
 // List of connection headers from RFC 9110 Section 7.6.1
 //
 // TE headers are allowed in HTTP/2 requests as long as the value is "trailers", so they're
@@ -87,6 +98,9 @@ pin_project! {
     {
         body_tx: SendStream<SendBuf<S::Data>>,
         data_done: bool,
+        chunking: Chunking,
+        // rest of a chunk larger than the piece size, and whether it ends the stream
+        pending: Option<(S::Data, bool)>,
         #[pin]
         stream: S,
     }
@@ -96,10 +110,22 @@ impl<S> PipeToSendStream<S>
 where
     S: Body,
 {
-    fn new(stream: S, tx: SendStream<SendBuf<S::Data>>) -> PipeToSendStream<S> {
+    fn new(
+        stream: S,
+        tx: SendStream<SendBuf<S::Data>>,
+        chunk_hint: Option<usize>,
+        chunk_bounds: Option<(usize, usize)>,
+    ) -> PipeToSendStream<S> {
+        let chunking = match (chunk_hint.filter(|&hint| hint > 0), chunk_bounds) {
+            (Some(hint), _) => Chunking::Fixed(hint),
+            (None, Some(bounds)) => Chunking::Adaptive(ChunkSizer::new(bounds)),
+            (None, None) => Chunking::Whole,
+        };
         PipeToSendStream {
             body_tx: tx,
             data_done: false,
+            chunking,
+            pending: None,
             stream,
         }
     }
@@ -149,17 +175,47 @@ where
                 )));
             }
 
+            if let (Some((mut rest, eos)), Some(limit)) = (me.pending.take(), me.chunking.limit()) {
+                // send the next piece of the chunk once there is capacity for it, slicing
+                // rather than copying the chunk when it is `Bytes`
+                let piece = rest.copy_to_bytes(rest.remaining().min(limit));
+                let is_eos = eos && !rest.has_remaining();
+                trace!("send body piece: {} bytes, eos={}", piece.len(), is_eos);
+
+                me.chunking.on_sent(piece.len());
+                me.body_tx
+                    .send_data(SendBuf::Bytes(piece), is_eos)
+                    .map_err(crate::core::Error::new_body_write)?;
+
+                if rest.has_remaining() {
+                    *me.pending = Some((rest, eos));
+                } else if is_eos {
+                    return Poll::Ready(Ok(()));
+                }
+                continue;
+            }
+
             match ready!(me.stream.as_mut().poll_frame(cx)) {
                 Some(Ok(frame)) => {
                     if frame.is_data() {
                         let chunk = frame.into_data().unwrap_or_else(|_| unreachable!());
                         let is_eos = me.stream.is_end_stream();
+                        if me
+                            .chunking
+                            .limit()
+                            .is_some_and(|limit| chunk.remaining() > limit)
+                        {
+                            *me.pending = Some((chunk, is_eos));
+                            continue;
+                        }
                         trace!(
                             "send body chunk: {} bytes, eos={}",
                             chunk.remaining(),
                             is_eos,
                         );
 
+                        me.chunking.on_sent(chunk.remaining());
+
                         let buf = SendBuf::Buf(chunk);
                         me.body_tx
                             .send_data(buf, is_eos)
@@ -192,6 +248,32 @@ where
     }
 }
 
+/// How the chunks of a request body are split before they are sent.
+enum Chunking {
+    /// Chunks are sent as the body yields them.
+    Whole,
+    /// Chunks are split into pieces of at most the chunk hint of the request.
+    Fixed(usize),
+    /// Chunks are split into pieces sized from the drain rate of the stream.
+    Adaptive(ChunkSizer),
+}
+
+impl Chunking {
+    fn limit(&self) -> Option<usize> {
+        match self {
+            Chunking::Whole => None,
+            Chunking::Fixed(hint) => Some(*hint),
+            Chunking::Adaptive(sizer) => Some(sizer.size()),
+        }
+    }
+
+    fn on_sent(&mut self, len: usize) {
+        if let Chunking::Adaptive(sizer) = self {
+            sizer.on_sent(len);
+        }
+    }
+}
+
 trait SendStreamExt {
     fn on_user_err<E>(&mut self, err: E) -> crate::core::Error
     where
@@ -220,6 +302,7 @@ impl<B: Buf> SendStreamExt for SendStream<SendBuf<B>> {
 #[repr(usize)]
 enum SendBuf<B> {
     Buf(B),
+    Bytes(Bytes),
     Cursor(Cursor<Box<[u8]>>),
     None,
 }
@@ -229,6 +312,7 @@ impl<B: Buf> Buf for SendBuf<B> {
     fn remaining(&self) -> usize {
         match *self {
             Self::Buf(ref b) => b.remaining(),
+            Self::Bytes(ref b) => b.remaining(),
             Self::Cursor(ref c) => Buf::remaining(c),
             Self::None => 0,
         }
@@ -238,6 +322,7 @@ impl<B: Buf> Buf for SendBuf<B> {
     fn chunk(&self) -> &[u8] {
         match *self {
             Self::Buf(ref b) => b.chunk(),
+            Self::Bytes(ref b) => b.chunk(),
             Self::Cursor(ref c) => c.chunk(),
             Self::None => &[],
         }
@@ -247,6 +332,7 @@ impl<B: Buf> Buf for SendBuf<B> {
     fn advance(&mut self, cnt: usize) {
         match *self {
             Self::Buf(ref mut b) => b.advance(cnt),
+            Self::Bytes(ref mut b) => b.advance(cnt),
             Self::Cursor(ref mut c) => c.advance(cnt),
             Self::None => {}
         }
@@ -255,6 +341,7 @@ impl<B: Buf> Buf for SendBuf<B> {
     fn chunks_vectored<'a>(&'a self, dst: &mut [IoSlice<'a>]) -> usize {
         match *self {
             Self::Buf(ref b) => b.chunks_vectored(dst),
+            Self::Bytes(ref b) => b.chunks_vectored(dst),
             Self::Cursor(ref c) => c.chunks_vectored(dst),
             Self::None => 0,
         }
@@ -1,11 +1,12 @@
 //! HTTP2 Ping usage
 //!
-//! hyper uses HTTP2 pings for two purposes:
+//! hyper uses HTTP2 pings for three purposes:
 //!
 //! 1. Adaptive flow control using BDP
 //! 2. Connection keep-alive
+//! 3. Pings sent on demand, to check a pooled connection
 //!
-//! Both cases are optional.
+//! The first two cases are optional.
 //!
 //! # BDP Algorithm
 //!
@@ -20,12 +21,13 @@ use std::{
     fmt,
     future::Future,
     pin::Pin,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, Weak},
     task::{self, Poll},
     time::{Duration, Instant},
 };
 
 use http2::{Ping, PingPong};
+use tokio::sync::oneshot;
 
 use crate::core::{common::time::Time, rt::Sleep};
 
@@ -35,12 +37,15 @@ pub(super) fn disabled() -> Recorder {
     Recorder { shared: None }
 }
 
-pub(super) fn channel(ping_pong: PingPong, config: Config, __timer: Time) -> (Recorder, Ponger) {
-    debug_assert!(
-        config.is_enabled(),
-        "ping channel requires bdp or keep-alive config",
-    );
-
+/// Creates the ping channel of a connection.
+///
+/// The `Recorder` is disabled unless bdp or keep-alive is configured, the `Pinger` can always
+/// send pings on demand.
+pub(super) fn channel(
+    ping_pong: PingPong,
+    config: Config,
+    __timer: Time,
+) -> (Recorder, Ponger, Pinger) {
     let bdp = config.bdp_initial_window.map(|wnd| Bdp {
         bdp: wnd,
         max_bandwidth: 0.0,
@@ -73,17 +78,28 @@ pub(super) fn channel(ping_pong: PingPong, config: Config, __timer: Time) -> (Re
         ping_pong,
         ping_sent_at: None,
         next_bdp_at,
+        pong_waiters: Vec::new(),
     }));
 
-    (
+    let recorder = if config.is_enabled() {
         Recorder {
             shared: Some(shared.clone()),
-        },
+        }
+    } else {
+        disabled()
+    };
+    let pinger = Pinger {
+        shared: Arc::downgrade(&shared),
+    };
+
+    (
+        recorder,
         Ponger {
             bdp,
             keep_alive,
             shared,
         },
+        pinger,
     )
 }
 
@@ -110,6 +126,12 @@ pub(super) struct Ponger {
     shared: Arc<Mutex<Shared>>,
 }
 
+/// Sends pings on demand. It does not keep the connection alive, nor count as an open stream.
+#[derive(Clone)]
+pub(crate) struct Pinger {
+    shared: Weak<Mutex<Shared>>,
+}
+
 struct Shared {
     ping_pong: PingPong,
     ping_sent_at: Option<Instant>,
@@ -128,6 +150,10 @@ struct Shared {
     last_read_at: Option<Instant>,
 
     is_keep_alive_timed_out: bool,
+
+    // on demand
+    /// Notified with the round trip time of the next pong.
+    pong_waiters: Vec<oneshot::Sender<Duration>>,
 }
 
 struct Bdp {
@@ -279,6 +305,10 @@ impl Ponger {
                 let rtt = now - start;
                 trace!("recv pong");
 
+                for tx in locked.pong_waiters.drain(..) {
+                    let _ = tx.send(rtt);
+                }
+
                 if let Some(ref mut ka) = self.keep_alive {
                     locked.update_last_read_at();
                     ka.maybe_schedule(is_idle, &locked);
@@ -299,12 +329,14 @@ impl Ponger {
             }
             Poll::Ready(Err(_e)) => {
                 debug!("pong error: {}", _e);
+                locked.pong_waiters.clear();
             }
             Poll::Pending => {
                 if let Some(ref mut ka) = self.keep_alive {
                     if let Err(KeepAliveTimedOut) = ka.maybe_timeout(cx) {
                         self.keep_alive = None;
                         locked.is_keep_alive_timed_out = true;
+                        locked.pong_waiters.clear();
                         return Poll::Ready(Ponged::KeepAliveTimedOut);
                     }
                 }
@@ -320,6 +352,30 @@ impl Ponger {
     }
 }
 
+// ===== impl Pinger =====
+
+impl Pinger {
+    /// Sends a ping, unless one is already in flight, and returns a receiver of the round trip
+    /// time of the next pong.
+    ///
+    /// Returns `None` if the connection is gone. The receiver fails if the connection closes
+    /// before the pong.
+    pub(crate) fn ping(&self) -> Option<oneshot::Receiver<Duration>> {
+        let shared = self.shared.upgrade()?;
+        let mut locked = shared.lock().unwrap();
+        if !locked.is_ping_sent() {
+            locked.send_ping();
+            if !locked.is_ping_sent() {
+                return None;
+            }
+        }
+
+        let (tx, rx) = oneshot::channel();
+        locked.pong_waiters.push(tx);
+        Some(rx)
+    }
+}
+
 // ===== impl Shared =====
 
 impl Shared {
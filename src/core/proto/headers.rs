@@ -142,7 +142,8 @@ pub(super) fn add_chunked(mut entry: http::header::OccupiedEntry<'_, HeaderValue
 /// Sorts the headers in the specified order.
 ///
 /// Headers in `headers_order` are sorted to the front, preserving their order.
-/// Remaining headers are appended in their original order.
+/// Remaining headers are appended in their original order. The values of a name keep their
+/// order, and stay separate.
 #[inline]
 pub(super) fn sort_headers(headers: &mut HeaderMap, orig: &OriginalHeaders) {
     if headers.len() <= 1 {
@@ -157,13 +158,13 @@ pub(super) fn sort_headers(headers: &mut HeaderMap, orig: &OriginalHeaders) {
         for value in headers.get_all(name) {
             sorted_headers.append(name.clone(), value.clone());
         }
-        headers.remove(name);
     }
 
-    // Then insert any remaining headers that were not ordered
-    for (key, value) in headers.drain() {
-        if let Some(key) = key {
-            sorted_headers.append(key, value);
+    // Then insert any remaining headers that were not ordered. Removing the ordered ones from
+    // `headers` instead would swap the last remaining headers into their slots.
+    for (name, value) in headers.iter() {
+        if !orig.contains_key(name) {
+            sorted_headers.append(name.clone(), value.clone());
         }
     }
 
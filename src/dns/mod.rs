@@ -2,8 +2,12 @@
 
 #[cfg(feature = "hickory-dns")]
 pub use hickory::{HickoryDnsResolver, LookupIpStrategy};
+#[doc(hidden)]
+pub use resolve::overrides_resolver;
 pub use resolve::{Addrs, Name, Resolve, Resolving};
-pub(crate) use resolve::{DnsResolverWithOverrides, DynResolver};
+pub(crate) use resolve::{
+    DnsOverrides, DnsResolverWithOverrides, DynResolver, ResolutionMemo, normalize_override_name,
+};
 
 pub(crate) mod gai;
 #[cfg(feature = "hickory-dns")]
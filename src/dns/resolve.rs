@@ -6,11 +6,12 @@ use std::{
     str::FromStr,
     sync::Arc,
     task::{Context, Poll},
+    time::{Duration, Instant},
 };
 
 use tower_service::Service;
 
-use crate::{core::client::connect::dns::Name as HyperName, error::BoxError};
+use crate::{core::client::connect::dns::Name as HyperName, error::BoxError, sync::Mutex};
 
 /// Alias for an `Iterator` trait object over `SocketAddr`.
 pub type Addrs = Box<dyn Iterator<Item = SocketAddr> + Send>;
@@ -59,11 +60,20 @@ impl FromStr for Name {
 #[derive(Clone)]
 pub(crate) struct DynResolver {
     resolver: Arc<dyn Resolve>,
+    memo: Option<ResolutionMemo>,
 }
 
 impl DynResolver {
     pub(crate) fn new(resolver: Arc<dyn Resolve>) -> Self {
-        Self { resolver }
+        Self {
+            resolver,
+            memo: None,
+        }
+    }
+
+    /// Consults and populates `memo` before invoking the resolver.
+    pub(crate) fn set_memo(&mut self, memo: ResolutionMemo) {
+        self.memo = Some(memo);
     }
 }
 
@@ -77,32 +87,123 @@ impl Service<HyperName> for DynResolver {
     }
 
     fn call(&mut self, name: HyperName) -> Self::Future {
-        self.resolver.resolve(Name(name))
+        let Some(memo) = self.memo.clone() else {
+            return self.resolver.resolve(Name(name));
+        };
+
+        if let Some(addrs) = memo.get(name.as_str()) {
+            let addrs: Addrs = Box::new(SharedAddrs { addrs, pos: 0 });
+            return Box::pin(std::future::ready(Ok(addrs)));
+        }
+
+        let host = normalize_override_name(name.as_str());
+        let resolving = self.resolver.resolve(Name(name));
+        Box::pin(async move {
+            let addrs: Arc<[SocketAddr]> = resolving.await?.collect();
+            memo.insert(host, addrs.clone());
+            Ok(Box::new(SharedAddrs { addrs, pos: 0 }) as Addrs)
+        })
     }
 }
 
+/// How long a memoized resolution may be reused within a request.
+const MEMO_VALIDITY: Duration = Duration::from_secs(10);
+
+/// The resolutions made for a single logical request, including its retries and
+/// redirect hops, so that connecting to the same host again does not resolve it again.
+///
+/// The memo is stored in the request extensions, and dropped with the request.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ResolutionMemo {
+    entries: Arc<Mutex<HashMap<Box<str>, (Instant, Arc<[SocketAddr]>)>>>,
+}
+
+impl ResolutionMemo {
+    /// Returns the addresses `name` resolved to, unless they are no longer valid.
+    fn get(&self, name: &str) -> Option<Arc<[SocketAddr]>> {
+        let entries = self.entries.lock();
+        let (resolved_at, addrs) = entries.get(&*normalize_override_name(name))?;
+        (resolved_at.elapsed() < MEMO_VALIDITY).then(|| addrs.clone())
+    }
+
+    fn insert(&self, name: Box<str>, addrs: Arc<[SocketAddr]>) {
+        self.entries.lock().insert(name, (Instant::now(), addrs));
+    }
+}
+
+/// DNS overrides, keyed by normalized domain name.
+pub(crate) type DnsOverrides = HashMap<Box<str>, Arc<[SocketAddr]>>;
+
+/// The longest domain name, in its textual form with a trailing dot.
+const MAX_NAME_LEN: usize = 254;
+
+/// Normalizes a domain name for override lookups: lowercase, without a trailing dot.
+pub(crate) fn normalize_override_name(domain: &str) -> Box<str> {
+    let domain = domain.strip_suffix('.').unwrap_or(domain);
+    domain.to_ascii_lowercase().into_boxed_str()
+}
+
 pub(crate) struct DnsResolverWithOverrides {
     dns_resolver: Arc<dyn Resolve>,
-    overrides: Arc<HashMap<String, Vec<SocketAddr>>>,
+    overrides: Arc<DnsOverrides>,
 }
 
 impl DnsResolverWithOverrides {
-    pub(crate) fn new(
-        dns_resolver: Arc<dyn Resolve>,
-        overrides: HashMap<String, Vec<SocketAddr>>,
-    ) -> Self {
+    pub(crate) fn new(dns_resolver: Arc<dyn Resolve>, overrides: Arc<DnsOverrides>) -> Self {
         DnsResolverWithOverrides {
             dns_resolver,
-            overrides: Arc::new(overrides),
+            overrides,
+        }
+    }
+
+    /// Looks up the override of a name, without allocating.
+    fn lookup(&self, name: &str) -> Option<&Arc<[SocketAddr]>> {
+        let name = name.strip_suffix('.').unwrap_or(name);
+        if !name.bytes().any(|b| b.is_ascii_uppercase()) {
+            return self.overrides.get(name);
+        }
+
+        // Longer names are not valid, and can't have been inserted by any domain.
+        if name.len() > MAX_NAME_LEN {
+            return None;
         }
+
+        let mut buf = [0; MAX_NAME_LEN];
+        let buf = &mut buf[..name.len()];
+        buf.copy_from_slice(name.as_bytes());
+        buf.make_ascii_lowercase();
+
+        // Lowercasing ASCII bytes keeps the string valid UTF-8.
+        std::str::from_utf8(buf)
+            .ok()
+            .and_then(|name| self.overrides.get(name))
     }
 }
 
+/// Builds the resolver a client uses for its DNS overrides, for the `dns_overrides` benchmark.
+#[doc(hidden)]
+pub fn overrides_resolver<'a>(
+    dns_resolver: Arc<dyn Resolve>,
+    overrides: impl IntoIterator<Item = (&'a str, &'a [SocketAddr])>,
+) -> Arc<dyn Resolve> {
+    let overrides = overrides
+        .into_iter()
+        .map(|(domain, addrs)| (normalize_override_name(domain), Arc::from(addrs)))
+        .collect();
+    Arc::new(DnsResolverWithOverrides::new(
+        dns_resolver,
+        Arc::new(overrides),
+    ))
+}
+
 impl Resolve for DnsResolverWithOverrides {
     fn resolve(&self, name: Name) -> Resolving {
-        match self.overrides.get(name.as_str()) {
+        match self.lookup(name.as_str()) {
             Some(dest) => {
-                let addrs: Addrs = Box::new(dest.clone().into_iter());
+                let addrs: Addrs = Box::new(SharedAddrs {
+                    addrs: dest.clone(),
+                    pos: 0,
+                });
                 Box::pin(std::future::ready(Ok(addrs)))
             }
             None => self.dns_resolver.resolve(name),
@@ -110,6 +211,28 @@ impl Resolve for DnsResolverWithOverrides {
     }
 }
 
+/// Iterator over overridden or memoized addresses, sharing them with the map they are
+/// stored in.
+struct SharedAddrs {
+    addrs: Arc<[SocketAddr]>,
+    pos: usize,
+}
+
+impl Iterator for SharedAddrs {
+    type Item = SocketAddr;
+
+    fn next(&mut self) -> Option<SocketAddr> {
+        let addr = self.addrs.get(self.pos).copied()?;
+        self.pos += 1;
+        Some(addr)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.addrs.len() - self.pos;
+        (len, Some(len))
+    }
+}
+
 mod sealed {
     use std::fmt;
 
@@ -126,3 +249,38 @@ mod sealed {
 
     impl std::error::Error for InvalidNameError {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoResolve;
+
+    impl Resolve for NoResolve {
+        fn resolve(&self, name: Name) -> Resolving {
+            let name = name.as_str().to_owned();
+            Box::pin(std::future::ready(Err(name.into())))
+        }
+    }
+
+    async fn resolve(resolver: &DnsResolverWithOverrides, name: &str) -> Option<Vec<SocketAddr>> {
+        let name = Name::from_str(name).unwrap();
+        resolver.resolve(name).await.ok().map(Iterator::collect)
+    }
+
+    #[tokio::test]
+    async fn overrides_ignore_case_and_trailing_dot() {
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let mut overrides = DnsOverrides::new();
+        overrides.insert(normalize_override_name("Example.COM."), Arc::from([addr]));
+        let resolver = DnsResolverWithOverrides::new(Arc::new(NoResolve), Arc::new(overrides));
+
+        for name in ["example.com", "EXAMPLE.com", "example.com.", "eXaMpLe.CoM."] {
+            assert_eq!(resolve(&resolver, name).await, Some(vec![addr]), "{name}");
+        }
+        for name in ["example.org", "www.example.com", "example.com..", ""] {
+            assert_eq!(resolve(&resolver, name).await, None, "{name}");
+        }
+        assert_eq!(resolve(&resolver, &"A".repeat(300)).await, None);
+    }
+}
@@ -1,6 +1,13 @@
 use std::{error::Error as StdError, fmt, io};
 
-use crate::{StatusCode, Url, core::ext::ReasonPhrase, util::Escape};
+use http::{HeaderName, Uri};
+
+use crate::{
+    DigestMismatch, EmulationConflicts, StatusCode, Url,
+    core::{client::connect::proxy::TunnelError, ext::ReasonPhrase},
+    mirrors::MirrorsExhausted,
+    util::Escape,
+};
 
 /// A `Result` alias where the `Err` case is `wreq::Error`.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -45,6 +52,7 @@ impl Error {
         Error::new(Kind::Body, Some(e))
     }
 
+    #[cfg(feature = "tls")]
     pub(crate) fn tls<E: Into<BoxError>>(e: E) -> Error {
         Error::new(Kind::Tls, Some(e))
     }
@@ -72,6 +80,42 @@ impl Error {
     pub(crate) fn url_bad_scheme(url: Url) -> Error {
         Error::new(Kind::Builder, Some(BadScheme)).with_url(url)
     }
+
+    /// Converts an `io::Error` coming out of a byte-stream adapter back into an `Error`.
+    ///
+    /// An `Error` that was converted into the `io::Error` is returned as it was, any other
+    /// `io::Error` becomes the source of a new error of `kind`.
+    pub(crate) fn from_io(kind: Kind, err: io::Error) -> Error {
+        if err.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+            *err.into_inner()
+                .expect("io::Error::get_ref was Some(_)")
+                .downcast::<Error>()
+                .expect("StdError::is() was true")
+        } else {
+            Error::new(kind, Some(err))
+        }
+    }
+
+    /// Returns the kind of the `io::Error` this error converts to.
+    fn io_kind(&self) -> io::ErrorKind {
+        if self.is_timeout() {
+            return io::ErrorKind::TimedOut;
+        }
+
+        let mut source = self.source();
+        while let Some(err) = source {
+            if let Some(io) = err.downcast_ref::<io::Error>() {
+                return io.kind();
+            }
+            source = err.source();
+        }
+
+        match self.inner.kind {
+            Kind::Builder => io::ErrorKind::InvalidInput,
+            Kind::Decode => io::ErrorKind::InvalidData,
+            _ => io::ErrorKind::Other,
+        }
+    }
 }
 
 impl Error {
@@ -185,6 +229,45 @@ impl Error {
         false
     }
 
+    /// Returns true if the error happened while connecting to a proxy or establishing a tunnel
+    /// through it, rather than while connecting to the destination through the proxy.
+    ///
+    /// Such errors include the proxy being unreachable, rejecting the credentials, or refusing
+    /// the tunnel. See [`Error::proxy_status`] for the status of a refused tunnel.
+    pub fn is_proxy_connect(&self) -> bool {
+        let mut source = self.source();
+
+        while let Some(err) = source {
+            if let Some(details) = err.downcast_ref::<ProxyConnectError>() {
+                return details.at_proxy;
+            }
+            #[cfg(feature = "tls")]
+            if err.is::<ProxyTlsError>() {
+                return true;
+            }
+
+            source = err.source();
+        }
+
+        false
+    }
+
+    /// Returns the status code a proxy answered a `CONNECT` request with, if it refused to
+    /// establish a tunnel.
+    pub fn proxy_status(&self) -> Option<StatusCode> {
+        let mut source = self.source();
+
+        while let Some(err) = source {
+            if let Some(tunnel_err) = err.downcast_ref::<TunnelError>() {
+                return tunnel_err.status();
+            }
+
+            source = err.source();
+        }
+
+        None
+    }
+
     /// Returns true if the error is related to a connection reset.
     pub fn is_connection_reset(&self) -> bool {
         let mut source = self.source();
@@ -207,10 +290,219 @@ impl Error {
     }
 
     /// Returns true if the error is related to TLS
+    #[cfg(feature = "tls")]
     pub fn is_tls(&self) -> bool {
         matches!(self.inner.kind, Kind::Tls)
     }
 
+    /// Returns true if the error was caused by the server signaling a TLS 1.3 downgrade
+    /// while `require_no_downgrade` is set.
+    #[cfg(feature = "tls")]
+    pub fn is_tls_downgrade(&self) -> bool {
+        let mut source = self.source();
+
+        while let Some(err) = source {
+            if err.is::<crate::tls::DowngradeDetected>() {
+                return true;
+            }
+
+            source = err.source();
+        }
+
+        false
+    }
+
+    /// Returns true if the error was caused by the server not stapling an OCSP response
+    /// while `require_ocsp_staple` is set.
+    #[cfg(feature = "tls")]
+    pub fn is_ocsp_staple_missing(&self) -> bool {
+        let mut source = self.source();
+
+        while let Some(err) = source {
+            if err.is::<crate::tls::OcspStapleMissing>() {
+                return true;
+            }
+
+            source = err.source();
+        }
+
+        false
+    }
+
+    /// Returns true if the error was caused by applying a
+    /// [`ClientUpdate`](crate::ClientUpdate) after another update was applied to the client
+    /// since it started.
+    #[cfg(feature = "tls")]
+    pub fn is_concurrent_update(&self) -> bool {
+        let mut source = self.source();
+
+        while let Some(err) = source {
+            if err.is::<ConcurrentUpdate>() {
+                return true;
+            }
+
+            source = err.source();
+        }
+
+        false
+    }
+
+    /// Returns the details of the failed certificate verification that caused the error, if
+    /// any.
+    ///
+    /// Certificates rejected by a revocation check report a
+    /// [`RevocationError`](crate::tls::RevocationError) instead.
+    #[cfg(feature = "tls")]
+    pub fn cert_verify_details(&self) -> Option<&crate::tls::CertVerifyError> {
+        let mut source = self.source();
+
+        while let Some(err) = source {
+            if let Some(details) = err.downcast_ref::<crate::tls::CertVerifyError>() {
+                return Some(details);
+            }
+
+            source = err.source();
+        }
+
+        None
+    }
+
+    /// Returns the details of a response body that ended before its declared length, if
+    /// that caused the error.
+    pub fn incomplete_body(&self) -> Option<&IncompleteBody> {
+        let mut source = self.source();
+
+        while let Some(err) = source {
+            if let Some(details) = err.downcast_ref::<IncompleteBody>() {
+                return Some(details);
+            }
+
+            // The details are carried by an `io::Error`, which does not report them as its
+            // source.
+            if let Some(details) = err
+                .downcast_ref::<io::Error>()
+                .and_then(io::Error::get_ref)
+                .and_then(|err| err.downcast_ref::<IncompleteBody>())
+            {
+                return Some(details);
+            }
+
+            source = err.source();
+        }
+
+        None
+    }
+
+    /// Returns the details of a response body that did not have its expected digest, if that
+    /// caused the error.
+    ///
+    /// See [`RequestBuilder::verify_digest`](crate::RequestBuilder::verify_digest).
+    pub fn digest_mismatch(&self) -> Option<&DigestMismatch> {
+        let mut source = self.source();
+
+        while let Some(err) = source {
+            if let Some(details) = err.downcast_ref::<DigestMismatch>() {
+                return Some(details);
+            }
+
+            source = err.source();
+        }
+
+        None
+    }
+
+    /// Returns the contradictions found in an emulation, if they caused the error.
+    ///
+    /// See [`EmulationProviderBuilder::strict`](crate::EmulationProviderBuilder::strict).
+    pub fn emulation_conflicts(&self) -> Option<&EmulationConflicts> {
+        let mut source = self.source();
+
+        while let Some(err) = source {
+            if let Some(details) = err.downcast_ref::<EmulationConflicts>() {
+                return Some(details);
+            }
+
+            source = err.source();
+        }
+
+        None
+    }
+
+    /// Returns the details of request headers exceeding their size budget, if that caused the
+    /// error.
+    ///
+    /// See [`ClientBuilder::max_request_header_bytes`](crate::ClientBuilder::max_request_header_bytes).
+    pub fn request_headers_too_large(&self) -> Option<&RequestHeadersTooLarge> {
+        let mut source = self.source();
+
+        while let Some(err) = source {
+            if let Some(details) = err.downcast_ref::<RequestHeadersTooLarge>() {
+                return Some(details);
+            }
+
+            source = err.source();
+        }
+
+        None
+    }
+
+    /// Returns the details of a failed TLS handshake with an `https` proxy, if that caused the
+    /// error.
+    ///
+    /// TLS errors of the destination, tunneled through the proxy, are not reported here.
+    #[cfg(feature = "tls")]
+    pub fn proxy_tls(&self) -> Option<&ProxyTlsError> {
+        let mut source = self.source();
+
+        while let Some(err) = source {
+            if let Some(details) = err.downcast_ref::<ProxyTlsError>() {
+                return Some(details);
+            }
+
+            source = err.source();
+        }
+
+        None
+    }
+
+    /// Returns the URI of the proxy a connect error happened through, if the connection was
+    /// made through a proxy.
+    pub fn proxy(&self) -> Option<&Uri> {
+        let mut source = self.source();
+
+        while let Some(err) = source {
+            if let Some(details) = err.downcast_ref::<ProxyConnectError>() {
+                return Some(&details.proxy);
+            }
+            #[cfg(feature = "tls")]
+            if let Some(details) = err.downcast_ref::<ProxyTlsError>() {
+                return Some(&details.proxy);
+            }
+
+            source = err.source();
+        }
+
+        None
+    }
+
+    /// Returns the attempts of a download from mirrors, if the error was caused by every mirror
+    /// failing.
+    ///
+    /// See [`Client::download_from_mirrors`](crate::Client::download_from_mirrors).
+    pub fn mirrors_exhausted(&self) -> Option<&MirrorsExhausted> {
+        let mut source = self.source();
+
+        while let Some(err) = source {
+            if let Some(details) = err.downcast_ref::<MirrorsExhausted>() {
+                return Some(details);
+            }
+
+            source = err.source();
+        }
+
+        None
+    }
+
     /// Returns true if the error is related to decoding the response's body
     pub fn is_decode(&self) -> bool {
         matches!(self.inner.kind, Kind::Decode)
@@ -277,6 +569,7 @@ impl fmt::Display for Error {
             Kind::Builder => f.write_str("builder error")?,
             Kind::Request => f.write_str("error sending request")?,
             Kind::Body => f.write_str("request or response body error")?,
+            #[cfg(feature = "tls")]
             Kind::Tls => f.write_str("tls error")?,
             Kind::Decode => f.write_str("error decoding response body")?,
             Kind::Redirect => f.write_str("error following redirect")?,
@@ -319,6 +612,28 @@ impl StdError for Error {
     }
 }
 
+/// Converts an `Error` into an `io::Error`, for byte-stream adapters such as
+/// [`Response::into_async_read`](crate::Response::into_async_read).
+///
+/// The `Error` is kept as the inner error of the `io::Error`, so it can be recovered with
+/// [`io::Error::get_ref`] and `downcast_ref::<wreq::Error>()`, or with
+/// [`io::Error::into_inner`] and `downcast::<wreq::Error>()`.
+///
+/// The kind of the `io::Error` is, in order:
+///
+/// - [`TimedOut`](io::ErrorKind::TimedOut) for a timeout, see [`Error::is_timeout`].
+/// - The kind of the first `io::Error` in the source chain, such as
+///   [`ConnectionRefused`](io::ErrorKind::ConnectionRefused) or
+///   [`ConnectionReset`](io::ErrorKind::ConnectionReset).
+/// - [`InvalidInput`](io::ErrorKind::InvalidInput) for a builder error, see [`Error::is_builder`].
+/// - [`InvalidData`](io::ErrorKind::InvalidData) for a decode error, see [`Error::is_decode`].
+/// - [`Other`](io::ErrorKind::Other) otherwise.
+impl From<Error> for io::Error {
+    fn from(err: Error) -> io::Error {
+        io::Error::new(err.io_kind(), err)
+    }
+}
+
 #[derive(Debug)]
 pub(crate) enum Kind {
     Builder,
@@ -326,6 +641,7 @@ pub(crate) enum Kind {
     Redirect,
     Status(StatusCode, Option<ReasonPhrase>),
     Body,
+    #[cfg(feature = "tls")]
     Tls,
     Decode,
     Upgrade,
@@ -342,6 +658,216 @@ impl fmt::Display for TimedOut {
 
 impl StdError for TimedOut {}
 
+#[cfg(feature = "tls")]
+#[derive(Debug)]
+pub(crate) struct ConcurrentUpdate;
+
+#[cfg(feature = "tls")]
+impl fmt::Display for ConcurrentUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the client was updated since this update started")
+    }
+}
+
+#[cfg(feature = "tls")]
+impl StdError for ConcurrentUpdate {}
+
+/// The cause of an error when a response body ended before its declared length.
+///
+/// The connection is closed by then, and the bytes received so far were delivered. If the
+/// server supports range requests, the rest of the body can be requested from
+/// [`received`](Self::received) on.
+///
+/// See [`Error::incomplete_body`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IncompleteBody {
+    received: u64,
+    expected: Option<u64>,
+}
+
+impl IncompleteBody {
+    pub(crate) fn new(received: u64, expected: Option<u64>) -> IncompleteBody {
+        IncompleteBody { received, expected }
+    }
+
+    /// Returns the number of body bytes received.
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+
+    /// Returns the body length declared by `Content-Length`, or `None` for a chunked body.
+    pub fn expected(&self) -> Option<u64> {
+        self.expected
+    }
+}
+
+impl fmt::Display for IncompleteBody {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("end of file before message length reached")?;
+        match self.expected {
+            Some(expected) => write!(f, " ({} of {expected} bytes received)", self.received),
+            None => write!(f, " ({} bytes received)", self.received),
+        }
+    }
+}
+
+impl StdError for IncompleteBody {}
+
+/// The cause of an error when the headers of a request did not fit in the size budget set with
+/// [`ClientBuilder::max_request_header_bytes`](crate::ClientBuilder::max_request_header_bytes).
+///
+/// The request was not sent.
+///
+/// See [`Error::request_headers_too_large`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestHeadersTooLarge {
+    estimated: usize,
+    limit: usize,
+    largest_header: Option<HeaderName>,
+}
+
+impl RequestHeadersTooLarge {
+    pub(crate) fn new(
+        estimated: usize,
+        limit: usize,
+        largest_header: Option<HeaderName>,
+    ) -> RequestHeadersTooLarge {
+        RequestHeadersTooLarge {
+            estimated,
+            limit,
+            largest_header,
+        }
+    }
+
+    /// Returns the estimated size of the headers, in bytes.
+    pub fn estimated(&self) -> usize {
+        self.estimated
+    }
+
+    /// Returns the size budget of the headers, in bytes.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Returns the name of the header taking the most room, all its fields together.
+    pub fn largest_header(&self) -> Option<&HeaderName> {
+        self.largest_header.as_ref()
+    }
+}
+
+impl fmt::Display for RequestHeadersTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "request headers of about {} bytes exceed the limit of {} bytes",
+            self.estimated, self.limit
+        )?;
+        if let Some(name) = &self.largest_header {
+            write!(f, " (largest header: {name})")?;
+        }
+        Ok(())
+    }
+}
+
+impl StdError for RequestHeadersTooLarge {}
+
+/// The cause of a connect error when the TLS handshake with an `https` proxy failed, before
+/// any request or tunnel was sent through it.
+///
+/// See [`Error::proxy_tls`].
+#[cfg(feature = "tls")]
+#[derive(Debug)]
+pub struct ProxyTlsError {
+    proxy: Uri,
+    source: BoxError,
+}
+
+#[cfg(feature = "tls")]
+impl ProxyTlsError {
+    pub(crate) fn new(proxy: Uri, source: BoxError) -> ProxyTlsError {
+        ProxyTlsError { proxy, source }
+    }
+
+    /// Returns the URI of the proxy.
+    pub fn proxy(&self) -> &Uri {
+        &self.proxy
+    }
+}
+
+#[cfg(feature = "tls")]
+impl fmt::Display for ProxyTlsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TLS handshake with proxy {} failed", self.proxy)
+    }
+}
+
+#[cfg(feature = "tls")]
+impl StdError for ProxyTlsError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+/// The cause of a connect error through a proxy, naming the proxy.
+///
+/// See [`Error::proxy`] and [`Error::is_proxy_connect`].
+#[derive(Debug)]
+pub(crate) struct ProxyConnectError {
+    proxy: Uri,
+    source: BoxError,
+    /// Whether connecting to the proxy, or establishing a tunnel through it, failed.
+    at_proxy: bool,
+}
+
+impl ProxyConnectError {
+    /// Names `proxy` in `err`, an error connecting to the proxy or establishing a tunnel
+    /// through it.
+    pub(crate) fn at_proxy(proxy: Uri, err: BoxError) -> BoxError {
+        #[cfg(feature = "tls")]
+        if err.is::<ProxyTlsError>() {
+            return err;
+        }
+        Box::new(ProxyConnectError {
+            proxy,
+            source: err,
+            at_proxy: true,
+        })
+    }
+
+    /// Names `proxy` in `err`, an error connecting to the destination through the proxy,
+    /// unless it already names it.
+    pub(crate) fn at_destination(proxy: Uri, err: BoxError) -> BoxError {
+        #[cfg(feature = "tls")]
+        if err.is::<ProxyTlsError>() {
+            return err;
+        }
+        if err.is::<ProxyConnectError>() {
+            return err;
+        }
+        Box::new(ProxyConnectError {
+            proxy,
+            source: err,
+            at_proxy: false,
+        })
+    }
+}
+
+impl fmt::Display for ProxyConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.at_proxy {
+            write!(f, "failed to connect to proxy {}", self.proxy)
+        } else {
+            write!(f, "failed to connect through proxy {}", self.proxy)
+        }
+    }
+}
+
+impl StdError for ProxyConnectError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&*self.source)
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct BadScheme;
 
@@ -353,6 +879,22 @@ impl fmt::Display for BadScheme {
 
 impl StdError for BadScheme {}
 
+/// The cause of an error when an `https` URL or a proxy connected to over TLS is used without
+/// the `tls` feature.
+#[cfg(not(feature = "tls"))]
+#[derive(Debug)]
+pub(crate) struct TlsDisabled;
+
+#[cfg(not(feature = "tls"))]
+impl fmt::Display for TlsDisabled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("TLS is not supported, the `tls` feature of wreq is disabled")
+    }
+}
+
+#[cfg(not(feature = "tls"))]
+impl StdError for TlsDisabled {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -360,21 +902,8 @@ mod tests {
     fn assert_send<T: Send>() {}
     fn assert_sync<T: Sync>() {}
 
-    impl super::Error {
-        fn into_io(self) -> io::Error {
-            io::Error::other(self)
-        }
-    }
-
     fn decode_io(e: io::Error) -> Error {
-        if e.get_ref().map(|r| r.is::<Error>()).unwrap_or(false) {
-            *e.into_inner()
-                .expect("io::Error::get_ref was Some(_)")
-                .downcast::<Error>()
-                .expect("StdError::is() was true")
-        } else {
-            Error::decode(e)
-        }
+        Error::from_io(Kind::Decode, e)
     }
 
     #[test]
@@ -398,7 +927,7 @@ mod tests {
     fn roundtrip_io_error() {
         let orig = Error::request("orig");
         // Convert wreq::Error into an io::Error...
-        let io = orig.into_io();
+        let io = io::Error::from(orig);
         // Convert that io::Error back into a wreq::Error...
         let err = decode_io(io);
         // It should have pulled out the original, not nested it...
@@ -428,6 +957,26 @@ mod tests {
         assert!(nested.is_timeout());
     }
 
+    #[test]
+    fn io_error_kinds() {
+        let kind = |err: Error| io::Error::from(err).kind();
+        assert_eq!(kind(Error::request(TimedOut)), io::ErrorKind::TimedOut);
+        assert_eq!(
+            kind(Error::request(io::Error::from(
+                io::ErrorKind::ConnectionRefused
+            ))),
+            io::ErrorKind::ConnectionRefused
+        );
+        assert_eq!(kind(Error::builder("bad")), io::ErrorKind::InvalidInput);
+        assert_eq!(kind(Error::decode("bad")), io::ErrorKind::InvalidData);
+        assert_eq!(kind(Error::body("bad")), io::ErrorKind::Other);
+
+        let io = io::Error::from(Error::request(TimedOut));
+        let inner = io.get_ref().unwrap().downcast_ref::<Error>().unwrap();
+        assert!(inner.is_request());
+        assert!(inner.is_timeout());
+    }
+
     #[test]
     fn is_connection_reset() {
         let err = Error::request(io::Error::new(
@@ -1,4 +1,6 @@
-use url::Url;
+use std::{fmt, net::Ipv6Addr};
+
+use url::{Position, Url};
 
 use crate::Error;
 
@@ -19,6 +21,14 @@ pub trait IntoUrlSealed {
     // `http::Uri`, in that it makes sense to use in a network request.
     fn into_url(self) -> crate::Result<Url>;
 
+    // Like `into_url`, also accepting an IPv6 literal host with a zone, which `Url` cannot hold.
+    fn into_url_with_zone(self) -> crate::Result<(Url, Option<Ipv6Zone>)>
+    where
+        Self: Sized,
+    {
+        self.into_url().map(|url| (url, None))
+    }
+
     fn as_str(&self) -> &str;
 }
 
@@ -60,11 +70,203 @@ where
             .into_url()
     }
 
+    fn into_url_with_zone(self) -> crate::Result<(Url, Option<Ipv6Zone>)> {
+        match Ipv6Zone::split(self.as_ref())? {
+            Some((url, zone)) => Ok((url.into_url()?, Some(zone))),
+            None => self.into_url().map(|url| (url, None)),
+        }
+    }
+
     fn as_str(&self) -> &str {
         self.as_ref()
     }
 }
 
+/// The zone of an IPv6 literal host, such as the `eth0` of `https://[fe80::1%25eth0]:8443/`.
+///
+/// The link-local addresses of a host are ambiguous unless qualified by the interface they are
+/// reached through. URLs name it with the zone identifier of [RFC 6874], percent-encoded after the
+/// address, which [`Url`] does not support. The URLs passed to [`Client::request`] as strings may
+/// have one, in the RFC 6874 form or in the raw `[fe80::1%eth0]` form, and the zone is then kept
+/// with the request rather than in its [`url`](crate::Request::url).
+///
+/// The zone scopes the address the connection is made to, while the `Host` header and the TLS
+/// certificate verification see the address alone, and the server name indication is omitted as
+/// for any IP address. Connections and cookies are not shared between the zones of an address.
+///
+/// [RFC 6874]: https://www.rfc-editor.org/rfc/rfc6874
+/// [`Client::request`]: crate::Client::request
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Ipv6Zone {
+    addr: Ipv6Addr,
+    name: String,
+    scope_id: u32,
+}
+
+impl Ipv6Zone {
+    /// Creates the zone `name` of `addr`, either the name of a network interface or a numeric
+    /// scope identifier.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `name` is neither a number nor the name of a network interface of the host.
+    pub fn new(addr: Ipv6Addr, name: impl Into<String>) -> crate::Result<Ipv6Zone> {
+        let name = name.into();
+        match scope_id(&name) {
+            Some(scope_id) => Ok(Ipv6Zone {
+                addr,
+                name,
+                scope_id,
+            }),
+            None => Err(Error::builder(format!(
+                "IPv6 zone `{name}` is not a network interface"
+            ))),
+        }
+    }
+
+    /// Returns the address the zone qualifies.
+    pub fn addr(&self) -> Ipv6Addr {
+        self.addr
+    }
+
+    /// Returns the name of the zone, as it appeared in the URL.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the scope identifier of the zone, the index of its network interface.
+    pub fn scope_id(&self) -> u32 {
+        self.scope_id
+    }
+
+    /// Returns `url` with the zone in its host, in the RFC 6874 form, if its host is the address
+    /// of the zone.
+    pub fn to_url_string(&self, url: &Url) -> String {
+        if self.applies_to(url.host_str().unwrap_or_default()) {
+            format!(
+                "{}{self}{}",
+                &url[..Position::BeforeHost],
+                &url[Position::AfterHost..]
+            )
+        } else {
+            url.to_string()
+        }
+    }
+
+    /// Returns whether `host`, bracketed or not, is the address of the zone.
+    pub(crate) fn applies_to(&self, host: &str) -> bool {
+        host.trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<Ipv6Addr>()
+            .is_ok_and(|addr| addr == self.addr)
+    }
+
+    /// Returns the URL the cookies of `url` are stored under, whose host is a name unique to the
+    /// address and the scope of the zone, as `Url` cannot hold the zone.
+    pub(crate) fn cookie_url(&self, url: &Url) -> Url {
+        let mut url = url.clone();
+        if self.applies_to(url.host_str().unwrap_or_default()) {
+            let addr: String = self
+                .addr
+                .octets()
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect();
+            let _ = url.set_host(Some(&format!("{addr}.{}.ipv6-zone.invalid", self.scope_id)));
+        }
+        url
+    }
+
+    /// Splits the zone out of the IPv6 literal host of the URL `s`, returning the URL without it.
+    fn split(s: &str) -> crate::Result<Option<(String, Ipv6Zone)>> {
+        let authority_start = s.find("://").map_or(0, |i| i + 3);
+        let authority_end = s[authority_start..]
+            .find(['/', '?', '#'])
+            .map_or(s.len(), |i| authority_start + i);
+        let authority = &s[authority_start..authority_end];
+        let host_start = authority_start + authority.rfind('@').map_or(0, |i| i + 1);
+
+        let Some(literal) = s[host_start..authority_end].strip_prefix('[') else {
+            return Ok(None);
+        };
+        let Some((literal, _)) = literal.split_once(']') else {
+            return Ok(None);
+        };
+        let Some((addr, zone)) = literal.split_once('%') else {
+            return Ok(None);
+        };
+
+        let invalid = || Error::builder(format!("invalid IPv6 zone in `{s}`"));
+        let addr = addr.parse::<Ipv6Addr>().map_err(|_| invalid())?;
+        // RFC 6874 percent-encodes the `%` separating the zone
+        let zone = zone.strip_prefix("25").unwrap_or(zone);
+        let name = percent_decode(zone).ok_or_else(invalid)?;
+
+        let zone = Ipv6Zone::new(addr, name)?;
+        let url = format!(
+            "{}[{addr}]{}",
+            &s[..host_start],
+            &s[host_start + literal.len() + 2..]
+        );
+        Ok(Some((url, zone)))
+    }
+}
+
+impl fmt::Display for Ipv6Zone {
+    /// Formats the zone as the host of a URL, such as `[fe80::1%25eth0]`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}%25", self.addr)?;
+        for b in self.name.bytes() {
+            if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+                write!(f, "{}", b as char)?;
+            } else {
+                write!(f, "%{b:02X}")?;
+            }
+        }
+        f.write_str("]")
+    }
+}
+
+/// Decodes the percent-encoded zone `s`, which must not be empty.
+fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+        } else if b.is_ascii_graphic() {
+            bytes.push(b);
+        } else {
+            return None;
+        }
+    }
+
+    String::from_utf8(bytes)
+        .ok()
+        .filter(|name| !name.is_empty() && !name.contains(|c: char| c.is_control()))
+}
+
+/// Returns the scope identifier of the zone `name`, a number or the name of an interface.
+fn scope_id(name: &str) -> Option<u32> {
+    if let Ok(scope_id) = name.parse() {
+        return Some(scope_id);
+    }
+
+    #[cfg(unix)]
+    {
+        let name = std::ffi::CString::new(name).ok()?;
+        // Safety: `name` is a valid C string for the duration of the call.
+        let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+        if index != 0 {
+            return Some(index);
+        }
+    }
+
+    None
+}
+
 mod sealed {
     use http::Uri;
 
@@ -98,6 +300,70 @@ mod tests {
         );
     }
 
+    #[test]
+    fn into_url_splits_ipv6_zone() {
+        let (url, zone) = "https://[fe80::1%252]:8443/path?q"
+            .into_url_with_zone()
+            .unwrap();
+        assert_eq!(url.as_str(), "https://[fe80::1]:8443/path?q");
+        let zone = zone.unwrap();
+        assert_eq!(zone.addr(), "fe80::1".parse::<Ipv6Addr>().unwrap());
+        assert_eq!(zone.name(), "2");
+        assert_eq!(zone.scope_id(), 2);
+        assert_eq!(
+            zone.to_url_string(&url),
+            "https://[fe80::1%252]:8443/path?q"
+        );
+
+        // the raw form is accepted, and emitted in the RFC 6874 form
+        let (url, zone) = "http://user@[fe80::1%3]/".into_url_with_zone().unwrap();
+        assert_eq!(url.as_str(), "http://user@[fe80::1]/");
+        assert_eq!(
+            zone.unwrap().to_url_string(&url),
+            "http://user@[fe80::1%253]/"
+        );
+
+        let (url, zone) = "http://[fe80::1]/".into_url_with_zone().unwrap();
+        assert_eq!(url.as_str(), "http://[fe80::1]/");
+        assert!(zone.is_none());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn into_url_resolves_ipv6_zone_interface() {
+        let (url, zone) = "http://[fe80::1%25lo]/".into_url_with_zone().unwrap();
+        let zone = zone.unwrap();
+        assert_eq!(zone.name(), "lo");
+        assert_ne!(zone.scope_id(), 0);
+        assert_eq!(zone.to_string(), "[fe80::1%25lo]");
+        assert_eq!(zone.to_url_string(&url), "http://[fe80::1%25lo]/");
+    }
+
+    #[test]
+    fn into_url_rejects_invalid_ipv6_zone() {
+        for url in [
+            "http://[fe80::1%25]/",
+            "http://[fe80::1%25no-such-interface0]/",
+            "http://[fe80::1%25e%2]/",
+            "http://[zz::1%25eth0]/",
+        ] {
+            assert!(url.into_url_with_zone().unwrap_err().is_builder(), "{url}");
+        }
+    }
+
+    #[test]
+    fn ipv6_zone_cookie_url_differs_per_scope() {
+        let url = Url::parse("http://[fe80::1]/").unwrap();
+        let addr = "fe80::1".parse().unwrap();
+        let first = Ipv6Zone::new(addr, "1").unwrap().cookie_url(&url);
+        let second = Ipv6Zone::new(addr, "2").unwrap().cookie_url(&url);
+        assert_eq!(
+            first.as_str(),
+            "http://fe800000000000000000000000000001.1.ipv6-zone.invalid/"
+        );
+        assert_ne!(first, second);
+    }
+
     #[tokio::test]
     async fn execute_request_rejects_invalid_hostname() {
         let url_str = "https://{{hostname}}/";
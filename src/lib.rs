@@ -220,11 +220,16 @@
 //! ```
 //!
 //! * `http://` is the scheme for http proxy
-//! * `https://` is the scheme for https proxy
+//! * `https://` is the scheme for https proxy, connected to over TLS, which tunnels all requests
+//!   with `CONNECT`
 //! * `socks4://` is the scheme for socks4 proxy
 //! * `socks4a://` is the scheme for socks4a proxy
 //! * `socks5://` is the scheme for socks5 proxy
 //! * `socks5h://` is the scheme for socks5h proxy
+//! * `socks4s://`, `socks4as://`, `socks5s://` and `socks5hs://` are the schemes for these socks
+//!   proxies, connected to over TLS
+//! * `unix://`, `socks5+unix://` and `socks5h+unix://` are the schemes for http and socks5 proxies
+//!   listening on a unix socket, such as `unix:///var/run/proxy.sock`
 //!  
 //! ## TLS
 //!
@@ -232,13 +237,23 @@
 //!
 //! - Various parts of TLS can also be configured or even disabled on the `ClientBuilder`.
 //!
+//! Without the **tls** feature, the crate builds without BoringSSL, and only speaks plain HTTP:
+//! requests to `https` URLs, and through `https` proxies, fail with a builder error. This suits
+//! targets where BoringSSL cannot be built, talking to a local proxy or sidecar that terminates
+//! TLS. Such builds enable the **plain-http** feature instead:
+//!
+//! ```toml
+//! wreq = { version = "5", default-features = false, features = ["plain-http"] }
+//! ```
+//!
 //! ## Certificate Store
 //!
 //! By default, wreq uses Mozilla's root certificates through the webpki-roots crate.
 //! This static root certificate bundle is not automatically updated and ignores any root
 //! certificates installed on the host. You can disable default-features to use the system's default
 //! certificate path. Additionally, wreq provides a certificate store for users to customize and
-//! update certificates.
+//! update certificates. The bundle stays available as `CertStore::mozilla_bundle`, which
+//! `ClientBuilder::use_bundled_roots` falls back to when the configured store is empty.
 //!
 //! Custom Certificate Store verification supports Root CA certificates, peer certificates, and
 //! self-signed certificate SSL pinning.
@@ -249,21 +264,38 @@
 //! enabled or disabled:
 //!
 //! - **full**: Enables all optional features.
+//! - **tls** *(enabled by default)*: Connects to HTTPS servers and proxies with BoringSSL, and
+//!   provides the [`tls`] module.
+//! - **plain-http**: Provides the digests the crate computes without BoringSSL, for builds without
+//!   **tls**. One of the two features must be enabled.
 //! - **websocket**: Provides websocket support.
 //! - **cookies**: Provides cookie session support.
+//! - **psl**: Provides a public suffix list, used to reject cookies set for a public suffix and
+//!   to trim cross-site referers to their origin.
 //! - **gzip**: Provides response body gzip decompression.
 //! - **brotli**: Provides response body brotli decompression.
 //! - **zstd**: Provides response body zstd decompression.
 //! - **deflate**: Provides response body deflate decompression.
 //! - **json**: Provides serialization and deserialization for JSON bodies.
+//! - **serde**: Implements `Serialize` and `Deserialize` for `TlsConfig`,
+//!   [`Http1Config`](http1::Http1Config) and [`Http2Config`](http2::Http2Config), so emulation
+//!   profiles can be loaded at runtime, and `Serialize` for `TlsConfigSnapshot`. Together with
+//!   **json**, whole profiles load with [`EmulationProvider::from_json`].
 //! - **multipart**: Provides functionality for multipart forms.
 //! - **charset** *(enabled by default)*: Improved support for decoding text.
 //! - **stream**: Adds support for `futures::Stream`.
 //! - **socks**: Provides SOCKS5 and SOCKS4 proxy support.
+//! - **vcr**: Records and replays interactions from cassette files, see [`vcr`].
 //! - **hickory-dns**: Enables a hickory-dns async resolver instead of default threadpool using
 //!   `getaddrinfo`.
 //! - **webpki-roots** *(enabled by default)*: Use the webpki-roots crate for root certificates.
+//!   Enables **tls**.
 //! - **system-proxy** *(enabled by default)*: Enable system proxy support.
+//! - **pac**: Route requests with proxy auto-config scripts, see [`Proxy::pac`].
+//! - **native-identity**: Load client certificates from the Windows or macOS keystore. Enables
+//!   **tls**.
+//! - **blake3**: Verify response bodies against BLAKE3 digests, see
+//!   [`RequestBuilder::verify_digest`].
 //! - **tracing**: Enable tracing logging support.
 //!
 //! [client]: ./struct.Client.html
@@ -275,6 +307,9 @@
 //! [Proxy]: ./struct.Proxy.html
 //! [cargo-features]: https://doc.rust-lang.org/stable/cargo/reference/manifest.html#the-features-section
 
+#[cfg(not(any(feature = "tls", feature = "plain-http")))]
+compile_error!("either the `tls` or the `plain-http` feature of wreq must be enabled");
+
 #[macro_use]
 mod trace;
 
@@ -287,8 +322,8 @@ mod response;
 mod sync;
 
 pub use self::{
-    error::{Error, Result},
-    into_url::IntoUrl,
+    error::{Error, IncompleteBody, RequestHeadersTooLarge, Result},
+    into_url::{IntoUrl, Ipv6Zone},
     response::ResponseBuilderExt,
 };
 
@@ -316,26 +351,60 @@ fn _assert_impls() {
     assert_sync::<Error>();
 }
 
+#[cfg(feature = "stream")]
+pub use self::client::FlowHandle;
+#[cfg(feature = "json")]
+pub use self::client::Ndjson;
+#[cfg(any(
+    feature = "gzip",
+    feature = "zstd",
+    feature = "brotli",
+    feature = "deflate",
+))]
+pub use self::client::PartialBody;
+pub use self::client::client_hints;
+pub use self::client::forwarded;
+pub use self::client::message_signature;
+pub use self::client::mirrors;
 #[cfg(feature = "multipart")]
 pub use self::client::multipart;
 #[cfg(feature = "websocket")]
 pub use self::client::websocket;
+#[cfg(feature = "tls")]
+pub use self::client::{ClientUpdate, TlsConfigSnapshot};
+#[cfg(feature = "tls")]
+pub use self::error::ProxyTlsError;
 pub use self::{
     client::{
-        Body, Client, ClientBuilder, EmulationProvider, EmulationProviderFactory, Request,
-        RequestBuilder, Response, Upgraded,
+        Body, Client, ClientBuilder, ConnId, DigestAlgorithm, DigestMismatch, EmulationApply,
+        EmulationConflicts, EmulationProvider, EmulationProviderBuilder, EmulationProviderFactory,
+        EmulationSelection, ExpectedDigest, FailoverConfig, FailureContext, FailureKind,
+        KeepaliveOutcome, OriginalUrl, PooledConnection, ProbeBuilder, ProbeDepth, ProbeReport,
+        ProfileStats, RedirectHop, Request, RequestAttempt, RequestBuilder, Response, RetryBudget,
+        RotatingEmulation, ThinkTime, ThinkTimeDelay, TrimmedCookies, Upgraded,
     },
+    connect::ConnectTiming,
     core::{
-        client::config::{http1, http2},
+        client::{
+            config::{http1, http2},
+            connect::{ProxyProtocolConfig, ProxyProtocolVersion},
+        },
+        ext::{ExcessData, RawResponseHead},
         header::OriginalHeaders,
     },
-    proxy::{NoProxy, Proxy},
+    proxy::{
+        AuthScheme, Credentials, NoProxy, ParsedChallenge, Proxy, ProxyAuthFuture,
+        ProxyAuthHandler, ProxyCredentials, ProxyCredentialsFuture, ProxyLatencyStats,
+        ProxySelector, ProxyStrategy,
+    },
 };
 
 mod client;
 mod connect;
 #[cfg(feature = "cookies")]
 pub mod cookie;
+#[cfg(feature = "psl")]
+pub mod psl;
 
 mod core;
 pub mod dns;
@@ -343,5 +412,8 @@ mod proxy;
 
 pub mod redirect;
 
+#[cfg(feature = "tls")]
 pub mod tls;
 mod util;
+#[cfg(feature = "vcr")]
+pub mod vcr;
@@ -0,0 +1,280 @@
+use std::{collections::HashMap, fmt, future::Future, pin::Pin, sync::Arc};
+
+use futures_util::future::{FutureExt, Shared};
+use http::{HeaderValue, Uri};
+
+use crate::sync::Mutex;
+
+/// Alias for the `Future` type returned by a [`ProxyAuthHandler`].
+pub type ProxyAuthFuture = Pin<Box<dyn Future<Output = Option<Credentials>> + Send>>;
+
+/// Answers the authentication challenges of proxies, for example by prompting the user.
+///
+/// The handler is called when a proxy responds with `407 Proxy Authentication Required`, either
+/// to a `CONNECT` request or a forwarded `http` request, with a challenge of a supported
+/// scheme. The request is then retried once with the returned credentials.
+pub trait ProxyAuthHandler: Send + Sync {
+    /// Returns the credentials answering the `challenge` of `proxy`, or `None` to fail the
+    /// request.
+    ///
+    /// Requests challenged by the same proxy while a call is pending wait for its credentials,
+    /// instead of calling the handler again. The credentials are then sent to the proxy with
+    /// later requests, until it rejects them with another challenge.
+    fn on_challenge(&self, proxy: &Uri, challenge: ParsedChallenge) -> ProxyAuthFuture;
+}
+
+/// The scheme of a [`ParsedChallenge`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AuthScheme {
+    /// The `Basic` scheme of RFC 7617.
+    Basic,
+}
+
+/// An authentication challenge parsed from a `Proxy-Authenticate` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedChallenge {
+    scheme: AuthScheme,
+    params: Vec<(String, String)>,
+}
+
+/// A username and password answering a [`ParsedChallenge`].
+#[derive(Clone)]
+pub struct Credentials {
+    username: String,
+    password: String,
+}
+
+/// The pending or answered challenge of a proxy.
+enum Answer {
+    Pending(Shared<Pin<Box<dyn Future<Output = Option<HeaderValue>> + Send>>>),
+    Ready(HeaderValue),
+}
+
+/// The credentials given by a [`ProxyAuthHandler`], cached per proxy.
+pub(crate) struct ProxyAuth {
+    handler: Arc<dyn ProxyAuthHandler>,
+    answers: Mutex<HashMap<Uri, Answer>>,
+}
+
+impl ParsedChallenge {
+    /// Parses the first challenge of a supported scheme from `Proxy-Authenticate` headers.
+    pub(crate) fn parse<'a, I>(headers: I) -> Option<ParsedChallenge>
+    where
+        I: IntoIterator<Item = &'a HeaderValue>,
+    {
+        headers
+            .into_iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(parse_challenges)
+            .find_map(|(scheme, params)| {
+                let scheme = if scheme.eq_ignore_ascii_case("basic") {
+                    AuthScheme::Basic
+                } else {
+                    return None;
+                };
+                Some(ParsedChallenge { scheme, params })
+            })
+    }
+
+    /// Returns the scheme of the challenge.
+    pub fn scheme(&self) -> AuthScheme {
+        self.scheme
+    }
+
+    /// Returns the `realm` parameter of the challenge.
+    pub fn realm(&self) -> Option<&str> {
+        self.param("realm")
+    }
+
+    /// Returns a parameter of the challenge, by case-insensitive name.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl Credentials {
+    /// Creates credentials from a username and password.
+    pub fn new(username: impl Into<String>, password: impl Into<String>) -> Credentials {
+        Credentials {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+
+    /// Returns the username.
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// Returns the `Proxy-Authorization` header answering a challenge of `scheme`.
+    fn header(&self, scheme: AuthScheme) -> HeaderValue {
+        match scheme {
+            AuthScheme::Basic => super::encode_basic_auth(&self.username, &self.password),
+        }
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ProxyAuth {
+    pub(crate) fn new(handler: Arc<dyn ProxyAuthHandler>) -> ProxyAuth {
+        ProxyAuth {
+            handler,
+            answers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the cached `Proxy-Authorization` header of `proxy`.
+    pub(crate) fn cached(&self, proxy: &Uri) -> Option<HeaderValue> {
+        match self.answers.lock().get(proxy) {
+            Some(Answer::Ready(header)) => Some(header.clone()),
+            _ => None,
+        }
+    }
+
+    /// Answers the challenge of `proxy` to a request sent with the `sent` authorization,
+    /// returning the `Proxy-Authorization` header to retry the request with.
+    pub(crate) async fn challenge<'a, I>(
+        &self,
+        proxy: &Uri,
+        challenges: I,
+        sent: Option<&HeaderValue>,
+    ) -> Option<HeaderValue>
+    where
+        I: IntoIterator<Item = &'a HeaderValue>,
+    {
+        let challenge = ParsedChallenge::parse(challenges);
+
+        let answer = {
+            let mut answers = self.answers.lock();
+            match answers.get(proxy) {
+                Some(Answer::Pending(answer)) => answer.clone(),
+                // the credentials were answered since the request was sent
+                Some(Answer::Ready(header)) if Some(header) != sent => return Some(header.clone()),
+                _ => {
+                    let Some(challenge) = challenge else {
+                        answers.remove(proxy);
+                        return None;
+                    };
+
+                    let handler = self.handler.clone();
+                    let uri = proxy.clone();
+                    let scheme = challenge.scheme;
+                    let answer = async move {
+                        let credentials = handler.on_challenge(&uri, challenge).await?;
+                        Some(credentials.header(scheme))
+                    }
+                    .boxed()
+                    .shared();
+
+                    answers.insert(proxy.clone(), Answer::Pending(answer.clone()));
+                    answer
+                }
+            }
+        };
+
+        let header = answer.clone().await;
+
+        let mut answers = self.answers.lock();
+        if let Some(Answer::Pending(pending)) = answers.get(proxy) {
+            if pending.ptr_eq(&answer) {
+                match header {
+                    Some(ref header) => {
+                        answers.insert(proxy.clone(), Answer::Ready(header.clone()));
+                    }
+                    None => {
+                        answers.remove(proxy);
+                    }
+                }
+            }
+        }
+
+        header
+    }
+}
+
+/// Splits a `Proxy-Authenticate` header into its challenges: a scheme, and its parameters with
+/// lowercase names.
+pub(super) fn parse_challenges(header: &str) -> Vec<(&str, Vec<(String, String)>)> {
+    const WS: [char; 2] = [' ', '\t'];
+
+    let mut challenges: Vec<(&str, Vec<(String, String)>)> = Vec::new();
+    let mut rest = header;
+    loop {
+        rest = rest.trim_start_matches([' ', '\t', ',']);
+        let end = rest.find([' ', '\t', ',', '=']).unwrap_or(rest.len());
+        let (token, after) = rest.split_at(end);
+        if token.is_empty() {
+            break;
+        }
+
+        let Some(value) = after.trim_start_matches(WS).strip_prefix('=') else {
+            challenges.push((token, Vec::new()));
+            rest = after;
+            continue;
+        };
+
+        let value = value.trim_start_matches(WS);
+        let (value, after) = match value.strip_prefix('"') {
+            Some(quoted) => unquote(quoted),
+            None => {
+                let end = value.find([' ', '\t', ',']).unwrap_or(value.len());
+                (value[..end].to_owned(), &value[end..])
+            }
+        };
+
+        if let Some((_, params)) = challenges.last_mut() {
+            params.push((token.to_ascii_lowercase(), value));
+        }
+        rest = after;
+    }
+    challenges
+}
+
+/// Unescapes a quoted string, returning it and the rest after the closing quote.
+fn unquote(quoted: &str) -> (String, &str) {
+    let mut value = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return (value, &quoted[i + 1..]),
+            '\\' => value.extend(chars.next().map(|(_, c)| c)),
+            c => value.push(c),
+        }
+    }
+    (value, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_challenge_headers() {
+        let headers = [
+            HeaderValue::from_static("Negotiate, NTLM"),
+            HeaderValue::from_static(
+                r#"Digest realm="d", nonce="abc", Basic realm="proxy \"one\"", charset=UTF-8"#,
+            ),
+        ];
+
+        let challenge = ParsedChallenge::parse(&headers).unwrap();
+        assert_eq!(challenge.scheme(), AuthScheme::Basic);
+        assert_eq!(challenge.realm(), Some(r#"proxy "one""#));
+        assert_eq!(challenge.param("Charset"), Some("UTF-8"));
+        assert_eq!(challenge.param("nonce"), None);
+
+        let unsupported = [HeaderValue::from_static("Negotiate abc==")];
+        assert_eq!(ParsedChallenge::parse(&unsupported), None);
+    }
+}
@@ -0,0 +1,277 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    hash::{Hash, Hasher},
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "socks")]
+use bytes::Bytes;
+use http::{
+    Extensions, HeaderValue, Uri,
+    uri::{Authority, Scheme},
+};
+
+use super::DigestAuth;
+use crate::sync::Mutex;
+
+/// Alias for the `Future` type returned by a [`ProxyCredentials`] provider.
+pub type ProxyCredentialsFuture = Pin<Box<dyn Future<Output = Option<(String, String)>> + Send>>;
+
+/// Provides the credentials sent to a proxy, for proxies issuing short-lived tokens.
+///
+/// See [`Proxy::credentials_provider`](crate::Proxy::credentials_provider).
+pub trait ProxyCredentials: Send + Sync {
+    /// Returns the username and password sent to the proxy with a request to `dst`, or `None`
+    /// to send the static authorization of the proxy, if any.
+    fn credentials(&self, dst: &Uri) -> ProxyCredentialsFuture;
+
+    /// Returns how long the credentials returned for a destination are reused, before the
+    /// provider is called again.
+    ///
+    /// The default, `None`, reuses them until the proxy rejects them.
+    fn ttl(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// The credentials given by a [`ProxyCredentials`] provider, cached per destination origin.
+#[derive(Clone)]
+pub(crate) struct CredentialsCache {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    provider: Arc<dyn ProxyCredentials>,
+    cached: Mutex<HashMap<(Option<Scheme>, Option<Authority>), Cached>>,
+}
+
+struct Cached {
+    header: HeaderValue,
+    expires: Option<Instant>,
+}
+
+impl CredentialsCache {
+    pub(crate) fn new(provider: Arc<dyn ProxyCredentials>) -> CredentialsCache {
+        CredentialsCache {
+            inner: Arc::new(Inner {
+                provider,
+                cached: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Returns the `Proxy-Authorization` header of a request to `dst`, from the cache while it
+    /// is fresh.
+    pub(crate) async fn header(&self, dst: &Uri) -> Option<HeaderValue> {
+        let now = Instant::now();
+        if let Some(cached) = self.inner.cached.lock().get(&origin(dst)) {
+            if cached.expires.is_none_or(|expires| expires > now) {
+                return Some(cached.header.clone());
+            }
+        }
+        self.fetch(dst).await
+    }
+
+    /// Calls the provider again once the proxy rejected the `rejected` header, returning the
+    /// header to retry the request with if it differs.
+    pub(crate) async fn refresh(
+        &self,
+        dst: &Uri,
+        rejected: Option<&HeaderValue>,
+    ) -> Option<HeaderValue> {
+        {
+            let mut cached = self.inner.cached.lock();
+            match cached.get(&origin(dst)) {
+                // the credentials were refreshed since the request was sent
+                Some(entry) if Some(&entry.header) != rejected => {
+                    return Some(entry.header.clone());
+                }
+                Some(_) => {
+                    cached.remove(&origin(dst));
+                }
+                None => {}
+            }
+        }
+
+        self.fetch(dst)
+            .await
+            .filter(|header| Some(header) != rejected)
+    }
+
+    async fn fetch(&self, dst: &Uri) -> Option<HeaderValue> {
+        let (username, password) = self.inner.provider.credentials(dst).await?;
+        let header = super::encode_basic_auth(&username, &password);
+        let expires = self
+            .inner
+            .provider
+            .ttl()
+            .and_then(|ttl| Instant::now().checked_add(ttl));
+
+        self.inner.cached.lock().insert(
+            origin(dst),
+            Cached {
+                header: header.clone(),
+                expires,
+            },
+        );
+        Some(header)
+    }
+}
+
+impl PartialEq for CredentialsCache {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Eq for CredentialsCache {}
+
+impl Hash for CredentialsCache {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::ptr::hash(Arc::as_ptr(&self.inner), state);
+    }
+}
+
+/// The function of a [`Proxy::auth_template`](crate::Proxy::auth_template), giving the
+/// credentials sent to the proxy with each request.
+#[derive(Clone)]
+pub(crate) struct AuthTemplate {
+    inner: Arc<dyn Fn(&Uri, &Extensions) -> (String, String) + Send + Sync>,
+}
+
+/// The credentials an [`AuthTemplate`] gave for a request, and the proxy they are sent to.
+///
+/// They are part of the pool key of the connection of the request, so that tunnels opened
+/// with other credentials are never shared.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) struct TemplatedAuth {
+    proxy: Uri,
+    username: String,
+    password: String,
+}
+
+impl AuthTemplate {
+    pub(crate) fn new(
+        template: Arc<dyn Fn(&Uri, &Extensions) -> (String, String) + Send + Sync>,
+    ) -> AuthTemplate {
+        AuthTemplate { inner: template }
+    }
+
+    /// Returns the credentials sent to `proxy` with a request to `dst` with `extensions`.
+    pub(crate) fn evaluate(
+        &self,
+        proxy: &Uri,
+        dst: &Uri,
+        extensions: &Extensions,
+    ) -> TemplatedAuth {
+        let (username, password) = (self.inner)(dst, extensions);
+        TemplatedAuth {
+            proxy: proxy.clone(),
+            username,
+            password,
+        }
+    }
+}
+
+impl PartialEq for AuthTemplate {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Eq for AuthTemplate {}
+
+impl Hash for AuthTemplate {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::ptr::hash(Arc::as_ptr(&self.inner).cast::<()>(), state);
+    }
+}
+
+impl TemplatedAuth {
+    /// Returns whether the credentials are sent to `proxy`.
+    pub(crate) fn is_for(&self, proxy: &Uri) -> bool {
+        self.proxy == *proxy
+    }
+
+    /// Returns the `Proxy-Authorization` header sending the credentials with Basic auth.
+    pub(crate) fn header(&self) -> HeaderValue {
+        super::encode_basic_auth(&self.username, &self.password)
+    }
+
+    /// Returns the credentials answering the `Digest` challenges of the proxy.
+    pub(crate) fn digest_auth(&self) -> DigestAuth {
+        DigestAuth::new(self.username.clone(), self.password.clone())
+    }
+
+    /// Returns the username and password of the SOCKS handshake.
+    #[cfg(feature = "socks")]
+    pub(crate) fn raw(&self) -> (Bytes, Bytes) {
+        (
+            Bytes::from(self.username.clone()),
+            Bytes::from(self.password.clone()),
+        )
+    }
+}
+
+impl fmt::Debug for TemplatedAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TemplatedAuth")
+            .field("proxy", &self.proxy)
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+fn origin(dst: &Uri) -> (Option<Scheme>, Option<Authority>) {
+    (dst.scheme().cloned(), dst.authority().cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    struct Counting(AtomicUsize);
+
+    impl ProxyCredentials for Counting {
+        fn credentials(&self, _dst: &Uri) -> ProxyCredentialsFuture {
+            let n = self.0.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move { Some(("user".to_owned(), format!("token-{n}"))) })
+        }
+    }
+
+    #[tokio::test]
+    async fn credentials_are_cached_until_rejected() {
+        let provider = Arc::new(Counting(AtomicUsize::new(0)));
+        let cache = CredentialsCache::new(provider.clone());
+        let dst: Uri = "http://hyper.rs/a".parse().unwrap();
+
+        let first = cache.header(&dst).await.unwrap();
+        let again = cache
+            .header(&"http://hyper.rs/b".parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(first, again);
+        assert_eq!(provider.0.load(Ordering::SeqCst), 1);
+
+        // another destination has credentials of its own
+        cache
+            .header(&"https://hyper.rs/".parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(provider.0.load(Ordering::SeqCst), 2);
+
+        let refreshed = cache.refresh(&dst, Some(&first)).await.unwrap();
+        assert_ne!(refreshed, first);
+        assert_eq!(cache.header(&dst).await, Some(refreshed.clone()));
+
+        // a request rejected with stale credentials retries with the refreshed ones
+        assert_eq!(cache.refresh(&dst, Some(&first)).await, Some(refreshed));
+        assert_eq!(provider.0.load(Ordering::SeqCst), 3);
+    }
+}
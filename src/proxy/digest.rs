@@ -0,0 +1,316 @@
+//! Answering the `Digest` challenges of proxies, as defined by RFC 7616.
+
+use std::fmt::Write;
+
+#[cfg(feature = "tls")]
+use boring2::hash::{MessageDigest, hash};
+use http::HeaderValue;
+#[cfg(not(feature = "tls"))]
+use sha2::Digest;
+
+use super::auth::parse_challenges;
+use crate::util::fast_random;
+
+/// The credentials of a proxy answering its `Digest` challenges to a request.
+///
+/// A challenge is answered once, and once more if the proxy found the nonce of the answer
+/// stale. Each answer is the first to its nonce, so its nonce count is always `00000001`.
+pub(crate) struct DigestAuth {
+    username: String,
+    password: String,
+    answered: u8,
+}
+
+/// A `Digest` challenge of a supported algorithm and quality of protection.
+#[derive(Debug, PartialEq, Eq)]
+struct Challenge {
+    algorithm: Algorithm,
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    /// Whether the `auth` quality of protection was offered, rather than none.
+    qop: bool,
+    stale: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    Md5,
+    Md5Sess,
+    Sha256,
+    Sha256Sess,
+}
+
+impl DigestAuth {
+    pub(crate) fn new(username: String, password: String) -> DigestAuth {
+        DigestAuth {
+            username,
+            password,
+            answered: 0,
+        }
+    }
+
+    /// Returns the `Proxy-Authorization` header answering the `Digest` challenge of
+    /// `challenges` for a request of `method` to `uri`, or `None` if there is none to answer.
+    pub(crate) fn answer<'a, I>(
+        &mut self,
+        challenges: I,
+        method: &str,
+        uri: &str,
+    ) -> Option<HeaderValue>
+    where
+        I: IntoIterator<Item = &'a HeaderValue>,
+    {
+        let challenge = Challenge::parse(challenges)?;
+        // the credentials were rejected, unless only the nonce was
+        if self.answered > 1 || (self.answered == 1 && !challenge.stale) {
+            return None;
+        }
+        self.answered += 1;
+
+        let cnonce = format!("{:016x}{:016x}", fast_random(), fast_random());
+        self.header(&challenge, method, uri, &cnonce)
+    }
+
+    fn header(
+        &self,
+        challenge: &Challenge,
+        method: &str,
+        uri: &str,
+        cnonce: &str,
+    ) -> Option<HeaderValue> {
+        const NC: &str = "00000001";
+
+        let algorithm = challenge.algorithm;
+        let mut ha1 = algorithm.hash(&format!(
+            "{}:{}:{}",
+            self.username, challenge.realm, self.password
+        ));
+        if algorithm.is_sess() {
+            ha1 = algorithm.hash(&format!("{ha1}:{}:{cnonce}", challenge.nonce));
+        }
+        let ha2 = algorithm.hash(&format!("{method}:{uri}"));
+        let response = if challenge.qop {
+            algorithm.hash(&format!(
+                "{ha1}:{}:{NC}:{cnonce}:auth:{ha2}",
+                challenge.nonce
+            ))
+        } else {
+            algorithm.hash(&format!("{ha1}:{}:{ha2}", challenge.nonce))
+        };
+
+        let mut header = format!(
+            "Digest username={}, realm={}, nonce={}, uri={}, algorithm={}, response=\"{response}\"",
+            quote(&self.username),
+            quote(&challenge.realm),
+            quote(&challenge.nonce),
+            quote(uri),
+            algorithm.name(),
+        );
+        if challenge.qop {
+            let _ = write!(header, ", qop=auth, nc={NC}, cnonce=\"{cnonce}\"");
+        }
+        if let Some(ref opaque) = challenge.opaque {
+            let _ = write!(header, ", opaque={}", quote(opaque));
+        }
+
+        let mut header = HeaderValue::from_bytes(header.as_bytes()).ok()?;
+        header.set_sensitive(true);
+        Some(header)
+    }
+}
+
+impl Challenge {
+    /// Parses the `Digest` challenge to answer from `Proxy-Authenticate` headers: the first
+    /// with a SHA-256 algorithm, or else the first with MD5.
+    fn parse<'a, I>(headers: I) -> Option<Challenge>
+    where
+        I: IntoIterator<Item = &'a HeaderValue>,
+    {
+        headers
+            .into_iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(parse_challenges)
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("digest"))
+            .filter_map(|(_, params)| {
+                let param = |name: &str| {
+                    params
+                        .iter()
+                        .find(|(param, _)| param == name)
+                        .map(|(_, value)| value.as_str())
+                };
+
+                let algorithm = match param("algorithm") {
+                    None => Algorithm::Md5,
+                    Some(name) => Algorithm::from_name(name)?,
+                };
+                // only the `auth` quality of protection is supported, not `auth-int`
+                let qop = match param("qop") {
+                    None => false,
+                    Some(qop) => {
+                        if !qop.split(',').any(|qop| qop.trim() == "auth") {
+                            return None;
+                        }
+                        true
+                    }
+                };
+
+                Some(Challenge {
+                    algorithm,
+                    realm: param("realm")?.to_owned(),
+                    nonce: param("nonce")?.to_owned(),
+                    opaque: param("opaque").map(str::to_owned),
+                    qop,
+                    stale: param("stale").is_some_and(|stale| stale.eq_ignore_ascii_case("true")),
+                })
+            })
+            .min_by_key(|challenge| challenge.algorithm.is_md5())
+    }
+}
+
+impl Algorithm {
+    fn from_name(name: &str) -> Option<Algorithm> {
+        [
+            Algorithm::Md5,
+            Algorithm::Md5Sess,
+            Algorithm::Sha256,
+            Algorithm::Sha256Sess,
+        ]
+        .into_iter()
+        .find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "MD5",
+            Algorithm::Md5Sess => "MD5-sess",
+            Algorithm::Sha256 => "SHA-256",
+            Algorithm::Sha256Sess => "SHA-256-sess",
+        }
+    }
+
+    fn is_md5(self) -> bool {
+        matches!(self, Algorithm::Md5 | Algorithm::Md5Sess)
+    }
+
+    fn is_sess(self) -> bool {
+        matches!(self, Algorithm::Md5Sess | Algorithm::Sha256Sess)
+    }
+
+    /// Returns the lowercase hex digest of `data`.
+    fn hash(self, data: &str) -> String {
+        #[cfg(feature = "tls")]
+        let digest = {
+            let md = if self.is_md5() {
+                MessageDigest::md5()
+            } else {
+                MessageDigest::sha256()
+            };
+            hash(md, data.as_bytes()).expect("digest is supported")
+        };
+        #[cfg(not(feature = "tls"))]
+        let digest = if self.is_md5() {
+            md5::Md5::digest(data).to_vec()
+        } else {
+            sha2::Sha256::digest(data).to_vec()
+        };
+        digest.iter().fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+    }
+}
+
+/// Returns `value` as a quoted string.
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the example of RFC 7616, section 3.9.1
+    const CHALLENGE: &str = r#"realm="http-auth@example.org", qop="auth, auth-int", nonce="7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS""#;
+    const CNONCE: &str = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+
+    fn response(header: &HeaderValue) -> &str {
+        let header = header.to_str().unwrap();
+        let start = header.find("response=\"").unwrap() + "response=\"".len();
+        &header[start..start + header[start..].find('"').unwrap()]
+    }
+
+    #[test]
+    fn answers_rfc_7616_examples() {
+        let auth = DigestAuth::new("Mufasa".to_owned(), "Circle of Life".to_owned());
+        let headers = [
+            HeaderValue::from_str(&format!("Digest {CHALLENGE}, algorithm=MD5")).unwrap(),
+            HeaderValue::from_str(&format!("Digest {CHALLENGE}, algorithm=SHA-256")).unwrap(),
+        ];
+
+        let challenge = Challenge::parse(&headers).unwrap();
+        assert_eq!(challenge.algorithm, Algorithm::Sha256);
+        let header = auth
+            .header(&challenge, "GET", "/dir/index.html", CNONCE)
+            .unwrap();
+        assert_eq!(
+            response(&header),
+            "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1"
+        );
+
+        let challenge = Challenge::parse(&headers[..1]).unwrap();
+        assert_eq!(challenge.algorithm, Algorithm::Md5);
+        let header = auth
+            .header(&challenge, "GET", "/dir/index.html", CNONCE)
+            .unwrap();
+        assert_eq!(response(&header), "8ca523f5e9506fed4657c9700eebdbec");
+        assert!(header.is_sensitive());
+        assert_eq!(
+            header.to_str().unwrap(),
+            "Digest username=\"Mufasa\", realm=\"http-auth@example.org\", \
+             nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", uri=\"/dir/index.html\", \
+             algorithm=MD5, response=\"8ca523f5e9506fed4657c9700eebdbec\", qop=auth, \
+             nc=00000001, cnonce=\"f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ\", \
+             opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\""
+        );
+    }
+
+    #[test]
+    fn answers_stale_challenges_once_more() {
+        let mut auth = DigestAuth::new("user".to_owned(), "pass".to_owned());
+        let fresh = [HeaderValue::from_static(
+            r#"Digest realm="r", nonce="a", qop="auth""#,
+        )];
+        let stale = [HeaderValue::from_static(
+            r#"Digest realm="r", nonce="b", qop="auth", stale=true"#,
+        )];
+
+        assert!(auth.answer(&fresh, "CONNECT", "hyper.rs:443").is_some());
+        assert!(auth.answer(&stale, "CONNECT", "hyper.rs:443").is_some());
+        assert!(auth.answer(&stale, "CONNECT", "hyper.rs:443").is_none());
+
+        // rejected credentials are not sent again
+        let mut auth = DigestAuth::new("user".to_owned(), "pass".to_owned());
+        assert!(auth.answer(&fresh, "CONNECT", "hyper.rs:443").is_some());
+        assert!(auth.answer(&fresh, "CONNECT", "hyper.rs:443").is_none());
+    }
+
+    #[test]
+    fn skips_unsupported_challenges() {
+        let headers = [
+            HeaderValue::from_static(r#"Basic realm="r""#),
+            HeaderValue::from_static(r#"Digest realm="r", nonce="a", algorithm=SHA-512-256"#),
+            HeaderValue::from_static(r#"Digest realm="r", nonce="a", qop="auth-int""#),
+        ];
+        assert_eq!(Challenge::parse(&headers), None);
+    }
+}
@@ -0,0 +1,407 @@
+//! Latency accounting used to pick the fastest proxy.
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use http::Uri;
+
+use crate::{sync::Mutex, util::fast_random};
+
+/// Time constant of the moving average.
+///
+/// A sample recorded this long ago weighs ~37% against a fresh one.
+const DECAY: Duration = Duration::from_secs(10);
+
+/// Estimates that have not been refreshed for this long are forgotten,
+/// so that a proxy which was slow or failing is probed again eventually.
+const STALE_AFTER: Duration = Duration::from_secs(60);
+
+/// Sample recorded when establishing a connection through a proxy fails.
+const FAILURE_PENALTY: Duration = Duration::from_secs(30);
+
+/// Strategy used to choose between several proxies intercepting the same request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProxyStrategy {
+    /// Use the first proxy, in the order they were added, that intercepts the request.
+    #[default]
+    First,
+
+    /// Sample two of the `n` lowest-latency proxies that intercept the request, and use
+    /// the faster of the two.
+    ///
+    /// The latency of a proxy is the time it takes to establish a tunnel (HTTP `CONNECT` or
+    /// SOCKS) through it, plus the time to first byte of the responses received through it,
+    /// each smoothed with an exponentially weighted moving average. Proxies that have not
+    /// been measured yet are tried first, and failures count as a very slow sample.
+    ///
+    /// The proxy of a plain `http://` request is chosen before its proxy headers are
+    /// applied, and only connections through that proxy are reused for it.
+    ///
+    /// The estimates are reported by
+    /// [`Client::proxy_latency_stats`](crate::Client::proxy_latency_stats).
+    FastestOf(usize),
+}
+
+/// The latency estimates of a proxy, see
+/// [`Client::proxy_latency_stats`](crate::Client::proxy_latency_stats).
+#[derive(Debug, Clone)]
+pub struct ProxyLatencyStats {
+    proxy: Uri,
+    tunnel: Option<Duration>,
+    ttfb: Option<Duration>,
+    tunnels: u64,
+    failures: u64,
+    responses: u64,
+}
+
+#[derive(Clone, Copy)]
+struct Ewma {
+    nanos: f64,
+    updated: Instant,
+}
+
+/// The moving averages of a proxy, and the number of samples they were fed.
+#[derive(Default)]
+struct Estimates {
+    tunnel: Option<Ewma>,
+    ttfb: Option<Ewma>,
+    tunnels: u64,
+    failures: u64,
+    responses: u64,
+}
+
+/// Per-proxy moving averages of tunnel establishment time and time to first byte.
+#[derive(Default)]
+pub(crate) struct ProxyLatency {
+    inner: Mutex<HashMap<Uri, Estimates>>,
+}
+
+/// Response extension recording the time to first byte of a response received through a
+/// proxy whose latency is tracked.
+#[derive(Clone)]
+pub(crate) struct ProxyTtfb {
+    proxy: Uri,
+    latency: Arc<ProxyLatency>,
+}
+
+// ===== impl ProxyLatencyStats =====
+
+impl ProxyLatencyStats {
+    /// Returns the URI of the proxy.
+    pub fn proxy(&self) -> &Uri {
+        &self.proxy
+    }
+
+    /// Returns the estimated time to establish a tunnel through the proxy, if one was
+    /// recently established or failed.
+    pub fn tunnel(&self) -> Option<Duration> {
+        self.tunnel
+    }
+
+    /// Returns the estimated time to first byte of the responses received through the
+    /// proxy, if one was recently received.
+    pub fn ttfb(&self) -> Option<Duration> {
+        self.ttfb
+    }
+
+    /// Returns how many tunnels were established through the proxy.
+    pub fn tunnels(&self) -> u64 {
+        self.tunnels
+    }
+
+    /// Returns how many times establishing a tunnel through the proxy failed.
+    pub fn failures(&self) -> u64 {
+        self.failures
+    }
+
+    /// Returns how many responses were received through the proxy.
+    pub fn responses(&self) -> u64 {
+        self.responses
+    }
+}
+
+// ===== impl Ewma =====
+
+impl Ewma {
+    fn new(sample: Duration, now: Instant) -> Ewma {
+        Ewma {
+            nanos: sample.as_nanos() as f64,
+            updated: now,
+        }
+    }
+
+    fn update(&mut self, sample: Duration, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated);
+        let weight = (-elapsed.as_secs_f64() / DECAY.as_secs_f64()).exp();
+        self.nanos = self.nanos * weight + sample.as_nanos() as f64 * (1.0 - weight);
+        self.updated = now;
+    }
+
+    fn estimate(&self, now: Instant) -> Option<f64> {
+        (now.saturating_duration_since(self.updated) < STALE_AFTER).then_some(self.nanos)
+    }
+
+    /// Feeds `sample` to the average in `slot`, starting it if there is none.
+    fn record(slot: &mut Option<Ewma>, sample: Duration, now: Instant) {
+        match slot {
+            Some(ewma) => ewma.update(sample, now),
+            None => *slot = Some(Ewma::new(sample, now)),
+        }
+    }
+}
+
+// ===== impl Estimates =====
+
+impl Estimates {
+    /// Returns the latency of the proxy, or `None` if it was not measured recently.
+    fn estimate(&self, now: Instant) -> Option<f64> {
+        let tunnel = self.tunnel.and_then(|ewma| ewma.estimate(now));
+        let ttfb = self.ttfb.and_then(|ewma| ewma.estimate(now));
+        match (tunnel, ttfb) {
+            (None, None) => None,
+            (tunnel, ttfb) => Some(tunnel.unwrap_or(0.0) + ttfb.unwrap_or(0.0)),
+        }
+    }
+}
+
+// ===== impl ProxyLatency =====
+
+impl ProxyLatency {
+    /// Records the time it took to establish a connection through `proxy`.
+    pub(crate) fn record(&self, proxy: &Uri, elapsed: Duration) {
+        let now = Instant::now();
+        let mut latencies = self.inner.lock();
+        let estimates = latencies.entry(proxy.clone()).or_default();
+        Ewma::record(&mut estimates.tunnel, elapsed, now);
+        estimates.tunnels += 1;
+    }
+
+    /// Records a failure to establish a connection through `proxy`.
+    pub(crate) fn record_failure(&self, proxy: &Uri) {
+        let now = Instant::now();
+        let mut latencies = self.inner.lock();
+        let estimates = latencies.entry(proxy.clone()).or_default();
+        Ewma::record(&mut estimates.tunnel, FAILURE_PENALTY, now);
+        estimates.failures += 1;
+    }
+
+    /// Records the time to first byte of a response received through `proxy`.
+    pub(crate) fn record_ttfb(&self, proxy: &Uri, elapsed: Duration) {
+        let now = Instant::now();
+        let mut latencies = self.inner.lock();
+        let estimates = latencies.entry(proxy.clone()).or_default();
+        Ewma::record(&mut estimates.ttfb, elapsed, now);
+        estimates.responses += 1;
+    }
+
+    /// Returns the estimates of every proxy measured since the client was built, ordered by
+    /// proxy.
+    pub(crate) fn stats(&self) -> Vec<ProxyLatencyStats> {
+        let now = Instant::now();
+        let nanos = |ewma: Option<Ewma>| {
+            ewma.and_then(|ewma| ewma.estimate(now))
+                .map(|nanos| Duration::from_nanos(nanos as u64))
+        };
+
+        let mut stats = self
+            .inner
+            .lock()
+            .iter()
+            .map(|(proxy, estimates)| ProxyLatencyStats {
+                proxy: proxy.clone(),
+                tunnel: nanos(estimates.tunnel),
+                ttfb: nanos(estimates.ttfb),
+                tunnels: estimates.tunnels,
+                failures: estimates.failures,
+                responses: estimates.responses,
+            })
+            .collect::<Vec<_>>();
+        stats.sort_by(|a, b| a.proxy.to_string().cmp(&b.proxy.to_string()));
+        stats
+    }
+
+    /// Orders the candidates from the lowest to the highest estimated latency, unmeasured
+    /// proxies first.
+    pub(crate) fn sort<T, F>(&self, candidates: &mut [T], uri: F)
+    where
+        F: Fn(&T) -> &Uri,
+    {
+        let now = Instant::now();
+        let latencies = self.inner.lock();
+        candidates.sort_by_cached_key(|candidate| {
+            latencies
+                .get(uri(candidate))
+                .and_then(|estimates| estimates.estimate(now))
+                .map_or(0, |nanos| nanos as u64)
+        });
+    }
+
+    /// Picks one of the candidates using power-of-two-choices among the `n` fastest.
+    pub(crate) fn select<T, F>(&self, mut candidates: Vec<T>, n: usize, uri: F) -> Option<T>
+    where
+        F: Fn(&T) -> &Uri,
+    {
+        if candidates.len() <= 1 {
+            return candidates.pop();
+        }
+
+        let now = Instant::now();
+        let estimate = {
+            let latencies = self.inner.lock();
+            move |candidate: &T| {
+                latencies
+                    .get(uri(candidate))
+                    .and_then(|estimates| estimates.estimate(now))
+                    // unmeasured proxies are probed first
+                    .unwrap_or(0.0)
+            }
+        };
+
+        candidates.sort_by(|a, b| estimate(a).total_cmp(&estimate(b)));
+        candidates.truncate(n.max(1));
+
+        let len = candidates.len();
+        if len == 1 {
+            return candidates.pop();
+        }
+
+        let first = fast_random() as usize % len;
+        let second = (first + 1 + fast_random() as usize % (len - 1)) % len;
+        let pick = if estimate(&candidates[second]) < estimate(&candidates[first]) {
+            second
+        } else {
+            first
+        };
+
+        Some(candidates.swap_remove(pick))
+    }
+}
+
+// ===== impl ProxyTtfb =====
+
+impl ProxyTtfb {
+    pub(crate) fn new(proxy: Uri, latency: Arc<ProxyLatency>) -> ProxyTtfb {
+        ProxyTtfb { proxy, latency }
+    }
+
+    /// Records the time to first byte of the response.
+    pub(crate) fn record(&self, elapsed: Duration) {
+        self.latency.record_ttfb(&self.proxy, elapsed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_converges_to_fastest_proxy() {
+        let proxies = [
+            (
+                Uri::from_static("http://fast.proxy"),
+                Duration::from_millis(10),
+            ),
+            (
+                Uri::from_static("http://medium.proxy"),
+                Duration::from_millis(50),
+            ),
+            (
+                Uri::from_static("http://slow.proxy"),
+                Duration::from_millis(200),
+            ),
+        ];
+
+        let latency = ProxyLatency::default();
+        let mut picks = [0usize; 3];
+
+        for _ in 0..300 {
+            let candidates = (0..proxies.len()).collect();
+            let pick = latency
+                .select(candidates, 3, |idx: &usize| &proxies[*idx].0)
+                .unwrap();
+
+            picks[pick] += 1;
+            latency.record(&proxies[pick].0, proxies[pick].1);
+        }
+
+        assert!(picks[0] > 150, "fastest proxy picked {} times", picks[0]);
+        assert!(picks[0] > picks[1], "{picks:?}");
+        // only probed until it has been measured once
+        assert!(picks[2] <= 2, "slowest proxy picked {} times", picks[2]);
+    }
+
+    #[test]
+    fn select_restricts_to_fastest_n() {
+        let fast = Uri::from_static("http://fast.proxy");
+        let slow = Uri::from_static("http://slow.proxy");
+
+        let latency = ProxyLatency::default();
+        latency.record(&fast, Duration::from_millis(10));
+        latency.record(&slow, Duration::from_millis(200));
+
+        for _ in 0..100 {
+            let pick = latency
+                .select(vec![&slow, &fast], 1, |uri: &&Uri| *uri)
+                .unwrap();
+            assert_eq!(pick, &fast);
+        }
+    }
+
+    #[test]
+    fn failure_is_penalized() {
+        let good = Uri::from_static("http://good.proxy");
+        let bad = Uri::from_static("http://bad.proxy");
+
+        let latency = ProxyLatency::default();
+        latency.record(&good, Duration::from_millis(100));
+        latency.record_failure(&bad);
+
+        let pick = latency.select(vec![&bad, &good], 2, |uri: &&Uri| *uri);
+        assert_eq!(pick, Some(&good));
+    }
+
+    #[test]
+    fn ttfb_counts_toward_latency() {
+        let fast = Uri::from_static("http://fast.proxy");
+        let slow = Uri::from_static("http://slow.proxy");
+
+        let latency = ProxyLatency::default();
+        latency.record(&fast, Duration::from_millis(20));
+        latency.record(&slow, Duration::from_millis(10));
+        latency.record_ttfb(&fast, Duration::from_millis(50));
+        latency.record_ttfb(&slow, Duration::from_millis(500));
+
+        let pick = latency.select(vec![&slow, &fast], 2, |uri: &&Uri| *uri);
+        assert_eq!(pick, Some(&fast));
+    }
+
+    #[test]
+    fn stats_report_estimates() {
+        let good = Uri::from_static("http://good.proxy");
+        let bad = Uri::from_static("http://bad.proxy");
+
+        let latency = ProxyLatency::default();
+        latency.record(&good, Duration::from_millis(100));
+        latency.record_ttfb(&good, Duration::from_millis(40));
+        latency.record_failure(&bad);
+
+        let stats = latency.stats();
+        assert_eq!(stats.len(), 2);
+
+        assert_eq!(stats[0].proxy(), &bad);
+        assert_eq!(stats[0].tunnel(), Some(FAILURE_PENALTY));
+        assert_eq!(stats[0].ttfb(), None);
+        assert_eq!(stats[0].failures(), 1);
+        assert_eq!(stats[0].tunnels(), 0);
+
+        assert_eq!(stats[1].proxy(), &good);
+        assert_eq!(stats[1].tunnel(), Some(Duration::from_millis(100)));
+        assert_eq!(stats[1].ttfb(), Some(Duration::from_millis(40)));
+        assert_eq!(stats[1].tunnels(), 1);
+        assert_eq!(stats[1].responses(), 1);
+    }
+}
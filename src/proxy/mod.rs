@@ -0,0 +1,1139 @@
+mod auth;
+mod credentials;
+mod digest;
+mod latency;
+#[cfg(feature = "pac")]
+mod pac;
+mod race;
+mod selector;
+
+use std::{error::Error as StdError, fmt, sync::Arc};
+
+#[cfg(feature = "socks")]
+use bytes::Bytes;
+use http::{
+    Extensions, HeaderMap, Uri,
+    header::{HeaderName, HeaderValue},
+};
+
+use crate::{
+    Url,
+    core::client::proxy::matcher,
+    error::{BadScheme, Error},
+    into_url::{IntoUrl, IntoUrlSealed},
+};
+
+pub(crate) use self::auth::ProxyAuth;
+pub use self::auth::{AuthScheme, Credentials, ParsedChallenge, ProxyAuthFuture, ProxyAuthHandler};
+pub(crate) use self::credentials::{AuthTemplate, CredentialsCache, TemplatedAuth};
+pub use self::credentials::{ProxyCredentials, ProxyCredentialsFuture};
+pub(crate) use self::digest::DigestAuth;
+pub(crate) use self::latency::{ProxyLatency, ProxyTtfb};
+pub use self::latency::{ProxyLatencyStats, ProxyStrategy};
+#[cfg(feature = "pac")]
+use self::pac::{Pac, PacConfig, PacRoute};
+pub(crate) use self::race::ProxyRace;
+pub use self::selector::ProxySelector;
+
+// # Internals
+//
+// This module is a couple pieces:
+//
+// - The public builder API
+// - The internal built types that our Connector knows how to use.
+//
+// The user creates a builder (`wreq::Proxy`), and configures any extras.
+// Once that type is passed to the `ClientBuilder`, we convert it into the
+// built matcher types, making use of `hyper-util`'s matchers.
+
+/// Configuration of a proxy that a `Client` should pass requests to.
+///
+/// A `Proxy` has a couple pieces to it:
+///
+/// - a URL of how to talk to the proxy
+/// - rules on what `Client` requests should be directed to the proxy
+///
+/// For instance, let's look at `Proxy::http`:
+///
+/// ```rust
+/// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let proxy = wreq::Proxy::http("https://secure.example")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// This proxy will intercept all HTTP requests, and make use of the proxy
+/// at `https://secure.example`. A request to `http://hyper.rs` will talk
+/// to your proxy. A request to `https://hyper.rs` will not.
+///
+/// Multiple `Proxy` rules can be configured for a `Client`. The `Client` will
+/// check each `Proxy` in the order it was added. This could mean that a
+/// `Proxy` added first with eager intercept rules, such as `Proxy::all`,
+/// would prevent a `Proxy` later in the list from ever working, so take care.
+///
+/// By enabling the `"socks"` feature it is possible to use a socks proxy:
+/// ```rust
+/// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let proxy = wreq::Proxy::http("socks5://192.168.1.1:9000")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// The `socks4s`, `socks4as`, `socks5s` and `socks5hs` schemes connect to the socks proxy
+/// over TLS, verifying its host name, before the socks handshake.
+///
+/// On unix platforms, a proxy listening on a unix socket is given by the path of the socket:
+/// `unix:///var/run/proxy.sock` for an HTTP proxy, and `socks5+unix:///var/run/proxy.sock` or
+/// `socks5h+unix:///var/run/proxy.sock` for a socks5 proxy. Such a URL has no user info, so the
+/// credentials of the proxy are set with [`Proxy::basic_auth`]. The proxy is reported with the
+/// hex-encoded path as its host, as in [`Response::proxy_used`](crate::Response::proxy_used).
+/// Creating one fails on other platforms.
+///
+/// By enabling the `"pac"` feature, the proxy of each request can be chosen by a
+/// proxy auto-config script, see [`Proxy::pac`].
+#[derive(Clone)]
+pub struct Proxy {
+    extra: Extra,
+    intercept: Intercept,
+    no_proxy: Option<NoProxy>,
+}
+
+/// A configuration for filtering out requests that shouldn't be proxied
+#[derive(Clone, Debug, Default)]
+pub struct NoProxy {
+    inner: String,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+struct Extra {
+    auth: Option<HeaderValue>,
+    misc: Option<HeaderMap>,
+    credentials: Option<CredentialsCache>,
+    template: Option<AuthTemplate>,
+    /// The proxies tunneled through, in order, to reach the proxy of a chain.
+    chain: Option<Arc<[Intercepted]>>,
+}
+
+impl std::hash::Hash for Extra {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        // Hash the auth header value bytes if present
+        if let Some(ref auth) = self.auth {
+            state.write(auth.as_bytes());
+        } else {
+            state.write_u8(0);
+        }
+
+        // Hash the misc headers by name and value bytes, in sorted order for determinism
+        if let Some(ref misc) = self.misc {
+            let mut items: Vec<_> = misc.iter().collect();
+            items.sort_by_key(|(k, _)| k.as_str());
+            for (k, v) in items {
+                state.write(k.as_str().as_bytes());
+                state.write(v.as_bytes());
+            }
+        } else {
+            state.write_u8(0);
+        }
+
+        self.credentials.hash(state);
+        self.template.hash(state);
+        self.chain.hash(state);
+    }
+}
+
+// ===== Internal =====
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Matcher {
+    inner: Box<matcher::Matcher>,
+    extra: Extra,
+    maybe_has_http_auth: bool,
+    maybe_has_http_custom_headers: bool,
+    #[cfg(feature = "pac")]
+    pac: Option<Pac>,
+}
+
+/// Our own type, wrapping an `Intercept`, since we may have a few additional
+/// pieces attached thanks to `wreq`s extra proxy configuration.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Intercepted {
+    inner: matcher::Intercept,
+    /// This is because of `wreq::Proxy`'s design which allows configuring
+    /// an explicit auth, besides what might have been in the URL (or Custom).
+    extra: Extra,
+}
+
+/// Trait used for converting into a proxy scheme. This trait supports
+/// parsing from a URL-like type, whilst also supporting proxy schemes
+/// built directly using the factory methods.
+pub trait IntoProxy {
+    fn into_proxy(self) -> crate::Result<Url>;
+}
+
+impl<S: IntoUrl> IntoProxy for S {
+    fn into_proxy(self) -> crate::Result<Url> {
+        // the proxies listening on a unix socket have a path rather than a host
+        if let Ok(url) = Url::parse(self.as_str()) {
+            if matcher::is_unix_scheme(url.scheme()) {
+                return unix_proxy(url);
+            }
+        }
+
+        match self.as_str().into_url() {
+            Ok(ok) => Ok(ok),
+            Err(e) => {
+                let mut presumed_to_have_scheme = true;
+                let mut source = e.source();
+                while let Some(err) = source {
+                    if let Some(parse_error) = err.downcast_ref::<url::ParseError>() {
+                        if *parse_error == url::ParseError::RelativeUrlWithoutBase {
+                            presumed_to_have_scheme = false;
+                            break;
+                        }
+                    } else if err.downcast_ref::<BadScheme>().is_some() {
+                        presumed_to_have_scheme = false;
+                        break;
+                    }
+                    source = err.source();
+                }
+                if presumed_to_have_scheme {
+                    return Err(Error::builder(e));
+                }
+                // the issue could have been caused by a missing scheme, so we try adding http://
+                let try_this = format!("http://{}", self.as_str());
+                try_this.into_url().map_err(|_| {
+                    // return the original error
+                    Error::builder(e)
+                })
+            }
+        }
+    }
+}
+
+/// Checks the URL of a proxy listening on a unix socket, such as `unix:///var/run/proxy.sock`.
+fn unix_proxy(url: Url) -> crate::Result<Url> {
+    if !cfg!(unix) {
+        return Err(Error::builder(format!(
+            "unix socket proxy {url} is only supported on unix platforms"
+        )));
+    }
+    if url.has_host() || !url.path().starts_with('/') {
+        return Err(Error::builder(format!(
+            "unix socket proxy {url} must have the absolute path of the socket, as in \
+             unix:///var/run/proxy.sock"
+        )));
+    }
+    Ok(url)
+}
+
+// These bounds are accidentally leaked by the blanket impl of IntoProxy
+// for all types that implement IntoUrl. So, this function exists to detect
+// if we were to break those bounds for a user.
+fn _implied_bounds() {
+    fn prox<T: IntoProxy>(_t: T) {}
+
+    fn url<T: IntoUrl>(t: T) {
+        prox(t);
+    }
+}
+
+impl Proxy {
+    /// Proxy all HTTP traffic to the passed URL.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # extern crate wreq;
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = wreq::Client::builder()
+    ///     .proxy(wreq::Proxy::http("https://my.prox")?)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {}
+    /// ```
+    pub fn http<U: IntoProxy>(proxy_scheme: U) -> crate::Result<Proxy> {
+        Ok(Proxy::new(Intercept::Http(proxy_scheme.into_proxy()?)))
+    }
+
+    /// Proxy all HTTPS traffic to the passed URL.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # extern crate wreq;
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = wreq::Client::builder()
+    ///     .proxy(wreq::Proxy::https("https://example.prox:4545")?)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {}
+    /// ```
+    pub fn https<U: IntoProxy>(proxy_scheme: U) -> crate::Result<Proxy> {
+        Ok(Proxy::new(Intercept::Https(proxy_scheme.into_proxy()?)))
+    }
+
+    /// Proxy **all** traffic to the passed URL.
+    ///
+    /// "All" refers to `https` and `http` URLs. Other schemes are not
+    /// recognized by wreq.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # extern crate wreq;
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = wreq::Client::builder()
+    ///     .proxy(wreq::Proxy::all("http://pro.xy")?)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {}
+    /// ```
+    pub fn all<U: IntoProxy>(proxy_scheme: U) -> crate::Result<Proxy> {
+        Ok(Proxy::new(Intercept::All(proxy_scheme.into_proxy()?)))
+    }
+
+    /// Proxy **all** traffic through a chain of proxies, in order.
+    ///
+    /// Connections are made through the last proxy of the chain, reached through the proxies
+    /// before it: the first proxy is connected to and asked to open a tunnel to the second,
+    /// with a `CONNECT` request or a SOCKS handshake, the handshake of the second runs over
+    /// that tunnel, and so on. The connection to an `https` or TLS SOCKS proxy is made over
+    /// TLS within the tunnel. The last proxy always tunnels to the destination, even for
+    /// `http` destinations.
+    ///
+    /// Each proxy keeps its own authorization, credentials, auth template and custom headers,
+    /// while their interception rules and no-proxy lists are ignored: a chain intercepts every
+    /// request not excluded by its own [`no_proxy`](Proxy::no_proxy). Methods called on the
+    /// chain configure its last proxy. Connections are reported through their last proxy, and
+    /// errors name the proxy of the chain that failed.
+    ///
+    /// # Errors
+    ///
+    /// Fails with a builder error if the chain is empty, or if it has a PAC proxy, a proxy of
+    /// an unsupported scheme, or a proxy listening on a unix socket other than the first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # extern crate wreq;
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let chain = wreq::Proxy::chain([
+    ///     wreq::Proxy::all("http://entry.prox:8080")?.basic_auth("Aladdin", "open sesame"),
+    ///     wreq::Proxy::all("socks5h://exit.prox:1080")?,
+    /// ])?;
+    /// let client = wreq::Client::builder().proxy(chain).build()?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {}
+    /// ```
+    pub fn chain<I>(proxies: I) -> crate::Result<Proxy>
+    where
+        I: IntoIterator<Item = Proxy>,
+    {
+        let mut chain = Vec::new();
+        let mut last: Option<(Url, Extra)> = None;
+        for proxy in proxies {
+            let url = match proxy.intercept {
+                Intercept::All(url) | Intercept::Http(url) | Intercept::Https(url) => url,
+                #[cfg(feature = "pac")]
+                Intercept::Pac(_) => {
+                    return Err(Error::builder("a proxy chain cannot have a PAC proxy"));
+                }
+            };
+            if let Some((url, extra)) = last.take() {
+                chain.push(chained(url, extra)?);
+            }
+            // a chain in the chain is flattened
+            let mut extra = proxy.extra;
+            if let Some(chained) = extra.chain.take() {
+                chain.extend(chained.iter().cloned());
+            }
+            last = Some((url, extra));
+        }
+
+        let (url, mut extra) = last.ok_or_else(|| Error::builder("a proxy chain is empty"))?;
+        let schemes = chain
+            .iter()
+            .filter_map(|proxy| proxy.uri().scheme_str())
+            .chain([url.scheme()]);
+        if schemes.skip(1).any(matcher::is_unix_scheme) {
+            return Err(Error::builder(
+                "only the first proxy of a chain can listen on a unix socket",
+            ));
+        }
+        if !chain.is_empty() {
+            extra.chain = Some(chain.into());
+        }
+
+        Ok(Proxy {
+            extra,
+            intercept: Intercept::All(url),
+            no_proxy: None,
+        })
+    }
+
+    /// Proxy traffic as decided by the proxy auto-config (PAC) script at `source`.
+    ///
+    /// The source is a path or `file` URL, read when the proxy is created, or an `http` or
+    /// `https` URL, fetched when the first request is sent. The script is fetched by the
+    /// client, with its resolver, TLS settings and other proxies, following redirects, and
+    /// must not exceed 1 MiB. The `FindProxyForURL(url, host)` function of the script is
+    /// called with each destination, and the proxies it returns are tried in order until a
+    /// connection is established, `DIRECT` standing for a direct connection.
+    /// `PROXY`, `HTTP`, `HTTPS`, and with the `"socks"` feature `SOCKS` and `SOCKS5`
+    /// proxies are supported. The result is cached for each origin, see
+    /// [`Proxy::pac_cache_ttl`].
+    ///
+    /// Scripts are evaluated by a restricted interpreter, supporting the common subset of
+    /// JavaScript used by PAC scripts and the PAC functions, except the date and time ones.
+    ///
+    /// The authorization and headers configured on this proxy are only sent with `CONNECT`
+    /// requests, not with forwarded `http` requests.
+    ///
+    /// # Errors
+    ///
+    /// Fails with a builder error if the file cannot be read or parsed, or if the URL is not
+    /// an `http`, `https` or `file` URL.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # extern crate wreq;
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = wreq::Client::builder()
+    ///     .proxy(wreq::Proxy::pac("http://wpad.corp.example/wpad.dat")?)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {}
+    /// ```
+    #[cfg(feature = "pac")]
+    pub fn pac(source: &str) -> crate::Result<Proxy> {
+        let config = PacConfig::new(source).map_err(Error::builder)?;
+        Ok(Proxy::new(Intercept::Pac(config)))
+    }
+
+    /// Sets how long the proxies returned by a PAC script for an origin are reused, before
+    /// the script is called again. Defaults to 5 minutes.
+    ///
+    /// Has no effect on proxies not created with [`Proxy::pac`].
+    #[cfg(feature = "pac")]
+    pub fn pac_cache_ttl(mut self, ttl: std::time::Duration) -> Proxy {
+        if let Intercept::Pac(ref mut config) = self.intercept {
+            config.ttl(ttl);
+        }
+        self
+    }
+
+    fn new(intercept: Intercept) -> Proxy {
+        Proxy {
+            extra: Extra {
+                auth: None,
+                misc: None,
+                credentials: None,
+                template: None,
+                chain: None,
+            },
+            intercept,
+            no_proxy: None,
+        }
+    }
+
+    /// Set the `Proxy-Authorization` header using Basic auth.
+    ///
+    /// The credentials also answer the `Digest` challenges of the proxy, with the MD5 and
+    /// SHA-256 algorithms of RFC 7616, unless a
+    /// [`credentials_provider`](Proxy::credentials_provider) or a [`ProxyAuthHandler`] is
+    /// configured. The same holds for credentials in the proxy URL.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # extern crate wreq;
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let proxy = wreq::Proxy::https("http://localhost:1234")?.basic_auth("Aladdin", "open sesame");
+    /// # Ok(())
+    /// # }
+    /// # fn main() {}
+    /// ```
+    pub fn basic_auth(mut self, username: &str, password: &str) -> Proxy {
+        match self.intercept {
+            Intercept::All(ref mut s)
+            | Intercept::Http(ref mut s)
+            | Intercept::Https(ref mut s) => {
+                url_auth(s, username, password);
+                let header = encode_basic_auth(username, password);
+                self.extra.auth = Some(header);
+            }
+            #[cfg(feature = "pac")]
+            Intercept::Pac(_) => {
+                self.extra.auth = Some(encode_basic_auth(username, password));
+            }
+        }
+
+        self
+    }
+
+    /// Set the `Proxy-Authorization` header to a specified value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # extern crate wreq;
+    /// # use wreq::header::*;
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let proxy = wreq::Proxy::https("http://localhost:1234")?
+    ///     .custom_http_auth(HeaderValue::from_static("justletmeinalreadyplease"));
+    /// # Ok(())
+    /// # }
+    /// # fn main() {}
+    /// ```
+    pub fn custom_http_auth(mut self, header_value: HeaderValue) -> Proxy {
+        self.extra.auth = Some(header_value);
+        self
+    }
+
+    /// Set a provider of the credentials sent to the proxy, for proxies issuing short-lived
+    /// tokens.
+    ///
+    /// The provider is awaited for the credentials of each destination, which are sent with
+    /// Basic auth in the `Proxy-Authorization` header of forwarded `http` requests and of
+    /// `CONNECT` requests, in place of the static authorization of the proxy. They are reused
+    /// for the [`ttl`](ProxyCredentials::ttl) of the provider. When the proxy answers with
+    /// `407 Proxy Authentication Required`, the provider is called again and the request is
+    /// retried once with the new credentials.
+    ///
+    /// A forwarded `http` request is only retried if its body can be cloned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # extern crate wreq;
+    /// # use std::sync::Arc;
+    /// use http::Uri;
+    /// use wreq::{Proxy, ProxyCredentials, ProxyCredentialsFuture};
+    ///
+    /// struct Vendor;
+    ///
+    /// impl ProxyCredentials for Vendor {
+    ///     fn credentials(&self, _dst: &Uri) -> ProxyCredentialsFuture {
+    ///         Box::pin(async { Some(("customer".to_owned(), "fresh-token".to_owned())) })
+    ///     }
+    /// }
+    ///
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let proxy = Proxy::all("http://localhost:1234")?.credentials_provider(Arc::new(Vendor));
+    /// # Ok(())
+    /// # }
+    /// # fn main() {}
+    /// ```
+    pub fn credentials_provider(mut self, provider: Arc<dyn ProxyCredentials>) -> Proxy {
+        self.extra.credentials = Some(CredentialsCache::new(provider));
+        self
+    }
+
+    /// Set a template of the credentials sent to the proxy, evaluated for each request with its
+    /// destination and extensions.
+    ///
+    /// Vendors routing requests by the username, such as `user-session-1234-country-de`, can
+    /// be given a username varying by destination host, or by a session id set on the request
+    /// with [`RequestBuilder::extension`](crate::RequestBuilder::extension). The credentials
+    /// are sent with Basic auth in the `Proxy-Authorization` header of forwarded `http`
+    /// requests and of `CONNECT` requests, and with the SOCKS handshake, in place of the
+    /// static authorization of the proxy. A [`credentials_provider`](Proxy::credentials_provider)
+    /// takes precedence over the template.
+    ///
+    /// Connections are pooled by the credentials of the template, so that a tunnel opened with
+    /// the credentials of a request is never used by a request with others. The templates of
+    /// the proxies of a [`chain`](Proxy::chain) are each evaluated for the tunnel opened
+    /// through their proxy. The template is not evaluated for the proxies of a PAC script.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # extern crate wreq;
+    /// # use std::sync::Arc;
+    /// use http::{Extensions, Uri};
+    ///
+    /// #[derive(Clone)]
+    /// struct Session(u64);
+    ///
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let template = |dst: &Uri, ext: &Extensions| {
+    ///     let session = ext.get::<Session>().map_or(0, |session| session.0);
+    ///     let country = if dst.host() == Some("example.de") { "de" } else { "us" };
+    ///     (format!("user-session-{session}-country-{country}"), "secret".to_owned())
+    /// };
+    /// let proxy = wreq::Proxy::all("http://localhost:1234")?.auth_template(Arc::new(template));
+    /// let client = wreq::Client::builder().proxy(proxy).build()?;
+    /// let req = client.get("https://example.de/").extension(Session(1234));
+    /// # Ok(())
+    /// # }
+    /// # fn main() {}
+    /// ```
+    pub fn auth_template(
+        mut self,
+        template: Arc<dyn Fn(&Uri, &Extensions) -> (String, String) + Send + Sync>,
+    ) -> Proxy {
+        self.extra.template = Some(AuthTemplate::new(template));
+        self
+    }
+
+    /// Adds a Custom Headers to Proxy
+    /// Adds custom headers to this Proxy
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate wreq;
+    /// # use wreq::header::*;
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert(USER_AGENT, "wreq".parse().unwrap());
+    /// let proxy = wreq::Proxy::https("http://localhost:1234")?.custom_http_headers(headers);
+    /// # Ok(())
+    /// # }
+    /// # fn main() {}
+    /// ```
+    pub fn custom_http_headers(mut self, headers: HeaderMap) -> Proxy {
+        match self.intercept {
+            Intercept::All(_) | Intercept::Http(_) | Intercept::Https(_) => {
+                self.extra.misc = Some(headers);
+            }
+            #[cfg(feature = "pac")]
+            Intercept::Pac(_) => {
+                self.extra.misc = Some(headers);
+            }
+        }
+
+        self
+    }
+
+    /// Appends headers sent to the proxy, with `CONNECT` requests and with forwarded `http`
+    /// requests.
+    ///
+    /// Unlike [`custom_http_headers`](Proxy::custom_http_headers), the headers are added to
+    /// those already set on the proxy, keeping every value of repeated names. The values of
+    /// headers [marked as sensitive](HeaderValue::set_sensitive) are masked in the verbose
+    /// connection logs.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate wreq;
+    /// # use wreq::header::*;
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = HeaderValue::from_static("session-1234");
+    /// session.set_sensitive(true);
+    ///
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert("x-proxy-session", session);
+    /// let proxy = wreq::Proxy::all("http://localhost:1234")?
+    ///     .headers(headers)
+    ///     .header(HeaderName::from_static("x-route"), HeaderValue::from_static("eu"));
+    /// # Ok(())
+    /// # }
+    /// # fn main() {}
+    /// ```
+    pub fn headers(mut self, headers: HeaderMap) -> Proxy {
+        let misc = self.extra.misc.get_or_insert_with(HeaderMap::new);
+        let mut name = None;
+        for (next, value) in headers {
+            name = next.or(name);
+            if let Some(ref name) = name {
+                misc.append(name.clone(), value);
+            }
+        }
+        self
+    }
+
+    /// Appends a header sent to the proxy, see [`Proxy::headers`].
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Proxy {
+        self.extra
+            .misc
+            .get_or_insert_with(HeaderMap::new)
+            .append(name, value);
+        self
+    }
+
+    /// Adds a `No Proxy` exclusion list to this Proxy
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # extern crate wreq;
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let proxy = wreq::Proxy::https("http://localhost:1234")?
+    ///     .no_proxy(wreq::NoProxy::from_string("direct.tld, sub.direct2.tld"));
+    /// # Ok(())
+    /// # }
+    /// # fn main() {}
+    /// ```
+    pub fn no_proxy(mut self, no_proxy: Option<NoProxy>) -> Proxy {
+        self.no_proxy = no_proxy;
+        self
+    }
+
+    pub(crate) fn into_matcher(self) -> Matcher {
+        let Proxy {
+            intercept,
+            extra,
+            no_proxy,
+        } = self;
+
+        let (url, builder_fn): (_, fn(matcher::Builder, String) -> matcher::Builder) =
+            match intercept {
+                Intercept::All(url) => (url, matcher::Builder::all),
+                Intercept::Http(url) => (url, matcher::Builder::http),
+                Intercept::Https(url) => (url, matcher::Builder::https),
+                #[cfg(feature = "pac")]
+                Intercept::Pac(config) => {
+                    // the inner matcher only keeps the no-proxy rules
+                    let no_proxy_str = no_proxy.as_ref().map(|n| n.inner.as_ref()).unwrap_or("");
+                    return Matcher {
+                        inner: Box::new(matcher::Matcher::builder().no(no_proxy_str).build()),
+                        extra,
+                        maybe_has_http_auth: false,
+                        maybe_has_http_custom_headers: false,
+                        pac: Some(Pac::new(config)),
+                    };
+                }
+            };
+
+        // the last proxy of a chain always tunnels
+        let chained = extra.chain.is_some();
+        let maybe_has_http_auth = !chained && cache_maybe_has_http_auth(&url, &extra.auth);
+        let maybe_has_http_custom_headers =
+            !chained && cache_maybe_has_http_custom_headers(&url, &extra.misc);
+        let no_proxy_str = no_proxy.as_ref().map(|n| n.inner.as_ref()).unwrap_or("");
+        let inner = Box::new(
+            builder_fn(matcher::Matcher::builder(), String::from(url))
+                .no(no_proxy_str)
+                .build(),
+        );
+
+        Matcher {
+            inner,
+            extra,
+            maybe_has_http_auth,
+            maybe_has_http_custom_headers,
+            #[cfg(feature = "pac")]
+            pac: None,
+        }
+    }
+}
+
+/// Returns the proxy at `url` as a proxy of a chain, tunneled through to the next.
+fn chained(url: Url, extra: Extra) -> crate::Result<Intercepted> {
+    let inner = matcher::Intercept::from_url(url.as_str())
+        .ok_or_else(|| Error::builder(format!("unsupported proxy {url} in a proxy chain")))?;
+    Ok(Intercepted { inner, extra })
+}
+
+fn cache_maybe_has_http_auth(url: &Url, extra: &Option<HeaderValue>) -> bool {
+    matches!(url.scheme(), "http" | "unix") && (url.password().is_some() || extra.is_some())
+}
+
+fn cache_maybe_has_http_custom_headers(url: &Url, extra: &Option<HeaderMap>) -> bool {
+    matches!(url.scheme(), "http" | "unix") && extra.is_some()
+}
+
+impl fmt::Debug for Proxy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Proxy")
+            .field(&self.intercept)
+            .field(&self.no_proxy)
+            .finish()
+    }
+}
+
+impl NoProxy {
+    /// Returns a new no-proxy configuration based on environment variables (or `None` if no
+    /// variables are set) see [self::NoProxy::from_string()] for the string format
+    pub fn from_env() -> Option<NoProxy> {
+        let raw = std::env::var("NO_PROXY")
+            .or_else(|_| std::env::var("no_proxy"))
+            .ok()?;
+
+        // Per the docs, this returns `None` if no environment variable is set. We can only reach
+        // here if an env var is set, so we return `Some(NoProxy::default)` if `from_string`
+        // returns None, which occurs with an empty string.
+        Some(Self::from_string(&raw).unwrap_or_default())
+    }
+
+    /// Returns a new no-proxy configuration based on a `no_proxy` string (or `None` if no variables
+    /// are set)
+    /// The rules are as follows:
+    /// * The environment variable `NO_PROXY` is checked, if it is not set, `no_proxy` is checked
+    /// * If neither environment variable is set, `None` is returned
+    /// * Entries are expected to be comma-separated (whitespace between entries is ignored)
+    /// * IP addresses (both IPv4 and IPv6) are allowed, as are optional subnet masks (by adding
+    ///   /size, for example "`192.168.1.0/24`").
+    /// * An entry "`*`" matches all hostnames (this is the only wildcard allowed)
+    /// * An entry "`localhost`" or "`<loopback>`" also matches the loopback addresses
+    ///   `127.0.0.0/8` and `::1`
+    /// * Any other entry is considered a domain name (and may contain a leading dot, or `*.`, for
+    ///   example `google.com`, `.google.com` and `*.google.com` are equivalent) and would match
+    ///   both that domain AND all subdomains.
+    /// * Entries and hosts are compared case-insensitively, and malformed entries are ignored
+    ///
+    /// For example, if `"NO_PROXY=google.com, 192.168.1.0/24"` was set, all the following would
+    /// match (and therefore would bypass the proxy):
+    /// * `http://google.com/`
+    /// * `http://www.google.com/`
+    /// * `http://192.168.1.42/`
+    ///
+    /// The URL `http://notgoogle.com/` would not match.
+    pub fn from_string(no_proxy_list: &str) -> Option<Self> {
+        // lazy parsed, to not make the type public in hyper-util
+        Some(NoProxy {
+            inner: no_proxy_list.into(),
+        })
+    }
+
+    /// Returns a new no-proxy configuration based on a `no_proxy` string, with the rules of
+    /// [`NoProxy::from_string`], rejecting malformed entries instead of ignoring them.
+    ///
+    /// # Errors
+    ///
+    /// Fails with a builder error naming the first malformed entry, such as `10.0.0.0/33` or
+    /// `300.1.1.1`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # extern crate wreq;
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let no_proxy = wreq::NoProxy::parse("localhost, 10.0.0.0/8, 192.168.0.0/16, ::1")?;
+    /// let proxy = wreq::Proxy::all("http://localhost:1234")?.no_proxy(Some(no_proxy));
+    /// # Ok(())
+    /// # }
+    /// # fn main() {}
+    /// ```
+    pub fn parse(no_proxy_list: &str) -> crate::Result<NoProxy> {
+        matcher::check_no_proxy(no_proxy_list)
+            .map_err(|problem| Error::builder(format!("invalid no-proxy list: {problem}")))?;
+        Ok(NoProxy {
+            inner: no_proxy_list.into(),
+        })
+    }
+}
+
+impl Matcher {
+    pub(crate) fn system() -> Self {
+        Self {
+            inner: Box::new(matcher::Matcher::from_system()),
+            extra: Extra {
+                auth: None,
+                misc: None,
+                credentials: None,
+                template: None,
+                chain: None,
+            },
+            // maybe env vars have auth!
+            maybe_has_http_auth: true,
+            maybe_has_http_custom_headers: true,
+            #[cfg(feature = "pac")]
+            pac: None,
+        }
+    }
+
+    pub(crate) fn intercept(&self, dst: &Uri) -> Option<Intercepted> {
+        self.inner.intercept(dst).map(|inner| Intercepted {
+            inner,
+            extra: self.extra.clone(),
+        })
+    }
+
+    /// Return whether this matcher routes requests with a PAC script.
+    #[cfg(feature = "pac")]
+    pub(crate) fn has_pac(&self) -> bool {
+        self.pac.is_some()
+    }
+
+    /// Returns the routes the PAC script of this matcher gives for `dst`, in order, `None`
+    /// standing for a direct connection. The script is fetched through `connector`.
+    #[cfg(feature = "pac")]
+    pub(crate) async fn pac_routes(
+        &self,
+        dst: &Uri,
+        connector: &crate::connect::ConnectorService,
+    ) -> Result<Vec<Option<Intercepted>>, crate::error::BoxError> {
+        let pac = self.pac.as_ref().ok_or("no PAC script")?;
+        if self.inner.bypasses(dst) {
+            return Ok(vec![None]);
+        }
+
+        let routes = pac.routes(dst, connector).await?;
+        Ok(routes
+            .iter()
+            .filter_map(|route| match route {
+                PacRoute::Direct => Some(None),
+                PacRoute::Proxy(scheme, authority) => {
+                    let inner = matcher::Intercept::from_authority(scheme, authority);
+                    if inner.is_none() {
+                        debug!("skipping invalid PAC proxy {authority:?}");
+                    }
+                    inner.map(|inner| {
+                        Some(Intercepted {
+                            inner,
+                            extra: self.extra.clone(),
+                        })
+                    })
+                }
+            })
+            .collect())
+    }
+
+    /// Return whether this matcher might provide HTTP (not s) auth.
+    ///
+    /// This is very specific. If this proxy needs auth to be part of a Forward
+    /// request (instead of a tunnel), this should return true.
+    ///
+    /// If it's not sure, this should return true.
+    ///
+    /// This is meant as a hint to allow skipping a more expensive check
+    /// (calling `intercept()`) if it will never need auth when Forwarding.
+    pub(crate) fn maybe_has_http_auth(&self) -> bool {
+        self.maybe_has_http_auth
+    }
+
+    pub(crate) fn http_non_tunnel_basic_auth(&self, dst: &Uri) -> Option<HeaderValue> {
+        if let Some(proxy) = self.intercept(dst) {
+            if proxy.forwards_http() {
+                return proxy.basic_auth().cloned();
+            }
+        }
+
+        None
+    }
+
+    pub(crate) fn maybe_has_http_custom_headers(&self) -> bool {
+        self.maybe_has_http_custom_headers
+    }
+
+    pub(crate) fn http_non_tunnel_custom_headers(&self, dst: &Uri) -> Option<HeaderMap> {
+        if let Some(proxy) = self.intercept(dst) {
+            if proxy.forwards_http() {
+                return proxy.custom_headers().cloned();
+            }
+        }
+
+        None
+    }
+}
+
+impl fmt::Debug for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl Intercepted {
+    pub(crate) fn uri(&self) -> &http::Uri {
+        self.inner.uri()
+    }
+
+    /// Returns whether plain HTTP requests are forwarded to the proxy rather than tunneled: for
+    /// `http` proxies, and `unix` proxies listening on a unix socket, unless chained.
+    pub(crate) fn forwards_http(&self) -> bool {
+        self.extra.chain.is_none() && matches!(self.uri().scheme_str(), Some("http" | "unix"))
+    }
+
+    /// Returns the proxies tunneled through, in order, to reach the proxy.
+    pub(crate) fn chain(&self) -> &[Intercepted] {
+        self.extra.chain.as_deref().unwrap_or_default()
+    }
+
+    pub(crate) fn basic_auth(&self) -> Option<&HeaderValue> {
+        if let Some(ref val) = self.extra.auth {
+            return Some(val);
+        }
+        self.inner.basic_auth()
+    }
+
+    /// Returns the credentials of the `Basic` authorization of the proxy, answering its
+    /// `Digest` challenges.
+    pub(crate) fn digest_auth(&self) -> Option<DigestAuth> {
+        let (username, password) = self.basic_credentials()?;
+        Some(DigestAuth::new(username, password))
+    }
+
+    /// Returns the username and password of the `Basic` authorization of the proxy.
+    fn basic_credentials(&self) -> Option<(String, String)> {
+        use base64::{Engine, prelude::BASE64_STANDARD};
+
+        let encoded = self.basic_auth()?.as_bytes().strip_prefix(b"Basic ")?;
+        let decoded = String::from_utf8(BASE64_STANDARD.decode(encoded).ok()?).ok()?;
+        let (username, password) = decoded.split_once(':')?;
+        Some((username.to_owned(), password.to_owned()))
+    }
+
+    pub(crate) fn custom_headers(&self) -> Option<&HeaderMap> {
+        if let Some(ref val) = self.extra.misc {
+            return Some(val);
+        }
+        None
+    }
+
+    pub(crate) fn credentials(&self) -> Option<&CredentialsCache> {
+        self.extra.credentials.as_ref()
+    }
+
+    pub(crate) fn auth_template(&self) -> Option<&AuthTemplate> {
+        self.extra.template.as_ref()
+    }
+
+    #[cfg(feature = "socks")]
+    pub(crate) fn raw_auth(&self) -> Option<(Bytes, Bytes)> {
+        self.inner.raw_auth().or_else(|| {
+            // the URL of a unix socket proxy has no user info to hold the credentials
+            if !matcher::is_unix_scheme(self.uri().scheme_str()?) {
+                return None;
+            }
+            let (username, password) = self.basic_credentials()?;
+            Some((username.into(), password.into()))
+        })
+    }
+}
+
+impl fmt::Debug for Intercepted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.uri().fmt(f)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Intercept {
+    All(Url),
+    Http(Url),
+    Https(Url),
+    #[cfg(feature = "pac")]
+    Pac(PacConfig),
+}
+
+fn url_auth(url: &mut Url, username: &str, password: &str) {
+    // the URL of a unix socket proxy has no host, and so no user info
+    if url.has_host() {
+        url.set_username(username).expect("is a base");
+        url.set_password(Some(password)).expect("is a base");
+    }
+}
+
+pub(crate) fn encode_basic_auth(username: &str, password: &str) -> HeaderValue {
+    crate::util::basic_auth(username, Some(password))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> http::Uri {
+        s.parse().unwrap()
+    }
+
+    fn intercepted_uri(p: &Matcher, s: &str) -> Uri {
+        p.intercept(&s.parse().unwrap()).unwrap().uri().clone()
+    }
+
+    #[test]
+    fn test_http() {
+        let target = "http://example.domain/";
+        let p = Proxy::http(target).unwrap().into_matcher();
+
+        let http = "http://hyper.rs";
+        let other = "https://hyper.rs";
+
+        assert_eq!(intercepted_uri(&p, http), target);
+        assert!(p.intercept(&url(other)).is_none());
+    }
+
+    #[test]
+    fn test_https() {
+        let target = "http://example.domain/";
+        let p = Proxy::https(target).unwrap().into_matcher();
+
+        let http = "http://hyper.rs";
+        let other = "https://hyper.rs";
+
+        assert!(p.intercept(&url(http)).is_none());
+        assert_eq!(intercepted_uri(&p, other), target);
+    }
+
+    #[test]
+    fn test_all() {
+        let target = "http://example.domain/";
+        let p = Proxy::all(target).unwrap().into_matcher();
+
+        let http = "http://hyper.rs";
+        let https = "https://hyper.rs";
+        // no longer supported
+        //let other = "x-youve-never-heard-of-me-mr-proxy://hyper.rs";
+
+        assert_eq!(intercepted_uri(&p, http), target);
+        assert_eq!(intercepted_uri(&p, https), target);
+        //assert_eq!(intercepted_uri(&p, other), target);
+    }
+
+    #[test]
+    fn test_standard_with_custom_auth_header() {
+        let target = "http://example.domain/";
+        let p = Proxy::all(target)
+            .unwrap()
+            .custom_http_auth(http::HeaderValue::from_static("testme"))
+            .into_matcher();
+
+        let got = p.intercept(&url("http://anywhere.local")).unwrap();
+        let auth = got.basic_auth().unwrap();
+        assert_eq!(auth, "testme");
+    }
+
+    #[test]
+    fn test_maybe_has_http_auth() {
+        let m = Proxy::all("https://letme:in@yo.local")
+            .unwrap()
+            .into_matcher();
+        assert!(!m.maybe_has_http_auth(), "https always tunnels");
+
+        let m = Proxy::all("http://letme:in@yo.local")
+            .unwrap()
+            .into_matcher();
+        assert!(m.maybe_has_http_auth(), "http forwards");
+    }
+
+    #[test]
+    fn test_chain() {
+        let inner = Proxy::chain([
+            Proxy::all("http://a.local").unwrap().basic_auth("a", "a"),
+            Proxy::all("https://b.local").unwrap(),
+        ])
+        .unwrap();
+        let p = Proxy::chain([inner, Proxy::all("http://c.local").unwrap()])
+            .unwrap()
+            .basic_auth("c", "c")
+            .into_matcher();
+        assert!(!p.maybe_has_http_auth(), "the last proxy tunnels");
+
+        let got = p.intercept(&url("http://hyper.rs")).unwrap();
+        assert_eq!(got.uri(), "http://c.local/");
+        assert!(!got.forwards_http());
+        assert_eq!(got.basic_auth().unwrap(), "Basic Yzpj");
+
+        // nested chains are flattened, each proxy keeping its own auth
+        let hops: Vec<_> = got.chain().iter().map(|hop| hop.uri().clone()).collect();
+        assert_eq!(hops, ["http://a.local/", "https://b.local/"]);
+        assert_eq!(got.chain()[0].basic_auth().unwrap(), "Basic YTph");
+        assert!(got.chain()[1].basic_auth().is_none());
+    }
+
+    #[test]
+    fn test_invalid_chains() {
+        assert!(Proxy::chain([]).unwrap_err().is_builder());
+        let unix = Proxy::chain([
+            Proxy::all("http://a.local").unwrap(),
+            Proxy::all("unix:///tmp/proxy.sock").unwrap(),
+        ]);
+        assert!(unix.unwrap_err().is_builder());
+    }
+}
@@ -0,0 +1,173 @@
+//! The functions PAC scripts are given, as browsers define them.
+//!
+//! The date and time functions are not supported.
+
+use std::net::{IpAddr, Ipv4Addr, ToSocketAddrs, UdpSocket};
+
+use super::script::{ScriptError, Value};
+
+/// Calls the PAC function `name`.
+pub(super) fn call(name: &str, args: &[Value]) -> Result<Value, ScriptError> {
+    let arg = |i: usize| args.get(i).map(Value::to_display).unwrap_or_default();
+
+    let value = match name {
+        "isPlainHostName" => Value::Bool(!arg(0).contains('.')),
+        "dnsDomainIs" => Value::Bool(dns_domain_is(&arg(0), &arg(1))),
+        "localHostOrDomainIs" => {
+            let (host, hostdom) = (arg(0), arg(1));
+            let matches = host.eq_ignore_ascii_case(&hostdom)
+                || (!host.contains('.')
+                    && hostdom
+                        .split_once('.')
+                        .is_some_and(|(name, _)| host.eq_ignore_ascii_case(name)));
+            Value::Bool(matches)
+        }
+        "dnsDomainLevels" => Value::Number(arg(0).matches('.').count() as f64),
+        "shExpMatch" => Value::Bool(sh_exp_match(&arg(0), &arg(1))),
+        "isResolvable" => Value::Bool(resolve(&arg(0)).is_some()),
+        "dnsResolve" => resolve(&arg(0)).map_or(Value::Null, |ip| Value::str(ip.to_string())),
+        "myIpAddress" => Value::str(my_ip_address().to_string()),
+        "isInNet" => {
+            let matches = match (
+                resolve(&arg(0)),
+                arg(1).parse::<Ipv4Addr>(),
+                arg(2).parse::<Ipv4Addr>(),
+            ) {
+                (Some(IpAddr::V4(ip)), Ok(pattern), Ok(mask)) => {
+                    let mask = u32::from(mask);
+                    u32::from(ip) & mask == u32::from(pattern) & mask
+                }
+                _ => false,
+            };
+            Value::Bool(matches)
+        }
+        "alert" => Value::Undefined,
+        "weekdayRange" | "dateRange" | "timeRange" => {
+            return Err(ScriptError::new(format!("`{name}` is not supported")));
+        }
+        _ => return Err(ScriptError::new(format!("`{name}` is not defined"))),
+    };
+    Ok(value)
+}
+
+fn dns_domain_is(host: &str, domain: &str) -> bool {
+    host.len() >= domain.len()
+        && host.as_bytes()[host.len() - domain.len()..].eq_ignore_ascii_case(domain.as_bytes())
+}
+
+/// Matches `s` against a shell expression, where `*` matches any characters and `?` one.
+fn sh_exp_match(s: &str, pattern: &str) -> bool {
+    let s: Vec<char> = s.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+
+    // the positions to resume from when a character does not match after a `*`
+    let (mut i, mut p) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while i < s.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((i, p));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == s[i] => {
+                i += 1;
+                p += 1;
+            }
+            _ => match star {
+                Some((si, sp)) => {
+                    star = Some((si + 1, sp));
+                    i = si + 1;
+                    p = sp + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Resolves `host` to its first IPv4 address, or its first address if it has none.
+fn resolve(host: &str) -> Option<IpAddr> {
+    if let Ok(ip) = host.trim_matches(['[', ']']).parse::<IpAddr>() {
+        return Some(ip);
+    }
+    let addrs: Vec<_> = (host, 0)
+        .to_socket_addrs()
+        .ok()?
+        .map(|addr| addr.ip())
+        .collect();
+    addrs
+        .iter()
+        .find(|ip| ip.is_ipv4())
+        .or_else(|| addrs.first())
+        .copied()
+}
+
+/// Returns the address of the interface of the default route, without sending any packet.
+fn my_ip_address() -> IpAddr {
+    UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .and_then(|socket| {
+            socket.connect((Ipv4Addr::new(198, 51, 100, 1), 53))?;
+            socket.local_addr()
+        })
+        .map(|addr| addr.ip())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_expressions() {
+        let cases = [
+            (
+                "http://home.netscape.com/people/ari/index.html",
+                "*/ari/*",
+                true,
+            ),
+            (
+                "http://home.netscape.com/people/montulli/index.html",
+                "*/ari/*",
+                false,
+            ),
+            ("proxy.example", "*.example", true),
+            ("example", "*.example", false),
+            ("a1.example", "a?.example", true),
+            ("a12.example", "a?.example", false),
+            ("anything", "*", true),
+            ("", "*", true),
+        ];
+        for (s, pattern, matches) in cases {
+            assert_eq!(sh_exp_match(s, pattern), matches, "{s:?} {pattern:?}");
+        }
+    }
+
+    #[test]
+    fn host_functions() {
+        let truthy = |name: &str, args: &[&str]| {
+            let args: Vec<_> = args.iter().map(|arg| Value::str(*arg)).collect();
+            call(name, &args).unwrap().truthy()
+        };
+
+        assert!(truthy("isPlainHostName", &["www"]));
+        assert!(!truthy("isPlainHostName", &["www.example"]));
+        assert!(truthy("dnsDomainIs", &["www.Example.com", ".example.com"]));
+        assert!(!truthy("dnsDomainIs", &["www", ".example.com"]));
+        assert!(truthy("localHostOrDomainIs", &["www", "www.example.com"]));
+        assert!(truthy(
+            "localHostOrDomainIs",
+            &["www.example.com", "www.example.com"]
+        ));
+        assert!(!truthy(
+            "localHostOrDomainIs",
+            &["www.other.com", "www.example.com"]
+        ));
+        assert!(truthy("isInNet", &["10.1.2.3", "10.0.0.0", "255.0.0.0"]));
+        assert!(!truthy("isInNet", &["11.1.2.3", "10.0.0.0", "255.0.0.0"]));
+        assert!(truthy("isResolvable", &["127.0.0.1"]));
+
+        let err = call("weekdayRange", &[Value::str("MON")]).unwrap_err();
+        assert_eq!(err.to_string(), "`weekdayRange` is not supported");
+    }
+}
@@ -0,0 +1,411 @@
+//! Proxy auto-config (PAC) scripts, see [`Proxy::pac`](crate::Proxy::pac).
+//!
+//! A PAC script defines a `FindProxyForURL(url, host)` function returning the proxies to try
+//! for a destination, in order, such as `PROXY proxy.example:8080; SOCKS5 proxy.example:1080;
+//! DIRECT`. Scripts are evaluated by a restricted interpreter, see [`script`].
+
+mod functions;
+mod script;
+
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use http::{
+    Request, StatusCode, Uri,
+    header::{ACCEPT, LOCATION, PROXY_AUTHORIZATION},
+    uri::Scheme,
+};
+use http_body_util::{BodyExt, Empty, LengthLimitError, Limited};
+use tokio::sync::OnceCell;
+use url::Url;
+
+use self::script::Script;
+use crate::{
+    connect::ConnectorService,
+    core::{client::Client as HttpClient, rt::TokioExecutor},
+    error::BoxError,
+    sync::Mutex,
+};
+
+/// How long the routes of a destination are cached by default.
+pub(crate) const DEFAULT_TTL: Duration = Duration::from_secs(300);
+
+/// The time allowed to fetch a script, following its redirects.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The largest script fetched.
+const MAX_SCRIPT_SIZE: usize = 1024 * 1024;
+
+/// The most redirects followed when fetching a script.
+const MAX_REDIRECTS: usize = 10;
+
+/// Destinations whose routes are cached before expired ones are pruned.
+const MAX_DESTINATIONS: usize = 1024;
+
+/// The configuration of a PAC script, as given to [`Proxy::pac`](crate::Proxy::pac).
+#[derive(Debug, Clone)]
+pub(crate) struct PacConfig {
+    source: PacSource,
+    ttl: Duration,
+}
+
+#[derive(Debug, Clone)]
+enum PacSource {
+    /// A script loaded from a file.
+    Script(Arc<Script>),
+    /// The `http` or `https` URL a script is fetched from on first use.
+    Url(Url),
+}
+
+/// A route returned by a PAC script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PacRoute {
+    Direct,
+    /// A proxy, by the scheme it is spoken to with and its `host:port`.
+    Proxy(&'static str, String),
+}
+
+/// A PAC script, with the routes it returned cached per destination origin.
+#[derive(Clone)]
+pub(crate) struct Pac {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    url: Option<Url>,
+    ttl: Duration,
+    /// Set once the script is fetched, callers waiting for the same fetch.
+    script: OnceCell<Arc<Script>>,
+    routes: Mutex<HashMap<String, (Arc<[PacRoute]>, Instant)>>,
+}
+
+impl PacConfig {
+    /// Loads the script at `source`, a file path or `file` URL, or returns the configuration
+    /// fetching it from an `http` or `https` URL.
+    pub(crate) fn new(source: &str) -> Result<PacConfig, BoxError> {
+        let source = match Url::parse(source) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => PacSource::Url(url),
+            Ok(url) if url.scheme() == "file" => {
+                let path = url
+                    .to_file_path()
+                    .map_err(|()| format!("invalid PAC file URL {url}"))?;
+                PacSource::Script(load(&path)?)
+            }
+            // a path, which may start with a drive letter on Windows
+            Ok(url) if url.scheme().len() > 1 => {
+                return Err(format!("unsupported PAC URL scheme {:?}", url.scheme()).into());
+            }
+            _ => PacSource::Script(load(Path::new(source))?),
+        };
+        Ok(PacConfig {
+            source,
+            ttl: DEFAULT_TTL,
+        })
+    }
+
+    pub(crate) fn ttl(&mut self, ttl: Duration) {
+        self.ttl = ttl;
+    }
+}
+
+fn load(path: &Path) -> Result<Arc<Script>, BoxError> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed to read PAC file {}: {err}", path.display()))?;
+    Ok(Arc::new(Script::parse(&source)?))
+}
+
+impl Pac {
+    pub(crate) fn new(config: PacConfig) -> Pac {
+        let (url, script) = match config.source {
+            PacSource::Script(script) => (None, Some(script)),
+            PacSource::Url(url) => (Some(url), None),
+        };
+        Pac {
+            inner: Arc::new(Inner {
+                url,
+                ttl: config.ttl,
+                script: OnceCell::new_with(script),
+                routes: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Returns the routes to try for `dst`, in order.
+    ///
+    /// The script is fetched through `connector` on first use, and evaluated on a blocking
+    /// thread, since its functions may resolve host names.
+    pub(crate) async fn routes(
+        &self,
+        dst: &Uri,
+        connector: &ConnectorService,
+    ) -> Result<Arc<[PacRoute]>, BoxError> {
+        let host = dst
+            .host()
+            .ok_or("destination has no host")?
+            .to_ascii_lowercase();
+        let key = format!("{}://{}", dst.scheme_str().unwrap_or_default(), host);
+        if let Some((routes, expires)) = self.inner.routes.lock().get(&key) {
+            if *expires > Instant::now() {
+                return Ok(routes.clone());
+            }
+        }
+
+        // the path of HTTPS URLs is not given to scripts, as browsers do
+        let url = if dst.scheme() == Some(&Scheme::HTTPS) {
+            let authority = dst.authority().map_or("", |authority| authority.as_str());
+            format!("https://{authority}/")
+        } else {
+            dst.to_string()
+        };
+        let host = host.trim_matches(['[', ']']).to_owned();
+
+        let script = self.script(connector).await?;
+        let routes = tokio::task::spawn_blocking(move || {
+            let result = script.find_proxy_for_url(&url, &host)?;
+            Ok::<_, BoxError>(Arc::<[PacRoute]>::from(parse_routes(&result)))
+        })
+        .await??;
+        trace!("PAC routes for {key}: {routes:?}");
+
+        let now = Instant::now();
+        if let Some(expires) = now.checked_add(self.inner.ttl) {
+            let mut cached = self.inner.routes.lock();
+            cache_routes(&mut cached, key, (routes.clone(), expires), now);
+        }
+        Ok(routes)
+    }
+
+    /// Returns the script, fetching it if it was not yet.
+    ///
+    /// Only one request fetches the script, the others waiting for it. A failed fetch is
+    /// retried by the next request.
+    async fn script(&self, connector: &ConnectorService) -> Result<Arc<Script>, BoxError> {
+        self.inner
+            .script
+            .get_or_try_init(|| async {
+                let url = self.inner.url.as_ref().ok_or("no PAC script")?;
+                let source = tokio::time::timeout(FETCH_TIMEOUT, fetch(connector, url))
+                    .await
+                    .unwrap_or_else(|_| Err("timed out".into()))
+                    .map_err(|err| format!("failed to fetch PAC script {url}: {err}"))?;
+                let script = tokio::task::spawn_blocking(move || Script::parse(&source)).await??;
+                Ok::<_, BoxError>(Arc::new(script))
+            })
+            .await
+            .cloned()
+    }
+}
+
+impl PartialEq for Pac {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Eq for Pac {}
+
+impl Hash for Pac {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::ptr::hash(Arc::as_ptr(&self.inner), state);
+    }
+}
+
+/// Parses the result of `FindProxyForURL`, skipping the entries of unsupported types. No entry
+/// stands for a direct connection.
+/// Caches the routes of `key`, making room if `MAX_DESTINATIONS` are cached.
+fn cache_routes(
+    cached: &mut HashMap<String, (Arc<[PacRoute]>, Instant)>,
+    key: String,
+    entry: (Arc<[PacRoute]>, Instant),
+    now: Instant,
+) {
+    if cached.len() >= MAX_DESTINATIONS && !cached.contains_key(&key) {
+        cached.retain(|_, (_, expires)| *expires > now);
+        // all still fresh, the one expiring first makes room
+        if cached.len() >= MAX_DESTINATIONS {
+            let first = cached
+                .iter()
+                .min_by_key(|(_, (_, expires))| *expires)
+                .map(|(key, _)| key.clone());
+            if let Some(first) = first {
+                cached.remove(&first);
+            }
+        }
+    }
+    cached.insert(key, entry);
+}
+
+fn parse_routes(result: &str) -> Vec<PacRoute> {
+    let routes: Vec<_> = result
+        .split(';')
+        .filter_map(|entry| {
+            let mut parts = entry.split_whitespace();
+            let kind = parts.next()?.to_ascii_uppercase();
+            let scheme = match kind.as_str() {
+                "DIRECT" => return Some(PacRoute::Direct),
+                "PROXY" | "HTTP" => "http",
+                "HTTPS" => "https",
+                // SOCKS proxies are skipped without the `socks` feature
+                "SOCKS" | "SOCKS4" if cfg!(feature = "socks") => "socks4",
+                "SOCKS5" if cfg!(feature = "socks") => "socks5",
+                _ => {
+                    debug!("skipping unknown PAC route {entry:?}");
+                    return None;
+                }
+            };
+            Some(PacRoute::Proxy(scheme, parts.next()?.to_owned()))
+        })
+        .collect();
+
+    if routes.is_empty() {
+        vec![PacRoute::Direct]
+    } else {
+        routes
+    }
+}
+
+/// Fetches a script through `connector`, which routes the request as any other of the client
+/// except through PAC scripts, following redirects.
+async fn fetch(connector: &ConnectorService, url: &Url) -> Result<String, BoxError> {
+    let connector = connector.without_pac();
+    let client = HttpClient::builder(TokioExecutor::new())
+        .pool_max_idle_per_host(0)
+        .build::<_, Empty<Bytes>>(connector.clone());
+
+    let mut url = url.clone();
+    for _ in 0..=MAX_REDIRECTS {
+        let uri = url.as_str().parse::<Uri>()?;
+        let mut req = Request::get(uri.clone())
+            .header(ACCEPT, "application/x-ns-proxy-autoconfig, */*")
+            .body(Empty::new())?;
+        if let Some(auth) = connector.forward_proxy_auth(&uri) {
+            req.headers_mut().insert(PROXY_AUTHORIZATION, auth);
+        }
+
+        let res = client.request(req).await?;
+        let status = res.status();
+        if matches!(status.as_u16(), 301 | 302 | 303 | 307 | 308) {
+            let location = res
+                .headers()
+                .get(LOCATION)
+                .ok_or("redirect without a location")?
+                .to_str()?;
+            url = url.join(location)?;
+            if !matches!(url.scheme(), "http" | "https") {
+                return Err(format!("redirect to unsupported URL {url}").into());
+            }
+            trace!("PAC script redirected to {url}");
+            continue;
+        }
+        if status != StatusCode::OK {
+            return Err(format!("status {status}").into());
+        }
+
+        let body = Limited::new(res.into_body(), MAX_SCRIPT_SIZE)
+            .collect()
+            .await
+            .map_err(|err| -> BoxError {
+                if err.is::<LengthLimitError>() {
+                    format!("the script is larger than {MAX_SCRIPT_SIZE} bytes").into()
+                } else {
+                    err
+                }
+            })?
+            .to_bytes();
+        return Ok(String::from_utf8_lossy(&body).into_owned());
+    }
+
+    Err("too many redirects".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_find_proxy_results() {
+        let cases = [
+            ("DIRECT", vec![PacRoute::Direct]),
+            ("", vec![PacRoute::Direct]),
+            (
+                "PROXY a.example:8080; proxy b.example:8080;DIRECT",
+                vec![
+                    PacRoute::Proxy("http", "a.example:8080".to_owned()),
+                    PacRoute::Proxy("http", "b.example:8080".to_owned()),
+                    PacRoute::Direct,
+                ],
+            ),
+            (
+                "HTTPS a.example:443; QUIC a.example:443",
+                vec![PacRoute::Proxy("https", "a.example:443".to_owned())],
+            ),
+        ];
+        for (result, routes) in cases {
+            assert_eq!(parse_routes(result), routes, "{result:?}");
+        }
+    }
+
+    #[test]
+    fn cached_routes_are_bounded() {
+        let routes = Arc::<[PacRoute]>::from(vec![PacRoute::Direct]);
+        let now = Instant::now();
+        let mut cached = HashMap::new();
+
+        cache_routes(
+            &mut cached,
+            "http://expired".to_owned(),
+            (routes.clone(), now),
+            now,
+        );
+        for i in 1..MAX_DESTINATIONS {
+            let expires = now + Duration::from_secs(i as u64);
+            cache_routes(
+                &mut cached,
+                format!("http://{i}"),
+                (routes.clone(), expires),
+                now,
+            );
+        }
+        assert_eq!(cached.len(), MAX_DESTINATIONS);
+
+        // the expired entry is pruned
+        let expires = now + Duration::from_secs(MAX_DESTINATIONS as u64);
+        cache_routes(
+            &mut cached,
+            "http://new".to_owned(),
+            (routes.clone(), expires),
+            now,
+        );
+        assert_eq!(cached.len(), MAX_DESTINATIONS);
+        assert!(!cached.contains_key("http://expired"));
+
+        // none expired, the one expiring first is evicted
+        cache_routes(
+            &mut cached,
+            "http://newer".to_owned(),
+            (routes, expires),
+            now,
+        );
+        assert_eq!(cached.len(), MAX_DESTINATIONS);
+        assert!(!cached.contains_key("http://1"));
+        assert!(cached.contains_key("http://new"));
+    }
+
+    #[cfg(feature = "socks")]
+    #[test]
+    fn parse_socks_results() {
+        assert_eq!(
+            parse_routes("SOCKS a.example:1080; SOCKS5 b.example:1080"),
+            vec![
+                PacRoute::Proxy("socks4", "a.example:1080".to_owned()),
+                PacRoute::Proxy("socks5", "b.example:1080".to_owned()),
+            ]
+        );
+    }
+}
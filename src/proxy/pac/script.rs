@@ -0,0 +1,1492 @@
+//! A restricted interpreter of the JavaScript PAC scripts are written in.
+//!
+//! It covers what PAC scripts commonly use: functions, `var`/`let`/`const` declarations,
+//! `if`/`else`, `for` and `while` loops, strings, numbers, booleans and array literals, the
+//! usual operators, a few string and array methods, and the PAC functions of
+//! [`functions`](super::functions). Objects, closures, regular expressions and exceptions are
+//! not supported, and fail the evaluation.
+
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use super::{MAX_SCRIPT_SIZE, functions};
+
+/// The number of steps an evaluation may take before it is aborted.
+const MAX_STEPS: usize = 1_000_000;
+
+/// The length of the longest string an evaluation may build, in bytes.
+const MAX_STRING_LEN: usize = MAX_SCRIPT_SIZE;
+
+/// The depth of function calls an evaluation may reach before it is aborted.
+const MAX_DEPTH: usize = 64;
+
+/// The depth of nested statements and expressions a script may have.
+const MAX_NESTING: usize = 128;
+
+/// The depth of nested statements and expressions an evaluation may reach, across function
+/// calls, before it is aborted.
+const MAX_EVAL_NESTING: usize = 512;
+
+/// A parsed PAC script.
+#[derive(Debug)]
+pub(crate) struct Script {
+    body: Vec<Stmt>,
+}
+
+/// A value of the interpreter.
+#[derive(Debug, Clone)]
+pub(crate) enum Value {
+    Undefined,
+    Null,
+    Bool(bool),
+    Number(f64),
+    Str(Arc<str>),
+    Array(Arc<Vec<Value>>),
+    Function(Arc<Function>),
+}
+
+#[derive(Debug)]
+pub(crate) struct Function {
+    name: String,
+    params: Vec<String>,
+    body: Vec<Stmt>,
+}
+
+#[derive(Debug)]
+enum Stmt {
+    Empty,
+    Function(Arc<Function>),
+    Var(Vec<(String, Option<Expr>)>),
+    Expr(Expr),
+    Block(Vec<Stmt>),
+    /// The conditions and statements of an `if` and its `else if`s, and the `else` statement.
+    If(Vec<(Expr, Stmt)>, Option<Box<Stmt>>),
+    For(Option<Box<Stmt>>, Option<Expr>, Option<Expr>, Box<Stmt>),
+    While(Expr, Box<Stmt>),
+    Return(Option<Expr>),
+    Break,
+    Continue,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Literal(Value),
+    Ident(String),
+    Array(Vec<Expr>),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    /// An operand, and the operators of the same precedence applied to it from left to right
+    /// with their right operand.
+    Binary(Box<Expr>, Vec<(BinOp, Expr)>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Cond(Box<Expr>, Box<Expr>, Box<Expr>),
+    Assign(String, Option<BinOp>, Box<Expr>),
+    /// `++x`, `x--`: the variable, the delta, and whether the new value is the result.
+    Update(String, f64, bool),
+    Call(Box<Expr>, Vec<Expr>),
+    Member(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    StrictEq,
+    StrictNe,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+}
+
+/// An error parsing or evaluating a script.
+#[derive(Debug)]
+pub(crate) struct ScriptError(String);
+
+impl ScriptError {
+    pub(super) fn new(message: impl Into<String>) -> ScriptError {
+        ScriptError(message.into())
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+fn error<T>(message: impl Into<String>) -> Result<T, ScriptError> {
+    Err(ScriptError::new(message))
+}
+
+/// Returns the string `s` built by the script, unless it is longer than `MAX_STRING_LEN`.
+fn built_str(s: String) -> Result<Value, ScriptError> {
+    if s.len() > MAX_STRING_LEN {
+        return error(format!(
+            "the script built a string longer than {MAX_STRING_LEN} bytes"
+        ));
+    }
+    Ok(Value::str(s))
+}
+
+impl Script {
+    /// Parses a script.
+    pub(crate) fn parse(source: &str) -> Result<Script, ScriptError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            depth: 0,
+        };
+        let mut body = Vec::new();
+        while !parser.at_end() {
+            body.push(parser.statement()?);
+        }
+
+        let script = Script { body };
+        let defines = script.body.iter().any(
+            |stmt| matches!(stmt, Stmt::Function(function) if function.name == "FindProxyForURL"),
+        );
+        if !defines {
+            return error("the script does not define `FindProxyForURL`");
+        }
+        Ok(script)
+    }
+
+    /// Calls `FindProxyForURL(url, host)`, returning the string it returns.
+    pub(crate) fn find_proxy_for_url(&self, url: &str, host: &str) -> Result<String, ScriptError> {
+        let mut interp = Interpreter {
+            globals: HashMap::new(),
+            steps: 0,
+            depth: 0,
+            nesting: 0,
+        };
+        let mut scope = None;
+        interp.block(&self.body, &mut scope)?;
+
+        let find = match interp.globals.get("FindProxyForURL") {
+            Some(Value::Function(function)) => function.clone(),
+            _ => return error("`FindProxyForURL` is not a function"),
+        };
+        let args = vec![Value::Str(url.into()), Value::Str(host.into())];
+        match interp.call(&find, args)? {
+            Value::Str(result) => Ok(result.to_string()),
+            Value::Undefined | Value::Null => Ok(String::new()),
+            other => error(format!(
+                "`FindProxyForURL` returned {} instead of a string",
+                other.to_display()
+            )),
+        }
+    }
+}
+
+// ===== values =====
+
+impl Value {
+    pub(crate) fn str(value: impl Into<Arc<str>>) -> Value {
+        Value::Str(value.into())
+    }
+
+    pub(crate) fn truthy(&self) -> bool {
+        match self {
+            Value::Undefined | Value::Null => false,
+            Value::Bool(b) => *b,
+            Value::Number(n) => *n != 0.0 && !n.is_nan(),
+            Value::Str(s) => !s.is_empty(),
+            Value::Array(_) | Value::Function(_) => true,
+        }
+    }
+
+    pub(crate) fn to_number(&self) -> f64 {
+        match self {
+            Value::Undefined => f64::NAN,
+            Value::Null => 0.0,
+            Value::Bool(b) => f64::from(u8::from(*b)),
+            Value::Number(n) => *n,
+            Value::Str(s) => {
+                let s = s.trim();
+                if s.is_empty() {
+                    0.0
+                } else {
+                    s.parse().unwrap_or(f64::NAN)
+                }
+            }
+            Value::Array(_) | Value::Function(_) => f64::NAN,
+        }
+    }
+
+    /// Returns the value as a string. The string of an array stops a little past
+    /// `MAX_STRING_LEN`, so that arrays nesting themselves cannot take all memory.
+    pub(crate) fn to_display(&self) -> String {
+        match self {
+            Value::Undefined => "undefined".to_owned(),
+            Value::Null => "null".to_owned(),
+            Value::Bool(b) => b.to_string(),
+            Value::Number(n) => format_number(*n),
+            Value::Str(s) => s.to_string(),
+            Value::Array(items) => {
+                let mut display = String::new();
+                for (i, item) in items.iter().enumerate() {
+                    if display.len() > MAX_STRING_LEN {
+                        break;
+                    }
+                    if i > 0 {
+                        display.push(',');
+                    }
+                    if !matches!(item, Value::Undefined | Value::Null) {
+                        display.push_str(&item.to_display());
+                    }
+                }
+                display
+            }
+            Value::Function(function) => format!("function {}()", function.name),
+        }
+    }
+
+    fn loose_eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Undefined | Value::Null, Value::Undefined | Value::Null) => true,
+            (Value::Undefined | Value::Null, _) | (_, Value::Undefined | Value::Null) => false,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => Arc::ptr_eq(a, b),
+            (Value::Function(a), Value::Function(b)) => Arc::ptr_eq(a, b),
+            (a, b) => a.to_number() == b.to_number(),
+        }
+    }
+
+    fn strict_eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Undefined, Value::Undefined) | (Value::Null, Value::Null) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => Arc::ptr_eq(a, b),
+            (Value::Function(a), Value::Function(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+fn format_number(n: f64) -> String {
+    if n.is_nan() {
+        "NaN".to_owned()
+    } else if n.is_infinite() {
+        if n > 0.0 { "Infinity" } else { "-Infinity" }.to_owned()
+    } else if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        n.to_string()
+    }
+}
+
+// ===== tokens =====
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Number(f64),
+    Punct(&'static str),
+}
+
+/// The punctuators, longest first.
+const PUNCTUATORS: &[&str] = &[
+    "===", "!==", "==", "!=", "<=", ">=", "&&", "||", "++", "--", "+=", "-=", "*=", "/=", "(", ")",
+    "{", "}", "[", "]", ";", ",", ".", "?", ":", "!", "<", ">", "+", "-", "*", "/", "%", "=",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ScriptError> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+
+    loop {
+        rest = rest.trim_start();
+        if let Some(comment) = rest.strip_prefix("//") {
+            rest = comment.find('\n').map_or("", |end| &comment[end..]);
+            continue;
+        }
+        if let Some(comment) = rest.strip_prefix("/*") {
+            match comment.find("*/") {
+                Some(end) => rest = &comment[end + 2..],
+                None => return error("unterminated comment"),
+            }
+            continue;
+        }
+
+        let Some(c) = rest.chars().next() else {
+            return Ok(tokens);
+        };
+
+        if c == '"' || c == '\'' {
+            let (value, after) = string_literal(&rest[1..], c)?;
+            tokens.push(Token::Str(value));
+            rest = after;
+        } else if c.is_ascii_digit()
+            || (c == '.' && rest[1..].starts_with(|c: char| c.is_ascii_digit()))
+        {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '.')
+                .unwrap_or(rest.len());
+            let literal = &rest[..end];
+            let number = match literal
+                .strip_prefix("0x")
+                .or_else(|| literal.strip_prefix("0X"))
+            {
+                Some(hex) => u64::from_str_radix(hex, 16).map(|n| n as f64).ok(),
+                None => literal.parse().ok(),
+            };
+            match number {
+                Some(number) => tokens.push(Token::Number(number)),
+                None => return error(format!("invalid number `{literal}`")),
+            }
+            rest = &rest[end..];
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            let end = rest
+                .find(|c: char| !c.is_alphanumeric() && c != '_' && c != '$')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_owned()));
+            rest = &rest[end..];
+        } else {
+            match PUNCTUATORS.iter().find(|punct| rest.starts_with(**punct)) {
+                Some(punct) => {
+                    tokens.push(Token::Punct(punct));
+                    rest = &rest[punct.len()..];
+                }
+                None => return error(format!("unexpected character `{c}`")),
+            }
+        }
+    }
+}
+
+/// Reads a string literal closed by `quote`, returning it and the rest after the quote.
+fn string_literal(source: &str, quote: char) -> Result<(String, &str), ScriptError> {
+    let mut value = String::new();
+    let mut chars = source.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c == quote => return Ok((value, &source[i + 1..])),
+            '\n' => break,
+            '\\' => match chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 't')) => value.push('\t'),
+                Some((_, 'r')) => value.push('\r'),
+                Some((_, '0')) => value.push('\0'),
+                Some((_, '\n')) => {}
+                Some((_, c)) => value.push(c),
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+    error("unterminated string")
+}
+
+// ===== parser =====
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// The nesting of the statement or expression being parsed.
+    depth: usize,
+}
+
+impl Parser {
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token, ScriptError> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        token.map_or_else(|| error("unexpected end of script"), Ok)
+    }
+
+    fn is_punct(&self, punct: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punct(p)) if *p == punct)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(ident)) if ident == keyword)
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        let found = self.is_punct(punct);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), ScriptError> {
+        if self.eat_punct(punct) {
+            Ok(())
+        } else {
+            error(format!("expected `{punct}`, found {}", self.describe()))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, ScriptError> {
+        match self.next()? {
+            Token::Ident(ident) => Ok(ident),
+            _ => {
+                self.pos -= 1;
+                error(format!("expected a name, found {}", self.describe()))
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self.peek() {
+            None => "the end of the script".to_owned(),
+            Some(Token::Ident(ident)) => format!("`{ident}`"),
+            Some(Token::Str(value)) => format!("{value:?}"),
+            Some(Token::Number(n)) => format_number(*n),
+            Some(Token::Punct(punct)) => format!("`{punct}`"),
+        }
+    }
+
+    /// Ends a statement, with an optional semicolon.
+    fn end_statement(&mut self) {
+        self.eat_punct(";");
+    }
+
+    /// Enters a level of nesting, failing past [`MAX_NESTING`].
+    ///
+    /// Deeply nested scripts would otherwise overflow the stack of the parser, or of the
+    /// interpreter.
+    fn enter(&mut self) -> Result<(), ScriptError> {
+        if self.depth >= MAX_NESTING {
+            return error("the script is nested too deeply");
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Parses with `parse` one level of nesting deeper.
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Parser) -> Result<T, ScriptError>,
+    ) -> Result<T, ScriptError> {
+        let depth = self.depth;
+        self.enter()?;
+        let parsed = parse(self);
+        self.depth = depth;
+        parsed
+    }
+
+    fn statement(&mut self) -> Result<Stmt, ScriptError> {
+        self.nested(Parser::nested_statement)
+    }
+
+    fn nested_statement(&mut self) -> Result<Stmt, ScriptError> {
+        if self.eat_punct(";") {
+            return Ok(Stmt::Empty);
+        }
+        if self.eat_punct("{") {
+            return Ok(Stmt::Block(self.block_rest()?));
+        }
+        if self.eat_keyword("function") {
+            let name = self.ident()?;
+            let function = self.function_rest(name)?;
+            return Ok(Stmt::Function(Arc::new(function)));
+        }
+        if self.is_keyword("var") || self.is_keyword("let") || self.is_keyword("const") {
+            let stmt = self.declaration()?;
+            self.end_statement();
+            return Ok(stmt);
+        }
+        if self.eat_keyword("if") {
+            let mut branches = Vec::new();
+            let mut otherwise = None;
+            loop {
+                self.expect("(")?;
+                let cond = self.expression()?;
+                self.expect(")")?;
+                branches.push((cond, self.statement()?));
+                if !self.eat_keyword("else") {
+                    break;
+                }
+                // `else if` chains are kept flat, however long they are
+                if !self.eat_keyword("if") {
+                    otherwise = Some(Box::new(self.statement()?));
+                    break;
+                }
+            }
+            return Ok(Stmt::If(branches, otherwise));
+        }
+        if self.eat_keyword("for") {
+            self.expect("(")?;
+            let init = if self.is_punct(";") {
+                None
+            } else if self.is_keyword("var") || self.is_keyword("let") || self.is_keyword("const") {
+                Some(Box::new(self.declaration()?))
+            } else {
+                Some(Box::new(Stmt::Expr(self.expression()?)))
+            };
+            self.expect(";")?;
+            let cond = if self.is_punct(";") {
+                None
+            } else {
+                Some(self.expression()?)
+            };
+            self.expect(";")?;
+            let update = if self.is_punct(")") {
+                None
+            } else {
+                Some(self.expression()?)
+            };
+            self.expect(")")?;
+            let body = Box::new(self.statement()?);
+            return Ok(Stmt::For(init, cond, update, body));
+        }
+        if self.eat_keyword("while") {
+            self.expect("(")?;
+            let cond = self.expression()?;
+            self.expect(")")?;
+            let body = Box::new(self.statement()?);
+            return Ok(Stmt::While(cond, body));
+        }
+        if self.eat_keyword("return") {
+            let value = if self.at_end() || self.is_punct(";") || self.is_punct("}") {
+                None
+            } else {
+                Some(self.expression()?)
+            };
+            self.end_statement();
+            return Ok(Stmt::Return(value));
+        }
+        if self.eat_keyword("break") {
+            self.end_statement();
+            return Ok(Stmt::Break);
+        }
+        if self.eat_keyword("continue") {
+            self.end_statement();
+            return Ok(Stmt::Continue);
+        }
+
+        let expr = self.expression()?;
+        self.end_statement();
+        Ok(Stmt::Expr(expr))
+    }
+
+    /// Parses the statements of a block, after its opening brace.
+    fn block_rest(&mut self) -> Result<Vec<Stmt>, ScriptError> {
+        let mut body = Vec::new();
+        while !self.eat_punct("}") {
+            if self.at_end() {
+                return error("expected `}`, found the end of the script");
+            }
+            body.push(self.statement()?);
+        }
+        Ok(body)
+    }
+
+    /// Parses the parameters and body of a function, after its name.
+    fn function_rest(&mut self, name: String) -> Result<Function, ScriptError> {
+        self.expect("(")?;
+        let mut params = Vec::new();
+        while !self.eat_punct(")") {
+            params.push(self.ident()?);
+            if !self.is_punct(")") {
+                self.expect(",")?;
+            }
+        }
+        self.expect("{")?;
+        let body = self.block_rest()?;
+        Ok(Function { name, params, body })
+    }
+
+    fn declaration(&mut self) -> Result<Stmt, ScriptError> {
+        // the keyword
+        self.next()?;
+        let mut vars = Vec::new();
+        loop {
+            let name = self.ident()?;
+            let init = if self.eat_punct("=") {
+                Some(self.assignment()?)
+            } else {
+                None
+            };
+            vars.push((name, init));
+            if !self.eat_punct(",") {
+                return Ok(Stmt::Var(vars));
+            }
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expr, ScriptError> {
+        self.assignment()
+    }
+
+    fn assignment(&mut self) -> Result<Expr, ScriptError> {
+        self.nested(Parser::nested_assignment)
+    }
+
+    fn nested_assignment(&mut self) -> Result<Expr, ScriptError> {
+        let target = self.conditional()?;
+        let op = match self.peek() {
+            Some(Token::Punct("=")) => None,
+            Some(Token::Punct("+=")) => Some(BinOp::Add),
+            Some(Token::Punct("-=")) => Some(BinOp::Sub),
+            Some(Token::Punct("*=")) => Some(BinOp::Mul),
+            Some(Token::Punct("/=")) => Some(BinOp::Div),
+            _ => return Ok(target),
+        };
+        self.pos += 1;
+
+        let Expr::Ident(name) = target else {
+            return error("only variables can be assigned to");
+        };
+        let value = self.assignment()?;
+        Ok(Expr::Assign(name, op, Box::new(value)))
+    }
+
+    fn conditional(&mut self) -> Result<Expr, ScriptError> {
+        let cond = self.or()?;
+        if !self.eat_punct("?") {
+            return Ok(cond);
+        }
+        let then = self.assignment()?;
+        self.expect(":")?;
+        let otherwise = self.assignment()?;
+        Ok(Expr::Cond(
+            Box::new(cond),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    fn or(&mut self) -> Result<Expr, ScriptError> {
+        let first = self.and()?;
+        if !self.is_punct("||") {
+            return Ok(first);
+        }
+        let mut operands = vec![first];
+        while self.eat_punct("||") {
+            operands.push(self.and()?);
+        }
+        Ok(Expr::Or(operands))
+    }
+
+    fn and(&mut self) -> Result<Expr, ScriptError> {
+        let first = self.equality()?;
+        if !self.is_punct("&&") {
+            return Ok(first);
+        }
+        let mut operands = vec![first];
+        while self.eat_punct("&&") {
+            operands.push(self.equality()?);
+        }
+        Ok(Expr::And(operands))
+    }
+
+    fn equality(&mut self) -> Result<Expr, ScriptError> {
+        let left = self.relational()?;
+        let mut rest = Vec::new();
+        loop {
+            let op = match self.peek() {
+                Some(Token::Punct("==")) => BinOp::Eq,
+                Some(Token::Punct("!=")) => BinOp::Ne,
+                Some(Token::Punct("===")) => BinOp::StrictEq,
+                Some(Token::Punct("!==")) => BinOp::StrictNe,
+                _ => return Ok(binary_chain(left, rest)),
+            };
+            self.pos += 1;
+            rest.push((op, self.relational()?));
+        }
+    }
+
+    fn relational(&mut self) -> Result<Expr, ScriptError> {
+        let left = self.additive()?;
+        let mut rest = Vec::new();
+        loop {
+            let op = match self.peek() {
+                Some(Token::Punct("<")) => BinOp::Lt,
+                Some(Token::Punct(">")) => BinOp::Gt,
+                Some(Token::Punct("<=")) => BinOp::Le,
+                Some(Token::Punct(">=")) => BinOp::Ge,
+                _ => return Ok(binary_chain(left, rest)),
+            };
+            self.pos += 1;
+            rest.push((op, self.additive()?));
+        }
+    }
+
+    fn additive(&mut self) -> Result<Expr, ScriptError> {
+        let left = self.multiplicative()?;
+        let mut rest = Vec::new();
+        loop {
+            let op = match self.peek() {
+                Some(Token::Punct("+")) => BinOp::Add,
+                Some(Token::Punct("-")) => BinOp::Sub,
+                _ => return Ok(binary_chain(left, rest)),
+            };
+            self.pos += 1;
+            rest.push((op, self.multiplicative()?));
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, ScriptError> {
+        let left = self.unary()?;
+        let mut rest = Vec::new();
+        loop {
+            let op = match self.peek() {
+                Some(Token::Punct("*")) => BinOp::Mul,
+                Some(Token::Punct("/")) => BinOp::Div,
+                Some(Token::Punct("%")) => BinOp::Rem,
+                _ => return Ok(binary_chain(left, rest)),
+            };
+            self.pos += 1;
+            rest.push((op, self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ScriptError> {
+        if self.eat_punct("!") {
+            return Ok(Expr::Not(Box::new(self.nested(Parser::unary)?)));
+        }
+        if self.eat_punct("-") {
+            return Ok(Expr::Neg(Box::new(self.nested(Parser::unary)?)));
+        }
+        if self.eat_punct("+") {
+            let operand = self.nested(Parser::unary)?;
+            return Ok(Expr::Binary(
+                Box::new(operand),
+                vec![(BinOp::Sub, Expr::Literal(Value::Number(0.0)))],
+            ));
+        }
+        for (punct, delta) in [("++", 1.0), ("--", -1.0)] {
+            if self.eat_punct(punct) {
+                let Expr::Ident(name) = self.nested(Parser::unary)? else {
+                    return error(format!("only variables can be used with `{punct}`"));
+                };
+                return Ok(Expr::Update(name, delta, true));
+            }
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Expr, ScriptError> {
+        // every call, member or index is nested in the ones before
+        let depth = self.depth;
+        let mut expr = self.primary()?;
+        loop {
+            if self.eat_punct("(") {
+                self.enter()?;
+                let mut args = Vec::new();
+                while !self.eat_punct(")") {
+                    args.push(self.assignment()?);
+                    if !self.is_punct(")") {
+                        self.expect(",")?;
+                    }
+                }
+                expr = Expr::Call(Box::new(expr), args);
+            } else if self.eat_punct(".") {
+                self.enter()?;
+                expr = Expr::Member(Box::new(expr), self.ident()?);
+            } else if self.eat_punct("[") {
+                self.enter()?;
+                let index = self.expression()?;
+                self.expect("]")?;
+                expr = Expr::Index(Box::new(expr), Box::new(index));
+            } else {
+                for (punct, delta) in [("++", 1.0), ("--", -1.0)] {
+                    if self.is_punct(punct) {
+                        let Expr::Ident(name) = expr else {
+                            return error(format!("only variables can be used with `{punct}`"));
+                        };
+                        self.pos += 1;
+                        self.depth = depth;
+                        return Ok(Expr::Update(name, delta, false));
+                    }
+                }
+                self.depth = depth;
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, ScriptError> {
+        let expr = match self.next()? {
+            Token::Number(n) => Expr::Literal(Value::Number(n)),
+            Token::Str(value) => Expr::Literal(Value::str(value)),
+            Token::Punct("(") => {
+                let expr = self.expression()?;
+                self.expect(")")?;
+                expr
+            }
+            Token::Punct("[") => {
+                let mut items = Vec::new();
+                while !self.eat_punct("]") {
+                    items.push(self.assignment()?);
+                    if !self.is_punct("]") {
+                        self.expect(",")?;
+                    }
+                }
+                Expr::Array(items)
+            }
+            Token::Ident(ident) => match ident.as_str() {
+                "true" => Expr::Literal(Value::Bool(true)),
+                "false" => Expr::Literal(Value::Bool(false)),
+                "null" => Expr::Literal(Value::Null),
+                "undefined" => Expr::Literal(Value::Undefined),
+                "function" | "new" | "typeof" | "this" | "switch" | "try" | "throw" => {
+                    return error(format!("`{ident}` is not supported"));
+                }
+                _ => Expr::Ident(ident),
+            },
+            Token::Punct(_) => {
+                self.pos -= 1;
+                return error(format!("unexpected {}", self.describe()));
+            }
+        };
+        Ok(expr)
+    }
+}
+
+/// Returns the expression applying the operators of `rest` to `first`, from left to right.
+fn binary_chain(first: Expr, rest: Vec<(BinOp, Expr)>) -> Expr {
+    if rest.is_empty() {
+        first
+    } else {
+        Expr::Binary(Box::new(first), rest)
+    }
+}
+
+// ===== interpreter =====
+
+enum Flow {
+    Normal,
+    Return(Value),
+    Break,
+    Continue,
+}
+
+struct Interpreter {
+    globals: HashMap<String, Value>,
+    steps: usize,
+    depth: usize,
+    /// The nesting of the statement or expression being evaluated, across function calls.
+    nesting: usize,
+}
+
+/// The variables of the function being called, `None` at the top level of the script.
+type Scope = Option<HashMap<String, Value>>;
+
+impl Interpreter {
+    fn step(&mut self) -> Result<(), ScriptError> {
+        self.steps += 1;
+        if self.steps > MAX_STEPS {
+            return error("the script runs for too long");
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, body: &[Stmt], scope: &mut Scope) -> Result<Flow, ScriptError> {
+        // function declarations are hoisted
+        for stmt in body {
+            if let Stmt::Function(function) = stmt {
+                let value = Value::Function(function.clone());
+                self.declare(scope, function.name.clone(), value);
+            }
+        }
+
+        for stmt in body {
+            match self.exec(stmt, scope)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    /// Enters a level of nesting, failing past [`MAX_EVAL_NESTING`].
+    fn enter(&mut self) -> Result<(), ScriptError> {
+        if self.nesting >= MAX_EVAL_NESTING {
+            return error("the script is nested too deeply");
+        }
+        self.nesting += 1;
+        Ok(())
+    }
+
+    fn exec(&mut self, stmt: &Stmt, scope: &mut Scope) -> Result<Flow, ScriptError> {
+        self.step()?;
+        self.enter()?;
+        let flow = self.nested_exec(stmt, scope);
+        self.nesting -= 1;
+        flow
+    }
+
+    fn nested_exec(&mut self, stmt: &Stmt, scope: &mut Scope) -> Result<Flow, ScriptError> {
+        match stmt {
+            Stmt::Empty | Stmt::Function(_) => {}
+            Stmt::Var(vars) => {
+                for (name, init) in vars {
+                    let value = match init {
+                        Some(init) => self.eval(init, scope)?,
+                        None => Value::Undefined,
+                    };
+                    self.declare(scope, name.clone(), value);
+                }
+            }
+            Stmt::Expr(expr) => {
+                self.eval(expr, scope)?;
+            }
+            Stmt::Block(body) => return self.block(body, scope),
+            Stmt::If(branches, otherwise) => {
+                for (cond, then) in branches {
+                    if self.eval(cond, scope)?.truthy() {
+                        return self.exec(then, scope);
+                    }
+                }
+                if let Some(otherwise) = otherwise {
+                    return self.exec(otherwise, scope);
+                }
+            }
+            Stmt::For(init, cond, update, body) => {
+                if let Some(init) = init {
+                    self.exec(init, scope)?;
+                }
+                loop {
+                    if let Some(cond) = cond {
+                        if !self.eval(cond, scope)?.truthy() {
+                            break;
+                        }
+                    }
+                    match self.exec(body, scope)? {
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Break => break,
+                        Flow::Normal | Flow::Continue => {}
+                    }
+                    if let Some(update) = update {
+                        self.eval(update, scope)?;
+                    }
+                }
+            }
+            Stmt::While(cond, body) => {
+                while self.eval(cond, scope)?.truthy() {
+                    match self.exec(body, scope)? {
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Break => break,
+                        Flow::Normal | Flow::Continue => {}
+                    }
+                }
+            }
+            Stmt::Return(value) => {
+                let value = match value {
+                    Some(value) => self.eval(value, scope)?,
+                    None => Value::Undefined,
+                };
+                return Ok(Flow::Return(value));
+            }
+            Stmt::Break => return Ok(Flow::Break),
+            Stmt::Continue => return Ok(Flow::Continue),
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn declare(&mut self, scope: &mut Scope, name: String, value: Value) {
+        match scope {
+            Some(locals) => locals.insert(name, value),
+            None => self.globals.insert(name, value),
+        };
+    }
+
+    fn lookup(&self, scope: &Scope, name: &str) -> Option<Value> {
+        scope
+            .as_ref()
+            .and_then(|locals| locals.get(name))
+            .or_else(|| self.globals.get(name))
+            .cloned()
+    }
+
+    fn assign(&mut self, scope: &mut Scope, name: &str, value: Value) {
+        match scope {
+            Some(locals) if locals.contains_key(name) => {
+                locals.insert(name.to_owned(), value);
+            }
+            // assigning an undeclared variable creates a global one
+            _ => {
+                self.globals.insert(name.to_owned(), value);
+            }
+        }
+    }
+
+    fn eval(&mut self, expr: &Expr, scope: &mut Scope) -> Result<Value, ScriptError> {
+        self.step()?;
+        self.enter()?;
+        let value = self.nested_eval(expr, scope);
+        self.nesting -= 1;
+        value
+    }
+
+    fn nested_eval(&mut self, expr: &Expr, scope: &mut Scope) -> Result<Value, ScriptError> {
+        let value = match expr {
+            Expr::Literal(value) => value.clone(),
+            Expr::Ident(name) => match self.lookup(scope, name) {
+                Some(value) => value,
+                None => return error(format!("`{name}` is not defined")),
+            },
+            Expr::Array(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| self.eval(item, scope))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Value::Array(Arc::new(items))
+            }
+            Expr::Not(operand) => Value::Bool(!self.eval(operand, scope)?.truthy()),
+            Expr::Neg(operand) => Value::Number(-self.eval(operand, scope)?.to_number()),
+            Expr::Binary(first, rest) => {
+                let mut left = self.eval(first, scope)?;
+                for (op, right) in rest {
+                    let right = self.eval(right, scope)?;
+                    left = binary(*op, &left, &right)?;
+                }
+                left
+            }
+            // the first falsy operand, or else the last
+            Expr::And(operands) => {
+                let mut value = Value::Undefined;
+                for operand in operands {
+                    value = self.eval(operand, scope)?;
+                    if !value.truthy() {
+                        break;
+                    }
+                }
+                value
+            }
+            // the first truthy operand, or else the last
+            Expr::Or(operands) => {
+                let mut value = Value::Undefined;
+                for operand in operands {
+                    value = self.eval(operand, scope)?;
+                    if value.truthy() {
+                        break;
+                    }
+                }
+                value
+            }
+            Expr::Cond(cond, then, otherwise) => {
+                if self.eval(cond, scope)?.truthy() {
+                    self.eval(then, scope)?
+                } else {
+                    self.eval(otherwise, scope)?
+                }
+            }
+            Expr::Assign(name, op, value) => {
+                let value = self.eval(value, scope)?;
+                let value = match op {
+                    Some(op) => {
+                        let Some(current) = self.lookup(scope, name) else {
+                            return error(format!("`{name}` is not defined"));
+                        };
+                        binary(*op, &current, &value)?
+                    }
+                    None => value,
+                };
+                self.assign(scope, name, value.clone());
+                value
+            }
+            Expr::Update(name, delta, prefix) => {
+                let Some(current) = self.lookup(scope, name) else {
+                    return error(format!("`{name}` is not defined"));
+                };
+                let old = current.to_number();
+                let new = old + delta;
+                self.assign(scope, name, Value::Number(new));
+                Value::Number(if *prefix { new } else { old })
+            }
+            Expr::Call(callee, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg, scope))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match &**callee {
+                    Expr::Member(object, method) => {
+                        let object = self.eval(object, scope)?;
+                        call_method(&object, method, &args)?
+                    }
+                    Expr::Ident(name) => match self.lookup(scope, name) {
+                        Some(Value::Function(function)) => self.call(&function, args)?,
+                        Some(_) => return error(format!("`{name}` is not a function")),
+                        None => functions::call(name, &args)?,
+                    },
+                    callee => match self.eval(callee, scope)? {
+                        Value::Function(function) => self.call(&function, args)?,
+                        _ => return error("only functions can be called"),
+                    },
+                }
+            }
+            Expr::Member(object, property) => {
+                let object = self.eval(object, scope)?;
+                match (&object, property.as_str()) {
+                    (Value::Str(s), "length") => Value::Number(s.encode_utf16().count() as f64),
+                    (Value::Array(items), "length") => Value::Number(items.len() as f64),
+                    _ => Value::Undefined,
+                }
+            }
+            Expr::Index(object, index) => {
+                let object = self.eval(object, scope)?;
+                let index = self.eval(index, scope)?.to_number();
+                match object {
+                    Value::Array(items) if index >= 0.0 && index.fract() == 0.0 => items
+                        .get(index as usize)
+                        .cloned()
+                        .unwrap_or(Value::Undefined),
+                    Value::Str(s) if index >= 0.0 && index.fract() == 0.0 => s
+                        .chars()
+                        .nth(index as usize)
+                        .map_or(Value::Undefined, |c| Value::str(c.to_string())),
+                    _ => Value::Undefined,
+                }
+            }
+        };
+        Ok(value)
+    }
+
+    fn call(&mut self, function: &Function, args: Vec<Value>) -> Result<Value, ScriptError> {
+        if self.depth >= MAX_DEPTH {
+            return error("too many nested function calls");
+        }
+
+        let mut locals = HashMap::new();
+        let mut args = args.into_iter();
+        for param in &function.params {
+            locals.insert(param.clone(), args.next().unwrap_or(Value::Undefined));
+        }
+
+        self.depth += 1;
+        let flow = self.block(&function.body, &mut Some(locals));
+        self.depth -= 1;
+        match flow? {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(Value::Undefined),
+        }
+    }
+}
+
+fn binary(op: BinOp, left: &Value, right: &Value) -> Result<Value, ScriptError> {
+    let numbers = || (left.to_number(), right.to_number());
+    let value = match op {
+        BinOp::Add => match (left, right) {
+            (Value::Str(_), _)
+            | (_, Value::Str(_))
+            | (Value::Array(_), _)
+            | (_, Value::Array(_)) => return built_str(left.to_display() + &right.to_display()),
+            _ => Value::Number(left.to_number() + right.to_number()),
+        },
+        BinOp::Sub => Value::Number(left.to_number() - right.to_number()),
+        BinOp::Mul => Value::Number(left.to_number() * right.to_number()),
+        BinOp::Div => Value::Number(left.to_number() / right.to_number()),
+        BinOp::Rem => Value::Number(left.to_number() % right.to_number()),
+        BinOp::Eq => Value::Bool(left.loose_eq(right)),
+        BinOp::Ne => Value::Bool(!left.loose_eq(right)),
+        BinOp::StrictEq => Value::Bool(left.strict_eq(right)),
+        BinOp::StrictNe => Value::Bool(!left.strict_eq(right)),
+        BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge => {
+            let ordering = match (left, right) {
+                (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+                _ => {
+                    let (a, b) = numbers();
+                    a.partial_cmp(&b)
+                }
+            };
+            Value::Bool(ordering.is_some_and(|ordering| match op {
+                BinOp::Lt => ordering.is_lt(),
+                BinOp::Gt => ordering.is_gt(),
+                BinOp::Le => ordering.is_le(),
+                _ => ordering.is_ge(),
+            }))
+        }
+    };
+    Ok(value)
+}
+
+/// Calls a method of a string or an array.
+fn call_method(object: &Value, method: &str, args: &[Value]) -> Result<Value, ScriptError> {
+    let arg = |i: usize| args.get(i).cloned().unwrap_or(Value::Undefined);
+    let string_arg = |i: usize| arg(i).to_display();
+    // an index argument, clamped to `0..=len`
+    let index_arg = |i: usize, len: usize, default: usize| match arg(i) {
+        Value::Undefined => default,
+        value => {
+            let n = value.to_number();
+            if n.is_nan() || n < 0.0 {
+                0
+            } else {
+                (n as usize).min(len)
+            }
+        }
+    };
+
+    let value = match object {
+        Value::Str(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            let len = chars.len();
+            let position = |found: Option<usize>| {
+                Value::Number(found.map_or(-1.0, |byte| s[..byte].chars().count() as f64))
+            };
+            match method {
+                "toLowerCase" => built_str(s.to_lowercase())?,
+                "toUpperCase" => built_str(s.to_uppercase())?,
+                "trim" => Value::str(s.trim()),
+                "indexOf" => position(s.find(&*string_arg(0))),
+                "lastIndexOf" => position(s.rfind(&*string_arg(0))),
+                "includes" => Value::Bool(s.contains(&*string_arg(0))),
+                "startsWith" => Value::Bool(s.starts_with(&*string_arg(0))),
+                "endsWith" => Value::Bool(s.ends_with(&*string_arg(0))),
+                "charAt" => {
+                    let i = index_arg(0, len, 0);
+                    Value::str(chars.get(i).map(char::to_string).unwrap_or_default())
+                }
+                "substring" => {
+                    let a = index_arg(0, len, 0);
+                    let b = index_arg(1, len, len);
+                    let (a, b) = (a.min(b), a.max(b));
+                    Value::str(chars[a..b].iter().collect::<String>())
+                }
+                "substr" => {
+                    let start = index_arg(0, len, 0);
+                    let count = index_arg(1, len - start, len - start);
+                    Value::str(chars[start..start + count].iter().collect::<String>())
+                }
+                "split" => {
+                    let parts: Vec<Value> = match arg(0) {
+                        Value::Undefined => vec![object.clone()],
+                        separator => {
+                            let separator = separator.to_display();
+                            if separator.is_empty() {
+                                chars.iter().map(|c| Value::str(c.to_string())).collect()
+                            } else {
+                                s.split(&*separator).map(Value::str).collect()
+                            }
+                        }
+                    };
+                    Value::Array(Arc::new(parts))
+                }
+                _ => return error(format!("strings have no method `{method}`")),
+            }
+        }
+        Value::Array(items) => match method {
+            "indexOf" => {
+                let needle = arg(0);
+                let found = items.iter().position(|item| item.strict_eq(&needle));
+                Value::Number(found.map_or(-1.0, |i| i as f64))
+            }
+            "join" => {
+                let separator = match arg(0) {
+                    Value::Undefined => ",".to_owned(),
+                    separator => separator.to_display(),
+                };
+                let items: Vec<_> = items
+                    .iter()
+                    .map(|item| match item {
+                        Value::Undefined | Value::Null => String::new(),
+                        item => item.to_display(),
+                    })
+                    .collect();
+                built_str(items.join(&separator))?
+            }
+            _ => return error(format!("arrays have no method `{method}`")),
+        },
+        _ => return error(format!("cannot call `{method}` on {}", object.to_display())),
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(script: &str, url: &str, host: &str) -> String {
+        Script::parse(script)
+            .unwrap()
+            .find_proxy_for_url(url, host)
+            .unwrap()
+    }
+
+    #[test]
+    fn evaluates_common_scripts() {
+        let script = r#"
+            // the hosts reached directly
+            var direct = ["intranet.example", "wiki.example"];
+
+            function isDirect(host) {
+                for (var i = 0; i < direct.length; i++) {
+                    if (dnsDomainIs(host, direct[i])) return true;
+                }
+                return false;
+            }
+
+            function FindProxyForURL(url, host) {
+                host = host.toLowerCase();
+                if (isPlainHostName(host) || isDirect(host))
+                    return "DIRECT";
+                /* plain HTTP uses another proxy */
+                if (url.substring(0, 5) == 'http:') {
+                    return "PROXY http-proxy.example:3128; DIRECT";
+                }
+                return shExpMatch(host, "*.eu.example") ? 'SOCKS5 eu.example:1080' : "PROXY "
+                    + "proxy.example" + ":" + (8000 + 80);
+            }
+        "#;
+
+        assert_eq!(find(script, "https://printer/", "printer"), "DIRECT");
+        assert_eq!(
+            find(script, "https://a.wiki.example/", "A.Wiki.Example"),
+            "DIRECT"
+        );
+        assert_eq!(
+            find(script, "http://hyper.rs/", "hyper.rs"),
+            "PROXY http-proxy.example:3128; DIRECT"
+        );
+        assert_eq!(
+            find(script, "https://shop.eu.example/", "shop.eu.example"),
+            "SOCKS5 eu.example:1080"
+        );
+        assert_eq!(
+            find(script, "https://hyper.rs/", "hyper.rs"),
+            "PROXY proxy.example:8080"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_scripts() {
+        let cases = [
+            (
+                "function FindProxyForURL(url, host) { return \"DIRECT\"",
+                "expected `}`",
+            ),
+            (
+                "function find(url, host) { return 'DIRECT'; }",
+                "does not define",
+            ),
+            ("var s = 'unterminated;", "unterminated string"),
+            (
+                "function FindProxyForURL(url, host) { return new Date(); }",
+                "not supported",
+            ),
+        ];
+        for (script, message) in cases {
+            let err = Script::parse(script).unwrap_err().to_string();
+            assert!(err.contains(message), "{script:?}: {err}");
+        }
+
+        let endless = Script::parse(
+            "function FindProxyForURL(url, host) { while (true) {} return 'DIRECT'; }",
+        )
+        .unwrap();
+        let err = endless.find_proxy_for_url("http://a/", "a").unwrap_err();
+        assert!(err.to_string().contains("too long"), "{err}");
+
+        let doubling = Script::parse(
+            "function FindProxyForURL(url, host) { var s = 'x'; while (true) s = s + s; }",
+        )
+        .unwrap();
+        let err = doubling.find_proxy_for_url("http://a/", "a").unwrap_err();
+        assert!(err.to_string().contains("longer than"), "{err}");
+
+        let appending = Script::parse(
+            "function FindProxyForURL(url, host) { var s = 'x'; while (true) s += s; }",
+        )
+        .unwrap();
+        let err = appending.find_proxy_for_url("http://a/", "a").unwrap_err();
+        assert!(err.to_string().contains("longer than"), "{err}");
+
+        let joining = Script::parse(
+            "function FindProxyForURL(url, host) { var a = ['x']; while (true) a = [a.join(), a.join()]; }",
+        )
+        .unwrap();
+        let err = joining.find_proxy_for_url("http://a/", "a").unwrap_err();
+        assert!(err.to_string().contains("longer than"), "{err}");
+
+        let recursive = Script::parse(
+            "function FindProxyForURL(url, host) { return FindProxyForURL(url, host); }",
+        )
+        .unwrap();
+        let err = recursive.find_proxy_for_url("http://a/", "a").unwrap_err();
+        assert!(err.to_string().contains("nested"), "{err}");
+    }
+
+    #[test]
+    fn rejects_deeply_nested_scripts() {
+        let define = |body: String| format!("function FindProxyForURL(url, host) {{ {body} }}");
+        let cases = [
+            format!(
+                "return {}'DIRECT'{};",
+                "(".repeat(100_000),
+                ")".repeat(100_000)
+            ),
+            format!(
+                "return {}'DIRECT'{};",
+                "[".repeat(100_000),
+                "]".repeat(100_000)
+            ),
+            format!(
+                "{}return 'DIRECT';{}",
+                "{".repeat(100_000),
+                "}".repeat(100_000)
+            ),
+            format!("{}return 'DIRECT';", "if (true) ".repeat(100_000)),
+            format!("return {}'DIRECT';", "!".repeat(100_000)),
+            format!("return host{};", ".length".repeat(100_000)),
+        ];
+        for body in cases {
+            let err = Script::parse(&define(body)).unwrap_err();
+            assert!(err.to_string().contains("nested too deeply"), "{err}");
+        }
+    }
+
+    #[test]
+    fn evaluates_long_chains() {
+        // chains of operators and of `else if`s are not nested
+        let direct = (0..10_000)
+            .map(|i| format!("host == 'h{i}.example'"))
+            .collect::<Vec<_>>()
+            .join(" || ");
+        let proxies = (0..10_000)
+            .map(|i| format!("if (host == 'p{i}.example') return 'PROXY p{i}:8080';"))
+            .collect::<Vec<_>>()
+            .join(" else ");
+        let port = vec!["1"; 10_000].join(" + ");
+        let script = format!(
+            "function FindProxyForURL(url, host) {{
+                if ({direct}) return 'DIRECT';
+                {proxies} else return 'PROXY fallback.example:' + ({port});
+            }}"
+        );
+
+        assert_eq!(
+            find(&script, "http://h9999.example/", "h9999.example"),
+            "DIRECT"
+        );
+        assert_eq!(
+            find(&script, "http://p9999.example/", "p9999.example"),
+            "PROXY p9999:8080"
+        );
+        assert_eq!(
+            find(&script, "http://x.example/", "x.example"),
+            "PROXY fallback.example:10000"
+        );
+    }
+}
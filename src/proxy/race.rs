@@ -0,0 +1,142 @@
+//! Racing tunnel establishment across the proxies intercepting a request.
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use http::{
+    Uri,
+    uri::{Authority, Scheme},
+};
+
+use crate::sync::Mutex;
+
+/// How long the proxy that won a race is tried first for the same destination.
+const AFFINITY: Duration = Duration::from_secs(30);
+
+/// Destinations remembered before expired winners are pruned.
+const MAX_DESTINATIONS: usize = 256;
+
+/// Races the tunnels through up to `candidates` proxies, starting one every `stagger`.
+#[derive(Clone)]
+pub(crate) struct ProxyRace {
+    candidates: usize,
+    stagger: Duration,
+    affinity: Arc<Mutex<HashMap<(Scheme, Authority), Winner>>>,
+}
+
+struct Winner {
+    proxy: Uri,
+    won: Instant,
+}
+
+// ===== impl ProxyRace =====
+
+impl ProxyRace {
+    pub(crate) fn new(candidates: usize, stagger: Duration) -> ProxyRace {
+        ProxyRace {
+            candidates: candidates.max(1),
+            stagger,
+            affinity: Default::default(),
+        }
+    }
+
+    /// Returns the delay after which the next candidate is started.
+    #[inline]
+    pub(crate) fn stagger(&self) -> Duration {
+        self.stagger
+    }
+
+    /// Puts the proxy that last won a race for `dst` first, and keeps the allowed number of
+    /// candidates.
+    pub(crate) fn order<T, F>(&self, dst: &Uri, candidates: &mut Vec<T>, uri: F)
+    where
+        F: Fn(&T) -> &Uri,
+    {
+        if let Some(key) = key(dst) {
+            let affinity = self.affinity.lock();
+            if let Some(winner) = affinity.get(&key).filter(|w| w.won.elapsed() < AFFINITY) {
+                if let Some(pos) = candidates.iter().position(|c| *uri(c) == winner.proxy) {
+                    let candidate = candidates.remove(pos);
+                    candidates.insert(0, candidate);
+                }
+            }
+        }
+        candidates.truncate(self.candidates);
+    }
+
+    /// Remembers `proxy` as the winner of a race for `dst`.
+    pub(crate) fn won(&self, dst: &Uri, proxy: &Uri) {
+        let Some(key) = key(dst) else {
+            return;
+        };
+
+        let mut affinity = self.affinity.lock();
+        if affinity.len() >= MAX_DESTINATIONS {
+            affinity.retain(|_, winner| winner.won.elapsed() < AFFINITY);
+        }
+        affinity.insert(
+            key,
+            Winner {
+                proxy: proxy.clone(),
+                won: Instant::now(),
+            },
+        );
+    }
+
+    /// Forgets `proxy` as the winner for `dst` after it failed.
+    pub(crate) fn failed(&self, dst: &Uri, proxy: &Uri) {
+        if let Some(key) = key(dst) {
+            let mut affinity = self.affinity.lock();
+            if affinity
+                .get(&key)
+                .is_some_and(|winner| winner.proxy == *proxy)
+            {
+                affinity.remove(&key);
+            }
+        }
+    }
+}
+
+fn key(dst: &Uri) -> Option<(Scheme, Authority)> {
+    Some((dst.scheme()?.clone(), dst.authority()?.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn winner_is_tried_first() {
+        let dst = Uri::from_static("https://example.com/");
+        let a = Uri::from_static("http://a.proxy");
+        let b = Uri::from_static("http://b.proxy");
+        let c = Uri::from_static("http://c.proxy");
+
+        let race = ProxyRace::new(2, Duration::from_millis(100));
+        let mut candidates = vec![&a, &b, &c];
+        race.order(&dst, &mut candidates, |uri: &&Uri| *uri);
+        assert_eq!(candidates, [&a, &b]);
+
+        race.won(&dst, &c);
+        let mut candidates = vec![&a, &b, &c];
+        race.order(&dst, &mut candidates, |uri: &&Uri| *uri);
+        assert_eq!(candidates, [&c, &a]);
+
+        // other destinations are not affected
+        let mut candidates = vec![&a, &b, &c];
+        race.order(
+            &Uri::from_static("https://other.com/"),
+            &mut candidates,
+            |uri: &&Uri| *uri,
+        );
+        assert_eq!(candidates, [&a, &b]);
+
+        race.failed(&dst, &c);
+        let mut candidates = vec![&a, &b, &c];
+        race.order(&dst, &mut candidates, |uri: &&Uri| *uri);
+        assert_eq!(candidates, [&a, &b]);
+    }
+}
@@ -0,0 +1,36 @@
+use http::Uri;
+
+use super::Proxy;
+
+/// Chooses the proxy of each request, for routing rules that static proxies cannot express.
+///
+/// See [`ClientBuilder::proxy_selector`](crate::ClientBuilder::proxy_selector).
+///
+/// # Example
+///
+/// ```
+/// use http::Uri;
+/// use wreq::{Proxy, ProxySelector};
+///
+/// struct ByDomain {
+///     datacenter: Proxy,
+///     residential: Proxy,
+/// }
+///
+/// impl ProxySelector for ByDomain {
+///     fn select(&self, uri: &Uri) -> Option<Proxy> {
+///         match uri.host()? {
+///             host if host.ends_with(".shop.example") => Some(self.residential.clone()),
+///             host if host.ends_with(".api.example") => Some(self.datacenter.clone()),
+///             _ => None,
+///         }
+///     }
+/// }
+/// ```
+pub trait ProxySelector: Send + Sync {
+    /// Returns the proxy of a request to `uri`, or `None` to use the proxies of the client.
+    ///
+    /// It is called for every request, and each redirect, so it may return another proxy for
+    /// the same destination over time.
+    fn select(&self, uri: &Uri) -> Option<Proxy>;
+}
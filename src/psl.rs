@@ -0,0 +1,245 @@
+//! Public suffix list
+//!
+//! Computes registrable domains (eTLD+1) from the [Public Suffix List], including its
+//! private section (e.g. `github.io`). A compiled copy of the list is bundled, and can be
+//! replaced at runtime with [`set_list`] to pick up a newer list without rebuilding.
+//!
+//! Hosts are expected in the form of [`Url::host_str`](url::Url::host_str): lowercase, with
+//! internationalized labels in punycode. IP addresses have no registrable domain.
+//!
+//! [Public Suffix List]: https://publicsuffix.org
+
+use std::{
+    collections::HashSet,
+    net::IpAddr,
+    sync::{Arc, LazyLock},
+};
+
+use crate::sync::RwLock;
+
+static LIST: LazyLock<RwLock<Option<Arc<List>>>> = LazyLock::new(|| RwLock::new(None));
+
+/// The relation between the sites of two hosts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSiteRelation {
+    /// Both hosts are the same.
+    SameHost,
+    /// The hosts differ, but share a registrable domain.
+    SameSite,
+    /// The hosts have different registrable domains, or are different IP addresses.
+    CrossSite,
+}
+
+/// A public suffix list parsed at runtime.
+///
+/// See [`set_list`].
+#[derive(Debug, Clone, Default)]
+pub struct List {
+    rules: HashSet<String>,
+    wildcards: HashSet<String>,
+    exceptions: HashSet<String>,
+}
+
+impl List {
+    /// Parses a list in the format of `public_suffix_list.dat`.
+    ///
+    /// Comments and blank lines are ignored, and internationalized rules are converted
+    /// to punycode.
+    pub fn parse(list: &str) -> List {
+        let mut parsed = List::default();
+
+        for line in list.lines() {
+            let rule = match line.split_whitespace().next() {
+                Some(rule) if !rule.starts_with("//") => rule,
+                _ => continue,
+            };
+
+            if let Some(rule) = rule.strip_prefix('!') {
+                parsed.exceptions.insert(to_ascii(rule));
+            } else if let Some(rule) = rule.strip_prefix("*.") {
+                parsed.wildcards.insert(to_ascii(rule));
+            } else {
+                parsed.rules.insert(to_ascii(rule));
+            }
+        }
+
+        parsed
+    }
+
+    /// Returns the length of the public suffix at the end of `host`.
+    fn suffix_len(&self, host: &str) -> usize {
+        // start offsets of every label
+        let starts = std::iter::once(0)
+            .chain(host.match_indices('.').map(|(dot, _)| dot + 1))
+            .collect::<Vec<_>>();
+
+        for (idx, &start) in starts.iter().enumerate() {
+            let candidate = &host[start..];
+            let parent = starts.get(idx + 1).map(|&next| &host[next..]);
+
+            // an exception prevails, and its parent is the suffix
+            if self.exceptions.contains(candidate) {
+                return parent.map_or(0, str::len);
+            }
+
+            if self.rules.contains(candidate)
+                || parent.is_some_and(|parent| self.wildcards.contains(parent))
+            {
+                return candidate.len();
+            }
+        }
+
+        // the implicit `*` rule
+        starts.last().map_or(0, |&start| host.len() - start)
+    }
+}
+
+/// Replaces the bundled list with `list`, for all subsequent lookups.
+pub fn set_list(list: List) {
+    *LIST.write() = Some(Arc::new(list));
+}
+
+/// Returns the public suffix of `host`, such as `co.uk` for `www.example.co.uk`.
+///
+/// Returns `None` for IP addresses and empty hosts.
+pub fn public_suffix(host: &str) -> Option<&str> {
+    let host = host.strip_suffix('.').unwrap_or(host);
+    if host.is_empty() || is_ip(host) {
+        return None;
+    }
+
+    match LIST.read().as_deref() {
+        Some(list) => {
+            let len = list.suffix_len(host);
+            (len > 0).then(|| &host[host.len() - len..])
+        }
+        None => psl_crate::suffix_str(host),
+    }
+}
+
+/// Returns the registrable domain (eTLD+1) of `host`, such as `example.co.uk` for
+/// `www.example.co.uk`.
+///
+/// Returns `None` for IP addresses, and for hosts that are themselves a public suffix.
+pub fn registrable_domain(host: &str) -> Option<&str> {
+    let host = host.strip_suffix('.').unwrap_or(host);
+    let suffix = public_suffix(host)?;
+    registrable(host, suffix.len())
+}
+
+/// Returns true if `host` is a public suffix, such as `co.uk` or `github.io`.
+pub fn is_public_suffix(host: &str) -> bool {
+    let host = host.strip_suffix('.').unwrap_or(host);
+    public_suffix(host) == Some(host)
+}
+
+/// Returns the relation between the sites of hosts `a` and `b`.
+pub fn same_site(a: &str, b: &str) -> SameSiteRelation {
+    if a.eq_ignore_ascii_case(b) {
+        return SameSiteRelation::SameHost;
+    }
+
+    match (registrable_domain(a), registrable_domain(b)) {
+        (Some(a), Some(b)) if a.eq_ignore_ascii_case(b) => SameSiteRelation::SameSite,
+        _ => SameSiteRelation::CrossSite,
+    }
+}
+
+/// Returns the registrable domain of `host`, given the length of its public suffix.
+fn registrable(host: &str, suffix_len: usize) -> Option<&str> {
+    let rest = host[..host.len() - suffix_len].strip_suffix('.')?;
+    let start = rest.rfind('.').map_or(0, |dot| dot + 1);
+    Some(&host[start..])
+}
+
+fn is_ip(host: &str) -> bool {
+    host.starts_with('[') || host.parse::<IpAddr>().is_ok()
+}
+
+fn to_ascii(rule: &str) -> String {
+    match url::Host::parse(rule) {
+        Ok(url::Host::Domain(domain)) => domain,
+        _ => rule.to_ascii_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST: &str = "\
+// ===BEGIN ICANN DOMAINS===
+uk
+co.uk
+*.ck
+!www.ck
+// ===BEGIN PRIVATE DOMAINS===
+github.io
+";
+
+    fn check(lookup: impl Fn(&str) -> Option<&str>) {
+        assert_eq!(lookup("www.example.co.uk"), Some("example.co.uk"));
+        assert_eq!(lookup("example.co.uk"), Some("example.co.uk"));
+        assert_eq!(lookup("co.uk"), None);
+        assert_eq!(lookup("uk"), None);
+        assert_eq!(lookup("user.github.io"), Some("user.github.io"));
+        assert_eq!(lookup("docs.user.github.io"), Some("user.github.io"));
+        assert_eq!(lookup("github.io"), None);
+        assert_eq!(lookup("127.0.0.1"), None);
+        assert_eq!(lookup("::1"), None);
+        assert_eq!(lookup("[::1]"), None);
+    }
+
+    #[test]
+    fn bundled_list() {
+        check(registrable_domain);
+
+        assert!(is_public_suffix("co.uk"));
+        assert!(is_public_suffix("github.io"));
+        assert!(!is_public_suffix("example.co.uk"));
+        assert!(!is_public_suffix("127.0.0.1"));
+    }
+
+    #[test]
+    fn parsed_list() {
+        let list = List::parse(LIST);
+        check(|host| {
+            if is_ip(host) {
+                return None;
+            }
+            registrable(host, list.suffix_len(host))
+        });
+
+        assert_eq!(list.suffix_len("example.other.ck"), "other.ck".len());
+        assert_eq!(list.suffix_len("www.ck"), "ck".len());
+        assert_eq!(list.suffix_len("example.unknown"), "unknown".len());
+    }
+
+    #[test]
+    fn same_site_relation() {
+        assert_eq!(
+            same_site("example.co.uk", "example.co.uk"),
+            SameSiteRelation::SameHost
+        );
+        assert_eq!(
+            same_site("www.example.co.uk", "api.example.co.uk"),
+            SameSiteRelation::SameSite
+        );
+        assert_eq!(
+            same_site("example.co.uk", "other.co.uk"),
+            SameSiteRelation::CrossSite
+        );
+        assert_eq!(
+            same_site("alice.github.io", "bob.github.io"),
+            SameSiteRelation::CrossSite
+        );
+        assert_eq!(
+            same_site("127.0.0.1", "127.0.0.2"),
+            SameSiteRelation::CrossSite
+        );
+        assert_eq!(
+            same_site("127.0.0.1", "127.0.0.1"),
+            SameSiteRelation::SameHost
+        );
+    }
+}
@@ -1,10 +1,13 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, sync::Arc};
 
 use boring2::ssl::ExtensionType;
 use bytes::Bytes;
 
-use super::{AlpnProtocol, AlpsProtocol, TlsVersion};
-use crate::tls::CertificateCompressionAlgorithm;
+use super::{
+    AesHwOverride, AlpnProtocol, AlpsProtocol, HostFlags, SessionKeyScope, Tls13CipherSuite,
+    TlsVersion,
+};
+use crate::tls::{CertificateCompressionAlgorithm, CertificateCompressor};
 
 /// Builder for `[`TlsConfig`]`.
 #[must_use]
@@ -25,12 +28,15 @@ pub struct TlsConfig {
     pub(crate) min_tls_version: Option<TlsVersion>,
     pub(crate) max_tls_version: Option<TlsVersion>,
     pub(crate) pre_shared_key: bool,
+    pub(crate) session_key_scope: SessionKeyScope,
     pub(crate) enable_ech_grease: bool,
+    pub(crate) enable_early_data: bool,
     pub(crate) permute_extensions: Option<bool>,
     pub(crate) grease_enabled: Option<bool>,
     pub(crate) enable_ocsp_stapling: bool,
     pub(crate) enable_signed_cert_timestamps: bool,
     pub(crate) record_size_limit: Option<u16>,
+    pub(crate) max_send_fragment: Option<usize>,
     pub(crate) psk_skip_session_ticket: bool,
     pub(crate) key_shares_limit: Option<u8>,
     pub(crate) psk_dhe_ke: bool,
@@ -38,13 +44,17 @@ pub struct TlsConfig {
     pub(crate) delegated_credentials: Option<Cow<'static, str>>,
     pub(crate) curves_list: Option<Cow<'static, str>>,
     pub(crate) cipher_list: Option<Cow<'static, str>>,
+    pub(crate) tls13_cipher_suites: Option<Cow<'static, [Tls13CipherSuite]>>,
+    pub(crate) require_no_downgrade: bool,
+    pub(crate) verify_hostflags: Option<HostFlags>,
     pub(crate) sigalgs_list: Option<Cow<'static, str>>,
     pub(crate) certificate_compression_algorithms:
         Option<Cow<'static, [CertificateCompressionAlgorithm]>>,
+    pub(crate) certificate_compressors: Vec<Arc<dyn CertificateCompressor>>,
     pub(crate) extension_permutation: Option<Cow<'static, [ExtensionType]>>,
-    pub(crate) aes_hw_override: Option<bool>,
+    pub(crate) extension_permutation_seed: Option<u64>,
+    pub(crate) aes_hw_override: Option<AesHwOverride>,
     pub(crate) prefer_chacha20: Option<bool>,
-    pub(crate) random_aes_hw_override: bool,
 }
 
 impl TlsConfigBuilder {
@@ -106,12 +116,35 @@ impl TlsConfigBuilder {
         self
     }
 
+    /// Sets which connections share sessions cached with
+    /// [`pre_shared_key`](Self::pre_shared_key).
+    ///
+    /// Defaults to [`SessionKeyScope::HostPort`].
+    pub fn session_key_scope(mut self, scope: SessionKeyScope) -> Self {
+        self.config.session_key_scope = scope;
+        self
+    }
+
     /// Sets the GREASE ECH extension flag.
     pub fn enable_ech_grease(mut self, enabled: bool) -> Self {
         self.config.enable_ech_grease = enabled;
         self
     }
 
+    /// Sets whether to send TLS 1.3 early data (0-RTT).
+    ///
+    /// Early data is only attempted when resuming a session that allows it, which requires
+    /// [`pre_shared_key`](Self::pre_shared_key), and only for `GET` and `HEAD` requests without
+    /// a body. If the server rejects early data, the request is retried once over a full
+    /// handshake. Whether early data was used is reported by the [`EarlyData`] response
+    /// extension.
+    ///
+    /// [`EarlyData`]: crate::tls::EarlyData
+    pub fn enable_early_data(mut self, enabled: bool) -> Self {
+        self.config.enable_early_data = enabled;
+        self
+    }
+
     /// Sets whether to permute ClientHello extensions.
     pub fn permute_extensions<T>(mut self, permute: T) -> Self
     where
@@ -148,6 +181,14 @@ impl TlsConfigBuilder {
         self
     }
 
+    /// Sets the maximum size of the plaintext in a TLS record sent by the client.
+    ///
+    /// Must be between 512 and 16384 bytes, or building the client fails. Defaults to 16384.
+    pub fn max_send_fragment<U: Into<Option<usize>>>(mut self, max: U) -> Self {
+        self.config.max_send_fragment = max.into();
+        self
+    }
+
     /// Sets the PSK skip session ticket flag.
     pub fn psk_skip_session_ticket(mut self, skip: bool) -> Self {
         self.config.psk_skip_session_ticket = skip;
@@ -175,7 +216,11 @@ impl TlsConfigBuilder {
         self
     }
 
-    /// Sets the delegated credentials.
+    /// Sets the signature algorithms advertised in the `delegated_credentials` extension.
+    ///
+    /// Takes a colon-separated list in the format of [`sigalgs_list`](Self::sigalgs_list),
+    /// e.g. `"ecdsa_secp256r1_sha256:ecdsa_secp384r1_sha384:ecdsa_secp521r1_sha512"` as
+    /// sent by Firefox. The extension is omitted when unset.
     pub fn delegated_credentials<T>(mut self, creds: T) -> Self
     where
         T: Into<Cow<'static, str>>,
@@ -184,7 +229,11 @@ impl TlsConfigBuilder {
         self
     }
 
-    /// Sets the supported curves list.
+    /// Sets the supported groups, as a colon-separated list of names in order of preference.
+    ///
+    /// Any group BoringSSL knows can be named, including post-quantum hybrids, e.g.
+    /// `"X25519MLKEM768:X25519:P-256:P-384"`. An unsupported name fails building the client
+    /// with an error naming it.
     pub fn curves_list<T>(mut self, curves: T) -> Self
     where
         T: Into<Cow<'static, str>>,
@@ -202,6 +251,42 @@ impl TlsConfigBuilder {
         self
     }
 
+    /// Sets the TLS 1.3 cipher suites, in order of preference.
+    ///
+    /// The suites are advertised ahead of the [`cipher_list`](Self::cipher_list), which only
+    /// applies to TLS 1.2 and below.
+    pub fn tls13_cipher_suites<T>(mut self, suites: T) -> Self
+    where
+        T: Into<Cow<'static, [Tls13CipherSuite]>>,
+    {
+        self.config.tls13_cipher_suites = Some(suites.into());
+        self
+    }
+
+    /// Sets whether to fail the handshake if the server signals a TLS 1.3 downgrade.
+    ///
+    /// A server supporting TLS 1.3 that negotiates an older version marks its ServerHello
+    /// random with a sentinel. When the client permits TLS 1.3 this is always an error,
+    /// but when it is limited to TLS 1.2 the sentinel is only reported in [`TlsInfo`].
+    ///
+    /// [`TlsInfo`]: crate::tls::TlsInfo
+    pub fn require_no_downgrade(mut self, enabled: bool) -> Self {
+        self.config.require_no_downgrade = enabled;
+        self
+    }
+
+    /// Sets the flags used to match the server certificate against the hostname.
+    ///
+    /// Only applies when hostname verification is enabled. Defaults to
+    /// [`HostFlags::NO_PARTIAL_WILDCARDS`].
+    pub fn verify_hostflags<T>(mut self, flags: T) -> Self
+    where
+        T: Into<Option<HostFlags>>,
+    {
+        self.config.verify_hostflags = flags.into();
+        self
+    }
+
     /// Sets the supported signature algorithms.
     pub fn sigalgs_list<T>(mut self, sigalgs: T) -> Self
     where
@@ -212,6 +297,8 @@ impl TlsConfigBuilder {
     }
 
     /// Sets the certificate compression algorithms.
+    ///
+    /// Algorithms are advertised in the given order of preference. Duplicates are ignored.
     pub fn certificate_compression_algorithms<T>(mut self, algs: T) -> Self
     where
         T: Into<Cow<'static, [CertificateCompressionAlgorithm]>>,
@@ -220,6 +307,22 @@ impl TlsConfigBuilder {
         self
     }
 
+    /// Registers a custom implementation of a certificate compression algorithm.
+    ///
+    /// The compressor replaces the built-in implementation of its algorithm. It is only
+    /// advertised if its algorithm is listed in
+    /// [`certificate_compression_algorithms`](Self::certificate_compression_algorithms),
+    /// so registering a compressor never changes the ClientHello.
+    pub fn certificate_compressor<C>(mut self, compressor: C) -> Self
+    where
+        C: CertificateCompressor,
+    {
+        self.config
+            .certificate_compressors
+            .push(Arc::new(compressor));
+        self
+    }
+
     /// Sets the extension permutation.
     pub fn extension_permutation<T>(mut self, permutation: T) -> Self
     where
@@ -229,18 +332,62 @@ impl TlsConfigBuilder {
         self
     }
 
+    /// Sets the seed of the permutation of ClientHello extensions.
+    ///
+    /// When set, every connection orders its extensions with a random number generator of the
+    /// client instead of [`permute_extensions`](Self::permute_extensions), so that clients with
+    /// the same seed send the same orders, connection by connection. The extensions of
+    /// [`extension_permutation`](Self::extension_permutation) are shuffled if set, otherwise
+    /// the usual extensions of a ClientHello.
+    ///
+    /// When unset, extensions are permuted at random if
+    /// [`permute_extensions`](Self::permute_extensions) is enabled.
+    pub fn extension_permutation_seed<T>(mut self, seed: T) -> Self
+    where
+        T: Into<Option<u64>>,
+    {
+        self.config.extension_permutation_seed = seed.into();
+        self
+    }
+
     /// Sets the AES hardware override flag.
+    ///
+    /// Equivalent to [`AesHwOverride::On`] or [`AesHwOverride::Off`].
     pub fn aes_hw_override<T>(mut self, enabled: T) -> Self
     where
         T: Into<Option<bool>>,
     {
-        self.config.aes_hw_override = enabled.into();
+        self.config.aes_hw_override = enabled.into().map(|enabled| {
+            if enabled {
+                AesHwOverride::On
+            } else {
+                AesHwOverride::Off
+            }
+        });
         self
     }
 
     /// Sets the random AES hardware override flag.
+    ///
+    /// Equivalent to [`AesHwOverride::Random`]. Disabling it removes a random override.
     pub fn random_aes_hw_override(mut self, enabled: bool) -> Self {
-        self.config.random_aes_hw_override = enabled;
+        if enabled {
+            self.config.aes_hw_override = Some(AesHwOverride::Random);
+        } else if matches!(
+            self.config.aes_hw_override,
+            Some(AesHwOverride::Random | AesHwOverride::SeededRandom { .. })
+        ) {
+            self.config.aes_hw_override = None;
+        }
+        self
+    }
+
+    /// Sets how the AES hardware support is overridden, replacing any override set before.
+    pub fn aes_hw_override_mode<T>(mut self, mode: T) -> Self
+    where
+        T: Into<Option<AesHwOverride>>,
+    {
+        self.config.aes_hw_override = mode.into();
         self
     }
 
@@ -265,6 +412,13 @@ impl TlsConfig {
             config: TlsConfig::default(),
         }
     }
+
+    /// Creates a `TlsConfigBuilder` starting from this configuration.
+    pub fn to_builder(&self) -> TlsConfigBuilder {
+        TlsConfigBuilder {
+            config: self.clone(),
+        }
+    }
 }
 
 impl Default for TlsConfig {
@@ -280,12 +434,15 @@ impl Default for TlsConfig {
             min_tls_version: None,
             max_tls_version: None,
             pre_shared_key: false,
+            session_key_scope: SessionKeyScope::HostPort,
             enable_ech_grease: false,
+            enable_early_data: false,
             permute_extensions: None,
             grease_enabled: None,
             enable_ocsp_stapling: false,
             enable_signed_cert_timestamps: false,
             record_size_limit: None,
+            max_send_fragment: None,
             psk_skip_session_ticket: false,
             key_shares_limit: None,
             psk_dhe_ke: true,
@@ -293,12 +450,16 @@ impl Default for TlsConfig {
             delegated_credentials: None,
             curves_list: None,
             cipher_list: None,
+            tls13_cipher_suites: None,
+            require_no_downgrade: false,
+            verify_hostflags: None,
             sigalgs_list: None,
             certificate_compression_algorithms: None,
+            certificate_compressors: Vec::new(),
             extension_permutation: None,
+            extension_permutation_seed: None,
             aes_hw_override: None,
             prefer_chacha20: None,
-            random_aes_hw_override: false,
         }
     }
 }
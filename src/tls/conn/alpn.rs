@@ -0,0 +1,29 @@
+use std::{error::Error as StdError, fmt};
+
+use boring2::ssl::SslRef;
+
+/// Checks that the protocol the server selected is one the connection can speak.
+pub(super) fn verify_negotiated_protocol(ssl: &SslRef) -> Result<(), UnsupportedProtocol> {
+    match ssl.selected_alpn_protocol() {
+        None | Some(b"h2" | b"http/1.1") => Ok(()),
+        Some(protocol) => Err(UnsupportedProtocol(protocol.into())),
+    }
+}
+
+/// The server selected an ALPN protocol other than HTTP/1.1 or HTTP/2.
+///
+/// Custom protocols can be advertised, but only HTTP/1.1 and HTTP/2 are spoken.
+#[derive(Debug)]
+pub(crate) struct UnsupportedProtocol(Box<[u8]>);
+
+impl fmt::Display for UnsupportedProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unsupported negotiated protocol `{}`",
+            String::from_utf8_lossy(&self.0)
+        )
+    }
+}
+
+impl StdError for UnsupportedProtocol {}
@@ -6,11 +6,9 @@ use std::{
 };
 
 use boring2::ssl::{SslSession, SslSessionRef, SslVersion};
-use http::uri::Authority;
 use linked_hash_set::LinkedHashSet;
 
-#[derive(Hash, PartialEq, Eq, Clone)]
-pub struct SessionKey(pub Authority);
+use crate::tls::{SessionKey, TlsSessionStats};
 
 #[derive(Clone)]
 struct HashSession(SslSession);
@@ -43,6 +41,10 @@ pub struct SessionCache {
     reverse: HashMap<HashSession, SessionKey>,
     /// Maximum capacity of LinkedHashSet per SessionKey
     per_key_session_capacity: usize,
+    hits: u64,
+    misses: u64,
+    full_handshakes: u64,
+    resumed_handshakes: u64,
 }
 
 impl SessionCache {
@@ -51,6 +53,10 @@ impl SessionCache {
             sessions: HashMap::new(),
             reverse: HashMap::new(),
             per_key_session_capacity,
+            hits: 0,
+            misses: 0,
+            full_handshakes: 0,
+            resumed_handshakes: 0,
         }
     }
 
@@ -71,10 +77,16 @@ impl SessionCache {
     }
 
     pub fn get(&mut self, key: &SessionKey) -> Option<SslSession> {
-        let session = {
-            let sessions = self.sessions.get_mut(key)?;
-            sessions.front().cloned()?.0
+        let session = self
+            .sessions
+            .get(key)
+            .and_then(|sessions| sessions.front().cloned());
+
+        let Some(HashSession(session)) = session else {
+            self.misses += 1;
+            return None;
         };
+        self.hits += 1;
 
         // https://tools.ietf.org/html/rfc8446#appendix-C.4
         // OpenSSL will remove the session from its cache after the handshake completes anyway, but
@@ -99,4 +111,29 @@ impl SessionCache {
             }
         }
     }
+
+    /// Counts a completed handshake, which may have resumed a session.
+    pub fn record_handshake(&mut self, resumed: bool) {
+        if resumed {
+            self.resumed_handshakes += 1;
+        } else {
+            self.full_handshakes += 1;
+        }
+    }
+
+    /// Removes all sessions, keeping the counters.
+    pub fn clear(&mut self) {
+        self.sessions.clear();
+        self.reverse.clear();
+    }
+
+    pub fn stats(&self) -> TlsSessionStats {
+        TlsSessionStats {
+            entries: self.reverse.len(),
+            hits: self.hits,
+            misses: self.misses,
+            full_handshakes: self.full_handshakes,
+            resumed_handshakes: self.resumed_handshakes,
+        }
+    }
 }
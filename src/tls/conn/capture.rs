@@ -0,0 +1,114 @@
+use std::sync::LazyLock;
+
+use boring2::{
+    error::ErrorStack,
+    ex_data::Index,
+    ssl::{Ssl, SslRef},
+};
+use bytes::Bytes;
+
+use crate::{sync::Mutex, tls::TlsHandshakeCapture};
+
+/// Upper bound of a single captured handshake message.
+///
+/// A ClientHello rarely exceeds a few kilobytes, even with post-quantum key
+/// shares, so anything beyond this is truncated rather than retained.
+const MAX_MESSAGE_LEN: usize = 16 * 1024;
+
+/// TLS record content type of handshake messages.
+const CONTENT_TYPE_HANDSHAKE: i32 = 22;
+
+/// Handshake message type of a ClientHello.
+const MESSAGE_TYPE_CLIENT_HELLO: u8 = 1;
+
+/// Handshake message type of a ServerHello.
+const MESSAGE_TYPE_SERVER_HELLO: u8 = 2;
+
+pub(super) fn capture_index() -> Result<Index<Ssl, Mutex<HandshakeRecorder>>, ErrorStack> {
+    static IDX: LazyLock<Result<Index<Ssl, Mutex<HandshakeRecorder>>, ErrorStack>> =
+        LazyLock::new(Ssl::new_ex_index);
+    IDX.clone()
+}
+
+/// Records the first ClientHello and ServerHello exchanged on a connection.
+#[derive(Default)]
+pub(crate) struct HandshakeRecorder {
+    client_hello: Option<Bytes>,
+    server_hello: Option<Bytes>,
+}
+
+impl HandshakeRecorder {
+    /// Records a handshake message observed by the message callback.
+    ///
+    /// Only the first ClientHello sent and the first ServerHello received are kept,
+    /// so a HelloRetryRequest does not overwrite the original ClientHello.
+    pub(super) fn record(&mut self, write: bool, content_type: i32, msg: &[u8]) {
+        if content_type != CONTENT_TYPE_HANDSHAKE {
+            return;
+        }
+
+        let slot = match (write, msg.first()) {
+            (true, Some(&MESSAGE_TYPE_CLIENT_HELLO)) => &mut self.client_hello,
+            (false, Some(&MESSAGE_TYPE_SERVER_HELLO)) => &mut self.server_hello,
+            _ => return,
+        };
+
+        if slot.is_none() {
+            let len = msg.len().min(MAX_MESSAGE_LEN);
+            *slot = Some(Bytes::copy_from_slice(&msg[..len]));
+        }
+    }
+
+    fn capture(&self) -> Option<TlsHandshakeCapture> {
+        self.client_hello
+            .clone()
+            .map(|client_hello| TlsHandshakeCapture {
+                client_hello,
+                server_hello: self.server_hello.clone(),
+            })
+    }
+}
+
+/// Returns the handshake messages captured on the given connection, if any.
+pub(crate) fn handshake_capture(ssl: &SslRef) -> Option<TlsHandshakeCapture> {
+    capture_index()
+        .ok()
+        .and_then(|idx| ssl.ex_data(idx))
+        .and_then(|recorder| recorder.lock().capture())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_first_hellos_only() {
+        let mut recorder = HandshakeRecorder::default();
+        recorder.record(true, CONTENT_TYPE_HANDSHAKE, &[1, 0, 0, 1, 0xaa]);
+        recorder.record(false, CONTENT_TYPE_HANDSHAKE, &[2, 0, 0, 1, 0xbb]);
+        recorder.record(true, CONTENT_TYPE_HANDSHAKE, &[1, 0, 0, 1, 0xcc]);
+        recorder.record(false, CONTENT_TYPE_HANDSHAKE, &[11, 0, 0, 0]);
+
+        let capture = recorder.capture().unwrap();
+        assert_eq!(capture.client_hello(), &[1, 0, 0, 1, 0xaa][..]);
+        assert_eq!(capture.server_hello().unwrap(), &[2, 0, 0, 1, 0xbb][..]);
+    }
+
+    #[test]
+    fn ignores_non_handshake_records() {
+        let mut recorder = HandshakeRecorder::default();
+        recorder.record(true, 23, &[1, 0, 0, 0]);
+        assert!(recorder.capture().is_none());
+    }
+
+    #[test]
+    fn truncates_oversized_messages() {
+        let mut recorder = HandshakeRecorder::default();
+        let mut msg = vec![0u8; MAX_MESSAGE_LEN * 2];
+        msg[0] = MESSAGE_TYPE_CLIENT_HELLO;
+        recorder.record(true, CONTENT_TYPE_HANDSHAKE, &msg);
+
+        let capture = recorder.capture().unwrap();
+        assert_eq!(capture.client_hello().len(), MAX_MESSAGE_LEN);
+    }
+}
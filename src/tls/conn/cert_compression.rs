@@ -1,10 +1,19 @@
-use std::io::{self, Read, Result, Write};
+use std::{
+    io::{self, Read, Result, Write},
+    marker::PhantomData,
+    sync::Arc,
+};
 
-use boring2::ssl::{CertificateCompressionAlgorithm, CertificateCompressor};
+use boring2::{
+    error::ErrorStack,
+    ssl::{CertificateCompressionAlgorithm, CertificateCompressor, SslConnectorBuilder},
+};
 use brotli::{CompressorWriter, Decompressor};
 use flate2::{Compression, read::ZlibDecoder, write::ZlibEncoder};
 use zstd::stream::{Decoder as ZstdDecoder, Encoder as ZstdEncoder};
 
+use crate::Error;
+
 #[derive(Debug, Clone, Default)]
 #[non_exhaustive]
 pub struct BrotliCertificateCompressor;
@@ -123,3 +132,74 @@ impl CertificateCompressor for ZstdCertificateCompressor {
         Ok(())
     }
 }
+
+/// Adapts a user-provided compressor to the algorithm of the built-in compressor `A`.
+struct DynCertificateCompressor<A> {
+    inner: Arc<dyn crate::tls::CertificateCompressor>,
+    _algorithm: PhantomData<fn() -> A>,
+}
+
+impl<A> CertificateCompressor for DynCertificateCompressor<A>
+where
+    A: CertificateCompressor,
+{
+    const ALGORITHM: CertificateCompressionAlgorithm = A::ALGORITHM;
+    const CAN_COMPRESS: bool = true;
+    const CAN_DECOMPRESS: bool = true;
+
+    fn compress<W>(&self, input: &[u8], output: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.inner.compress(input, output)
+    }
+
+    fn decompress<W>(&self, input: &[u8], output: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.inner.decompress(input, output)
+    }
+}
+
+/// Adds `algorithm` to the advertised certificate compression algorithms,
+/// using `custom` in place of the built-in implementation if provided.
+///
+/// Fails for algorithms without a built-in implementation, whose compressor could not be
+/// registered.
+pub(super) fn add_certificate_compressor(
+    builder: &mut SslConnectorBuilder,
+    algorithm: crate::tls::CertificateCompressionAlgorithm,
+    custom: Option<Arc<dyn crate::tls::CertificateCompressor>>,
+) -> crate::Result<()> {
+    fn add<A>(
+        builder: &mut SslConnectorBuilder,
+        builtin: A,
+        custom: Option<Arc<dyn crate::tls::CertificateCompressor>>,
+    ) -> std::result::Result<(), ErrorStack>
+    where
+        A: CertificateCompressor,
+    {
+        match custom {
+            Some(inner) => {
+                builder.add_certificate_compression_algorithm(DynCertificateCompressor::<A> {
+                    inner,
+                    _algorithm: PhantomData,
+                })
+            }
+            None => builder.add_certificate_compression_algorithm(builtin),
+        }
+    }
+
+    if algorithm.0 == CertificateCompressionAlgorithm::ZLIB {
+        add(builder, ZlibCertificateCompressor::default(), custom).map_err(Error::tls)
+    } else if algorithm.0 == CertificateCompressionAlgorithm::BROTLI {
+        add(builder, BrotliCertificateCompressor::default(), custom).map_err(Error::tls)
+    } else if algorithm.0 == CertificateCompressionAlgorithm::ZSTD {
+        add(builder, ZstdCertificateCompressor::default(), custom).map_err(Error::tls)
+    } else {
+        Err(Error::builder(format!(
+            "unsupported certificate compression algorithm: {algorithm:?}"
+        )))
+    }
+}
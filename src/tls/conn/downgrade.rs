@@ -0,0 +1,56 @@
+use std::{error::Error as StdError, fmt};
+
+use boring2::ssl::SslRef;
+
+/// Prefix of the sentinel a TLS 1.3 server writes to the last 8 bytes of the
+/// ServerHello random when negotiating an older version (RFC 8446, section 4.1.3).
+const DOWNGRADE_SENTINEL: &[u8; 7] = b"DOWNGRD";
+
+/// Returns whether the ServerHello random of the connection carries a downgrade sentinel.
+pub(super) fn has_downgrade_sentinel(ssl: &SslRef) -> bool {
+    let mut random = [0u8; 32];
+    let len = ssl.server_random(&mut random);
+    is_downgrade_sentinel(&random[..len])
+}
+
+fn is_downgrade_sentinel(random: &[u8]) -> bool {
+    match random.len().checked_sub(8).map(|start| &random[start..]) {
+        // TLS 1.2 is marked with 0x01, TLS 1.1 and below with 0x00
+        Some([prefix @ .., last]) => prefix == DOWNGRADE_SENTINEL && matches!(last, 0 | 1),
+        _ => false,
+    }
+}
+
+/// The server signaled a TLS 1.3 downgrade, and `require_no_downgrade` is set.
+#[derive(Debug)]
+pub(crate) struct DowngradeDetected;
+
+impl fmt::Display for DowngradeDetected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("server hello carries a TLS 1.3 downgrade sentinel")
+    }
+}
+
+impl StdError for DowngradeDetected {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_downgrade_sentinel() {
+        let mut random = [0xaau8; 32];
+        assert!(!is_downgrade_sentinel(&random));
+
+        random[24..].copy_from_slice(b"DOWNGRD\x01");
+        assert!(is_downgrade_sentinel(&random));
+
+        random[24..].copy_from_slice(b"DOWNGRD\x00");
+        assert!(is_downgrade_sentinel(&random));
+
+        random[24..].copy_from_slice(b"DOWNGRD\x02");
+        assert!(!is_downgrade_sentinel(&random));
+
+        assert!(!is_downgrade_sentinel(&[]));
+    }
+}
@@ -1,21 +1,48 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, sync::Arc};
 
+use boring_sys2 as ffi;
 use boring2::{
     error::ErrorStack,
-    ssl::{ConnectConfiguration, SslConnectorBuilder, SslVerifyMode},
+    ssl::{ConnectConfiguration, ExtensionType, SslConnectorBuilder, SslVerifyMode},
 };
 use bytes::Bytes;
 
 use crate::{
     Error,
     tls::{
-        CertStore, CertificateCompressionAlgorithm,
-        conn::cert_compression::{
-            BrotliCertificateCompressor, ZlibCertificateCompressor, ZstdCertificateCompressor,
-        },
+        CertStore, CertificateCompressionAlgorithm, CertificateCompressor,
+        conn::cert_compression::add_certificate_compressor,
     },
+    util::RandomSource,
 };
 
+/// The smallest maximum record plaintext size BoringSSL accepts.
+const MIN_SEND_FRAGMENT: usize = 512;
+
+/// The largest record plaintext size allowed by TLS.
+const MAX_SEND_FRAGMENT: usize = 16384;
+
+/// The ClientHello extensions shuffled by a seeded permutation when no extension permutation
+/// is configured. `pre_shared_key` is left out, it must stay the last extension.
+pub(crate) const PERMUTED_EXTENSIONS: &[ExtensionType] = &[
+    ExtensionType::SERVER_NAME,
+    ExtensionType::EXTENDED_MASTER_SECRET,
+    ExtensionType::RENEGOTIATE,
+    ExtensionType::SUPPORTED_GROUPS,
+    ExtensionType::EC_POINT_FORMATS,
+    ExtensionType::SESSION_TICKET,
+    ExtensionType::APPLICATION_LAYER_PROTOCOL_NEGOTIATION,
+    ExtensionType::STATUS_REQUEST,
+    ExtensionType::DELEGATED_CREDENTIAL,
+    ExtensionType::KEY_SHARE,
+    ExtensionType::SUPPORTED_VERSIONS,
+    ExtensionType::SIGNATURE_ALGORITHMS,
+    ExtensionType::PSK_KEY_EXCHANGE_MODES,
+    ExtensionType::RECORD_SIZE_LIMIT,
+    ExtensionType::CERT_COMPRESSION,
+    ExtensionType::ENCRYPTED_CLIENT_HELLO,
+];
+
 /// SslConnectorBuilderExt trait for `SslConnectorBuilder`.
 pub trait SslConnectorBuilderExt {
     /// Configure the CertStore for the given `SslConnectorBuilder`.
@@ -24,11 +51,20 @@ pub trait SslConnectorBuilderExt {
     /// Configure the certificate verification for the given `SslConnectorBuilder`.
     fn set_cert_verification(self, enable: bool) -> crate::Result<SslConnectorBuilder>;
 
-    /// Configure the certificate compression algorithm for the given `SslConnectorBuilder`.
+    /// Configure the certificate compression algorithms for the given `SslConnectorBuilder`,
+    /// in order of preference, preferring `compressors` over the built-in implementations.
     fn add_certificate_compression_algorithms(
         self,
         algs: Option<Cow<'static, [CertificateCompressionAlgorithm]>>,
+        compressors: &[Arc<dyn CertificateCompressor>],
     ) -> crate::Result<SslConnectorBuilder>;
+
+    /// Configure the maximum plaintext size of records sent by the given `SslConnectorBuilder`.
+    fn set_max_send_fragment(&mut self, max: usize) -> crate::Result<()>;
+
+    /// Configure the supported groups for the given `SslConnectorBuilder`, naming the first
+    /// unsupported group on failure.
+    fn set_curves_list_checked(&mut self, curves: &str) -> crate::Result<()>;
 }
 
 /// ConnectConfigurationExt trait for `ConnectConfiguration`.
@@ -40,8 +76,17 @@ pub trait ConnectConfigurationExt {
         use_new_codepoint: bool,
     ) -> Result<&mut ConnectConfiguration, ErrorStack>;
 
-    /// Configure the random aes hardware override for the given `ConnectConfiguration`.
-    fn set_random_aes_hw_override(&mut self, enable: bool);
+    /// Configure the random aes hardware override for the given `ConnectConfiguration`,
+    /// drawn from `random`.
+    fn set_random_aes_hw_override(&mut self, random: Option<&RandomSource>);
+
+    /// Configure the order of the ClientHello extensions for the given `ConnectConfiguration`,
+    /// a permutation of `extensions` drawn from `random`.
+    fn set_random_extension_permutation(
+        &mut self,
+        random: Option<&RandomSource>,
+        extensions: &[ExtensionType],
+    ) -> Result<&mut ConnectConfiguration, ErrorStack>;
 }
 
 impl SslConnectorBuilderExt for SslConnectorBuilder {
@@ -70,32 +115,61 @@ impl SslConnectorBuilderExt for SslConnectorBuilder {
     fn add_certificate_compression_algorithms(
         mut self,
         algs: Option<Cow<'static, [CertificateCompressionAlgorithm]>>,
+        compressors: &[Arc<dyn CertificateCompressor>],
     ) -> crate::Result<SslConnectorBuilder> {
         if let Some(algs) = algs {
-            for algorithm in algs.iter() {
-                if algorithm == &CertificateCompressionAlgorithm::ZLIB {
-                    self.add_certificate_compression_algorithm(
-                        ZlibCertificateCompressor::default(),
-                    ).map_err(Error::tls)?;
+            // BoringSSL advertises the algorithms in the order they are added.
+            for (idx, algorithm) in algs.iter().enumerate() {
+                if algs[..idx].contains(algorithm) {
+                    continue;
                 }
 
-                if algorithm == &CertificateCompressionAlgorithm::BROTLI {
-                    self.add_certificate_compression_algorithm(
-                        BrotliCertificateCompressor::default(),
-                    )
-                    .map_err(Error::tls)?;
-                }
+                // The last compressor registered for an algorithm wins.
+                let custom = compressors
+                    .iter()
+                    .rev()
+                    .find(|compressor| compressor.algorithm() == *algorithm)
+                    .cloned();
 
-                if algorithm == &CertificateCompressionAlgorithm::ZSTD {
-                    self.add_certificate_compression_algorithm(
-                        ZstdCertificateCompressor::default(),
-                    ).map_err(Error::tls)?;
-                }
+                add_certificate_compressor(&mut self, *algorithm, custom)?;
             }
         }
 
         Ok(self)
     }
+
+    #[inline]
+    fn set_max_send_fragment(&mut self, max: usize) -> crate::Result<()> {
+        if !(MIN_SEND_FRAGMENT..=MAX_SEND_FRAGMENT).contains(&max) {
+            return Err(Error::builder(format!(
+                "max_send_fragment must be between {MIN_SEND_FRAGMENT} and {MAX_SEND_FRAGMENT}, got {max}"
+            )));
+        }
+
+        // BoringSSL clamps the value instead of failing, so it is checked above.
+        unsafe {
+            ffi::SSL_CTX_set_max_send_fragment(self.as_ptr(), max);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn set_curves_list_checked(&mut self, curves: &str) -> crate::Result<()> {
+        let Err(err) = self.set_curves_list(curves) else {
+            return Ok(());
+        };
+
+        // BoringSSL doesn't report which group it rejected, so find it one at a time.
+        match curves
+            .split(':')
+            .find(|curve| self.set_curves_list(curve).is_err())
+        {
+            Some(curve) => Err(Error::builder(format!(
+                "unsupported curve `{curve}` in curves list"
+            ))),
+            None => Err(Error::tls(err)),
+        }
+    }
 }
 
 impl ConnectConfigurationExt for ConnectConfiguration {
@@ -118,10 +192,340 @@ impl ConnectConfigurationExt for ConnectConfiguration {
     }
 
     #[inline]
-    fn set_random_aes_hw_override(&mut self, enable: bool) {
-        if enable {
-            let random_bool = (crate::util::fast_random() % 2) == 0;
+    fn set_random_aes_hw_override(&mut self, random: Option<&RandomSource>) {
+        if let Some(random) = random {
+            let random_bool = (random.next() % 2) == 0;
             self.set_aes_hw_override(random_bool);
         }
     }
+
+    #[inline]
+    fn set_random_extension_permutation(
+        &mut self,
+        random: Option<&RandomSource>,
+        extensions: &[ExtensionType],
+    ) -> Result<&mut ConnectConfiguration, ErrorStack> {
+        if let Some(random) = random {
+            // Fisher-Yates, so that every order is as likely
+            let mut permutation = extensions.to_vec();
+            for i in (1..permutation.len()).rev() {
+                let j = (random.next() % (i as u64 + 1)) as usize;
+                permutation.swap(i, j);
+            }
+            self.set_extension_permutation(&permutation)?;
+        }
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read, Write};
+
+    use boring2::ssl::{HandshakeError, SslConnector, SslMethod};
+
+    use super::*;
+
+    /// Stream that records what is written and never has anything to read.
+    #[derive(Default)]
+    struct Recorder(Vec<u8>);
+
+    impl Read for Recorder {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::WouldBlock.into())
+        }
+    }
+
+    impl Write for Recorder {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[derive(Debug)]
+    struct Passthrough(CertificateCompressionAlgorithm);
+
+    impl CertificateCompressor for Passthrough {
+        fn algorithm(&self) -> CertificateCompressionAlgorithm {
+            self.0
+        }
+
+        fn compress(&self, input: &[u8], output: &mut dyn io::Write) -> io::Result<()> {
+            output.write_all(input)
+        }
+
+        fn decompress(&self, input: &[u8], output: &mut dyn io::Write) -> io::Result<()> {
+            output.write_all(input)
+        }
+    }
+
+    fn client_hello(
+        algs: &'static [CertificateCompressionAlgorithm],
+        compressors: &[Arc<dyn CertificateCompressor>],
+    ) -> Vec<u8> {
+        let connector = SslConnector::builder(SslMethod::tls_client())
+            .unwrap()
+            .add_certificate_compression_algorithms(Some(Cow::Borrowed(algs)), compressors)
+            .unwrap()
+            .build();
+
+        match connector.connect("example.com", Recorder::default()) {
+            Err(HandshakeError::WouldBlock(stream)) => stream.get_ref().0.clone(),
+            _ => panic!("handshake should wait for the ServerHello"),
+        }
+    }
+
+    fn client_hello_with_alps(alps: Option<&'static [u8]>, use_new_codepoint: bool) -> Vec<u8> {
+        let connector = SslConnector::builder(SslMethod::tls_client())
+            .unwrap()
+            .build();
+
+        let mut cfg = connector.configure().unwrap();
+        cfg.set_alpn_protos(b"\x02h2").unwrap();
+        cfg.set_alps_protos(alps.map(Bytes::from_static), use_new_codepoint)
+            .unwrap();
+
+        match cfg.connect("example.com", Recorder::default()) {
+            Err(HandshakeError::WouldBlock(stream)) => stream.get_ref().0.clone(),
+            _ => panic!("handshake should wait for the ServerHello"),
+        }
+    }
+
+    /// Returns the types of the extensions in a ClientHello.
+    fn extension_types(client_hello: &[u8]) -> Vec<u16> {
+        extensions(client_hello).map(|(ty, _)| ty).collect()
+    }
+
+    /// Iterates over the extensions of a ClientHello.
+    fn extensions(client_hello: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+        let u16_at = |pos: usize| u16::from_be_bytes([client_hello[pos], client_hello[pos + 1]]);
+
+        // record header, handshake header, legacy version and random
+        let mut pos = 5 + 4 + 2 + 32;
+        pos += 1 + client_hello[pos] as usize;
+        pos += 2 + u16_at(pos) as usize;
+        pos += 1 + client_hello[pos] as usize;
+        pos += 2;
+
+        std::iter::from_fn(move || {
+            if pos + 4 > client_hello.len() {
+                return None;
+            }
+
+            let ty = u16_at(pos);
+            let len = u16_at(pos + 2) as usize;
+            let data = &client_hello[pos + 4..pos + 4 + len];
+            pos += 4 + len;
+            Some((ty, data))
+        })
+    }
+
+    /// Returns the algorithm identifiers of the `compress_certificate` extension.
+    fn advertised(client_hello: &[u8]) -> Vec<u16> {
+        extensions(client_hello)
+            .find(|(ty, _)| *ty == 27)
+            .map(|(_, data)| {
+                data[1..]
+                    .chunks(2)
+                    .map(|id| u16::from_be_bytes([id[0], id[1]]))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn advertises_algorithms_in_order() {
+        let hello = client_hello(&[CertificateCompressionAlgorithm::BROTLI], &[]);
+        assert_eq!(advertised(&hello), [2]);
+
+        let hello = client_hello(
+            &[
+                CertificateCompressionAlgorithm::ZLIB,
+                CertificateCompressionAlgorithm::BROTLI,
+                CertificateCompressionAlgorithm::ZSTD,
+            ],
+            &[],
+        );
+        assert_eq!(advertised(&hello), [1, 2, 3]);
+
+        let hello = client_hello(
+            &[
+                CertificateCompressionAlgorithm::ZSTD,
+                CertificateCompressionAlgorithm::ZLIB,
+                CertificateCompressionAlgorithm::ZSTD,
+            ],
+            &[],
+        );
+        assert_eq!(advertised(&hello), [3, 1]);
+    }
+
+    #[test]
+    fn alps_extension_follows_override() {
+        const ALPS_OLD: u16 = 17513;
+        const ALPS_NEW: u16 = 17613;
+
+        let types = extension_types(&client_hello_with_alps(Some(b"h2"), false));
+        assert!(types.contains(&ALPS_OLD));
+        assert!(!types.contains(&ALPS_NEW));
+
+        let types = extension_types(&client_hello_with_alps(Some(b"h2"), true));
+        assert!(types.contains(&ALPS_NEW));
+        assert!(!types.contains(&ALPS_OLD));
+
+        let types = extension_types(&client_hello_with_alps(None, true));
+        assert!(!types.contains(&ALPS_OLD));
+        assert!(!types.contains(&ALPS_NEW));
+    }
+
+    #[test]
+    fn custom_compressor_keeps_client_hello() {
+        let compressors: [Arc<dyn CertificateCompressor>; 2] = [
+            Arc::new(Passthrough(CertificateCompressionAlgorithm::BROTLI)),
+            Arc::new(Passthrough(CertificateCompressionAlgorithm::ZSTD)),
+        ];
+
+        let hello = client_hello(&[CertificateCompressionAlgorithm::BROTLI], &compressors);
+        assert_eq!(advertised(&hello), [2]);
+
+        let hello = client_hello(
+            &[
+                CertificateCompressionAlgorithm::ZLIB,
+                CertificateCompressionAlgorithm::BROTLI,
+                CertificateCompressionAlgorithm::ZSTD,
+            ],
+            &compressors,
+        );
+        assert_eq!(advertised(&hello), [1, 2, 3]);
+    }
+
+    #[test]
+    fn delegated_credentials_extension() {
+        const DELEGATED_CREDENTIALS: u16 = 34;
+
+        let mut builder = SslConnector::builder(SslMethod::tls_client()).unwrap();
+        builder
+            .set_delegated_credentials("ecdsa_secp256r1_sha256:ecdsa_secp384r1_sha384")
+            .unwrap();
+
+        let hello = match builder.build().connect("example.com", Recorder::default()) {
+            Err(HandshakeError::WouldBlock(stream)) => stream.get_ref().0.clone(),
+            _ => panic!("handshake should wait for the ServerHello"),
+        };
+        let schemes = extensions(&hello)
+            .find(|(ty, _)| *ty == DELEGATED_CREDENTIALS)
+            .map(|(_, data)| data.to_vec());
+        assert_eq!(schemes, Some(vec![0, 4, 0x04, 0x03, 0x05, 0x03]));
+
+        let hello = client_hello(&[], &[]);
+        assert!(!extension_types(&hello).contains(&DELEGATED_CREDENTIALS));
+    }
+
+    #[test]
+    fn seeded_aes_hw_override_is_reproducible() {
+        /// Returns the cipher suites of the ClientHello of a connection.
+        fn cipher_suites(connector: &SslConnector, random: &RandomSource) -> Vec<u8> {
+            let mut cfg = connector.configure().unwrap();
+            cfg.set_random_aes_hw_override(Some(random));
+            let hello = match cfg.connect("example.com", Recorder::default()) {
+                Err(HandshakeError::WouldBlock(stream)) => stream.get_ref().0.clone(),
+                _ => panic!("handshake should wait for the ServerHello"),
+            };
+
+            // record header, handshake header, legacy version, random and session id
+            let mut pos = 5 + 4 + 2 + 32;
+            pos += 1 + hello[pos] as usize;
+            let len = u16::from_be_bytes([hello[pos], hello[pos + 1]]) as usize;
+            hello[pos + 2..pos + 2 + len].to_vec()
+        }
+
+        let connector = SslConnector::builder(SslMethod::tls_client())
+            .unwrap()
+            .build();
+        let orderings = |seed| {
+            let random = RandomSource::seeded(seed);
+            (0..16)
+                .map(|_| cipher_suites(&connector, &random))
+                .collect::<Vec<_>>()
+        };
+
+        let first = orderings(42);
+        assert_eq!(first, orderings(42));
+
+        // both overrides are chosen, which order the cipher suites differently
+        let mut distinct = first.clone();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 2);
+    }
+
+    #[test]
+    fn seeded_extension_permutation_is_reproducible() {
+        let connector = SslConnector::builder(SslMethod::tls_client())
+            .unwrap()
+            .build();
+        let orderings = |seed| {
+            let random = RandomSource::seeded(seed);
+            (0..8)
+                .map(|_| {
+                    let mut cfg = connector.configure().unwrap();
+                    cfg.set_random_extension_permutation(Some(&random), PERMUTED_EXTENSIONS)
+                        .unwrap();
+                    match cfg.connect("example.com", Recorder::default()) {
+                        Err(HandshakeError::WouldBlock(stream)) => {
+                            extension_types(&stream.get_ref().0)
+                        }
+                        _ => panic!("handshake should wait for the ServerHello"),
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let first = orderings(42);
+        assert_eq!(first, orderings(42));
+        assert_ne!(first, orderings(43));
+
+        // connections of the same client draw different orders of the same extensions
+        assert_ne!(first[0], first[1]);
+        let mut sorted = first[0].clone();
+        sorted.sort_unstable();
+        let mut other = first[1].clone();
+        other.sort_unstable();
+        assert_eq!(sorted, other);
+    }
+
+    #[test]
+    fn max_send_fragment_range() {
+        let mut builder = SslConnector::builder(SslMethod::tls_client()).unwrap();
+        assert!(builder.set_max_send_fragment(511).unwrap_err().is_builder());
+        assert!(
+            builder
+                .set_max_send_fragment(16385)
+                .unwrap_err()
+                .is_builder()
+        );
+        builder.set_max_send_fragment(512).unwrap();
+        builder.set_max_send_fragment(16384).unwrap();
+    }
+
+    #[test]
+    fn curves_list_names_unsupported_curve() {
+        use std::error::Error as _;
+
+        let mut builder = SslConnector::builder(SslMethod::tls_client()).unwrap();
+        builder
+            .set_curves_list_checked("X25519MLKEM768:X25519:P-256:P-384")
+            .unwrap();
+
+        let err = builder
+            .set_curves_list_checked("X25519:secp256k1:P-256")
+            .unwrap_err();
+        assert!(err.is_builder());
+        assert!(err.source().unwrap().to_string().contains("`secp256k1`"));
+    }
 }
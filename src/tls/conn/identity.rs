@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use boring2::ssl::SslConnector;
+
+use crate::Error;
+
+/// A host an identity is used for: either an exact host, or `*.` followed by a domain,
+/// matching any of its subdomains.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum HostPattern {
+    Exact(Box<str>),
+    Subdomains(Box<str>),
+}
+
+impl HostPattern {
+    /// Parses a host pattern, ignoring case and a trailing dot.
+    pub(crate) fn parse(pattern: &str) -> crate::Result<HostPattern> {
+        let pattern = pattern.strip_suffix('.').unwrap_or(pattern);
+        let (domain, wildcard) = match pattern.strip_prefix("*.") {
+            Some(domain) => (domain, true),
+            None => (pattern, false),
+        };
+
+        if domain.is_empty() || domain.contains(['*', '/', ':']) || domain.starts_with('.') {
+            return Err(Error::builder(format!("invalid host pattern `{pattern}`")));
+        }
+
+        let domain = domain.to_ascii_lowercase().into_boxed_str();
+        Ok(if wildcard {
+            HostPattern::Subdomains(domain)
+        } else {
+            HostPattern::Exact(domain)
+        })
+    }
+
+    /// Returns how specifically `host` is matched: the number of labels of a wildcard domain,
+    /// or `usize::MAX` for an exact host. Returns `None` if `host` does not match.
+    fn matches(&self, host: &str) -> Option<usize> {
+        match self {
+            HostPattern::Exact(exact) => exact.eq_ignore_ascii_case(host).then_some(usize::MAX),
+            HostPattern::Subdomains(domain) => {
+                let split = host.len().checked_sub(domain.len() + 1)?;
+                let (sub, suffix) = (host.get(..split)?, host.get(split..)?);
+                (!sub.is_empty()
+                    && suffix.starts_with('.')
+                    && suffix[1..].eq_ignore_ascii_case(domain))
+                .then(|| domain.split('.').count())
+            }
+        }
+    }
+}
+
+/// The connectors authenticating with the identities given for hosts.
+#[derive(Clone, Default)]
+pub(super) struct HostIdentities(Option<Arc<[(HostPattern, SslConnector)]>>);
+
+impl HostIdentities {
+    pub(super) fn new<I>(identities: I) -> HostIdentities
+    where
+        I: IntoIterator<Item = (HostPattern, SslConnector)>,
+    {
+        let identities: Arc<[_]> = identities.into_iter().collect();
+        HostIdentities((!identities.is_empty()).then_some(identities))
+    }
+
+    /// Returns the connector of the pattern matching `host` most specifically.
+    ///
+    /// An exact host is preferred over a wildcard, and a longer wildcard domain over a
+    /// shorter one. Among equally specific patterns, the first one given is used.
+    pub(super) fn select(&self, host: &str) -> Option<&SslConnector> {
+        let host = host.strip_suffix('.').unwrap_or(host);
+        let mut best: Option<(usize, &SslConnector)> = None;
+        for (pattern, connector) in self.0.as_deref()? {
+            if let Some(rank) = pattern.matches(host) {
+                if best.is_none_or(|(best, _)| rank > best) {
+                    best = Some((rank, connector));
+                }
+            }
+        }
+        best.map(|(_, connector)| connector)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_pattern_matching() {
+        let exact = HostPattern::parse("API.example.com.").unwrap();
+        assert_eq!(exact, HostPattern::Exact("api.example.com".into()));
+        assert!(exact.matches("api.EXAMPLE.com").is_some());
+        assert!(exact.matches("x.api.example.com").is_none());
+
+        let wildcard = HostPattern::parse("*.example.com").unwrap();
+        assert_eq!(wildcard.matches("a.example.com"), Some(2));
+        assert_eq!(wildcard.matches("a.b.example.com"), Some(2));
+        assert!(wildcard.matches("example.com").is_none());
+        assert!(wildcard.matches("aexample.com").is_none());
+        assert!(wildcard.matches(".example.com").is_none());
+
+        for invalid in ["", "*", "*.", "a.*.com", "**.example.com", "host:443"] {
+            assert!(HostPattern::parse(invalid).is_err(), "{invalid:?}");
+        }
+    }
+}
@@ -1,25 +1,44 @@
 //! SSL support via BoringSSL.
 
+mod alpn;
 mod cache;
+mod capture;
 mod cert_compression;
+mod downgrade;
 mod ext;
+mod identity;
+mod ocsp;
+mod revocation;
 mod service;
+mod verify;
 
 use std::{
+    borrow::Cow,
     fmt::{self, Debug},
     io,
     pin::Pin,
-    sync::{Arc, LazyLock},
+    sync::{
+        Arc, LazyLock,
+        atomic::{AtomicU8, Ordering},
+    },
     task::{Context, Poll},
+    time::Instant,
 };
 
+use boring_sys2 as ffi;
 use boring2::{
     error::ErrorStack,
     ex_data::Index,
-    ssl::{Ssl, SslConnector, SslMethod, SslOptions, SslSessionCacheMode},
+    ssl::{
+        ErrorCode, ExtensionType, Ssl, SslConnector, SslConnectorBuilder, SslMethod, SslOptions,
+        SslRef, SslSessionCacheMode, SslVerifyMode,
+    },
+    x509::verify::X509CheckFlags,
 };
 use bytes::Bytes;
-use cache::{SessionCache, SessionKey};
+use cache::SessionCache;
+use capture::{HandshakeRecorder, capture_index};
+use foreign_types::ForeignTypeRef;
 use http::Uri;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_boring2::SslStream;
@@ -35,17 +54,134 @@ use crate::{
     error::BoxError,
     sync::Mutex,
     tls::{
-        AlpnProtocol, CertStore, Identity, KeyLogPolicy, TlsConfig, TlsVersion,
-        conn::ext::{ConnectConfigurationExt, SslConnectorBuilderExt},
+        AesHwOverride, AlpnProtocol, AlpsOverride, CertStore, CertVerification, EarlyData,
+        HostFlags, Identity, KeyLogPolicy, ServerNameOverride, SessionKey, SessionKeyScope,
+        Tls13CipherSuite, TlsConfig, TlsInfo, TlsSessionStats, TlsVersion,
+        conn::ext::{ConnectConfigurationExt, PERMUTED_EXTENSIONS, SslConnectorBuilderExt},
+        keylog::KeyLogHandle,
     },
+    util::RandomSource,
+};
+
+pub(crate) use self::capture::handshake_capture;
+pub(crate) use self::downgrade::DowngradeDetected;
+pub(crate) use self::identity::HostPattern;
+pub(crate) use self::ocsp::OcspStapleMissing;
+pub(crate) use self::revocation::{Crl, RevocationCheck};
+pub use self::revocation::{RevocationError, RevocationPolicy};
+pub use self::verify::CertVerifyError;
+use self::{
+    downgrade::has_downgrade_sentinel,
+    identity::HostIdentities,
+    ocsp::has_ocsp_staple,
+    service::normalize_host,
+    verify::{record_verify_failure, verify_failure_index},
 };
 
+/// TLS 1.3 cipher suites used by [`TlsConnectorBuilder::tls13_only`] when none are configured.
+const DEFAULT_TLS13_CIPHER_SUITES: &[Tls13CipherSuite] = &[
+    Tls13CipherSuite::AES_128_GCM_SHA256,
+    Tls13CipherSuite::AES_256_GCM_SHA384,
+    Tls13CipherSuite::CHACHA20_POLY1305_SHA256,
+];
+
+/// Collects the [`TlsInfo`] of an established connection.
+pub(crate) fn tls_info(ssl: &SslRef) -> TlsInfo {
+    TlsInfo {
+        peer_certificate: ssl.peer_certificate().and_then(|c| c.to_der().ok()),
+        cipher_suite: ssl.current_cipher().and_then(|c| c.standard_name()),
+        downgrade_sentinel: has_downgrade_sentinel(ssl),
+        peer_alps_settings: ssl.peer_application_settings().map(<[u8]>::to_vec),
+        ocsp_response: ssl.ocsp_status().map(<[u8]>::to_vec),
+        session_reused: ssl.session_reused(),
+    }
+}
+
+/// Returns when the handshake of a connection was started.
+pub(crate) fn handshake_started(ssl: &SslRef) -> Option<Instant> {
+    handshake_start_index()
+        .ok()
+        .and_then(|idx| ssl.ex_data(idx))
+        .copied()
+}
+
+/// `SSL_ERROR_EARLY_DATA_REJECTED`, returned once the server rejected early data.
+const SSL_ERROR_EARLY_DATA_REJECTED: i32 = 15;
+
+/// Returns whether the error was caused by the server rejecting early data.
+pub(crate) fn is_early_data_rejected(mut err: &(dyn std::error::Error + 'static)) -> bool {
+    loop {
+        let ssl_err = err
+            .downcast_ref::<io::Error>()
+            .and_then(io::Error::get_ref)
+            .and_then(|err| err.downcast_ref::<boring2::ssl::Error>())
+            .or_else(|| err.downcast_ref::<boring2::ssl::Error>());
+
+        if let Some(ssl_err) = ssl_err {
+            return ssl_err.code() == ErrorCode::from_raw(SSL_ERROR_EARLY_DATA_REJECTED);
+        }
+
+        match err.source() {
+            Some(source) => err = source,
+            None => return false,
+        }
+    }
+}
+
+/// The handshake of a connection that sent early data, completed once the server's response to
+/// it was read.
+///
+/// Clones share the state, so the client can tell whether the server accepted the early data
+/// while the connection is driven elsewhere.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct EarlyDataHandshake(Arc<AtomicU8>);
+
+impl EarlyDataHandshake {
+    const PENDING: u8 = 0;
+    const ACCEPTED: u8 = 1;
+    const DONE: u8 = 2;
+
+    /// Records the outcome of the early data once the handshake of `ssl` completed.
+    pub(crate) fn update(&self, ssl: &SslRef) {
+        if self.is_pending() && !ssl.in_early_data() {
+            // SAFETY: `ssl` is a valid SSL object for the duration of the call.
+            let accepted = unsafe { ffi::SSL_early_data_accepted(ssl.as_ptr()) } == 1;
+            let state = if accepted { Self::ACCEPTED } else { Self::DONE };
+            self.0.store(state, Ordering::Release);
+        }
+    }
+
+    /// Returns whether the handshake is not completed yet, the connection not being safe to
+    /// reuse for other requests.
+    pub(crate) fn is_pending(&self) -> bool {
+        self.0.load(Ordering::Acquire) == Self::PENDING
+    }
+
+    /// Returns [`EarlyData::Accepted`] once the handshake completed with the server accepting
+    /// the early data.
+    pub(crate) fn outcome(&self) -> Option<EarlyData> {
+        (self.0.load(Ordering::Acquire) == Self::ACCEPTED).then_some(EarlyData::Accepted)
+    }
+}
+
 fn key_index() -> Result<Index<Ssl, SessionKey>, ErrorStack> {
     static IDX: LazyLock<Result<Index<Ssl, SessionKey>, ErrorStack>> =
         LazyLock::new(Ssl::new_ex_index);
     IDX.clone()
 }
 
+fn handshake_start_index() -> Result<Index<Ssl, Instant>, ErrorStack> {
+    static IDX: LazyLock<Result<Index<Ssl, Instant>, ErrorStack>> =
+        LazyLock::new(Ssl::new_ex_index);
+    IDX.clone()
+}
+
+fn keylog_index() -> Result<Index<Ssl, KeyLogHandle>, ErrorStack> {
+    static IDX: LazyLock<Result<Index<Ssl, KeyLogHandle>, ErrorStack>> =
+        LazyLock::new(Ssl::new_ex_index);
+    IDX.clone()
+}
+
 /// Builds for [`HandshakeConfig`].
 pub struct HandshakeConfigBuilder {
     settings: HandshakeConfig,
@@ -59,11 +195,22 @@ pub struct HandshakeConfig {
     skip_session_ticket: bool,
     enable_ech_grease: bool,
     verify_hostname: bool,
+    verify_hostflags: Option<HostFlags>,
     tls_sni: bool,
     alpn_protos: Option<Bytes>,
     alps_protos: Option<Bytes>,
     alps_use_new_codepoint: bool,
-    random_aes_hw_override: bool,
+    random_aes_hw_override: Option<RandomSource>,
+    extension_permutation: Option<(RandomSource, Cow<'static, [ExtensionType]>)>,
+    debug_capture: bool,
+    early_data: bool,
+    require_no_downgrade: bool,
+    require_ocsp_staple: bool,
+    server_name: Option<ServerNameOverride>,
+    cert_verification: Option<CertVerification>,
+    session_key_scope: SessionKeyScope,
+    no_resume: bool,
+    keylog: Option<KeyLogHandle>,
 }
 
 impl HandshakeConfigBuilder {
@@ -79,6 +226,12 @@ impl HandshakeConfigBuilder {
         self
     }
 
+    /// Sets which connections share cached sessions.
+    pub fn session_key_scope(mut self, scope: SessionKeyScope) -> Self {
+        self.settings.session_key_scope = scope;
+        self
+    }
+
     /// Skips the session ticket.
     pub fn skip_session_ticket(mut self, skip: bool) -> Self {
         self.settings.skip_session_ticket = skip;
@@ -97,6 +250,12 @@ impl HandshakeConfigBuilder {
         self
     }
 
+    /// Sets hostname verification flags.
+    pub fn verify_hostflags(mut self, flags: Option<HostFlags>) -> Self {
+        self.settings.verify_hostflags = flags;
+        self
+    }
+
     /// Sets TLS SNI.
     pub fn tls_sni(mut self, sni: bool) -> Self {
         self.settings.tls_sni = sni;
@@ -115,9 +274,46 @@ impl HandshakeConfigBuilder {
         self
     }
 
-    /// Sets random AES hardware override.
-    pub fn random_aes_hw_override(mut self, override_: bool) -> Self {
-        self.settings.random_aes_hw_override = override_;
+    /// Sets the source of random AES hardware overrides, chosen per connection.
+    pub fn random_aes_hw_override(mut self, random: Option<RandomSource>) -> Self {
+        self.settings.random_aes_hw_override = random;
+        self
+    }
+
+    /// Sets the seed of the permutations of `extensions`, drawn per connection.
+    pub fn seeded_extension_permutation(
+        mut self,
+        seed: Option<u64>,
+        extensions: Option<Cow<'static, [ExtensionType]>>,
+    ) -> Self {
+        self.settings.extension_permutation = seed.map(|seed| {
+            let extensions = extensions.unwrap_or(Cow::Borrowed(PERMUTED_EXTENSIONS));
+            (RandomSource::seeded(seed), extensions)
+        });
+        self
+    }
+
+    /// Sets handshake message capture.
+    pub fn debug_capture(mut self, enabled: bool) -> Self {
+        self.settings.debug_capture = enabled;
+        self
+    }
+
+    /// Sets early data.
+    pub fn early_data(mut self, enabled: bool) -> Self {
+        self.settings.early_data = enabled;
+        self
+    }
+
+    /// Sets downgrade sentinel rejection.
+    pub fn require_no_downgrade(mut self, enabled: bool) -> Self {
+        self.settings.require_no_downgrade = enabled;
+        self
+    }
+
+    /// Sets OCSP staple enforcement.
+    pub fn require_ocsp_staple(mut self, enabled: bool) -> Self {
+        self.settings.require_ocsp_staple = enabled;
         self
     }
 
@@ -144,11 +340,22 @@ impl Default for HandshakeConfig {
             skip_session_ticket: false,
             enable_ech_grease: false,
             verify_hostname: true,
+            verify_hostflags: None,
             tls_sni: true,
             alpn_protos: None,
             alps_protos: None,
             alps_use_new_codepoint: false,
-            random_aes_hw_override: false,
+            random_aes_hw_override: None,
+            extension_permutation: None,
+            debug_capture: false,
+            early_data: false,
+            require_no_downgrade: false,
+            require_ocsp_staple: false,
+            server_name: None,
+            cert_verification: None,
+            session_key_scope: SessionKeyScope::default(),
+            no_resume: false,
+            keylog: None,
         }
     }
 }
@@ -163,11 +370,12 @@ pub struct HttpsConnector<T> {
 #[derive(Clone)]
 struct Inner {
     ssl: SslConnector,
+    host_identities: HostIdentities,
     cache: Option<Arc<Mutex<SessionCache>>>,
     config: HandshakeConfig,
 }
 
-/// A builder for creating a `TlsConnector`.
+/// A builder for creating a [`TlsConnector`].
 #[derive(Clone)]
 pub struct TlsConnectorBuilder {
     keylog_policy: Option<KeyLogPolicy>,
@@ -176,14 +384,50 @@ pub struct TlsConnectorBuilder {
     tls_sni: bool,
     verify_hostname: bool,
     identity: Option<Identity>,
+    host_identities: Vec<(HostPattern, Identity)>,
     cert_store: Option<CertStore>,
     cert_verification: bool,
+    revocation: Option<RevocationCheck>,
+    require_ocsp_staple: bool,
+    debug_capture: bool,
+    tls13_only: bool,
 }
 
-/// A layer which wraps services in an `SslConnector`.
+/// A built TLS connector, which can be shared by several clients.
+///
+/// Building a connector parses the cipher and signature algorithm lists of its
+/// [`TlsConfig`], loads the certificate store and allocates the session cache. Passing a
+/// clone of the same connector to
+/// [`ClientBuilder::tls_connector`](crate::ClientBuilder::tls_connector) lets clients share
+/// that work, and the sessions they establish.
 #[derive(Clone)]
 pub struct TlsConnector {
     inner: Inner,
+    builder: Arc<TlsConnectorBuilder>,
+    config: Arc<TlsConfig>,
+}
+
+/// A handle to the session cache of a `TlsConnector`.
+#[derive(Clone, Default)]
+pub(crate) struct TlsSessions(Option<Arc<Mutex<SessionCache>>>);
+
+impl TlsSessions {
+    /// Removes all cached sessions.
+    ///
+    /// Handshakes already resuming a session are not affected.
+    pub(crate) fn clear(&self) {
+        if let Some(ref cache) = self.0 {
+            cache.lock().clear();
+        }
+    }
+
+    /// Returns the statistics of the session cache.
+    pub(crate) fn stats(&self) -> TlsSessionStats {
+        self.0
+            .as_ref()
+            .map(|cache| cache.lock().stats())
+            .unwrap_or_default()
+    }
 }
 
 // ===== impl HttpsConnector =====
@@ -195,6 +439,45 @@ impl HttpsConnector<HttpConnector> {
         self.inner.config.alpn_protos = alpn.map(|p| p.encode());
     }
 
+    /// Allows early data on the connection, if enabled by the TLS configuration.
+    #[inline]
+    pub fn set_early_data(&mut self, allowed: bool) {
+        self.inner.config.early_data &= allowed;
+    }
+
+    /// Sets the TLS server name override for the connection.
+    #[inline]
+    pub fn set_server_name(&mut self, server_name: Option<ServerNameOverride>) {
+        self.inner.config.server_name = server_name;
+    }
+
+    /// Overrides the ALPS protocols of the TLS configuration for the connection.
+    #[inline]
+    pub fn set_alps(&mut self, alps: Option<AlpsOverride>) {
+        if let Some(alps) = alps {
+            self.inner.config.alps_protos = alps.protos;
+            self.inner.config.alps_use_new_codepoint = alps.use_new_codepoint;
+        }
+    }
+
+    /// Sets the certificate verification override for the connection.
+    #[inline]
+    pub fn set_cert_verification(&mut self, verification: Option<CertVerification>) {
+        self.inner.config.cert_verification = verification;
+    }
+
+    /// Skips resuming a cached session for the connection, while still caching the new one.
+    #[inline]
+    pub fn set_no_resume(&mut self, no_resume: bool) {
+        self.inner.config.no_resume = no_resume;
+    }
+
+    /// Logs the keys of the connection to `keylog`, instead of the file of the connector.
+    #[inline]
+    pub fn set_keylog(&mut self, keylog: Option<KeyLogHandle>) {
+        self.inner.config.keylog = keylog;
+    }
+
     /// Sets the tcp connect options for the connector.
     #[inline]
     pub fn set_tcp_connect_options(&mut self, options: Option<TcpConnectOptions>) {
@@ -222,19 +505,53 @@ where
 
 impl Inner {
     fn setup_ssl(&self, uri: &Uri, host: &str) -> Result<Ssl, ErrorStack> {
-        let mut cfg = self.ssl.configure()?;
+        let server_name = self.config.server_name.as_ref().and_then(|name| {
+            let sni = name.sni.as_deref()?;
+            Some((normalize_host(sni), name.verify_uri_host))
+        });
+
+        // Authenticate with the identity of the server name, if one was given for it
+        let name = server_name.map_or(host, |(sni, _)| sni);
+        let ssl = self.host_identities.select(name).unwrap_or(&self.ssl);
+        let mut cfg = ssl.configure()?;
 
-        // Use server name indication
-        cfg.set_use_server_name_indication(self.config.tls_sni);
+        // Use server name indication, always sent when overridden
+        cfg.set_use_server_name_indication(self.config.tls_sni || server_name.is_some());
 
         // Verify hostname
         cfg.set_verify_hostname(self.config.verify_hostname);
 
+        // Override certificate verification for this connection
+        if let Some(ref verification) = self.config.cert_verification {
+            if let Some(accept_invalid_certs) = verification.accept_invalid_certs {
+                cfg.set_verify(if accept_invalid_certs {
+                    SslVerifyMode::NONE
+                } else {
+                    SslVerifyMode::PEER
+                });
+            }
+            if let Some(ref store) = verification.cert_store {
+                store.add_to_ssl(&mut cfg)?;
+            }
+        }
+
+        // Record why certificate verification fails, to report it with the handshake error
+        let verify_mode = cfg.verify_mode();
+        if verify_mode.contains(SslVerifyMode::PEER) {
+            cfg.set_ex_data(verify_failure_index()?, Mutex::new(None));
+            cfg.set_verify_callback(verify_mode, record_verify_failure);
+        }
+
         // Set ECH grease
         cfg.set_enable_ech_grease(self.config.enable_ech_grease);
 
         // Set AES hardware override
-        cfg.set_random_aes_hw_override(self.config.random_aes_hw_override);
+        cfg.set_random_aes_hw_override(self.config.random_aes_hw_override.as_ref());
+
+        // Set the seeded extension permutation
+        if let Some((ref random, ref extensions)) = self.config.extension_permutation {
+            cfg.set_random_extension_permutation(Some(random), extensions)?;
+        }
 
         // Set ALPS protos
         cfg.set_alps_protos(
@@ -247,11 +564,17 @@ impl Inner {
             cfg.set_alpn_protos(alpn_protos)?;
         }
 
-        if let Some(authority) = uri.authority() {
-            let key = SessionKey(authority.clone());
-
+        // Sessions are keyed by the URI, so they are neither resumed nor stored with an
+        // overridden server name or certificate verification
+        let session_key = (server_name.is_none() && self.config.cert_verification.is_none())
+            .then(|| self.config.session_key_scope.key(uri))
+            .flatten();
+        if let Some(key) = session_key {
             if let Some(ref cache) = self.cache {
-                if let Some(session) = cache.lock().get(&key) {
+                if self.config.no_resume {
+                    // Force a full handshake, the session key below still stores its session
+                    cfg.set_options(SslOptions::NO_TICKET)?;
+                } else if let Some(session) = cache.lock().get(&key) {
                     unsafe {
                         cfg.set_session(&session)?;
                     }
@@ -266,7 +589,76 @@ impl Inner {
             cfg.set_ex_data(idx, key);
         }
 
-        cfg.into_ssl(host)
+        // Only sent if the session in use allows it
+        if self.config.early_data {
+            cfg.set_early_data_enabled(true);
+        }
+
+        // Record when the handshake starts, to time it
+        cfg.set_ex_data(handshake_start_index()?, Instant::now());
+
+        // Record the handshake messages of this connection only
+        if self.config.debug_capture {
+            let idx = capture_index()?;
+            cfg.set_ex_data(idx, Mutex::new(HandshakeRecorder::default()));
+        }
+
+        // Log the keys of this connection to its own file
+        if let Some(ref keylog) = self.config.keylog {
+            cfg.set_ex_data(keylog_index()?, keylog.clone());
+        }
+
+        let mut ssl = match server_name {
+            Some((sni, true)) if self.config.verify_hostname => {
+                // Send the overridden name, but verify the certificate against the URI host
+                cfg.set_verify_hostname(false);
+                let mut ssl = cfg.into_ssl(sni)?;
+                set_verify_host(&mut ssl, host)?;
+                ssl
+            }
+            Some((sni, _)) => cfg.into_ssl(sni)?,
+            None => cfg.into_ssl(host)?,
+        };
+
+        // `into_ssl` resets the hostname flags, so they are only set afterwards
+        if let (Some(flags), true) = (self.config.verify_hostflags, self.config.verify_hostname) {
+            ssl.param_mut().set_hostflags(flags.0);
+        }
+
+        Ok(ssl)
+    }
+
+    /// Counts a completed handshake as full or resumed in the statistics of the session cache.
+    fn record_handshake(&self, ssl: &SslRef) {
+        if let Some(ref cache) = self.cache {
+            cache.lock().record_handshake(ssl.session_reused());
+        }
+    }
+
+    /// Rejects an established connection whose server signaled a downgrade, if required.
+    fn verify_downgrade(&self, ssl: &SslRef) -> Result<(), DowngradeDetected> {
+        if self.config.require_no_downgrade && has_downgrade_sentinel(ssl) {
+            return Err(DowngradeDetected);
+        }
+        Ok(())
+    }
+
+    /// Rejects an established connection without a stapled OCSP response, if required.
+    fn verify_ocsp_staple(&self, ssl: &SslRef) -> Result<(), OcspStapleMissing> {
+        if self.config.require_ocsp_staple && !has_ocsp_staple(ssl) {
+            return Err(OcspStapleMissing);
+        }
+        Ok(())
+    }
+}
+
+/// Sets the host the peer certificate is verified against, as `into_ssl` does for the SNI.
+fn set_verify_host(ssl: &mut SslRef, host: &str) -> Result<(), ErrorStack> {
+    let param = ssl.param_mut();
+    param.set_hostflags(X509CheckFlags::NO_PARTIAL_WILDCARDS);
+    match host.parse() {
+        Ok(ip) => param.set_ip(ip),
+        Err(_) => param.set_host(host),
     }
 }
 
@@ -287,6 +679,13 @@ impl TlsConnectorBuilder {
         self
     }
 
+    /// Sets the identities used for client certificate authentication with matching hosts.
+    #[inline(always)]
+    pub(crate) fn host_identities(mut self, identities: Vec<(HostPattern, Identity)>) -> Self {
+        self.host_identities = identities;
+        self
+    }
+
     /// Sets the certificate store used for TLS verification.
     #[inline(always)]
     pub fn cert_store<T>(mut self, cert_store: T) -> Self
@@ -304,6 +703,20 @@ impl TlsConnectorBuilder {
         self
     }
 
+    /// Sets the revocation check of server certificates.
+    #[inline(always)]
+    pub(crate) fn revocation(mut self, check: Option<RevocationCheck>) -> Self {
+        self.revocation = check;
+        self
+    }
+
+    /// Sets the OCSP staple requirement flag.
+    #[inline(always)]
+    pub fn require_ocsp_staple(mut self, enabled: bool) -> Self {
+        self.require_ocsp_staple = enabled;
+        self
+    }
+
     /// Sets the minimum TLS version to use.
     #[inline(always)]
     pub fn min_version<T>(mut self, version: T) -> Self
@@ -338,20 +751,69 @@ impl TlsConnectorBuilder {
         self
     }
 
-    /// Build the `TlsConnector` with the provided configuration.
-    pub fn build(&self, mut cfg: TlsConfig) -> crate::Result<TlsConnector> {
-        // Replace the default configuration with the provided one
-        cfg.max_tls_version = cfg.max_tls_version.or(self.max_version);
-        cfg.min_tls_version = cfg.min_tls_version.or(self.min_version);
+    /// Sets the handshake message capture flag.
+    #[inline(always)]
+    pub fn debug_capture(mut self, enabled: bool) -> Self {
+        self.debug_capture = enabled;
+        self
+    }
+
+    /// Sets the TLS 1.3-only flag.
+    #[inline(always)]
+    pub fn tls13_only(mut self, enabled: bool) -> Self {
+        self.tls13_only = enabled;
+        self
+    }
+
+    /// Returns whether TLS keys are logged.
+    pub(crate) fn keylog_enabled(&self) -> bool {
+        self.keylog_policy.is_some()
+    }
+
+    /// Returns whether server certificates are verified.
+    pub(crate) fn cert_verification_enabled(&self) -> bool {
+        self.cert_verification
+    }
+
+    /// Returns whether the hostname of server certificates is verified.
+    pub(crate) fn verify_hostname_enabled(&self) -> bool {
+        self.verify_hostname
+    }
+
+    /// Returns whether the Server Name Indication extension is sent.
+    pub(crate) fn tls_sni_enabled(&self) -> bool {
+        self.tls_sni
+    }
 
+    /// Returns whether a client identity is presented to any server.
+    pub(crate) fn has_identity(&self) -> bool {
+        self.identity.is_some() || !self.host_identities.is_empty()
+    }
+
+    /// Creates an `SslConnectorBuilder` for the configuration, authenticating with `identity`.
+    fn connector_builder(
+        &self,
+        cfg: &TlsConfig,
+        identity: Option<&Identity>,
+    ) -> crate::Result<SslConnectorBuilder> {
         let mut connector = SslConnector::no_default_verify_builder(SslMethod::tls_client())
             .map_err(Error::tls)?
             .set_cert_store(self.cert_store.as_ref())?
             .set_cert_verification(self.cert_verification)?
-            .add_certificate_compression_algorithms(cfg.certificate_compression_algorithms)?;
+            .add_certificate_compression_algorithms(
+                cfg.certificate_compression_algorithms.clone(),
+                &cfg.certificate_compressors,
+            )?;
 
         // Set Identity
-        call_option_ref_try!(self, identity, &mut connector, add_to_tls);
+        if let Some(identity) = identity {
+            identity.add_to_tls(&mut connector)?;
+        }
+
+        // Set certificate revocation checking, unless certificates are not verified
+        if let (Some(check), true) = (&self.revocation, self.cert_verification) {
+            check.install(&mut connector);
+        }
 
         // Set minimum TLS version
         set_option_inner_try!(cfg, min_tls_version, connector, set_min_proto_version);
@@ -400,20 +862,41 @@ impl TlsConnectorBuilder {
         // Set TLS grease options
         set_option!(cfg, grease_enabled, connector, set_grease_enabled);
 
-        // Set TLS permute extensions options
-        set_option!(cfg, permute_extensions, connector, set_permute_extensions);
+        // Set TLS permute extensions options, seeded permutations are chosen per connection
+        if cfg.extension_permutation_seed.is_none() {
+            set_option!(cfg, permute_extensions, connector, set_permute_extensions);
+        }
 
         // Set TLS ALPN protocols
         set_option_ref_try!(cfg, alpn_protos, connector, set_alpn_protos);
 
         // Set TLS curves list
-        set_option_ref_try!(cfg, curves_list, connector, set_curves_list);
+        if let Some(curves) = cfg.curves_list.as_deref() {
+            connector.set_curves_list_checked(curves)?;
+        }
 
         // Set TLS signature algorithms list
         set_option_ref_try!(cfg, sigalgs_list, connector, set_sigalgs_list);
 
-        // Set TLS cipher list
-        set_option_ref_try!(cfg, cipher_list, connector, set_cipher_list);
+        // Set TLS cipher list, led by the TLS 1.3 cipher suites in their given order
+        let tls13_cipher_suites = cfg
+            .tls13_cipher_suites
+            .as_deref()
+            .or(self.tls13_only.then_some(DEFAULT_TLS13_CIPHER_SUITES));
+        if let Some(suites) = tls13_cipher_suites {
+            let mut cipher_list = Tls13CipherSuite::join(suites);
+            if let (false, Some(legacy)) = (self.tls13_only, cfg.cipher_list.as_deref()) {
+                cipher_list.push(':');
+                cipher_list.push_str(legacy);
+            }
+
+            connector.set_preserve_tls13_cipher_list(true);
+            connector
+                .set_cipher_list(&cipher_list)
+                .map_err(Error::tls)?;
+        } else {
+            set_option_ref_try!(cfg, cipher_list, connector, set_cipher_list);
+        }
 
         // Set TLS delegated credentials
         set_option_ref_try!(
@@ -426,6 +909,11 @@ impl TlsConnectorBuilder {
         // Set TLS record size limit
         set_option!(cfg, record_size_limit, connector, set_record_size_limit);
 
+        // Set TLS maximum send fragment
+        if let Some(max) = cfg.max_send_fragment {
+            connector.set_max_send_fragment(max)?;
+        }
+
         // Set TLS key shares limit
         set_option!(cfg, key_shares_limit, connector, set_key_shares_limit);
 
@@ -437,34 +925,106 @@ impl TlsConnectorBuilder {
             set_extension_permutation
         );
 
-        // Set TLS aes hardware override
-        set_option!(cfg, aes_hw_override, connector, set_aes_hw_override);
+        // Set TLS aes hardware override, random overrides are chosen per connection
+        match cfg.aes_hw_override {
+            Some(AesHwOverride::On) => connector.set_aes_hw_override(true),
+            Some(AesHwOverride::Off) => connector.set_aes_hw_override(false),
+            _ => {}
+        }
 
         // Set TLS prefer chacha20 (Encryption order between AES-256-GCM/AES-128-GCM)
         set_option!(cfg, prefer_chacha20, connector, set_prefer_chacha20);
 
-        // Set TLS keylog policy if provided
-        if let Some(ref policy) = self.keylog_policy {
-            let handle = policy
-                .clone()
-                .open_handle()
-                .map_err(crate::Error::builder)?;
-            connector.set_keylog_callback(move |_, line| {
+        // Set TLS keylog policy if provided, connections may log to a file of their own
+        let handle = self
+            .keylog_policy
+            .clone()
+            .map(KeyLogPolicy::open_handle)
+            .transpose()
+            .map_err(crate::Error::builder)?;
+        connector.set_keylog_callback(move |ssl, line| {
+            let handle = keylog_index()
+                .ok()
+                .and_then(|idx| ssl.ex_data(idx))
+                .or(handle.as_ref());
+            if let Some(handle) = handle {
                 handle.write_log_line(line);
+            }
+        });
+
+        // Set TLS handshake message capture if enabled
+        if self.debug_capture {
+            connector.set_msg_callback(|ssl, write, content_type, msg| {
+                if let Some(recorder) = capture_index().ok().and_then(|idx| ssl.ex_data(idx)) {
+                    recorder.lock().record(write, content_type, msg);
+                }
             });
         }
 
+        Ok(connector)
+    }
+
+    /// Applies the options of this builder that override the provided configuration.
+    fn effective_config(&self, mut cfg: TlsConfig) -> TlsConfig {
+        // Replace the default configuration with the provided one
+        cfg.max_tls_version = cfg.max_tls_version.or(self.max_version);
+        cfg.min_tls_version = cfg.min_tls_version.or(self.min_version);
+
+        // Restrict to TLS 1.3 regardless of the provided configuration
+        if self.tls13_only {
+            cfg.min_tls_version = Some(TlsVersion::TLS_1_3);
+            cfg.max_tls_version = Some(TlsVersion::TLS_1_3);
+        }
+
+        // Always request OCSP stapling if a staple is required
+        cfg.enable_ocsp_stapling |= self.require_ocsp_staple;
+        cfg
+    }
+
+    /// Build the `TlsConnector` with the provided configuration.
+    pub fn build(&self, cfg: TlsConfig) -> crate::Result<TlsConnector> {
+        Arc::new(self.clone()).build_shared(cfg)
+    }
+
+    /// Build a `TlsConnector` keeping a reference to this builder.
+    fn build_shared(self: Arc<Self>, cfg: TlsConfig) -> crate::Result<TlsConnector> {
+        let cfg = self.effective_config(cfg);
+
+        let mut connector = self.connector_builder(&cfg, self.identity.as_ref())?;
+        let mut host_identities = self
+            .host_identities
+            .iter()
+            .map(|(pattern, identity)| {
+                let connector = self.connector_builder(&cfg, Some(identity))?;
+                Ok((pattern.clone(), connector))
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+
         // Create the `HandshakeConfig` with the default session cache capacity.
         let config = HandshakeConfig::builder()
             .session_cache_capacity(8)
             .session_cache(cfg.pre_shared_key)
+            .session_key_scope(cfg.session_key_scope.clone())
             .skip_session_ticket(cfg.psk_skip_session_ticket)
-            .alps_protos(cfg.alps_protos)
+            .alps_protos(cfg.alps_protos.clone())
             .alps_use_new_codepoint(cfg.alps_use_new_codepoint)
             .enable_ech_grease(cfg.enable_ech_grease)
             .tls_sni(self.tls_sni)
             .verify_hostname(self.verify_hostname)
-            .random_aes_hw_override(cfg.random_aes_hw_override)
+            .verify_hostflags(cfg.verify_hostflags)
+            .random_aes_hw_override(match cfg.aes_hw_override {
+                Some(AesHwOverride::Random) => Some(RandomSource::Fast),
+                Some(AesHwOverride::SeededRandom { seed }) => Some(RandomSource::seeded(seed)),
+                _ => None,
+            })
+            .seeded_extension_permutation(
+                cfg.extension_permutation_seed,
+                cfg.extension_permutation.clone(),
+            )
+            .debug_capture(self.debug_capture)
+            .early_data(cfg.enable_early_data)
+            .require_no_downgrade(cfg.require_no_downgrade)
+            .require_ocsp_staple(self.require_ocsp_staple)
             .build();
 
         // If the session cache is disabled, we don't need to set up any callbacks.
@@ -473,15 +1033,10 @@ impl TlsConnectorBuilder {
                 config.session_cache_capacity,
             )));
 
-            connector.set_session_cache_mode(SslSessionCacheMode::CLIENT);
-            connector.set_new_session_callback({
-                let cache = cache.clone();
-                move |ssl, session| {
-                    if let Ok(Some(key)) = key_index().map(|idx| ssl.ex_data(idx)) {
-                        cache.lock().insert(key.clone(), session);
-                    }
-                }
-            });
+            set_session_cache(&mut connector, &cache);
+            for (_, connector) in &mut host_identities {
+                set_session_cache(connector, &cache);
+            }
 
             cache
         });
@@ -489,13 +1044,33 @@ impl TlsConnectorBuilder {
         Ok(TlsConnector {
             inner: Inner {
                 ssl: connector.build(),
+                host_identities: HostIdentities::new(
+                    host_identities
+                        .into_iter()
+                        .map(|(pattern, connector)| (pattern, connector.build())),
+                ),
                 cache,
                 config,
             },
+            builder: self,
+            config: Arc::new(cfg),
         })
     }
 }
 
+/// Stores the sessions established through `connector` in `cache`.
+fn set_session_cache(connector: &mut SslConnectorBuilder, cache: &Arc<Mutex<SessionCache>>) {
+    connector.set_session_cache_mode(SslSessionCacheMode::CLIENT);
+    connector.set_new_session_callback({
+        let cache = cache.clone();
+        move |ssl, session| {
+            if let Ok(Some(key)) = key_index().map(|idx| ssl.ex_data(idx)) {
+                cache.lock().insert(key.clone(), session);
+            }
+        }
+    });
+}
+
 // ===== impl TlsConnector =====
 
 impl TlsConnector {
@@ -504,14 +1079,39 @@ impl TlsConnector {
         TlsConnectorBuilder {
             keylog_policy: None,
             identity: None,
+            host_identities: Vec::new(),
             cert_store: None,
             cert_verification: true,
+            revocation: None,
+            require_ocsp_staple: false,
             min_version: None,
             max_version: None,
             tls_sni: true,
             verify_hostname: true,
+            debug_capture: false,
+            tls13_only: false,
         }
     }
+
+    /// Returns a handle to the session cache of this connector.
+    pub(crate) fn sessions(&self) -> TlsSessions {
+        TlsSessions(self.inner.cache.clone())
+    }
+
+    /// Returns the configuration of this connector, after the overrides of its builder.
+    pub(crate) fn config(&self) -> &TlsConfig {
+        &self.config
+    }
+
+    /// Returns the builder this connector was built from.
+    pub(crate) fn origin(&self) -> &TlsConnectorBuilder {
+        &self.builder
+    }
+
+    /// Builds a new connector from the builder of this one, with its own session cache.
+    pub(crate) fn rebuild(&self, cfg: TlsConfig) -> crate::Result<TlsConnector> {
+        self.builder.clone().build_shared(cfg)
+    }
 }
 
 /// A stream which may be wrapped with TLS.
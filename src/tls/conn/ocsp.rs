@@ -0,0 +1,21 @@
+use std::{error::Error as StdError, fmt};
+
+use boring2::ssl::SslRef;
+
+/// Returns whether the server stapled an OCSP response to the connection.
+pub(super) fn has_ocsp_staple(ssl: &SslRef) -> bool {
+    ssl.ocsp_status()
+        .is_some_and(|response| !response.is_empty())
+}
+
+/// The server stapled no OCSP response, and `require_ocsp_staple` is set.
+#[derive(Debug)]
+pub(crate) struct OcspStapleMissing;
+
+impl fmt::Display for OcspStapleMissing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("server did not staple an OCSP response")
+    }
+}
+
+impl StdError for OcspStapleMissing {}
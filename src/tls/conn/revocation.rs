@@ -0,0 +1,285 @@
+//! Certificate revocation checking against CRLs.
+
+use std::{error::Error as StdError, ffi::c_int, fmt, ptr, sync::Arc};
+
+use boring_sys2 as ffi;
+use boring2::{
+    error::ErrorStack,
+    ssl::{SslConnectorBuilder, SslRef},
+    x509::X509StoreContextRef,
+};
+use foreign_types::ForeignTypeRef;
+
+use crate::{Error, sync::RwLock};
+
+/// How a certificate is treated when its revocation status cannot be determined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RevocationPolicy {
+    /// Reject certificates without a valid CRL from their issuer.
+    #[default]
+    HardFail,
+    /// Accept certificates without a valid CRL from their issuer, unless revoked.
+    SoftFail,
+}
+
+/// The reason a certificate failed revocation checking.
+///
+/// Available as the source of an [`Error`](crate::Error) when a TLS handshake fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RevocationError {
+    /// The certificate is listed as revoked by its issuer.
+    Revoked,
+    /// No CRL of the issuer is available, or it could not be verified.
+    CrlUnavailable,
+    /// The CRL of the issuer has expired, or is not valid yet.
+    CrlExpired,
+}
+
+impl RevocationError {
+    /// Returns the revocation failure of a connection, if its certificate verification
+    /// failed because of one.
+    pub(crate) fn from_ssl(ssl: &SslRef) -> Option<RevocationError> {
+        Self::from_verify_error(ssl.verify_result().as_raw())
+    }
+
+    fn from_verify_error(err: c_int) -> Option<RevocationError> {
+        match err as u32 {
+            ffi::X509_V_ERR_CERT_REVOKED => Some(RevocationError::Revoked),
+            ffi::X509_V_ERR_UNABLE_TO_GET_CRL
+            | ffi::X509_V_ERR_UNABLE_TO_GET_CRL_ISSUER
+            | ffi::X509_V_ERR_UNABLE_TO_DECRYPT_CRL_SIGNATURE
+            | ffi::X509_V_ERR_CRL_SIGNATURE_FAILURE
+            | ffi::X509_V_ERR_ERROR_IN_CRL_LAST_UPDATE_FIELD
+            | ffi::X509_V_ERR_ERROR_IN_CRL_NEXT_UPDATE_FIELD => {
+                Some(RevocationError::CrlUnavailable)
+            }
+            ffi::X509_V_ERR_CRL_HAS_EXPIRED | ffi::X509_V_ERR_CRL_NOT_YET_VALID => {
+                Some(RevocationError::CrlExpired)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RevocationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            RevocationError::Revoked => "certificate has been revoked",
+            RevocationError::CrlUnavailable => "certificate revocation list is unavailable",
+            RevocationError::CrlExpired => "certificate revocation list has expired",
+        })
+    }
+}
+
+impl StdError for RevocationError {}
+
+/// A parsed certificate revocation list.
+pub(crate) struct Crl(*mut ffi::X509_CRL);
+
+// SAFETY: an `X509_CRL` is not modified after parsing, and safe to share between threads.
+unsafe impl Send for Crl {}
+unsafe impl Sync for Crl {}
+
+impl Crl {
+    /// Parses every CRL in PEM data, or a single DER-encoded CRL.
+    pub(crate) fn parse(data: &[u8]) -> crate::Result<Vec<Crl>> {
+        if data.trim_ascii_start().starts_with(b"-----BEGIN") {
+            Self::stack_from_pem(data)
+        } else {
+            Self::from_der(data).map(|crl| vec![crl])
+        }
+    }
+
+    fn from_der(der: &[u8]) -> crate::Result<Crl> {
+        let len = der.len().try_into().map_err(Error::builder)?;
+        let mut ptr = der.as_ptr();
+        let crl = unsafe { ffi::d2i_X509_CRL(ptr::null_mut(), &mut ptr, len) };
+        if crl.is_null() {
+            return Err(Error::tls(ErrorStack::get()));
+        }
+        Ok(Crl(crl))
+    }
+
+    fn stack_from_pem(pem: &[u8]) -> crate::Result<Vec<Crl>> {
+        let len = pem.len().try_into().map_err(Error::builder)?;
+        let bio = unsafe { ffi::BIO_new_mem_buf(pem.as_ptr().cast(), len) };
+        if bio.is_null() {
+            return Err(Error::tls(ErrorStack::get()));
+        }
+
+        let mut crls = Vec::new();
+        loop {
+            let crl =
+                unsafe { ffi::PEM_read_bio_X509_CRL(bio, ptr::null_mut(), None, ptr::null_mut()) };
+            if crl.is_null() {
+                break;
+            }
+            crls.push(Crl(crl));
+        }
+        unsafe { ffi::BIO_free(bio) };
+
+        // Reading always ends with an error, which is only reported without any CRL
+        let errors = ErrorStack::get();
+        if crls.is_empty() {
+            return Err(Error::tls(errors));
+        }
+        Ok(crls)
+    }
+}
+
+impl Drop for Crl {
+    fn drop(&mut self) {
+        unsafe { ffi::X509_CRL_free(self.0) }
+    }
+}
+
+/// A set of CRLs, with the stack handed to certificate verification.
+struct CrlSet {
+    crls: Vec<Crl>,
+    stack: *mut ffi::OPENSSL_STACK,
+}
+
+// SAFETY: the stack only references the CRLs, and is not modified after construction.
+unsafe impl Send for CrlSet {}
+unsafe impl Sync for CrlSet {}
+
+impl CrlSet {
+    fn new(crls: Vec<Crl>) -> crate::Result<CrlSet> {
+        let stack = unsafe { ffi::OPENSSL_sk_new_null() };
+        if stack.is_null() {
+            return Err(Error::tls(ErrorStack::get()));
+        }
+
+        let set = CrlSet { crls, stack };
+        for crl in &set.crls {
+            if unsafe { ffi::OPENSSL_sk_push(set.stack, crl.0.cast()) } == 0 {
+                return Err(Error::tls(ErrorStack::get()));
+            }
+        }
+        Ok(set)
+    }
+}
+
+impl Drop for CrlSet {
+    fn drop(&mut self) {
+        // The CRLs are owned by `crls`, so only the stack is freed
+        unsafe { ffi::OPENSSL_sk_free(self.stack) }
+    }
+}
+
+/// Checks the revocation status of server certificates against a replaceable set of CRLs.
+#[derive(Clone)]
+pub(crate) struct RevocationCheck {
+    crls: Arc<RwLock<Arc<CrlSet>>>,
+    check_all: bool,
+    policy: RevocationPolicy,
+}
+
+impl RevocationCheck {
+    /// Creates a check of the leaf certificate, or of the whole chain if `check_all` is set.
+    pub(crate) fn new(
+        crls: Vec<Crl>,
+        check_all: bool,
+        policy: RevocationPolicy,
+    ) -> crate::Result<RevocationCheck> {
+        Ok(RevocationCheck {
+            crls: Arc::new(RwLock::new(Arc::new(CrlSet::new(crls)?))),
+            check_all,
+            policy,
+        })
+    }
+
+    /// Replaces the CRLs used by subsequent handshakes.
+    pub(crate) fn set_crls(&self, crls: Vec<Crl>) -> crate::Result<()> {
+        let crls = Arc::new(CrlSet::new(crls)?);
+        *self.crls.write() = crls;
+        Ok(())
+    }
+
+    /// Verifies server certificates of the connector against the CRLs.
+    pub(crate) fn install(&self, connector: &mut SslConnectorBuilder) {
+        let check = self.clone();
+        connector.set_cert_verify_callback(move |ctx| check.verify(ctx));
+    }
+
+    fn verify(&self, ctx: &mut X509StoreContextRef) -> bool {
+        // Keeps the CRLs alive until verification finishes, even if replaced meanwhile
+        let crls = self.crls.read().clone();
+
+        let mut flags = ffi::X509_V_FLAG_CRL_CHECK;
+        if self.check_all {
+            flags |= ffi::X509_V_FLAG_CRL_CHECK_ALL;
+        }
+
+        unsafe {
+            let ctx = ctx.as_ptr();
+            ffi::X509_STORE_CTX_set0_crls(ctx, crls.stack.cast());
+            ffi::X509_STORE_CTX_set_flags(ctx, flags as _);
+            if self.policy == RevocationPolicy::SoftFail {
+                ffi::X509_STORE_CTX_set_verify_cb(ctx, Some(soft_fail));
+            }
+            if ffi::X509_verify_cert(ctx) == 1 {
+                return true;
+            }
+        }
+
+        // The verify callback of the connection may have been replaced by `soft_fail`
+        super::verify::record(ctx);
+        false
+    }
+}
+
+/// Verify callback ignoring errors caused by missing or expired CRLs.
+unsafe extern "C" fn soft_fail(ok: c_int, ctx: *mut ffi::X509_STORE_CTX) -> c_int {
+    if ok == 0 {
+        let err = unsafe { ffi::X509_STORE_CTX_get_error(ctx) };
+        if let Some(RevocationError::CrlUnavailable | RevocationError::CrlExpired) =
+            RevocationError::from_verify_error(err)
+        {
+            return 1;
+        }
+    }
+    ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pem_and_der_crls() {
+        let pem = include_bytes!("../../../tests/support/crl/crl.pem");
+        let mut both = pem.to_vec();
+        both.extend_from_slice(include_bytes!("../../../tests/support/crl/expired.crl.pem"));
+
+        assert_eq!(Crl::parse(pem).unwrap().len(), 1);
+        assert_eq!(
+            Crl::parse(include_bytes!("../../../tests/support/crl/crl.der"))
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(Crl::parse(&both).unwrap().len(), 2);
+        assert!(Crl::parse(b"-----BEGIN X509 CRL-----\n").is_err());
+        assert!(Crl::parse(b"\x30\x03\x02\x01\x00").is_err());
+    }
+
+    #[test]
+    fn classifies_verify_errors() {
+        let classify = |err: u32| RevocationError::from_verify_error(err as c_int);
+        assert_eq!(
+            classify(ffi::X509_V_ERR_CERT_REVOKED),
+            Some(RevocationError::Revoked)
+        );
+        assert_eq!(
+            classify(ffi::X509_V_ERR_UNABLE_TO_GET_CRL),
+            Some(RevocationError::CrlUnavailable)
+        );
+        assert_eq!(
+            classify(ffi::X509_V_ERR_CRL_HAS_EXPIRED),
+            Some(RevocationError::CrlExpired)
+        );
+        assert_eq!(classify(ffi::X509_V_ERR_CERT_HAS_EXPIRED), None);
+    }
+}
@@ -12,7 +12,10 @@ use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_boring2::SslStream;
 use tower_service::Service;
 
-use super::{HttpsConnector, MaybeHttpsStream};
+use super::{
+    CertVerifyError, HttpsConnector, MaybeHttpsStream, RevocationError,
+    alpn::verify_negotiated_protocol,
+};
 use crate::{
     core::{client::connect::Connection, rt::TokioIo},
     error::BoxError,
@@ -54,7 +57,12 @@ where
             let ssl = inner.setup_ssl(&uri, host)?;
             let stream = tokio_boring2::SslStreamBuilder::new(ssl, conn)
                 .connect()
-                .await?;
+                .await
+                .map_err(handshake_error)?;
+            inner.record_handshake(stream.ssl());
+            inner.verify_downgrade(stream.ssl())?;
+            inner.verify_ocsp_staple(stream.ssl())?;
+            verify_negotiated_protocol(stream.ssl())?;
 
             Ok(MaybeHttpsStream::Https(stream))
         };
@@ -89,7 +97,12 @@ where
             let ssl = inner.setup_ssl(&uri, host)?;
             let stream = tokio_boring2::SslStreamBuilder::new(ssl, stream.into_inner())
                 .connect()
-                .await?;
+                .await
+                .map_err(handshake_error)?;
+            inner.record_handshake(stream.ssl());
+            inner.verify_downgrade(stream.ssl())?;
+            inner.verify_ocsp_staple(stream.ssl())?;
+            verify_negotiated_protocol(stream.ssl())?;
 
             Ok(stream)
         };
@@ -98,10 +111,24 @@ where
     }
 }
 
+/// Reports a failed revocation check or certificate verification as the cause of a handshake
+/// error.
+fn handshake_error<S>(err: tokio_boring2::HandshakeError<S>) -> BoxError
+where
+    S: Debug + Send + Sync + 'static,
+{
+    let cause = err.ssl().and_then(|ssl| {
+        RevocationError::from_ssl(ssl)
+            .map(BoxError::from)
+            .or_else(|| CertVerifyError::from_ssl(ssl).map(BoxError::from))
+    });
+    cause.unwrap_or_else(|| err.into())
+}
+
 /// If `host` is an IPv6 address, we must strip away the square brackets that surround
 /// it (otherwise, boring will fail to parse the host as an IP address, eventually
 /// causing the handshake to fail due a hostname verification error).
-fn normalize_host(host: &str) -> &str {
+pub(super) fn normalize_host(host: &str) -> &str {
     if host.is_empty() {
         return host;
     }
@@ -117,3 +144,18 @@ fn normalize_host(host: &str) -> &str {
 
     host
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_host_strips_ipv6_brackets() {
+        assert_eq!(normalize_host("[::1]"), "::1");
+        assert_eq!(normalize_host("[2001:db8::1]"), "2001:db8::1");
+        assert_eq!(normalize_host("[not-an-ip]"), "[not-an-ip]");
+        assert_eq!(normalize_host("127.0.0.1"), "127.0.0.1");
+        assert_eq!(normalize_host("example.com"), "example.com");
+        assert_eq!(normalize_host(""), "");
+    }
+}
@@ -0,0 +1,113 @@
+use std::{error::Error as StdError, ffi::c_int, fmt, sync::LazyLock};
+
+use boring2::{
+    error::ErrorStack,
+    ex_data::Index,
+    ssl::{Ssl, SslRef},
+    x509::{X509NameRef, X509StoreContext, X509StoreContextRef},
+};
+
+use crate::sync::Mutex;
+
+pub(super) fn verify_failure_index()
+-> Result<Index<Ssl, Mutex<Option<CertVerifyError>>>, ErrorStack> {
+    static IDX: LazyLock<Result<Index<Ssl, Mutex<Option<CertVerifyError>>>, ErrorStack>> =
+        LazyLock::new(Ssl::new_ex_index);
+    IDX.clone()
+}
+
+/// Why a server certificate failed verification.
+///
+/// Available with [`Error::cert_verify_details`](crate::Error::cert_verify_details) when a
+/// TLS handshake fails, and as the source of the error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertVerifyError {
+    code: c_int,
+    reason: &'static str,
+    depth: u32,
+    subject: Option<String>,
+}
+
+impl CertVerifyError {
+    /// Returns the verification failure recorded for a connection, if any.
+    pub(crate) fn from_ssl(ssl: &SslRef) -> Option<CertVerifyError> {
+        ssl.ex_data(verify_failure_index().ok()?)?.lock().clone()
+    }
+
+    /// Returns the `X509_V_ERR_*` code of the failure.
+    pub fn code(&self) -> c_int {
+        self.code
+    }
+
+    /// Returns a description of the failure, such as `certificate has expired`.
+    pub fn reason(&self) -> &str {
+        self.reason
+    }
+
+    /// Returns the depth of the offending certificate in the chain, `0` being the server's.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Returns the subject of the offending certificate, such as `CN=example.com`.
+    pub fn subject(&self) -> Option<&str> {
+        self.subject.as_deref()
+    }
+}
+
+impl fmt::Display for CertVerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "certificate verify failed: {}", self.reason)?;
+        match self.subject {
+            Some(ref subject) => write!(f, " ({subject}, depth {})", self.depth),
+            None => write!(f, " (depth {})", self.depth),
+        }
+    }
+}
+
+impl StdError for CertVerifyError {}
+
+/// Verify callback recording the first failure of a connection, without changing the result.
+pub(super) fn record_verify_failure(ok: bool, ctx: &mut X509StoreContextRef) -> bool {
+    if !ok {
+        record(ctx);
+    }
+    ok
+}
+
+/// Records the current error of `ctx` for its connection, unless one was recorded already.
+pub(super) fn record(ctx: &X509StoreContextRef) {
+    let Ok(ssl_idx) = X509StoreContext::ssl_idx() else {
+        return;
+    };
+    let Some(slot) = verify_failure_index()
+        .ok()
+        .and_then(|idx| ctx.ex_data(ssl_idx)?.ex_data(idx))
+    else {
+        return;
+    };
+
+    let error = ctx.error();
+    slot.lock().get_or_insert_with(|| CertVerifyError {
+        code: error.as_raw(),
+        reason: error.error_string(),
+        depth: ctx.error_depth(),
+        subject: ctx
+            .current_cert()
+            .map(|cert| format_name(cert.subject_name())),
+    });
+}
+
+/// Formats a name as comma separated `key=value` entries.
+fn format_name(name: &X509NameRef) -> String {
+    name.entries()
+        .map(|entry| {
+            let key = entry.object().nid().short_name().unwrap_or("?");
+            match entry.data().as_utf8() {
+                Ok(value) => format!("{key}={value}"),
+                Err(_) => format!("{key}=?"),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
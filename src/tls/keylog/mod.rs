@@ -16,7 +16,7 @@ static GLOBAL_KEYLOG_FILE_MAPPING: OnceLock<RwLock<HashMap<PathBuf, KeyLogHandle
     OnceLock::new();
 
 /// Specifies the intent for a (TLS) keylogger to be used in a client or server configuration.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum KeyLogPolicy {
     /// Uses the default behavior, respecting the `SSLKEYLOGFILE` environment variable.
     ///
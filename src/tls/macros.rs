@@ -34,11 +34,3 @@ macro_rules! set_option_inner_try {
             .map_err(Error::tls)?;
     };
 }
-
-macro_rules! call_option_ref_try {
-    ($owner:expr, $field:ident, $target:expr, $method:ident) => {
-        if let Some(val) = $owner.$field.as_ref() {
-            val.$method($target)?;
-        }
-    };
-}
@@ -9,16 +9,37 @@ mod macros;
 mod config;
 mod conn;
 mod keylog;
+#[cfg(feature = "serde")]
+mod serde;
 mod x509;
 
+use std::{
+    borrow::Cow,
+    fmt,
+    hash::{Hash, Hasher},
+    io,
+    ops::{BitOr, BitOrAssign},
+    sync::Arc,
+};
+
 pub use boring2::ssl::ExtensionType;
+use boring2::x509::verify::X509CheckFlags;
 use bytes::{Bytes, BytesMut};
+use http::Uri;
 
-pub(crate) use self::conn::{HttpsConnector, MaybeHttpsStream, TlsConnector, TlsConnectorBuilder};
+pub(crate) use self::conn::{
+    Crl, DowngradeDetected, EarlyDataHandshake, HostPattern, HttpsConnector, MaybeHttpsStream,
+    OcspStapleMissing, RevocationCheck, TlsSessions, handshake_capture, handshake_started,
+    is_early_data_rejected, tls_info,
+};
 pub use self::{
-    config::TlsConfig,
+    config::{TlsConfig, TlsConfigBuilder},
+    conn::{CertVerifyError, RevocationError, RevocationPolicy, TlsConnector, TlsConnectorBuilder},
     keylog::KeyLogPolicy,
-    x509::{CertStore, CertStoreBuilder, Certificate, CertificateInput, Identity},
+    x509::{
+        CertStore, CertStoreBuilder, Certificate, CertificateInput, Identity, IdentityError,
+        SignFuture, TlsSigner,
+    },
 };
 
 /// A TLS protocol version.
@@ -39,23 +60,147 @@ impl TlsVersion {
     pub const TLS_1_3: TlsVersion = TlsVersion(boring2::ssl::SslVersion::TLS1_3);
 }
 
-/// A TLS ALPN protocol.
+/// A TLS signature algorithm, as requested from a [`TlsSigner`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignatureAlgorithm(pub(crate) boring2::ssl::SslSignatureAlgorithm);
+
+impl SignatureAlgorithm {
+    /// `rsa_pkcs1_sha256`
+    pub const RSA_PKCS1_SHA256: SignatureAlgorithm =
+        SignatureAlgorithm(boring2::ssl::SslSignatureAlgorithm::RSA_PKCS1_SHA256);
+
+    /// `rsa_pkcs1_sha384`
+    pub const RSA_PKCS1_SHA384: SignatureAlgorithm =
+        SignatureAlgorithm(boring2::ssl::SslSignatureAlgorithm::RSA_PKCS1_SHA384);
+
+    /// `rsa_pkcs1_sha512`
+    pub const RSA_PKCS1_SHA512: SignatureAlgorithm =
+        SignatureAlgorithm(boring2::ssl::SslSignatureAlgorithm::RSA_PKCS1_SHA512);
+
+    /// `rsa_pss_rsae_sha256`
+    pub const RSA_PSS_RSAE_SHA256: SignatureAlgorithm =
+        SignatureAlgorithm(boring2::ssl::SslSignatureAlgorithm::RSA_PSS_RSAE_SHA256);
+
+    /// `rsa_pss_rsae_sha384`
+    pub const RSA_PSS_RSAE_SHA384: SignatureAlgorithm =
+        SignatureAlgorithm(boring2::ssl::SslSignatureAlgorithm::RSA_PSS_RSAE_SHA384);
+
+    /// `rsa_pss_rsae_sha512`
+    pub const RSA_PSS_RSAE_SHA512: SignatureAlgorithm =
+        SignatureAlgorithm(boring2::ssl::SslSignatureAlgorithm::RSA_PSS_RSAE_SHA512);
+
+    /// `ecdsa_secp256r1_sha256`
+    pub const ECDSA_SECP256R1_SHA256: SignatureAlgorithm =
+        SignatureAlgorithm(boring2::ssl::SslSignatureAlgorithm::ECDSA_SECP256R1_SHA256);
+
+    /// `ecdsa_secp384r1_sha384`
+    pub const ECDSA_SECP384R1_SHA384: SignatureAlgorithm =
+        SignatureAlgorithm(boring2::ssl::SslSignatureAlgorithm::ECDSA_SECP384R1_SHA384);
+
+    /// `ecdsa_secp521r1_sha512`
+    pub const ECDSA_SECP521R1_SHA512: SignatureAlgorithm =
+        SignatureAlgorithm(boring2::ssl::SslSignatureAlgorithm::ECDSA_SECP521R1_SHA512);
+
+    /// `ed25519`
+    pub const ED25519: SignatureAlgorithm =
+        SignatureAlgorithm(boring2::ssl::SslSignatureAlgorithm::ED25519);
+}
+
+/// A TLS 1.3 cipher suite.
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
-pub struct AlpnProtocol(&'static [u8]);
+pub struct Tls13CipherSuite(&'static str);
+
+impl Tls13CipherSuite {
+    /// `TLS_AES_128_GCM_SHA256`
+    pub const AES_128_GCM_SHA256: Tls13CipherSuite = Tls13CipherSuite("TLS_AES_128_GCM_SHA256");
+
+    /// `TLS_AES_256_GCM_SHA384`
+    pub const AES_256_GCM_SHA384: Tls13CipherSuite = Tls13CipherSuite("TLS_AES_256_GCM_SHA384");
+
+    /// `TLS_CHACHA20_POLY1305_SHA256`
+    pub const CHACHA20_POLY1305_SHA256: Tls13CipherSuite =
+        Tls13CipherSuite("TLS_CHACHA20_POLY1305_SHA256");
+
+    /// Returns the standard name of the cipher suite.
+    #[inline(always)]
+    pub const fn name(&self) -> &'static str {
+        self.0
+    }
+
+    /// Joins the names of the cipher suites into a cipher list string.
+    fn join(suites: &[Tls13CipherSuite]) -> String {
+        suites
+            .iter()
+            .map(Tls13CipherSuite::name)
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+}
+
+/// A TLS ALPN protocol.
+///
+/// Besides the protocols defined here, any protocol can be advertised with
+/// [`AlpnProtocol::custom`]. The connection still speaks HTTP/1.1 or HTTP/2, so if the server
+/// selects another protocol, connecting fails.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct AlpnProtocol(Bytes);
 
 impl AlpnProtocol {
     /// Prefer HTTP/1.1
-    pub const HTTP1: AlpnProtocol = AlpnProtocol(b"\x08http/1.1");
+    pub const HTTP1: AlpnProtocol = AlpnProtocol(Bytes::from_static(b"\x08http/1.1"));
 
     /// Prefer HTTP/2
-    pub const HTTP2: AlpnProtocol = AlpnProtocol(b"\x02h2");
+    pub const HTTP2: AlpnProtocol = AlpnProtocol(Bytes::from_static(b"\x02h2"));
 
     /// Prefer HTTP/3
-    pub const HTTP3: AlpnProtocol = AlpnProtocol(b"\x02h3");
+    pub const HTTP3: AlpnProtocol = AlpnProtocol(Bytes::from_static(b"\x02h3"));
+
+    /// Creates an ALPN protocol from its identification sequence, such as `"h2c"`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `name` is empty or longer than 255 bytes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use wreq::tls::{AlpnProtocol, TlsConfig};
+    ///
+    /// // advertise HTTP/1.1 ahead of HTTP/2 and HTTP/3
+    /// let config = TlsConfig::builder()
+    ///     .alpn_protos(&[
+    ///         AlpnProtocol::HTTP1,
+    ///         AlpnProtocol::HTTP2,
+    ///         AlpnProtocol::custom("h3")?,
+    ///     ])
+    ///     .build();
+    /// # drop(config);
+    /// # Ok::<(), wreq::Error>(())
+    /// ```
+    pub fn custom(name: impl AsRef<[u8]>) -> crate::Result<AlpnProtocol> {
+        let name = name.as_ref();
+        let len = u8::try_from(name.len())
+            .ok()
+            .filter(|len| *len > 0)
+            .ok_or_else(|| {
+                crate::Error::builder("ALPN protocol must be between 1 and 255 bytes long")
+            })?;
+
+        let mut buf = BytesMut::with_capacity(name.len() + 1);
+        buf.extend_from_slice(&[len]);
+        buf.extend_from_slice(name);
+        Ok(AlpnProtocol(buf.freeze()))
+    }
+
+    /// Returns the identification sequence of the protocol, without its length prefix.
+    #[inline]
+    pub fn name(&self) -> &[u8] {
+        &self.0[1..]
+    }
 
     #[inline]
     pub(crate) fn encode(self) -> Bytes {
-        Bytes::from_static(self.0)
+        self.0
     }
 
     #[inline]
@@ -70,7 +215,7 @@ impl AlpnProtocol {
 impl AsRef<[u8]> for AlpnProtocol {
     #[inline(always)]
     fn as_ref(&self) -> &[u8] {
-        self.0
+        &self.0
     }
 }
 
@@ -123,11 +268,73 @@ impl CertificateCompressionAlgorithm {
         CertificateCompressionAlgorithm(boring2::ssl::CertificateCompressionAlgorithm::ZSTD);
 }
 
+/// Flags controlling how the server certificate is matched against the hostname.
+///
+/// Combine flags with `|`. When not configured, partial wildcards such as `f*.example.com`
+/// are already rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostFlags(X509CheckFlags);
+
+impl HostFlags {
+    /// Don't match wildcard names at all.
+    pub const NO_WILDCARDS: HostFlags = HostFlags(X509CheckFlags::NO_WILDCARDS);
+
+    /// Don't match wildcards that are only part of the leftmost label, such as `f*.example.com`.
+    pub const NO_PARTIAL_WILDCARDS: HostFlags = HostFlags(X509CheckFlags::NO_PARTIAL_WILDCARDS);
+
+    /// Never fall back to the subject common name, even without DNS names in the certificate.
+    pub const NEVER_CHECK_SUBJECT: HostFlags = HostFlags(X509CheckFlags::NEVER_CHECK_SUBJECT);
+
+    /// Returns flags with nothing set, allowing partial wildcards.
+    pub const fn empty() -> HostFlags {
+        HostFlags(X509CheckFlags::empty())
+    }
+
+    /// Returns whether all flags of `other` are set.
+    pub fn contains(self, other: HostFlags) -> bool {
+        self.0.contains(other.0)
+    }
+}
+
+impl BitOr for HostFlags {
+    type Output = HostFlags;
+
+    fn bitor(self, rhs: HostFlags) -> HostFlags {
+        HostFlags(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for HostFlags {
+    fn bitor_assign(&mut self, rhs: HostFlags) {
+        self.0 = self.0 | rhs.0;
+    }
+}
+
+/// A user-provided implementation of a certificate compression algorithm.
+///
+/// Registered with [`TlsConfigBuilder::certificate_compressor`], it replaces the
+/// built-in implementation of the algorithm it reports.
+pub trait CertificateCompressor: fmt::Debug + Send + Sync + 'static {
+    /// The algorithm implemented by this compressor.
+    fn algorithm(&self) -> CertificateCompressionAlgorithm;
+
+    /// Compresses `input`, writing the result to `output`.
+    fn compress(&self, input: &[u8], output: &mut dyn io::Write) -> io::Result<()>;
+
+    /// Decompresses `input`, writing the result to `output`.
+    fn decompress(&self, input: &[u8], output: &mut dyn io::Write) -> io::Result<()>;
+}
+
 /// Hyper extension carrying extra TLS layer information.
 /// Made available to clients on responses when `tls_info` is set.
 #[derive(Debug, Clone)]
 pub struct TlsInfo {
     pub(crate) peer_certificate: Option<Vec<u8>>,
+    pub(crate) cipher_suite: Option<&'static str>,
+    pub(crate) downgrade_sentinel: bool,
+    pub(crate) peer_alps_settings: Option<Vec<u8>>,
+    pub(crate) ocsp_response: Option<Vec<u8>>,
+    pub(crate) session_reused: bool,
 }
 
 impl TlsInfo {
@@ -135,6 +342,255 @@ impl TlsInfo {
     pub fn peer_certificate(&self) -> Option<&[u8]> {
         self.peer_certificate.as_ref().map(|der| &der[..])
     }
+
+    /// Get the standard name of the negotiated cipher suite.
+    pub fn cipher_suite(&self) -> Option<&str> {
+        self.cipher_suite
+    }
+
+    /// Returns true if the ServerHello random carried a TLS 1.3 downgrade sentinel,
+    /// meaning a server supporting TLS 1.3 negotiated an older version.
+    pub fn downgrade_sentinel(&self) -> bool {
+        self.downgrade_sentinel
+    }
+
+    /// Get the application settings the server sent over ALPS, if it was negotiated.
+    ///
+    /// For HTTP/2 this is the server's SETTINGS frame payload.
+    pub fn peer_alps_settings(&self) -> Option<&[u8]> {
+        self.peer_alps_settings.as_deref()
+    }
+
+    /// Get the DER encoded OCSP response the server stapled to the handshake.
+    ///
+    /// Only requested by the client if
+    /// [`enable_ocsp_stapling`](TlsConfigBuilder::enable_ocsp_stapling) is set.
+    pub fn ocsp_response(&self) -> Option<&[u8]> {
+        self.ocsp_response.as_deref()
+    }
+
+    /// Returns true if the handshake of the connection resumed a previous session.
+    ///
+    /// This describes the handshake of the connection that carried the response, which may
+    /// have happened for an earlier request. Whether the connection itself was taken from the
+    /// pool, without any handshake for this request, is reported by
+    /// [`ConnectTiming::reused`](crate::ConnectTiming::reused).
+    pub fn session_reused(&self) -> bool {
+        self.session_reused
+    }
+}
+
+/// Statistics of the TLS session cache of a `Client`.
+///
+/// Returned by [`Client::tls_session_stats`](crate::Client::tls_session_stats).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TlsSessionStats {
+    pub(crate) entries: usize,
+    pub(crate) hits: u64,
+    pub(crate) misses: u64,
+    pub(crate) full_handshakes: u64,
+    pub(crate) resumed_handshakes: u64,
+}
+
+impl TlsSessionStats {
+    /// Get the number of sessions currently cached.
+    pub fn entries(&self) -> usize {
+        self.entries
+    }
+
+    /// Get the number of handshakes that found a session to resume.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Get the number of handshakes that found no session to resume.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Get the number of completed handshakes that did not resume a session.
+    ///
+    /// Unlike [`misses`](Self::misses), this includes the handshakes offering a session the
+    /// server declined to resume.
+    pub fn full_handshakes(&self) -> u64 {
+        self.full_handshakes
+    }
+
+    /// Get the number of completed handshakes that resumed a session.
+    pub fn resumed_handshakes(&self) -> u64 {
+        self.resumed_handshakes
+    }
+}
+
+/// The key TLS sessions are cached and resumed under.
+///
+/// Sessions are only resumed by connections with the same key.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct SessionKey(String);
+
+impl SessionKey {
+    /// Creates a session key from an arbitrary string.
+    pub fn new<K: Into<String>>(key: K) -> SessionKey {
+        SessionKey(key.into())
+    }
+
+    /// Get the key as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Which connections share cached TLS sessions.
+///
+/// Defaults to [`SessionKeyScope::HostPort`].
+#[derive(Clone, Default)]
+#[non_exhaustive]
+pub enum SessionKeyScope {
+    /// Sessions are shared by connections to the same host and port.
+    #[default]
+    HostPort,
+    /// Sessions are shared by connections to the same host, on any port.
+    Host,
+    /// Sessions are keyed by the given function of the request URI.
+    Custom(Arc<dyn Fn(&Uri) -> SessionKey + Send + Sync>),
+}
+
+impl SessionKeyScope {
+    /// Creates a scope keying sessions by the given function of the request URI.
+    pub fn custom<F>(f: F) -> SessionKeyScope
+    where
+        F: Fn(&Uri) -> SessionKey + Send + Sync + 'static,
+    {
+        SessionKeyScope::Custom(Arc::new(f))
+    }
+
+    /// Returns the session key of a connection to `uri`.
+    pub(crate) fn key(&self, uri: &Uri) -> Option<SessionKey> {
+        match self {
+            SessionKeyScope::HostPort => uri
+                .authority()
+                .map(|authority| SessionKey(authority.as_str().to_ascii_lowercase())),
+            SessionKeyScope::Host => uri.host().map(|host| SessionKey(host.to_ascii_lowercase())),
+            SessionKeyScope::Custom(f) => Some(f(uri)),
+        }
+    }
+}
+
+impl fmt::Debug for SessionKeyScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionKeyScope::HostPort => f.write_str("HostPort"),
+            SessionKeyScope::Host => f.write_str("Host"),
+            SessionKeyScope::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// Overrides whether the client assumes AES hardware support, which decides if AES-GCM or
+/// ChaCha20-Poly1305 cipher suites are preferred.
+///
+/// Without an override, the support of the CPU is detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AesHwOverride {
+    /// AES hardware support is assumed, preferring AES-GCM.
+    On,
+    /// No AES hardware support is assumed, preferring ChaCha20-Poly1305.
+    Off,
+    /// Every connection chooses between `On` and `Off` at random.
+    Random,
+    /// Every connection chooses between `On` and `Off` with a random number generator of the
+    /// client, so that clients with the same seed make the same choices, connection by
+    /// connection.
+    SeededRandom {
+        /// The seed of the random number generator.
+        seed: u64,
+    },
+}
+
+/// Per-request override of the TLS server name.
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub(crate) struct ServerNameOverride {
+    /// The name sent in the SNI extension instead of the URI host.
+    pub(crate) sni: Option<Cow<'static, str>>,
+    /// Whether the certificate is verified against the URI host rather than the SNI.
+    pub(crate) verify_uri_host: bool,
+}
+
+/// Per-request override of the ALPS protocols.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub(crate) struct AlpsOverride {
+    /// The encoded protocols, or `None` to disable ALPS.
+    pub(crate) protos: Option<Bytes>,
+    /// Whether to use the new ALPS codepoint.
+    pub(crate) use_new_codepoint: bool,
+}
+
+/// Per-request override of certificate verification.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CertVerification {
+    /// Whether invalid certificates are accepted, overriding the client setting.
+    pub(crate) accept_invalid_certs: Option<bool>,
+    /// The store certificates are verified against, overriding the client store.
+    pub(crate) cert_store: Option<CertStore>,
+}
+
+impl CertVerification {
+    /// Returns true if nothing is overridden.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.accept_invalid_certs.is_none() && self.cert_store.is_none()
+    }
+}
+
+impl PartialEq for CertVerification {
+    fn eq(&self, other: &Self) -> bool {
+        self.accept_invalid_certs == other.accept_invalid_certs
+            && match (&self.cert_store, &other.cert_store) {
+                (Some(a), Some(b)) => a.ptr_eq(b),
+                (None, None) => true,
+                _ => false,
+            }
+    }
+}
+
+impl Eq for CertVerification {}
+
+impl Hash for CertVerification {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.accept_invalid_certs.hash(state);
+        if let Some(ref store) = self.cert_store {
+            store.hash_ptr(state);
+        }
+    }
+}
+
+/// Response extension reporting whether a request was sent as TLS 1.3 early data.
+/// Made available to clients on responses when early data was attempted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EarlyData {
+    /// The connection was resumed with early data, and the server accepted it.
+    Accepted,
+    /// The server rejected early data, and the request was retried over a full handshake.
+    Rejected,
+}
+
+/// Hyper extension carrying the raw TLS handshake messages of a connection.
+/// Made available to clients on responses when `tls_debug_capture` is set.
+#[derive(Debug, Clone)]
+pub struct TlsHandshakeCapture {
+    pub(crate) client_hello: Bytes,
+    pub(crate) server_hello: Option<Bytes>,
+}
+
+impl TlsHandshakeCapture {
+    /// Get the ClientHello handshake message sent by the client.
+    pub fn client_hello(&self) -> &[u8] {
+        &self.client_hello
+    }
+
+    /// Get the ServerHello handshake message received from the server.
+    pub fn server_hello(&self) -> Option<&[u8]> {
+        self.server_hello.as_deref()
+    }
 }
 
 fn encode_sequence<'a, T, I>(items: I) -> Bytes
@@ -175,6 +631,55 @@ mod tests {
         assert_eq!(alpn, Bytes::from_static(b"\x08http/1.1\x02h2\x02h3"));
     }
 
+    #[test]
+    fn cert_verification_compares_store_identity() {
+        let store = CertStore::builder().build().unwrap();
+        let other = CertStore::builder().build().unwrap();
+
+        let verify = |accept_invalid_certs, cert_store| CertVerification {
+            accept_invalid_certs,
+            cert_store,
+        };
+
+        assert_eq!(verify(Some(true), None), verify(Some(true), None));
+        assert_ne!(verify(Some(true), None), verify(Some(false), None));
+        assert_ne!(verify(None, None), verify(Some(false), None));
+        assert_eq!(
+            verify(None, Some(store.clone())),
+            verify(None, Some(store.clone()))
+        );
+        assert_ne!(verify(None, Some(store)), verify(None, Some(other)));
+    }
+
+    #[test]
+    fn session_key_scope() {
+        let key = |scope: SessionKeyScope, uri: &str| {
+            scope
+                .key(&uri.parse().unwrap())
+                .map(|key| key.as_str().to_owned())
+        };
+
+        assert_eq!(
+            key(SessionKeyScope::HostPort, "https://Example.com:8443/"),
+            Some("example.com:8443".to_owned())
+        );
+        assert_eq!(
+            key(SessionKeyScope::Host, "https://Example.com:8443/"),
+            Some("example.com".to_owned())
+        );
+        assert_eq!(key(SessionKeyScope::Host, "/path"), None);
+
+        let scope = SessionKeyScope::custom(|uri| {
+            let host = uri.host().unwrap_or_default();
+            let site = host.splitn(2, '.').last().unwrap_or(host);
+            SessionKey::new(site)
+        });
+        assert_eq!(
+            key(scope, "https://www.example.com/"),
+            Some("example.com".to_owned())
+        );
+    }
+
     #[test]
     fn alpn_protocol_encode_single() {
         let alpn = AlpnProtocol::HTTP1.encode();
@@ -186,4 +691,27 @@ mod tests {
         let alpn = AlpnProtocol::HTTP3.encode();
         assert_eq!(alpn, b"\x02h3".as_ref());
     }
+
+    #[test]
+    fn alpn_protocol_custom() {
+        let h2 = AlpnProtocol::custom("h2").unwrap();
+        assert_eq!(h2, AlpnProtocol::HTTP2);
+        assert_eq!(h2.name(), b"h2");
+
+        let alpn = AlpnProtocol::encode_sequence(&[
+            AlpnProtocol::custom("h3").unwrap(),
+            AlpnProtocol::HTTP2,
+            AlpnProtocol::custom(vec![b'x'; 255]).unwrap(),
+        ]);
+        assert_eq!(alpn.len(), 3 + 3 + 256);
+        assert_eq!(&alpn[..6], b"\x02h3\x02h2");
+        assert_eq!(alpn[6], 255);
+
+        assert!(AlpnProtocol::custom("").unwrap_err().is_builder());
+        assert!(
+            AlpnProtocol::custom(vec![b'x'; 256])
+                .unwrap_err()
+                .is_builder()
+        );
+    }
 }
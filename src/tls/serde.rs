@@ -0,0 +1,456 @@
+//! Serde support for [`TlsConfig`] and the types it contains.
+//!
+//! Protocols, versions, cipher suites and compression algorithms are written by name,
+//! extensions by their IANA code point. Registered [`CertificateCompressor`]s are code,
+//! not data, so they are never serialized, and a [`SessionKeyScope::Custom`] scope fails
+//! to serialize.
+//!
+//! [`CertificateCompressor`]: super::CertificateCompressor
+
+use std::borrow::Cow;
+
+use boring2::ssl::ExtensionType;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de, ser};
+
+use super::{
+    AesHwOverride, AlpnProtocol, AlpsProtocol, CertificateCompressionAlgorithm, HostFlags,
+    SessionKeyScope, Tls13CipherSuite, TlsConfig, TlsVersion,
+};
+
+/// Defines a string representation for a type with a fixed set of constants.
+macro_rules! named {
+    ($ty:ident, $what:literal, [$($name:literal => $value:expr),+ $(,)?]) => {
+        impl $ty {
+            const NAMES: &'static [(&'static str, $ty)] = &[$(($name, $value)),+];
+
+            fn to_name(self) -> Option<&'static str> {
+                Self::NAMES
+                    .iter()
+                    .find(|(_, value)| *value == self)
+                    .map(|(name, _)| *name)
+            }
+
+            fn from_name(name: &str) -> Option<$ty> {
+                Self::NAMES
+                    .iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, value)| *value)
+            }
+        }
+
+        impl Serialize for $ty {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                match self.to_name() {
+                    Some(name) => serializer.serialize_str(name),
+                    None => Err(ser::Error::custom(concat!("unknown ", $what))),
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $ty {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let name = Cow::<str>::deserialize(deserializer)?;
+                $ty::from_name(&name).ok_or_else(|| {
+                    de::Error::custom(format_args!(concat!("unknown ", $what, " `{}`"), name))
+                })
+            }
+        }
+    };
+}
+
+named!(TlsVersion, "TLS version", [
+    "1.0" => TlsVersion::TLS_1_0,
+    "1.1" => TlsVersion::TLS_1_1,
+    "1.2" => TlsVersion::TLS_1_2,
+    "1.3" => TlsVersion::TLS_1_3,
+]);
+
+named!(Tls13CipherSuite, "TLS 1.3 cipher suite", [
+    "TLS_AES_128_GCM_SHA256" => Tls13CipherSuite::AES_128_GCM_SHA256,
+    "TLS_AES_256_GCM_SHA384" => Tls13CipherSuite::AES_256_GCM_SHA384,
+    "TLS_CHACHA20_POLY1305_SHA256" => Tls13CipherSuite::CHACHA20_POLY1305_SHA256,
+]);
+
+/// ALPN protocols are written by name, which may be any protocol, not only the constants.
+impl Serialize for AlpnProtocol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match std::str::from_utf8(self.name()) {
+            Ok(name) => serializer.serialize_str(name),
+            Err(_) => Err(ser::Error::custom("ALPN protocol is not valid UTF-8")),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AlpnProtocol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = Cow::<str>::deserialize(deserializer)?;
+        AlpnProtocol::custom(&*name)
+            .map_err(|_| de::Error::custom(format_args!("invalid ALPN protocol `{name}`")))
+    }
+}
+
+named!(AlpsProtocol, "ALPS protocol", [
+    "http/1.1" => AlpsProtocol::HTTP1,
+    "h2" => AlpsProtocol::HTTP2,
+    "h3" => AlpsProtocol::HTTP3,
+]);
+
+named!(CertificateCompressionAlgorithm, "certificate compression algorithm", [
+    "zlib" => CertificateCompressionAlgorithm::ZLIB,
+    "brotli" => CertificateCompressionAlgorithm::BROTLI,
+    "zstd" => CertificateCompressionAlgorithm::ZSTD,
+]);
+
+/// Hostname verification flags are written as a list of flag names.
+const HOST_FLAG_NAMES: &[(&str, HostFlags)] = &[
+    ("no_wildcards", HostFlags::NO_WILDCARDS),
+    ("no_partial_wildcards", HostFlags::NO_PARTIAL_WILDCARDS),
+    ("never_check_subject", HostFlags::NEVER_CHECK_SUBJECT),
+];
+
+impl Serialize for HostFlags {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            HOST_FLAG_NAMES
+                .iter()
+                .filter(|(_, flag)| self.contains(*flag))
+                .map(|(name, _)| name),
+        )
+    }
+}
+
+impl<'de> Deserialize<'de> for HostFlags {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<Cow<str>>::deserialize(deserializer)?.iter().try_fold(
+            HostFlags::empty(),
+            |flags, name| {
+                HOST_FLAG_NAMES
+                    .iter()
+                    .find(|(n, _)| n == name)
+                    .map(|(_, flag)| flags | *flag)
+                    .ok_or_else(|| {
+                        de::Error::custom(format_args!(
+                            "unknown hostname verification flag `{name}`"
+                        ))
+                    })
+            },
+        )
+    }
+}
+
+/// The serialized form of [`SessionKeyScope`].
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ScopeRepr {
+    HostPort,
+    Host,
+}
+
+/// The serialized form of [`TlsConfig`].
+///
+/// Missing fields take the value of [`TlsConfig::default`].
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Repr {
+    alpn_protos: Option<Vec<AlpnProtocol>>,
+    alps_protos: Option<Vec<AlpsProtocol>>,
+    alps_use_new_codepoint: bool,
+    session_ticket: bool,
+    min_tls_version: Option<TlsVersion>,
+    max_tls_version: Option<TlsVersion>,
+    pre_shared_key: bool,
+    session_key_scope: ScopeRepr,
+    enable_ech_grease: bool,
+    enable_early_data: bool,
+    permute_extensions: Option<bool>,
+    grease_enabled: Option<bool>,
+    enable_ocsp_stapling: bool,
+    enable_signed_cert_timestamps: bool,
+    record_size_limit: Option<u16>,
+    max_send_fragment: Option<usize>,
+    psk_skip_session_ticket: bool,
+    key_shares_limit: Option<u8>,
+    psk_dhe_ke: bool,
+    renegotiation: bool,
+    delegated_credentials: Option<Cow<'static, str>>,
+    curves_list: Option<Cow<'static, str>>,
+    cipher_list: Option<Cow<'static, str>>,
+    tls13_cipher_suites: Option<Vec<Tls13CipherSuite>>,
+    require_no_downgrade: bool,
+    verify_hostflags: Option<HostFlags>,
+    sigalgs_list: Option<Cow<'static, str>>,
+    certificate_compression_algorithms: Option<Vec<CertificateCompressionAlgorithm>>,
+    extension_permutation: Option<Vec<u16>>,
+    extension_permutation_seed: Option<u64>,
+    aes_hw_override: Option<bool>,
+    prefer_chacha20: Option<bool>,
+    random_aes_hw_override: bool,
+    random_aes_hw_override_seed: Option<u64>,
+}
+
+impl Default for Repr {
+    fn default() -> Self {
+        Repr::try_from(&TlsConfig::default()).expect("default config is serializable")
+    }
+}
+
+impl TryFrom<&TlsConfig> for Repr {
+    type Error = &'static str;
+
+    fn try_from(config: &TlsConfig) -> Result<Self, Self::Error> {
+        Ok(Repr {
+            alpn_protos: config.alpn_protos.as_deref().map(decode_alpn).transpose()?,
+            alps_protos: config.alps_protos.as_deref().map(decode_alps).transpose()?,
+            alps_use_new_codepoint: config.alps_use_new_codepoint,
+            session_ticket: config.session_ticket,
+            min_tls_version: config.min_tls_version,
+            max_tls_version: config.max_tls_version,
+            pre_shared_key: config.pre_shared_key,
+            session_key_scope: match config.session_key_scope {
+                SessionKeyScope::HostPort => ScopeRepr::HostPort,
+                SessionKeyScope::Host => ScopeRepr::Host,
+                SessionKeyScope::Custom(_) => return Err("custom session key scope"),
+            },
+            enable_ech_grease: config.enable_ech_grease,
+            enable_early_data: config.enable_early_data,
+            permute_extensions: config.permute_extensions,
+            grease_enabled: config.grease_enabled,
+            enable_ocsp_stapling: config.enable_ocsp_stapling,
+            enable_signed_cert_timestamps: config.enable_signed_cert_timestamps,
+            record_size_limit: config.record_size_limit,
+            max_send_fragment: config.max_send_fragment,
+            psk_skip_session_ticket: config.psk_skip_session_ticket,
+            key_shares_limit: config.key_shares_limit,
+            psk_dhe_ke: config.psk_dhe_ke,
+            renegotiation: config.renegotiation,
+            delegated_credentials: config.delegated_credentials.clone(),
+            curves_list: config.curves_list.clone(),
+            cipher_list: config.cipher_list.clone(),
+            tls13_cipher_suites: config.tls13_cipher_suites.as_deref().map(<[_]>::to_vec),
+            require_no_downgrade: config.require_no_downgrade,
+            verify_hostflags: config.verify_hostflags,
+            sigalgs_list: config.sigalgs_list.clone(),
+            certificate_compression_algorithms: config
+                .certificate_compression_algorithms
+                .as_deref()
+                .map(<[_]>::to_vec),
+            extension_permutation: config
+                .extension_permutation
+                .as_deref()
+                .map(|exts| exts.iter().map(|&ext| u16::from(ext)).collect()),
+            extension_permutation_seed: config.extension_permutation_seed,
+            aes_hw_override: match config.aes_hw_override {
+                Some(AesHwOverride::On) => Some(true),
+                Some(AesHwOverride::Off) => Some(false),
+                _ => None,
+            },
+            prefer_chacha20: config.prefer_chacha20,
+            random_aes_hw_override: matches!(
+                config.aes_hw_override,
+                Some(AesHwOverride::Random | AesHwOverride::SeededRandom { .. })
+            ),
+            random_aes_hw_override_seed: match config.aes_hw_override {
+                Some(AesHwOverride::SeededRandom { seed }) => Some(seed),
+                _ => None,
+            },
+        })
+    }
+}
+
+impl From<Repr> for TlsConfig {
+    fn from(repr: Repr) -> Self {
+        TlsConfig {
+            alpn_protos: repr.alpn_protos.map(|p| AlpnProtocol::encode_sequence(&p)),
+            alps_protos: repr.alps_protos.map(|p| AlpsProtocol::encode_sequence(&p)),
+            alps_use_new_codepoint: repr.alps_use_new_codepoint,
+            session_ticket: repr.session_ticket,
+            min_tls_version: repr.min_tls_version,
+            max_tls_version: repr.max_tls_version,
+            pre_shared_key: repr.pre_shared_key,
+            session_key_scope: match repr.session_key_scope {
+                ScopeRepr::HostPort => SessionKeyScope::HostPort,
+                ScopeRepr::Host => SessionKeyScope::Host,
+            },
+            enable_ech_grease: repr.enable_ech_grease,
+            enable_early_data: repr.enable_early_data,
+            permute_extensions: repr.permute_extensions,
+            grease_enabled: repr.grease_enabled,
+            enable_ocsp_stapling: repr.enable_ocsp_stapling,
+            enable_signed_cert_timestamps: repr.enable_signed_cert_timestamps,
+            record_size_limit: repr.record_size_limit,
+            max_send_fragment: repr.max_send_fragment,
+            psk_skip_session_ticket: repr.psk_skip_session_ticket,
+            key_shares_limit: repr.key_shares_limit,
+            psk_dhe_ke: repr.psk_dhe_ke,
+            renegotiation: repr.renegotiation,
+            delegated_credentials: repr.delegated_credentials,
+            curves_list: repr.curves_list,
+            cipher_list: repr.cipher_list,
+            tls13_cipher_suites: repr.tls13_cipher_suites.map(Cow::Owned),
+            require_no_downgrade: repr.require_no_downgrade,
+            verify_hostflags: repr.verify_hostflags,
+            sigalgs_list: repr.sigalgs_list,
+            certificate_compression_algorithms: repr
+                .certificate_compression_algorithms
+                .map(Cow::Owned),
+            certificate_compressors: Vec::new(),
+            extension_permutation: repr
+                .extension_permutation
+                .map(|exts| exts.into_iter().map(ExtensionType::from).collect()),
+            extension_permutation_seed: repr.extension_permutation_seed,
+            aes_hw_override: match (repr.random_aes_hw_override_seed, repr.aes_hw_override) {
+                (Some(seed), _) => Some(AesHwOverride::SeededRandom { seed }),
+                (None, _) if repr.random_aes_hw_override => Some(AesHwOverride::Random),
+                (None, Some(true)) => Some(AesHwOverride::On),
+                (None, Some(false)) => Some(AesHwOverride::Off),
+                (None, None) => None,
+            },
+            prefer_chacha20: repr.prefer_chacha20,
+        }
+    }
+}
+
+impl Serialize for TlsConfig {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Repr::try_from(self)
+            .map_err(ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TlsConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Repr::deserialize(deserializer).map(TlsConfig::from)
+    }
+}
+
+/// Splits ALPN wire format, where every protocol is prefixed with its length.
+fn decode_alpn(mut buf: &[u8]) -> Result<Vec<AlpnProtocol>, &'static str> {
+    let mut protos = Vec::new();
+    while let Some((&len, rest)) = buf.split_first() {
+        let (proto, rest) = rest
+            .split_at_checked(len as usize)
+            .ok_or("malformed ALPN protocol list")?;
+        protos.push(AlpnProtocol::custom(proto).map_err(|_| "malformed ALPN protocol list")?);
+        buf = rest;
+    }
+    Ok(protos)
+}
+
+/// Splits ALPS protocols, which are concatenated without a length prefix.
+fn decode_alps(mut buf: &[u8]) -> Result<Vec<AlpsProtocol>, &'static str> {
+    let mut protos = Vec::new();
+    while !buf.is_empty() {
+        let (_, proto) = AlpsProtocol::NAMES
+            .iter()
+            .find(|(name, _)| buf.starts_with(name.as_bytes()))
+            .ok_or("unknown ALPS protocol")?;
+        protos.push(*proto);
+        buf = &buf[proto.as_ref().len()..];
+    }
+    Ok(protos)
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use super::*;
+    use crate::tls::SessionKey;
+
+    fn chrome() -> TlsConfig {
+        TlsConfig::builder()
+            .curves_list("X25519MLKEM768:X25519:P-256:P-384")
+            .cipher_list(concat!(
+                "TLS_AES_128_GCM_SHA256:TLS_AES_256_GCM_SHA384:TLS_CHACHA20_POLY1305_SHA256:",
+                "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256:TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256:",
+                "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384:TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384"
+            ))
+            .sigalgs_list(concat!(
+                "ecdsa_secp256r1_sha256:rsa_pss_rsae_sha256:rsa_pkcs1_sha256:",
+                "ecdsa_secp384r1_sha384:rsa_pss_rsae_sha384:rsa_pkcs1_sha384:",
+                "rsa_pss_rsae_sha512:rsa_pkcs1_sha512"
+            ))
+            .alpn_protos(&[AlpnProtocol::HTTP2, AlpnProtocol::HTTP1])
+            .alps_protos(&[AlpsProtocol::HTTP2])
+            .alps_use_new_codepoint(true)
+            .certificate_compression_algorithms(&[CertificateCompressionAlgorithm::BROTLI])
+            .tls13_cipher_suites(&[
+                Tls13CipherSuite::AES_128_GCM_SHA256,
+                Tls13CipherSuite::AES_256_GCM_SHA384,
+                Tls13CipherSuite::CHACHA20_POLY1305_SHA256,
+            ])
+            .extension_permutation(&[ExtensionType::SERVER_NAME, ExtensionType::KEY_SHARE])
+            .min_tls_version(TlsVersion::TLS_1_2)
+            .max_tls_version(TlsVersion::TLS_1_3)
+            .enable_ech_grease(true)
+            .permute_extensions(true)
+            .grease_enabled(true)
+            .enable_ocsp_stapling(true)
+            .enable_signed_cert_timestamps(true)
+            .pre_shared_key(true)
+            .session_key_scope(SessionKeyScope::Host)
+            .verify_hostflags(HostFlags::NO_PARTIAL_WILDCARDS | HostFlags::NEVER_CHECK_SUBJECT)
+            .aes_hw_override(true)
+            .build()
+    }
+
+    #[test]
+    fn tls_config_round_trips() {
+        let json = serde_json::to_value(chrome()).unwrap();
+        assert_eq!(json["alpn_protos"], serde_json::json!(["h2", "http/1.1"]));
+        assert_eq!(json["alps_protos"], serde_json::json!(["h2"]));
+        assert_eq!(json["min_tls_version"], "1.2");
+        assert_eq!(json["extension_permutation"], serde_json::json!([0, 51]));
+        assert_eq!(json["session_key_scope"], "host");
+        assert_eq!(
+            json["verify_hostflags"],
+            serde_json::json!(["no_partial_wildcards", "never_check_subject"])
+        );
+
+        let config: TlsConfig = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(config.cipher_list, chrome().cipher_list);
+        assert_eq!(config.alpn_protos, chrome().alpn_protos);
+        assert_eq!(config.alps_protos, chrome().alps_protos);
+        assert_eq!(serde_json::to_value(config).unwrap(), json);
+    }
+
+    #[test]
+    fn tls_config_partial_and_unknown_fields() {
+        let config: TlsConfig = serde_json::from_str(r#"{"cipher_list": "ALL"}"#).unwrap();
+        assert_eq!(config.cipher_list.as_deref(), Some("ALL"));
+        assert_eq!(config.alpn_protos, TlsConfig::default().alpn_protos);
+        assert!(config.psk_dhe_ke);
+
+        serde_json::from_str::<TlsConfig>(r#"{"cipher_lsit": "ALL"}"#).unwrap_err();
+        serde_json::from_str::<TlsConfig>(r#"{"min_tls_version": "1.4"}"#).unwrap_err();
+        serde_json::from_str::<TlsConfig>(r#"{"alpn_protos": [""]}"#).unwrap_err();
+
+        let custom: TlsConfig =
+            serde_json::from_str(r#"{"alpn_protos": ["spdy/3", "h2"]}"#).unwrap();
+        assert_eq!(
+            custom.alpn_protos.as_deref(),
+            Some(&b"\x06spdy/3\x02h2"[..])
+        );
+        assert_eq!(
+            serde_json::to_value(custom).unwrap()["alpn_protos"],
+            serde_json::json!(["spdy/3", "h2"])
+        );
+        serde_json::from_str::<TlsConfig>(r#"{"verify_hostflags": ["no_idna"]}"#).unwrap_err();
+
+        let seeded = TlsConfig::builder()
+            .aes_hw_override_mode(AesHwOverride::SeededRandom { seed: 7 })
+            .build();
+        let json = serde_json::to_value(seeded).unwrap();
+        assert_eq!(json["random_aes_hw_override"], true);
+        assert_eq!(json["random_aes_hw_override_seed"], 7);
+        let config: TlsConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            config.aes_hw_override,
+            Some(AesHwOverride::SeededRandom { seed: 7 })
+        );
+
+        let custom = TlsConfig::builder()
+            .session_key_scope(SessionKeyScope::custom(|_| SessionKey::new("all")))
+            .build();
+        serde_json::to_value(custom).unwrap_err();
+    }
+}
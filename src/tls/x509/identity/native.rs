@@ -0,0 +1,558 @@
+//! Client identities from the OS keystore.
+
+use std::sync::Arc;
+
+use boring2::{hash::MessageDigest, x509::X509};
+
+use super::{Identity, IdentityError, TlsSigner};
+use crate::Error;
+
+/// A certificate with a private key, as found in an OS keystore.
+trait StoreEntry {
+    /// The DER encoded certificate.
+    fn certificate(&self) -> &[u8];
+
+    /// Exports the certificate with its private key.
+    ///
+    /// Fails with [`IdentityError::NotExportable`] if the keystore does not give the key out.
+    fn export(&self) -> crate::Result<Identity>;
+
+    /// A signer using the private key in place, inside the keystore.
+    fn signer(&self) -> crate::Result<Arc<dyn TlsSigner>>;
+}
+
+pub(super) fn find(query: &str) -> crate::Result<Identity> {
+    load(select(platform::entries()?, query)?)
+}
+
+/// Exports the identity of an entry, or leaves its key in the keystore to sign handshakes if
+/// it cannot be exported.
+fn load<E: StoreEntry>(entry: E) -> crate::Result<Identity> {
+    match entry.export() {
+        Err(err) if is_not_exportable(&err) => {}
+        res => return res,
+    }
+
+    let cert = X509::from_der(entry.certificate())
+        .and_then(|cert| cert.to_pem())
+        .map_err(Error::tls)?;
+    Identity::from_external_signer(&cert, entry.signer()?)
+}
+
+fn is_not_exportable(err: &Error) -> bool {
+    std::error::Error::source(err)
+        .and_then(|source| source.downcast_ref::<IdentityError>())
+        .is_some_and(|reason| *reason == IdentityError::NotExportable)
+}
+
+/// Picks the entry whose thumbprint equals `query`, falling back to a subject match.
+fn select<E: StoreEntry>(entries: Vec<E>, query: &str) -> crate::Result<E> {
+    let thumbprint = parse_thumbprint(query);
+    let subject = query.to_lowercase();
+
+    let mut by_subject = None;
+    for entry in entries {
+        if thumbprint.is_some() && thumbprint == sha1(entry.certificate()) {
+            return Ok(entry);
+        }
+
+        if by_subject.is_none()
+            && subject_of(entry.certificate()).is_some_and(|s| s.to_lowercase().contains(&subject))
+        {
+            by_subject = Some(entry);
+        }
+    }
+
+    by_subject.ok_or_else(|| Error::builder(IdentityError::NotFound))
+}
+
+/// The subject name of a DER encoded certificate, such as `CN=client, O=Example`.
+fn subject_of(der: &[u8]) -> Option<String> {
+    let cert = X509::from_der(der).ok()?;
+    let entries = cert
+        .subject_name()
+        .entries()
+        .filter_map(|entry| {
+            let name = entry.object().nid().short_name().ok()?;
+            let value = entry.data().as_utf8().ok()?;
+            Some(format!("{name}={value}"))
+        })
+        .collect::<Vec<_>>();
+    Some(entries.join(", "))
+}
+
+fn parse_thumbprint(query: &str) -> Option<Vec<u8>> {
+    let hex = query
+        .chars()
+        .filter(|c| !matches!(c, ' ' | ':'))
+        .collect::<String>();
+
+    if hex.len() != 40 {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn sha1(der: &[u8]) -> Option<Vec<u8>> {
+    X509::from_der(der)
+        .and_then(|cert| cert.digest(MessageDigest::sha1()))
+        .map(|digest| digest.to_vec())
+        .ok()
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::{ffi::c_void, future, io, ptr, sync::Arc};
+
+    use boring2::{bn::BigNum, ecdsa::EcdsaSig, hash::MessageDigest};
+    use schannel::{
+        RawPointer,
+        cert_context::PrivateKey,
+        cert_store::{CertStore, Memory},
+        ncrypt_key::NcryptKey,
+    };
+    use windows_sys::Win32::Security::Cryptography::{
+        BCRYPT_PKCS1_PADDING_INFO, BCRYPT_PSS_PADDING_INFO, BCRYPT_SHA256_ALGORITHM,
+        BCRYPT_SHA384_ALGORITHM, BCRYPT_SHA512_ALGORITHM, NCRYPT_PAD_PKCS1_FLAG,
+        NCRYPT_PAD_PSS_FLAG, NCryptSignHash,
+    };
+
+    use super::StoreEntry;
+    use crate::{
+        Error,
+        error::BoxError,
+        tls::{Identity, IdentityError, SignFuture, SignatureAlgorithm, TlsSigner},
+    };
+
+    pub(super) struct Entry(schannel::cert_context::CertContext);
+
+    pub(super) fn entries() -> crate::Result<Vec<Entry>> {
+        let store = CertStore::open_current_user("My").map_err(Error::tls)?;
+        Ok(store.certs().map(Entry).collect())
+    }
+
+    impl StoreEntry for Entry {
+        fn certificate(&self) -> &[u8] {
+            self.0.to_der()
+        }
+
+        fn export(&self) -> crate::Result<Identity> {
+            // a throwaway password, the archive never leaves this function
+            const PASSWORD: &str = "wreq";
+
+            let mut memory = Memory::new().map_err(Error::tls)?;
+            memory.add_cert(&self.0).map_err(Error::tls)?;
+            let pkcs12 = memory
+                .into_store()
+                .export_pkcs12(PASSWORD)
+                .map_err(|_| Error::builder(IdentityError::NotExportable))?;
+
+            Identity::from_pkcs12_der(&pkcs12, PASSWORD)
+        }
+
+        fn signer(&self) -> crate::Result<Arc<dyn TlsSigner>> {
+            match self.0.private_key().acquire().map_err(Error::tls)? {
+                PrivateKey::NcryptKey(key) => Ok(Arc::new(CngSigner(key))),
+                // legacy CryptoAPI keys cannot sign PSS or ECDSA handshakes
+                PrivateKey::CryptProv(_) => Err(Error::builder(IdentityError::NotExportable)),
+            }
+        }
+    }
+
+    /// Signs handshakes with a CNG key that stays in the keystore.
+    struct CngSigner(NcryptKey);
+
+    enum Padding {
+        Pkcs1,
+        Pss,
+        Ecdsa,
+    }
+
+    impl CngSigner {
+        fn sign_hash(
+            &self,
+            algorithm: SignatureAlgorithm,
+            message: &[u8],
+        ) -> Result<Vec<u8>, BoxError> {
+            let (padding, bits) = match algorithm {
+                SignatureAlgorithm::RSA_PKCS1_SHA256 => (Padding::Pkcs1, 256),
+                SignatureAlgorithm::RSA_PKCS1_SHA384 => (Padding::Pkcs1, 384),
+                SignatureAlgorithm::RSA_PKCS1_SHA512 => (Padding::Pkcs1, 512),
+                SignatureAlgorithm::RSA_PSS_RSAE_SHA256 => (Padding::Pss, 256),
+                SignatureAlgorithm::RSA_PSS_RSAE_SHA384 => (Padding::Pss, 384),
+                SignatureAlgorithm::RSA_PSS_RSAE_SHA512 => (Padding::Pss, 512),
+                SignatureAlgorithm::ECDSA_SECP256R1_SHA256 => (Padding::Ecdsa, 256),
+                SignatureAlgorithm::ECDSA_SECP384R1_SHA384 => (Padding::Ecdsa, 384),
+                SignatureAlgorithm::ECDSA_SECP521R1_SHA512 => (Padding::Ecdsa, 512),
+                _ => return Err(format!("unsupported signature algorithm {algorithm:?}").into()),
+            };
+            let (digest, name) = match bits {
+                256 => (MessageDigest::sha256(), BCRYPT_SHA256_ALGORITHM),
+                384 => (MessageDigest::sha384(), BCRYPT_SHA384_ALGORITHM),
+                _ => (MessageDigest::sha512(), BCRYPT_SHA512_ALGORITHM),
+            };
+            let hash = boring2::hash::hash(digest, message)?;
+
+            let pkcs1 = BCRYPT_PKCS1_PADDING_INFO { pszAlgId: name };
+            let pss = BCRYPT_PSS_PADDING_INFO {
+                pszAlgId: name,
+                cbSalt: hash.len() as u32,
+            };
+            let (info, flags): (*const c_void, u32) = match padding {
+                Padding::Pkcs1 => (ptr::from_ref(&pkcs1).cast(), NCRYPT_PAD_PKCS1_FLAG),
+                Padding::Pss => (ptr::from_ref(&pss).cast(), NCRYPT_PAD_PSS_FLAG),
+                Padding::Ecdsa => (ptr::null(), 0),
+            };
+
+            // SAFETY: the key handle is owned by `self.0`, the padding info and the buffers
+            // outlive the calls, and their lengths are passed along with them.
+            let sign = |out: &mut [u8], len: &mut u32| unsafe {
+                // a null buffer asks for the length of the signature
+                let buf = if out.is_empty() {
+                    ptr::null_mut()
+                } else {
+                    out.as_mut_ptr()
+                };
+                let status = NCryptSignHash(
+                    self.0.as_ptr() as usize,
+                    info,
+                    hash.as_ptr(),
+                    hash.len() as u32,
+                    buf,
+                    out.len() as u32,
+                    len,
+                    flags,
+                );
+                if status == 0 {
+                    Ok(())
+                } else {
+                    Err(io::Error::from_raw_os_error(status))
+                }
+            };
+
+            let mut len = 0;
+            sign(&mut [], &mut len)?;
+            let mut signature = vec![0; len as usize];
+            sign(&mut signature, &mut len)?;
+            signature.truncate(len as usize);
+
+            if let Padding::Ecdsa = padding {
+                // CNG returns `r || s`, TLS expects a DER encoded `ECDSA-Sig-Value`
+                let (r, s) = signature.split_at(signature.len() / 2);
+                let sig = EcdsaSig::from_private_components(
+                    BigNum::from_slice(r)?,
+                    BigNum::from_slice(s)?,
+                )?;
+                signature = sig.to_der()?;
+            }
+            Ok(signature)
+        }
+    }
+
+    impl TlsSigner for CngSigner {
+        fn sign(&self, algorithm: SignatureAlgorithm, message: &[u8]) -> SignFuture {
+            Box::pin(future::ready(self.sign_hash(algorithm, message)))
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::{future, sync::Arc};
+
+    use boring2::{
+        bn::{BigNum, BigNumContext},
+        ec::{EcGroup, EcKey, EcPoint},
+        nid::Nid,
+        pkey::PKey,
+        rsa::Rsa,
+        x509::X509,
+    };
+    use security_framework::{
+        identity::SecIdentity,
+        item::{ItemClass, ItemSearchOptions, Reference, SearchResult},
+        key::{Algorithm, SecKey},
+    };
+
+    use super::StoreEntry;
+    use crate::{
+        Error,
+        tls::{Identity, IdentityError, SignFuture, SignatureAlgorithm, TlsSigner},
+    };
+
+    pub(super) struct Entry {
+        identity: SecIdentity,
+        der: Vec<u8>,
+    }
+
+    pub(super) fn entries() -> crate::Result<Vec<Entry>> {
+        let results = ItemSearchOptions::new()
+            .class(ItemClass::identity())
+            .load_refs(true)
+            .limit(i64::MAX)
+            .search()
+            .map_err(Error::tls)?;
+
+        Ok(results
+            .into_iter()
+            .filter_map(|result| match result {
+                SearchResult::Ref(Reference::Identity(identity)) => {
+                    let der = identity.certificate().ok()?.to_der();
+                    Some(Entry { identity, der })
+                }
+                _ => None,
+            })
+            .collect())
+    }
+
+    impl StoreEntry for Entry {
+        fn certificate(&self) -> &[u8] {
+            &self.der
+        }
+
+        fn export(&self) -> crate::Result<Identity> {
+            let key = self.identity.private_key().map_err(Error::tls)?;
+            let data = key
+                .external_representation()
+                .ok_or_else(|| Error::builder(IdentityError::NotExportable))?;
+            let data = data.bytes();
+
+            // RSA keys are exported as PKCS #1, EC keys as `04 || X || Y || K`
+            let pkey = match data.first() {
+                Some(0x04) => {
+                    let nid = match data.len() {
+                        97 => Nid::X9_62_PRIME256V1,
+                        145 => Nid::SECP384R1,
+                        199 => Nid::SECP521R1,
+                        _ => return Err(Error::builder(IdentityError::UnsupportedKeyType)),
+                    };
+                    let group = EcGroup::from_curve_name(nid).map_err(Error::tls)?;
+                    let point_len = data.len() - (data.len() - 1) / 3;
+                    let mut ctx = BigNumContext::new().map_err(Error::tls)?;
+                    let point = EcPoint::from_bytes(&group, &data[..point_len], &mut ctx)
+                        .map_err(Error::tls)?;
+                    let k = BigNum::from_slice(&data[point_len..]).map_err(Error::tls)?;
+                    let ec =
+                        EcKey::from_private_components(&group, &k, &point).map_err(Error::tls)?;
+                    PKey::from_ec_key(ec).map_err(Error::tls)?
+                }
+                _ => {
+                    let rsa = Rsa::private_key_from_der(data)
+                        .map_err(|_| Error::builder(IdentityError::UnsupportedKeyType))?;
+                    PKey::from_rsa(rsa).map_err(Error::tls)?
+                }
+            };
+
+            let cert = X509::from_der(&self.der).map_err(Error::tls)?;
+            Identity::new(pkey, cert, Vec::new())
+        }
+
+        fn signer(&self) -> crate::Result<Arc<dyn TlsSigner>> {
+            let key = self.identity.private_key().map_err(Error::tls)?;
+            Ok(Arc::new(KeychainSigner(key)))
+        }
+    }
+
+    /// Signs handshakes with a key that stays in the keychain.
+    struct KeychainSigner(SecKey);
+
+    impl TlsSigner for KeychainSigner {
+        fn sign(&self, algorithm: SignatureAlgorithm, message: &[u8]) -> SignFuture {
+            let algorithm = match algorithm {
+                SignatureAlgorithm::RSA_PKCS1_SHA256 => {
+                    Algorithm::RSASignatureMessagePKCS1v15SHA256
+                }
+                SignatureAlgorithm::RSA_PKCS1_SHA384 => {
+                    Algorithm::RSASignatureMessagePKCS1v15SHA384
+                }
+                SignatureAlgorithm::RSA_PKCS1_SHA512 => {
+                    Algorithm::RSASignatureMessagePKCS1v15SHA512
+                }
+                SignatureAlgorithm::RSA_PSS_RSAE_SHA256 => Algorithm::RSASignatureMessagePSSSHA256,
+                SignatureAlgorithm::RSA_PSS_RSAE_SHA384 => Algorithm::RSASignatureMessagePSSSHA384,
+                SignatureAlgorithm::RSA_PSS_RSAE_SHA512 => Algorithm::RSASignatureMessagePSSSHA512,
+                SignatureAlgorithm::ECDSA_SECP256R1_SHA256 => {
+                    Algorithm::ECDSASignatureMessageX962SHA256
+                }
+                SignatureAlgorithm::ECDSA_SECP384R1_SHA384 => {
+                    Algorithm::ECDSASignatureMessageX962SHA384
+                }
+                SignatureAlgorithm::ECDSA_SECP521R1_SHA512 => {
+                    Algorithm::ECDSASignatureMessageX962SHA512
+                }
+                _ => {
+                    let err = format!("unsupported signature algorithm {algorithm:?}");
+                    return Box::pin(future::ready(Err(err.into())));
+                }
+            };
+
+            // the keychain returns ECDSA signatures DER encoded, as TLS expects them
+            let signature = self
+                .0
+                .create_signature(algorithm, message)
+                .map_err(|err| err.to_string().into());
+            Box::pin(future::ready(signature))
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+mod platform {
+    use std::sync::Arc;
+
+    use super::StoreEntry;
+    use crate::{
+        Error,
+        tls::{Identity, IdentityError, TlsSigner},
+    };
+
+    pub(super) enum Entry {}
+
+    pub(super) fn entries() -> crate::Result<Vec<Entry>> {
+        Err(Error::builder(IdentityError::NotFound))
+    }
+
+    impl StoreEntry for Entry {
+        fn certificate(&self) -> &[u8] {
+            match *self {}
+        }
+
+        fn export(&self) -> crate::Result<Identity> {
+            match *self {}
+        }
+
+        fn signer(&self) -> crate::Result<Arc<dyn TlsSigner>> {
+            match *self {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+
+    use super::{super::PrivateKey, *};
+    use crate::tls::{SignFuture, SignatureAlgorithm};
+
+    const CERT: &[u8] = include_bytes!("../../../../tests/support/identity/cert.pem");
+    const KEY: &[u8] = include_bytes!("../../../../tests/support/identity/key.pem");
+    const OTHER_CERT: &[u8] = include_bytes!("../../../../tests/support/identity/other.cert.pem");
+
+    struct MockEntry {
+        der: Vec<u8>,
+        name: &'static str,
+        exportable: bool,
+    }
+
+    impl StoreEntry for MockEntry {
+        fn certificate(&self) -> &[u8] {
+            &self.der
+        }
+
+        fn export(&self) -> crate::Result<Identity> {
+            if !self.exportable {
+                return Err(Error::builder(IdentityError::NotExportable));
+            }
+            Identity::from_pem_parts(CERT, KEY)
+        }
+
+        fn signer(&self) -> crate::Result<Arc<dyn TlsSigner>> {
+            Ok(Arc::new(MockSigner))
+        }
+    }
+
+    struct MockSigner;
+
+    impl TlsSigner for MockSigner {
+        fn sign(&self, _: SignatureAlgorithm, _: &[u8]) -> SignFuture {
+            Box::pin(async { Err("signer unavailable".into()) })
+        }
+    }
+
+    fn der(pem: &[u8]) -> Vec<u8> {
+        X509::from_pem(pem).unwrap().to_der().unwrap()
+    }
+
+    fn mock_entries() -> (Vec<MockEntry>, String) {
+        let thumbprint = X509::from_pem(CERT)
+            .unwrap()
+            .digest(MessageDigest::sha1())
+            .unwrap()
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect::<Vec<_>>()
+            .join(":");
+
+        let entries = vec![
+            MockEntry {
+                der: b"not a certificate".to_vec(),
+                name: "garbage",
+                exportable: true,
+            },
+            MockEntry {
+                der: der(OTHER_CERT),
+                name: "other",
+                exportable: true,
+            },
+            MockEntry {
+                der: der(CERT),
+                name: "client",
+                exportable: true,
+            },
+        ];
+        (entries, thumbprint)
+    }
+
+    #[test]
+    fn selects_by_thumbprint_or_subject() {
+        let (entries, thumbprint) = mock_entries();
+        let entry = select(entries, &thumbprint).unwrap();
+        assert_eq!(entry.name, "client");
+        entry.export().unwrap();
+
+        let (entries, thumbprint) = mock_entries();
+        let entry = select(entries, &thumbprint.replace(':', "").to_lowercase()).unwrap();
+        assert_eq!(entry.name, "client");
+
+        let (entries, _) = mock_entries();
+        assert_eq!(select(entries, "OTHER").unwrap().name, "other");
+
+        let (entries, _) = mock_entries();
+        assert_eq!(select(entries, "cn=wreq client").unwrap().name, "client");
+
+        let (entries, _) = mock_entries();
+        let err = select(entries, "missing").unwrap_err();
+        let reason = err.source().unwrap().downcast_ref::<IdentityError>();
+        assert_eq!(reason, Some(&IdentityError::NotFound));
+    }
+
+    #[test]
+    fn subject_is_read_from_the_certificate() {
+        assert_eq!(subject_of(&der(CERT)).as_deref(), Some("CN=wreq client"));
+        assert_eq!(subject_of(b"not a certificate"), None);
+    }
+
+    #[test]
+    fn non_exportable_keys_sign_in_the_keystore() {
+        let identity = load(MockEntry {
+            der: der(CERT),
+            name: "client",
+            exportable: true,
+        })
+        .unwrap();
+        assert!(matches!(identity.key, PrivateKey::Local(_)));
+
+        let identity = load(MockEntry {
+            der: der(CERT),
+            name: "client",
+            exportable: false,
+        })
+        .unwrap();
+        assert!(matches!(identity.key, PrivateKey::External(_)));
+        assert_eq!(identity.cert.to_der().unwrap(), der(CERT));
+    }
+}
@@ -4,7 +4,7 @@ mod store;
 use boring2::x509::X509;
 
 pub use self::{
-    identity::Identity,
+    identity::{Identity, IdentityError, SignFuture, TlsSigner},
     store::{CertStore, CertStoreBuilder},
 };
 use crate::Error;
@@ -1,9 +1,15 @@
 mod parser;
 
-use std::{fmt::Debug, path::Path, sync::Arc};
+use std::{
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    path::Path,
+    sync::Arc,
+};
 
 use boring2::{
-    ssl::SslConnectorBuilder,
+    error::ErrorStack,
+    ssl::{SslConnectorBuilder, SslRef},
     x509::store::{X509Store, X509StoreBuilder},
 };
 use parser::{
@@ -161,13 +167,9 @@ pub struct CertStore(Arc<X509Store>);
 impl Default for CertStore {
     fn default() -> Self {
         #[cfg(feature = "webpki-roots")]
-        pub(super) static LOAD_CERTS: std::sync::LazyLock<CertStore> =
-            std::sync::LazyLock::new(|| {
-                CertStore::builder()
-                    .add_der_certs(webpki_root_certs::TLS_SERVER_ROOT_CERTS)
-                    .build()
-                    .expect("failed to load default cert store")
-            });
+        {
+            CertStore::mozilla_bundle()
+        }
 
         #[cfg(not(feature = "webpki-roots"))]
         {
@@ -176,9 +178,6 @@ impl Default for CertStore {
                 .build()
                 .expect("failed to load default cert store")
         }
-
-        #[cfg(feature = "webpki-roots")]
-        LOAD_CERTS.clone()
     }
 }
 
@@ -198,6 +197,22 @@ impl CertStore {
         }
     }
 
+    /// Returns the Mozilla root certificates bundled through the `webpki-roots` feature.
+    ///
+    /// The certificates are parsed on first use only; later calls share the same store.
+    #[cfg(feature = "webpki-roots")]
+    pub fn mozilla_bundle() -> CertStore {
+        static BUNDLE: std::sync::OnceLock<CertStore> = std::sync::OnceLock::new();
+        BUNDLE
+            .get_or_init(|| {
+                CertStore::builder()
+                    .add_der_certs(webpki_root_certs::TLS_SERVER_ROOT_CERTS)
+                    .build()
+                    .expect("failed to load bundled cert store")
+            })
+            .clone()
+    }
+
     /// Creates a new `CertStore` from a collection of DER-encoded certificates.
     #[inline]
     pub fn from_der_certs<'c, C>(certs: C) -> crate::Result<CertStore>
@@ -247,4 +262,31 @@ impl CertStore {
     pub(crate) fn add_to_tls(&self, tls: &mut SslConnectorBuilder) {
         tls.set_cert_store_ref(&self.0);
     }
+
+    #[inline]
+    pub(crate) fn add_to_ssl(&self, ssl: &mut SslRef) -> Result<(), ErrorStack> {
+        ssl.set_verify_cert_store_ref(&self.0)
+    }
+
+    /// Returns whether the store holds no certificates.
+    ///
+    /// Stores backed by a hashed directory load their certificates lazily, and are
+    /// reported empty until then.
+    #[cfg(feature = "webpki-roots")]
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.objects().is_empty()
+    }
+
+    /// Returns whether both handles refer to the same store.
+    #[inline]
+    pub(crate) fn ptr_eq(&self, other: &CertStore) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Hashes the identity of the store, consistent with [`CertStore::ptr_eq`].
+    #[inline]
+    pub(crate) fn hash_ptr<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).hash(state);
+    }
 }
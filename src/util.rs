@@ -1,6 +1,9 @@
-use std::fmt;
+use std::{fmt, sync::Arc};
 
-use crate::header::{Entry, HeaderMap, HeaderValue, OccupiedEntry};
+use crate::{
+    header::{Entry, HeaderMap, HeaderValue, OccupiedEntry},
+    sync::Mutex,
+};
 
 pub fn basic_auth<U, P>(username: U, password: Option<P>) -> HeaderValue
 where
@@ -61,6 +64,35 @@ pub(crate) fn fast_random() -> u64 {
     })
 }
 
+/// A source of random numbers: either [`fast_random`], or a sequence seeded for reproducible
+/// results, which is shared by clones.
+#[derive(Clone)]
+pub(crate) enum RandomSource {
+    Fast,
+    Seeded(Arc<Mutex<u64>>),
+}
+
+impl RandomSource {
+    pub(crate) fn seeded(seed: u64) -> RandomSource {
+        RandomSource::Seeded(Arc::new(Mutex::new(seed)))
+    }
+
+    pub(crate) fn next(&self) -> u64 {
+        match self {
+            RandomSource::Fast => fast_random(),
+            // splitmix64, which is well distributed for any seed
+            RandomSource::Seeded(state) => {
+                let mut state = state.lock();
+                *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+                let mut z = *state;
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                z ^ (z >> 31)
+            }
+        }
+    }
+}
+
 pub(crate) fn replace_headers(dst: &mut HeaderMap, src: HeaderMap) {
     // IntoIter of HeaderMap yields (Option<HeaderName>, HeaderValue).
     // The first time a name is yielded, it will be Some(name), and if
@@ -0,0 +1,445 @@
+//! Recording and replay of HTTP interactions
+//!
+//! A [`Vcr`] installed with [`ClientBuilder::vcr`] records the requests a client sends, and
+//! the responses it receives, into a cassette file. The cassette can then be replayed, so
+//! that tests run deterministically and without network access.
+//!
+//! Cassettes are JSON files. Bodies are stored as text when they are valid UTF-8, and
+//! base64-encoded otherwise. Response bodies are stored as received, before decompression.
+//!
+//! A request matches a recorded interaction when the method and the URL are equal, the
+//! headers selected with [`Vcr::match_header`] have the same values, and, unless disabled
+//! with [`Vcr::match_body`], the bodies have the same SHA-256 digest. Volatile headers such
+//! as `Date` or request ids are ignored simply by not selecting them.
+//!
+//! ```rust,no_run
+//! # async fn run() -> wreq::Result<()> {
+//! use wreq::vcr::Vcr;
+//!
+//! let client = wreq::Client::builder()
+//!     .vcr(Vcr::auto("tests/cassettes/example.json").match_header(wreq::header::ACCEPT))
+//!     .build()?;
+//!
+//! // Sent over the network the first time, replayed from the cassette afterwards.
+//! let body = client.get("https://example.com/").send().await?.text().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ClientBuilder::vcr`]: crate::ClientBuilder::vcr
+
+use std::{
+    error::Error as StdError,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use bytes::Bytes;
+use http::{
+    HeaderMap, HeaderName, HeaderValue, Method, Request, Response, StatusCode,
+    header::{AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION},
+    response::Parts,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{Body, core::body::Incoming, error::Error, sync::Mutex};
+
+/// The value persisted in place of redacted headers.
+const REDACTED: &str = "[REDACTED]";
+
+/// The default cap on the size of persisted response bodies.
+const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// How a [`Vcr`] uses its cassette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VcrMode {
+    /// Sends every request, and records the interactions into a new cassette.
+    Record,
+    /// Answers requests from an existing cassette, without sending them.
+    Replay,
+    /// Answers requests from the cassette when they match an interaction, and sends and
+    /// records them otherwise. The cassette is created if it does not exist.
+    Auto,
+}
+
+/// Records and replays the interactions of a client, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Vcr {
+    path: PathBuf,
+    mode: VcrMode,
+    match_headers: Vec<HeaderName>,
+    match_body: bool,
+    redact_headers: Vec<HeaderName>,
+    max_body_size: usize,
+    passthrough_unmatched: bool,
+}
+
+/// The error returned in [`VcrMode::Replay`] for a request that matches no recorded
+/// interaction.
+#[derive(Debug)]
+pub struct Unmatched {
+    method: Method,
+    url: String,
+}
+
+// ===== impl Vcr =====
+
+impl Vcr {
+    /// Creates a `Vcr` using the cassette at `path` in the given mode.
+    ///
+    /// The `Authorization`, `Proxy-Authorization` and `Cookie` request headers are redacted
+    /// by default, and response bodies are persisted up to 1 MiB.
+    pub fn new<P: AsRef<Path>>(path: P, mode: VcrMode) -> Vcr {
+        Vcr {
+            path: path.as_ref().to_path_buf(),
+            mode,
+            match_headers: Vec::new(),
+            match_body: true,
+            redact_headers: vec![AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE],
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            passthrough_unmatched: false,
+        }
+    }
+
+    /// Creates a `Vcr` in [`VcrMode::Record`].
+    pub fn record<P: AsRef<Path>>(path: P) -> Vcr {
+        Vcr::new(path, VcrMode::Record)
+    }
+
+    /// Creates a `Vcr` in [`VcrMode::Replay`].
+    pub fn replay<P: AsRef<Path>>(path: P) -> Vcr {
+        Vcr::new(path, VcrMode::Replay)
+    }
+
+    /// Creates a `Vcr` in [`VcrMode::Auto`].
+    pub fn auto<P: AsRef<Path>>(path: P) -> Vcr {
+        Vcr::new(path, VcrMode::Auto)
+    }
+
+    /// Requires the values of the request header `name` to be equal for a request to match
+    /// an interaction.
+    pub fn match_header(mut self, name: HeaderName) -> Vcr {
+        self.match_headers.push(name);
+        self
+    }
+
+    /// Sets whether the request bodies must be equal for a request to match an interaction.
+    ///
+    /// Streaming bodies are never hashed, and only match interactions recorded from
+    /// streaming bodies.
+    ///
+    /// Defaults to `true`.
+    pub fn match_body(mut self, enabled: bool) -> Vcr {
+        self.match_body = enabled;
+        self
+    }
+
+    /// Replaces the values of the header `name` in persisted requests and responses.
+    ///
+    /// Redacted request headers can still be matched on: incoming requests are redacted
+    /// the same way before they are compared.
+    pub fn redact_header(mut self, name: HeaderName) -> Vcr {
+        self.redact_headers.push(name);
+        self
+    }
+
+    /// Sets the size above which recorded response bodies are not persisted.
+    ///
+    /// The response is still returned in full, but the interaction is left out of the
+    /// cassette.
+    ///
+    /// Defaults to 1 MiB.
+    pub fn max_body_size(mut self, max: usize) -> Vcr {
+        self.max_body_size = max;
+        self
+    }
+
+    /// Sets whether requests matching no interaction are sent over the network in
+    /// [`VcrMode::Replay`], instead of failing with [`Unmatched`].
+    ///
+    /// Such requests are not recorded.
+    ///
+    /// Defaults to `false`.
+    pub fn passthrough_unmatched(mut self, enabled: bool) -> Vcr {
+        self.passthrough_unmatched = enabled;
+        self
+    }
+
+    /// Returns the path of the cassette.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the mode of this `Vcr`.
+    pub fn mode(&self) -> VcrMode {
+        self.mode
+    }
+
+    fn redact(&self, headers: &HeaderMap) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if self.redact_headers.contains(name) {
+                    REDACTED.to_owned()
+                } else {
+                    String::from_utf8_lossy(value.as_bytes()).into_owned()
+                };
+                (name.as_str().to_owned(), value)
+            })
+            .collect()
+    }
+}
+
+// ===== impl Unmatched =====
+
+impl fmt::Display for Unmatched {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "no recorded interaction matches {} {}",
+            self.method, self.url
+        )
+    }
+}
+
+impl StdError for Unmatched {}
+
+// ===== cassette =====
+
+#[derive(Default, Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body_sha256: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: RecordedBody,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum RecordedBody {
+    Text(String),
+    Base64(String),
+}
+
+/// The interactions of a [`Vcr`], shared by the clones of a client.
+pub(crate) struct Cassette {
+    vcr: Vcr,
+    state: Mutex<CassetteState>,
+}
+
+struct CassetteState {
+    interactions: Vec<Interaction>,
+    played: Vec<bool>,
+}
+
+/// What to do with a request, see [`Cassette::lookup`].
+pub(crate) enum Lookup {
+    Replay(Response<Incoming>),
+    Record(RequestKey),
+    Passthrough,
+    Unmatched(Unmatched),
+}
+
+/// The recorded form of a request, used to match and to persist it.
+pub(crate) struct RequestKey(RecordedRequest);
+
+impl Cassette {
+    /// Opens the cassette of `vcr`, reading the recorded interactions unless recording anew.
+    pub(crate) fn open(vcr: Vcr) -> crate::Result<Cassette> {
+        let file = match vcr.mode {
+            VcrMode::Record => CassetteFile::default(),
+            VcrMode::Replay => read_cassette(&vcr.path)?,
+            VcrMode::Auto => match read_cassette(&vcr.path) {
+                Err(err) if is_not_found(&err) => CassetteFile::default(),
+                result => result?,
+            },
+        };
+
+        let played = vec![false; file.interactions.len()];
+        Ok(Cassette {
+            vcr,
+            state: Mutex::new(CassetteState {
+                interactions: file.interactions,
+                played,
+            }),
+        })
+    }
+
+    /// Decides whether `req` is answered from the cassette, or sent and possibly recorded.
+    pub(crate) fn lookup(&self, req: &Request<Body>) -> Lookup {
+        let key = self.key(req);
+
+        if self.vcr.mode != VcrMode::Record {
+            if let Some(res) = self.replay(&key) {
+                return Lookup::Replay(res);
+            }
+        }
+
+        match self.vcr.mode {
+            VcrMode::Replay if self.vcr.passthrough_unmatched => Lookup::Passthrough,
+            VcrMode::Replay => Lookup::Unmatched(Unmatched {
+                method: req.method().clone(),
+                url: req.uri().to_string(),
+            }),
+            VcrMode::Record | VcrMode::Auto => Lookup::Record(key),
+        }
+    }
+
+    /// Persists the interaction of `key` with the response made of `parts` and `body`.
+    pub(crate) fn record(&self, key: RequestKey, parts: &Parts, body: &Bytes) -> io::Result<()> {
+        if body.len() > self.vcr.max_body_size {
+            debug!(
+                "vcr: response body of {} {} exceeds {} bytes, not recorded",
+                key.0.method, key.0.url, self.vcr.max_body_size
+            );
+            return Ok(());
+        }
+
+        let body = match std::str::from_utf8(body) {
+            Ok(text) => RecordedBody::Text(text.to_owned()),
+            Err(_) => RecordedBody::Base64(STANDARD.encode(body)),
+        };
+        let interaction = Interaction {
+            request: key.0,
+            response: RecordedResponse {
+                status: parts.status.as_u16(),
+                headers: self.vcr.redact(&parts.headers),
+                body,
+            },
+        };
+
+        let mut state = self.state.lock();
+        state.interactions.push(interaction);
+        state.played.push(true);
+
+        let file = CassetteFile {
+            interactions: state.interactions.clone(),
+        };
+        let json = serde_json::to_vec_pretty(&file).map_err(io::Error::other)?;
+        if let Some(parent) = self.vcr.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.vcr.path, json)
+    }
+
+    fn key(&self, req: &Request<Body>) -> RequestKey {
+        let body_sha256 = req.body().as_bytes().map(|bytes| {
+            #[cfg(feature = "tls")]
+            let digest = boring2::sha::sha256(bytes);
+            #[cfg(not(feature = "tls"))]
+            let digest = <sha2::Sha256 as sha2::Digest>::digest(bytes);
+            digest.iter().map(|byte| format!("{byte:02x}")).collect()
+        });
+
+        RequestKey(RecordedRequest {
+            method: req.method().as_str().to_owned(),
+            url: req.uri().to_string(),
+            headers: self.vcr.redact(req.headers()),
+            body_sha256,
+        })
+    }
+
+    /// Returns the response of the first unplayed interaction matching `key`, or of the
+    /// last matching one once all have been played.
+    fn replay(&self, key: &RequestKey) -> Option<Response<Incoming>> {
+        let mut state = self.state.lock();
+        let CassetteState {
+            interactions,
+            played,
+        } = &mut *state;
+
+        let matching = interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, interaction)| self.matches(&interaction.request, &key.0))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        let index = matching
+            .iter()
+            .copied()
+            .find(|&index| !played[index])
+            .or_else(|| matching.last().copied())?;
+
+        played[index] = true;
+        Some(to_response(&interactions[index].response))
+    }
+
+    fn matches(&self, recorded: &RecordedRequest, req: &RecordedRequest) -> bool {
+        let values = |headers: &[(String, String)], name: &HeaderName| {
+            headers
+                .iter()
+                .filter(|(n, _)| n.eq_ignore_ascii_case(name.as_str()))
+                .map(|(_, v)| v.clone())
+                .collect::<Vec<_>>()
+        };
+
+        recorded.method == req.method
+            && recorded.url == req.url
+            && (!self.vcr.match_body || recorded.body_sha256 == req.body_sha256)
+            && self
+                .vcr
+                .match_headers
+                .iter()
+                .all(|name| values(&recorded.headers, name) == values(&req.headers, name))
+    }
+}
+
+fn read_cassette(path: &Path) -> crate::Result<CassetteFile> {
+    let data = fs::read(path).map_err(Error::builder)?;
+    let file: CassetteFile = serde_json::from_slice(&data).map_err(Error::builder)?;
+    for interaction in &file.interactions {
+        if let RecordedBody::Base64(ref data) = interaction.response.body {
+            STANDARD.decode(data).map_err(Error::builder)?;
+        }
+    }
+    Ok(file)
+}
+
+fn is_not_found(err: &Error) -> bool {
+    StdError::source(err)
+        .and_then(|source| source.downcast_ref::<io::Error>())
+        .is_some_and(|err| err.kind() == io::ErrorKind::NotFound)
+}
+
+fn to_response(recorded: &RecordedResponse) -> Response<Incoming> {
+    let body = match recorded.body {
+        RecordedBody::Text(ref text) => Bytes::from(text.clone()),
+        // validated when the cassette was read
+        RecordedBody::Base64(ref data) => {
+            STANDARD.decode(data).map(Bytes::from).unwrap_or_default()
+        }
+    };
+
+    let mut res = Response::new(Incoming::full(body));
+    *res.status_mut() = StatusCode::from_u16(recorded.status).unwrap_or(StatusCode::OK);
+    let headers = res.headers_mut();
+    for (name, value) in &recorded.headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            headers.append(name, value);
+        }
+    }
+    res
+}
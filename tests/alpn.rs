@@ -0,0 +1,112 @@
+use std::net::SocketAddr;
+
+use boring2::ssl::{SslAcceptor, SslFiletype, SslMethod};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use wreq::{
+    Client, EmulationProvider,
+    tls::{AlpnProtocol, TlsConfig},
+};
+
+/// Selects the first protocol the client offers, and serves it as the body of a response.
+async fn server() -> SocketAddr {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/support/identity");
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor
+        .set_certificate_chain_file(format!("{dir}/cert.pem"))
+        .unwrap();
+    acceptor
+        .set_private_key_file(format!("{dir}/key.pem"), SslFiletype::PEM)
+        .unwrap();
+    acceptor.set_alpn_select_callback(|_, client| Ok(&client[1..][..usize::from(client[0])]));
+    let acceptor = acceptor.build();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (io, _) = listener.accept().await.unwrap();
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                if let Ok(mut stream) = tokio_boring2::accept(&acceptor, io).await {
+                    let selected = stream.ssl().selected_alpn_protocol().unwrap_or_default();
+                    let res = [
+                        format!(
+                            "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n",
+                            selected.len()
+                        )
+                        .as_bytes(),
+                        selected,
+                    ]
+                    .concat();
+
+                    let mut buf = [0; 1024];
+                    let _ = stream.read(&mut buf).await;
+                    let _ = stream.write_all(&res).await;
+                }
+            });
+        }
+    });
+    addr
+}
+
+async fn get(addr: SocketAddr, alpn: &[AlpnProtocol]) -> wreq::Result<String> {
+    let emulation = EmulationProvider::builder()
+        .tls_config(TlsConfig::builder().alpn_protos(alpn).build())
+        .build()
+        .unwrap();
+
+    let client = Client::builder()
+        .emulation(emulation)
+        .cert_verification(false)
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    client
+        .get(format!("https://127.0.0.1:{}/", addr.port()))
+        .send()
+        .await?
+        .text()
+        .await
+}
+
+#[tokio::test]
+async fn custom_alpn_protocols_are_advertised_in_order() {
+    let addr = server().await;
+
+    let selected = get(
+        addr,
+        &[
+            AlpnProtocol::HTTP1,
+            AlpnProtocol::HTTP2,
+            AlpnProtocol::custom("h3").unwrap(),
+        ],
+    )
+    .await
+    .unwrap();
+    assert_eq!(selected, "http/1.1");
+}
+
+#[tokio::test]
+async fn unsupported_negotiated_protocol_fails_to_connect() {
+    let addr = server().await;
+
+    let err = get(
+        addr,
+        &[AlpnProtocol::custom("h3").unwrap(), AlpnProtocol::HTTP2],
+    )
+    .await
+    .unwrap_err();
+    assert!(err.is_connect(), "{err:?}");
+
+    let mut source = std::error::Error::source(&err);
+    let mut found = false;
+    while let Some(err) = source {
+        found |= err.to_string() == "unsupported negotiated protocol `h3`";
+        source = err.source();
+    }
+    assert!(found, "{err:?}");
+}
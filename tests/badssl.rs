@@ -29,6 +29,36 @@ async fn test_badssl_modern() {
     assert!(!text.is_empty());
 }
 
+#[cfg(feature = "webpki-roots")]
+#[tokio::test]
+async fn test_badssl_bundled_roots_fallback() {
+    let empty = || wreq::tls::CertStore::builder().build().unwrap();
+
+    let err = wreq::Client::builder()
+        .cert_store(empty())
+        .no_proxy()
+        .build()
+        .unwrap()
+        .get("https://mozilla-modern.badssl.com/")
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.cert_verify_details().is_some());
+
+    let res = wreq::Client::builder()
+        .cert_store(empty())
+        .use_bundled_roots(true)
+        .no_proxy()
+        .connect_timeout(Duration::from_secs(360))
+        .build()
+        .unwrap()
+        .get("https://mozilla-modern.badssl.com/")
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+}
+
 #[tokio::test]
 async fn test_badssl_self_signed() {
     let text = wreq::Client::builder()
@@ -70,7 +100,7 @@ async fn test_3des_support() -> wreq::Result<()> {
                 .curves_list(CURVES_LIST)
                 .build(),
         )
-        .build();
+        .build()?;
 
     let client = Client::builder()
         .emulation(emulation)
@@ -106,7 +136,7 @@ async fn test_firefox_7x_100_cipher() -> wreq::Result<()> {
                 .curves_list(CURVES_LIST)
                 .build(),
         )
-        .build();
+        .build()?;
     let client = Client::builder()
         .emulation(emulation)
         .cert_verification(false)
@@ -137,7 +167,7 @@ async fn test_alps_new_endpoint() -> wreq::Result<()> {
                 .alps_use_new_codepoint(true)
                 .build(),
         )
-        .build();
+        .build()?;
 
     let client = wreq::Client::builder()
         .emulation(emulation)
@@ -183,7 +213,7 @@ async fn test_aes_hw_override() -> wreq::Result<()> {
                 .prefer_chacha20(true)
                 .build(),
         )
-        .build();
+        .build()?;
 
     let client = wreq::Client::builder()
         .emulation(emulation)
@@ -233,3 +263,29 @@ async fn test_ssl_pinning() {
     let res = client.get("https://www.google.com").send().await;
     assert!(res.is_err());
 }
+
+#[tokio::test]
+async fn test_tls_session_stats() -> wreq::Result<()> {
+    let emulation = EmulationProvider::builder()
+        .tls_config(TlsConfig::builder().pre_shared_key(true).build())
+        .build()?;
+
+    let client = Client::builder()
+        .emulation(emulation)
+        .connect_timeout(Duration::from_secs(360))
+        .build()?;
+    assert_eq!(client.tls_session_stats(), Default::default());
+
+    let resp = client.get("https://www.google.com").send().await?;
+    let _ = resp.bytes().await?;
+
+    let stats = client.tls_session_stats();
+    assert_eq!(stats.misses(), 1);
+    assert_eq!(stats.hits(), 0);
+
+    client.clear_tls_sessions();
+    let cleared = client.tls_session_stats();
+    assert_eq!(cleared.entries(), 0);
+    assert_eq!(cleared.misses(), 1);
+    Ok(())
+}
@@ -0,0 +1,310 @@
+mod support;
+use std::net::SocketAddr;
+
+use boring2::ssl::{SslAcceptor, SslFiletype, SslMethod, SslVersion};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use wreq::{
+    Client, EmulationProvider,
+    tls::{Tls13CipherSuite, TlsConfig, TlsInfo, TlsVersion},
+};
+
+const ECDHE_ECDSA_AES128_GCM_SHA256: u16 = 0xc02b;
+const ECDHE_RSA_AES128_GCM_SHA256: u16 = 0xc02f;
+
+const STATUS_REQUEST: u16 = 5;
+const SIGNATURE_ALGORITHMS: u16 = 13;
+const SIGNED_CERTIFICATE_TIMESTAMP: u16 = 18;
+
+struct ClientHello {
+    cipher_suites: Vec<u16>,
+    extensions: Vec<(u16, Vec<u8>)>,
+}
+
+fn u16_at(buf: &[u8], pos: usize) -> u16 {
+    u16::from_be_bytes([buf[pos], buf[pos + 1]])
+}
+
+/// Parses the cipher suites and extensions of a ClientHello record.
+fn parse_client_hello(record: &[u8]) -> ClientHello {
+    // Skip the record and handshake headers, the version and the random.
+    let mut pos = 5 + 4 + 2 + 32;
+    pos += 1 + usize::from(record[pos]);
+
+    let len = usize::from(u16_at(record, pos));
+    let cipher_suites = record[pos + 2..pos + 2 + len]
+        .chunks(2)
+        .map(|suite| u16_at(suite, 0))
+        .collect();
+    pos += 2 + len;
+    pos += 1 + usize::from(record[pos]);
+
+    let end = pos + 2 + usize::from(u16_at(record, pos));
+    pos += 2;
+    let mut extensions = Vec::new();
+    while pos < end {
+        let len = usize::from(u16_at(record, pos + 2));
+        extensions.push((u16_at(record, pos), record[pos + 4..pos + 4 + len].to_vec()));
+        pos += 4 + len;
+    }
+
+    ClientHello {
+        cipher_suites,
+        extensions,
+    }
+}
+
+/// Captures the ClientHello sent by the client, without completing the handshake.
+async fn client_hello(client: Client) -> ClientHello {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (mut io, _) = listener.accept().await.unwrap();
+        let mut record = vec![0; 5];
+        io.read_exact(&mut record).await.unwrap();
+        let len = usize::from(u16_at(&record, 3));
+        record.resize(5 + len, 0);
+        io.read_exact(&mut record[5..]).await.unwrap();
+        record
+    });
+
+    let _ = client
+        .get(format!("https://127.0.0.1:{}/", addr.port()))
+        .send()
+        .await;
+    parse_client_hello(&server.await.unwrap())
+}
+
+/// Serves empty responses over TLS versions up to `max_version`, closing connections after a
+/// single response.
+async fn tls_server(max_version: SslVersion) -> SocketAddr {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/support/identity");
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor
+        .set_certificate_chain_file(format!("{dir}/cert.pem"))
+        .unwrap();
+    acceptor
+        .set_private_key_file(format!("{dir}/key.pem"), SslFiletype::PEM)
+        .unwrap();
+    acceptor.set_max_proto_version(Some(max_version)).unwrap();
+    let acceptor = acceptor.build();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (io, _) = listener.accept().await.unwrap();
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                if let Ok(mut stream) = tokio_boring2::accept(&acceptor, io).await {
+                    let mut buf = [0; 1024];
+                    if matches!(stream.read(&mut buf).await, Ok(n) if n > 0) {
+                        let res =
+                            "HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: 0\r\n\r\n";
+                        let _ = stream.write_all(res.as_bytes()).await;
+                    }
+                    let _ = stream.shutdown().await;
+                }
+            });
+        }
+    });
+    addr
+}
+
+fn emulation() -> EmulationProvider {
+    EmulationProvider::builder()
+        .tls_config(
+            TlsConfig::builder()
+                .grease_enabled(false)
+                .enable_ocsp_stapling(true)
+                .enable_signed_cert_timestamps(true)
+                .cipher_list("ECDHE-ECDSA-AES128-GCM-SHA256:ECDHE-RSA-AES128-GCM-SHA256")
+                .sigalgs_list("ecdsa_secp256r1_sha256:rsa_pss_rsae_sha256")
+                .build(),
+        )
+        .build()
+        .unwrap()
+}
+
+fn legacy_suites(hello: &ClientHello) -> Vec<u16> {
+    hello
+        .cipher_suites
+        .iter()
+        .copied()
+        .filter(|&suite| {
+            suite == ECDHE_ECDSA_AES128_GCM_SHA256 || suite == ECDHE_RSA_AES128_GCM_SHA256
+        })
+        .collect()
+}
+
+fn has_extension(hello: &ClientHello, ty: u16) -> bool {
+    hello.extensions.iter().any(|(t, _)| *t == ty)
+}
+
+fn sigalgs(hello: &ClientHello) -> &[u8] {
+    let (_, data) = hello
+        .extensions
+        .iter()
+        .find(|(ty, _)| *ty == SIGNATURE_ALGORITHMS)
+        .unwrap();
+    &data[2..]
+}
+
+#[tokio::test]
+async fn emulation_cipher_list_is_used_by_default() {
+    let client = Client::builder()
+        .emulation(emulation())
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let hello = client_hello(client).await;
+    assert_eq!(
+        legacy_suites(&hello),
+        [ECDHE_ECDSA_AES128_GCM_SHA256, ECDHE_RSA_AES128_GCM_SHA256]
+    );
+    assert_eq!(sigalgs(&hello), [0x04, 0x03, 0x08, 0x04]);
+}
+
+#[tokio::test]
+async fn cipher_and_sigalgs_lists_override_emulation() {
+    let client = Client::builder()
+        .emulation(emulation())
+        .tls_cipher_list("ECDHE-RSA-AES128-GCM-SHA256:ECDHE-ECDSA-AES128-GCM-SHA256")
+        .tls_sigalgs_list("rsa_pss_rsae_sha256:ecdsa_secp256r1_sha256")
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let hello = client_hello(client).await;
+    assert_eq!(
+        legacy_suites(&hello),
+        [ECDHE_RSA_AES128_GCM_SHA256, ECDHE_ECDSA_AES128_GCM_SHA256]
+    );
+    assert_eq!(sigalgs(&hello), [0x08, 0x04, 0x04, 0x03]);
+
+    // The other settings of the emulation are kept.
+    assert!(has_extension(&hello, STATUS_REQUEST));
+    assert!(has_extension(&hello, SIGNED_CERTIFICATE_TIMESTAMP));
+}
+
+#[tokio::test]
+async fn cipher_list_overrides_emulation_set_afterwards() {
+    let client = Client::builder()
+        .tls_cipher_list("ECDHE-RSA-AES128-GCM-SHA256:ECDHE-ECDSA-AES128-GCM-SHA256")
+        .emulation(emulation())
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let hello = client_hello(client).await;
+    assert_eq!(
+        legacy_suites(&hello),
+        [ECDHE_RSA_AES128_GCM_SHA256, ECDHE_ECDSA_AES128_GCM_SHA256]
+    );
+}
+
+#[tokio::test]
+async fn tls13_only_fails_against_tls12_server() {
+    let client = Client::builder()
+        .tls13_only(true)
+        .cert_verification(false)
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let addr = tls_server(SslVersion::TLS1_2).await;
+    let err = client
+        .get(format!("https://127.0.0.1:{}/", addr.port()))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_connect(), "{err:?}");
+    assert!(!err.is_tls_downgrade(), "{err:?}");
+    let errs = support::error::inspect(err).join(": ").to_ascii_lowercase();
+    assert!(
+        errs.contains("protocol"),
+        "protocol version error expected, got: {errs}"
+    );
+
+    let addr = tls_server(SslVersion::TLS1_3).await;
+    let res = client
+        .get(format!("https://127.0.0.1:{}/", addr.port()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), wreq::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn negotiated_cipher_suite_follows_tls13_pin() {
+    let addr = tls_server(SslVersion::TLS1_3).await;
+
+    for suite in [
+        Tls13CipherSuite::AES_256_GCM_SHA384,
+        Tls13CipherSuite::CHACHA20_POLY1305_SHA256,
+    ] {
+        let emulation = EmulationProvider::builder()
+            .tls_config(
+                TlsConfig::builder()
+                    .tls13_cipher_suites(vec![suite])
+                    .build(),
+            )
+            .build()
+            .unwrap();
+        let client = Client::builder()
+            .emulation(emulation)
+            .tls13_only(true)
+            .tls_info(true)
+            .cert_verification(false)
+            .no_proxy()
+            .build()
+            .unwrap();
+
+        let res = client
+            .get(format!("https://127.0.0.1:{}/", addr.port()))
+            .send()
+            .await
+            .unwrap();
+        let info = res.extensions().get::<TlsInfo>().unwrap();
+        assert_eq!(info.cipher_suite(), Some(suite.name()));
+    }
+}
+
+#[tokio::test]
+async fn require_no_downgrade_rejects_downgrade_sentinel() {
+    // the server supports TLS 1.3, so it marks the TLS 1.2 it negotiates with the client
+    let addr = tls_server(SslVersion::TLS1_3).await;
+    let url = format!("https://127.0.0.1:{}/", addr.port());
+    let client = |require_no_downgrade| {
+        let emulation = EmulationProvider::builder()
+            .tls_config(
+                TlsConfig::builder()
+                    .max_tls_version(TlsVersion::TLS_1_2)
+                    .require_no_downgrade(require_no_downgrade)
+                    .build(),
+            )
+            .build()
+            .unwrap();
+        Client::builder()
+            .emulation(emulation)
+            .tls_info(true)
+            .cert_verification(false)
+            .no_proxy()
+            .build()
+            .unwrap()
+    };
+
+    let res = client(false).get(&url).send().await.unwrap();
+    assert!(
+        res.extensions()
+            .get::<TlsInfo>()
+            .unwrap()
+            .downgrade_sentinel()
+    );
+
+    let err = client(true).get(&url).send().await.unwrap_err();
+    assert!(err.is_tls_downgrade(), "{err:?}");
+}
@@ -2,6 +2,7 @@ mod support;
 
 #[cfg(feature = "json")]
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
 use http::{
     HeaderMap, Version,
@@ -232,6 +233,114 @@ async fn test_headers_order_with_request() {
     assert_eq!(res.status(), wreq::StatusCode::OK);
 }
 
+/// Returns the order of repeated headers, with a cookie and a custom header appended between
+/// them.
+fn repeated_headers() -> (HeaderMap, OriginalHeaders) {
+    let mut headers = HeaderMap::new();
+    headers.append("x-custom", "1".parse().unwrap());
+    headers.append("cookie", "a=1".parse().unwrap());
+    headers.append("x-custom", "2".parse().unwrap());
+    headers.append("cookie", "b=2".parse().unwrap());
+    headers.append("x-custom", "3".parse().unwrap());
+
+    // one spelling per value
+    let mut order = OriginalHeaders::new();
+    order.extend(["Cookie", "cookie", "X-Custom", "x-custom", "X-CUSTOM"]);
+    (headers, order)
+}
+
+#[tokio::test]
+async fn http1_repeated_headers_are_separate_lines_in_append_order() {
+    use tokio::io::AsyncReadExt;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (mut io, _) = listener.accept().await.unwrap();
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            let mut buf = [0; 1024];
+            let n = io.read(&mut buf).await.unwrap();
+            assert!(n > 0);
+            head.extend_from_slice(&buf[..n]);
+        }
+        io.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+            .await
+            .unwrap();
+        String::from_utf8(head).unwrap()
+    });
+
+    let (headers, order) = repeated_headers();
+    Client::builder()
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap()
+        .get(format!("http://{addr}/"))
+        .headers(headers)
+        .original_headers(order)
+        .send()
+        .await
+        .unwrap();
+
+    let head = server.await.unwrap();
+    let lines: Vec<_> = head
+        .split("\r\n")
+        .filter(|line| {
+            let line = line.to_lowercase();
+            line.starts_with("cookie:") || line.starts_with("x-custom:")
+        })
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            "Cookie: a=1",
+            "cookie: b=2",
+            "X-Custom: 1",
+            "x-custom: 2",
+            "X-CUSTOM: 3"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn http2_repeated_headers_are_separate_fields_in_append_order() {
+    let server = server::http(move |req| async move {
+        let fields: Vec<_> = req
+            .headers()
+            .iter()
+            .filter(|(name, _)| *name == "cookie" || *name == "x-custom")
+            .map(|(name, value)| format!("{name}: {}", value.to_str().unwrap()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                "cookie: a=1",
+                "cookie: b=2",
+                "x-custom: 1",
+                "x-custom: 2",
+                "x-custom: 3"
+            ]
+        );
+        http::Response::default()
+    });
+
+    let (headers, order) = repeated_headers();
+    let res = Client::builder()
+        .http2_only()
+        .no_proxy()
+        .build()
+        .unwrap()
+        .get(format!("http://{}/", server.addr()))
+        .headers(headers)
+        .original_headers(order)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.version(), Version::HTTP_2);
+    assert_eq!(res.status(), wreq::StatusCode::OK);
+}
+
 #[tokio::test]
 async fn donot_set_content_length_0_if_have_no_body() {
     let server = server::http(move |req| async move {
@@ -312,6 +421,86 @@ async fn response_bytes() {
     assert_eq!("Hello", bytes);
 }
 
+#[tokio::test]
+#[cfg(feature = "json")]
+async fn ndjson_bulk_post_streams_in_bounded_chunks() {
+    const ITEMS: u64 = 100_000;
+
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()[CONTENT_TYPE], "application/x-ndjson");
+        assert_eq!(req.headers().get(CONTENT_LENGTH), None);
+
+        // count the lines as they arrive, without holding the body
+        let (mut lines, mut sum, mut max_frame) = (0u64, 0u64, 0);
+        let mut partial = Vec::new();
+        let mut body = req.into_body();
+        while let Some(frame) = body.frame().await {
+            let data = frame.unwrap().into_data().unwrap();
+            max_frame = max_frame.max(data.len());
+            partial.extend_from_slice(&data);
+
+            let end = partial
+                .iter()
+                .rposition(|&b| b == b'\n')
+                .map_or(0, |i| i + 1);
+            for line in partial[..end]
+                .split(|&b| b == b'\n')
+                .filter(|l| !l.is_empty())
+            {
+                let item: serde_json::Value = serde_json::from_slice(line).unwrap();
+                sum += item["id"].as_u64().unwrap();
+                lines += 1;
+            }
+            partial.drain(..end);
+        }
+        assert!(partial.is_empty());
+
+        http::Response::new(format!("{lines} {sum} {max_frame}").into())
+    });
+
+    let docs = (0..ITEMS).map(|id| serde_json::json!({ "id": id, "op": "index" }));
+    let res = Client::new()
+        .post(format!("http://{}/bulk", server.addr()))
+        .ndjson(wreq::Body::ndjson_from_iter(docs).flush_threshold(4096))
+        .send()
+        .await
+        .unwrap();
+
+    let text = res.text().await.unwrap();
+    let counts: Vec<u64> = text.split(' ').map(|n| n.parse().unwrap()).collect();
+    assert_eq!(counts[0], ITEMS);
+    assert_eq!(counts[1], ITEMS * (ITEMS - 1) / 2);
+    // chunks never exceed the threshold by more than one line
+    assert!(
+        counts[2] <= 4096 + 64,
+        "largest chunk was {} bytes",
+        counts[2]
+    );
+}
+
+#[tokio::test]
+#[cfg(feature = "json")]
+async fn ndjson_buffered_sets_content_length() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()[CONTENT_LENGTH], "18");
+        let body = req.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "{\"id\":1}\n{\"id\":2}\n");
+        http::Response::default()
+    });
+
+    let docs = vec![
+        serde_json::json!({ "id": 1 }),
+        serde_json::json!({ "id": 2 }),
+    ];
+    let res = Client::new()
+        .post(format!("http://{}/", server.addr()))
+        .ndjson(wreq::Body::ndjson_from_iter(docs).buffered())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), wreq::StatusCode::OK);
+}
+
 #[tokio::test]
 #[cfg(feature = "json")]
 async fn response_json() {
@@ -330,6 +519,50 @@ async fn response_json() {
     assert_eq!("Hello", text);
 }
 
+#[tokio::test]
+#[cfg(feature = "json")]
+async fn fan_out_json_post() {
+    let echo = || {
+        server::http(move |req| async move {
+            assert_eq!(req.method(), "POST");
+            assert_eq!(req.headers()[CONTENT_TYPE], "application/json");
+            assert_eq!(req.headers()["x-fan-out"], "1");
+
+            let body = req.into_body().collect().await.unwrap().to_bytes();
+            http::Response::new(body.into())
+        })
+    };
+    let servers = [echo(), echo(), echo()];
+
+    let mut map = HashMap::new();
+    map.insert("region", "all");
+
+    let builders = Client::new()
+        .post("http://localhost/unused")
+        .header("x-fan-out", "1")
+        .json(&map)
+        .fan_out(
+            servers
+                .iter()
+                .map(|server| format!("http://{}/write", server.addr())),
+        )
+        .unwrap();
+    assert_eq!(builders.len(), 3);
+
+    let responses = futures::future::join_all(builders.into_iter().map(|b| b.send())).await;
+    for res in responses {
+        let res = res.unwrap();
+        assert_eq!(res.text().await.unwrap(), r#"{"region":"all"}"#);
+    }
+
+    let builders = Client::new()
+        .post("http://localhost/unused")
+        .fan_out(["not a url", "http://localhost/"])
+        .unwrap();
+    assert!(builders[0].try_clone().is_none());
+    assert!(builders[1].try_clone().is_some());
+}
+
 #[tokio::test]
 async fn body_pipe_response() {
     use http_body_util::BodyExt;
@@ -918,3 +1151,1522 @@ async fn skip_default_headers() {
     assert_eq!(res.url().as_str(), &url);
     assert_eq!(res.status(), wreq::StatusCode::OK);
 }
+
+#[tokio::test]
+async fn remove_header_leaves_out_one_default_header_across_redirects() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers().get("dnt"), None);
+        assert_eq!(req.headers().get("x-custom"), None);
+        assert_eq!(req.headers().get("accept"), Some(&"*/*".parse().unwrap()));
+        if req.uri().path() == "/start" {
+            http::Response::builder()
+                .status(302)
+                .header("location", "/end")
+                .body(Default::default())
+                .unwrap()
+        } else {
+            http::Response::default()
+        }
+    });
+
+    let client = wreq::Client::builder()
+        .default_headers({
+            let mut headers = wreq::header::HeaderMap::new();
+            headers.insert("dnt", "1".parse().unwrap());
+            headers.insert("accept", "*/*".parse().unwrap());
+            headers
+        })
+        .redirect(wreq::redirect::Policy::limited(1))
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let res = client
+        .get(format!("http://{}/start", server.addr()))
+        .header("x-custom", "set")
+        .remove_header("dnt")
+        .remove_header("x-custom")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.url().path(), "/end");
+    assert_eq!(res.status(), wreq::StatusCode::OK);
+}
+
+type Dispatches = Arc<Mutex<Vec<(wreq::RequestAttempt, wreq::RedirectHop, wreq::OriginalUrl)>>>;
+
+/// Records the dispatch extensions of requests and responses passing through.
+#[derive(Clone, Default)]
+struct RecordDispatchLayer {
+    seen: Dispatches,
+}
+
+#[derive(Clone)]
+struct RecordDispatch<S> {
+    inner: S,
+    seen: Dispatches,
+}
+
+fn record_dispatch(seen: &Dispatches, extensions: &http::Extensions) {
+    seen.lock().unwrap().push((
+        *extensions.get::<wreq::RequestAttempt>().unwrap(),
+        *extensions.get::<wreq::RedirectHop>().unwrap(),
+        extensions.get::<wreq::OriginalUrl>().unwrap().clone(),
+    ));
+}
+
+impl<S> tower::Layer<S> for RecordDispatchLayer {
+    type Service = RecordDispatch<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RecordDispatch {
+            inner,
+            seen: self.seen.clone(),
+        }
+    }
+}
+
+impl<S, ReqBody, ResBody> tower::Service<http::Request<ReqBody>> for RecordDispatch<S>
+where
+    S: tower::Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = futures::future::BoxFuture<'static, Result<S::Response, S::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<ReqBody>) -> Self::Future {
+        record_dispatch(&self.seen, req.extensions());
+        let seen = self.seen.clone();
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+            let res = fut.await?;
+            record_dispatch(&seen, res.extensions());
+            Ok(res)
+        })
+    }
+}
+
+#[tokio::test]
+async fn dispatch_extensions_track_retries_and_redirects() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let paths = Arc::new(Mutex::new(Vec::new()));
+    let server_paths = paths.clone();
+    tokio::spawn(async move {
+        let refused = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        loop {
+            let (io, _) = listener.accept().await.unwrap();
+            let refused = refused.clone();
+            let paths = server_paths.clone();
+            tokio::spawn(async move {
+                let mut conn = http2::server::handshake(io).await.unwrap();
+                while let Some(Ok((req, mut respond))) = conn.accept().await {
+                    let path = req.uri().path().to_owned();
+                    paths.lock().unwrap().push(path.clone());
+
+                    match path.as_str() {
+                        "/redirect" => {
+                            let res = http::Response::builder()
+                                .status(http::StatusCode::FOUND)
+                                .header(http::header::LOCATION, "/target")
+                                .body(())
+                                .unwrap();
+                            respond.send_response(res, true).unwrap();
+                        }
+                        "/target" if !refused.swap(true, std::sync::atomic::Ordering::SeqCst) => {
+                            respond.send_reset(http2::Reason::REFUSED_STREAM);
+                        }
+                        _ => {
+                            respond
+                                .send_response(http::Response::new(()), true)
+                                .unwrap();
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    let layer = RecordDispatchLayer::default();
+    let client = Client::builder()
+        .http2_only()
+        .no_proxy()
+        .layer(layer.clone())
+        .build()
+        .unwrap();
+
+    let url = format!("http://{addr}/redirect");
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), wreq::StatusCode::OK);
+    assert_eq!(res.url().path(), "/target");
+
+    assert_eq!(
+        *paths.lock().unwrap(),
+        ["/redirect", "/target", "/redirect", "/target"]
+    );
+
+    let original_url = wreq::OriginalUrl(url.parse().unwrap());
+    let seen = layer.seen.lock().unwrap().clone();
+    assert_eq!(
+        seen,
+        [
+            // the request as seen by the layer
+            (
+                wreq::RequestAttempt(1),
+                wreq::RedirectHop(0),
+                original_url.clone()
+            ),
+            // the response, produced by the retried request after following the redirect
+            (wreq::RequestAttempt(2), wreq::RedirectHop(1), original_url),
+        ]
+    );
+
+    // the extensions are also available on the response
+    assert_eq!(res.extensions().get(), Some(&wreq::RequestAttempt(2)));
+    assert_eq!(res.extensions().get(), Some(&wreq::RedirectHop(1)));
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn bytes_stream_with_control_stalls_sender_while_paused() {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use futures_util::StreamExt;
+
+    const TOTAL: usize = 16 * 1024 * 1024;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let sent = Arc::new(AtomicUsize::new(0));
+    let server_sent = sent.clone();
+    tokio::spawn(async move {
+        let (io, _) = listener.accept().await.unwrap();
+        let mut conn = http2::server::handshake(io).await.unwrap();
+        while let Some(Ok((_req, mut respond))) = conn.accept().await {
+            let sent = server_sent.clone();
+            tokio::spawn(async move {
+                let mut stream = respond
+                    .send_response(http::Response::new(()), false)
+                    .unwrap();
+
+                let mut remaining = TOTAL;
+                while remaining > 0 {
+                    stream.reserve_capacity(remaining.min(16 * 1024));
+                    let n = match std::future::poll_fn(|cx| stream.poll_capacity(cx)).await {
+                        Some(Ok(n)) => n.min(remaining),
+                        _ => return,
+                    };
+                    if n == 0 {
+                        continue;
+                    }
+
+                    remaining -= n;
+                    stream
+                        .send_data(bytes::Bytes::from(vec![0; n]), remaining == 0)
+                        .unwrap();
+                    sent.fetch_add(n, Ordering::SeqCst);
+                }
+            });
+        }
+    });
+
+    let client = Client::builder().http2_only().no_proxy().build().unwrap();
+    let (mut stream, flow) = client
+        .get(format!("http://{addr}/"))
+        .send()
+        .await
+        .unwrap()
+        .bytes_stream_with_control();
+
+    let mut received = stream.next().await.unwrap().unwrap().len();
+    flow.pause();
+    assert!(flow.is_paused());
+
+    // let the server exhaust the window it was granted
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let stalled = sent.load(Ordering::SeqCst);
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    assert_eq!(sent.load(Ordering::SeqCst), stalled, "server kept sending");
+    assert!(stalled < TOTAL, "whole body was buffered while paused");
+
+    flow.resume();
+    while let Some(chunk) = stream.next().await {
+        received += chunk.unwrap().len();
+    }
+
+    assert_eq!(received, TOTAL);
+    assert_eq!(sent.load(Ordering::SeqCst), TOTAL);
+}
+
+#[tokio::test]
+async fn h2_stream_window_bounds_buffered_bytes() {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    const TOTAL: usize = 16 * 1024 * 1024;
+    const READ: usize = 4 * 1024 * 1024;
+    const WINDOW: u32 = 64 * 1024;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // bytes sent on the `/small` and `/large` streams
+    let sent = Arc::new([AtomicUsize::new(0), AtomicUsize::new(0)]);
+    let server_sent = sent.clone();
+    tokio::spawn(async move {
+        let (io, _) = listener.accept().await.unwrap();
+        let mut conn = http2::server::handshake(io).await.unwrap();
+        while let Some(Ok((req, mut respond))) = conn.accept().await {
+            let sent = server_sent.clone();
+            let idx = usize::from(req.uri().path() == "/large");
+            tokio::spawn(async move {
+                let mut stream = respond
+                    .send_response(http::Response::new(()), false)
+                    .unwrap();
+
+                let mut remaining = TOTAL;
+                while remaining > 0 {
+                    stream.reserve_capacity(remaining.min(16 * 1024));
+                    let n = match std::future::poll_fn(|cx| stream.poll_capacity(cx)).await {
+                        Some(Ok(n)) => n.min(remaining),
+                        _ => return,
+                    };
+                    if n == 0 {
+                        continue;
+                    }
+
+                    remaining -= n;
+                    stream
+                        .send_data(bytes::Bytes::from(vec![0; n]), remaining == 0)
+                        .unwrap();
+                    sent[idx].fetch_add(n, Ordering::SeqCst);
+                }
+            });
+        }
+    });
+
+    let client = Client::builder().http2_only().no_proxy().build().unwrap();
+    let (small, large) = tokio::join!(
+        client
+            .get(format!("http://{addr}/small"))
+            .h2_stream_window(WINDOW)
+            .send(),
+        client.get(format!("http://{addr}/large")).send(),
+    );
+    let (mut small, mut large) = (small.unwrap(), large.unwrap());
+
+    // read past the initial stream window of both, then stop reading
+    let (small_read, large_read) = tokio::join!(
+        async {
+            let mut read = 0;
+            while read < READ {
+                read += small.chunk().await.unwrap().unwrap().len();
+            }
+            read
+        },
+        async {
+            let mut read = 0;
+            while read < READ {
+                read += large.chunk().await.unwrap().unwrap().len();
+            }
+            read
+        },
+    );
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let small_buffered = sent[0].load(Ordering::SeqCst) - small_read;
+    let large_buffered = sent[1].load(Ordering::SeqCst) - large_read;
+    assert!(
+        small_buffered <= 2 * WINDOW as usize,
+        "small window stream buffered {small_buffered} bytes"
+    );
+    assert!(
+        large_buffered > 1024 * 1024,
+        "default stream buffered {large_buffered} bytes"
+    );
+}
+
+#[tokio::test]
+async fn h2_chunk_hint_splits_data_frames() {
+    const LEN: usize = 64 * 1024;
+    const HINT: usize = 1000;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let frames = Arc::new(Mutex::new(Vec::new()));
+    let server_frames = frames.clone();
+    tokio::spawn(async move {
+        let (io, _) = listener.accept().await.unwrap();
+        let mut conn = http2::server::handshake(io).await.unwrap();
+        while let Some(Ok((req, mut respond))) = conn.accept().await {
+            let frames = server_frames.clone();
+            tokio::spawn(async move {
+                let mut body = req.into_body();
+                while let Some(data) = body.data().await {
+                    let data = data.unwrap();
+                    let _ = body.flow_control().release_capacity(data.len());
+                    frames.lock().unwrap().push(data.len());
+                }
+                respond
+                    .send_response(http::Response::new(()), true)
+                    .unwrap();
+            });
+        }
+    });
+
+    let client = Client::builder().http2_only().no_proxy().build().unwrap();
+    let res = client
+        .post(format!("http://{addr}/"))
+        .h2_chunk_hint(HINT)
+        .body(vec![0u8; LEN])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), wreq::StatusCode::OK);
+
+    let frames = frames.lock().unwrap();
+    assert_eq!(frames.iter().sum::<usize>(), LEN);
+    assert!(frames.iter().all(|&len| len <= HINT), "{frames:?}");
+}
+
+#[tokio::test]
+async fn upload_chunk_bounds_split_body_without_altering_it() {
+    const LEN: usize = 1024 * 1024;
+    const MIN: usize = 1000;
+    const MAX: usize = 4000;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let received = Arc::new(Mutex::new((Vec::new(), Vec::new())));
+    let server_received = received.clone();
+    tokio::spawn(async move {
+        let (io, _) = listener.accept().await.unwrap();
+        let mut conn = http2::server::handshake(io).await.unwrap();
+        while let Some(Ok((req, mut respond))) = conn.accept().await {
+            let received = server_received.clone();
+            tokio::spawn(async move {
+                let mut body = req.into_body();
+                while let Some(data) = body.data().await {
+                    let data = data.unwrap();
+                    let _ = body.flow_control().release_capacity(data.len());
+                    let (frames, bytes) = &mut *received.lock().unwrap();
+                    frames.push(data.len());
+                    bytes.extend_from_slice(&data);
+                }
+                respond
+                    .send_response(http::Response::new(()), true)
+                    .unwrap();
+            });
+        }
+    });
+
+    let body: Vec<u8> = (0..LEN).map(|i| (i % 251) as u8).collect();
+    let client = Client::builder()
+        .http2_only()
+        .upload_chunk_bounds(MIN, MAX)
+        .no_proxy()
+        .build()
+        .unwrap();
+    let res = client
+        .post(format!("http://{addr}/"))
+        .body(body.clone())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), wreq::StatusCode::OK);
+
+    let (frames, bytes) = &*received.lock().unwrap();
+    assert!(*bytes == body, "the body was altered");
+    assert!(frames.iter().all(|&len| len <= MAX), "{frames:?}");
+}
+
+#[tokio::test]
+async fn http2_priorities_follow_settings_in_order() {
+    use tokio::io::AsyncReadExt;
+    use wreq::{
+        EmulationProvider,
+        http2::{Http2Config, Priorities, Priority, StreamDependency, StreamId},
+    };
+
+    const PRIORITIES: [(u32, u32, u8, bool); 6] = [
+        (3, 0, 200, false),
+        (5, 0, 100, false),
+        (7, 0, 0, false),
+        (9, 7, 0, false),
+        (11, 3, 0, false),
+        (13, 0, 240, true),
+    ];
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // records the type, stream and payload of the frames up to the first HEADERS
+    let server = tokio::spawn(async move {
+        let (mut io, _) = listener.accept().await.unwrap();
+        io.write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0]).await.unwrap();
+
+        let mut preface = [0; 24];
+        io.read_exact(&mut preface).await.unwrap();
+        let mut frames = Vec::new();
+        loop {
+            let mut head = [0; 9];
+            io.read_exact(&mut head).await.unwrap();
+            let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+            let stream = u32::from_be_bytes([head[5], head[6], head[7], head[8]]) & 0x7fff_ffff;
+            let mut payload = vec![0; len];
+            io.read_exact(&mut payload).await.unwrap();
+            frames.push((head[3], stream, payload));
+            if head[3] == 0x1 {
+                return frames;
+            }
+        }
+    });
+
+    let priorities = Priorities::builder()
+        .extend(PRIORITIES.map(|(id, dep, weight, exclusive)| {
+            Priority::new(
+                StreamId::from(id),
+                StreamDependency::new(StreamId::from(dep), weight, exclusive),
+            )
+        }))
+        .build();
+    let emulation = EmulationProvider::builder()
+        .http2_config(Http2Config::builder().priorities(priorities).build())
+        .build()
+        .unwrap();
+    let client = Client::builder()
+        .emulation(emulation)
+        .http2_only()
+        .no_proxy()
+        .build()
+        .unwrap();
+    let _ = tokio::time::timeout(
+        std::time::Duration::from_secs(1),
+        client.get(format!("http://{addr}/")).send(),
+    )
+    .await;
+
+    let frames = server.await.unwrap();
+    assert_eq!(frames[0].0, 0x4, "the preface starts with SETTINGS");
+
+    let sent: Vec<_> = frames
+        .iter()
+        .filter(|(kind, _, _)| *kind == 0x2)
+        .map(|(_, stream, payload)| {
+            let dependency = u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]);
+            (
+                *stream,
+                dependency & 0x7fff_ffff,
+                payload[4],
+                dependency & 0x8000_0000 != 0,
+            )
+        })
+        .collect();
+    assert_eq!(sent, PRIORITIES);
+
+    // the PRIORITY frames come before the request, right after the connection frames
+    let first_priority = frames.iter().position(|(kind, _, _)| *kind == 0x2).unwrap();
+    assert!(
+        frames[..first_priority]
+            .iter()
+            .all(|(kind, _, _)| matches!(kind, 0x4 | 0x8)),
+        "{:?}",
+        frames
+            .iter()
+            .map(|(kind, stream, _)| (kind, stream))
+            .collect::<Vec<_>>()
+    );
+}
+
+/// Returns the settings of the initial SETTINGS frame the client sends with `config`.
+async fn http2_initial_settings(config: wreq::http2::Http2Config) -> Vec<(u16, u32)> {
+    use tokio::io::AsyncReadExt;
+    use wreq::EmulationProvider;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = tokio::spawn(async move {
+        let (mut io, _) = listener.accept().await.unwrap();
+        let mut preface = [0; 24];
+        io.read_exact(&mut preface).await.unwrap();
+        let mut head = [0; 9];
+        io.read_exact(&mut head).await.unwrap();
+        assert_eq!(head[3], 0x4, "the preface starts with SETTINGS");
+        let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+        let mut payload = vec![0; len];
+        io.read_exact(&mut payload).await.unwrap();
+        payload
+            .chunks(6)
+            .map(|s| {
+                (
+                    u16::from_be_bytes([s[0], s[1]]),
+                    u32::from_be_bytes([s[2], s[3], s[4], s[5]]),
+                )
+            })
+            .collect()
+    });
+
+    let client = Client::builder()
+        .emulation(
+            EmulationProvider::builder()
+                .http2_config(config)
+                .build()
+                .unwrap(),
+        )
+        .http2_only()
+        .no_proxy()
+        .build()
+        .unwrap();
+    let _ = tokio::time::timeout(
+        std::time::Duration::from_millis(200),
+        client.get(format!("http://{addr}/")).send(),
+    )
+    .await;
+
+    server.await.unwrap()
+}
+
+#[tokio::test]
+async fn http2_grease_setting_is_inserted_in_settings_order() {
+    use wreq::http2::{Http2Config, SettingId, SettingsOrder};
+
+    let config = || {
+        Http2Config::builder()
+            .header_table_size(65536)
+            .enable_push(false)
+            .initial_window_size(6291456)
+            .max_header_list_size(262144)
+            .enable_connect_protocol(true)
+            .no_rfc7540_priorities(true)
+            .settings_order(
+                SettingsOrder::builder()
+                    .extend([
+                        SettingId::HeaderTableSize,
+                        SettingId::EnablePush,
+                        SettingId::InitialWindowSize,
+                        SettingId::MaxHeaderListSize,
+                        SettingId::EnableConnectProtocol,
+                        SettingId::NoRfc7540Priorities,
+                    ])
+                    .build(),
+            )
+            .grease_setting(true)
+            .grease_setting_position(4)
+            .build()
+    };
+
+    let first = http2_initial_settings(config()).await;
+    let ids: Vec<_> = first.iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids[..4], [1, 2, 4, 6]);
+    assert_eq!(ids[5..], [8, 9]);
+    assert_eq!(ids[4] & 0x0f0f, 0x0a0a, "{:#06x} is not reserved", ids[4]);
+
+    // picked again for each connection
+    let mut differs = false;
+    for _ in 0..4 {
+        differs |= http2_initial_settings(config()).await[4] != first[4];
+    }
+    assert!(differs);
+}
+
+#[tokio::test]
+async fn http2_grease_setting_value_is_pinned() {
+    use wreq::http2::Http2Config;
+
+    let config = || {
+        Http2Config::builder()
+            .initial_window_size(6291456)
+            .grease_setting_value(0x1a2a, 7)
+            .build()
+    };
+
+    for _ in 0..2 {
+        let settings = http2_initial_settings(config()).await;
+        assert_eq!(settings.last(), Some(&(0x1a2a, 7)));
+        assert!(settings.contains(&(4, 6291456)));
+    }
+}
+
+#[test]
+#[should_panic(expected = "0x?a?a")]
+fn http2_grease_setting_value_must_be_reserved() {
+    let _ = wreq::http2::Http2Config::builder().grease_setting_value(0x1234, 0);
+}
+
+#[tokio::test]
+async fn http2_grease_setting_is_ignored_by_servers() {
+    use wreq::{EmulationProvider, http2::Http2Config};
+
+    let server = server::http(move |_req| async { http::Response::new("Hello".into()) });
+
+    let client = Client::builder()
+        .emulation(
+            EmulationProvider::builder()
+                .http2_config(Http2Config::builder().grease_setting(true).build())
+                .build()
+                .unwrap(),
+        )
+        .http2_only()
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let res = client
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.version(), Version::HTTP_2);
+    assert_eq!(res.text().await.unwrap(), "Hello");
+}
+
+#[tokio::test]
+async fn http1_header_case_follows_headers_order() {
+    use http::HeaderValue;
+    use tokio::io::AsyncReadExt;
+    use wreq::{EmulationProvider, http1::Http1Config};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = tokio::spawn(async move {
+        let (mut io, _) = listener.accept().await.unwrap();
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            let mut buf = [0; 1024];
+            let n = io.read(&mut buf).await.unwrap();
+            assert!(n > 0);
+            head.extend_from_slice(&buf[..n]);
+        }
+        io.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+            .await
+            .unwrap();
+        String::from_utf8(head).unwrap()
+    });
+
+    // Chrome title-cases its headers, except for the client hints
+    let mut order = OriginalHeaders::new();
+    order.extend([
+        "host",
+        "sec-ch-ua",
+        "sec-ch-ua-mobile",
+        "upgrade-insecure-requests",
+        "user-agent",
+        "accept",
+        "accept-encoding",
+    ]);
+    let mut header_case = OriginalHeaders::new();
+    header_case.extend(["sec-ch-ua", "sec-ch-ua-mobile"]);
+
+    let mut headers = HeaderMap::new();
+    headers.insert("accept", HeaderValue::from_static("*/*"));
+    headers.insert("user-agent", HeaderValue::from_static("chrome"));
+    headers.insert("sec-ch-ua-mobile", HeaderValue::from_static("?0"));
+    headers.insert("accept-encoding", HeaderValue::from_static("gzip"));
+    headers.insert("sec-ch-ua", HeaderValue::from_static("\"Chromium\""));
+    headers.insert("upgrade-insecure-requests", HeaderValue::from_static("1"));
+
+    let emulation = EmulationProvider::builder()
+        .http1_config(
+            Http1Config::builder()
+                .title_case_headers(true)
+                .header_case(header_case)
+                .build(),
+        )
+        .default_headers(headers)
+        .original_headers(order)
+        .build()
+        .unwrap();
+    let client = Client::builder()
+        .emulation(emulation)
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    client
+        .get(format!("http://{addr}/"))
+        .header("x-requested-with", "XMLHttpRequest")
+        .send()
+        .await
+        .unwrap();
+
+    let head = server.await.unwrap();
+    let names: Vec<_> = head
+        .split("\r\n")
+        .skip(1)
+        .filter_map(|line| line.split_once(':').map(|(name, _)| name))
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "Host",
+            "sec-ch-ua",
+            "sec-ch-ua-mobile",
+            "Upgrade-Insecure-Requests",
+            "User-Agent",
+            "Accept",
+            "Accept-Encoding",
+            "X-Requested-With",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn headers_order_from_defaults_follows_insertion_order() {
+    use http::HeaderValue;
+    use tokio::io::AsyncReadExt;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = tokio::spawn(async move {
+        let (mut io, _) = listener.accept().await.unwrap();
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\nbody") {
+            let mut buf = [0; 1024];
+            let n = io.read(&mut buf).await.unwrap();
+            assert!(n > 0);
+            head.extend_from_slice(&buf[..n]);
+        }
+        io.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+            .await
+            .unwrap();
+        String::from_utf8(head).unwrap()
+    });
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-first", HeaderValue::from_static("1"));
+    headers.insert("user-agent", HeaderValue::from_static("agent"));
+    headers.insert("accept", HeaderValue::from_static("*/*"));
+    let client = Client::builder()
+        .default_headers(headers)
+        .headers_order_from_defaults()
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    client
+        .post(format!("http://{addr}/"))
+        .header("x-extra", "1")
+        .header("cookie", "a=b")
+        .body("body")
+        .send()
+        .await
+        .unwrap();
+
+    let head = server.await.unwrap();
+    let names: Vec<_> = head
+        .split("\r\n")
+        .skip(1)
+        .filter_map(|line| line.split_once(':').map(|(name, _)| name))
+        .filter(|name| *name != "accept-encoding")
+        .collect();
+    // the headers of the request missing from the order are appended
+    assert_eq!(
+        names,
+        [
+            "host",
+            "content-length",
+            "x-first",
+            "user-agent",
+            "accept",
+            "cookie",
+            "x-extra",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn emulation_headers_hook_runs_on_every_hop() {
+    use http::HeaderValue;
+    use wreq::{EmulationProvider, Method, Url};
+
+    let server = server::http(move |req| async move {
+        let names: Vec<_> = req
+            .headers()
+            .keys()
+            .map(|name| name.as_str())
+            .filter(|name| ["user-agent", "accept", "sec-fetch-site", "priority"].contains(name))
+            .collect();
+        let res = if req.uri().path() == "/start" {
+            http::Response::builder()
+                .status(http::StatusCode::FOUND)
+                .header(http::header::LOCATION, "/target")
+        } else {
+            http::Response::builder()
+        };
+        res.header("x-names", names.join(","))
+            .header("x-site", req.headers()["sec-fetch-site"].clone())
+            .body(Default::default())
+            .unwrap()
+    });
+
+    let mut order = OriginalHeaders::new();
+    order.extend(["user-agent", "accept"]);
+    let mut headers = HeaderMap::new();
+    headers.insert("accept", HeaderValue::from_static("*/*"));
+    headers.insert("user-agent", HeaderValue::from_static("browser"));
+
+    let hops = Arc::new(Mutex::new(Vec::new()));
+    let emulation = EmulationProvider::builder()
+        .default_headers(headers)
+        .original_headers(order)
+        .headers_hook({
+            let hops = hops.clone();
+            Arc::new(move |url: &Url, method: &Method, headers: &mut HeaderMap| {
+                // the default headers are merged, the encodings are not added yet
+                assert_eq!(headers["user-agent"], "browser");
+                assert!(!headers.contains_key("accept-encoding"));
+                hops.lock()
+                    .unwrap()
+                    .push((method.clone(), url.path().to_owned()));
+
+                let site = if url.path() == "/start" {
+                    "none"
+                } else {
+                    "same-origin"
+                };
+                headers.insert("sec-fetch-site", HeaderValue::from_static(site));
+                headers.insert("priority", HeaderValue::from_static("u=0, i"));
+            })
+        })
+        .build()
+        .unwrap();
+    let client = Client::builder()
+        .emulation(emulation)
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let res = client
+        .get(format!("http://{}/start", server.addr()))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.url().path(), "/target");
+    assert_eq!(res.headers()["x-site"], "same-origin");
+    // the names the hook inserts follow the ordered ones, in the order they were inserted
+    assert_eq!(
+        res.headers()["x-names"],
+        "user-agent,accept,sec-fetch-site,priority"
+    );
+    assert_eq!(
+        *hops.lock().unwrap(),
+        [
+            (Method::GET, "/start".to_owned()),
+            (Method::GET, "/target".to_owned()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn http2_lowered_max_concurrent_streams_queues_requests() {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use wreq::{EmulationProvider, http2::Http2Config};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let active = Arc::new(AtomicUsize::new(0));
+    let max_active = Arc::new(AtomicUsize::new(0));
+    let (server_active, server_max_active) = (active.clone(), max_active.clone());
+    tokio::spawn(async move {
+        let (io, _) = listener.accept().await.unwrap();
+        // the client assumes 100 streams until these SETTINGS arrive
+        let mut conn = http2::server::Builder::new()
+            .max_concurrent_streams(1)
+            .handshake::<_, bytes::Bytes>(io)
+            .await
+            .unwrap();
+        while let Some(Ok((_req, mut respond))) = conn.accept().await {
+            let active = server_active.clone();
+            let max_active = server_max_active.clone();
+            tokio::spawn(async move {
+                let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                max_active.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                active.fetch_sub(1, Ordering::SeqCst);
+                respond
+                    .send_response(http::Response::new(()), true)
+                    .unwrap();
+            });
+        }
+    });
+
+    let snapshots = Arc::new(Mutex::new(Vec::new()));
+    let recorded = snapshots.clone();
+    let http2_config = Http2Config::builder()
+        .on_remote_settings(move |settings| {
+            recorded
+                .lock()
+                .unwrap()
+                .push(settings.max_concurrent_streams());
+        })
+        .build();
+
+    let client = Client::builder()
+        .http2_only()
+        .no_proxy()
+        .emulation(
+            EmulationProvider::builder()
+                .http2_config(http2_config)
+                .build()
+                .unwrap(),
+        )
+        .build()
+        .unwrap();
+
+    let url = format!("http://{addr}/");
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), wreq::StatusCode::OK);
+
+    let responses = futures::future::join_all((0..10).map(|_| client.get(&url).send())).await;
+    for res in responses {
+        assert_eq!(res.unwrap().status(), wreq::StatusCode::OK);
+    }
+
+    assert_eq!(max_active.load(Ordering::SeqCst), 1);
+    assert_eq!(*snapshots.lock().unwrap(), [Some(1)]);
+}
+
+/// Serves a body of `len` bytes on every request, counting accepted connections.
+async fn counting_server(
+    status: http::StatusCode,
+    len: usize,
+) -> (std::net::SocketAddr, Arc<std::sync::atomic::AtomicUsize>) {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let accepted = Arc::new(AtomicUsize::new(0));
+    let server_accepted = accepted.clone();
+    tokio::spawn(async move {
+        loop {
+            let (io, _) = listener.accept().await.unwrap();
+            server_accepted.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(async move {
+                let service = hyper::service::service_fn(move |_req| async move {
+                    let body = http_body_util::Full::new(bytes::Bytes::from(vec![0; len]));
+                    let mut res = http::Response::new(body);
+                    *res.status_mut() = status;
+                    Ok::<_, std::convert::Infallible>(res)
+                });
+                let _ = hyper::server::conn::http1::Builder::new()
+                    .serve_connection(hyper_util::rt::TokioIo::new(io), service)
+                    .await;
+            });
+        }
+    });
+
+    (addr, accepted)
+}
+
+#[tokio::test]
+async fn drained_response_reuses_connection() {
+    use std::{sync::atomic::Ordering, time::Duration};
+
+    let (addr, accepted) = counting_server(http::StatusCode::OK, 64 * 1024).await;
+    let client = Client::builder().http1_only().no_proxy().build().unwrap();
+
+    for _ in 0..3 {
+        let res = client.get(format!("http://{addr}/")).send().await.unwrap();
+        assert_eq!(res.drain().await.unwrap(), 64 * 1024);
+        // let the connection return to the pool
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(accepted.load(Ordering::SeqCst), 1);
+
+    // a dropped response doesn't give its connection back
+    for _ in 0..2 {
+        drop(client.get(format!("http://{addr}/")).send().await.unwrap());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(accepted.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn drain_max_and_error_for_status_drained() {
+    use std::{sync::atomic::Ordering, time::Duration};
+
+    let (addr, _) = counting_server(http::StatusCode::OK, 64 * 1024).await;
+    let client = Client::builder().http1_only().no_proxy().build().unwrap();
+    let res = client.get(format!("http://{addr}/")).send().await.unwrap();
+    assert!(res.drain_max(1024).await.unwrap_err().is_body());
+    let res = client.get(format!("http://{addr}/")).send().await.unwrap();
+    assert_eq!(res.drain_max(64 * 1024).await.unwrap(), 64 * 1024);
+
+    let (addr, accepted) = counting_server(http::StatusCode::SERVICE_UNAVAILABLE, 64 * 1024).await;
+    for _ in 0..2 {
+        let err = client
+            .get(format!("http://{addr}/"))
+            .send()
+            .await
+            .unwrap()
+            .error_for_status_drained()
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), Some(http::StatusCode::SERVICE_UNAVAILABLE));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(accepted.load(Ordering::SeqCst), 1);
+
+    // a larger error body is dropped instead of drained
+    let (addr, accepted) =
+        counting_server(http::StatusCode::SERVICE_UNAVAILABLE, 1024 * 1024).await;
+    for _ in 0..2 {
+        let err = client
+            .get(format!("http://{addr}/"))
+            .send()
+            .await
+            .unwrap()
+            .error_for_status_drained()
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), Some(http::StatusCode::SERVICE_UNAVAILABLE));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(accepted.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn retry_budget_limits_http2_retries() {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // refuses every stream, which is safe to retry
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let server_attempts = attempts.clone();
+    tokio::spawn(async move {
+        loop {
+            let (io, _) = listener.accept().await.unwrap();
+            let attempts = server_attempts.clone();
+            tokio::spawn(async move {
+                let mut conn = http2::server::handshake(io).await.unwrap();
+                while let Some(Ok((_req, mut respond))) = conn.accept().await {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    respond.send_reset(http2::Reason::REFUSED_STREAM);
+                }
+            });
+        }
+    });
+
+    let client = Client::builder().http2_only().no_proxy().build().unwrap();
+    client
+        .get(format!("http://{addr}/"))
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(attempts.swap(0, Ordering::SeqCst), 3);
+
+    // nothing was deposited, and there is no reserve
+    let budget = wreq::RetryBudget::new(Duration::from_secs(10), 0, 0.5);
+    let client = Client::builder()
+        .http2_only()
+        .retry_budget(budget.clone())
+        .no_proxy()
+        .build()
+        .unwrap();
+    client
+        .get(format!("http://{addr}/"))
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(attempts.swap(0, Ordering::SeqCst), 1);
+    assert_eq!(budget.exhausted(), 1);
+}
+
+#[tokio::test]
+async fn connect_timing_reports_new_and_reused_connections() {
+    use std::time::Duration;
+
+    use wreq::ConnectTiming;
+
+    let server = server::http(move |_req| async { http::Response::default() });
+    let client = Client::builder().http1_only().no_proxy().build().unwrap();
+    let url = format!("http://{}/", server.addr());
+
+    let res = client.get(&url).send().await.unwrap();
+    let timing = *res.extensions().get::<ConnectTiming>().unwrap();
+    assert!(!timing.reused());
+    assert!(timing.tcp() > Duration::ZERO);
+    assert_eq!(timing.tls(), None);
+    res.bytes().await.unwrap();
+
+    let res = client.get(&url).send().await.unwrap();
+    let timing = *res.extensions().get::<ConnectTiming>().unwrap();
+    assert!(timing.reused());
+    assert_eq!(timing.tcp(), Duration::ZERO);
+    assert_eq!(timing.tls(), None);
+}
+
+#[tokio::test]
+async fn think_time_delays_requests_unless_opted_out() {
+    use std::time::{Duration, Instant};
+
+    use wreq::{ThinkTime, ThinkTimeDelay};
+
+    let server = server::http(move |_req| async { http::Response::default() });
+    let client = Client::builder()
+        .think_time(ThinkTime::fixed(Duration::from_millis(200)))
+        .no_proxy()
+        .build()
+        .unwrap();
+    let url = format!("http://{}/", server.addr());
+
+    let start = Instant::now();
+    let res = client.get(&url).send().await.unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(200));
+    assert_eq!(
+        res.extensions().get::<ThinkTimeDelay>(),
+        Some(&ThinkTimeDelay(Duration::from_millis(200)))
+    );
+
+    let start = Instant::now();
+    let res = client.get(&url).no_think_time().send().await.unwrap();
+    assert!(start.elapsed() < Duration::from_millis(200));
+    assert!(res.extensions().get::<ThinkTimeDelay>().is_none());
+}
+
+#[tokio::test]
+async fn think_time_does_not_count_toward_timeout() {
+    use std::time::Duration;
+
+    use wreq::ThinkTime;
+
+    let server = server::http(move |_req| async { http::Response::default() });
+    let client = Client::builder()
+        .think_time(ThinkTime::fixed(Duration::from_millis(300)))
+        .timeout(Duration::from_millis(150))
+        .no_proxy()
+        .build()
+        .unwrap();
+    let url = format!("http://{}/", server.addr());
+
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), wreq::StatusCode::OK);
+
+    let res = client
+        .get(&url)
+        .timeout(Duration::from_millis(150))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), wreq::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn emulation_pool_keeps_connections_per_profile() {
+    use std::collections::HashMap;
+
+    use http::{HeaderValue, header::USER_AGENT};
+    use tokio::{io::AsyncReadExt, net::TcpListener};
+    use wreq::{EmulationProvider, EmulationSelection, RotatingEmulation};
+
+    // Keeps connections alive, and answers with the connection number and the user agent.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        for conn in 0.. {
+            let (mut io, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let mut buf = Vec::new();
+                loop {
+                    let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+                        let mut chunk = [0; 1024];
+                        match io.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                        continue;
+                    };
+                    let head = String::from_utf8_lossy(&buf[..end]).to_lowercase();
+                    buf.drain(..end + 4);
+                    let ua = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("user-agent: "))
+                        .unwrap_or_default();
+                    let body = format!("{conn} {ua}");
+                    let res = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{body}",
+                        body.len()
+                    );
+                    if io.write_all(res.as_bytes()).await.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+
+    let profile = |ua: &'static str| {
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_static(ua));
+        EmulationProvider::builder()
+            .default_headers(headers)
+            .build()
+            .unwrap()
+    };
+    let client = Client::builder()
+        .emulation_pool(RotatingEmulation::new([(profile("a"), 1), (profile("b"), 1)]).seed(7))
+        .user_agent("client")
+        .no_proxy()
+        .build()
+        .unwrap();
+    let url = format!("http://{addr}/");
+
+    let mut connections = HashMap::new();
+    for _ in 0..20 {
+        let res = client.get(&url).send().await.unwrap();
+        let selection = *res.extensions().get::<EmulationSelection>().unwrap();
+        let text = res.text().await.unwrap();
+        let (conn, ua) = text.split_once(' ').unwrap();
+        assert_eq!(ua, ["a", "b"][selection.0]);
+        assert_eq!(
+            *connections.entry(conn.to_owned()).or_insert(selection),
+            selection
+        );
+    }
+    // both profiles were used, each on its own reused connection
+    assert_eq!(connections.len(), 2);
+
+    // a request with its own emulation is not rotated
+    let res = client
+        .get(&url)
+        .emulation(profile("c"))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.extensions().get::<EmulationSelection>().is_none());
+    assert!(res.text().await.unwrap().ends_with(" c"));
+}
+
+const RAW_HEAD: &[u8] = b"HTTP/1.1 200 OK\r\n\
+    X-Signed: One\r\n\
+    content-length: 2\r\n\
+    Set-Cookie: a=1\r\n\
+    x-SIGNED:  two \r\n\
+    Set-Cookie: b=2\r\n\
+    \r\n";
+
+#[tokio::test]
+async fn capture_raw_response_head_keeps_http1_head_verbatim() {
+    use wreq::{RawResponseHead, message_signature};
+
+    let server = server::low_level_with_response(|_raw_request, client_socket| {
+        Box::new(async move {
+            client_socket.write_all(RAW_HEAD).await.unwrap();
+            client_socket.write_all(b"ok").await.unwrap();
+        })
+    });
+    let client = Client::builder().no_proxy().build().unwrap();
+    let url = format!("http://{}/", server.addr());
+
+    let res = client
+        .get(&url)
+        .capture_raw_response_head(true)
+        .send()
+        .await
+        .unwrap();
+    let head = res.extensions().get::<RawResponseHead>().unwrap().clone();
+    assert_eq!(res.text().await.unwrap(), "ok");
+
+    assert_eq!(head.status(), 200);
+    assert_eq!(head.as_bytes(), Some(RAW_HEAD));
+    let fields: Vec<_> = head.fields().collect();
+    assert_eq!(
+        fields,
+        [
+            (&b"X-Signed"[..], &b"One"[..]),
+            (&b"content-length"[..], &b"2"[..]),
+            (&b"Set-Cookie"[..], &b"a=1"[..]),
+            (&b"x-SIGNED"[..], &b"two"[..]),
+            (&b"Set-Cookie"[..], &b"b=2"[..]),
+        ]
+    );
+
+    let params = r#"("@status" "x-signed" "set-cookie");keyid="test-key""#;
+    assert_eq!(
+        message_signature::signature_base(&head, params).unwrap(),
+        format!(
+            "\"@status\": 200\n\
+             \"x-signed\": One, two\n\
+             \"set-cookie\": a=1, b=2\n\
+             \"@signature-params\": {params}"
+        )
+    );
+
+    // not captured unless asked for
+    let res = client.get(&url).send().await.unwrap();
+    assert!(res.extensions().get::<RawResponseHead>().is_none());
+}
+
+#[tokio::test]
+async fn capture_raw_response_head_keeps_http2_fields() {
+    use http::HeaderValue;
+    use wreq::RawResponseHead;
+
+    let server = server::http(move |_req| async {
+        let mut res = http::Response::default();
+        let headers = res.headers_mut();
+        headers.append("x-signed", HeaderValue::from_static("one"));
+        headers.append("content-type", HeaderValue::from_static("text/plain"));
+        headers.append("x-signed", HeaderValue::from_static("two"));
+        res
+    });
+    let client = Client::builder().http2_only().no_proxy().build().unwrap();
+
+    let res = client
+        .get(format!("http://{}/", server.addr()))
+        .capture_raw_response_head(true)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.version(), Version::HTTP_2);
+
+    let head = res.extensions().get::<RawResponseHead>().unwrap();
+    assert_eq!(head.as_bytes(), None);
+    let values: Vec<_> = head.get_all("X-Signed").collect();
+    assert_eq!(values, [&b"one"[..], &b"two"[..]]);
+    assert_eq!(
+        head.fields().find(|(name, _)| *name == b"content-type"),
+        Some((&b"content-type"[..], &b"text/plain"[..]))
+    );
+}
+
+/// Returns a link-local address of an interface of the host, and the name of the interface.
+#[cfg(target_os = "linux")]
+fn link_local_address() -> Option<(std::net::Ipv6Addr, String)> {
+    // each line is the address in hex, the index, prefix length, scope and flags, and the name
+    let table = std::fs::read_to_string("/proc/net/if_inet6").ok()?;
+    table.lines().find_map(|line| {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        let octets = u128::from_str_radix(fields.first()?, 16).ok()?;
+        let addr = std::net::Ipv6Addr::from(octets);
+        let interface = fields.last()?;
+        (addr.segments()[0] & 0xffc0 == 0xfe80).then(|| (addr, interface.to_string()))
+    })
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn ipv6_zone_scopes_link_local_connections() {
+    use tokio::io::AsyncReadExt;
+
+    let Some((addr, interface)) = link_local_address() else {
+        eprintln!("no link-local address, skipping");
+        return;
+    };
+    let zone = wreq::Ipv6Zone::new(addr, interface.as_str()).unwrap();
+    let bind = std::net::SocketAddrV6::new(addr, 0, 0, zone.scope_id());
+    let listener = tokio::net::TcpListener::bind(bind).await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = tokio::spawn(async move {
+        let (mut io, _) = listener.accept().await.unwrap();
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            let mut buf = [0; 1024];
+            let n = io.read(&mut buf).await.unwrap();
+            assert!(n > 0);
+            head.extend_from_slice(&buf[..n]);
+        }
+        io.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+            .await
+            .unwrap();
+        String::from_utf8(head).unwrap()
+    });
+
+    let client = Client::builder().no_proxy().build().unwrap();
+    let url = format!("http://[{addr}%25{interface}]:{port}/");
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.url().as_str(), format!("http://[{addr}]:{port}/"));
+
+    // the Host header carries the address without the zone
+    let head = server.await.unwrap();
+    assert!(
+        head.contains(&format!("\r\nhost: [{addr}]:{port}\r\n")),
+        "{head}"
+    );
+
+    // without the zone, the link-local address is not reachable
+    let err = client
+        .get(format!("http://[{addr}]:{port}/"))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_connect(), "{err:?}");
+}
+
+#[tokio::test]
+async fn proxy_protocol_header_precedes_requests() {
+    use tokio::io::AsyncReadExt;
+    use wreq::{ProxyProtocolConfig, ProxyProtocolVersion};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = tokio::spawn(async move {
+        let (mut io, _) = listener.accept().await.unwrap();
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            let mut buf = [0; 1024];
+            let n = io.read(&mut buf).await.unwrap();
+            assert!(n > 0);
+            head.extend_from_slice(&buf[..n]);
+        }
+        io.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+            .await
+            .unwrap();
+        String::from_utf8(head).unwrap()
+    });
+
+    let client = Client::builder()
+        .no_proxy()
+        .proxy_protocol(
+            ProxyProtocolConfig::new(ProxyProtocolVersion::V1)
+                .source("192.0.2.1:56324".parse().unwrap()),
+        )
+        .build()
+        .unwrap();
+    let res = client.get(format!("http://{addr}/")).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+
+    let head = server.await.unwrap();
+    let expected = format!(
+        "PROXY TCP4 192.0.2.1 127.0.0.1 56324 {}\r\nGET / HTTP/1.1\r\n",
+        addr.port()
+    );
+    assert!(head.starts_with(&expected), "{head}");
+}
@@ -0,0 +1,118 @@
+mod support;
+use support::server;
+use tokio::io::AsyncWriteExt;
+use wreq::{Client, ExcessData};
+
+#[tokio::test]
+async fn short_body_reports_received_and_expected_length() {
+    let server = server::low_level_with_response(|_raw_request, client_socket| {
+        Box::new(async move {
+            client_socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello")
+                .await
+                .unwrap();
+            client_socket.shutdown().await.unwrap();
+        })
+    });
+
+    let client = Client::builder().no_proxy().build().unwrap();
+    let url = format!("http://{}/", server.addr());
+
+    let err = client
+        .get(&url)
+        .send()
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap_err();
+    let incomplete = err.incomplete_body().unwrap();
+    assert_eq!(incomplete.received(), 5);
+    assert_eq!(incomplete.expected(), Some(10));
+
+    let (bytes, err) = client
+        .get(&url)
+        .send()
+        .await
+        .unwrap()
+        .bytes_allow_incomplete()
+        .await
+        .unwrap();
+    assert_eq!(bytes, "hello");
+    assert_eq!(err.unwrap().incomplete_body().unwrap().received(), 5);
+}
+
+#[tokio::test]
+async fn complete_body_is_not_reported_incomplete() {
+    let server = server::low_level_with_response(|_raw_request, client_socket| {
+        Box::new(async move {
+            client_socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello")
+                .await
+                .unwrap();
+        })
+    });
+
+    let (bytes, err) = Client::builder()
+        .no_proxy()
+        .build()
+        .unwrap()
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .await
+        .unwrap()
+        .bytes_allow_incomplete()
+        .await
+        .unwrap();
+    assert_eq!(bytes, "hello");
+    assert!(err.is_none());
+}
+
+#[tokio::test]
+async fn excess_data_is_truncated_and_connection_not_reused() {
+    // The excess data looks like a response, which a reused connection would return for
+    // the next request.
+    let server = server::low_level_with_response(|_raw_request, client_socket| {
+        Box::new(async move {
+            client_socket
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello\
+                      HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nevil",
+                )
+                .await
+                .unwrap();
+        })
+    });
+
+    let client = Client::builder().no_proxy().build().unwrap();
+    let url = format!("http://{}/", server.addr());
+
+    for _ in 0..2 {
+        let res = client.get(&url).send().await.unwrap();
+        assert_eq!(res.extensions().get::<ExcessData>().unwrap().bytes(), 42);
+        assert_eq!(res.text().await.unwrap(), "hello");
+    }
+}
+
+#[tokio::test]
+async fn response_without_excess_data_has_no_extension() {
+    let server = server::low_level_with_response(|_raw_request, client_socket| {
+        Box::new(async move {
+            client_socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello")
+                .await
+                .unwrap();
+        })
+    });
+
+    let res = Client::builder()
+        .no_proxy()
+        .build()
+        .unwrap()
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.extensions().get::<ExcessData>().is_none());
+    assert_eq!(res.text().await.unwrap(), "hello");
+}
@@ -186,3 +186,96 @@ async fn cookie_store_path() {
     let url = format!("http://{}/subpath", server.addr());
     client.get(&url).send().await.unwrap();
 }
+
+#[cfg(feature = "psl")]
+#[test]
+fn cookie_jar_rejects_public_suffix_domain() {
+    use wreq::cookie::{CookieStore, Jar};
+
+    let jar = Jar::default();
+
+    let url = "https://www.example.co.uk/".parse().unwrap();
+    jar.add_cookie_str("a=1; Domain=co.uk", &url);
+    jar.add_cookie_str("b=2; Domain=example.co.uk", &url);
+
+    let other = "https://other.co.uk/".parse().unwrap();
+    assert!(jar.cookies(&other).is_none());
+
+    let sibling = "https://api.example.co.uk/".parse().unwrap();
+    assert_eq!(jar.cookies(&sibling).unwrap(), vec!["b=2"]);
+
+    // a public suffix may still set a host-only cookie for itself
+    let url = "https://github.io/".parse().unwrap();
+    jar.add_cookie_str("c=3; Domain=github.io", &url);
+    assert_eq!(jar.cookies(&url).unwrap(), vec!["c=3"]);
+
+    let user = "https://user.github.io/".parse().unwrap();
+    assert!(jar.cookies(&user).is_none());
+}
+
+#[tokio::test]
+async fn cookie_store_exceeding_header_budget_fails_before_sending() {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    let hits = Arc::new(AtomicUsize::new(0));
+    let server = server::http({
+        let hits = hits.clone();
+        move |_req| {
+            hits.fetch_add(1, Ordering::SeqCst);
+            async { http::Response::default() }
+        }
+    });
+
+    let url = format!("http://{}/", server.addr());
+    let jar = Arc::new(wreq::cookie::Jar::default());
+    for i in 0..8 {
+        jar.add_cookie_str(&format!("c{i}={}", "x".repeat(200)), &url.parse().unwrap());
+    }
+
+    let client = wreq::Client::builder()
+        .cookie_provider(jar)
+        .max_request_header_bytes(1024)
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let err = client.get(&url).send().await.unwrap_err();
+    assert!(err.is_request());
+    let details = err.request_headers_too_large().unwrap();
+    assert_eq!(details.limit(), 1024);
+    assert!(details.estimated() > 1024);
+    assert_eq!(details.largest_header(), Some(&http::header::COOKIE));
+    assert_eq!(hits.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn cookie_store_trims_cookies_to_fit_header_budget() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["cookie"], "session=1");
+        http::Response::default()
+    });
+
+    let root = format!("http://{}/", server.addr()).parse().unwrap();
+    let jar = std::sync::Arc::new(wreq::cookie::Jar::default());
+    jar.add_cookie_str(&format!("tracking={}; Path=/", "x".repeat(1024)), &root);
+    jar.add_cookie_str("session=1; Path=/app", &root);
+
+    let client = wreq::Client::builder()
+        .cookie_provider(jar)
+        .max_request_header_bytes(512)
+        .trim_cookies_to_fit(true)
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    // the cookie with the shortest path is listed last, and dropped first
+    let url = format!("http://{}/app", server.addr());
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(
+        res.extensions().get::<wreq::TrimmedCookies>(),
+        Some(&wreq::TrimmedCookies(vec!["tracking".to_owned()]))
+    );
+}
@@ -0,0 +1,159 @@
+mod support;
+
+use std::{net::SocketAddr, time::Duration};
+
+use futures_util::future::join_all;
+use support::server;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use wreq::{Client, DigestAlgorithm, ExpectedDigest, header::RANGE};
+
+/// The SHA-256 digest of `hello world`.
+const HELLO_WORLD: [u8; 32] = [
+    0xb9, 0x4d, 0x27, 0xb9, 0x93, 0x4d, 0x3e, 0x08, 0xa5, 0x2e, 0x52, 0xd7, 0xda, 0x7d, 0xab, 0xfa,
+    0xc4, 0x84, 0xef, 0xe3, 0x7a, 0x53, 0x80, 0xee, 0x90, 0x88, 0xf7, 0xac, 0xe2, 0xef, 0xcd, 0xe9,
+];
+
+fn client() -> Client {
+    Client::builder().no_proxy().build().unwrap()
+}
+
+#[tokio::test]
+async fn matching_digest() {
+    let server = server::http(move |_req| async { http::Response::new("hello world".into()) });
+
+    let body = client()
+        .get(format!("http://{}/", server.addr()))
+        .verify_digest(DigestAlgorithm::Sha256, HELLO_WORLD)
+        .send()
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap();
+    assert_eq!(body, "hello world");
+}
+
+#[tokio::test]
+async fn corrupted_byte_fails_at_the_end() {
+    let server = server::http(move |_req| async { http::Response::new("hello worle".into()) });
+
+    let mut res = client()
+        .get(format!("http://{}/", server.addr()))
+        .verify_digest(DigestAlgorithm::Sha256, HELLO_WORLD)
+        .send()
+        .await
+        .unwrap();
+
+    // the data is delivered, the mismatch is reported once the body ended
+    assert_eq!(res.chunk().await.unwrap().unwrap(), "hello worle");
+    let err = res.chunk().await.unwrap_err();
+    assert!(err.is_body());
+    let mismatch = err.digest_mismatch().unwrap();
+    assert_eq!(mismatch.algorithm(), DigestAlgorithm::Sha256);
+    assert_eq!(mismatch.expected(), HELLO_WORLD);
+    assert_ne!(mismatch.actual(), HELLO_WORLD);
+}
+
+/// Serves `hello world`, cutting the first response after `hello`, and the rest of it to
+/// a range request.
+async fn interrupted_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut io, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let n = io.read(&mut buf).await.unwrap();
+            let head = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+
+            let res: &[u8] = if head.contains("range: bytes=5-") {
+                b"HTTP/1.1 206 Partial Content\r\ncontent-range: bytes 5-10/11\r\ncontent-length: 6\r\n\r\n world"
+            } else {
+                b"HTTP/1.1 200 OK\r\ncontent-length: 11\r\n\r\nhello"
+            };
+            let _ = io.write_all(res).await;
+            let _ = io.shutdown().await;
+        }
+    });
+    addr
+}
+
+#[tokio::test]
+async fn resumed_download_hashes_across_segments() {
+    let addr = interrupted_server().await;
+    let client = client();
+    let url = format!("http://{addr}/");
+    let digest = ExpectedDigest::new(DigestAlgorithm::Sha256, HELLO_WORLD);
+
+    let (first, err) = client
+        .get(&url)
+        .expected_digest(digest.clone())
+        .send()
+        .await
+        .unwrap()
+        .bytes_allow_incomplete()
+        .await
+        .unwrap();
+    assert_eq!(first, "hello");
+    assert!(err.unwrap().incomplete_body().is_some());
+    assert_eq!(digest.hashed_bytes(), 5);
+
+    let rest = client
+        .get(&url)
+        .header(RANGE, format!("bytes={}-", digest.hashed_bytes()))
+        .expected_digest(digest.clone())
+        .send()
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap();
+    assert_eq!(rest, " world");
+    assert_eq!(digest.hashed_bytes(), 0);
+}
+
+/// Serves `hello world` to every request, pausing after `hello`.
+async fn slow_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut io, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let mut buf = vec![0; 4096];
+                let _ = io.read(&mut buf).await.unwrap();
+                let _ = io
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 11\r\n\r\nhello")
+                    .await;
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                let _ = io.write_all(b" world").await;
+                let _ = io.shutdown().await;
+            });
+        }
+    });
+    addr
+}
+
+#[tokio::test]
+async fn fanned_out_requests_hash_on_their_own() {
+    let addr = slow_server().await;
+
+    let requests = client()
+        .get(format!("http://{addr}/"))
+        .verify_digest(DigestAlgorithm::Sha256, HELLO_WORLD)
+        .fan_out([format!("http://{addr}/a"), format!("http://{addr}/b")])
+        .unwrap();
+    let bodies = join_all(
+        requests
+            .into_iter()
+            .map(|req| async move { req.send().await?.bytes().await }),
+    )
+    .await;
+
+    for body in bodies {
+        assert_eq!(body.unwrap(), "hello world");
+    }
+}
@@ -0,0 +1,460 @@
+use std::{fs::File, net::SocketAddr};
+
+use http::{HeaderMap, HeaderValue, header};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use wreq::{
+    Client, EmulationApply, EmulationProvider, OriginalHeaders,
+    http1::Http1Config,
+    http2::{
+        Http2Config, Priorities, Priority, PseudoId, PseudoOrder, SettingId, SettingsOrder,
+        StreamDependency, StreamId,
+    },
+    tls::{
+        AlpnProtocol, AlpsProtocol, CertificateCompressionAlgorithm, ExtensionType, TlsConfig,
+        TlsVersion,
+    },
+};
+
+const PROFILE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/support/emulation/firefox.json"
+);
+
+/// The Firefox profile of the `emulation_firefox` example, built in code.
+fn firefox() -> EmulationProvider {
+    let tls = TlsConfig::builder()
+        .curves_list("X25519:P-256:P-384:P-521:ffdhe2048:ffdhe3072")
+        .cipher_list(concat!(
+            "TLS_AES_128_GCM_SHA256:TLS_CHACHA20_POLY1305_SHA256:TLS_AES_256_GCM_SHA384:",
+            "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256:TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256:",
+            "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256:",
+            "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256:",
+            "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384:TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384:",
+            "TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA:TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA:",
+            "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA:TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA:",
+            "TLS_RSA_WITH_AES_128_GCM_SHA256:TLS_RSA_WITH_AES_256_GCM_SHA384:",
+            "TLS_RSA_WITH_AES_128_CBC_SHA:TLS_RSA_WITH_AES_256_CBC_SHA"
+        ))
+        .sigalgs_list(concat!(
+            "ecdsa_secp256r1_sha256:ecdsa_secp384r1_sha384:ecdsa_secp521r1_sha512:",
+            "rsa_pss_rsae_sha256:rsa_pss_rsae_sha384:rsa_pss_rsae_sha512:",
+            "rsa_pkcs1_sha256:rsa_pkcs1_sha384:rsa_pkcs1_sha512:ecdsa_sha1:rsa_pkcs1_sha1"
+        ))
+        .delegated_credentials(concat!(
+            "ecdsa_secp256r1_sha256:ecdsa_secp384r1_sha384:ecdsa_secp521r1_sha512:",
+            "ecdsa_sha1"
+        ))
+        .certificate_compression_algorithms(&[
+            CertificateCompressionAlgorithm::ZLIB,
+            CertificateCompressionAlgorithm::BROTLI,
+            CertificateCompressionAlgorithm::ZSTD,
+        ])
+        .alpn_protos(&[AlpnProtocol::HTTP2, AlpnProtocol::HTTP1])
+        .record_size_limit(0x4001)
+        .pre_shared_key(true)
+        .enable_ech_grease(true)
+        .min_tls_version(TlsVersion::TLS_1_0)
+        .max_tls_version(TlsVersion::TLS_1_3)
+        .prefer_chacha20(true)
+        .aes_hw_override(false)
+        .random_aes_hw_override(true)
+        .extension_permutation(&[
+            ExtensionType::SERVER_NAME,
+            ExtensionType::EXTENDED_MASTER_SECRET,
+            ExtensionType::RENEGOTIATE,
+            ExtensionType::SUPPORTED_GROUPS,
+            ExtensionType::EC_POINT_FORMATS,
+            ExtensionType::SESSION_TICKET,
+            ExtensionType::APPLICATION_LAYER_PROTOCOL_NEGOTIATION,
+            ExtensionType::STATUS_REQUEST,
+            ExtensionType::DELEGATED_CREDENTIAL,
+            ExtensionType::KEY_SHARE,
+            ExtensionType::SUPPORTED_VERSIONS,
+            ExtensionType::SIGNATURE_ALGORITHMS,
+            ExtensionType::PSK_KEY_EXCHANGE_MODES,
+            ExtensionType::RECORD_SIZE_LIMIT,
+            ExtensionType::CERT_COMPRESSION,
+            ExtensionType::ENCRYPTED_CLIENT_HELLO,
+        ])
+        .build();
+
+    let http1 = Http1Config::builder()
+        .allow_obsolete_multiline_headers_in_responses(true)
+        .max_headers(100)
+        .build();
+
+    let http2 = Http2Config::builder()
+        .initial_stream_id(15)
+        .header_table_size(65536)
+        .initial_stream_window_size(131072)
+        .max_frame_size(16384)
+        .initial_connection_window_size(12517377 + 65535)
+        .headers_stream_dependency(StreamDependency::new(StreamId::from(13), 41, false))
+        .headers_pseudo_order(
+            PseudoOrder::builder()
+                .extend([
+                    PseudoId::Method,
+                    PseudoId::Scheme,
+                    PseudoId::Authority,
+                    PseudoId::Path,
+                ])
+                .build(),
+        )
+        .settings_order(
+            SettingsOrder::builder()
+                .extend([
+                    SettingId::HeaderTableSize,
+                    SettingId::EnablePush,
+                    SettingId::MaxConcurrentStreams,
+                    SettingId::InitialWindowSize,
+                    SettingId::MaxFrameSize,
+                    SettingId::MaxHeaderListSize,
+                    SettingId::EnableConnectProtocol,
+                    SettingId::NoRfc7540Priorities,
+                ])
+                .build(),
+        )
+        .priorities(
+            Priorities::builder()
+                .extend(
+                    [
+                        (3, 0, 200),
+                        (5, 0, 100),
+                        (7, 0, 0),
+                        (9, 7, 0),
+                        (11, 3, 0),
+                        (13, 0, 240),
+                    ]
+                    .map(|(id, dep, weight): (u32, u32, u8)| {
+                        Priority::new(
+                            StreamId::from(id),
+                            StreamDependency::new(StreamId::from(dep), weight, false),
+                        )
+                    }),
+                )
+                .build(),
+        )
+        .build();
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::USER_AGENT,
+        HeaderValue::from_static(
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10.15; rv:136.0) Gecko/20100101 Firefox/136.0",
+        ),
+    );
+    headers.insert(
+        header::ACCEPT_LANGUAGE,
+        HeaderValue::from_static("en-US,en;q=0.9"),
+    );
+    headers.insert(
+        header::ACCEPT_ENCODING,
+        HeaderValue::from_static("gzip, deflate, br"),
+    );
+    headers.insert(header::COOKIE, HeaderValue::from_static("foo=bar"));
+
+    let mut original_headers = OriginalHeaders::new();
+    original_headers.insert("cookie");
+    original_headers.insert("content-length");
+    original_headers.insert("USER-AGENT");
+    original_headers.insert("ACCEPT-LANGUAGE");
+    original_headers.insert("ACCEPT-ENCODING");
+
+    EmulationProvider::builder()
+        .tls_config(tls)
+        .http1_config(http1)
+        .http2_config(http2)
+        .default_headers(headers)
+        .original_headers(original_headers)
+        .build()
+        .unwrap()
+}
+
+/// Answers every request with an empty response, and sends the request heads it received.
+async fn server() -> (SocketAddr, tokio::sync::mpsc::UnboundedReceiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            let (mut io, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let n = io.read(&mut buf).await.unwrap();
+            let _ = tx.send(String::from_utf8_lossy(&buf[..n]).into_owned());
+            let _ = io
+                .write_all(b"HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: 0\r\n\r\n")
+                .await;
+        }
+    });
+    (addr, rx)
+}
+
+fn client(emulation: EmulationProvider) -> Client {
+    Client::builder()
+        .emulation(emulation)
+        .no_proxy()
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn json_profile_matches_the_same_profile_built_in_code() {
+    let from_json = client(EmulationProvider::from_reader(File::open(PROFILE).unwrap()).unwrap());
+    let in_code = client(firefox());
+
+    assert_eq!(
+        serde_json::to_value(from_json.tls_config_snapshot()).unwrap(),
+        serde_json::to_value(in_code.tls_config_snapshot()).unwrap()
+    );
+
+    // the headers are sent in the same order and case
+    let (addr, mut heads) = server().await;
+    let url = format!("http://{addr}/");
+    from_json.get(&url).send().await.unwrap();
+    in_code.get(&url).send().await.unwrap();
+    let from_json = heads.recv().await.unwrap();
+    assert!(from_json.contains("USER-AGENT: Mozilla/5.0"), "{from_json}");
+    assert_eq!(from_json, heads.recv().await.unwrap());
+}
+
+#[test]
+fn json_profile_errors_name_the_field() {
+    let err = EmulationProvider::from_json(r#"{"tls": {"min_tls_version": "1.4"}}"#).unwrap_err();
+    assert!(err.is_builder());
+    assert!(err.to_string().contains("tls.min_tls_version"), "{err}");
+}
+
+#[tokio::test]
+async fn emulation_with_tls_keeps_the_headers() {
+    let crawler = Client::builder()
+        .user_agent("my-crawler/1.0")
+        .emulation_with(firefox(), EmulationApply::TLS | EmulationApply::HTTP2)
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        serde_json::to_value(crawler.tls_config_snapshot().tls()).unwrap(),
+        serde_json::to_value(client(firefox()).tls_config_snapshot().tls()).unwrap()
+    );
+
+    let (addr, mut heads) = server().await;
+    crawler.get(format!("http://{addr}/")).send().await.unwrap();
+    let head = heads.recv().await.unwrap();
+    assert!(head.contains("user-agent: my-crawler/1.0"), "{head}");
+    assert!(!head.contains("Firefox"), "{head}");
+}
+
+#[test]
+fn http2_only_emulation_keeps_the_tls_configuration() {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::USER_AGENT, HeaderValue::from_static("app/1.0"));
+    let http2 = Http2Config::builder()
+        .initial_stream_window_size(6291456)
+        .settings_order(
+            SettingsOrder::builder()
+                .extend([SettingId::InitialWindowSize, SettingId::HeaderTableSize])
+                .build(),
+        )
+        .build();
+    let emulation = || EmulationProvider::http2_only(http2.clone(), headers.clone(), None).unwrap();
+
+    let layered = Client::builder()
+        .emulation(firefox())
+        .emulation(emulation())
+        .no_proxy()
+        .build()
+        .unwrap();
+    let snapshot = layered.tls_config_snapshot();
+    let firefox = client(firefox()).tls_config_snapshot();
+    assert_eq!(
+        serde_json::to_value(snapshot.tls()).unwrap(),
+        serde_json::to_value(firefox.tls()).unwrap()
+    );
+    assert_eq!(
+        serde_json::to_value(snapshot.http1()).unwrap(),
+        serde_json::to_value(firefox.http1()).unwrap()
+    );
+    assert_eq!(
+        serde_json::to_value(snapshot.http2()).unwrap(),
+        serde_json::to_value(&http2).unwrap()
+    );
+    assert_eq!(snapshot.default_headers(), &headers);
+
+    // without another emulation, the TLS configuration is the default one
+    let plain = Client::builder()
+        .emulation(emulation())
+        .no_proxy()
+        .build()
+        .unwrap();
+    assert_eq!(
+        serde_json::to_value(plain.tls_config_snapshot().tls()).unwrap(),
+        serde_json::to_value(Client::new().tls_config_snapshot().tls()).unwrap()
+    );
+}
+
+#[test]
+fn update_applies_only_the_tls_of_an_emulation() {
+    let updated = Client::builder().no_proxy().build().unwrap();
+
+    let err = updated
+        .update()
+        .emulation_with(firefox(), EmulationApply::all())
+        .apply()
+        .unwrap_err();
+    assert!(err.is_builder());
+
+    updated
+        .update()
+        .emulation_with(firefox(), EmulationApply::TLS)
+        .apply()
+        .unwrap();
+    assert_eq!(
+        serde_json::to_value(updated.tls_config_snapshot().tls()).unwrap(),
+        serde_json::to_value(client(firefox()).tls_config_snapshot().tls()).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn emulation_locale_replaces_only_accept_language() {
+    let (addr, mut heads) = server().await;
+    let url = format!("http://{addr}/");
+
+    let before = Client::builder()
+        .emulation_locale("de-DE,de;q=0.9")
+        .emulation(firefox())
+        .no_proxy()
+        .build()
+        .unwrap();
+    let after = Client::builder()
+        .emulation(firefox())
+        .emulation_locale("de-DE,de;q=0.9")
+        .no_proxy()
+        .build()
+        .unwrap();
+    for client in [&before, &after] {
+        client.get(&url).send().await.unwrap();
+        let head = heads.recv().await.unwrap();
+        assert!(
+            head.contains("ACCEPT-LANGUAGE: de-DE,de;q=0.9\r\n"),
+            "{head}"
+        );
+        assert!(head.contains("USER-AGENT: Mozilla/5.0"), "{head}");
+    }
+
+    // a request setting the header keeps it
+    before
+        .get(&url)
+        .header(header::ACCEPT_LANGUAGE, "fr-FR")
+        .send()
+        .await
+        .unwrap();
+    let head = heads.recv().await.unwrap();
+    assert!(head.contains("ACCEPT-LANGUAGE: fr-FR\r\n"), "{head}");
+
+    // profiles without the header do not get one
+    Client::builder()
+        .emulation_locale("de-DE,de;q=0.9")
+        .emulation(EmulationProvider::default())
+        .no_proxy()
+        .build()
+        .unwrap()
+        .get(&url)
+        .send()
+        .await
+        .unwrap();
+    let head = heads.recv().await.unwrap();
+    assert!(!head.to_lowercase().contains("accept-language"), "{head}");
+}
+
+#[test]
+fn contradicting_emulation_lists_each_problem() {
+    let mut order = OriginalHeaders::new();
+    order.extend(["user-agent", "x-missing", "cookie"]);
+    let mut headers = HeaderMap::new();
+    headers.insert(header::USER_AGENT, HeaderValue::from_static("browser"));
+
+    let builder = || {
+        EmulationProvider::builder()
+            .tls_config(
+                TlsConfig::builder()
+                    .alpn_protos(&[AlpnProtocol::HTTP1])
+                    .alps_protos(&[AlpsProtocol::HTTP2])
+                    .build(),
+            )
+            .http2_config(Http2Config::builder().build())
+            .default_headers(headers.clone())
+            .original_headers(order.clone())
+    };
+
+    let err = builder().build().unwrap_err();
+    assert!(err.is_builder());
+    let problems = err.emulation_conflicts().unwrap().problems();
+    assert_eq!(problems.len(), 3, "{problems:?}");
+    assert!(problems[2].contains("x-missing"), "{problems:?}");
+
+    // a lenient emulation is built, and applied, as is
+    let emulation = builder().strict(false).build().unwrap();
+    Client::builder().emulation(emulation).build().unwrap();
+}
+
+#[test]
+fn http2_only_conflicts_with_http1_alpn() {
+    let emulation = || {
+        EmulationProvider::builder()
+            .tls_config(
+                TlsConfig::builder()
+                    .alpn_protos(&[AlpnProtocol::HTTP1])
+                    .build(),
+            )
+            .build()
+            .unwrap()
+    };
+
+    Client::builder().emulation(emulation()).build().unwrap();
+    let err = Client::builder()
+        .emulation(emulation())
+        .http2_only()
+        .build()
+        .unwrap_err();
+    assert_eq!(err.emulation_conflicts().unwrap().problems().len(), 1);
+}
+
+#[test]
+fn lenient_emulation_without_tls_keeps_checks() {
+    let http1_only = || {
+        TlsConfig::builder()
+            .alpn_protos(&[AlpnProtocol::HTTP1])
+            .build()
+    };
+    let strict = EmulationProvider::builder()
+        .tls_config(http1_only())
+        .build()
+        .unwrap();
+    let lenient = || {
+        EmulationProvider::builder()
+            .tls_config(http1_only())
+            .http2_config(Http2Config::builder().build())
+            .strict(false)
+            .build()
+            .unwrap()
+    };
+
+    // the TLS configuration checked is not the one of the lenient emulation
+    let err = Client::builder()
+        .emulation(strict)
+        .emulation_with(lenient(), EmulationApply::HTTP2)
+        .http2_only()
+        .build()
+        .unwrap_err();
+    assert_eq!(err.emulation_conflicts().unwrap().problems().len(), 1);
+
+    Client::builder()
+        .emulation_with(lenient(), EmulationApply::TLS)
+        .http2_only()
+        .build()
+        .unwrap();
+}
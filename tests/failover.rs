@@ -0,0 +1,159 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use boring2::ssl::{SslAcceptor, SslFiletype, SslMethod};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use wreq::{
+    Client, EmulationProvider, EmulationSelection, FailoverConfig, FailureContext, FailureKind,
+    tls::{TlsConfig, TlsVersion},
+};
+
+const TLS_AES_128_GCM_SHA256: u16 = 0x1301;
+
+/// Returns whether the ClientHello waiting on `io` offers TLS 1.3, without consuming it.
+async fn offers_tls13(io: &TcpStream) -> bool {
+    let mut record = vec![0; 16 * 1024];
+    let hello = loop {
+        let n = io.peek(&mut record).await.unwrap();
+        if n >= 5 && n >= 5 + usize::from(u16::from_be_bytes([record[3], record[4]])) {
+            break &record[5..n];
+        }
+    };
+
+    // handshake header, legacy version, random and session id
+    let hello = &hello[38..];
+    let hello = &hello[1 + usize::from(hello[0])..];
+    let len = usize::from(u16::from_be_bytes([hello[0], hello[1]]));
+    hello[2..2 + len]
+        .chunks(2)
+        .any(|suite| u16::from_be_bytes([suite[0], suite[1]]) == TLS_AES_128_GCM_SHA256)
+}
+
+/// Serves a single empty response per connection, resetting the handshakes not offering TLS 1.3
+/// unless `accept_all` is set.
+async fn server(accept_all: Arc<AtomicBool>) -> SocketAddr {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/support/crl");
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor
+        .set_certificate_chain_file(format!("{dir}/valid.pem"))
+        .unwrap();
+    acceptor
+        .set_private_key_file(format!("{dir}/valid.key"), SslFiletype::PEM)
+        .unwrap();
+    let acceptor = acceptor.build();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (io, _) = listener.accept().await.unwrap();
+            let acceptor = acceptor.clone();
+            let accept_all = accept_all.clone();
+            tokio::spawn(async move {
+                if !accept_all.load(Ordering::SeqCst) && !offers_tls13(&io).await {
+                    // closing with the ClientHello unread resets the connection
+                    drop(io);
+                    return;
+                }
+                if let Ok(mut stream) = tokio_boring2::accept(&acceptor, io).await {
+                    let mut buf = [0; 1024];
+                    let _ = stream.read(&mut buf).await;
+                    let _ = stream
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: 0\r\n\r\n",
+                        )
+                        .await;
+                    let _ = stream.shutdown().await;
+                }
+            });
+        }
+    });
+    addr
+}
+
+fn profile(max_tls_version: TlsVersion) -> EmulationProvider {
+    EmulationProvider::builder()
+        .tls_config(
+            TlsConfig::builder()
+                .max_tls_version(max_tls_version)
+                .build(),
+        )
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn profile_failover_fails_over_and_recovers() {
+    const COOLDOWN: Duration = Duration::from_millis(300);
+
+    let accept_all = Arc::new(AtomicBool::new(false));
+    let addr = server(accept_all.clone()).await;
+    let kinds = Arc::new(Mutex::new(Vec::new()));
+    let client = Client::builder()
+        .profile_failover(FailoverConfig {
+            // the server resets the primary, which does not offer TLS 1.3
+            profiles: vec![profile(TlsVersion::TLS_1_2), profile(TlsVersion::TLS_1_3)],
+            trigger: {
+                let kinds = kinds.clone();
+                Arc::new(move |ctx: &FailureContext<'_>| {
+                    kinds.lock().unwrap().push(ctx.kind());
+                    ctx.is_blocked()
+                })
+            },
+            cooldown: COOLDOWN,
+        })
+        .cert_verification(false)
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap();
+    let url = format!("https://127.0.0.1:{}/", addr.port());
+    let origin = format!("https://127.0.0.1:{}", addr.port());
+
+    let send = || async {
+        client
+            .get(&url)
+            .send()
+            .await
+            .map(|res| res.extensions().get::<EmulationSelection>().unwrap().0)
+    };
+
+    // blocked, the origin fails over to the fallback
+    assert!(send().await.is_err());
+    assert_eq!(
+        kinds.lock().unwrap().pop(),
+        Some(Some(FailureKind::HandshakeReset))
+    );
+    let stats = client.profile_stats();
+    assert_eq!(stats.len(), 1);
+    assert_eq!(stats[0].origin(), origin);
+    assert_eq!(stats[0].profile(), 1);
+    assert_eq!(stats[0].failovers(), 1);
+    assert_eq!(send().await.unwrap(), 1);
+
+    // after the cooldown, the primary is probed again, and still blocked
+    tokio::time::sleep(COOLDOWN).await;
+    assert!(send().await.is_err());
+    assert_eq!(client.profile_stats()[0].profile(), 1);
+    assert_eq!(client.profile_stats()[0].failovers(), 1);
+    assert_eq!(send().await.unwrap(), 1);
+
+    // once the primary is accepted again, the origin recovers
+    accept_all.store(true, Ordering::SeqCst);
+    tokio::time::sleep(COOLDOWN).await;
+    assert_eq!(send().await.unwrap(), 0);
+    let stats = client.profile_stats();
+    assert_eq!(stats[0].profile(), 0);
+    assert_eq!(stats[0].recoveries(), 1);
+    assert_eq!(send().await.unwrap(), 0);
+    assert_eq!(kinds.lock().unwrap().pop(), Some(None));
+}
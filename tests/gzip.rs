@@ -361,3 +361,165 @@ async fn test_chunked_fragmented_response_with_extra_bytes() {
     assert!(err.is_decode());
     assert!(start.elapsed() >= DELAY_BETWEEN_RESPONSE_PARTS - DELAY_MARGIN);
 }
+
+#[tokio::test]
+async fn test_salvage_partial_body_on_truncated_stream() {
+    let content: String = (0..10_000).map(|i| format!("{i} ")).collect();
+    let gzipped_content = gzip_compress(content.as_bytes());
+    let truncated = gzipped_content[..gzipped_content.len() * 9 / 10].to_vec();
+
+    let server = server::low_level_with_response(move |_raw_request, client_socket| {
+        let truncated = truncated.clone();
+        Box::new(async move {
+            let content_length_header =
+                format!("Content-Length: {}\r\n\r\n", truncated.len()).into_bytes();
+            let response = [
+                COMPRESSED_RESPONSE_HEADERS,
+                &content_length_header,
+                &truncated,
+            ]
+            .concat();
+
+            client_socket
+                .write_all(response.as_slice())
+                .await
+                .expect("response write_all failed");
+            client_socket.flush().await.expect("response flush failed");
+        })
+    });
+
+    let client = wreq::Client::new();
+
+    let err = client
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .await
+        .expect("response")
+        .bytes()
+        .await
+        .unwrap_err();
+    assert!(err.is_decode());
+
+    let mut res = client
+        .get(format!("http://{}/", server.addr()))
+        .salvage_partial_body(true)
+        .send()
+        .await
+        .expect("response");
+
+    let mut body = Vec::new();
+    while let Some(chunk) = res.chunk().await.expect("chunk") {
+        body.extend_from_slice(&chunk);
+    }
+    assert!(!body.is_empty());
+    assert!(content.as_bytes().starts_with(&body));
+
+    let partial = res.extensions().get::<wreq::PartialBody>().unwrap();
+    assert_eq!(partial.decoded_bytes(), body.len() as u64);
+    assert!(partial.error().is_decode());
+}
+
+#[tokio::test]
+async fn test_drain_skips_decompression() {
+    let content = vec![b'a'; 64 * 1024];
+    let gzipped_content = gzip_compress(&content);
+    let wire_len = gzipped_content.len() as u64;
+    // a body that fails to decompress is drained all the same
+    let corrupted = [&gzipped_content[..16], &[0xff; 64][..]].concat();
+
+    let server = server::low_level_with_response(move |raw_request, client_socket| {
+        let body = if raw_request.starts_with(b"GET /corrupted") {
+            corrupted.clone()
+        } else {
+            gzipped_content.clone()
+        };
+        Box::new(async move {
+            let content_length_header =
+                format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes();
+            let response = [COMPRESSED_RESPONSE_HEADERS, &content_length_header, &body].concat();
+
+            client_socket
+                .write_all(response.as_slice())
+                .await
+                .expect("response write_all failed");
+            client_socket.flush().await.expect("response flush failed");
+        })
+    });
+
+    let client = wreq::Client::new();
+
+    let res = client
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .await
+        .expect("response");
+    assert_eq!(res.drain().await.expect("drain"), wire_len);
+    assert!(wire_len < content.len() as u64);
+
+    let res = client
+        .get(format!("http://{}/corrupted", server.addr()))
+        .send()
+        .await
+        .expect("response");
+    assert_eq!(res.drain().await.expect("drain"), 80);
+}
+
+/// Returns the 99th percentile of how late a task waking up every millisecond runs, while
+/// a highly compressed body is decompressed on the same single-threaded runtime.
+async fn probe_lateness_p99(url: &str, budget: usize, len: usize) -> std::time::Duration {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        time::{Duration, Instant},
+    };
+
+    let done = Arc::new(AtomicBool::new(false));
+    let probe = tokio::spawn({
+        let done = done.clone();
+        async move {
+            let mut lateness = Vec::new();
+            while !done.load(Ordering::Relaxed) {
+                let start = Instant::now();
+                tokio::time::sleep(Duration::from_millis(1)).await;
+                lateness.push(start.elapsed().saturating_sub(Duration::from_millis(1)));
+            }
+            lateness
+        }
+    });
+
+    let client = wreq::Client::builder()
+        .body_poll_budget(budget)
+        .no_proxy()
+        .build()
+        .unwrap();
+    let body = client.get(url).send().await.unwrap().bytes().await.unwrap();
+    assert_eq!(body.len(), len);
+
+    done.store(true, Ordering::Relaxed);
+    let mut lateness = probe.await.unwrap();
+    lateness.sort();
+    lateness[lateness.len() * 99 / 100]
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn test_body_poll_budget_lets_other_tasks_run() {
+    const LEN: usize = 200 * 1024 * 1024;
+
+    let gzipped = gzip_compress(&vec![0; LEN]);
+    let server = server::http(move |_| {
+        let gzipped = gzipped.clone();
+        async move {
+            http::Response::builder()
+                .header("content-encoding", "gzip")
+                .body(gzipped.into())
+                .unwrap()
+        }
+    });
+    let url = format!("http://{}/gzip", server.addr());
+
+    let unbounded = probe_lateness_p99(&url, 0, LEN).await;
+    let bounded = probe_lateness_p99(&url, 512 * 1024, LEN).await;
+    assert!(bounded < unbounded, "{bounded:?} >= {unbounded:?}");
+}
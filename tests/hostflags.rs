@@ -0,0 +1,82 @@
+use std::net::SocketAddr;
+
+use boring2::ssl::{SslAcceptor, SslFiletype, SslMethod};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use wreq::{
+    Client, EmulationProvider,
+    tls::{CertStore, HostFlags, TlsConfig},
+};
+
+const CA: &[u8] = include_bytes!("support/hostflags/ca.pem");
+
+/// Serves a single empty response per connection, with a certificate for `f*.example.com`.
+async fn server() -> SocketAddr {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/support/hostflags");
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor
+        .set_certificate_chain_file(format!("{dir}/partial_wildcard.pem"))
+        .unwrap();
+    acceptor
+        .set_private_key_file(format!("{dir}/partial_wildcard.key"), SslFiletype::PEM)
+        .unwrap();
+    let acceptor = acceptor.build();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (io, _) = listener.accept().await.unwrap();
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                if let Ok(mut stream) = tokio_boring2::accept(&acceptor, io).await {
+                    let mut buf = [0; 1024];
+                    let _ = stream.read(&mut buf).await;
+                    let _ = stream
+                        .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                        .await;
+                }
+            });
+        }
+    });
+    addr
+}
+
+async fn get(addr: SocketAddr, flags: Option<HostFlags>) -> wreq::Result<wreq::Response> {
+    let emulation = EmulationProvider::builder()
+        .tls_config(TlsConfig::builder().verify_hostflags(flags).build())
+        .build()
+        .unwrap();
+
+    let client = Client::builder()
+        .emulation(emulation)
+        .cert_store(CertStore::from_pem_certs([CA]).unwrap())
+        .resolve("foo.example.com", addr)
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    client
+        .get(format!("https://foo.example.com:{}/", addr.port()))
+        .send()
+        .await
+}
+
+#[tokio::test]
+async fn partial_wildcard_follows_hostflags() {
+    let addr = server().await;
+
+    let res = get(addr, Some(HostFlags::empty())).await.unwrap();
+    assert_eq!(res.status(), wreq::StatusCode::OK);
+
+    let err = get(addr, Some(HostFlags::NO_PARTIAL_WILDCARDS))
+        .await
+        .unwrap_err();
+    assert!(err.is_connect());
+
+    // Partial wildcards are rejected by default
+    get(addr, None).await.unwrap_err();
+}
@@ -0,0 +1,263 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use boring2::{
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{PKey, Private},
+    rsa::Padding,
+    sign::{RsaPssSaltlen, Signer},
+    ssl::{SslAcceptor, SslFiletype, SslMethod, SslVerifyMode},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use wreq::{
+    Client, Identity,
+    tls::{SignFuture, SignatureAlgorithm, TlsSigner},
+};
+
+const CERT: &[u8] = include_bytes!("support/identity/cert.pem");
+const KEY: &[u8] = include_bytes!("support/identity/key.pem");
+const OTHER_CERT: &[u8] = include_bytes!("support/identity/other.cert.pem");
+const OTHER_KEY: &[u8] = include_bytes!("support/identity/other.key.pem");
+
+/// Serves responses with the common name of the client certificate of the connection as body.
+async fn server() -> SocketAddr {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/support/identity");
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor
+        .set_certificate_chain_file(format!("{dir}/cert.pem"))
+        .unwrap();
+    acceptor
+        .set_private_key_file(format!("{dir}/key.pem"), SslFiletype::PEM)
+        .unwrap();
+    acceptor.set_verify_callback(
+        SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT,
+        |_, _| true,
+    );
+    let acceptor = acceptor.build();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (io, _) = listener.accept().await.unwrap();
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                let Ok(mut stream) = tokio_boring2::accept(&acceptor, io).await else {
+                    return;
+                };
+                let name = stream
+                    .ssl()
+                    .peer_certificate()
+                    .and_then(|cert| {
+                        let entry = cert.subject_name().entries_by_nid(Nid::COMMONNAME).next()?;
+                        Some(entry.data().as_utf8().ok()?.to_string())
+                    })
+                    .unwrap_or_default();
+
+                let mut buf = [0; 1024];
+                while matches!(stream.read(&mut buf).await, Ok(n) if n > 0) {
+                    let res = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{name}",
+                        name.len()
+                    );
+                    if stream.write_all(res.as_bytes()).await.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    addr
+}
+
+async fn client_name(client: &Client, host: &str, addr: SocketAddr) -> String {
+    client
+        .get(format!("https://{host}:{}/", addr.port()))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn identity_for_selects_identity_by_host() {
+    let addr = server().await;
+
+    let client = Client::builder()
+        .identity(Identity::from_pem_parts(CERT, KEY).unwrap())
+        .identity_for(
+            "*.mtls.test",
+            Identity::from_pem_parts(OTHER_CERT, OTHER_KEY).unwrap(),
+        )
+        .cert_verification(false)
+        .resolve("a.mtls.test", addr)
+        .resolve("mtls.test", addr)
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    for _ in 0..2 {
+        assert_eq!(
+            client_name(&client, "a.mtls.test", addr).await,
+            "wreq other"
+        );
+        assert_eq!(client_name(&client, "mtls.test", addr).await, "wreq client");
+    }
+}
+
+#[tokio::test]
+async fn identity_for_without_global_identity() {
+    let addr = server().await;
+
+    let client = Client::builder()
+        .identity_for(
+            "a.mtls.test",
+            Identity::from_pem_parts(OTHER_CERT, OTHER_KEY).unwrap(),
+        )
+        .cert_verification(false)
+        .resolve("a.mtls.test", addr)
+        .resolve("b.mtls.test", addr)
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        client_name(&client, "a.mtls.test", addr).await,
+        "wreq other"
+    );
+
+    // the server requires a certificate, which is not sent for other hosts
+    let err = client
+        .get(format!("https://b.mtls.test:{}/", addr.port()))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_connect() || err.is_request(), "{err:?}");
+}
+
+#[test]
+fn identity_for_rejects_invalid_pattern() {
+    let err = Client::builder()
+        .identity_for(
+            "a.*.test",
+            Identity::from_pem_parts(OTHER_CERT, OTHER_KEY).unwrap(),
+        )
+        .build()
+        .unwrap_err();
+    assert!(err.is_builder());
+}
+
+#[tokio::test]
+async fn connect_timing_includes_tls_handshake() {
+    let addr = server().await;
+
+    let client = Client::builder()
+        .identity(Identity::from_pem_parts(CERT, KEY).unwrap())
+        .cert_verification(false)
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let res = client
+        .get(format!("https://127.0.0.1:{}/", addr.port()))
+        .send()
+        .await
+        .unwrap();
+    let timing = *res.extensions().get::<wreq::ConnectTiming>().unwrap();
+    assert!(!timing.reused());
+    assert!(timing.tls().is_some_and(|tls| !tls.is_zero()));
+}
+
+/// Signs with an in-memory RSA key, counting the signatures, or fails every signature.
+struct CountingSigner {
+    key: Option<PKey<Private>>,
+    signatures: AtomicUsize,
+}
+
+impl TlsSigner for CountingSigner {
+    fn sign(&self, algorithm: SignatureAlgorithm, message: &[u8]) -> SignFuture {
+        self.signatures.fetch_add(1, Ordering::SeqCst);
+        let Some(ref key) = self.key else {
+            return Box::pin(async { Err("signer unavailable".into()) });
+        };
+
+        let digest = match algorithm {
+            SignatureAlgorithm::RSA_PSS_RSAE_SHA256 => MessageDigest::sha256(),
+            SignatureAlgorithm::RSA_PSS_RSAE_SHA384 => MessageDigest::sha384(),
+            SignatureAlgorithm::RSA_PSS_RSAE_SHA512 => MessageDigest::sha512(),
+            _ => return Box::pin(async { Err("unexpected signature algorithm".into()) }),
+        };
+        let mut signer = Signer::new(digest, key).unwrap();
+        signer.set_rsa_padding(Padding::PKCS1_PSS).unwrap();
+        signer
+            .set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)
+            .unwrap();
+        signer.update(message).unwrap();
+        let signature = signer.sign_to_vec().unwrap();
+
+        // complete later, as a remote signer would
+        Box::pin(async move {
+            tokio::task::yield_now().await;
+            Ok(signature)
+        })
+    }
+}
+
+#[tokio::test]
+async fn external_signer_signs_handshake() {
+    let addr = server().await;
+
+    let signer = Arc::new(CountingSigner {
+        key: Some(PKey::private_key_from_pem(KEY).unwrap()),
+        signatures: AtomicUsize::new(0),
+    });
+    let client = Client::builder()
+        .identity(Identity::from_external_signer(CERT, signer.clone()).unwrap())
+        .cert_verification(false)
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    assert_eq!(client_name(&client, "127.0.0.1", addr).await, "wreq client");
+    assert_eq!(signer.signatures.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn external_signer_failure_fails_handshake() {
+    let addr = server().await;
+
+    let signer = Arc::new(CountingSigner {
+        key: None,
+        signatures: AtomicUsize::new(0),
+    });
+    let client = Client::builder()
+        .identity(Identity::from_external_signer(CERT, signer.clone()).unwrap())
+        .cert_verification(false)
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let err = client
+        .get(format!("https://127.0.0.1:{}/", addr.port()))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_connect(), "{err:?}");
+    assert_eq!(signer.signatures.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,58 @@
+mod support;
+
+use std::time::Duration;
+
+use support::server;
+use wreq::{Client, KeepaliveOutcome};
+
+fn client() -> Client {
+    Client::builder().http2_only().no_proxy().build().unwrap()
+}
+
+#[tokio::test]
+async fn keepalive_pings_http2_connection() {
+    let server = server::http(move |_req| async { http::Response::default() });
+    let client = client();
+
+    let res = client
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .await
+        .unwrap();
+    res.bytes().await.unwrap();
+
+    let pooled = client.pooled_connections();
+    assert_eq!(pooled.len(), 1);
+    let conn = &pooled[0];
+    assert_eq!(conn.version(), http::Version::HTTP_2);
+    assert_eq!(conn.authority().as_str(), server.addr().to_string());
+
+    let outcome = client.keepalive(conn.id(), Duration::from_secs(5)).await;
+    assert!(matches!(outcome, KeepaliveOutcome::Acked(_)), "{outcome:?}");
+    assert_eq!(client.pooled_connections().len(), 1);
+}
+
+#[tokio::test]
+async fn keepalive_evicts_closed_connection() {
+    let server = server::http(move |_req| async { http::Response::default() });
+    let client = client();
+
+    let res = client
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .await
+        .unwrap();
+    res.bytes().await.unwrap();
+    let id = client.pooled_connections()[0].id();
+
+    // shutting the server down closes its connections
+    drop(server);
+
+    let outcome = client.keepalive(id, Duration::from_secs(1)).await;
+    assert_eq!(outcome, KeepaliveOutcome::Evicted);
+    assert!(client.pooled_connections().is_empty());
+    assert_eq!(
+        client.keepalive(id, Duration::from_secs(1)).await,
+        KeepaliveOutcome::NotPooled
+    );
+}
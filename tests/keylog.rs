@@ -0,0 +1,89 @@
+use std::{collections::HashSet, net::SocketAddr, path::Path, time::Duration};
+
+use boring2::ssl::{SslAcceptor, SslFiletype, SslMethod};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use wreq::{Client, tls::KeyLogPolicy};
+
+/// Serves a single empty response per connection.
+async fn server() -> SocketAddr {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/support/identity");
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor
+        .set_certificate_chain_file(format!("{dir}/cert.pem"))
+        .unwrap();
+    acceptor
+        .set_private_key_file(format!("{dir}/key.pem"), SslFiletype::PEM)
+        .unwrap();
+    let acceptor = acceptor.build();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (io, _) = listener.accept().await.unwrap();
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                if let Ok(mut stream) = tokio_boring2::accept(&acceptor, io).await {
+                    let mut buf = [0; 1024];
+                    let _ = stream.read(&mut buf).await;
+                    let _ = stream
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: 0\r\n\r\n",
+                        )
+                        .await;
+                    let _ = stream.shutdown().await;
+                }
+            });
+        }
+    });
+    addr
+}
+
+/// Returns the client randoms of the connections logged to `path`, once any was.
+async fn logged_connections(path: &Path) -> HashSet<String> {
+    for _ in 0..50 {
+        let lines = std::fs::read_to_string(path).unwrap_or_default();
+        let randoms: HashSet<_> = lines
+            .lines()
+            .filter_map(|line| line.split(' ').nth(1))
+            .map(str::to_owned)
+            .collect();
+        if !randoms.is_empty() {
+            return randoms;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    HashSet::new()
+}
+
+#[tokio::test]
+async fn request_keylog_only_logs_its_connection() {
+    let addr = server().await;
+    let path = std::env::temp_dir().join(format!("wreq-keylog-{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let client = Client::builder()
+        .cert_verification(false)
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap();
+    let url = format!("https://127.0.0.1:{}/", addr.port());
+
+    client.get(&url).send().await.unwrap();
+    assert!(!path.exists());
+
+    client
+        .get(&url)
+        .keylog(KeyLogPolicy::File(path.clone()))
+        .send()
+        .await
+        .unwrap();
+
+    // keys are written in order, those of the first connection would come first
+    assert_eq!(logged_connections(&path).await.len(), 1);
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,143 @@
+mod support;
+
+use std::net::SocketAddr;
+
+use support::server;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use wreq::{
+    Client, DigestAlgorithm, ExpectedDigest, Url,
+    mirrors::{MirrorOptions, MirrorOutcome},
+};
+
+/// The SHA-256 digest of `hello world`.
+const HELLO_WORLD: [u8; 32] = [
+    0xb9, 0x4d, 0x27, 0xb9, 0x93, 0x4d, 0x3e, 0x08, 0xa5, 0x2e, 0x52, 0xd7, 0xda, 0x7d, 0xab, 0xfa,
+    0xc4, 0x84, 0xef, 0xe3, 0x7a, 0x53, 0x80, 0xee, 0x90, 0x88, 0xf7, 0xac, 0xe2, 0xef, 0xcd, 0xe9,
+];
+
+fn client() -> Client {
+    Client::builder().no_proxy().build().unwrap()
+}
+
+fn url(addr: SocketAddr) -> Url {
+    Url::parse(&format!("http://{addr}/release")).unwrap()
+}
+
+/// Returns the address of a mirror that is down.
+async fn down() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    listener.local_addr().unwrap()
+}
+
+/// Serves the raw responses of `respond` to the head of each request.
+async fn raw_server(respond: fn(&str) -> &'static [u8]) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut io, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let n = io.read(&mut buf).await.unwrap();
+            let head = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+            let _ = io.write_all(respond(&head)).await;
+            let _ = io.shutdown().await;
+        }
+    });
+    addr
+}
+
+#[tokio::test]
+async fn good_mirror_wins_over_down_and_corrupt_ones() {
+    let down = down().await;
+    let corrupt = server::http(move |_req| async { http::Response::new("hello worle".into()) });
+    let good = server::http(move |_req| async { http::Response::new("hello world".into()) });
+
+    let download = client()
+        .download_from_mirrors(
+            vec![url(down), url(corrupt.addr()), url(good.addr())],
+            MirrorOptions::new()
+                .expected_digest(ExpectedDigest::new(DigestAlgorithm::Sha256, HELLO_WORLD)),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(download.body(), "hello world");
+    assert_eq!(download.mirror(), &url(good.addr()));
+
+    // the failures fail over right away, without using the second attempt of their mirror
+    let attempts = download.attempts();
+    assert_eq!(attempts.len(), 3);
+    assert_eq!(attempts[0].mirror(), &url(down));
+    assert!(matches!(attempts[0].outcome(), MirrorOutcome::Failed(err) if err.is_connect()));
+    assert_eq!(attempts[1].mirror(), &url(corrupt.addr()));
+    assert!(matches!(
+        attempts[1].outcome(),
+        MirrorOutcome::Failed(err) if err.digest_mismatch().is_some()
+    ));
+    assert_eq!(attempts[2].mirror(), &url(good.addr()));
+    assert!(matches!(attempts[2].outcome(), MirrorOutcome::Served));
+}
+
+#[tokio::test]
+async fn interrupted_download_resumes_on_the_next_mirror() {
+    // cuts the content after `hello`
+    let first =
+        raw_server(|_| b"HTTP/1.1 200 OK\r\netag: \"v1\"\r\ncontent-length: 11\r\n\r\nhello").await;
+    // serves the same content, with the same `ETag`
+    let second = raw_server(|head| {
+        if head.contains("range: bytes=5-") && head.contains("if-range: \"v1\"") {
+            b"HTTP/1.1 206 Partial Content\r\netag: \"v1\"\r\ncontent-range: bytes 5-10/11\r\n\
+              content-length: 6\r\n\r\n world"
+        } else {
+            b"HTTP/1.1 500 Internal Server Error\r\ncontent-length: 0\r\n\r\n"
+        }
+    })
+    .await;
+
+    let download = client()
+        .download_from_mirrors(
+            vec![url(first), url(second)],
+            MirrorOptions::new()
+                .attempts_per_mirror(1)
+                .expected_digest(ExpectedDigest::new(DigestAlgorithm::Sha256, HELLO_WORLD)),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(download.body(), "hello world");
+    assert_eq!(download.mirror(), &url(second));
+    let attempts = download.attempts();
+    assert_eq!(attempts.len(), 2);
+    assert_eq!(attempts[0].offset(), 0);
+    assert!(
+        matches!(attempts[0].outcome(), MirrorOutcome::Failed(err) if err.incomplete_body().is_some())
+    );
+    assert_eq!(attempts[1].offset(), 5);
+    assert!(matches!(attempts[1].outcome(), MirrorOutcome::Served));
+}
+
+#[tokio::test]
+async fn exhausted_mirrors_report_their_attempts() {
+    let down = down().await;
+    let missing = server::http(move |_req| async {
+        http::Response::builder()
+            .status(http::StatusCode::NOT_FOUND)
+            .body(Default::default())
+            .unwrap()
+    });
+
+    let err = client()
+        .download_from_mirrors(vec![url(down), url(missing.addr())], MirrorOptions::new())
+        .await
+        .unwrap_err();
+
+    let attempts = err.mirrors_exhausted().unwrap().attempts();
+    assert_eq!(attempts.len(), 2);
+    assert!(matches!(
+        attempts[1].outcome(),
+        MirrorOutcome::Failed(err) if err.status() == Some(http::StatusCode::NOT_FOUND)
+    ));
+}
@@ -0,0 +1,115 @@
+mod support;
+
+use http::Version;
+use support::server;
+use tower::layer::util::Identity;
+
+#[tokio::test]
+async fn http1_over_tcp() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.version(), Version::HTTP_11);
+        http::Response::new("hello".into())
+    });
+
+    let res = wreq::Client::builder()
+        .no_proxy()
+        .build()
+        .unwrap()
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.version(), Version::HTTP_11);
+    assert_eq!(res.text().await.unwrap(), "hello");
+}
+
+#[tokio::test]
+async fn http2_over_tcp() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.version(), Version::HTTP_2);
+        http::Response::default()
+    });
+
+    let res = wreq::Client::builder()
+        .http2_only()
+        .no_proxy()
+        .build()
+        .unwrap()
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.version(), Version::HTTP_2);
+}
+
+#[tokio::test]
+async fn middleware_over_tcp() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let res = wreq::Client::builder()
+        .layer(Identity::new())
+        .no_proxy()
+        .build()
+        .unwrap()
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), wreq::StatusCode::OK);
+}
+
+#[cfg(feature = "cookies")]
+#[tokio::test]
+async fn cookies_over_tcp() {
+    let server = server::http(move |req| async move {
+        if req.uri() == "/2" {
+            assert_eq!(req.headers()["cookie"], "key=val");
+        }
+        http::Response::builder()
+            .header("Set-Cookie", "key=val; HttpOnly")
+            .body(Default::default())
+            .unwrap()
+    });
+
+    let client = wreq::Client::builder()
+        .cookie_store(true)
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    client
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .await
+        .unwrap();
+    client
+        .get(format!("http://{}/2", server.addr()))
+        .send()
+        .await
+        .unwrap();
+}
+
+#[cfg(not(feature = "tls"))]
+#[tokio::test]
+async fn https_is_rejected() {
+    let err = wreq::Client::builder()
+        .no_proxy()
+        .build()
+        .unwrap()
+        .get("https://127.0.0.1:1/")
+        .send()
+        .await
+        .unwrap_err();
+
+    assert!(err.is_builder());
+    assert_eq!(
+        err.url().map(|url| url.as_str()),
+        Some("https://127.0.0.1:1/")
+    );
+
+    let source = std::error::Error::source(&err).unwrap();
+    assert!(source.to_string().contains("`tls` feature"));
+}
@@ -0,0 +1,144 @@
+mod support;
+
+use std::net::SocketAddr;
+
+use support::server;
+use tokio::{io::AsyncWriteExt, net::TcpListener};
+use wreq::{Client, ConnectTiming, ProbeDepth};
+
+fn client() -> Client {
+    Client::builder()
+        .resolve("probe.test", "127.0.0.1:0".parse().unwrap())
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap()
+}
+
+async fn reused(client: &Client, url: &str) -> bool {
+    let res = client.get(url).send().await.unwrap();
+    let reused = res.extensions().get::<ConnectTiming>().unwrap().reused();
+    res.bytes().await.unwrap();
+    reused
+}
+
+#[tokio::test]
+async fn probe_dns_reports_overridden_addrs() {
+    let report = client()
+        .probe("http://probe.test:8080/", ProbeDepth::Dns)
+        .send()
+        .await
+        .unwrap();
+
+    assert!(report.is_success());
+    assert_eq!(report.addrs(), ["127.0.0.1:8080".parse().unwrap()]);
+    assert!(report.dns().is_some());
+    assert_eq!(report.tcp(), None);
+}
+
+#[tokio::test]
+async fn probe_http_does_not_pool_connection() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.method(), "HEAD");
+        http::Response::default()
+    });
+    let client = client();
+    let url = format!("http://probe.test:{}/", server.addr().port());
+
+    let report = client.probe(&url, ProbeDepth::Http).send().await.unwrap();
+    assert!(report.is_success(), "{:?}", report.error());
+    assert_eq!(report.status(), Some(http::StatusCode::OK));
+    assert_eq!(report.version(), Some(http::Version::HTTP_11));
+    assert_eq!(report.remote_addr(), Some(server.addr()));
+    assert!(report.tcp().is_some());
+    assert!(report.http().is_some());
+    assert_eq!(report.tls(), None);
+
+    assert!(!reused(&client, &url).await);
+}
+
+#[tokio::test]
+async fn probe_keep_pools_connection() {
+    let server = server::http(move |_req| async { http::Response::default() });
+    let client = client();
+    let url = format!("http://probe.test:{}/", server.addr().port());
+
+    let report = client
+        .probe(&url, ProbeDepth::Http)
+        .keep(true)
+        .send()
+        .await
+        .unwrap();
+    assert!(report.is_success(), "{:?}", report.error());
+
+    assert!(reused(&client, &url).await);
+}
+
+#[tokio::test]
+async fn probe_attributes_failed_handshake_to_tls() {
+    // accepts connections, but answers the handshake with garbage
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut io, _) = listener.accept().await.unwrap();
+            let _ = io.write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n").await;
+        }
+    });
+
+    let report = client()
+        .probe(
+            format!("https://probe.test:{}/", addr.port()),
+            ProbeDepth::Http,
+        )
+        .send()
+        .await
+        .unwrap();
+
+    assert!(!report.is_success());
+    assert_eq!(report.failed_phase(), Some(ProbeDepth::Tls));
+    assert!(report.error().unwrap().is_connect());
+    assert_eq!(report.status(), None);
+
+    // a TCP probe stops before the handshake
+    let report = client()
+        .probe(
+            format!("https://probe.test:{}/", addr.port()),
+            ProbeDepth::Tcp,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert!(report.is_success(), "{:?}", report.error());
+    assert_eq!(report.remote_addr(), Some(addr));
+    assert_eq!(report.tls(), None);
+}
+
+#[tokio::test]
+async fn probe_attributes_refused_connection_to_tcp() {
+    let addr: SocketAddr = {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap()
+    };
+
+    let report = client()
+        .probe(format!("http://{addr}/"), ProbeDepth::Http)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(report.failed_phase(), Some(ProbeDepth::Tcp));
+    assert!(report.error().unwrap().is_connect());
+    assert_eq!(report.addrs(), [addr]);
+    assert_eq!(report.tcp(), None);
+}
+
+#[tokio::test]
+async fn probe_rejects_bad_scheme() {
+    let err = client()
+        .probe("ftp://probe.test/", ProbeDepth::Dns)
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_builder());
+}
@@ -1,7 +1,19 @@
 mod support;
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
 use http_body_util::BodyExt;
 use support::server;
-use wreq::{Body, redirect::Policy};
+use wreq::{
+    Body,
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect::Policy,
+};
 
 #[tokio::test]
 async fn test_redirect_301_and_302_and_303_changes_post_to_get() {
@@ -463,3 +475,53 @@ async fn test_redirect_301_302_303_empty_payload_headers() {
         );
     }
 }
+
+#[tokio::test]
+async fn test_redirect_hops_resolve_each_host_once() {
+    struct CountingResolver(AtomicUsize);
+
+    impl Resolve for CountingResolver {
+        fn resolve(&self, _: Name) -> Resolving {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let addrs: Addrs = Box::new(std::iter::once(SocketAddr::from(([127, 0, 0, 1], 0))));
+            Box::pin(std::future::ready(Ok(addrs)))
+        }
+    }
+
+    // a.test/1 -> b.test/2 -> a.test/3 -> b.test/4
+    let server = server::http(move |req| async move {
+        let host = req.headers()["host"].to_str().unwrap();
+        let port = host.rsplit(':').next().unwrap();
+        let location = match req.uri().path() {
+            "/1" => format!("http://b.test:{port}/2"),
+            "/2" => format!("http://a.test:{port}/3"),
+            "/3" => format!("http://b.test:{port}/4"),
+            _ => return http::Response::new(Body::default()),
+        };
+        http::Response::builder()
+            .status(302)
+            .header("location", location)
+            .body(Body::default())
+            .unwrap()
+    });
+
+    let resolver = Arc::new(CountingResolver(AtomicUsize::new(0)));
+    let client = wreq::Client::builder()
+        .dns_resolver(resolver.clone())
+        .redirect(Policy::default())
+        .pool_max_idle_per_host(0)
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let url = format!("http://a.test:{}/1", server.addr().port());
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), wreq::StatusCode::OK);
+    assert_eq!(res.url().path(), "/4");
+    assert_eq!(resolver.0.load(Ordering::SeqCst), 2);
+
+    // The resolutions are not shared with subsequent requests.
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), wreq::StatusCode::OK);
+    assert_eq!(resolver.0.load(Ordering::SeqCst), 4);
+}
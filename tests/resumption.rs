@@ -0,0 +1,291 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use boring2::ssl::{SslAcceptor, SslFiletype, SslMethod};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use wreq::{
+    Client, ConnectTiming, EmulationProvider, RequestAttempt,
+    tls::{EarlyData, TlsConfig, TlsConnector, TlsInfo},
+};
+
+fn acceptor(early_data: bool) -> SslAcceptor {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/support/identity");
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor
+        .set_certificate_chain_file(format!("{dir}/cert.pem"))
+        .unwrap();
+    acceptor
+        .set_private_key_file(format!("{dir}/key.pem"), SslFiletype::PEM)
+        .unwrap();
+    if early_data {
+        // SAFETY: the context is valid for the duration of the call.
+        unsafe { boring_sys2::SSL_CTX_set_early_data_enabled(acceptor.as_ptr(), 1) };
+    }
+    acceptor.build()
+}
+
+/// Serves responses with whether the session of their connection was resumed as body.
+///
+/// Connections are closed after a single response, unless `keep_alive` is set.
+async fn server(keep_alive: bool) -> SocketAddr {
+    let acceptor = acceptor(false);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (io, _) = listener.accept().await.unwrap();
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                if let Ok(mut stream) = tokio_boring2::accept(&acceptor, io).await {
+                    let reused = stream.ssl().session_reused().to_string();
+                    let connection = if keep_alive { "keep-alive" } else { "close" };
+                    let res = format!(
+                        "HTTP/1.1 200 OK\r\nconnection: {connection}\r\ncontent-length: {}\r\n\r\n{reused}",
+                        reused.len()
+                    );
+
+                    let mut buf = [0; 1024];
+                    while matches!(stream.read(&mut buf).await, Ok(n) if n > 0) {
+                        if stream.write_all(res.as_bytes()).await.is_err() || !keep_alive {
+                            break;
+                        }
+                    }
+                    let _ = stream.shutdown().await;
+                }
+            });
+        }
+    });
+    addr
+}
+
+/// Serves responses with whether their request was received as early data as body, closing
+/// connections after a single response, and returns the number of connections accepted.
+///
+/// The sessions of the first connection allow early data. With `reject`, the later connections
+/// are accepted with other session ticket keys, so resuming the session rejects early data.
+async fn early_data_server(reject: bool) -> (SocketAddr, Arc<AtomicUsize>) {
+    let first = acceptor(true);
+    let later = if reject {
+        acceptor(true)
+    } else {
+        first.clone()
+    };
+    let connections = Arc::new(AtomicUsize::new(0));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let accepted = connections.clone();
+    tokio::spawn(async move {
+        loop {
+            let (io, _) = listener.accept().await.unwrap();
+            let acceptor = if accepted.fetch_add(1, Ordering::SeqCst) == 0 {
+                first.clone()
+            } else {
+                later.clone()
+            };
+            tokio::spawn(async move {
+                if let Ok(mut stream) = tokio_boring2::accept(&acceptor, io).await {
+                    // set until the early data of the client was read
+                    let early = stream.ssl().in_early_data().to_string();
+                    let mut buf = [0; 1024];
+                    if matches!(stream.read(&mut buf).await, Ok(n) if n > 0) {
+                        let res = format!(
+                            "HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: {}\r\n\r\n{early}",
+                            early.len()
+                        );
+                        let _ = stream.write_all(res.as_bytes()).await;
+                    }
+                    let _ = stream.shutdown().await;
+                }
+            });
+        }
+    });
+    (addr, connections)
+}
+
+fn early_data_client() -> Client {
+    let emulation = EmulationProvider::builder()
+        .tls_config(
+            TlsConfig::builder()
+                .pre_shared_key(true)
+                .enable_early_data(true)
+                .build(),
+        )
+        .build()
+        .unwrap();
+    Client::builder()
+        .emulation(emulation)
+        .cert_verification(false)
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap()
+}
+
+async fn resumed(client: &Client, addr: SocketAddr, no_resume: bool) -> bool {
+    client
+        .get(format!("https://127.0.0.1:{}/", addr.port()))
+        .tls_no_resume(no_resume)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn tls_no_resume_forces_full_handshake() {
+    let addr = server(false).await;
+
+    let emulation = EmulationProvider::builder()
+        .tls_config(TlsConfig::builder().pre_shared_key(true).build())
+        .build()
+        .unwrap();
+    let client = Client::builder()
+        .emulation(emulation)
+        .cert_verification(false)
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    assert!(!resumed(&client, addr, false).await);
+    assert!(resumed(&client, addr, false).await);
+
+    assert!(!resumed(&client, addr, true).await);
+    let stats = client.tls_session_stats();
+    assert_eq!((stats.hits(), stats.misses()), (1, 1));
+
+    // the session of the full handshake was cached, and is resumed
+    assert!(resumed(&client, addr, false).await);
+}
+
+#[tokio::test]
+async fn tls_info_reports_session_reuse() {
+    async fn send(client: &Client, addr: SocketAddr) -> (bool, bool) {
+        let res = client
+            .get(format!("https://127.0.0.1:{}/", addr.port()))
+            .send()
+            .await
+            .unwrap();
+        let session_reused = res.extensions().get::<TlsInfo>().unwrap().session_reused();
+        let pooled = res.extensions().get::<ConnectTiming>().unwrap().reused();
+        assert_eq!(res.text().await.unwrap(), session_reused.to_string());
+        (session_reused, pooled)
+    }
+
+    let emulation = EmulationProvider::builder()
+        .tls_config(TlsConfig::builder().pre_shared_key(true).build())
+        .build()
+        .unwrap();
+    let client = Client::builder()
+        .emulation(emulation)
+        .tls_info(true)
+        .cert_verification(false)
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let closing = server(false).await;
+    assert_eq!(send(&client, closing).await, (false, false));
+    assert_eq!(send(&client, closing).await, (true, false));
+
+    // a pooled connection keeps reporting the handshake it was established with
+    let pooled = server(true).await;
+    assert_eq!(send(&client, pooled).await, (false, false));
+    assert_eq!(send(&client, pooled).await, (false, true));
+
+    let stats = client.tls_session_stats();
+    assert_eq!(
+        (stats.full_handshakes(), stats.resumed_handshakes()),
+        (2, 1)
+    );
+}
+
+#[tokio::test]
+async fn shared_tls_connector_shares_sessions() {
+    let addr = server(false).await;
+
+    let connector = TlsConnector::builder()
+        .cert_verification(false)
+        .build(TlsConfig::builder().pre_shared_key(true).build())
+        .unwrap();
+    let a = Client::builder()
+        .tls_connector(connector.clone())
+        .no_proxy()
+        .build()
+        .unwrap();
+    let b = Client::builder()
+        .tls_connector(connector)
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    assert!(!resumed(&a, addr, false).await);
+    assert!(resumed(&b, addr, false).await);
+
+    // updating a client gives it a connector of its own, with an empty session cache
+    b.update().tls(|_| {}).apply().unwrap();
+    assert!(!resumed(&b, addr, false).await);
+    assert!(resumed(&a, addr, false).await);
+
+    let stats = a.tls_session_stats();
+    assert_eq!(
+        (stats.full_handshakes(), stats.resumed_handshakes()),
+        (1, 2)
+    );
+}
+
+#[tokio::test]
+async fn early_data_is_accepted_on_resumption() {
+    let (addr, connections) = early_data_server(false).await;
+    let client = early_data_client();
+    let url = format!("https://127.0.0.1:{}/", addr.port());
+
+    // no session to resume yet
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.extensions().get::<EarlyData>(), None);
+    assert_eq!(res.text().await.unwrap(), "false");
+
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.extensions().get(), Some(&EarlyData::Accepted));
+    assert_eq!(res.text().await.unwrap(), "true");
+
+    // requests unsafe to replay wait for the handshake
+    let res = client.post(&url).body("body").send().await.unwrap();
+    assert_eq!(res.extensions().get::<EarlyData>(), None);
+    assert_eq!(res.text().await.unwrap(), "false");
+
+    assert_eq!(connections.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn rejected_early_data_is_retried_once() {
+    let (addr, connections) = early_data_server(true).await;
+    let client = early_data_client();
+    let url = format!("https://127.0.0.1:{}/", addr.port());
+
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.text().await.unwrap(), "false");
+
+    // the early data of the second connection is rejected, and the request sent again on a
+    // third connection, without early data
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.extensions().get(), Some(&EarlyData::Rejected));
+    assert_eq!(res.extensions().get(), Some(&RequestAttempt(2)));
+    assert_eq!(res.text().await.unwrap(), "false");
+    assert_eq!(connections.load(Ordering::SeqCst), 3);
+}
@@ -0,0 +1,174 @@
+use std::{error::Error as _, net::SocketAddr};
+
+use boring2::ssl::{SslAcceptor, SslFiletype, SslMethod};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use wreq::{
+    Client,
+    tls::{CertStore, RevocationError, RevocationPolicy},
+};
+
+const CA: &[u8] = include_bytes!("support/crl/ca.pem");
+const CRL: &[u8] = include_bytes!("support/crl/crl.pem");
+const EXPIRED_CRL: &[u8] = include_bytes!("support/crl/expired.crl.pem");
+
+/// Serves a single empty response per connection, with the named certificate.
+async fn server(name: &str) -> SocketAddr {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/support/crl");
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor
+        .set_certificate_chain_file(format!("{dir}/{name}.pem"))
+        .unwrap();
+    acceptor
+        .set_private_key_file(format!("{dir}/{name}.key"), SslFiletype::PEM)
+        .unwrap();
+    let acceptor = acceptor.build();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (io, _) = listener.accept().await.unwrap();
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                if let Ok(mut stream) = tokio_boring2::accept(&acceptor, io).await {
+                    let mut buf = [0; 1024];
+                    let _ = stream.read(&mut buf).await;
+                    let _ = stream
+                        .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                        .await;
+                }
+            });
+        }
+    });
+    addr
+}
+
+fn client(crls: &[&[u8]], policy: RevocationPolicy) -> Client {
+    Client::builder()
+        .cert_store(CertStore::from_pem_certs([CA]).unwrap())
+        .crl(crls)
+        .revocation_policy(policy)
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap()
+}
+
+async fn revocation_error(client: &Client, addr: SocketAddr) -> Option<RevocationError> {
+    let err = client
+        .get(format!("https://{addr}/"))
+        .send()
+        .await
+        .unwrap_err();
+
+    let mut source = err.source();
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<RevocationError>() {
+            return Some(*err);
+        }
+        source = err.source();
+    }
+    None
+}
+
+#[tokio::test]
+async fn revoked_certificate_is_rejected() {
+    let revoked = server("revoked").await;
+    let valid = server("valid").await;
+
+    for policy in [RevocationPolicy::HardFail, RevocationPolicy::SoftFail] {
+        let client = client(&[CRL], policy);
+        assert_eq!(
+            revocation_error(&client, revoked).await,
+            Some(RevocationError::Revoked)
+        );
+
+        let res = client.get(format!("https://{valid}/")).send().await;
+        assert_eq!(res.unwrap().status(), wreq::StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn hard_fail_rejects_missing_or_expired_crl() {
+    let valid = server("valid").await;
+
+    let client = client(&[], RevocationPolicy::HardFail);
+    assert_eq!(
+        revocation_error(&client, valid).await,
+        Some(RevocationError::CrlUnavailable)
+    );
+
+    let client = self::client(&[EXPIRED_CRL], RevocationPolicy::HardFail);
+    assert_eq!(
+        revocation_error(&client, valid).await,
+        Some(RevocationError::CrlExpired)
+    );
+}
+
+#[tokio::test]
+async fn soft_fail_accepts_missing_or_expired_crl() {
+    let valid = server("valid").await;
+
+    for crls in [&[][..], &[EXPIRED_CRL][..]] {
+        let client = client(crls, RevocationPolicy::SoftFail);
+        let res = client.get(format!("https://{valid}/")).send().await;
+        assert_eq!(res.unwrap().status(), wreq::StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn crls_are_replaced_at_runtime() {
+    let revoked = server("revoked").await;
+
+    let client = client(&[EXPIRED_CRL], RevocationPolicy::HardFail);
+    assert_eq!(
+        revocation_error(&client, revoked).await,
+        Some(RevocationError::CrlExpired)
+    );
+
+    client.set_crls([CRL]).unwrap();
+    assert_eq!(
+        revocation_error(&client, revoked).await,
+        Some(RevocationError::Revoked)
+    );
+
+    let client = Client::builder().no_proxy().build().unwrap();
+    assert!(client.set_crls([CRL]).unwrap_err().is_builder());
+}
+
+#[tokio::test]
+async fn missing_ocsp_staple() {
+    let valid = server("valid").await;
+
+    let client = Client::builder()
+        .cert_store(CertStore::from_pem_certs([CA]).unwrap())
+        .require_ocsp_staple(true)
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap();
+    let err = client
+        .get(format!("https://{valid}/"))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_ocsp_staple_missing());
+
+    let client = Client::builder()
+        .cert_store(CertStore::from_pem_certs([CA]).unwrap())
+        .tls_info(true)
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap();
+    let res = client
+        .get(format!("https://{valid}/"))
+        .send()
+        .await
+        .unwrap();
+    let info = res.extensions().get::<wreq::tls::TlsInfo>().unwrap();
+    assert_eq!(info.ocsp_response(), None);
+}
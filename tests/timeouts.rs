@@ -294,3 +294,168 @@ async fn response_body_timeout_forwards_size_hint() {
 
     assert_eq!(res.content_length(), Some(5));
 }
+
+#[tokio::test]
+async fn request_timeout_overrides_client_timeout() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| {
+        async {
+            // delay returning the response
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            http::Response::default()
+        }
+    });
+
+    let client = wreq::Client::builder()
+        .timeout(Duration::from_millis(100))
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/slow", server.addr());
+
+    let res = client
+        .get(&url)
+        .timeout(Duration::from_secs(2))
+        .send()
+        .await
+        .expect("request timeout should override client timeout");
+
+    assert_eq!(res.status(), wreq::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn read_timeout_does_not_override_total_timeout() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| {
+        async {
+            // delay returning the response
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            http::Response::default()
+        }
+    });
+
+    let client = wreq::Client::builder().no_proxy().build().unwrap();
+
+    let url = format!("http://{}/slow", server.addr());
+
+    let err = client
+        .get(&url)
+        .timeout(Duration::from_millis(100))
+        .read_timeout(Duration::from_secs(2))
+        .send()
+        .await
+        .unwrap_err();
+
+    assert!(err.is_timeout());
+}
+
+#[tokio::test]
+async fn zero_request_timeout_disables_client_timeout() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| {
+        async {
+            // delay returning the response
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            http::Response::default()
+        }
+    });
+
+    let client = wreq::Client::builder()
+        .timeout(Duration::from_millis(100))
+        .read_timeout(Duration::from_millis(100))
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/slow", server.addr());
+
+    let res = client
+        .get(&url)
+        .timeout(Duration::ZERO)
+        .read_timeout(Duration::ZERO)
+        .send()
+        .await
+        .expect("zero timeouts should disable the client timeouts");
+
+    assert_eq!(res.status(), wreq::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn upgraded_connection_ignores_timeouts() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |req| {
+        tokio::spawn(async move {
+            let mut upgraded = hyper_util::rt::TokioIo::new(hyper::upgrade::on(req).await.unwrap());
+
+            // stay idle for longer than both client timeouts
+            tokio::time::sleep(Duration::from_secs(3)).await;
+            upgraded.write_all(b"bar=foo").await.unwrap();
+        });
+
+        async {
+            http::Response::builder()
+                .status(http::StatusCode::SWITCHING_PROTOCOLS)
+                .header(http::header::CONNECTION, "upgrade")
+                .header(http::header::UPGRADE, "foobar")
+                .body(wreq::Body::default())
+                .unwrap()
+        }
+    });
+
+    let client = wreq::Client::builder()
+        .timeout(Duration::from_secs(2))
+        .read_timeout(Duration::from_secs(1))
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let res = client
+        .get(format!("http://{}", server.addr()))
+        .header(http::header::CONNECTION, "upgrade")
+        .header(http::header::UPGRADE, "foobar")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), http::StatusCode::SWITCHING_PROTOCOLS);
+    let mut upgraded = res.upgrade().await.unwrap();
+
+    let mut buf = vec![];
+    upgraded.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b"bar=foo");
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn read_timeout_through_async_read_downcasts_to_error() {
+    use tokio::io::AsyncReadExt;
+
+    let server = server::http(move |_req| async {
+        let body = wreq::Body::wrap_stream(futures_util::stream::once(async {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            Ok::<_, std::convert::Infallible>("Hello")
+        }));
+        http::Response::new(body)
+    });
+
+    let client = wreq::Client::builder()
+        .read_timeout(Duration::from_millis(100))
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/slow", server.addr());
+    let mut reader = client.get(&url).send().await.unwrap().into_async_read();
+    let err = reader.read_to_end(&mut Vec::new()).await.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+
+    let err = err.into_inner().unwrap().downcast::<wreq::Error>().unwrap();
+    assert!(err.is_timeout());
+}
@@ -0,0 +1,219 @@
+use std::net::SocketAddr;
+
+use boring2::ssl::{SslAcceptor, SslFiletype, SslMethod};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use wreq::{
+    Client, OriginalHeaders,
+    header::{self, HeaderMap, HeaderValue},
+    tls::{TlsHandshakeCapture, TlsVersion},
+};
+
+/// Serves a single empty response per connection.
+async fn server() -> SocketAddr {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/support/crl");
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor
+        .set_certificate_chain_file(format!("{dir}/valid.pem"))
+        .unwrap();
+    acceptor
+        .set_private_key_file(format!("{dir}/valid.key"), SslFiletype::PEM)
+        .unwrap();
+    let acceptor = acceptor.build();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (io, _) = listener.accept().await.unwrap();
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                if let Ok(mut stream) = tokio_boring2::accept(&acceptor, io).await {
+                    let mut buf = [0; 1024];
+                    let _ = stream.read(&mut buf).await;
+                    let _ = stream
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: 0\r\n\r\n",
+                        )
+                        .await;
+                    let _ = stream.shutdown().await;
+                }
+            });
+        }
+    });
+    addr
+}
+
+/// Returns the cipher suites offered in the ClientHello sent for a request to `addr`.
+async fn offered_cipher_suites(client: &Client, addr: SocketAddr) -> Vec<u16> {
+    let res = client
+        .get(format!("https://127.0.0.1:{}/", addr.port()))
+        .send()
+        .await
+        .unwrap();
+    let capture = res.extensions().get::<TlsHandshakeCapture>().unwrap();
+
+    // handshake header, legacy version and random
+    let hello = &capture.client_hello()[38..];
+    let hello = &hello[1 + hello[0] as usize..];
+    let len = u16::from_be_bytes([hello[0], hello[1]]) as usize;
+    hello[2..2 + len]
+        .chunks(2)
+        .map(|suite| u16::from_be_bytes([suite[0], suite[1]]))
+        .collect()
+}
+
+#[test]
+fn snapshot_reflects_builder_overrides() {
+    let client = Client::builder()
+        .min_tls_version(TlsVersion::TLS_1_3)
+        .http1_only()
+        .cert_verification(false)
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let snapshot = client.tls_config_snapshot();
+    assert!(!snapshot.cert_verification());
+    assert!(!snapshot.identity());
+
+    let json = serde_json::to_value(&snapshot).unwrap();
+    assert_eq!(json["tls"]["min_tls_version"], "1.3");
+    assert_eq!(json["tls"]["alpn_protos"], serde_json::json!(["http/1.1"]));
+    assert_eq!(json["cert_verification"], false);
+    assert!(json["http1"].is_object());
+    assert!(json["http2"].is_object());
+}
+
+#[test]
+fn snapshot_reports_default_headers_and_their_order() {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::USER_AGENT, HeaderValue::from_static("browser"));
+    headers.append(header::ACCEPT, HeaderValue::from_static("text/html"));
+    headers.append(header::ACCEPT, HeaderValue::from_static("*/*"));
+    let mut token = HeaderValue::from_static("Bearer secret");
+    token.set_sensitive(true);
+    headers.insert(header::AUTHORIZATION, token);
+    let mut order = OriginalHeaders::new();
+    order.extend(["Accept", "User-Agent"]);
+
+    let client = Client::builder()
+        .default_headers(headers.clone())
+        .original_headers(order)
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let snapshot = client.tls_config_snapshot();
+    assert_eq!(snapshot.default_headers(), &headers);
+    assert_eq!(snapshot.headers_order().unwrap().len(), 2);
+
+    let json = serde_json::to_value(&snapshot).unwrap();
+    assert_eq!(json["default_headers"]["user-agent"], "browser");
+    assert_eq!(
+        json["default_headers"]["accept"],
+        serde_json::json!(["text/html", "*/*"])
+    );
+    assert_eq!(json["default_headers"]["authorization"], "<redacted>");
+    assert_eq!(
+        json["headers_order"],
+        serde_json::json!(["Accept", "User-Agent"])
+    );
+}
+
+#[tokio::test]
+async fn update_tls_applies_to_subsequent_handshakes() {
+    const TLS_AES_128_GCM_SHA256: u16 = 0x1301;
+    const ECDHE_ECDSA_AES128_GCM_SHA256: u16 = 0xc02b;
+
+    let addr = server().await;
+    let client = Client::builder()
+        .tls_debug_capture(true)
+        .cert_verification(false)
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let suites = offered_cipher_suites(&client, addr).await;
+    assert!(suites.contains(&TLS_AES_128_GCM_SHA256));
+
+    // updating a clone applies to the original too
+    client
+        .clone()
+        .update()
+        .tls(|cfg| {
+            *cfg = cfg
+                .to_builder()
+                .max_tls_version(TlsVersion::TLS_1_2)
+                .cipher_list("ECDHE-ECDSA-AES128-GCM-SHA256")
+                .build()
+        })
+        .apply()
+        .unwrap();
+
+    let suites = offered_cipher_suites(&client, addr).await;
+    assert!(!suites.contains(&TLS_AES_128_GCM_SHA256), "{suites:x?}");
+    assert!(
+        suites.contains(&ECDHE_ECDSA_AES128_GCM_SHA256),
+        "{suites:x?}"
+    );
+
+    let json = serde_json::to_value(client.tls_config_snapshot()).unwrap();
+    assert_eq!(json["tls"]["max_tls_version"], "1.2");
+    assert_eq!(json["tls"]["alpn_protos"], serde_json::json!(["http/1.1"]));
+}
+
+#[test]
+fn failed_update_leaves_client_unchanged() {
+    let client = Client::builder().no_proxy().build().unwrap();
+
+    client
+        .update()
+        .tls(|cfg| *cfg = cfg.to_builder().cipher_list("NOT-A-CIPHER").build())
+        .apply()
+        .unwrap_err();
+
+    let json = serde_json::to_value(client.tls_config_snapshot()).unwrap();
+    assert_ne!(json["tls"]["cipher_list"], "NOT-A-CIPHER");
+}
+
+#[test]
+fn concurrent_update_is_rejected_unless_overwritten() {
+    let client = Client::builder().no_proxy().build().unwrap();
+    let min_tls_version = |version| {
+        move |cfg: &mut wreq::tls::TlsConfig| {
+            *cfg = cfg.to_builder().min_tls_version(version).build()
+        }
+    };
+
+    let first = client.update().tls(min_tls_version(TlsVersion::TLS_1_2));
+    let second = client.update().tls(min_tls_version(TlsVersion::TLS_1_3));
+    first.apply().unwrap();
+
+    // the second update started from the configuration before the first one
+    let err = second.apply().unwrap_err();
+    assert!(err.is_concurrent_update(), "{err}");
+    let json = serde_json::to_value(client.tls_config_snapshot()).unwrap();
+    assert_eq!(json["tls"]["min_tls_version"], "1.2");
+
+    let stale = client.update().tls(min_tls_version(TlsVersion::TLS_1_3));
+    client
+        .update()
+        .tls(min_tls_version(TlsVersion::TLS_1_1))
+        .apply()
+        .unwrap();
+    stale.apply_overwrite().unwrap();
+    let json = serde_json::to_value(client.tls_config_snapshot()).unwrap();
+    assert_eq!(json["tls"]["min_tls_version"], "1.3");
+
+    // discarding leaves the client unchanged
+    client
+        .update()
+        .tls(min_tls_version(TlsVersion::TLS_1_0))
+        .discard();
+    let json = serde_json::to_value(client.tls_config_snapshot()).unwrap();
+    assert_eq!(json["tls"]["min_tls_version"], "1.3");
+}
@@ -0,0 +1,140 @@
+mod support;
+use std::{
+    error::Error as StdError,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use support::server;
+use wreq::{
+    Client,
+    header::{AUTHORIZATION, HeaderName},
+    vcr::{Unmatched, Vcr},
+};
+
+const BINARY: &[u8] = &[0, 159, 146, 150, 255];
+
+fn cassette(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("wreq-vcr-{}-{name}.json", std::process::id()))
+}
+
+fn client(vcr: Vcr) -> Client {
+    Client::builder().vcr(vcr).no_proxy().build().unwrap()
+}
+
+fn counting_server(hits: Arc<AtomicUsize>) -> server::Server {
+    server::http(move |req| {
+        let hits = hits.clone();
+        async move {
+            let n = hits.fetch_add(1, Ordering::SeqCst);
+            let body = match req.uri().path() {
+                "/binary" => wreq::Body::from(BINARY),
+                _ => wreq::Body::from(format!("hello {n}")),
+            };
+            http::Response::builder()
+                .header("x-served", n.to_string())
+                .body(body)
+                .unwrap()
+        }
+    })
+}
+
+#[tokio::test]
+async fn recorded_interactions_replay_without_network() {
+    let path = cassette("replay");
+    let hits = Arc::new(AtomicUsize::new(0));
+    let server = counting_server(hits.clone());
+    let text_url = format!("http://{}/text", server.addr());
+    let binary_url = format!("http://{}/binary", server.addr());
+
+    let recorder = client(Vcr::record(&path));
+    let text = recorder
+        .get(&text_url)
+        .header(AUTHORIZATION, "Bearer secret")
+        .send()
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap();
+    let binary = recorder
+        .post(&binary_url)
+        .body("payload")
+        .send()
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap();
+    assert_eq!(binary, BINARY);
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+    let json = std::fs::read_to_string(&path).unwrap();
+    assert!(json.contains("\"base64\""));
+    assert!(!json.contains("secret"));
+
+    drop(server);
+
+    let player = client(Vcr::replay(&path));
+    let res = player.get(&text_url).send().await.unwrap();
+    assert_eq!(res.headers()["x-served"], "0");
+    assert_eq!(res.bytes().await.unwrap(), text);
+    let res = player
+        .post(&binary_url)
+        .body("payload")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.bytes().await.unwrap(), binary);
+
+    // a different body is a different request
+    let err = player
+        .post(&binary_url)
+        .body("other")
+        .send()
+        .await
+        .unwrap_err();
+    let mut source = err.source();
+    while let Some(err) = source {
+        if err.is::<Unmatched>() {
+            break;
+        }
+        source = err.source();
+    }
+    assert!(source.is_some(), "{err:?}");
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn auto_mode_matches_on_selected_headers() {
+    let path = cassette("auto");
+    let hits = Arc::new(AtomicUsize::new(0));
+    let server = counting_server(hits.clone());
+    let url = format!("http://{}/text", server.addr());
+
+    let version = HeaderName::from_static("x-api-version");
+    let client = client(Vcr::auto(&path).match_header(version.clone()));
+    let get = |api: &'static str, request_id: &'static str| {
+        client
+            .get(&url)
+            .header(&version, api)
+            .header("x-request-id", request_id)
+            .send()
+    };
+
+    let first = get("1", "a").await.unwrap().text().await.unwrap();
+    // the request id is not matched on
+    let replayed = get("1", "b").await.unwrap().text().await.unwrap();
+    assert_eq!(first, replayed);
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+    let other = get("2", "c").await.unwrap().text().await.unwrap();
+    assert_eq!(other, "hello 1");
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,123 @@
+use std::net::SocketAddr;
+
+use boring2::ssl::{SslAcceptor, SslFiletype, SslMethod};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use wreq::{Client, tls::CertStore};
+
+const CA: &[u8] = include_bytes!("support/crl/ca.pem");
+const EXPIRED: &[u8] = include_bytes!("support/verify/expired.pem");
+
+// `X509_V_ERR_*` codes
+const CERT_HAS_EXPIRED: i32 = 10;
+const UNABLE_TO_GET_ISSUER_CERT_LOCALLY: i32 = 20;
+const HOSTNAME_MISMATCH: i32 = 62;
+
+/// Serves a single empty response per connection, with the certificate at `path`.
+async fn server(path: &str) -> SocketAddr {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/support");
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor
+        .set_certificate_chain_file(format!("{dir}/{path}.pem"))
+        .unwrap();
+    acceptor
+        .set_private_key_file(format!("{dir}/{path}.key"), SslFiletype::PEM)
+        .unwrap();
+    let acceptor = acceptor.build();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (io, _) = listener.accept().await.unwrap();
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                if let Ok(mut stream) = tokio_boring2::accept(&acceptor, io).await {
+                    let mut buf = [0; 1024];
+                    let _ = stream.read(&mut buf).await;
+                    let _ = stream
+                        .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                        .await;
+                }
+            });
+        }
+    });
+    addr
+}
+
+fn client(store: &[u8]) -> Client {
+    Client::builder()
+        .cert_store(CertStore::from_pem_certs([store]).unwrap())
+        .resolve("wrong.test", "127.0.0.1:0".parse().unwrap())
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn expired_certificate_details() {
+    let addr = server("verify/expired").await;
+
+    let err = client(EXPIRED)
+        .get(format!("https://localhost:{}/", addr.port()))
+        .send()
+        .await
+        .unwrap_err();
+
+    let details = err.cert_verify_details().expect("verify details");
+    assert_eq!(details.code(), CERT_HAS_EXPIRED);
+    assert_eq!(details.reason(), "certificate has expired");
+    assert_eq!(details.depth(), 0);
+    assert_eq!(details.subject(), Some("CN=localhost"));
+}
+
+#[tokio::test]
+async fn wrong_host_and_untrusted_certificate_details() {
+    let addr = server("crl/valid").await;
+
+    let err = client(CA)
+        .get(format!("https://wrong.test:{}/", addr.port()))
+        .send()
+        .await
+        .unwrap_err();
+    let details = err.cert_verify_details().expect("verify details");
+    assert_eq!(details.code(), HOSTNAME_MISMATCH);
+    assert_eq!(details.depth(), 0);
+    assert_eq!(details.subject(), Some("CN=localhost"));
+
+    let err = client(EXPIRED)
+        .get(format!("https://localhost:{}/", addr.port()))
+        .send()
+        .await
+        .unwrap_err();
+    let details = err.cert_verify_details().expect("verify details");
+    assert_eq!(details.code(), UNABLE_TO_GET_ISSUER_CERT_LOCALLY);
+    assert_eq!(details.subject(), Some("CN=localhost"));
+
+    // succeeds, and reports nothing, once trusted
+    let res = client(CA)
+        .get(format!("https://localhost:{}/", addr.port()))
+        .send()
+        .await;
+    assert_eq!(res.unwrap().status(), wreq::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn accepted_invalid_certificate_has_no_details() {
+    let addr = server("verify/expired").await;
+
+    let client = Client::builder()
+        .cert_verification(false)
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap();
+    let res = client
+        .get(format!("https://localhost:{}/", addr.port()))
+        .send()
+        .await;
+    assert_eq!(res.unwrap().status(), wreq::StatusCode::OK);
+}